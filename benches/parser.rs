@@ -0,0 +1,50 @@
+/*
+ * E-ink Power CLI - Parser Benchmarks
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Cost of parsing one monitor sample
+//!
+//! `compile_per_call` compiles the battery parser's patterns on every call,
+//! as the parser did before they moved to `json::patterns`; the difference
+//! to `battery_response` is what the shared patterns save per sample.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use eink_power_cli::json::{patterns, ResponseParser};
+
+const BATTERY_REPLY: &str = "📊 LTC2959 Measurements:
+   🔋 Voltage: 6088 mV
+   ⚡ Current: -170 mA
+   🔋 Charge: 1250 mAh
+   ⚡ Power: -1040 mW
+   🌡️  Temperature: 23.5°C";
+
+fn battery_parser(c: &mut Criterion) {
+    let mut group = c.benchmark_group("battery_parser");
+    group.bench_function("battery_response", |b| {
+        b.iter(|| ResponseParser::parse_battery_response(black_box(BATTERY_REPLY)))
+    });
+    group.bench_function("compile_per_call", |b| {
+        let sources: Vec<&str> = [
+            &patterns::VOLTAGE,
+            &patterns::CURRENT,
+            &patterns::CHARGE,
+            &patterns::POWER,
+            &patterns::TEMPERATURE,
+        ]
+        .iter()
+        .map(|pattern| pattern.as_str())
+        .collect();
+        b.iter(|| {
+            for source in &sources {
+                black_box(regex::Regex::new(source).unwrap());
+            }
+            ResponseParser::parse_battery_response(black_box(BATTERY_REPLY))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, battery_parser);
+criterion_main!(benches);
@@ -0,0 +1,13 @@
+/*
+ * E-ink Power CLI - Build Script
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Records the target triple, which `self-update` looks releases up by
+
+fn main() {
+    let target = std::env::var("TARGET").expect("cargo sets TARGET");
+    println!("cargo:rustc-env=EINK_POWER_CLI_TARGET={}", target);
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,58 @@
+/*
+ * E-ink Power CLI - Concurrent Handle Example
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Two tasks sharing one power controller through cloned handles
+
+use eink_power_cli::power::control::PowerState;
+use eink_power_cli::power::handle::PowerHandle;
+use eink_power_cli::power::{PowerController, Rail};
+use eink_power_cli::Connection;
+use std::time::Duration;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let connection = Connection::new("/dev/ttyLP2", 115200, false)?;
+    let (handle, controller_task) = PowerHandle::spawn(PowerController::new(connection), 8);
+    let handle = handle.with_timeout(Duration::from_secs(5));
+
+    // Reads the battery once a second
+    let battery = handle.clone();
+    let readings = tokio::spawn(async move {
+        for _ in 0..5 {
+            match battery.measure().await {
+                Ok(measurement) => println!(
+                    "🔋 {} mV, {} mA",
+                    measurement.voltage_mv.unwrap_or_default(),
+                    measurement.current_ma.unwrap_or_default()
+                ),
+                Err(e) => eprintln!("Battery read failed: {}", e),
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    });
+
+    // Meanwhile toggles the WiFi rail
+    let rails = handle.clone();
+    let toggles = tokio::spawn(async move {
+        for state in [PowerState::On, PowerState::Off] {
+            match rails.control_rail(Rail::Wifi, state).await {
+                Ok(response) => println!("📶 {}", response.trim()),
+                Err(e) => eprintln!("WiFi control failed: {}", e),
+            }
+            tokio::time::sleep(Duration::from_millis(2500)).await;
+        }
+    });
+
+    readings.await?;
+    toggles.await?;
+
+    // The controller task closes the port once the last handle is gone
+    drop(handle);
+    controller_task.await?;
+    Ok(())
+}
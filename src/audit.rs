@@ -0,0 +1,324 @@
+/*
+ * E-ink Power CLI - Idle Power Audit
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `power-audit`: what a power budget review needs, in one document
+//!
+//! Reads the rail states and defaults, the LTC2959 and NFC sleep states and
+//! the wake configuration, and optionally samples the battery current for a
+//! while. The result is checked against the low-power checklist; every
+//! item it breaks is listed as an [`AuditFinding`] with a stable code, so
+//! reports archived per firmware release can be compared field by field.
+//!
+//! Sections are read like [`crate::snapshot::Snapshot`] sections: one that
+//! fails records its error and the rest are still read.
+
+use crate::error::{PowerCliError, Result};
+use crate::json::{
+    Ltc2959Json, NfcJson, PowerStatsJson, RailDefaultsJson, ResponseParser, SampleStatsJson,
+};
+use crate::power::control::PowerController;
+use crate::power::rails::PowerRail;
+use crate::power::stats::{PowerCounters, PowerStatsDerived};
+use crate::power::wake::WakeMask;
+use crate::snapshot::Section;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Time between current readings while sampling
+pub const SLEEP_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Words in an NFC or LTC2959 state that mean the part is asleep
+const ASLEEP_WORDS: [&str; 6] = ["sleep", "standby", "idle", "off", "down", "disabled"];
+
+/// Low-power checklist item an audit can find broken
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditCheck {
+    /// Display rail switches on at boot
+    DisplayDefaultOn,
+    /// WiFi rail switches on at boot
+    WifiDefaultOn,
+    /// Display rail is on now
+    DisplayOn,
+    /// WiFi rail is on now
+    WifiOn,
+    /// NFC controller is not asleep
+    NfcAwake,
+    /// LTC2959 converts continuously instead of in smart sleep
+    ContinuousAdc,
+    /// No wake source is enabled, so the controller would never wake
+    NoWakeSource,
+    /// The battery was charging, so the sample says nothing about drain
+    ChargingDuringSample,
+}
+
+/// One checklist item the unit breaks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditFinding {
+    pub check: AuditCheck,
+    pub detail: String,
+}
+
+/// Battery current sampled with `--sleep-sample-s`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SleepSample {
+    pub duration_s: u64,
+    /// Readings that succeeded
+    pub readings: u32,
+    pub failed: u32,
+    /// Current over the readings, negative while discharging
+    pub current_ma: Option<SampleStatsJson>,
+}
+
+/// Result of `power-audit`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PowerAudit {
+    pub taken_at: DateTime<Utc>,
+    /// Version of this CLI
+    pub cli_version: String,
+    /// Firmware version from `version`, to file the report under
+    pub firmware_version: Section<String>,
+    pub power_stats: Section<PowerStatsJson>,
+    /// Wake rate since boot from `power_stats`; `None` without its uptime
+    #[serde(default)]
+    pub power_derived: Option<PowerStatsDerived>,
+    /// Rail states from `pm <rail> status`
+    pub rails: Section<BTreeMap<PowerRail, bool>>,
+    pub rail_defaults: Section<RailDefaultsJson>,
+    /// `ltc2959 status`, for the ADC mode
+    pub ltc2959: Section<Ltc2959Json>,
+    pub nfc: Section<NfcJson>,
+    pub wake_sources: Section<WakeMask>,
+    /// `None` unless a sample was asked for
+    pub sleep_sample: Option<Section<SleepSample>>,
+    /// Median drain over the sample in mA; `None` without a discharging
+    /// sample
+    pub estimated_sleep_current_ma: Option<f64>,
+    /// Broken checklist items, in [`AuditCheck`] order
+    pub findings: Vec<AuditFinding>,
+}
+
+impl PowerAudit {
+    /// Read every section, then sample the current for `sleep_sample_s`
+    /// seconds if given
+    pub async fn take(controller: &mut PowerController, sleep_sample_s: Option<u64>) -> Self {
+        let firmware_version = controller.get_system_info().await.and_then(|response| {
+            ResponseParser::parse_system_info(&response)
+                .version
+                .ok_or(PowerCliError::InvalidResponse { response })
+        });
+        let power_stats = controller
+            .pm_stats()
+            .await
+            .map(|response| ResponseParser::parse_pm_stats(&response));
+        let rails = controller.rail_states().await;
+        let rail_defaults = controller
+            .pm_command("defaults")
+            .await
+            .map(|response| ResponseParser::parse_rail_defaults(&response));
+        let ltc2959 = controller
+            .battery_status()
+            .await
+            .map(|response| ResponseParser::parse_ltc2959_status(&response));
+        let nfc = controller
+            .nfc_command("status")
+            .await
+            .map(|response| ResponseParser::parse_nfc_status(&response));
+        let wake_sources = controller.wake_sources().await;
+        let sleep_sample = match sleep_sample_s {
+            Some(seconds) => Some(Section::read(sample_current(controller, seconds).await)),
+            None => None,
+        };
+
+        let mut audit = Self {
+            taken_at: Utc::now(),
+            cli_version: env!("CARGO_PKG_VERSION").to_string(),
+            firmware_version: Section::read(firmware_version),
+            power_stats: Section::read(power_stats),
+            power_derived: None,
+            rails: Section::read(rails),
+            rail_defaults: Section::read(rail_defaults),
+            ltc2959: Section::read(ltc2959),
+            nfc: Section::read(nfc),
+            wake_sources: Section::read_optional(wake_sources),
+            sleep_sample,
+            estimated_sleep_current_ma: None,
+            findings: Vec::new(),
+        };
+        audit.power_derived = audit
+            .power_stats
+            .data
+            .as_ref()
+            .and_then(PowerCounters::from_pm_stats)
+            .map(|counters| PowerStatsDerived::since_boot(&counters));
+        audit.estimated_sleep_current_ma = audit.estimate_sleep_current();
+        audit.findings = audit.check();
+        audit
+    }
+
+    /// Median drain over the sample, if the battery was discharging
+    pub fn estimate_sleep_current(&self) -> Option<f64> {
+        let median = self.sample_current()?.median;
+        (median <= 0.0).then_some(-median)
+    }
+
+    /// The checklist items the audited state breaks
+    ///
+    /// A section that failed to read breaks nothing; see
+    /// [`Self::failed_sections`].
+    pub fn check(&self) -> Vec<AuditFinding> {
+        let mut findings = Vec::new();
+        let mut flag = |check, detail: String| findings.push(AuditFinding { check, detail });
+
+        if let Some(defaults) = &self.rail_defaults.data {
+            if defaults.disp == Some(true) {
+                flag(
+                    AuditCheck::DisplayDefaultOn,
+                    "display rail defaults to ON".to_string(),
+                );
+            }
+            if defaults.wifi == Some(true) {
+                flag(
+                    AuditCheck::WifiDefaultOn,
+                    "WiFi rail defaults to ON".to_string(),
+                );
+            }
+        }
+        if let Some(rails) = &self.rails.data {
+            if rails.get(&PowerRail::Display) == Some(&true) {
+                flag(AuditCheck::DisplayOn, "display rail is ON".to_string());
+            }
+            if rails.get(&PowerRail::Wifi) == Some(&true) {
+                flag(AuditCheck::WifiOn, "WiFi rail is ON".to_string());
+            }
+        }
+        if let Some(state) = self.nfc_awake() {
+            flag(AuditCheck::NfcAwake, format!("NFC is {}", state));
+        }
+        if let Some(mode) = self.adc_mode() {
+            if mode.to_lowercase().contains("continuous") {
+                flag(
+                    AuditCheck::ContinuousAdc,
+                    format!("LTC2959 ADC mode is {}", mode),
+                );
+            }
+        }
+        if let Some(mask) = &self.wake_sources.data {
+            if mask.sources.is_empty() {
+                flag(
+                    AuditCheck::NoWakeSource,
+                    "no wake source is enabled".to_string(),
+                );
+            }
+        }
+        if let Some(current) = self.sample_current() {
+            if current.median > 0.0 {
+                flag(
+                    AuditCheck::ChargingDuringSample,
+                    format!(
+                        "battery was charging at {:.0} mA during the sample",
+                        current.median
+                    ),
+                );
+            }
+        }
+        findings
+    }
+
+    /// Names of the sections that failed
+    pub fn failed_sections(&self) -> Vec<&'static str> {
+        self.section_errors()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Each failed section with its error
+    pub fn section_errors(&self) -> Vec<(&'static str, &str)> {
+        let sample_error = self
+            .sleep_sample
+            .as_ref()
+            .and_then(|sample| sample.error.as_deref());
+        let errors = [
+            ("firmware version", self.firmware_version.error.as_deref()),
+            ("power stats", self.power_stats.error.as_deref()),
+            ("rails", self.rails.error.as_deref()),
+            ("rail defaults", self.rail_defaults.error.as_deref()),
+            ("ltc2959", self.ltc2959.error.as_deref()),
+            ("nfc", self.nfc.error.as_deref()),
+            ("wake sources", self.wake_sources.error.as_deref()),
+            ("sleep sample", sample_error),
+        ];
+        errors
+            .into_iter()
+            .filter_map(|(name, error)| Some((name, error?)))
+            .collect()
+    }
+
+    /// ADC mode from `ltc2959 status`, else the LTC2959 state in `pm stats`
+    pub fn adc_mode(&self) -> Option<&str> {
+        self.ltc2959
+            .data
+            .as_ref()
+            .and_then(|ltc| ltc.adc_mode.as_deref())
+            .or_else(|| {
+                self.power_stats
+                    .data
+                    .as_ref()
+                    .and_then(|stats| stats.ltc2959_state.as_deref())
+            })
+    }
+
+    /// How the NFC controller reports being awake, if it is
+    ///
+    /// Uses the NFC state in `pm stats`, or `NFC Active` from `nfc status`
+    /// on firmware that does not report one.
+    pub fn nfc_awake(&self) -> Option<String> {
+        let stats_state = self
+            .power_stats
+            .data
+            .as_ref()
+            .and_then(|stats| stats.nfc_state.as_deref());
+        match stats_state {
+            Some(state) => {
+                let lower = state.to_lowercase();
+                let asleep = ASLEEP_WORDS.iter().any(|word| lower.contains(word));
+                (!asleep).then(|| state.to_string())
+            }
+            None => {
+                let active = self.nfc.data.as_ref().and_then(|nfc| nfc.nfc_active);
+                (active == Some(true)).then(|| "active".to_string())
+            }
+        }
+    }
+
+    /// Battery current over the sample, if one was taken
+    pub fn sample_current(&self) -> Option<&SampleStatsJson> {
+        self.sleep_sample
+            .as_ref()?
+            .data
+            .as_ref()?
+            .current_ma
+            .as_ref()
+    }
+}
+
+/// Read the battery current once a second for `seconds`
+async fn sample_current(controller: &mut PowerController, seconds: u64) -> Result<SleepSample> {
+    let count = u32::try_from(seconds.max(1)).unwrap_or(u32::MAX);
+    let samples = controller
+        .battery_monitor()
+        .sample(count, SLEEP_SAMPLE_INTERVAL)
+        .await?;
+    Ok(SleepSample {
+        duration_s: seconds,
+        readings: samples.requested - samples.failed,
+        failed: samples.failed,
+        current_ma: samples.current_ma,
+    })
+}
@@ -0,0 +1,736 @@
+/*
+ * E-ink Power CLI - Battery Log Export
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Turning battery logs written by this tool into typed tables
+//!
+//! `battery read --watch`, `battery read` and `monitor` log readings as
+//! NDJSON envelopes (`--format ndjson`) or as CSV (`--format csv`). The
+//! reader accepts both, envelopes from before `schema_version` was added
+//! included, and normalizes every reading to a [`LogRecord`]. Envelopes of
+//! other commands are ignored. Lines that cannot be read are skipped and
+//! counted rather than failing the export.
+//!
+//! `monitor --follow --console-log` keeps the raw console instead, each line
+//! stamped with the time it arrived (a [`ConsoleLog`]). Its samples are read
+//! with the same [`SampleParser`] as the live session.
+//!
+//! Records are sorted by time, filtered to the requested range and written
+//! with derived columns: power from voltage and current when not reported,
+//! state of charge from a battery capacity, and the change since the
+//! previous record.
+
+use crate::error::{PowerCliError, Result};
+use crate::json::samples::{SampleParser, UnparsedLine};
+use crate::json::{BatteryJson, OUTPUT_SCHEMA_VERSION};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use clap::ValueEnum;
+use log::debug;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Columns of the exported table, in order
+pub const EXPORT_COLUMNS: &[&str] = &[
+    "timestamp",
+    "voltage_mv",
+    "current_ma",
+    "charge_mah",
+    "power_mw",
+    "temperature_c",
+    "soc_percent",
+    "elapsed_s",
+    "dt_s",
+    "voltage_delta_mv",
+    "charge_delta_mah",
+];
+
+/// File format of `log export`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row
+    Csv,
+    /// Apache Parquet (builds with the `parquet` feature)
+    Parquet,
+}
+
+impl ExportFormat {
+    /// Short description for human-readable output
+    pub fn description(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Parquet => "Parquet",
+        }
+    }
+
+    /// Format for `output`: Parquet for a `.parquet` file, otherwise CSV
+    pub fn for_path(output: &Path) -> Self {
+        match output.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("parquet") => ExportFormat::Parquet,
+            _ => ExportFormat::Csv,
+        }
+    }
+}
+
+/// One battery reading from a log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub battery: BatteryJson,
+    /// State of charge as logged; `None` if the log had none
+    pub soc_percent: Option<f64>,
+}
+
+/// Counts from reading a log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogReadStats {
+    /// Non-blank lines read
+    pub lines: u64,
+    /// Lines that could not be read
+    pub skipped: u64,
+    /// Well-formed lines without battery readings (other commands,
+    /// summaries, errors)
+    pub ignored: u64,
+}
+
+/// Readings in a log, whichever format it is in
+///
+/// A log whose first line is a JSON object is read as NDJSON, one whose
+/// first line starts with a time stamp as a console log, anything else as
+/// CSV with a header row.
+#[allow(dead_code)] // Used by tests
+pub fn read_log(text: &str) -> (Vec<LogRecord>, LogReadStats) {
+    read_log_with(text, &mut SampleParser::new())
+}
+
+/// [`read_log`] reading console logs with `parser`
+pub fn read_log_with(text: &str, parser: &mut SampleParser) -> (Vec<LogRecord>, LogReadStats) {
+    let first = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    if first.starts_with('{') {
+        read_ndjson(text)
+    } else if console_line(first).is_some() {
+        read_console_log(text, parser)
+    } else {
+        read_csv(text)
+    }
+}
+
+/// Time stamp and text of a console log line
+fn console_line(line: &str) -> Option<(DateTime<Utc>, &str)> {
+    let (stamp, text) = line.split_once(' ').unwrap_or((line, ""));
+    let stamp = DateTime::parse_from_rfc3339(stamp).ok()?;
+    Some((stamp.with_timezone(&Utc), text))
+}
+
+/// Samples in a console log, each at the time its first line arrived
+///
+/// Lines without a time stamp are skipped; lines that are not part of a
+/// sample are counted as skipped too, so a log in a format no parser knows
+/// shows up as such.
+fn read_console_log(text: &str, parser: &mut SampleParser) -> (Vec<LogRecord>, LogReadStats) {
+    let mut stats = LogReadStats::default();
+    // Time stamp of each line given to the parser
+    let mut stamps = Vec::new();
+    let mut samples = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        stats.lines += 1;
+        match console_line(line.trim_end()) {
+            Some((stamp, text)) => {
+                stamps.push(stamp);
+                samples.extend(parser.push(&format!("{}\n", text)));
+            }
+            None => {
+                debug!("Skipping line {}: no time stamp", index + 1);
+                stats.skipped += 1;
+            }
+        }
+    }
+    samples.extend(parser.flush());
+    stats.skipped += parser.stats().unparsed;
+
+    let records = samples
+        .into_iter()
+        .filter_map(|sample| {
+            Some(LogRecord {
+                timestamp: *stamps.get(usize::try_from(sample.line).ok()? - 1)?,
+                battery: sample.battery,
+                soc_percent: None,
+            })
+        })
+        .collect();
+    (records, stats)
+}
+
+/// Raw console output kept by `monitor --follow --console-log`
+///
+/// Each line is written once complete, after the time it arrived in
+/// RFC 3339, so [`read_log`] can read the samples back.
+pub struct ConsoleLog<W: Write> {
+    writer: W,
+    /// Text after the last line break, with the time it started arriving
+    partial: Option<(DateTime<Utc>, String)>,
+}
+
+impl ConsoleLog<std::fs::File> {
+    /// Append to the file at `path`, creating it if needed
+    pub fn append(path: &Path) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self::new(file))
+    }
+}
+
+impl<W: Write> ConsoleLog<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            partial: None,
+        }
+    }
+
+    /// Record `output` that arrived at `at`
+    pub fn record(&mut self, at: DateTime<Utc>, output: &str) -> Result<()> {
+        let mut rest = output;
+        while let Some(end) = rest.find('\n') {
+            let (started, mut line) = self.partial.take().unwrap_or((at, String::new()));
+            line.push_str(&rest[..end]);
+            let line = line.trim_end_matches('\r');
+            if !line.trim().is_empty() {
+                writeln!(
+                    self.writer,
+                    "{} {}",
+                    started.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    line
+                )?;
+            }
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            self.partial
+                .get_or_insert_with(|| (at, String::new()))
+                .1
+                .push_str(rest);
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Write out a last line without a line break
+    pub fn finish(&mut self) -> Result<()> {
+        match self.partial.as_ref() {
+            Some(&(started, _)) => self.record(started, "\n"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reading fields shared by every battery record layout
+#[derive(Deserialize)]
+struct SampleFields {
+    timestamp: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    battery: BatteryJson,
+    soc_percent: Option<f64>,
+}
+
+impl SampleFields {
+    fn into_record(self, fallback: Option<DateTime<Utc>>) -> Option<LogRecord> {
+        Some(LogRecord {
+            timestamp: self.timestamp.or(fallback)?,
+            battery: self.battery,
+            soc_percent: self.soc_percent,
+        })
+    }
+}
+
+/// What one NDJSON line holds
+enum Line {
+    Records(Vec<LogRecord>),
+    Ignored,
+    Corrupt(String),
+}
+
+fn read_ndjson(text: &str) -> (Vec<LogRecord>, LogReadStats) {
+    let mut records = Vec::new();
+    let mut stats = LogReadStats::default();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        stats.lines += 1;
+        match ndjson_line(line) {
+            Line::Records(found) => records.extend(found),
+            Line::Ignored => stats.ignored += 1,
+            Line::Corrupt(reason) => {
+                debug!("Skipping line {}: {}", index + 1, reason);
+                stats.skipped += 1;
+            }
+        }
+    }
+    (records, stats)
+}
+
+fn ndjson_line(line: &str) -> Line {
+    let value: Value = match serde_json::from_str(line) {
+        Ok(value) => value,
+        Err(e) => return Line::Corrupt(e.to_string()),
+    };
+    let Some(command) = value.get("command").and_then(Value::as_str) else {
+        // A bare sample, as kept by hand or by other tools
+        return match serde_json::from_value::<SampleFields>(value) {
+            Ok(fields) => match fields.into_record(None) {
+                Some(record) => Line::Records(vec![record]),
+                None => Line::Corrupt("no timestamp".to_string()),
+            },
+            Err(e) => Line::Corrupt(e.to_string()),
+        };
+    };
+
+    // Envelopes from before schema_version read the same way as version 1
+    let version = value.get("schema_version").map(Value::as_u64);
+    if matches!(version, Some(None)) || version.flatten() > Some(OUTPUT_SCHEMA_VERSION.into()) {
+        return Line::Corrupt(format!(
+            "unsupported schema_version {}",
+            value["schema_version"]
+        ));
+    }
+    if value.get("status").and_then(Value::as_str) != Some("success") {
+        return Line::Ignored;
+    }
+    let timestamp = value
+        .get("timestamp")
+        .and_then(|t| serde_json::from_value::<DateTime<Utc>>(t.clone()).ok());
+    let data = value.get("data").cloned().unwrap_or(Value::Null);
+
+    let samples = match (command, data.get("samples")) {
+        // `battery read --samples` keeps every reading
+        ("battery read", Some(Value::Array(samples))) => samples.clone(),
+        ("battery read" | "battery watch" | "monitor", _) => vec![data],
+        _ => return Line::Ignored,
+    };
+    let mut records = Vec::new();
+    for sample in samples {
+        match serde_json::from_value::<SampleFields>(sample) {
+            Ok(fields) => match fields.into_record(timestamp) {
+                Some(record) => records.push(record),
+                None => return Line::Corrupt("no timestamp".to_string()),
+            },
+            Err(e) => return Line::Corrupt(e.to_string()),
+        }
+    }
+    Line::Records(records)
+}
+
+fn read_csv(text: &str) -> (Vec<LogRecord>, LogReadStats) {
+    let mut records = Vec::new();
+    let mut stats = LogReadStats::default();
+    let mut header: Option<Vec<String>> = None;
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        stats.lines += 1;
+        let cells: Vec<&str> = line.split(',').map(str::trim).collect();
+        // Concatenated logs repeat the header
+        if cells.first() == Some(&"timestamp") {
+            header = Some(cells.iter().map(|cell| cell.to_string()).collect());
+            stats.ignored += 1;
+            continue;
+        }
+        let record = match &header {
+            Some(header) => csv_record(header, &cells),
+            None => Err("no header row before it".to_string()),
+        };
+        match record {
+            Ok(record) => records.push(record),
+            Err(reason) => {
+                debug!("Skipping line {}: {}", index + 1, reason);
+                stats.skipped += 1;
+            }
+        }
+    }
+    (records, stats)
+}
+
+fn csv_record(header: &[String], cells: &[&str]) -> std::result::Result<LogRecord, String> {
+    if cells.len() != header.len() {
+        return Err(format!(
+            "{} fields, header has {}",
+            cells.len(),
+            header.len()
+        ));
+    }
+    let cell = |name: &str| {
+        header
+            .iter()
+            .position(|column| column == name)
+            .map(|index| cells[index])
+            .filter(|value| !value.is_empty())
+    };
+    fn parse<T: std::str::FromStr>(
+        name: &str,
+        value: Option<&str>,
+    ) -> std::result::Result<Option<T>, String> {
+        value
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| format!("{} '{}' is not a number", name, value))
+            })
+            .transpose()
+    }
+
+    let timestamp = cell("timestamp").ok_or("no timestamp")?;
+    let timestamp = DateTime::parse_from_rfc3339(timestamp)
+        .map_err(|e| format!("timestamp '{}': {}", timestamp, e))?
+        .with_timezone(&Utc);
+    Ok(LogRecord {
+        timestamp,
+        battery: BatteryJson {
+            voltage_mv: parse("voltage_mv", cell("voltage_mv"))?,
+            current_ma: parse("current_ma", cell("current_ma"))?,
+            charge_mah: parse("charge_mah", cell("charge_mah"))?,
+            power_mw: parse("power_mw", cell("power_mw"))?,
+            temperature_c: parse("temperature_c", cell("temperature_c"))?,
+        },
+        soc_percent: parse("soc_percent", cell("soc_percent"))?,
+    })
+}
+
+/// Parse a `--from`/`--to` bound
+///
+/// Accepts RFC 3339, a local date and time `YYYY-MM-DD HH:MM[:SS]` and a
+/// local date `YYYY-MM-DD`, meaning midnight at its start.
+pub fn parse_time_bound(text: &str) -> std::result::Result<DateTime<Utc>, String> {
+    let text = text.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let naive = [
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .map(|date| date.and_time(Default::default()))
+    })
+    .ok_or_else(|| {
+        format!(
+            "'{}' is not a time like 2025-10-09 or 2025-10-09 14:00",
+            text
+        )
+    })?;
+    naive
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+        .ok_or_else(|| format!("{} does not exist in the local time zone", text))
+}
+
+/// A record with its derived columns
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportRow {
+    #[serde(flatten)]
+    pub record: LogRecord,
+    /// Seconds since the first exported record
+    pub elapsed_s: f64,
+    /// Seconds since the previous record
+    pub dt_s: Option<f64>,
+    pub voltage_delta_mv: Option<i32>,
+    pub charge_delta_mah: Option<i32>,
+}
+
+/// Sort `records` by time, keep those in `[from, to)` and derive the
+/// extra columns
+///
+/// Power comes from voltage and current when the log has none, and the
+/// state of charge from `capacity_mah` when the log has none.
+pub fn export_rows(
+    mut records: Vec<LogRecord>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    capacity_mah: Option<u32>,
+) -> Vec<ExportRow> {
+    records.sort_by_key(|record| record.timestamp);
+    records.retain(|record| {
+        from.is_none_or(|from| record.timestamp >= from)
+            && to.is_none_or(|to| record.timestamp < to)
+    });
+
+    let first = records.first().map(|record| record.timestamp);
+    let mut previous: Option<LogRecord> = None;
+    let mut rows = Vec::with_capacity(records.len());
+    for mut record in records {
+        let battery = &mut record.battery;
+        if battery.power_mw.is_none() {
+            battery.power_mw = battery
+                .voltage_mv
+                .zip(battery.current_ma)
+                .map(|(mv, ma)| (f64::from(mv) * f64::from(ma) / 1000.0).round() as i32);
+        }
+        if record.soc_percent.is_none() {
+            record.soc_percent = battery
+                .charge_mah
+                .zip(capacity_mah.filter(|&c| c > 0))
+                .map(|(charge, capacity)| f64::from(charge) * 100.0 / f64::from(capacity));
+        }
+
+        let seconds =
+            |since: DateTime<Utc>| (record.timestamp - since).num_milliseconds() as f64 / 1000.0;
+        let delta = |field: fn(&BatteryJson) -> Option<u16>| {
+            let previous = previous.as_ref()?;
+            Some(i32::from(field(&record.battery)?) - i32::from(field(&previous.battery)?))
+        };
+        let row = ExportRow {
+            elapsed_s: first.map(seconds).unwrap_or_default(),
+            dt_s: previous
+                .as_ref()
+                .map(|previous| seconds(previous.timestamp)),
+            voltage_delta_mv: delta(|battery| battery.voltage_mv),
+            charge_delta_mah: delta(|battery| battery.charge_mah),
+            record: record.clone(),
+        };
+        previous = Some(record);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Write `rows` as CSV with a header of [`EXPORT_COLUMNS`]
+pub fn write_csv<W: Write>(rows: &[ExportRow], mut writer: W) -> Result<()> {
+    fn cell<T: ToString>(value: Option<T>) -> String {
+        value.map(|value| value.to_string()).unwrap_or_default()
+    }
+
+    writeln!(writer, "{}", EXPORT_COLUMNS.join(","))?;
+    for row in rows {
+        let record = &row.record;
+        let battery = &record.battery;
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{:.3},{},{},{}",
+            record
+                .timestamp
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            cell(battery.voltage_mv),
+            cell(battery.current_ma),
+            cell(battery.charge_mah),
+            cell(battery.power_mw),
+            cell(battery.temperature_c),
+            cell(record.soc_percent.map(|soc| format!("{:.2}", soc))),
+            row.elapsed_s,
+            cell(row.dt_s.map(|dt| format!("{:.3}", dt))),
+            cell(row.voltage_delta_mv),
+            cell(row.charge_delta_mah),
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write `rows` as a Parquet file with the columns of [`EXPORT_COLUMNS`]
+///
+/// Timestamps are UTC milliseconds; readings are 32-bit integers and the
+/// fractional columns doubles, so pandas gets the same types for every
+/// file.
+#[cfg(feature = "parquet")]
+pub fn write_parquet(rows: &[ExportRow], file: std::fs::File) -> Result<()> {
+    use parquet::data_type::{DoubleType, Int32Type, Int64Type};
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+    use std::sync::Arc;
+
+    fn error(e: parquet::errors::ParquetError) -> PowerCliError {
+        PowerCliError::Io(std::io::Error::other(e))
+    }
+
+    /// Present values and definition levels of an optional column
+    fn optional<T: Copy>(values: impl Iterator<Item = Option<T>>) -> (Vec<T>, Vec<i16>) {
+        let mut present = Vec::new();
+        let mut levels = Vec::new();
+        for value in values {
+            levels.push(i16::from(value.is_some()));
+            present.extend(value);
+        }
+        (present, levels)
+    }
+
+    let schema = Arc::new(
+        parse_message_type(
+            "message battery_log {
+                REQUIRED INT64 timestamp (TIMESTAMP(MILLIS,true));
+                OPTIONAL INT32 voltage_mv;
+                OPTIONAL INT32 current_ma;
+                OPTIONAL INT32 charge_mah;
+                OPTIONAL INT32 power_mw;
+                OPTIONAL DOUBLE temperature_c;
+                OPTIONAL DOUBLE soc_percent;
+                REQUIRED DOUBLE elapsed_s;
+                OPTIONAL DOUBLE dt_s;
+                OPTIONAL INT32 voltage_delta_mv;
+                OPTIONAL INT32 charge_delta_mah;
+            }",
+        )
+        .map_err(error)?,
+    );
+    let mut writer = SerializedFileWriter::new(file, schema, Arc::new(WriterProperties::default()))
+        .map_err(error)?;
+    let mut group = writer.next_row_group().map_err(error)?;
+
+    let int32 = |field: fn(&ExportRow) -> Option<i32>| optional(rows.iter().map(field));
+    let double = |field: fn(&ExportRow) -> Option<f64>| optional(rows.iter().map(field));
+    let timestamps: Vec<i64> = rows
+        .iter()
+        .map(|row| row.record.timestamp.timestamp_millis())
+        .collect();
+    let elapsed: Vec<f64> = rows.iter().map(|row| row.elapsed_s).collect();
+
+    let mut column = 0;
+    while let Some(mut writer) = group.next_column().map_err(error)? {
+        let written = match column {
+            0 => writer
+                .typed::<Int64Type>()
+                .write_batch(&timestamps, None, None),
+            7 => writer
+                .typed::<DoubleType>()
+                .write_batch(&elapsed, None, None),
+            1..=4 | 9 | 10 => {
+                let (values, levels) = int32(match column {
+                    1 => |row| row.record.battery.voltage_mv.map(i32::from),
+                    2 => |row| row.record.battery.current_ma.map(i32::from),
+                    3 => |row| row.record.battery.charge_mah.map(i32::from),
+                    4 => |row| row.record.battery.power_mw,
+                    9 => |row| row.voltage_delta_mv,
+                    _ => |row| row.charge_delta_mah,
+                });
+                writer
+                    .typed::<Int32Type>()
+                    .write_batch(&values, Some(&levels), None)
+            }
+            _ => {
+                let (values, levels) = double(match column {
+                    5 => |row| row.record.battery.temperature_c.map(f64::from),
+                    6 => |row| row.record.soc_percent,
+                    _ => |row| row.dt_s,
+                });
+                writer
+                    .typed::<DoubleType>()
+                    .write_batch(&values, Some(&levels), None)
+            }
+        };
+        written.map_err(error)?;
+        writer.close().map_err(error)?;
+        column += 1;
+    }
+    group.close().map_err(error)?;
+    writer.close().map_err(error)?;
+    Ok(())
+}
+
+/// Outcome of `log export`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportReport {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub format: ExportFormat,
+    #[serde(flatten)]
+    pub read: LogReadStats,
+    /// Records outside `--from`/`--to`
+    pub filtered: u64,
+    /// Rows written
+    pub written: u64,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// Layout of the samples in a console log, e.g. `compact`
+    #[serde(default)]
+    pub sample_format: Option<String>,
+    /// Console log lines that are not a sample, with `--show-unparsed`,
+    /// numbered among the lines with a time stamp
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unparsed: Vec<UnparsedLine>,
+}
+
+/// What `log export` does
+#[derive(Debug, Clone)]
+pub struct Export {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// `None` picks the format from the output file extension
+    pub format: Option<ExportFormat>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub capacity_mah: Option<u32>,
+    /// Keep the console log lines that are not a sample in the report
+    pub show_unparsed: bool,
+}
+
+impl Export {
+    /// Read the log, write the table and report what was done
+    pub fn run(&self) -> Result<ExportReport> {
+        let format = self
+            .format
+            .unwrap_or_else(|| ExportFormat::for_path(&self.output));
+        if cfg!(not(feature = "parquet")) && format == ExportFormat::Parquet {
+            return Err(PowerCliError::InvalidArguments {
+                message:
+                    "Parquet output needs a build with the 'parquet' feature; use --format csv"
+                        .to_string(),
+            });
+        }
+        if let Some((from, to)) = self.from.zip(self.to).filter(|(from, to)| from >= to) {
+            return Err(PowerCliError::InvalidArguments {
+                message: format!("--from {} is not before --to {}", from, to),
+            });
+        }
+
+        let text = std::fs::read_to_string(&self.input)?;
+        let mut parser = SampleParser::new().keep_unparsed(self.show_unparsed);
+        let (records, read) = read_log_with(&text, &mut parser);
+        let found = records.len() as u64;
+        let rows = export_rows(records, self.from, self.to, self.capacity_mah);
+
+        let file = std::fs::File::create(&self.output)?;
+        match format {
+            ExportFormat::Csv => write_csv(&rows, std::io::BufWriter::new(file))?,
+            #[cfg(feature = "parquet")]
+            ExportFormat::Parquet => write_parquet(&rows, file)?,
+            #[cfg(not(feature = "parquet"))]
+            ExportFormat::Parquet => unreachable!("rejected above"),
+        }
+
+        Ok(ExportReport {
+            input: self.input.clone(),
+            output: self.output.clone(),
+            format,
+            read,
+            filtered: found - rows.len() as u64,
+            written: rows.len() as u64,
+            from: self.from,
+            to: self.to,
+            sample_format: parser.format().map(String::from),
+            unparsed: parser.take_unparsed(),
+        })
+    }
+}
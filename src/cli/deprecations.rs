@@ -0,0 +1,70 @@
+/*
+ * E-ink Power CLI - Deprecated Commands
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Renamed subcommands and when their old names go away
+//!
+//! A renamed subcommand keeps its old name as a hidden clap alias, so
+//! scripts written against it keep working. [`DEPRECATIONS`] is the one
+//! place a rename is recorded: the old spelling on the command line is
+//! looked up there to print a warning on stderr (suppressed with `--quiet`)
+//! and to mark JSON output with `"deprecated": true`. `migrations` prints
+//! the table.
+
+use super::Commands;
+use serde::Serialize;
+
+/// One renamed subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Deprecation {
+    /// Old subcommand path, e.g. `pm disp`
+    pub old: &'static str,
+    /// Subcommand path replacing it, e.g. `pm display`
+    pub new: &'static str,
+    /// Release that drops the old name
+    pub removed_in: &'static str,
+}
+
+impl Deprecation {
+    pub const fn new(old: &'static str, new: &'static str, removed_in: &'static str) -> Self {
+        Self {
+            old,
+            new,
+            removed_in,
+        }
+    }
+
+    /// One-line warning for stderr, with a fixed prefix for scripts to grep
+    pub fn warning(&self) -> String {
+        format!(
+            "warning: deprecated command '{}', use '{}' (removed in {})",
+            self.old, self.new, self.removed_in
+        )
+    }
+}
+
+/// Every renamed subcommand; the old name must be a clap alias of the new
+pub const DEPRECATIONS: &[Deprecation] = &[
+    Deprecation::new("power disp", "power display", "3.0.0"),
+    Deprecation::new("pm disp", "pm display", "3.0.0"),
+    Deprecation::new("pm defaults disp", "pm defaults display", "3.0.0"),
+    Deprecation::new("ltc2959 cc-gpio", "ltc2959 gpio", "3.0.0"),
+];
+
+/// Entry for the old spelling in `args` (after the program name), if
+/// `command` is what it parsed to
+///
+/// Checking the parsed command keeps an old spelling inside the arguments
+/// of another command, such as `schedule at`, from being reported.
+pub fn find(args: &[String], command: &Commands) -> Option<&'static Deprecation> {
+    let name = command.name();
+    DEPRECATIONS.iter().find(|deprecation| {
+        let old: Vec<&str> = deprecation.old.split_whitespace().collect();
+        deprecation.new == name
+            && args
+                .windows(old.len())
+                .any(|window| window.iter().map(String::as_str).eq(old.iter().copied()))
+    })
+}
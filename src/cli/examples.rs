@@ -0,0 +1,757 @@
+/*
+ * E-ink Power CLI - Usage Examples
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Example invocations shown in `--help` and by `eink-power-cli examples`
+//!
+//! Each example names the subcommand it belongs to and is rendered into that
+//! subcommand's long help (`--help`, not `-h`). Invocations are written as
+//! typed after the program name and split on whitespace, so they cannot use
+//! quoted arguments. A test parses every one, so an example that no longer
+//! matches the CLI fails the build.
+
+use clap::Command;
+use serde::Serialize;
+
+/// One example invocation
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Example {
+    /// Subcommand path the example belongs to, e.g. `pm sleep`
+    pub command: &'static str,
+    /// Arguments after the program name
+    pub invocation: &'static str,
+    /// What the invocation does, one line
+    pub description: &'static str,
+}
+
+impl Example {
+    pub const fn new(
+        command: &'static str,
+        invocation: &'static str,
+        description: &'static str,
+    ) -> Self {
+        Self {
+            command,
+            invocation,
+            description,
+        }
+    }
+
+    /// Full argument vector, program name first
+    #[allow(dead_code)] // Used by tests
+    pub fn args(&self) -> impl Iterator<Item = &'static str> {
+        std::iter::once(env!("CARGO_PKG_NAME")).chain(self.invocation.split_whitespace())
+    }
+
+    /// Whether the command, invocation or description contains `keyword`
+    /// (case-insensitive)
+    pub fn matches(&self, keyword: &str) -> bool {
+        let keyword = keyword.to_lowercase();
+        [self.command, self.invocation, self.description]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&keyword))
+    }
+
+    /// Invocation line followed by the indented description
+    pub fn format_human(&self) -> String {
+        format!(
+            "  {} {}\n      {}",
+            env!("CARGO_PKG_NAME"),
+            self.invocation,
+            self.description
+        )
+    }
+}
+
+/// Every registered example, grouped by subcommand
+pub static EXAMPLES: &[Example] = &[
+    Example::new("ping", "ping", "Check the controller answers"),
+    Example::new("version", "version", "Show the controller firmware version"),
+    Example::new(
+        "info",
+        "--format json info",
+        "System information as JSON for scripts",
+    ),
+    Example::new(
+        "info",
+        "info --all",
+        "Everything a support request needs, section by section",
+    ),
+    Example::new(
+        "snapshot",
+        "snapshot --redact --output pmu-snapshot.json",
+        "Save a support report without the unit identity, to attach to a ticket",
+    ),
+    Example::new(
+        "power-audit",
+        "--format json power-audit --sleep-sample-s 60",
+        "Check the idle power state and archive it with a one-minute current sample",
+    ),
+    Example::new("status", "status", "Power management statistics"),
+    Example::new(
+        "latency",
+        "latency --samples 20",
+        "Measure serial round-trip time over 20 pings",
+    ),
+    Example::new(
+        "monitor",
+        "monitor --continuous --interval 10",
+        "Sample the battery every 10 s until interrupted",
+    ),
+    Example::new(
+        "monitor",
+        "--format csv monitor --continuous --interval 60 --source ltc2959",
+        "Log coulomb counter readings as CSV once a minute",
+    ),
+    Example::new(
+        "monitor",
+        "monitor --continuous --interval 30 --status-file /run/eink-power/status.json",
+        "Keep a JSON status file for other services, rewritten after every sample",
+    ),
+    Example::new(
+        "monitor",
+        "monitor --continuous --interval 30 --charger",
+        "Track charging by the state the charger reports rather than the current",
+    ),
+    Example::new(
+        "monitor",
+        "monitor --follow --interval 5 --console-log console.log",
+        "Read the samples pm monitor prints, keeping the raw console output",
+    ),
+    Example::new(
+        "batch",
+        "batch --file commands.txt",
+        "Run one command per line from a file",
+    ),
+    Example::new(
+        "run",
+        "run prep-for-shipping",
+        "Run the steps of a macro from the configuration file",
+    ),
+    Example::new(
+        "run",
+        "run prep-for-shipping --dry-run",
+        "Check a macro and show what it would send",
+    ),
+    Example::new(
+        "run",
+        "run --list",
+        "List the macros in the configuration file",
+    ),
+    Example::new(
+        "log export",
+        "log export battery.ndjson -o battery.csv --capacity 3000",
+        "Typed table of a battery read --watch log, with state of charge",
+    ),
+    Example::new(
+        "log export",
+        "log export battery.csv -o day.parquet --from 2025-10-09 --to 2025-10-10",
+        "One day of readings as Parquet",
+    ),
+    Example::new(
+        "log export",
+        "log export console.log -o pm.csv --show-unparsed",
+        "Samples from a monitor --follow console log, listing lines that are not samples",
+    ),
+    Example::new(
+        "history",
+        "history -n 5 --grep sleep",
+        "Last five recorded commands mentioning sleep",
+    ),
+    Example::new(
+        "stats",
+        "stats",
+        "Serial traffic, timeouts and retries on the device link",
+    ),
+    Example::new(
+        "stats",
+        "--format prometheus stats --reset",
+        "Export the link counters, then start them again",
+    ),
+    Example::new(
+        "schedule at",
+        "schedule at 22:00 power display off",
+        "Switch the display rail off at ten tonight",
+    ),
+    Example::new(
+        "schedule at",
+        "schedule at +90s --backend systemd pm sleep --time 8h",
+        "Put the controller to sleep in 90 seconds from a systemd timer",
+    ),
+    Example::new(
+        "schedule list",
+        "schedule list",
+        "Pending, finished and cancelled schedules with their ids",
+    ),
+    Example::new(
+        "schedule cancel",
+        "schedule cancel 3f2a9c1d",
+        "Cancel a pending schedule before it runs",
+    ),
+    Example::new(
+        "fleet scan",
+        "fleet scan --save fleet.json",
+        "Inventory the PMUs on every USB serial adapter and keep it",
+    ),
+    Example::new(
+        "fleet diff",
+        "fleet diff fleet.json",
+        "Units moved, added, removed or upgraded since the saved scan",
+    ),
+    Example::new(
+        "state show",
+        "state show",
+        "List files stored for the device",
+    ),
+    Example::new(
+        "state clear",
+        "state clear",
+        "Forget everything stored for the device",
+    ),
+    Example::new(
+        "setup",
+        "setup",
+        "Choose the serial port and save default options, asking for each",
+    ),
+    Example::new(
+        "setup",
+        "--device /dev/ttyUSB0 --format json setup --yes --profile fleet --capacity 3000",
+        "Write the configuration from a provisioning script",
+    ),
+    Example::new(
+        "provision",
+        "provision unit.toml --output EINK-000123.json",
+        "Provision a unit from its manifest and keep the record for the MES",
+    ),
+    Example::new(
+        "provision",
+        "provision unit.toml --resume",
+        "Repeat only the steps a failed provisioning run did not pass",
+    ),
+    Example::new(
+        "hil-test",
+        "hil-test candidate.toml --junit hil-results.xml",
+        "Flash and check a devkit from a test plan, with a JUnit report for CI",
+    ),
+    Example::new(
+        "simulate",
+        "simulate --sim-scenario low-battery battery read",
+        "Read the battery of a simulated unit whose battery is nearly flat",
+    ),
+    Example::new(
+        "simulate",
+        "simulate --pty --sim-firmware 2.4.0",
+        "Serve a simulated controller in the background and print its device path",
+    ),
+    Example::new(
+        "examples",
+        "examples sleep",
+        "Examples whose command or description mentions sleep",
+    ),
+    Example::new(
+        "migrations",
+        "migrations",
+        "Renamed commands and when their old names stop working",
+    ),
+    Example::new(
+        "parse-check",
+        "parse-check tests/fixtures/transcripts",
+        "Check the parsers still read a corpus of device transcripts as well as its baseline records",
+    ),
+    Example::new(
+        "self-update",
+        "self-update --check-only",
+        "Exit with 5 if the artifact server has a newer release for this target",
+    ),
+    Example::new(
+        "self-update",
+        "self-update --channel beta",
+        "Install the latest beta release over this executable, keeping the old one as .old",
+    ),
+    // system
+    Example::new(
+        "system info",
+        "system info --identity",
+        "System information including the unit serial number",
+    ),
+    Example::new(
+        "system info",
+        "system info --expect-version ^2.2",
+        "Exit with code 4 unless the firmware is a 2.x release from 2.2 on",
+    ),
+    Example::new(
+        "system reboot",
+        "system reboot --cold",
+        "Cold-reset the controller",
+    ),
+    Example::new(
+        "system uptime",
+        "system uptime",
+        "Time since the controller booted",
+    ),
+    Example::new(
+        "system time-ref",
+        "system time-ref",
+        "Save how PMU uptime maps to host time",
+    ),
+    Example::new(
+        "system time-ref",
+        "system time-ref --at 67427",
+        "Host time of a PMU log line stamped 00:01:07.427",
+    ),
+    Example::new(
+        "system dfu-mode",
+        "system dfu-mode 60",
+        "Stay in the bootloader for 60 s to accept an update",
+    ),
+    Example::new(
+        "system dfu-mode",
+        "system dfu-mode --cancel",
+        "Reset a board left waiting in the bootloader",
+    ),
+    Example::new(
+        "system erase app",
+        "system erase app",
+        "Erase both application slots",
+    ),
+    Example::new(
+        "system erase defaults",
+        "system erase defaults",
+        "Erase power rail defaults stored in flash",
+    ),
+    Example::new(
+        "system verify",
+        "system verify --require-production --min-version 2.3.0",
+        "Release gate: fail on dirty, development or old firmware",
+    ),
+    Example::new(
+        "system set-baud",
+        "system set-baud 460800 --persist",
+        "Switch the console to 460800 baud and keep it across resets",
+    ),
+    Example::new(
+        "system factory-reset",
+        "system factory-reset --skip charge-reset --yes",
+        "Factory reset without touching the coulomb counter, no prompt",
+    ),
+    // power
+    Example::new("power pmic", "power pmic on", "Power up the i.MX93 PMIC"),
+    Example::new(
+        "power wifi",
+        "power wifi off",
+        "Cut power to the WiFi module",
+    ),
+    Example::new(
+        "power display",
+        "power display status",
+        "Show whether the display rail is on",
+    ),
+    Example::new("power stats", "power stats", "Power statistics"),
+    Example::new(
+        "power stats",
+        "--format json power stats",
+        "Wakes per hour by source and share of time asleep, under `derived`",
+    ),
+    Example::new("power coulomb", "power coulomb", "Coulomb counter readings"),
+    Example::new(
+        "power coulomb",
+        "power coulomb --reset",
+        "Zero the accumulated charge after a battery swap",
+    ),
+    Example::new(
+        "power sequence",
+        "power sequence imx93 display",
+        "Bring up the application processor and display in dependency order",
+    ),
+    // battery
+    Example::new(
+        "battery read",
+        "battery read",
+        "Battery voltage, current and charge",
+    ),
+    Example::new(
+        "battery read",
+        "battery read --watch --interval 2 --capacity 3000",
+        "Live one-line reading with a voltage sparkline until Ctrl-C",
+    ),
+    Example::new(
+        "battery read",
+        "battery read --samples 5 --sample-interval-ms 500",
+        "Median of five readings, so a refresh dip is not a low battery",
+    ),
+    Example::new(
+        "battery status",
+        "battery status --brief",
+        "Print just charging, discharging or idle",
+    ),
+    Example::new(
+        "battery enable",
+        "battery enable",
+        "Start battery monitoring",
+    ),
+    Example::new(
+        "battery disable",
+        "battery disable",
+        "Stop battery monitoring",
+    ),
+    // gpio
+    Example::new("gpio get", "gpio get gpioa 5", "Read pin A5"),
+    Example::new(
+        "gpio get",
+        "gpio get PTA5",
+        "Read the same pin by its schematic name",
+    ),
+    Example::new("gpio set", "gpio set gpiob 3 1", "Drive pin B3 high"),
+    Example::new(
+        "gpio script",
+        "gpio script a0=1,5ms,a1=1,2ms,b3=0",
+        "Display power-up sequence, as one firmware transaction if supported",
+    ),
+    Example::new(
+        "gpio script",
+        "--format json gpio script --host a0=0,10ms,a0=1",
+        "Pulse a pin from the host and report the measured timing",
+    ),
+    Example::new(
+        "gpio config",
+        "gpio config gpioa 0 input-pullup",
+        "Make A0 an input with pull-up and verify it",
+    ),
+    Example::new(
+        "gpio config",
+        "gpio config --pins a0,a1,b3 --mode output",
+        "Make several pins outputs at once",
+    ),
+    // nfc
+    Example::new(
+        "nfc scan",
+        "nfc scan",
+        "Look for the NTA5332 on the I2C bus",
+    ),
+    Example::new("nfc status", "nfc status", "NFC chip and RF field status"),
+    Example::new("nfc init", "nfc init", "Initialize the NTA5332"),
+    Example::new("nfc debug", "nfc debug", "Full NFC debug dump"),
+    Example::new(
+        "nfc debug",
+        "--format json --raw-compress nfc debug",
+        "Keep the whole dump in the JSON document, compressed",
+    ),
+    Example::new("nfc rfdbg", "nfc rfdbg", "RF interface diagnostics"),
+    Example::new("nfc ed", "nfc ed", "Field detection status"),
+    Example::new("nfc enable", "nfc enable", "Enable the RF interface"),
+    Example::new("nfc disable", "nfc disable", "Disable the RF interface"),
+    Example::new("nfc reset", "nfc reset", "Reset the NTA5332"),
+    Example::new("nfc info", "nfc info", "NFC device information"),
+    Example::new(
+        "nfc field-detect",
+        "nfc field-detect",
+        "Check for an RF field",
+    ),
+    Example::new(
+        "nfc tag",
+        "nfc tag --uid-format decimal",
+        "Identify the tag in the field, UID as a decimal number",
+    ),
+    Example::new("nfc sram-read", "nfc sram-read", "Dump the SRAM mailbox"),
+    Example::new(
+        "nfc sram-write",
+        "nfc sram-write --file mailbox.bin",
+        "Write a file of up to 64 bytes to the SRAM mailbox",
+    ),
+    Example::new(
+        "nfc transfer",
+        "nfc transfer from-phone provisioning.bin --timeout 60",
+        "Receive a provisioning blob from a phone held on the antenna",
+    ),
+    // board
+    Example::new(
+        "board reset",
+        "board reset",
+        "Power-cycle the controller board",
+    ),
+    Example::new(
+        "board shutdown",
+        "board shutdown",
+        "Power the board off for good",
+    ),
+    // ltc2959
+    Example::new(
+        "ltc2959 init",
+        "ltc2959 init",
+        "Initialize the coulomb counter",
+    ),
+    Example::new(
+        "ltc2959 read",
+        "ltc2959 read",
+        "Voltage, current, charge and power",
+    ),
+    Example::new(
+        "ltc2959 read",
+        "ltc2959 read --continuous --interval-ms 200 --duration 30s",
+        "Profile the current for 30 s in continuous ADC mode, then restore the mode",
+    ),
+    Example::new("ltc2959 status", "ltc2959 status", "Status and alert flags"),
+    Example::new(
+        "ltc2959 enable",
+        "ltc2959 enable",
+        "Enable ADC measurements",
+    ),
+    Example::new(
+        "ltc2959 disable",
+        "ltc2959 disable",
+        "Put the ADC in ultra-low power",
+    ),
+    Example::new(
+        "ltc2959 scan",
+        "ltc2959 scan",
+        "Look for the LTC2959 on the I2C bus",
+    ),
+    Example::new(
+        "ltc2959 set-charge",
+        "ltc2959 set-charge 1200",
+        "Set the accumulated charge to 1200 mAh",
+    ),
+    Example::new(
+        "ltc2959 zero-charge",
+        "ltc2959 zero-charge --yes",
+        "Zero the accumulated charge without asking",
+    ),
+    Example::new(
+        "ltc2959 charge-complete",
+        "ltc2959 charge-complete",
+        "Mark the battery fully charged",
+    ),
+    Example::new("ltc2959 gpio", "ltc2959 gpio off", "Drive CC_GPIO low"),
+    Example::new(
+        "ltc2959 production-reset",
+        "ltc2959 production-reset",
+        "Reset the counter for a freshly installed battery",
+    ),
+    Example::new(
+        "ltc2959 adc-mode",
+        "ltc2959 adc-mode 4",
+        "Select ADC mode 4",
+    ),
+    Example::new(
+        "ltc2959 reg-read",
+        "ltc2959 reg-read 0x01",
+        "Read register 0x01",
+    ),
+    Example::new(
+        "ltc2959 reg-write",
+        "ltc2959 reg-write 0x01 0x60",
+        "Write 0x60 to register 0x01",
+    ),
+    // pm
+    Example::new("pm stats", "pm stats", "Power management statistics"),
+    Example::new(
+        "pm sleep",
+        "pm sleep --time 1h --vlls1 --alloff",
+        "Canonical overnight sleep: all rails off in VLLS1, RTC wake-up after 1 h",
+    ),
+    Example::new(
+        "pm sleep",
+        "pm sleep --time 1d12h --vlls3 --wifi",
+        "Sleep 36 hours in VLLS3 with WiFi off",
+    ),
+    Example::new("pm wake", "pm wake", "Show what woke the controller last"),
+    Example::new(
+        "pm wake-sources show",
+        "pm wake-sources show",
+        "Wake sources enabled in the firmware",
+    ),
+    Example::new(
+        "pm wake-sources enable",
+        "pm wake-sources enable button",
+        "Let the user button wake the controller",
+    ),
+    Example::new(
+        "pm wake-sources disable",
+        "pm wake-sources disable nfc",
+        "Stop NFC fields waking the controller",
+    ),
+    Example::new("pm measure", "pm measure", "One-shot battery measurement"),
+    Example::new(
+        "pm monitor",
+        "pm monitor start 30",
+        "Start firmware-side monitoring every 30 s",
+    ),
+    Example::new("pm all", "pm all off", "Turn every rail off"),
+    Example::new("pm pmic", "pm pmic on", "Power up the PMIC"),
+    Example::new("pm wifi", "pm wifi on", "Power up WiFi"),
+    Example::new("pm display", "pm display off", "Power down the display"),
+    Example::new(
+        "pm defaults show",
+        "pm defaults show",
+        "Rail states the bootloader applies at power-up",
+    ),
+    Example::new(
+        "pm defaults save",
+        "pm defaults save",
+        "Store the current rail states as defaults",
+    ),
+    Example::new(
+        "pm defaults export",
+        "pm defaults export defaults.json",
+        "Back up the stored defaults",
+    ),
+    Example::new(
+        "pm defaults import",
+        "pm defaults import defaults.json",
+        "Restore defaults from a backup",
+    ),
+    Example::new(
+        "pm defaults pmic",
+        "pm defaults pmic on",
+        "Power the PMIC at boot",
+    ),
+    Example::new(
+        "pm defaults wifi",
+        "pm defaults wifi off",
+        "Keep WiFi off at boot",
+    ),
+    Example::new(
+        "pm defaults display",
+        "pm defaults display on",
+        "Power the display at boot",
+    ),
+    Example::new(
+        "pm ltc2959",
+        "pm ltc2959 sleep",
+        "Put the coulomb counter to sleep",
+    ),
+    Example::new("pm nfc", "pm nfc wake", "Wake the NFC chip"),
+    Example::new(
+        "pm battery-check",
+        "pm battery-check",
+        "Load test and internal resistance check",
+    ),
+    Example::new(
+        "pm battery-check",
+        "pm battery-check --samples 3",
+        "Decide the health verdict on the median of three checks",
+    ),
+    Example::new(
+        "pm imx93",
+        "pm imx93 status",
+        "Show whether the i.MX93 is powered",
+    ),
+    // rtc
+    Example::new(
+        "rtc status",
+        "rtc status",
+        "Internal and external RTC status",
+    ),
+    Example::new("rtc get", "rtc get", "Internal RTC counter"),
+    Example::new(
+        "rtc config",
+        "rtc config auto",
+        "Power the i.MX93 on an RTC alarm if the PMIC is off",
+    ),
+    Example::new(
+        "rtc show",
+        "rtc show",
+        "External RTC interrupt configuration",
+    ),
+    Example::new(
+        "rtc calibrate",
+        "rtc calibrate -12",
+        "Slow the external RTC crystal by 12 ppm",
+    ),
+    Example::new(
+        "rtc calibration-read",
+        "rtc calibration-read",
+        "Current external RTC offset",
+    ),
+    // firmware
+    Example::new("firmware list", "firmware list", "Installed images"),
+    Example::new(
+        "firmware info",
+        "--format json firmware info",
+        "Running version, slots and confirm state as JSON",
+    ),
+    Example::new(
+        "firmware upload",
+        "firmware upload --fast -f app.signed.bin",
+        "Upload at 921600 baud, then restore the console rate",
+    ),
+    Example::new(
+        "firmware upload",
+        "--allow-bootloader firmware upload --skip-reset -f app.signed.bin",
+        "Upload to a PMU already sitting in the bootloader",
+    ),
+    Example::new(
+        "firmware analyze",
+        "firmware analyze app.signed.bin",
+        "Inspect an image header offline",
+    ),
+    Example::new(
+        "firmware reset",
+        "firmware reset",
+        "Reset the PMU into the bootloader",
+    ),
+    // comm
+    Example::new("comm bt-wake", "comm bt-wake on", "Assert BT_WAKE_HOST"),
+    Example::new("comm wl-wake", "comm wl-wake off", "Release WL_WAKE_HOST"),
+    // identity
+    Example::new(
+        "identity show",
+        "identity show",
+        "Programmed serial and hardware revision",
+    ),
+    Example::new(
+        "identity write",
+        "identity write --serial EPC-0001234 --hw-rev 3 --date 2025-10-09 --yes",
+        "Program the unit identity at production",
+    ),
+];
+
+/// Examples registered for exactly this subcommand path
+pub fn for_command(command: &str) -> impl Iterator<Item = &'static Example> + '_ {
+    EXAMPLES.iter().filter(move |e| e.command == command)
+}
+
+/// Examples matching `keyword`, or all of them
+pub fn matching(keyword: Option<&str>) -> Vec<&'static Example> {
+    EXAMPLES
+        .iter()
+        .filter(|e| keyword.is_none_or(|keyword| e.matches(keyword)))
+        .collect()
+}
+
+/// Long-help section for a subcommand path, if it has examples
+pub fn long_help(command: &str) -> Option<String> {
+    let lines: Vec<String> = for_command(command).map(Example::format_human).collect();
+    (!lines.is_empty()).then(|| format!("Examples:\n{}", lines.join("\n")))
+}
+
+/// Attach every registered example to its subcommand's long help
+pub fn with_examples(command: Command) -> Command {
+    attach(
+        command.after_long_help(
+            "Run `eink-power-cli examples [KEYWORD]` for example invocations of every command.",
+        ),
+        "",
+    )
+}
+
+fn attach(mut command: Command, path: &str) -> Command {
+    if let Some(help) = long_help(path) {
+        command = command.after_long_help(help);
+    }
+    let names: Vec<String> = command
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    for name in names {
+        let sub_path = if path.is_empty() {
+            name.clone()
+        } else {
+            format!("{} {}", path, name)
+        };
+        command = command.mut_subcommand(name, |sub| attach(sub, &sub_path));
+    }
+    command
+}
@@ -4,11 +4,47 @@
  * All rights reserved.
  */
 
-use clap::{Parser, Subcommand, ValueEnum};
+pub mod deprecations;
+pub mod examples;
+
+use crate::battery_log::{parse_time_bound, ExportFormat};
+use crate::config::Config;
+use crate::firmware::dfu::DEFAULT_DFU_TIMEOUT_S;
+use crate::json::raw::{RawMode, RawOptions};
+use crate::json::ParsedPayload;
+use crate::power::battery::{
+    DEFAULT_DEADBAND_MA, DEFAULT_DEBOUNCE_SAMPLES, DEFAULT_SAMPLE_INTERVAL_MS,
+    DEFAULT_SPARKLINE_SAMPLES,
+};
+use crate::power::factory_reset::FactoryResetStep;
+use crate::power::gpio::{GpioPin, GpioScript};
+use crate::power::passthrough::{TransferDirection, DEFAULT_TRANSFER_TIMEOUT_S};
+use crate::power::rails::PowerRail;
+use crate::power::sleep::parse_sleep_duration;
+use crate::power::wake::WakeSource;
+use crate::schedule::{self, ScheduleBackend};
+use crate::serial::connection::{EchoCheck, ResyncMode, TimeoutRetry, SUPPORTED_BAUD_RATES};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use log::warn;
+use semver::VersionReq;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::time::Duration;
+
+/// Round trips one invocation is expected to need for the default
+/// `--max-duration`: handshake, shell recovery, command and verification
+pub const DEADLINE_ROUND_TRIPS: u64 = 4;
+
+/// Slack added to the default `--max-duration` for settle delays and reboots
+pub const DEADLINE_MARGIN_SECS: u64 = 10;
+
+/// Console rate of `firmware upload --fast` given without a value
+pub const FAST_UPLOAD_BAUD: u32 = 921_600;
 
 /// E-ink Power CLI - Command-line interface for power management controller
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(
     name = "eink-power-cli",
     version,
@@ -33,6 +69,14 @@ pub struct Cli {
     #[arg(short, long, default_value = "3", help = "Command timeout in seconds")]
     pub timeout: u64,
 
+    /// Overall time limit for the whole invocation in seconds
+    #[arg(
+        long,
+        value_name = "SECS",
+        help = "Abort the whole invocation after this many seconds (default: timeout x 4 + 10; off for monitor, batch, firmware, power sequence and snapshot)"
+    )]
+    pub max_duration: Option<u64>,
+
     /// Output format
     #[arg(short, long, default_value = "human", help = "Output format")]
     pub format: OutputFormat,
@@ -49,13 +93,361 @@ pub struct Cli {
     #[arg(short, long, help = "Suppress non-error output")]
     pub quiet: bool,
 
+    /// Write the output to a file once the command has succeeded
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write the output to FILE, replacing it atomically and only if the command succeeds"
+    )]
+    pub output: Option<PathBuf>,
+
+    /// Add the output to the end of the `--output` file
+    #[arg(
+        long,
+        requires = "output",
+        help = "Append to the --output file instead of replacing it"
+    )]
+    pub append: bool,
+
+    /// Write the error to the `--output` file when the command fails
+    #[arg(
+        long,
+        requires = "output",
+        help = "Write the error document to the --output file when the command fails"
+    )]
+    pub output_on_error: bool,
+
+    /// Flush stdout after every line of output
+    #[arg(
+        long,
+        help = "Flush output after every line when piping (may reduce throughput in high-frequency monitoring)"
+    )]
+    pub line_buffered: bool,
+
+    /// Print the shell commands that would be sent without opening the device
+    #[arg(
+        long,
+        help = "Show the commands that would be sent without sending them"
+    )]
+    pub dry_run: bool,
+
+    /// Discard stale receive data before executing the command
+    #[arg(long, help = "Flush stale serial input before executing the command")]
+    pub flush_before_command: bool,
+
+    /// Send the shell-enable sequence if the PMU console is stuck in log-only mode
+    #[arg(
+        long,
+        help = "Automatically re-enable the PMU shell if only log output is seen"
+    )]
+    pub auto_recover_shell: bool,
+
+    /// Turn off local echo and CRLF translation left on by a terminal
+    /// program, when the connect handshake finds them
+    #[arg(
+        long,
+        help = "Restore the PMU shell's echo and line-ending settings if they are off"
+    )]
+    pub fix_console: bool,
+
+    /// Firmware commands only: skip the console handshake so they work while
+    /// the PMU is in its bootloader
+    #[arg(
+        long,
+        help = "Skip the console handshake for firmware commands (PMU in bootloader)"
+    )]
+    pub allow_bootloader: bool,
+
+    /// Minimum gap between consecutive commands in milliseconds
+    #[arg(
+        long,
+        value_name = "MS",
+        help = "Minimum gap between consecutive commands in ms (default: config or 5)"
+    )]
+    pub pacing_ms: Option<u64>,
+
+    /// When to clear the PMU shell input line before a command
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHEN",
+        help = "Clear the PMU shell input line before commands (default: config or after-error)"
+    )]
+    pub resync: Option<ResyncMode>,
+
+    /// Which commands are only run once the shell has echoed them unchanged
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHICH",
+        help = "Check the shell echo before running commands (default: config or destructive)"
+    )]
+    pub verify_echo: Option<EchoCheck>,
+
+    /// Which command timeouts are retried once
+    #[arg(
+        long,
+        value_enum,
+        value_name = "WHICH",
+        help = "Send a command again after a timeout (default: config or silent)"
+    )]
+    pub retry_timeout: Option<TimeoutRetry>,
+
+    /// Send the next command right after one that resets the PMU UART
+    /// (`pm sleep`, `system reset`) instead of waiting out its guard time
+    #[arg(
+        long,
+        help = "Do not wait for the PMU UART after commands that reset it (for experts)"
+    )]
+    pub no_guard: bool,
+
+    /// Stop the systemd unit of a process holding the serial port (e.g. a
+    /// getty) and start it again afterwards
+    #[arg(
+        long,
+        help = "Stop the systemd unit holding the serial port for the duration of the command"
+    )]
+    pub steal: bool,
+
+    /// Switch on the rails a rail depends on (e.g. the PMIC for the
+    /// display) instead of refusing to switch it on
+    #[arg(long, help = "Switch on the rails a rail depends on first")]
+    pub auto_deps: bool,
+
+    /// Send every status query to the controller instead of reusing
+    /// responses from earlier in the same invocation
+    #[arg(
+        long,
+        help = "Do not reuse status query responses within this invocation"
+    )]
+    pub no_cache: bool,
+
+    /// Do not record this invocation in the per-device history log
+    #[arg(long, help = "Do not record this invocation in the command history")]
+    pub no_history: bool,
+
+    /// Report which response patterns matched for every parsed field
+    #[arg(
+        long,
+        help = "Show which fields the response parser matched, missed or could not read"
+    )]
+    pub explain_parse: bool,
+
+    /// Print the session context (settings, last command, received bytes)
+    /// when a command fails
+    #[arg(
+        long,
+        help = "On failure, print the connection settings, last command and last bytes received for a bug report"
+    )]
+    pub bug_report: bool,
+
+    /// Print the banner a freshly reset PMU sends before its first prompt
+    #[arg(
+        long,
+        help = "Print the PMU boot banner if one was received while connecting"
+    )]
+    pub show_banner: bool,
+
+    /// How much of the controller reply JSON output keeps in `raw_response`
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        help = "Keep the controller reply in JSON output in full, truncated to 2 KiB, or not at all (default: truncated, or full with --raw-compress)"
+    )]
+    pub raw: Option<RawMode>,
+
+    /// Store `raw_response` as base64 of its zlib stream
+    #[arg(
+        long,
+        help = "Store the controller reply in JSON output zlib-compressed and base64-encoded"
+    )]
+    pub raw_compress: bool,
+
+    /// Whether `--timeout` was given on the command line, so it applies to
+    /// every command instead of the per-command timeouts
+    #[arg(skip)]
+    pub timeout_given: bool,
+
+    /// Whether `--format` was given on the command line
+    #[arg(skip)]
+    pub format_given: bool,
+
+    /// Renamed subcommand the command line used the old name of
+    #[arg(skip)]
+    pub deprecation: Option<&'static deprecations::Deprecation>,
+
     /// Command to execute
     #[command(subcommand)]
     pub command: Option<Commands>,
 }
 
+impl Cli {
+    /// Parse the process arguments with examples attached to `--help`
+    ///
+    /// Defaults from the configuration file fill in options not given on
+    /// the command line. A file that fails to load is left for [`Config::load`]
+    /// to report when the command runs. A renamed subcommand typed by its old
+    /// name is recorded in `deprecation`.
+    pub fn parse_with_examples() -> Self {
+        let args: Vec<String> = std::env::args_os()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        Self::parse_invocation(args)
+    }
+
+    /// [`Self::parse_with_examples`] for `args`, program name first
+    ///
+    /// Exits with clap's message if they do not parse.
+    pub fn parse_invocation(args: Vec<String>) -> Self {
+        let matches = examples::with_examples(Self::command()).get_matches_from(&args);
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        if let Some(command) = &cli.command {
+            cli.deprecation = deprecations::find(&args[1..], command);
+        }
+        if let Ok(config) = Config::load(cli.config.as_deref()) {
+            cli.apply_config(&config, &matches);
+        }
+        cli
+    }
+
+    /// Take options left at their built-in default from `config`
+    pub fn apply_config(&mut self, config: &Config, matches: &ArgMatches) {
+        let defaulted = |id: &str| {
+            matches!(
+                matches.value_source(id),
+                None | Some(ValueSource::DefaultValue)
+            )
+        };
+        if let Some(device) = config
+            .connection
+            .device
+            .as_ref()
+            .filter(|_| defaulted("device"))
+        {
+            self.device = device.clone();
+        }
+        self.timeout_given = !defaulted("timeout");
+        self.format_given = !defaulted("format");
+        if let Some(timeout) = config.connection.timeout.filter(|_| defaulted("timeout")) {
+            self.timeout = timeout;
+        }
+        if let Some(format) = config
+            .output
+            .format
+            .as_ref()
+            .filter(|_| defaulted("format"))
+        {
+            self.format = format.clone();
+        }
+        if let Some(Commands::Battery(BatteryCommands::Read {
+            watch: true,
+            capacity,
+            ..
+        })) = &mut self.command
+        {
+            *capacity = capacity.or(config.battery.capacity_mah);
+        }
+        if let Some(Commands::Log(LogCommands::Export { capacity, .. })) = &mut self.command {
+            *capacity = capacity.or(config.battery.capacity_mah);
+        }
+    }
+
+    /// Whether parse diagnostics are attached to output (`--verbose` implies it)
+    pub fn explains_parse(&self) -> bool {
+        self.explain_parse || self.verbose
+    }
+
+    /// How JSON output stores the controller reply
+    ///
+    /// `--raw-compress` alone keeps the whole reply, since compressing is
+    /// for keeping it compactly rather than cutting it.
+    pub fn raw_options(&self) -> RawOptions {
+        let default = match self.raw_compress {
+            true => RawMode::Full,
+            false => RawMode::Truncated,
+        };
+        RawOptions {
+            mode: self.raw.unwrap_or(default),
+            compress: self.raw_compress,
+            ..RawOptions::default()
+        }
+    }
+
+    /// Whether a failure carries the session context (`--verbose` implies it)
+    pub fn reports_session(&self) -> bool {
+        self.bug_report || self.verbose
+    }
+
+    /// Decoration of human-readable output
+    ///
+    /// Every option currently leaves the default style.
+    pub fn output_style(&self) -> crate::render::OutputStyle {
+        crate::render::OutputStyle::default()
+    }
+
+    /// Reject contradictory global options before anything is opened
+    ///
+    /// Combinations that are only pointless are logged as warnings.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.quiet && self.verbose {
+            return Err("--quiet and --verbose cannot be used together".to_string());
+        }
+
+        if self.quiet && self.format_given && matches!(self.format, OutputFormat::Human) {
+            warn!("--quiet suppresses all human-readable output; --format human has no effect");
+        }
+
+        if let Some(command) = &self.command {
+            if matches!(self.format, OutputFormat::Csv) && !command.has_csv_output() {
+                warn!(
+                    "'{}' has no CSV output; printing human-readable text",
+                    command.name()
+                );
+            }
+            if matches!(self.format, OutputFormat::Prometheus) && !command.has_prometheus_output() {
+                return Err(format!(
+                    "'{}' has no Prometheus output; use --format json or csv",
+                    command.name()
+                ));
+            }
+            if self.allow_bootloader && !matches!(command, Commands::Firmware(_)) {
+                warn!("--allow-bootloader only affects firmware commands");
+            }
+        }
+        if self.dry_run && matches!(self.command, Some(Commands::Setup { .. })) {
+            return Err("setup cannot be combined with --dry-run".to_string());
+        }
+        if self.raw_compress && self.raw == Some(RawMode::Omit) {
+            return Err("--raw-compress cannot be used with --raw omit".to_string());
+        }
+        if self.max_duration == Some(0) {
+            return Err("--max-duration must be at least 1 second".to_string());
+        }
+        Ok(())
+    }
+
+    /// Overall deadline for the command, if any
+    ///
+    /// An explicit `--max-duration` always applies. Otherwise long-running
+    /// commands are unbounded and everything else gets
+    /// `timeout x DEADLINE_ROUND_TRIPS + DEADLINE_MARGIN_SECS`.
+    pub fn deadline(&self) -> Option<Duration> {
+        if let Some(secs) = self.max_duration {
+            return Some(Duration::from_secs(secs));
+        }
+        if self.command.as_ref().is_some_and(Commands::is_long_running) {
+            return None;
+        }
+        Some(Duration::from_secs(
+            self.timeout * DEADLINE_ROUND_TRIPS + DEADLINE_MARGIN_SECS,
+        ))
+    }
+}
+
 /// Available output formats
-#[derive(ValueEnum, Clone, Debug)]
+#[derive(ValueEnum, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
     /// Human-readable format with colors and emojis
     Human,
@@ -63,6 +455,14 @@ pub enum OutputFormat {
     Json,
     /// CSV format for data analysis
     Csv,
+    /// Newline-delimited JSON, one compact record per line
+    Ndjson,
+    /// NDJSON records marked with a `type`: sample, event, progress, summary
+    /// or error
+    #[serde(rename = "json-lines")]
+    JsonLines,
+    /// Prometheus text exposition format for metrics scraping
+    Prometheus,
 }
 
 /// Available commands
@@ -112,12 +512,62 @@ pub enum Commands {
     #[command(subcommand)]
     Comm(CommCommands),
 
+    /// Unit identity stored in the NFC EEPROM
+    #[command(subcommand)]
+    Identity(IdentityCommands),
+
     /// Connectivity test
     Ping,
 
     /// Get controller version
     Version,
 
+    /// Show system information (alias for `system info`)
+    Info {
+        /// Show everything a support request needs (alias for `snapshot`)
+        #[arg(long)]
+        all: bool,
+    },
+
+    /// Gather the state of every subsystem into one report for support
+    ///
+    /// System info, uptime, identity, firmware images (with mcumgr),
+    /// battery, power statistics, rail defaults, RTC and NFC status, and the
+    /// connection settings. A subsystem that fails is reported as such and
+    /// the rest are still read.
+    Snapshot {
+        /// Also write the report as JSON to this file
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Leave out the unit identity and serial number
+        #[arg(long)]
+        redact: bool,
+    },
+
+    /// Check the idle power state against the low-power checklist
+    ///
+    /// Reads `pm stats`, the rail states and defaults, the LTC2959 ADC mode,
+    /// the NFC state and the wake sources, and flags anything that keeps the
+    /// board from its lowest sleep current. With `--sleep-sample-s` the
+    /// battery current is also sampled once a second and its median drain
+    /// reported as the estimated sleep current.
+    PowerAudit {
+        /// Sample the battery current for this many seconds
+        #[arg(long, value_name = "SECONDS")]
+        sleep_sample_s: Option<u64>,
+    },
+
+    /// Show power management status (alias for `pm stats`)
+    Status,
+
+    /// Measure serial link round-trip latency
+    Latency {
+        /// Number of pings to send (default 5)
+        #[arg(short, long)]
+        samples: Option<u8>,
+    },
+
     /// Monitor continuously
     Monitor {
         /// Monitoring interval in seconds
@@ -127,6 +577,51 @@ pub enum Commands {
         /// Run continuously
         #[arg(short, long)]
         continuous: bool,
+
+        /// Start `pm monitor` on the controller and read the samples it
+        /// prints, every --interval seconds, until Ctrl-C
+        #[arg(long, conflicts_with = "continuous")]
+        follow: bool,
+
+        /// Print console lines that are not a sample on stderr
+        #[arg(long, requires = "follow")]
+        show_unparsed: bool,
+
+        /// Append everything the console prints to this file, each line
+        /// stamped with the time it arrived; `log export` reads it back
+        #[arg(long, value_name = "PATH", requires = "follow")]
+        console_log: Option<PathBuf>,
+
+        /// Command used to take each sample
+        #[arg(long, value_enum, default_value = "measure")]
+        source: MonitorSource,
+
+        /// Current band around 0 mA reported as idle rather than charging
+        #[arg(long, value_name = "MA", default_value_t = DEFAULT_DEADBAND_MA)]
+        deadband: u16,
+
+        /// Consecutive samples a new charging state must hold before it is reported
+        #[arg(long, value_name = "SAMPLES", default_value_t = DEFAULT_DEBOUNCE_SAMPLES)]
+        debounce: u32,
+
+        /// Also read the charger state the firmware reports with every
+        /// sample, so trickle charge and termination are not taken from the
+        /// current alone
+        #[arg(long, conflicts_with = "follow")]
+        charger: bool,
+
+        /// Keep a JSON file with the latest readings for other services
+        #[arg(long, value_name = "PATH", requires = "continuous")]
+        status_file: Option<PathBuf>,
+
+        /// Seconds between status file writes [default: after every sample]
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            value_parser = clap::value_parser!(u64).range(1..),
+            requires = "status_file"
+        )]
+        status_file_interval: Option<u64>,
     },
 
     /// Execute batch commands from file
@@ -135,13 +630,613 @@ pub enum Commands {
         #[arg(short, long)]
         file: PathBuf,
     },
+
+    /// Run a macro from the `[macros]` section of the configuration file
+    ///
+    /// Each step is a command as typed after `eink-power-cli`, run in order
+    /// over one connection like the lines of a batch file. Every step is
+    /// checked before the first one is sent, and the run stops at the first
+    /// step that fails.
+    Run {
+        /// Macro to run
+        #[arg(required_unless_present = "list")]
+        name: Option<String>,
+        /// List the macros in the configuration file
+        #[arg(long, conflicts_with = "name")]
+        list: bool,
+        /// Show the commands the steps would send without sending them (as
+        /// the global --dry-run)
+        #[arg(long)]
+        dry_run: bool,
+        /// Steps of the macro, filled in from the configuration file
+        #[arg(skip)]
+        steps: Vec<String>,
+    },
+
+    /// Inspect or reset the state stored for the device
+    #[command(subcommand)]
+    State(StateCommands),
+
+    /// Show the command history recorded for the device
+    History {
+        /// Number of most recent entries to show
+        #[arg(short = 'n', long, default_value = "20")]
+        last: usize,
+
+        /// Only show entries containing this text (case-insensitive)
+        #[arg(short, long)]
+        grep: Option<String>,
+    },
+
+    /// Convert battery logs written by this tool into tables
+    #[command(subcommand)]
+    Log(LogCommands),
+
+    /// Show serial link statistics for the device
+    ///
+    /// Bytes, commands, timeouts, retries, reconnects and reply latency,
+    /// added up over every invocation since the last reset.
+    Stats {
+        /// Print the totals, then start counting again from zero
+        #[arg(long)]
+        reset: bool,
+    },
+
+    /// Run a command once at a later time
+    ///
+    /// The command waits in a detached process or a systemd timer and then
+    /// runs as if typed at that time, so it is recorded in the history.
+    #[command(subcommand)]
+    Schedule(ScheduleCommands),
+
+    /// Inventory of every PMU attached to the host
+    ///
+    /// Probes the serial ports one after another and lists the firmware
+    /// version, identity serial number, battery voltage and rail states of
+    /// each PMU that answers, or why a port gave none. Entries carry the
+    /// USB adapter serial number, which stays the same across reboots when
+    /// the device nodes do not.
+    #[command(subcommand)]
+    Fleet(FleetCommands),
+
+    /// Pick the serial port and save default options to the config file
+    ///
+    /// Lists the serial ports, checks that the controller answers on the
+    /// chosen one, asks for the default output format, timeout, battery
+    /// capacity and profile name, then writes them to `--config` or the
+    /// default location. Ends by checking the new setup.
+    Setup {
+        /// Do not ask; take the answers from `--device`, `--format`,
+        /// `--timeout` and the options below
+        #[arg(short, long)]
+        yes: bool,
+        /// Replace an existing configuration file
+        #[arg(long)]
+        force: bool,
+        /// Profile name to save
+        #[arg(long)]
+        profile: Option<String>,
+        /// Battery capacity to save, for the state of charge
+        #[arg(long, value_name = "MAH")]
+        capacity: Option<u32>,
+    },
+
+    /// Provision a unit from a manifest in one run
+    ///
+    /// Checks the firmware version (uploading the manifest's image if the
+    /// unit runs an older one), programs the identity, sets the rail
+    /// defaults, provisions the battery gauge, sets the RTC interrupt action
+    /// and runs the health checks, stopping at the first failure. Prints one
+    /// pass/fail record with the outcome of each step.
+    Provision {
+        /// TOML manifest describing the unit
+        manifest: PathBuf,
+        /// Repeat only the steps that have not passed in the last failed
+        /// run of the same manifest
+        #[arg(long)]
+        resume: bool,
+        /// Also write the record as JSON to this file
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+        /// Do not ask for confirmation (as `auto_confirm` in the manifest)
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Run a hardware-in-the-loop test plan against the connected devkit
+    ///
+    /// The TOML plan lists `command`, `upload-firmware`, `sleep-test`,
+    /// `assert` and `raw` steps, run in order with per-step timeouts and
+    /// retries. A failed step does not stop the run unless it is marked
+    /// `critical`. Prints a line per step and a pass/fail report.
+    HilTest {
+        /// TOML test plan
+        plan: PathBuf,
+        /// Also write the report as JUnit XML to this file
+        #[arg(long, value_name = "FILE")]
+        junit: Option<PathBuf>,
+    },
+
+    /// Run a simulated controller, for demos and development without a board
+    ///
+    /// With --pty the simulator serves a pseudo-terminal in the background
+    /// and prints its path for `--device`. Otherwise the invocation after
+    /// the simulator options runs against it in this process, e.g.
+    /// `simulate --sim-scenario low-battery battery read`.
+    Simulate {
+        /// Serve a pseudo-terminal and print its path
+        #[arg(long = "pty")]
+        listen_pty: bool,
+        /// Serve the pseudo-terminal from this process until Ctrl-C instead
+        /// of in the background
+        #[arg(long, requires = "listen_pty")]
+        foreground: bool,
+        /// Unit the simulator plays
+        #[arg(long = "sim-scenario", value_enum, default_value = "normal")]
+        scenario: SimScenario,
+        /// Firmware version the simulator reports
+        #[arg(long = "sim-firmware", value_name = "VERSION")]
+        firmware: Option<String>,
+        /// Put a phone in the NFC field
+        #[arg(long = "sim-nfc-field")]
+        nfc_field: bool,
+        /// Invocation to run against the simulator, global options included
+        #[arg(
+            trailing_var_arg = true,
+            allow_hyphen_values = true,
+            value_name = "ARGS",
+            conflicts_with = "listen_pty",
+            required_unless_present = "listen_pty"
+        )]
+        invocation: Vec<String>,
+    },
+
+    /// Show example invocations
+    Examples {
+        /// Only show examples mentioning this keyword (case-insensitive)
+        filter: Option<String>,
+    },
+
+    /// List renamed subcommands, their new names and when the old ones go
+    Migrations,
+
+    /// Measure how much of a directory of device transcripts the parsers
+    /// understand
+    ///
+    /// Each `.txt` file holds one reply, named after its shell command with
+    /// the words joined by `_` (e.g. `ltc2959_status.fw23.txt`). The check
+    /// fails when a parser panics or a field is populated in fewer
+    /// transcripts than the baseline records.
+    ParseCheck {
+        /// Directory of transcripts
+        dir: PathBuf,
+        /// Baseline file [default: baseline.json in DIR]
+        #[arg(long, value_name = "FILE")]
+        baseline: Option<PathBuf>,
+        /// Record the current coverage as the baseline instead of checking it
+        #[arg(long)]
+        update_baseline: bool,
+    },
+
+    /// Replace this executable with the latest release from the artifact
+    /// server
+    ///
+    /// Looks up the newest release for this build's target on the channel,
+    /// and if it is newer downloads it, checks its SHA-256 (and minisign
+    /// signature when `[update] public_key` is set) and renames it over the
+    /// running executable, keeping the old one as `<executable>.old`. A
+    /// failure at any stage leaves the installed executable as it was.
+    SelfUpdate {
+        /// Release channel [default: `[update] channel`, or stable]
+        #[arg(long)]
+        channel: Option<String>,
+        /// Artifact server [default: `[update] url`]
+        #[arg(long)]
+        url: Option<String>,
+        /// Only report whether a newer release exists; exits with 5 if one
+        /// does
+        #[arg(long)]
+        check_only: bool,
+    },
+}
+
+impl Commands {
+    /// Replace a top-level convenience alias with the command it stands for
+    ///
+    /// Other commands are returned unchanged.
+    pub fn resolve_alias(self) -> Self {
+        match self {
+            Commands::Info { all: false } => Commands::System(SystemCommands::Info {
+                identity: false,
+                expect_version: None,
+            }),
+            Commands::Info { all: true } => Commands::Snapshot {
+                output: None,
+                redact: false,
+            },
+            Commands::Status => Commands::Pm(PowerManagementCommands::Stats),
+            command => command,
+        }
+    }
+
+    /// Parser for the controller reply this command prints
+    ///
+    /// Every command is listed, so a new one has to pick its parser;
+    /// [`ParsedPayload::Raw`] for replies without one and for commands that
+    /// build their output themselves.
+    pub fn payload(&self) -> ParsedPayload {
+        match self {
+            Commands::System(command) => match command {
+                SystemCommands::Info { .. } | SystemCommands::Uptime => ParsedPayload::System,
+                SystemCommands::Reboot { .. }
+                | SystemCommands::TimeRef { .. }
+                | SystemCommands::DfuMode { .. }
+                | SystemCommands::Erase(EraseCommands::App | EraseCommands::Defaults)
+                | SystemCommands::Verify { .. }
+                | SystemCommands::SetBaud { .. }
+                | SystemCommands::FactoryReset { .. } => ParsedPayload::Raw,
+            },
+            Commands::Power(command) => match command {
+                PowerCommands::Pmic { .. }
+                | PowerCommands::Wifi { .. }
+                | PowerCommands::Display { .. } => ParsedPayload::StateChange,
+                PowerCommands::Coulomb { reset: false, .. } => ParsedPayload::Battery,
+                PowerCommands::Coulomb { reset: true, .. }
+                | PowerCommands::Stats
+                | PowerCommands::Sequence { .. } => ParsedPayload::Raw,
+            },
+            Commands::Battery(command) => match command {
+                BatteryCommands::Read {
+                    watch: false,
+                    samples: None,
+                    ..
+                } => ParsedPayload::Battery,
+                // The battery monitor reads through `ltc2959 status`
+                BatteryCommands::Status { brief: false, .. } => ParsedPayload::Ltc2959,
+                BatteryCommands::Enable | BatteryCommands::Disable => ParsedPayload::StateChange,
+                BatteryCommands::Read { .. } | BatteryCommands::Status { brief: true, .. } => {
+                    ParsedPayload::Raw
+                }
+            },
+            Commands::Gpio(command) => match command {
+                GpioCommands::Get { port, pin } => match GpioPin::from_parts(port, *pin) {
+                    Ok(gpio) => ParsedPayload::Gpio {
+                        port: gpio.port,
+                        pin: gpio.pin,
+                    },
+                    // Rejected before anything is sent
+                    Err(_) => ParsedPayload::Raw,
+                },
+                GpioCommands::Set { .. } => ParsedPayload::StateChange,
+                GpioCommands::Config { .. } | GpioCommands::Script { .. } => ParsedPayload::Raw,
+            },
+            Commands::Nfc(command) => match command {
+                NfcCommands::Status => ParsedPayload::Nfc,
+                NfcCommands::Enable | NfcCommands::Disable => ParsedPayload::StateChange,
+                NfcCommands::Scan
+                | NfcCommands::Init
+                | NfcCommands::Debug
+                | NfcCommands::Rfdbg
+                | NfcCommands::Ed
+                | NfcCommands::Reset
+                | NfcCommands::Info
+                | NfcCommands::FieldDetect
+                | NfcCommands::Tag { .. }
+                | NfcCommands::SramRead
+                | NfcCommands::SramWrite { .. }
+                | NfcCommands::Transfer { .. } => ParsedPayload::Raw,
+            },
+            Commands::Board(BoardCommands::Reset | BoardCommands::Shutdown) => ParsedPayload::Raw,
+            Commands::Ltc2959(command) => match command {
+                Ltc2959Commands::Read {
+                    continuous: false, ..
+                } => ParsedPayload::Battery,
+                Ltc2959Commands::Status => ParsedPayload::Ltc2959,
+                Ltc2959Commands::Enable | Ltc2959Commands::Disable => ParsedPayload::StateChange,
+                Ltc2959Commands::Read { .. }
+                | Ltc2959Commands::Init
+                | Ltc2959Commands::Scan
+                | Ltc2959Commands::SetCharge { .. }
+                | Ltc2959Commands::ZeroCharge { .. }
+                | Ltc2959Commands::ChargeComplete
+                | Ltc2959Commands::Gpio { .. }
+                | Ltc2959Commands::ProductionReset
+                | Ltc2959Commands::AdcMode { .. }
+                | Ltc2959Commands::RegRead { .. }
+                | Ltc2959Commands::RegWrite { .. } => ParsedPayload::Raw,
+            },
+            Commands::Pm(command) => match command {
+                PowerManagementCommands::Stats => ParsedPayload::PowerStats,
+                PowerManagementCommands::Measure => ParsedPayload::Measurement,
+                PowerManagementCommands::All { .. }
+                | PowerManagementCommands::Pmic { .. }
+                | PowerManagementCommands::Wifi { .. }
+                | PowerManagementCommands::Display { .. }
+                | PowerManagementCommands::Imx93 { .. } => ParsedPayload::StateChange,
+                PowerManagementCommands::Defaults(command) => match command {
+                    DefaultsCommands::Show
+                    | DefaultsCommands::Save
+                    | DefaultsCommands::Import { .. }
+                    | DefaultsCommands::Pmic { .. }
+                    | DefaultsCommands::Wifi { .. }
+                    | DefaultsCommands::Display { .. } => ParsedPayload::RailDefaults,
+                    DefaultsCommands::Export { .. } => ParsedPayload::Raw,
+                },
+                PowerManagementCommands::Sleep { .. }
+                | PowerManagementCommands::Wake
+                | PowerManagementCommands::WakeSources(_)
+                | PowerManagementCommands::Monitor { .. }
+                | PowerManagementCommands::Ltc2959 { .. }
+                | PowerManagementCommands::Nfc { .. }
+                | PowerManagementCommands::BatteryCheck { .. } => ParsedPayload::Raw,
+            },
+            Commands::Rtc(command) => match command {
+                RtcCommands::Status | RtcCommands::Config { .. } | RtcCommands::Show => {
+                    ParsedPayload::Rtc
+                }
+                RtcCommands::Get => ParsedPayload::RtcCounter,
+                RtcCommands::Calibrate { .. } | RtcCommands::CalibrationRead => ParsedPayload::Raw,
+            },
+            Commands::Firmware(
+                FirmwareCommands::List
+                | FirmwareCommands::Upload { .. }
+                | FirmwareCommands::Analyze { .. }
+                | FirmwareCommands::Reset
+                | FirmwareCommands::Info,
+            ) => ParsedPayload::Raw,
+            Commands::Comm(CommCommands::BtWake { .. } | CommCommands::WlWake { .. }) => {
+                ParsedPayload::Raw
+            }
+            Commands::Identity(IdentityCommands::Show | IdentityCommands::Write { .. }) => {
+                ParsedPayload::Raw
+            }
+            Commands::Version | Commands::Info { all: false } => ParsedPayload::System,
+            Commands::Status => ParsedPayload::PowerStats,
+            Commands::Ping
+            | Commands::Info { all: true }
+            | Commands::Snapshot { .. }
+            | Commands::PowerAudit { .. }
+            | Commands::Latency { .. }
+            | Commands::Monitor { .. }
+            | Commands::Batch { .. }
+            | Commands::Run { .. }
+            | Commands::State(StateCommands::Show | StateCommands::Clear)
+            | Commands::History { .. }
+            | Commands::Log(LogCommands::Export { .. })
+            | Commands::Stats { .. }
+            | Commands::Schedule(
+                ScheduleCommands::At { .. }
+                | ScheduleCommands::List
+                | ScheduleCommands::Cancel { .. }
+                | ScheduleCommands::Run { .. },
+            )
+            | Commands::Fleet(FleetCommands::Scan { .. } | FleetCommands::Diff { .. })
+            | Commands::Setup { .. }
+            | Commands::Provision { .. }
+            | Commands::HilTest { .. }
+            | Commands::Simulate { .. }
+            | Commands::Examples { .. }
+            | Commands::Migrations
+            | Commands::ParseCheck { .. }
+            | Commands::SelfUpdate { .. } => ParsedPayload::Raw,
+        }
+    }
+
+    /// Whether `--format csv` produces CSV for this command
+    ///
+    /// These commands print reports that do not fit one table and fall back
+    /// to human-readable text.
+    pub fn has_csv_output(&self) -> bool {
+        !matches!(
+            self,
+            Commands::History { .. }
+                | Commands::Stats { .. }
+                | Commands::Log(_)
+                | Commands::Snapshot { .. }
+                | Commands::Info { all: true }
+                | Commands::PowerAudit { .. }
+                | Commands::Provision { .. }
+                | Commands::HilTest { .. }
+                | Commands::Simulate { .. }
+                | Commands::State(_)
+                | Commands::Schedule(_)
+                | Commands::Fleet(FleetCommands::Diff { .. })
+                | Commands::Examples { .. }
+                | Commands::Migrations
+                | Commands::ParseCheck { .. }
+                | Commands::SelfUpdate { .. }
+                | Commands::Setup { .. }
+                | Commands::Batch { .. }
+                | Commands::Run { list: true, .. }
+                | Commands::Firmware(_)
+                | Commands::Power(PowerCommands::Sequence { .. })
+                | Commands::Pm(PowerManagementCommands::WakeSources(_))
+                | Commands::Pm(PowerManagementCommands::BatteryCheck {
+                    samples: Some(_),
+                    ..
+                })
+                | Commands::Battery(BatteryCommands::Read {
+                    samples: Some(_),
+                    ..
+                })
+                | Commands::Identity(_)
+                | Commands::Nfc(
+                    NfcCommands::SramRead
+                        | NfcCommands::SramWrite { .. }
+                        | NfcCommands::Transfer { .. }
+                )
+                | Commands::Gpio(GpioCommands::Config { .. } | GpioCommands::Script { .. })
+                | Commands::System(
+                    SystemCommands::Verify { .. }
+                        | SystemCommands::SetBaud { .. }
+                        | SystemCommands::FactoryReset { .. }
+                        | SystemCommands::TimeRef { .. }
+                        | SystemCommands::DfuMode { .. }
+                )
+        )
+    }
+
+    /// Whether `--format prometheus` produces metrics for this command
+    ///
+    /// Only commands that measure or count something have a metrics form;
+    /// anything else would put CSV or text in front of a scraper.
+    pub fn has_prometheus_output(&self) -> bool {
+        matches!(
+            self,
+            Commands::Latency { .. }
+                | Commands::Monitor { .. }
+                | Commands::Stats { .. }
+                | Commands::Run { list: false, .. }
+                | Commands::Battery(BatteryCommands::Read { watch: true, .. })
+                | Commands::Ltc2959(Ltc2959Commands::Read {
+                    continuous: true,
+                    ..
+                })
+        )
+    }
+
+    /// Whether the command runs for an open-ended time, takes as many
+    /// `--samples` as asked for, or reads sections that may each time out,
+    /// and is exempt from the default deadline
+    pub fn is_long_running(&self) -> bool {
+        matches!(
+            self,
+            Commands::Monitor { .. }
+                | Commands::Snapshot { .. }
+                | Commands::Info { all: true }
+                | Commands::Batch { .. }
+                | Commands::Run { .. }
+                | Commands::Fleet(_)
+                | Commands::Firmware(_)
+                | Commands::Provision { .. }
+                | Commands::HilTest { .. }
+                | Commands::Power(PowerCommands::Sequence { .. })
+                | Commands::PowerAudit {
+                    sleep_sample_s: Some(_)
+                }
+                | Commands::Battery(BatteryCommands::Read { watch: true, .. })
+                | Commands::Battery(BatteryCommands::Read {
+                    samples: Some(_),
+                    ..
+                })
+                | Commands::Ltc2959(Ltc2959Commands::Read {
+                    continuous: true,
+                    ..
+                })
+                | Commands::Pm(PowerManagementCommands::BatteryCheck {
+                    samples: Some(_),
+                    ..
+                })
+                | Commands::System(SystemCommands::Erase(_))
+                | Commands::Nfc(NfcCommands::Transfer { .. })
+        )
+    }
+
+    /// Whether the command can be a line of a batch file or a macro step
+    ///
+    /// Commands that manage the tool or its files rather than the device
+    /// cannot, nor can batches, macros and test plans themselves.
+    pub fn runs_as_step(&self) -> bool {
+        !matches!(
+            self,
+            Commands::Batch { .. }
+                | Commands::Run { .. }
+                | Commands::History { .. }
+                | Commands::Stats { .. }
+                | Commands::Log(_)
+                | Commands::State(_)
+                | Commands::Schedule(_)
+                | Commands::Fleet(_)
+                | Commands::Simulate { .. }
+                | Commands::Examples { .. }
+                | Commands::Migrations
+                | Commands::ParseCheck { .. }
+                | Commands::SelfUpdate { .. }
+        )
+    }
+
+    /// Whether the command prints a single bare word meant for scripts
+    pub fn is_brief(&self) -> bool {
+        matches!(
+            self,
+            Commands::Battery(BatteryCommands::Status { brief: true, .. })
+                | Commands::Simulate {
+                    listen_pty: true,
+                    ..
+                }
+        )
+    }
+
+    /// Subcommand path as typed, e.g. `battery read` or `system factory-reset`
+    ///
+    /// Derived from the `Debug` form, so only variant names are included,
+    /// never argument values.
+    pub fn name(&self) -> String {
+        let debug = format!("{:?}", self);
+        let mut words = Vec::new();
+        for segment in debug.split('(') {
+            let ident: String = segment
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect();
+            let rest = &segment[ident.len()..];
+            if ident.is_empty() {
+                break;
+            }
+            words.push(kebab_case(&ident));
+            if !rest.is_empty() {
+                break;
+            }
+        }
+        words.join(" ")
+    }
+}
+
+/// Parse `line`, a command as typed after `eink-power-cli`, as a batch
+/// line or macro step
+///
+/// Global options come from the invocation running the step, so `line`
+/// holds only the command. The error is the first line of clap's message.
+pub fn parse_step(line: &str) -> Result<Commands, String> {
+    let args = std::iter::once(env!("CARGO_PKG_NAME")).chain(line.split_whitespace());
+    match Cli::try_parse_from(args) {
+        Ok(Cli {
+            command: Some(command),
+            ..
+        }) if command.runs_as_step() => Ok(command),
+        Ok(Cli {
+            command: Some(_), ..
+        }) => Err(format!("'{}' cannot be run as a step", line)),
+        Ok(_) => Err(format!("no command in '{}'", line)),
+        Err(e) => Err(e.to_string().lines().next().unwrap_or_default().to_string()),
+    }
+}
+
+/// `FactoryReset` -> `factory-reset`
+fn kebab_case(ident: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in ident.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 {
+            out.push('-');
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
 }
 
 /// System-level commands
 #[derive(Subcommand, Debug, Clone)]
 pub enum SystemCommands {
     /// Get system information
-    Info,
+    Info {
+        /// Also read the unit identity from the NFC EEPROM
+        #[arg(long)]
+        identity: bool,
+        /// Fail with exit code 4 unless the firmware version meets this
+        /// semver requirement (e.g. ">=2.2, <3")
+        #[arg(long, value_name = "REQ", value_parser = parse_version_req)]
+        expect_version: Option<VersionReq>,
+    },
     /// Reboot the controller
     Reboot {
         /// Cold reset (default: warm reset)
@@ -150,15 +1245,66 @@ pub enum SystemCommands {
     },
     /// Get system uptime
     Uptime,
+    /// Map PMU uptime to host time, for lining up PMU and host logs
+    ///
+    /// The mapping is saved until the PMU reboots. Later runs with `--at`
+    /// translate a PMU log timestamp to host time with it.
+    TimeRef {
+        /// PMU timestamp (milliseconds since boot) to show on the host clock
+        #[arg(long, value_name = "PMU_MS")]
+        at: Option<u64>,
+    },
     /// Request bootloader DFU mode
+    ///
+    /// Reports whether the bootloader enumerated and how long the window
+    /// stays open. A timeout of 0 keeps the bootloader waiting until it is
+    /// reset and has to be confirmed; `--cancel` resets it.
     DfuMode {
-        /// Timeout in seconds (0-255, default: 20, 0=infinite)
-        #[arg(default_value = "20")]
+        /// Timeout in seconds (0-255, 0=infinite)
+        #[arg(default_value_t = DEFAULT_DFU_TIMEOUT_S)]
         timeout: u8,
+        /// Leave DFU mode: reset the bootloader with mcumgr, or the
+        /// application on the console if it never left
+        #[arg(long)]
+        cancel: bool,
+        /// Do not ask for confirmation of an infinite timeout
+        #[arg(short, long)]
+        yes: bool,
     },
     /// Erase operations
     #[command(subcommand)]
     Erase(EraseCommands),
+    /// Check the connected firmware against release policy
+    Verify {
+        /// Fail unless the firmware is a clean production build
+        #[arg(long)]
+        require_production: bool,
+        /// Fail if the firmware is older than this version (e.g. 2.3.0)
+        #[arg(long)]
+        min_version: Option<String>,
+    },
+    /// Switch the controller console to another baud rate
+    ///
+    /// The local port follows and is checked with `ping`; if the controller
+    /// does not answer, the original rate is restored.
+    SetBaud {
+        /// New baud rate (9600-921600)
+        #[arg(value_parser = parse_baud_rate)]
+        rate: u32,
+        /// Keep the new rate across controller resets
+        #[arg(long)]
+        persist: bool,
+    },
+    /// Return the controller to factory state (erase defaults, reset the
+    /// coulomb counter, clear the RTC interrupt config, reboot, verify)
+    FactoryReset {
+        /// Skip a step (repeatable)
+        #[arg(long, value_enum)]
+        skip: Vec<FactoryResetStep>,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
 }
 
 /// Power control commands
@@ -177,7 +1323,8 @@ pub enum PowerCommands {
         state: PowerState,
     },
     /// Control display power
-    Disp {
+    #[command(alias = "disp")]
+    Display {
         /// Power state
         #[arg(value_enum)]
         state: PowerState,
@@ -185,16 +1332,72 @@ pub enum PowerCommands {
     /// Show power statistics
     Stats,
     /// Show battery coulomb counter readings
-    Coulomb,
+    Coulomb {
+        /// Zero the accumulated charge, e.g. after a battery swap; the
+        /// discarded charge is kept in the state directory
+        #[arg(long)]
+        reset: bool,
+        /// Do not ask for confirmation
+        #[arg(short, long, requires = "reset")]
+        yes: bool,
+    },
+    /// Power on several rails, dependencies first
+    Sequence {
+        /// Rails to power on (dependencies are added and ordered automatically)
+        #[arg(value_enum, required = true)]
+        rails: Vec<PowerRail>,
+    },
 }
 
 /// Battery monitoring commands
 #[derive(Subcommand, Debug, Clone)]
 pub enum BatteryCommands {
     /// Read battery measurements
-    Read,
+    Read {
+        /// Keep reading until Ctrl-C, redrawing one line on a terminal
+        #[arg(long)]
+        watch: bool,
+
+        /// Seconds between readings with --watch
+        #[arg(
+            long,
+            default_value_t = 1,
+            value_parser = clap::value_parser!(u64).range(1..),
+            requires = "watch"
+        )]
+        interval: u64,
+
+        /// Battery capacity, to show the state of charge
+        #[arg(long, value_name = "MAH", requires = "watch")]
+        capacity: Option<u32>,
+
+        /// Voltage readings shown in the sparkline
+        #[arg(long, value_name = "SAMPLES", default_value_t = DEFAULT_SPARKLINE_SAMPLES, requires = "watch")]
+        sparkline: usize,
+
+        /// Take this many readings and report their median, mean and range
+        #[arg(
+            long,
+            value_name = "N",
+            value_parser = clap::value_parser!(u32).range(1..),
+            conflicts_with = "watch"
+        )]
+        samples: Option<u32>,
+
+        /// Milliseconds between readings with --samples
+        #[arg(long, value_name = "MS", default_value_t = DEFAULT_SAMPLE_INTERVAL_MS, requires = "samples")]
+        sample_interval_ms: u64,
+    },
     /// Get battery status
-    Status,
+    Status {
+        /// Print only `charging`, `discharging` or `idle`
+        #[arg(long)]
+        brief: bool,
+
+        /// Current band around 0 mA reported as idle
+        #[arg(long, value_name = "MA", default_value_t = DEFAULT_DEADBAND_MA)]
+        deadband: u16,
+    },
     /// Enable battery monitoring
     Enable,
     /// Disable battery monitoring
@@ -205,29 +1408,66 @@ pub enum BatteryCommands {
 #[derive(Subcommand, Debug, Clone)]
 pub enum GpioCommands {
     /// Read GPIO state
+    ///
+    /// The pin is given as a port and number (`gpioa 5`, `A 5`) or as one
+    /// name (`A5`, `pta5`, `PTA5`).
     Get {
-        /// GPIO port (e.g., gpioa, gpiob)
+        /// GPIO port (e.g., gpioa, A, PTA) or the whole pin (e.g., PTA5)
         port: String,
-        /// GPIO pin number
-        pin: u8,
+        /// GPIO pin number, unless PORT names the pin
+        pin: Option<u8>,
     },
     /// Set GPIO state
+    ///
+    /// The pin is given as a port and number (`gpioa 5 1`) or as one name
+    /// (`PTA5 1`).
     Set {
-        /// GPIO port (e.g., gpioa, gpiob)
+        /// GPIO port (e.g., gpioa, A, PTA) or the whole pin (e.g., PTA5)
         port: String,
-        /// GPIO pin number
+        /// GPIO pin number, or the value when PORT names the pin
         pin: u8,
         /// Value to set (0 or 1)
-        value: u8,
+        value: Option<u8>,
     },
-    /// Configure GPIO pin
+    /// Configure GPIO pin and read it back to verify the mode
     Config {
-        /// GPIO port (e.g., gpioa, gpiob)
-        port: String,
-        /// GPIO pin number
-        pin: u8,
-        /// GPIO mode (input, output, etc.)
-        mode: String,
+        /// GPIO port (e.g., gpioa, A, PTA) or the whole pin (e.g., PTA5)
+        #[arg(required_unless_present = "pins")]
+        port: Option<String>,
+        /// GPIO pin number, or the mode when PORT names the pin
+        #[arg(required_unless_present = "pins")]
+        pin: Option<String>,
+        /// GPIO mode (input, output, input-pullup, etc.)
+        mode: Option<String>,
+        /// Configure several pins the same way, e.g. a0,a1,b3 (with --mode)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            conflicts_with_all = ["port", "pin", "mode"],
+            requires = "pins_mode"
+        )]
+        pins: Vec<GpioPin>,
+        /// Mode applied to every pin in --pins
+        #[arg(
+            long = "mode",
+            id = "pins_mode",
+            value_name = "MODE",
+            requires = "pins"
+        )]
+        pins_mode: Option<String>,
+    },
+    /// Drive several pins in order with delays in between
+    ///
+    /// Runs as one firmware transaction when the firmware has `gpio batch`,
+    /// for timing the round trip of each command cannot reach; otherwise
+    /// each pin is set from the host over the open connection.
+    Script {
+        /// Steps separated by commas: pin settings like a0=1 and delays
+        /// like 5ms, 500us or 1s, e.g. a0=1,5ms,a1=1,2ms,b3=0
+        ops: GpioScript,
+        /// Set each pin from the host even if the firmware has `gpio batch`
+        #[arg(long)]
+        host: bool,
     },
 }
 
@@ -256,6 +1496,46 @@ pub enum NfcCommands {
     Info,
     /// Check field detection
     FieldDetect,
+    /// Identify the NFC tag currently in the field
+    Tag {
+        /// UID display format
+        #[arg(long, value_enum, default_value = "hex")]
+        uid_format: UidFormat,
+    },
+    /// Read the 64-byte SRAM mailbox
+    SramRead,
+    /// Write up to 64 bytes to the start of the SRAM mailbox
+    SramWrite {
+        /// Bytes as hex digits, e.g. 0102A0FF
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        data_hex: Option<String>,
+        /// Write the contents of a file instead
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+    },
+    /// Move a file to or from a phone through the SRAM mailbox in
+    /// pass-through mode
+    Transfer {
+        /// Which way the data goes
+        #[arg(value_enum)]
+        direction: TransferDirection,
+        /// File to send, or to write what is received
+        file: PathBuf,
+        /// Seconds to wait for the phone, at the start and for each mailbox
+        #[arg(long, value_name = "SECS", default_value_t = DEFAULT_TRANSFER_TIMEOUT_S)]
+        timeout: u64,
+    },
+}
+
+/// NFC tag UID display formats
+#[derive(ValueEnum, Clone, Debug)]
+pub enum UidFormat {
+    /// Colon-separated hex bytes (04:AB:CD:EF)
+    Hex,
+    /// UID bytes as a single big-endian decimal number
+    Decimal,
+    /// Contiguous hex digits (04ABCDEF)
+    Raw,
 }
 
 /// Power management commands
@@ -292,9 +1572,15 @@ pub enum PowerManagementCommands {
         /// VLLS3 mode (~412 nA, full RAM, most wake sources)
         #[arg(long)]
         vlls3: bool,
+        /// Sleep in VLLS0/VLLS1 even if nothing could wake the controller
+        #[arg(long)]
+        force: bool,
     },
     /// Show last LLS wake source
     Wake,
+    /// Inspect or change which sources may wake the controller
+    #[command(subcommand)]
+    WakeSources(WakeSourcesCommands),
     /// Battery voltage and current measurement (one-time)
     Measure,
     /// Start/stop monitoring
@@ -324,7 +1610,8 @@ pub enum PowerManagementCommands {
         state: PowerState,
     },
     /// Control display power
-    Disp {
+    #[command(alias = "disp")]
+    Display {
         /// Power state
         #[arg(value_enum)]
         state: PowerState,
@@ -345,7 +1632,15 @@ pub enum PowerManagementCommands {
         action: DeviceAction,
     },
     /// Perform battery health check
-    BatteryCheck,
+    BatteryCheck {
+        /// Run the check this many times and decide on the median
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        samples: Option<u32>,
+
+        /// Milliseconds between checks with --samples
+        #[arg(long, value_name = "MS", default_value_t = DEFAULT_SAMPLE_INTERVAL_MS, requires = "samples")]
+        sample_interval_ms: u64,
+    },
     /// Control i.MX93 power
     Imx93 {
         /// Power state
@@ -369,7 +1664,26 @@ pub enum Ltc2959Commands {
     /// Initialize LTC2959 coulomb counter
     Init,
     /// Read voltage, current, charge, power
-    Read,
+    Read {
+        /// Switch the ADC to continuous mode and stream readings, restoring
+        /// the previous mode afterwards
+        #[arg(long)]
+        continuous: bool,
+
+        /// Milliseconds between readings with --continuous
+        #[arg(
+            long,
+            value_name = "MS",
+            default_value_t = DEFAULT_SAMPLE_INTERVAL_MS,
+            value_parser = clap::value_parser!(u64).range(1..),
+            requires = "continuous"
+        )]
+        interval_ms: u64,
+
+        /// Stop after this long, e.g. `30s` or `5m` (default: until Ctrl-C)
+        #[arg(long, value_parser = parse_read_duration, requires = "continuous")]
+        duration: Option<Duration>,
+    },
     /// Show device status and alert flags
     Status,
     /// Enable ADC measurements (smart sleep)
@@ -383,10 +1697,17 @@ pub enum Ltc2959Commands {
         /// Charge value in mAh
         charge: u32,
     },
+    /// Zero the accumulated charge and verify it, as `power coulomb --reset`
+    ZeroCharge {
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
     /// Trigger charge complete
     ChargeComplete,
     /// Control CC_GPIO pin
-    CcGpio {
+    #[command(alias = "cc-gpio")]
+    Gpio {
         /// GPIO state
         #[arg(value_enum)]
         state: PowerState,
@@ -425,12 +1746,33 @@ pub enum FirmwareCommands {
         /// Skip system reset (assume already in bootloader mode)
         #[arg(long)]
         skip_reset: bool,
-        /// Custom serial port (default: /dev/ttyLP2)
+        /// Serial port for mcumgr (default: the global --device)
         #[arg(long)]
         port: Option<String>,
-        /// Custom baud rate (default: 115200)
+        /// Baud rate for mcumgr (default: the global --baud)
         #[arg(long)]
         baud: Option<u32>,
+        /// Switch the console to this rate for the upload, then restore it
+        #[arg(
+            long,
+            value_name = "RATE",
+            num_args = 0..=1,
+            default_missing_value = "921600",
+            value_parser = parse_baud_rate,
+            conflicts_with_all = ["skip_reset", "baud", "transport_ble", "transport_udp"]
+        )]
+        fast: Option<u32>,
+        /// Upload over Bluetooth LE to this peer address instead of serial
+        #[arg(long, value_name = "ADDR", conflicts_with_all = ["port", "baud", "transport_udp"])]
+        transport_ble: Option<String>,
+        /// Upload over UDP to this endpoint instead of serial
+        #[arg(long, value_name = "HOST:PORT", conflicts_with_all = ["port", "baud"])]
+        transport_udp: Option<String>,
+    },
+    /// Inspect an MCUboot image file without connecting to the device
+    Analyze {
+        /// Firmware file path
+        file: std::path::PathBuf,
     },
     /// Reset PMU into bootloader mode
     Reset,
@@ -439,13 +1781,23 @@ pub enum FirmwareCommands {
 }
 
 /// Power states
-#[derive(ValueEnum, Clone, Debug)]
+#[derive(ValueEnum, Clone, Copy, Debug)]
 pub enum PowerState {
     On,
     Off,
     Status,
 }
 
+impl From<PowerState> for crate::power::control::PowerState {
+    fn from(state: PowerState) -> Self {
+        match state {
+            PowerState::On => Self::On,
+            PowerState::Off => Self::Off,
+            PowerState::Status => Self::Status,
+        }
+    }
+}
+
 /// Monitor actions
 #[derive(ValueEnum, Clone, Debug)]
 pub enum MonitorAction {
@@ -468,10 +1820,19 @@ pub enum RtcCommands {
     },
     /// Show external RTC interrupt configuration
     Show,
+    /// Correct external RTC (PCF2131) crystal drift
+    Calibrate {
+        /// Frequency offset to apply in ppm (-100 to 100, 4.34 ppm steps)
+        #[arg(allow_hyphen_values = true, value_parser = clap::value_parser!(i16).range(-100..=100))]
+        ppm_offset: i16,
+    },
+    /// Read the external RTC offset register
+    CalibrationRead,
 }
 
 /// External RTC interrupt actions
-#[derive(ValueEnum, Clone, Debug)]
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ExternalRtcAction {
     /// No action - just log the event
     None,
@@ -481,6 +1842,32 @@ pub enum ExternalRtcAction {
     Auto,
 }
 
+impl ExternalRtcAction {
+    /// Argument of `rtc config`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExternalRtcAction::None => "none",
+            ExternalRtcAction::Wake => "wake",
+            ExternalRtcAction::Auto => "auto",
+        }
+    }
+}
+
+/// Units `simulate` can play
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SimScenario {
+    /// Healthy unit on production firmware
+    Normal,
+    /// Nearly flat battery: low voltage and charge, degraded health check
+    LowBattery,
+    /// Stuck in the MCUboot serial recovery bootloader: the shell is silent
+    /// and only mcumgr echo is answered
+    BootloaderMode,
+    /// Noisy line: log lines in replies, garbled characters, slow console
+    FlakySerial,
+}
+
 /// Erase commands
 #[derive(Subcommand, Debug, Clone)]
 pub enum EraseCommands {
@@ -490,6 +1877,23 @@ pub enum EraseCommands {
     Defaults,
 }
 
+/// Wake-source configuration commands
+#[derive(Subcommand, Debug, Clone)]
+pub enum WakeSourcesCommands {
+    /// Show the wake sources enabled in the firmware
+    Show,
+    /// Allow a source to wake the controller
+    Enable {
+        #[arg(value_enum)]
+        source: WakeSource,
+    },
+    /// Stop a source waking the controller
+    Disable {
+        #[arg(value_enum)]
+        source: WakeSource,
+    },
+}
+
 /// Power rail defaults commands
 #[derive(Subcommand, Debug, Clone)]
 pub enum DefaultsCommands {
@@ -497,6 +1901,16 @@ pub enum DefaultsCommands {
     Show,
     /// Save current power rail states as defaults
     Save,
+    /// Back up the stored defaults to a JSON file
+    Export {
+        /// Output file
+        file: PathBuf,
+    },
+    /// Apply and save defaults from a JSON file written by `export`
+    Import {
+        /// Input file
+        file: PathBuf,
+    },
     /// Set PMIC_EN default state
     Pmic {
         /// Power state
@@ -510,13 +1924,150 @@ pub enum DefaultsCommands {
         state: PowerState,
     },
     /// Set DISP_EN default state
-    Disp {
+    #[command(alias = "disp")]
+    Display {
         /// Power state
         #[arg(value_enum)]
         state: PowerState,
     },
 }
 
+/// Stored state commands
+#[derive(Subcommand, Debug, Clone)]
+pub enum StateCommands {
+    /// List the files stored for the device
+    Show,
+    /// Delete all state stored for the device
+    Clear,
+}
+
+/// Battery log commands
+#[derive(Subcommand, Debug, Clone)]
+pub enum LogCommands {
+    /// Write a battery log as a typed CSV or Parquet table
+    ///
+    /// Reads the NDJSON or CSV that `battery read --watch`, `battery read`
+    /// and `monitor` write, or a `monitor --follow --console-log` capture,
+    /// sorts it by time and adds power, state of charge and the change
+    /// since the previous reading. Lines that cannot be read are skipped
+    /// and counted.
+    Export {
+        /// Log file (NDJSON, CSV or console log)
+        input: PathBuf,
+        /// Table file to write
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Table format [default: parquet for a .parquet file, else csv]
+        #[arg(long, value_enum)]
+        format: Option<ExportFormat>,
+        /// Leave out readings before this time: RFC 3339, "2025-10-09 14:00"
+        /// or 2025-10-09
+        #[arg(long, value_parser = parse_time_bound)]
+        from: Option<DateTime<Utc>>,
+        /// Leave out readings from this time on
+        #[arg(long, value_parser = parse_time_bound)]
+        to: Option<DateTime<Utc>>,
+        /// Battery capacity, for the state of charge of readings without one
+        #[arg(long, value_name = "MAH")]
+        capacity: Option<u32>,
+        /// Print console log lines that are not a sample on stderr
+        #[arg(long)]
+        show_unparsed: bool,
+    },
+}
+
+/// Fleet inventory commands
+#[derive(Subcommand, Debug, Clone)]
+pub enum FleetCommands {
+    /// Probe every candidate port and list the PMUs found
+    Scan {
+        /// Port to probe instead of the ones found on the host (repeatable)
+        #[arg(long = "port", value_name = "PATH")]
+        ports: Vec<String>,
+        /// Also probe built-in UARTs, not only USB serial adapters
+        #[arg(long, conflicts_with = "ports")]
+        all_ports: bool,
+        /// Write the inventory to this file for a later `fleet diff`
+        #[arg(long, value_name = "FILE")]
+        save: Option<PathBuf>,
+    },
+    /// Compare a saved inventory with a later one, or with a new scan
+    Diff {
+        /// Inventory written by `fleet scan --save`
+        before: PathBuf,
+        /// Later inventory [default: scan now]
+        after: Option<PathBuf>,
+        /// Port to probe for the new scan (repeatable)
+        #[arg(long = "port", value_name = "PATH", conflicts_with = "after")]
+        ports: Vec<String>,
+        /// Also probe built-in UARTs in the new scan
+        #[arg(long, conflicts_with_all = ["after", "ports"])]
+        all_ports: bool,
+    },
+}
+
+/// Deferred command commands
+#[derive(Subcommand, Debug, Clone)]
+pub enum ScheduleCommands {
+    /// Run a command at a given time
+    At {
+        /// When to run: +90s, +1h30m, 22:00, "2025-12-24 22:00" or RFC 3339
+        #[arg(value_parser = parse_due_now)]
+        at: DateTime<Utc>,
+        /// How to wait for the due time
+        #[arg(long, value_enum, default_value = "detach")]
+        backend: ScheduleBackend,
+        /// Command to run, as typed after eink-power-cli (e.g. power disp off)
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// List the scheduled commands
+    List,
+    /// Cancel a pending command
+    Cancel {
+        /// Schedule id shown by `schedule at` and `schedule list`
+        id: String,
+    },
+    /// Wait for a schedule and run it (started by `schedule at`)
+    #[command(hide = true)]
+    Run { id: String },
+}
+
+fn parse_due_now(text: &str) -> Result<DateTime<Utc>, String> {
+    schedule::parse_due(text, Local::now())
+}
+
+/// Unit identity commands
+#[derive(Subcommand, Debug, Clone)]
+pub enum IdentityCommands {
+    /// Show the programmed serial, hardware revision and manufacture date
+    Show,
+    /// Program the identity and verify it by reading it back
+    Write {
+        /// Serial number (up to 12 letters, digits or '-')
+        #[arg(long)]
+        serial: String,
+        /// Hardware revision
+        #[arg(long)]
+        hw_rev: u8,
+        /// Manufacture date as YYYY-MM-DD (default: today)
+        #[arg(long, value_name = "DATE")]
+        date: Option<NaiveDate>,
+        /// Do not ask for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// Sampling primitive for the monitor command
+#[derive(ValueEnum, Clone, Debug)]
+pub enum MonitorSource {
+    /// One-shot `pm measure` (works while the ADC is in smart-sleep)
+    Measure,
+    /// Coulomb counter readout via `ltc2959 read`
+    Ltc2959,
+}
+
 /// Device actions (wake/sleep)
 #[derive(ValueEnum, Clone, Debug)]
 pub enum DeviceAction {
@@ -526,19 +2077,55 @@ pub enum DeviceAction {
     Sleep,
 }
 
+impl DeviceAction {
+    /// Action keyword understood by the controller shell
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceAction::Wake => "wake",
+            DeviceAction::Sleep => "sleep",
+        }
+    }
+}
+
 /// Communication control commands
 #[derive(Subcommand, Debug, Clone)]
 pub enum CommCommands {
-    /// Control BT_WAKE_HOST signal (PTC1)
+    /// Control BT_WAKE_HOST signal (PTC1, gpioc 1)
     BtWake {
         /// Power state
         #[arg(value_enum)]
         state: PowerState,
     },
-    /// Control WL_WAKE_HOST signal (PTC3)
+    /// Control WL_WAKE_HOST signal (PTC3, gpioc 3)
     WlWake {
         /// Power state
         #[arg(value_enum)]
         state: PowerState,
     },
 }
+
+fn parse_read_duration(text: &str) -> Result<Duration, String> {
+    parse_sleep_duration(text)
+        .filter(|duration| !duration.is_zero())
+        .ok_or_else(|| format!("invalid duration '{}'; expected e.g. 30s, 5m or 1h", text))
+}
+
+/// A semver requirement such as `>=2.2, <3`
+fn parse_version_req(text: &str) -> Result<VersionReq, String> {
+    VersionReq::parse(text).map_err(|e| format!("invalid version requirement '{}': {}", text, e))
+}
+
+/// Accept only console rates the controller firmware supports
+fn parse_baud_rate(s: &str) -> Result<u32, String> {
+    let rate: u32 = s
+        .parse()
+        .map_err(|_| format!("invalid baud rate '{}'", s))?;
+    if SUPPORTED_BAUD_RATES.contains(&rate) {
+        Ok(rate)
+    } else {
+        Err(format!(
+            "unsupported baud rate {}; supported: {:?}",
+            rate, SUPPORTED_BAUD_RATES
+        ))
+    }
+}
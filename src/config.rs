@@ -0,0 +1,181 @@
+/*
+ * E-ink Power CLI - Configuration File
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Settings loaded from `~/.config/eink-power-cli/config.toml` or `--config`
+
+use crate::cli::OutputFormat;
+use crate::error::Result;
+use crate::power::rails::PowerRailGraph;
+use crate::serial::{CommandMap, EchoCheck, ResyncMode, TimeoutRetry};
+use crate::state;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Settings read from the configuration file
+///
+/// Sections the CLI does not use yet are ignored. Options given on the
+/// command line take precedence over the file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    /// Name of this setup, e.g. `bench` or `fleet`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Serial link settings (`[connection]`)
+    #[serde(default)]
+    pub connection: ConnectionConfig,
+    /// Output settings (`[output]`)
+    #[serde(default)]
+    pub output: OutputConfig,
+    /// Battery settings (`[battery]`)
+    #[serde(default)]
+    pub battery: BatteryConfig,
+    /// Power rail settings (`[rails]`)
+    #[serde(default, skip_serializing_if = "RailsConfig::is_unset")]
+    pub rails: RailsConfig,
+    /// Shell root command overrides (`[commands]`)
+    #[serde(default, skip_serializing_if = "CommandMap::is_empty")]
+    pub commands: CommandMap,
+    /// Timeouts in seconds by command prefix (`[timeouts]`), e.g.
+    /// `"system erase" = 30`; `--timeout` overrides them
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub timeouts: BTreeMap<String, u64>,
+    /// Milliseconds to send nothing after a command by prefix (`[guards]`),
+    /// e.g. `"pm sleep" = 500`; `--no-guard` turns them off
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub guards: BTreeMap<String, u64>,
+    /// Named command sequences (`[macros]`), run with `run <name>`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub macros: BTreeMap<String, Vec<String>>,
+    /// Where `self-update` gets releases (`[update]`)
+    #[serde(default, skip_serializing_if = "UpdateConfig::is_unset")]
+    pub update: UpdateConfig,
+}
+
+/// `[connection]` section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionConfig {
+    /// Serial device used when `--device` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// Command timeout in seconds used when `--timeout` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Minimum gap between consecutive commands; `--pacing-ms` overrides it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pacing_ms: Option<u64>,
+    /// When to clear the shell input line; `--resync` overrides it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resync: Option<ResyncMode>,
+    /// Which commands have their echo checked; `--verify-echo` overrides it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verify_echo: Option<EchoCheck>,
+    /// Which command timeouts are retried; `--retry-timeout` overrides it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_timeout: Option<TimeoutRetry>,
+    /// systemd unit `--steal` stops when the process holding the port runs
+    /// in none, e.g. `serial-getty@ttyLP2.service`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steal_unit: Option<String>,
+}
+
+/// `[output]` section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OutputConfig {
+    /// Output format used when `--format` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<OutputFormat>,
+}
+
+/// `[battery]` section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatteryConfig {
+    /// Battery capacity used when `battery read --capacity` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capacity_mah: Option<u32>,
+}
+
+/// `[rails]` section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RailsConfig {
+    /// Rail dependencies such as `disp requires pmic`, replacing the board
+    /// topology; an empty list turns the checks off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<Vec<String>>,
+}
+
+impl RailsConfig {
+    /// Whether the board default applies
+    pub fn is_unset(&self) -> bool {
+        self.dependencies.is_none()
+    }
+
+    /// Dependency model for rail switching
+    pub fn graph(&self) -> Result<PowerRailGraph> {
+        match &self.dependencies {
+            Some(rules) => PowerRailGraph::from_rules(rules),
+            None => Ok(PowerRailGraph::default()),
+        }
+    }
+}
+
+/// `[update]` section
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpdateConfig {
+    /// Artifact server root; `self-update --url` overrides it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Release channel; `self-update --channel` overrides it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// minisign public key releases must be signed with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+impl UpdateConfig {
+    /// Whether the section is empty
+    pub fn is_unset(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl Config {
+    /// Default location of the configuration file
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("eink-power-cli").join("config.toml"))
+    }
+
+    /// Load the configuration
+    ///
+    /// An explicit `path` must exist; the default file is optional and
+    /// defaults are used when it is missing.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Self::default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let settings = config::Config::builder()
+            .add_source(config::File::from(path).required(required))
+            .build()?;
+        Ok(settings.try_deserialize()?)
+    }
+
+    /// The configuration as TOML that [`Config::load`] reads back unchanged
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).map_err(|e| config::ConfigError::Message(e.to_string()).into())
+    }
+
+    /// Write the configuration to `path`, replacing it atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        state::write_atomic(path, self.to_toml()?.as_bytes())?;
+        Ok(())
+    }
+}
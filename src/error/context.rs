@@ -0,0 +1,113 @@
+/*
+ * E-ink Power CLI - Error Context
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Context chains for [`PowerCliError`]
+//!
+//! A [`ContextualError`] keeps the original error intact and records what
+//! the CLI was doing when it happened, innermost first:
+//!
+//! ```text
+//! while executing command battery read
+//! while sending 'ltc2959 read' to /dev/ttyLP2
+//! Command timeout after 3s: partial response of 14 bytes.
+//! ```
+
+use super::report::SessionContext;
+use super::PowerCliError;
+use std::fmt;
+
+/// [`PowerCliError`] with the chain of operations that led to it
+#[derive(Debug)]
+pub struct ContextualError {
+    pub error: PowerCliError,
+    /// Innermost context first
+    pub context: Vec<String>,
+    /// Session the error happened in, for `--bug-report`
+    pub session: Option<Box<SessionContext>>,
+}
+
+impl ContextualError {
+    /// Add an outer context line
+    pub fn context(mut self, ctx: impl Into<String>) -> Self {
+        self.context.push(ctx.into());
+        self
+    }
+
+    /// Attach the session the error happened in
+    pub fn with_session(mut self, session: SessionContext) -> Self {
+        self.session = Some(Box::new(session));
+        self
+    }
+
+    /// Process exit code of the root error
+    pub fn exit_code(&self) -> i32 {
+        self.error.exit_code()
+    }
+}
+
+impl fmt::Display for ContextualError {
+    /// Context lines, outermost first, above the root error
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ctx in self.context.iter().rev() {
+            writeln!(f, "{}", ctx)?;
+        }
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for ContextualError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<PowerCliError> for ContextualError {
+    fn from(error: PowerCliError) -> Self {
+        Self {
+            error,
+            context: Vec::new(),
+            session: None,
+        }
+    }
+}
+
+impl PowerCliError {
+    /// Wrap this error with a first context line
+    pub fn with_context(self, ctx: impl Into<String>) -> ContextualError {
+        ContextualError::from(self).context(ctx)
+    }
+}
+
+/// Errors that can take another context line; used by [`context!`](crate::context)
+pub trait AddContext {
+    fn add_context(self, ctx: String) -> ContextualError;
+}
+
+impl AddContext for PowerCliError {
+    fn add_context(self, ctx: String) -> ContextualError {
+        self.with_context(ctx)
+    }
+}
+
+impl AddContext for ContextualError {
+    fn add_context(self, ctx: String) -> ContextualError {
+        self.context(ctx)
+    }
+}
+
+/// Attach a formatted context line to the error of a `Result`
+///
+/// The message is only formatted on the error path:
+///
+/// ```ignore
+/// let response = context!(connection.send_command("version").await, "while reading version")?;
+/// ```
+#[macro_export]
+macro_rules! context {
+    ($result:expr, $($arg:tt)+) => {
+        $result.map_err(|e| $crate::error::context::AddContext::add_context(e, format!($($arg)+)))
+    };
+}
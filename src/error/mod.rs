@@ -0,0 +1,221 @@
+/*
+ * E-ink Power CLI - Error Handling
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+pub mod context;
+pub mod report;
+
+pub use context::ContextualError;
+
+use crate::json::raw;
+use crate::json::BatteryVerdict;
+use crate::serial::connection::Phase;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Exit code of [`PowerCliError::VersionMismatch`]
+pub const VERSION_MISMATCH_EXIT_CODE: i32 = 4;
+
+/// Exit code of [`PowerCliError::UpdateAvailable`]
+pub const UPDATE_AVAILABLE_EXIT_CODE: i32 = 5;
+
+/// Bytes of a partial reply kept in [`PowerCliError::Timeout`]
+pub const PARTIAL_REPLY_LIMIT: usize = 256;
+
+/// Main error type for the E-ink Power CLI application
+#[derive(Error, Debug)]
+#[allow(dead_code)] // Some variants are defined for future use
+pub enum PowerCliError {
+    /// Serial communication errors
+    #[error("Serial communication error: {0}")]
+    Serial(#[from] serialport::Error),
+
+    /// Tokio serial errors
+    #[error("Async serial error: {0}")]
+    TokioSerial(String),
+
+    /// I/O errors
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Command timeout
+    ///
+    /// No data at all points at the wiring, the baud rate or a sleeping
+    /// PMU; a partial reply (often only the echo) at a slow command.
+    #[error("Command timeout after {timeout}s: {}", timeout_hint(.bytes_received))]
+    Timeout {
+        timeout: u64,
+        bytes_received: usize,
+        /// Start of what arrived, at most [`PARTIAL_REPLY_LIMIT`] bytes
+        partial: Option<String>,
+    },
+
+    /// Invalid response from controller
+    #[error("Invalid response from controller: {response}")]
+    InvalidResponse { response: String },
+
+    /// Controller returned an error
+    #[error("Controller error: {message}")]
+    ControllerError { message: String },
+
+    /// Configuration errors
+    #[error("Configuration error: {0}")]
+    Config(#[from] config::ConfigError),
+
+    /// JSON parsing errors
+    #[error("JSON parsing error: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// JSON output written by an incompatible build
+    #[error("Unsupported JSON output schema version {found} (this build reads {expected})")]
+    SchemaVersion { found: String, expected: u32 },
+
+    /// Device not found
+    #[error("Device not found: {device}")]
+    DeviceNotFound { device: String },
+
+    /// Overall `--max-duration` deadline expired
+    #[error("Deadline of {seconds}s exceeded during {phase}")]
+    DeadlineExceeded { seconds: u64, phase: Phase },
+
+    /// Another process has the serial port open
+    #[error(
+        "Serial port {device} is in use by {holder}.\n\
+         Stop it, or retry with --steal to stop its systemd unit for the duration of the command"
+    )]
+    PortBusy { device: String, holder: String },
+
+    /// Connection not established
+    #[error("Connection not established - call connect() first")]
+    NotConnected,
+
+    /// Invalid command format
+    #[error("Invalid command format: {command}")]
+    InvalidCommand { command: String },
+
+    /// Contradictory command-line options
+    #[error("Invalid arguments: {message}")]
+    InvalidArguments { message: String },
+
+    /// Battery monitoring error
+    #[error("Battery monitoring error: {message}")]
+    BatteryError { message: String },
+
+    /// Power control error
+    #[error("Power control error: {message}")]
+    PowerError { message: String },
+
+    /// NFC interface error
+    #[error("NFC interface error: {message}")]
+    NfcError { message: String },
+
+    /// GPIO control error
+    #[error("GPIO control error: {message}")]
+    GpioError { message: String },
+
+    /// Controller console is streaming logs but the shell is not responding
+    #[error(
+        "PMU shell unavailable: console produced log output but no prompt or pong.\n\
+         Seen: {snippet}\n\
+         Reset the controller board or retry with --auto-recover-shell"
+    )]
+    ShellUnavailable { snippet: String },
+
+    /// Controller is in MCUboot serial recovery instead of the application
+    #[error(
+        "PMU on {device} is in the bootloader, not the application firmware.\n\
+         Use `firmware upload --skip-reset --allow-bootloader <file>` \
+         or `firmware reset --allow-bootloader`"
+    )]
+    InBootloader { device: String },
+
+    /// Console baud-rate change did not complete
+    #[error("Baud rate change failed: {message}")]
+    BaudChange { message: String },
+
+    /// Battery health check verdict was not healthy
+    #[error("Battery health check verdict: {}", verdict.as_str())]
+    BatteryUnhealthy { verdict: BatteryVerdict },
+
+    /// Firmware version does not meet `system info --expect-version`
+    #[error("Firmware version {version} does not meet the requirement {requirement}")]
+    VersionMismatch {
+        version: String,
+        requirement: String,
+    },
+
+    /// Firmware management errors
+    #[error("Firmware error: {message}")]
+    FirmwareError { message: String },
+
+    /// `parse-check` found a parser panic or coverage below the baseline
+    #[error("Parse coverage check failed: {message}")]
+    ParseCoverage { message: String },
+
+    /// A `hil-test` plan step failed
+    #[error("Hardware-in-the-loop test failed: {message}")]
+    HilTest { message: String },
+
+    /// `self-update` could not check, download, verify or install a release
+    #[error("Self-update failed: {message}")]
+    UpdateError { message: String },
+
+    /// `self-update --check-only` found a newer release
+    #[error("Update available: {current} -> {latest}")]
+    UpdateAvailable { current: String, latest: String },
+
+    /// Stopped by Ctrl-C while waiting
+    #[error("Cancelled while {during}")]
+    Cancelled { during: String },
+}
+
+impl PowerCliError {
+    /// [`PowerCliError::Timeout`] after `timeout`, with the bytes
+    /// `received` before it
+    pub fn timeout(timeout: Duration, received: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(received);
+        PowerCliError::Timeout {
+            timeout: timeout.as_secs_f64().ceil() as u64,
+            bytes_received: received.len(),
+            partial: (!received.is_empty())
+                .then(|| raw::truncate(&text, PARTIAL_REPLY_LIMIT).to_string()),
+        }
+    }
+
+    /// Process exit code for this error
+    ///
+    /// Battery health verdicts map to their own codes (see
+    /// [`BatteryVerdict::exit_code`]), an unexpected firmware version exits
+    /// with 4, an available update found by `self-update --check-only` with
+    /// 5 and a cancellation with 130, as after SIGINT; everything else exits
+    /// with 1.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            PowerCliError::BatteryUnhealthy { verdict } => verdict.exit_code(),
+            PowerCliError::VersionMismatch { .. } => VERSION_MISMATCH_EXIT_CODE,
+            PowerCliError::UpdateAvailable { .. } => UPDATE_AVAILABLE_EXIT_CODE,
+            PowerCliError::Cancelled { .. } => crate::util::INTERRUPTED_EXIT_CODE,
+            _ => 1,
+        }
+    }
+}
+
+/// Second part of the [`PowerCliError::Timeout`] message: what arrived and
+/// which setting to look at
+fn timeout_hint(bytes_received: &usize) -> String {
+    match bytes_received {
+        0 => "no data received.\n\
+              Check the wiring, --device and --baud; the PMU may be asleep"
+            .to_string(),
+        n => format!(
+            "partial response of {} bytes.\n\
+             The command may be slow; consider increasing --timeout",
+            n
+        ),
+    }
+}
+
+/// Result type alias for convenience
+pub type Result<T> = std::result::Result<T, PowerCliError>;
@@ -0,0 +1,191 @@
+/*
+ * E-ink Power CLI - Error Reports
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Session context attached to a failed command
+//!
+//! With `--bug-report` (or `--verbose`) an error is followed by the
+//! settings the connection ran with, where they came from, the last command
+//! written to the port and the last [`RECEIVED_TAIL_LEN`] bytes read back,
+//! so a pasted failure can be reproduced.
+//!
+//! Identity blocks of the NFC EEPROM are never included: the data of an
+//! identity write is replaced by [`REDACTED`], and the received bytes are
+//! left out once any command touched the identity.
+
+use crate::json::ResponseParser;
+use crate::power::identity::touches_identity;
+use crate::serial::connection::{EchoCheck, ResyncMode, RECEIVED_TAIL_LEN};
+use crate::serial::console::{self, ConsoleQuirk};
+use crate::serial::Connection;
+use crate::snapshot::ConnectionSettings;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Replacement for identity data
+pub const REDACTED: &str = "[redacted]";
+
+/// What the CLI was running with when a command failed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionContext {
+    /// Version of this CLI
+    pub cli_version: String,
+    pub connection: ConnectionSettings,
+    pub resync: ResyncMode,
+    pub verify_echo: EchoCheck,
+    /// Console quirks the connect handshake found
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub console_quirks: Vec<ConsoleQuirk>,
+    /// Configuration file read; `None` when only defaults applied
+    pub config_file: Option<PathBuf>,
+    pub profile: Option<String>,
+    /// Firmware version, if a reply to `version` or `system info` was cached
+    /// or the boot banner showed it
+    pub firmware_version: Option<String>,
+    /// Command that failed, or else the last one written to the port
+    pub last_command: Option<String>,
+    pub elapsed_ms: u64,
+    pub timeouts: u64,
+    /// Commands sent again after the shell dropped them
+    pub retries: u64,
+    /// Last raw bytes received, lossily decoded
+    pub received_tail: String,
+    /// Set when identity data was left out
+    pub redacted: bool,
+}
+
+impl SessionContext {
+    /// Capture the state of `connection` after `elapsed`
+    pub fn collect(
+        connection: &Connection,
+        settings: ConnectionSettings,
+        config_file: Option<PathBuf>,
+        profile: Option<String>,
+        elapsed: Duration,
+    ) -> Self {
+        let redacted = connection
+            .commands_sent()
+            .iter()
+            .any(|command| touches_identity(command));
+        let firmware_version = ["version", "system info"]
+            .into_iter()
+            .filter_map(|command| connection.cached_response(command))
+            .find_map(|response| ResponseParser::parse_system_info(response).version)
+            .or_else(|| {
+                connection
+                    .boot_banner()
+                    .and_then(|banner| banner.firmware_version.clone())
+            });
+        let stats = connection.stats();
+
+        Self {
+            cli_version: env!("CARGO_PKG_VERSION").to_string(),
+            connection: settings,
+            resync: connection.resync_mode(),
+            verify_echo: connection.echo_check(),
+            console_quirks: connection.console_quirks().to_vec(),
+            config_file,
+            profile,
+            firmware_version,
+            last_command: connection
+                .failed_command()
+                .or(connection.commands_sent().last().map(String::as_str))
+                .map(redact_command),
+            elapsed_ms: elapsed.as_millis() as u64,
+            timeouts: stats.timeouts,
+            retries: stats.retries,
+            received_tail: match redacted {
+                true => REDACTED.to_string(),
+                false => String::from_utf8_lossy(connection.received_tail()).into_owned(),
+            },
+            redacted,
+        }
+    }
+
+    /// Compact block for stderr
+    pub fn format_human(&self) -> String {
+        let connection = &self.connection;
+        let config = match (&self.config_file, &self.profile) {
+            (Some(file), Some(profile)) => format!("{} (profile {})", file.display(), profile),
+            (Some(file), None) => file.display().to_string(),
+            (None, _) => "defaults".to_string(),
+        };
+        let mut lines = vec![
+            "Session context:".to_string(),
+            format!("  CLI version:    {}", self.cli_version),
+            format!(
+                "  Device:         {} at {} baud",
+                connection.device, connection.baud
+            ),
+            format!(
+                "  Timing:         timeout {} s, pacing {} ms",
+                connection.timeout_s, connection.pacing_ms
+            ),
+            format!(
+                "  Recovery:       resync {}, echo check {}, shell recovery {}",
+                kebab(&self.resync),
+                kebab(&self.verify_echo),
+                if connection.auto_recover_shell {
+                    "on"
+                } else {
+                    "off"
+                }
+            ),
+        ];
+        if !self.console_quirks.is_empty() {
+            lines.push(format!(
+                "  Console:        {}",
+                console::describe(&self.console_quirks)
+            ));
+        }
+        lines.extend([
+            format!("  Config:         {}", config),
+            format!(
+                "  Firmware:       {}",
+                self.firmware_version.as_deref().unwrap_or("unknown")
+            ),
+            format!(
+                "  Last command:   {}",
+                self.last_command.as_deref().unwrap_or("none")
+            ),
+            format!(
+                "  Elapsed:        {} ms, {} timeouts, {} retries",
+                self.elapsed_ms, self.timeouts, self.retries
+            ),
+        ]);
+        if self.received_tail.is_empty() {
+            lines.push("  Received:       nothing".to_string());
+        } else {
+            lines.push(format!(
+                "  Received (last {} bytes at most):",
+                RECEIVED_TAIL_LEN
+            ));
+            lines.push(format!("    {}", self.received_tail.escape_debug()));
+        }
+        lines.join("\n")
+    }
+}
+
+/// `command` with the data of an identity write replaced
+fn redact_command(command: &str) -> String {
+    if !touches_identity(command) {
+        return command.to_string();
+    }
+    let words: Vec<&str> = command.split_whitespace().collect();
+    match words.iter().position(|&word| word == "write") {
+        Some(at) if words.len() > at + 2 => format!("{} {}", words[..at + 2].join(" "), REDACTED),
+        _ => command.to_string(),
+    }
+}
+
+/// Command-line spelling of a setting
+fn kebab(value: &impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
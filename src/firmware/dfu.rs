@@ -0,0 +1,91 @@
+/*
+ * E-ink Power CLI - DFU Window
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Bootloader DFU window requested with `system dfu-mode`
+//!
+//! The firmware hands over to the bootloader, which waits the given number
+//! of seconds for an mcumgr upload before booting the application again.
+//! A timeout of 0 makes it wait until it is reset, so the board stays in
+//! the bootloader until `system dfu-mode --cancel` or a power cycle.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Window length when none is given
+pub const DEFAULT_DFU_TIMEOUT_S: u8 = 20;
+
+/// Outcome of `system dfu-mode`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DfuModeReport {
+    /// Window length asked for; 0 waits until reset
+    pub timeout_s: u8,
+    /// When the window closes; `None` when the bootloader waits forever
+    pub deadline: Option<DateTime<Utc>>,
+    /// Whole seconds left in the window once the bootloader was checked
+    pub remaining_s: Option<u64>,
+    /// Whether the bootloader answered an mcumgr echo
+    pub enumerated: bool,
+    /// Controller reply to the request
+    pub response: String,
+}
+
+impl DfuModeReport {
+    /// Report for a window of `timeout_s` requested at `requested_at`, with
+    /// the bootloader checked at `checked_at`
+    pub fn new(
+        timeout_s: u8,
+        requested_at: DateTime<Utc>,
+        checked_at: DateTime<Utc>,
+        enumerated: bool,
+        response: String,
+    ) -> Self {
+        let deadline =
+            (timeout_s > 0).then(|| requested_at + TimeDelta::seconds(i64::from(timeout_s)));
+        let remaining_s = deadline.map(|deadline| {
+            (deadline - checked_at)
+                .num_seconds()
+                .max(0)
+                .try_into()
+                .unwrap_or(0)
+        });
+        Self {
+            timeout_s,
+            deadline,
+            remaining_s,
+            enumerated,
+            response,
+        }
+    }
+}
+
+/// How `system dfu-mode --cancel` got the board out of the bootloader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DfuExit {
+    /// The bootloader accepted an SMP reset
+    McumgrReset,
+    /// The application was still on the console and took `system reset`
+    ConsoleReset,
+}
+
+impl DfuExit {
+    /// Short description for human-readable output
+    pub fn description(self) -> &'static str {
+        match self {
+            DfuExit::McumgrReset => "mcumgr reset",
+            DfuExit::ConsoleReset => "console system reset",
+        }
+    }
+}
+
+/// Outcome of `system dfu-mode --cancel`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DfuCancelReport {
+    /// Reset that was accepted
+    pub method: DfuExit,
+    /// Output of the accepted reset
+    pub response: String,
+}
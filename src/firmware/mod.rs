@@ -0,0 +1,937 @@
+/*
+ * Firmware Management Module for E-ink Power CLI
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+pub mod dfu;
+pub mod slots;
+pub mod verify;
+
+#[allow(unused_imports)] // FirmwareImageInfo is used by tests
+pub use verify::{analyze_firmware_image, FirmwareImageInfo};
+
+use dfu::{DfuCancelReport, DfuExit, DfuModeReport};
+use slots::{FirmwareImage, FirmwareInfo};
+
+use crate::error::PowerCliError;
+use crate::json::{write_ndjson, write_record, RecordType, ResponseParser};
+use crate::serial::connection::{BaudStage, BaudTransition, BootloaderProbe};
+use crate::serial::protocol::baud_command;
+use crate::serial::{CommandMap, Connection};
+use crate::util::{self, CancellationToken, Outcome, Spinner};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Default time allowed for new firmware to boot after the final reset
+const DEFAULT_BOOT_WAIT: Duration = Duration::from_secs(15);
+
+/// Time the PMU takes to hand over to the bootloader after a reset or
+/// DFU request
+const BOOTLOADER_SETTLE: Duration = Duration::from_millis(2000);
+
+/// Interval between upload progress events
+const PROGRESS_TICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Step of the `firmware upload` flow
+///
+/// The serialized names (`reset`, `upload`, `final_reset`, `verify`) are a
+/// stable interface for update orchestration; new steps may be added but
+/// existing names will not change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirmwareStep {
+    /// Reset the PMU into its bootloader
+    Reset,
+    /// Transfer the image with mcumgr
+    Upload,
+    /// Reset the PMU to boot the new image
+    FinalReset,
+    /// Wait for boot and read back the version
+    Verify,
+}
+
+/// State of a [`FirmwareStep`] reported in a [`FirmwareEvent`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepStatus {
+    Started,
+    /// Periodic tick while a long step is running
+    Progress,
+    Completed,
+    Skipped,
+    Failed,
+}
+
+/// One progress event, emitted as an NDJSON line in JSON mode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareEvent {
+    pub step: FirmwareStep,
+    pub status: StepStatus,
+    /// Step duration, on `completed` and `failed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    /// Time since the step started, on `progress`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl FirmwareEvent {
+    fn new(step: FirmwareStep, status: StepStatus) -> Self {
+        Self {
+            step,
+            status,
+            duration_ms: None,
+            elapsed_ms: None,
+            error: None,
+        }
+    }
+}
+
+/// Final document emitted after the last [`FirmwareEvent`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirmwareSummary {
+    pub success: bool,
+    /// Whether the new firmware answered after booting
+    pub verified: bool,
+    pub firmware: String,
+    pub duration_ms: u64,
+    /// Final state of every step, in order
+    pub steps: Vec<FirmwareEvent>,
+    /// Console rate changes made for `--fast`, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub baud_transitions: Vec<BaudTransition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Transport mcumgr uses to reach the bootloader
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum McumgrTransport {
+    /// UART, e.g. `/dev/ttyLP2` at 115200 baud
+    Serial { port: String, baud: u32 },
+    /// Bluetooth LE peer address
+    Ble { address: String },
+    /// SMP over UDP
+    Udp { host: String, port: u16 },
+}
+
+impl McumgrTransport {
+    /// Parse a `host:port` UDP endpoint (IPv6 hosts may be bracketed)
+    pub fn udp_from_str(endpoint: &str) -> Result<Self, PowerCliError> {
+        let invalid = || PowerCliError::InvalidCommand {
+            command: format!("invalid UDP endpoint '{}', expected host:port", endpoint),
+        };
+
+        let (host, port) = endpoint.rsplit_once(':').ok_or_else(invalid)?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        let port = port.parse().map_err(|_| invalid())?;
+
+        Ok(McumgrTransport::Udp {
+            host: host.to_string(),
+            port,
+        })
+    }
+
+    /// Value for mcumgr's `--conntype`
+    pub fn conntype(&self) -> &'static str {
+        match self {
+            McumgrTransport::Serial { .. } => "serial",
+            McumgrTransport::Ble { .. } => "ble",
+            McumgrTransport::Udp { .. } => "udp",
+        }
+    }
+
+    /// Value for mcumgr's `--connstring`
+    pub fn connstring(&self) -> String {
+        match self {
+            McumgrTransport::Serial { port, baud } => format!("{},baud={}", port, baud),
+            McumgrTransport::Ble { address } => format!("peer_id={}", address),
+            McumgrTransport::Udp { host, port } if host.contains(':') => {
+                format!("[{}]:{}", host, port)
+            }
+            McumgrTransport::Udp { host, port } => format!("{}:{}", host, port),
+        }
+    }
+}
+
+impl std::fmt::Display for McumgrTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            McumgrTransport::Serial { port, baud } => write!(f, "{} at {} baud", port, baud),
+            McumgrTransport::Ble { .. } => write!(f, "BLE {}", self.connstring()),
+            McumgrTransport::Udp { .. } => write!(f, "UDP {}", self.connstring()),
+        }
+    }
+}
+
+/// Where a firmware command reaches the controller
+///
+/// The shell console is always the global `--device`/`--baud`. mcumgr uses
+/// the same port and rate unless `firmware upload --port`/`--baud` (or a
+/// BLE/UDP transport) override it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareTransports {
+    /// Serial port of the controller's shell
+    pub console_device: String,
+    pub console_baud: u32,
+    pub mcumgr: McumgrTransport,
+}
+
+impl FirmwareTransports {
+    /// Console on `device` at `baud`, mcumgr there too unless overridden
+    pub fn resolve(
+        device: &str,
+        baud: u32,
+        mcumgr_port: Option<&str>,
+        mcumgr_baud: Option<u32>,
+    ) -> Self {
+        Self {
+            console_device: device.to_string(),
+            console_baud: baud,
+            mcumgr: McumgrTransport::Serial {
+                port: mcumgr_port.unwrap_or(device).to_string(),
+                baud: mcumgr_baud.unwrap_or(baud),
+            },
+        }
+    }
+
+    /// Use `transport` for mcumgr instead
+    pub fn with_mcumgr(mut self, transport: McumgrTransport) -> Self {
+        self.mcumgr = transport;
+        self
+    }
+
+    /// Warning when mcumgr is on a different serial port or rate than the
+    /// console; BLE and UDP are separate links by design
+    pub fn mismatch_warning(&self) -> Option<String> {
+        match &self.mcumgr {
+            McumgrTransport::Serial { port, baud }
+                if *port != self.console_device || *baud != self.console_baud =>
+            {
+                Some(format!(
+                    "mcumgr uses {} but the console is {} at {} baud",
+                    self.mcumgr, self.console_device, self.console_baud
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Send an SMP echo with mcumgr; true if the bootloader answered
+///
+/// `connection_args` are the `--conntype`/`--connstring` arguments.
+fn smp_echo(program: &str, connection_args: &[String]) -> Result<bool, PowerCliError> {
+    let output = Command::new(program)
+        .args(connection_args)
+        .args(["echo", "bootloader_test"])
+        .output()
+        .map_err(PowerCliError::Io)?;
+    Ok(output.status.success())
+}
+
+/// Bootloader probe for [`Connection::set_bootloader_probe`]
+///
+/// Runs the same SMP echo as the firmware upload's bootloader check over
+/// `transport`. A missing mcumgr counts as "no bootloader".
+pub fn bootloader_probe(program: &str, transport: McumgrTransport) -> BootloaderProbe {
+    let program = program.to_string();
+    let args = vec![
+        "--conntype".to_string(),
+        transport.conntype().to_string(),
+        "--connstring".to_string(),
+        transport.connstring(),
+    ];
+    Box::new(move || match smp_echo(&program, &args) {
+        Ok(answered) => answered,
+        Err(e) => {
+            debug!("Bootloader probe could not run {}: {}", program, e);
+            false
+        }
+    })
+}
+
+/// Firmware management interface
+pub struct FirmwareManager {
+    connection: Connection,
+    transports: FirmwareTransports,
+    commands: CommandMap,
+    mcumgr_program: String,
+    boot_wait: Duration,
+    /// NDJSON progress sink; `None` narrates for humans on stderr
+    events: Option<Box<dyn Write + Send>>,
+    /// Mark events with their JSON Lines `type`
+    record_types: bool,
+    /// Final event of each step of the current upload
+    step_log: Vec<FirmwareEvent>,
+    /// Console rate to switch to for the upload
+    fast_baud: Option<u32>,
+    /// Rate to return to once a fast upload is done
+    restore_baud: Option<u32>,
+    /// Console rate changes made during the current upload
+    baud_log: Vec<BaudTransition>,
+    /// Stops waits and the upload early, e.g. on Ctrl-C
+    cancel: CancellationToken,
+}
+
+impl FirmwareManager {
+    /// Create a new firmware manager over resolved console and mcumgr transports
+    pub fn new(connection: Connection, transports: FirmwareTransports) -> Self {
+        Self {
+            connection,
+            transports,
+            commands: CommandMap::default(),
+            mcumgr_program: "mcumgr".to_string(),
+            boot_wait: DEFAULT_BOOT_WAIT,
+            events: None,
+            record_types: false,
+            step_log: Vec::new(),
+            fast_baud: None,
+            restore_baud: None,
+            baud_log: Vec::new(),
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Emit machine-readable upload progress to `writer` instead of narrating
+    pub fn set_json_events(&mut self, writer: Box<dyn Write + Send>) {
+        self.events = Some(writer);
+    }
+
+    /// Write the events as `--format json-lines` records: `progress` for
+    /// each step, `summary` for the upload
+    pub fn set_record_types(&mut self, record_types: bool) {
+        self.record_types = record_types;
+    }
+
+    /// Stop waiting and uploading once `cancel` is cancelled
+    pub fn set_cancel(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
+
+    /// Run a different mcumgr executable
+    pub fn set_mcumgr_program(&mut self, program: &str) {
+        self.mcumgr_program = program.to_string();
+    }
+
+    /// Time to wait for the new firmware to boot before verifying it
+    #[allow(dead_code)] // Used by tests
+    pub fn set_boot_wait(&mut self, boot_wait: Duration) {
+        self.boot_wait = boot_wait;
+    }
+
+    /// Use remapped shell root commands (for forked firmware)
+    pub fn set_command_map(&mut self, commands: CommandMap) {
+        self.commands = commands;
+    }
+
+    /// Flush and close the console connection
+    pub async fn close(&mut self) {
+        self.connection.close().await;
+    }
+
+    /// Switch the console to `rate` for uploads and restore it afterwards
+    ///
+    /// Only applies to the serial transport, and needs the application shell
+    /// to negotiate, so it is ignored with `skip_reset`.
+    pub fn set_fast_baud(&mut self, rate: u32) {
+        self.fast_baud = Some(rate);
+    }
+
+    /// Use a different mcumgr transport (e.g. BLE or UDP)
+    #[allow(dead_code)] // Used by tests
+    pub fn set_transport(&mut self, transport: McumgrTransport) {
+        self.transports.mcumgr = transport;
+    }
+
+    /// Console and mcumgr transports in use
+    #[allow(dead_code)] // Used by tests
+    pub fn transports(&self) -> &FirmwareTransports {
+        &self.transports
+    }
+
+    /// Full mcumgr argument list for `subcommand` over the configured transport
+    pub fn build_mcumgr_args(&self, subcommand: &[&str]) -> Vec<String> {
+        let mut args = vec![
+            "--conntype".to_string(),
+            self.transports.mcumgr.conntype().to_string(),
+            "--connstring".to_string(),
+            self.transports.mcumgr.connstring(),
+        ];
+        args.extend(subcommand.iter().map(|arg| arg.to_string()));
+        args
+    }
+
+    /// List installed firmware images using mcumgr
+    pub async fn list_images(&mut self) -> Result<String, PowerCliError> {
+        info!("Listing firmware images using mcumgr");
+
+        let output = Command::new(&self.mcumgr_program)
+            .args(self.build_mcumgr_args(&["image", "list"]))
+            .output()
+            .map_err(PowerCliError::Io)?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(PowerCliError::FirmwareError {
+                message: format!("mcumgr image list failed: {}", stderr),
+            });
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Ok(stdout.to_string())
+    }
+
+    /// Installed image slots, parsed from `mcumgr image list`
+    pub async fn image_slots(&mut self) -> Result<Vec<FirmwareImage>, PowerCliError> {
+        let output = self.list_images().await?;
+        Ok(slots::parse_image_list(&output))
+    }
+
+    /// Version reported by the running application's `version` command
+    ///
+    /// `Ok(None)` if the console answered without a version line.
+    pub async fn console_version(&mut self) -> Result<Option<String>, PowerCliError> {
+        self.connection.connect().await?;
+        let response = self.connection.send_command("version").await?;
+        Ok(ResponseParser::parse_system_info(&response).version)
+    }
+
+    /// Running version and slot state
+    ///
+    /// Either the console or mcumgr may be unreachable (the PMU sits in the
+    /// bootloader, or another process holds the port); that side is
+    /// reported as null. Fails only if neither answers.
+    pub async fn get_info(&mut self) -> Result<FirmwareInfo, PowerCliError> {
+        info!("Getting firmware slot information");
+
+        let running_version = match self.console_version().await {
+            Ok(version) => Some(version),
+            Err(e) => {
+                warn!("Console not reachable for version: {}", e);
+                None
+            }
+        };
+        let images = match self.image_slots().await {
+            Ok(images) => Some(images),
+            Err(e) if running_version.is_some() => {
+                warn!("Bootloader not reachable for slot list: {}", e);
+                None
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(FirmwareInfo::new(running_version, images))
+    }
+
+    /// Reset PMU into bootloader mode
+    pub async fn reset_to_bootloader(&mut self) -> Result<String, PowerCliError> {
+        info!("Resetting PMU to bootloader mode");
+
+        // Try to send system reset command to PMU
+        // This may fail if PMU is already in bootloader mode, which is fine
+        match self.send_system_reset().await {
+            Ok(response) => {
+                info!("System reset command sent successfully");
+                debug!("Reset response: {}", response);
+            }
+            Err(e) => {
+                warn!(
+                    "System reset command failed (PMU may already be in bootloader mode): {}",
+                    e
+                );
+            }
+        }
+
+        if self.bootloader_enumerated().await {
+            info!("PMU is now in bootloader mode");
+            Ok("PMU successfully reset to bootloader mode".to_string())
+        } else {
+            // Don't fail - the bootloader might be there but not responding to our test
+            Ok("Reset command sent, PMU should be in bootloader mode".to_string())
+        }
+    }
+
+    /// Ask the firmware for a DFU window of `timeout_s` seconds (0 waits
+    /// until reset) and check that the bootloader enumerated
+    pub async fn enter_dfu_mode(&mut self, timeout_s: u8) -> Result<DfuModeReport, PowerCliError> {
+        info!("Requesting DFU mode for {} s", timeout_s);
+
+        self.connection.connect().await?;
+        let command = self
+            .commands
+            .apply(&format!("system dfu-mode {}", timeout_s));
+        let requested_at = chrono::Utc::now();
+        let response = self.connection.send_command(&command).await?;
+        let enumerated = self.bootloader_enumerated().await;
+
+        Ok(DfuModeReport::new(
+            timeout_s,
+            requested_at,
+            chrono::Utc::now(),
+            enumerated,
+            response,
+        ))
+    }
+
+    /// Get the PMU out of the bootloader
+    ///
+    /// An SMP reset is tried first. If the bootloader does not take it, the
+    /// application may still be running (the request never reached the
+    /// bootloader), so `system reset` is sent on the console.
+    pub async fn exit_bootloader(&mut self) -> Result<DfuCancelReport, PowerCliError> {
+        info!("Leaving bootloader mode");
+
+        let output = Command::new(&self.mcumgr_program)
+            .args(self.build_mcumgr_args(&["reset"]))
+            .output()
+            .map_err(PowerCliError::Io)?;
+        if output.status.success() {
+            return Ok(DfuCancelReport {
+                method: DfuExit::McumgrReset,
+                response: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            });
+        }
+        warn!(
+            "mcumgr reset failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+
+        match self.send_system_reset().await {
+            Ok(response) => Ok(DfuCancelReport {
+                method: DfuExit::ConsoleReset,
+                response,
+            }),
+            Err(e) => Err(PowerCliError::FirmwareError {
+                message: format!(
+                    "neither the bootloader nor the console took a reset ({}); power cycle the board",
+                    e
+                ),
+            }),
+        }
+    }
+
+    /// Wait for the PMU to hand over to the bootloader and check that it
+    /// answers, as after `firmware reset` and `system dfu-mode`
+    async fn bootloader_enumerated(&mut self) -> bool {
+        if util::wait(BOOTLOADER_SETTLE, &self.cancel).await == Outcome::Cancelled {
+            return false;
+        }
+        match self.verify_bootloader_mode().await {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("Could not verify bootloader mode: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Upload firmware image
+    ///
+    /// Runs reset → upload → final reset → verify. In JSON mode every step
+    /// transition and upload tick is written as an NDJSON [`FirmwareEvent`],
+    /// followed by a [`FirmwareSummary`]; otherwise progress is narrated on
+    /// stderr.
+    pub async fn upload_firmware(
+        &mut self,
+        firmware_path: &Path,
+        skip_reset: bool,
+    ) -> Result<String, PowerCliError> {
+        let started = Instant::now();
+        self.step_log.clear();
+        self.baud_log.clear();
+
+        self.narrate("🚀 Starting firmware upload process...");
+        self.narrate(&format!("📁 Firmware file: {}", firmware_path.display()));
+        self.narrate(&format!(
+            "🔌 Console: {} at {} baud",
+            self.transports.console_device, self.transports.console_baud
+        ));
+        self.narrate(&format!("🔌 mcumgr:  {}", self.transports.mcumgr));
+        if let Some(warning) = self.transports.mismatch_warning() {
+            warn!("{}", warning);
+            self.narrate(&format!("⚠️  {}", warning));
+        }
+
+        let result = self.run_upload_steps(firmware_path, skip_reset).await;
+        self.restore_console_rate();
+
+        let verified = self
+            .step_log
+            .iter()
+            .any(|e| e.step == FirmwareStep::Verify && e.status == StepStatus::Completed);
+        let summary = FirmwareSummary {
+            success: result.is_ok(),
+            verified,
+            firmware: firmware_path.display().to_string(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            steps: self.step_log.clone(),
+            baud_transitions: self.baud_log.clone(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        self.write_event(RecordType::Summary, &summary)?;
+
+        result
+    }
+
+    /// The four upload steps; returns the human-readable result lines
+    async fn run_upload_steps(
+        &mut self,
+        firmware_path: &Path,
+        skip_reset: bool,
+    ) -> Result<String, PowerCliError> {
+        // Check if firmware file exists
+        if !firmware_path.exists() {
+            return Err(PowerCliError::FirmwareError {
+                message: format!("Firmware file not found: {}", firmware_path.display()),
+            });
+        }
+
+        let mut results = Vec::new();
+
+        // The rate can only be negotiated with the application shell
+        if let (Some(rate), false) = (self.fast_baud, skip_reset) {
+            self.narrate(&format!("\n⚡ Switching console to {} baud...", rate));
+            self.enter_fast_console(rate).await?;
+            results.push(format!("✅ Baud: {} baud for upload", rate));
+        }
+
+        // Step 1: Reset to bootloader mode (unless skipped)
+        if !skip_reset {
+            self.narrate("\n🔄 Step 1/4: Resetting PMU to bootloader mode...");
+            let step = self.step_started(FirmwareStep::Reset)?;
+            let reset_result = self.reset_to_bootloader().await;
+            let reset_result = self.step_finished(FirmwareStep::Reset, step, reset_result)?;
+            results.push(format!("✅ Reset: {}", reset_result));
+            self.narrate(&format!("   {}", reset_result));
+        } else {
+            self.narrate("\n⏭️  Step 1/4: Skipping reset (assuming bootloader mode)");
+            self.emit(FirmwareEvent::new(FirmwareStep::Reset, StepStatus::Skipped))?;
+            results.push("⏭️  Reset: Skipped (assuming bootloader mode)".to_string());
+        }
+
+        // Step 2: Upload firmware using mcumgr
+        self.narrate("\n📤 Step 2/4: Uploading firmware...");
+        let step = self.step_started(FirmwareStep::Upload)?;
+        let upload_result = self.mcumgr_upload(firmware_path, step).await;
+        let upload_result = self.step_finished(FirmwareStep::Upload, step, upload_result)?;
+        results.push(format!("✅ Upload: {}", upload_result));
+        self.narrate(&format!("   {}", upload_result));
+
+        // Step 3: Reset PMU to run new firmware
+        self.narrate("\n🔄 Step 3/4: Resetting PMU to run new firmware...");
+        let step = self.step_started(FirmwareStep::FinalReset)?;
+        let final_reset_result = self.mcumgr_reset().await;
+        let final_reset_result =
+            self.step_finished(FirmwareStep::FinalReset, step, final_reset_result)?;
+        results.push(format!("✅ Final Reset: {}", final_reset_result));
+        self.narrate(&format!("   {}", final_reset_result));
+
+        // The new firmware boots at its default rate
+        self.restore_console_rate();
+
+        // Step 4: Wait for firmware to boot with progress indication
+        self.narrate(&format!(
+            "\n⏳ Step 4/4: Waiting for firmware to boot ({} seconds)...",
+            self.boot_wait.as_secs()
+        ));
+        let step = self.step_started(FirmwareStep::Verify)?;
+        if let Err(e) = self.wait_for_boot().await {
+            return self.step_finished(FirmwareStep::Verify, step, Err(e));
+        }
+
+        self.narrate("🔍 Verifying new firmware...");
+        match self.verify_new_firmware().await {
+            Ok(version_info) => {
+                self.step_finished(FirmwareStep::Verify, step, Ok(()))?;
+                results.push(format!("✅ Verification: {}", version_info));
+                self.narrate(&format!("   ✅ {}", version_info));
+            }
+            Err(e) => {
+                // The image is already installed, so this does not fail the upload
+                warn!("Could not verify new firmware: {}", e);
+                let _ = self.step_finished::<()>(FirmwareStep::Verify, step, Err(e));
+                results.push(
+                    "⚠️  Verification: Could not verify new firmware (may still be booting)"
+                        .to_string(),
+                );
+                self.narrate("   ⚠️  Could not verify new firmware (may still be booting)");
+            }
+        }
+
+        self.narrate("\n🎉 Firmware update process completed!");
+        Ok(results.join("\n"))
+    }
+
+    /// Negotiate `rate` with the shell and point mcumgr at it
+    async fn enter_fast_console(&mut self, rate: u32) -> Result<(), PowerCliError> {
+        let command = self.commands.apply(&baud_command(rate, false));
+        let change = self.connection.change_baud_rate(&command, rate).await;
+        self.baud_log.extend(change.transitions.iter().cloned());
+        if let Some(message) = change.failure_message() {
+            return Err(PowerCliError::BaudChange { message });
+        }
+
+        self.restore_baud = Some(change.original_rate);
+        if let McumgrTransport::Serial { baud, .. } = &mut self.transports.mcumgr {
+            *baud = rate;
+        }
+        self.transports.console_baud = rate;
+        Ok(())
+    }
+
+    /// Return the local port and mcumgr to the rate used before a fast upload
+    ///
+    /// The controller drops back to its default rate when it resets, so only
+    /// the host side needs to follow.
+    fn restore_console_rate(&mut self) {
+        let Some(original) = self.restore_baud.take() else {
+            return;
+        };
+        let fast = self.connection.baud_rate();
+        if let McumgrTransport::Serial { baud, .. } = &mut self.transports.mcumgr {
+            *baud = original;
+        }
+        self.transports.console_baud = original;
+        // Reopened at the restored rate when next used
+        self.connection.set_baud_rate(original);
+        info!("Baud Restored: {} -> {} ok", fast, original);
+        self.baud_log.push(BaudTransition {
+            stage: BaudStage::Restored,
+            from: fast,
+            to: original,
+            ok: true,
+            error: None,
+        });
+    }
+
+    /// Sleep for the boot wait, showing a countdown in human mode
+    async fn wait_for_boot(&mut self) -> Result<(), PowerCliError> {
+        let outcome = if self.events.is_some() {
+            util::wait(self.boot_wait, &self.cancel).await
+        } else {
+            util::countdown(self.boot_wait, "Waiting for boot", &self.cancel).await
+        };
+        outcome.or_cancelled("waiting for the new firmware to boot")
+    }
+
+    /// Print a human progress line on stderr (silent in JSON mode)
+    fn narrate(&self, message: &str) {
+        if self.events.is_none() {
+            eprintln!("{}", message);
+        }
+    }
+
+    /// Write a progress event in JSON mode and remember the last state per step
+    fn emit(&mut self, event: FirmwareEvent) -> Result<(), PowerCliError> {
+        if event.status != StepStatus::Progress {
+            self.step_log.retain(|e| e.step != event.step);
+            self.step_log.push(event.clone());
+        }
+        self.write_event(RecordType::Progress, &event)
+    }
+
+    /// Write one line to the JSON progress sink, if there is one
+    fn write_event<T: Serialize>(
+        &mut self,
+        record_type: RecordType,
+        event: &T,
+    ) -> Result<(), PowerCliError> {
+        if let Some(writer) = self.events.as_mut() {
+            match self.record_types {
+                true => write_record(writer, record_type, event)?,
+                false => write_ndjson(writer, event)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Report a step as started and return its start time
+    fn step_started(&mut self, step: FirmwareStep) -> Result<Instant, PowerCliError> {
+        self.emit(FirmwareEvent::new(step, StepStatus::Started))?;
+        Ok(Instant::now())
+    }
+
+    /// Report a step's outcome and pass its result through
+    fn step_finished<T>(
+        &mut self,
+        step: FirmwareStep,
+        started: Instant,
+        result: Result<T, PowerCliError>,
+    ) -> Result<T, PowerCliError> {
+        let mut event = match &result {
+            Ok(_) => FirmwareEvent::new(step, StepStatus::Completed),
+            Err(e) => FirmwareEvent {
+                error: Some(e.to_string()),
+                ..FirmwareEvent::new(step, StepStatus::Failed)
+            },
+        };
+        event.duration_ms = Some(started.elapsed().as_millis() as u64);
+        self.emit(event)?;
+        result
+    }
+
+    /// Send system reset command to PMU
+    async fn send_system_reset(&mut self) -> Result<String, PowerCliError> {
+        debug!("Sending system reset command to PMU");
+
+        // Connect to PMU and send reset command
+        self.connection.connect().await?;
+        let command = self.commands.apply("system reset");
+        let response = self.connection.send_command(&command).await?;
+
+        Ok(response)
+    }
+
+    /// Verify PMU is in bootloader mode
+    async fn verify_bootloader_mode(&mut self) -> Result<String, PowerCliError> {
+        debug!("Verifying bootloader mode with mcumgr");
+
+        if smp_echo(&self.mcumgr_program, &self.build_mcumgr_args(&[]))? {
+            Ok("Bootloader responding".to_string())
+        } else {
+            Err(PowerCliError::FirmwareError {
+                message: "Bootloader not responding".to_string(),
+            })
+        }
+    }
+
+    /// Upload firmware using mcumgr
+    async fn mcumgr_upload(
+        &mut self,
+        firmware_path: &Path,
+        started: Instant,
+    ) -> Result<String, PowerCliError> {
+        info!("Uploading firmware: {}", firmware_path.display());
+
+        // Get file size for progress indication
+        let file_size = std::fs::metadata(firmware_path)
+            .map_err(PowerCliError::Io)?
+            .len();
+
+        self.narrate(&format!(
+            "📦 Starting upload of {} ({} bytes)...",
+            firmware_path.file_name().unwrap().to_string_lossy(),
+            file_size
+        ));
+
+        let mut child = Command::new(&self.mcumgr_program)
+            .args(self.build_mcumgr_args(&["image", "upload", firmware_path.to_str().unwrap()]))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(PowerCliError::Io)?;
+
+        // Show progress while the upload is running
+        let mut last_tick = Instant::now();
+        let mut spinner = self
+            .events
+            .is_none()
+            .then(|| Spinner::new("Uploading firmware... Please wait"));
+
+        loop {
+            if self.cancel.is_cancelled() {
+                let _ = child.kill();
+                let _ = child.wait();
+                if let Some(spinner) = spinner.take() {
+                    spinner.finish("⚠️", "Upload cancelled");
+                }
+                return Err(PowerCliError::Cancelled {
+                    during: "uploading firmware".to_string(),
+                });
+            }
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    // Process finished
+                    if let Some(spinner) = spinner.take() {
+                        spinner.finish("✅", "Upload completed!");
+                    }
+
+                    let output = child.wait_with_output().map_err(PowerCliError::Io)?;
+
+                    let stdout = String::from_utf8_lossy(&output.stdout);
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+
+                    if !status.success() {
+                        return Err(PowerCliError::FirmwareError {
+                            message: format!("mcumgr upload failed: {}\n{}", stderr, stdout),
+                        });
+                    }
+
+                    return Ok(format!(
+                        "Firmware uploaded successfully: {}",
+                        firmware_path.file_name().unwrap().to_string_lossy()
+                    ));
+                }
+                Ok(None) => {
+                    // Process still running, show progress
+                    if let Some(spinner) = spinner.as_mut() {
+                        spinner.tick();
+                    } else if last_tick.elapsed() >= PROGRESS_TICK_INTERVAL {
+                        last_tick = Instant::now();
+                        self.emit(FirmwareEvent {
+                            elapsed_ms: Some(started.elapsed().as_millis() as u64),
+                            ..FirmwareEvent::new(FirmwareStep::Upload, StepStatus::Progress)
+                        })?;
+                    }
+
+                    // Wait a bit before checking again
+                    util::wait(Duration::from_millis(100), &self.cancel).await;
+                }
+                Err(e) => {
+                    return Err(PowerCliError::Io(e));
+                }
+            }
+        }
+    }
+
+    /// Reset PMU using mcumgr
+    async fn mcumgr_reset(&mut self) -> Result<String, PowerCliError> {
+        info!("Resetting PMU using mcumgr");
+
+        let output = Command::new(&self.mcumgr_program)
+            .args(self.build_mcumgr_args(&["reset"]))
+            .output()
+            .map_err(PowerCliError::Io)?;
+
+        // mcumgr reset may not return success if the device resets immediately
+        // So we don't strictly check the exit code
+        let _stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        if !stderr.is_empty() && !stderr.contains("timeout") {
+            warn!("mcumgr reset stderr: {}", stderr);
+        }
+
+        Ok("PMU reset command sent".to_string())
+    }
+
+    /// Verify new firmware is running
+    async fn verify_new_firmware(&mut self) -> Result<String, PowerCliError> {
+        debug!("Verifying new firmware is running");
+
+        // Give firmware a bit more time to fully initialize
+        util::wait(Duration::from_millis(2000), &self.cancel)
+            .await
+            .or_cancelled("waiting for the new firmware to start")?;
+
+        // Try to connect and get version
+        self.connection.connect().await?;
+        let response = self.connection.send_command("version").await?;
+
+        Ok(format!(
+            "New firmware version: {}",
+            response.lines().next().unwrap_or("Unknown")
+        ))
+    }
+}
@@ -0,0 +1,255 @@
+/*
+ * E-ink Power CLI - Firmware Slots
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Installed image slots and the combined `firmware info` report
+//!
+//! Slot data comes from `mcumgr image list`, which prints one block per
+//! slot:
+//!
+//! ```text
+//! Images:
+//!  image=0 slot=0
+//!     version: 2.2.0.298
+//!     bootable: true
+//!     flags: active confirmed
+//!     hash: 3b4c5d...
+//! Split status: N/A (0)
+//! ```
+//!
+//! A slot without an image is not listed at all.
+
+use crate::json::SectionedJson;
+use serde::{Deserialize, Serialize};
+
+/// Slots of the primary image in an MCUboot swap layout
+pub const PRIMARY_IMAGE_SLOTS: [u32; 2] = [0, 1];
+
+/// One image slot reported by `mcumgr image list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareImage {
+    pub image: u32,
+    pub slot: u32,
+    /// The slot holds no image; every other field is empty
+    pub empty: bool,
+    pub version: Option<String>,
+    pub bootable: Option<bool>,
+    /// The image is running
+    pub active: bool,
+    /// The image is marked good and will keep booting
+    pub confirmed: bool,
+    /// The image boots on the next reset
+    pub pending: bool,
+    /// A pending image that stays without being confirmed (not a test boot)
+    pub permanent: bool,
+    pub hash: Option<String>,
+}
+
+impl FirmwareImage {
+    /// Placeholder for a slot the image list does not mention
+    pub fn empty(image: u32, slot: u32) -> Self {
+        Self {
+            image,
+            slot,
+            empty: true,
+            version: None,
+            bootable: None,
+            active: false,
+            confirmed: false,
+            pending: false,
+            permanent: false,
+            hash: None,
+        }
+    }
+
+    /// Flags as mcumgr prints them, e.g. `active confirmed`
+    pub fn flags(&self) -> String {
+        [
+            (self.active, "active"),
+            (self.confirmed, "confirmed"),
+            (self.pending, "pending"),
+            (self.permanent, "permanent"),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+/// Parse `mcumgr image list` output
+///
+/// Each `image=N slot=M` line heads a section of the output; the other
+/// sections (the `Images:` header, split status) are ignored.
+pub fn parse_image_list(output: &str) -> Vec<FirmwareImage> {
+    SectionedJson::parse(output)
+        .sections
+        .iter()
+        .filter_map(|section| {
+            let (image, slot) = parse_slot_header(section.name.as_deref()?)?;
+            let mut current = FirmwareImage {
+                empty: false,
+                ..FirmwareImage::empty(image, slot)
+            };
+            current.version = section.get("version").map(str::to_string);
+            current.bootable = section.get("bootable").and_then(|v| v.parse().ok());
+            current.hash = section
+                .get("hash")
+                .filter(|hash| !hash.is_empty())
+                .map(str::to_string);
+            for flag in section.get("flags").unwrap_or_default().split_whitespace() {
+                match flag {
+                    "active" => current.active = true,
+                    "confirmed" => current.confirmed = true,
+                    "pending" => current.pending = true,
+                    "permanent" => current.permanent = true,
+                    _ => {}
+                }
+            }
+            Some(current)
+        })
+        .collect()
+}
+
+/// `image=0 slot=1` -> `(0, 1)`
+fn parse_slot_header(line: &str) -> Option<(u32, u32)> {
+    let mut image = None;
+    let mut slot = None;
+    for word in line.split_whitespace() {
+        match word.split_once('=')? {
+            ("image", value) => image = value.parse().ok(),
+            ("slot", value) => slot = value.parse().ok(),
+            _ => return None,
+        }
+    }
+    Some((image?, slot?))
+}
+
+/// Result of `firmware info`
+///
+/// Built from the console `version` reply and the mcumgr slot list; either
+/// source may be unreachable, leaving its fields null.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareInfo {
+    /// The application console answered `version`
+    pub console_reachable: bool,
+    /// Version reported by the running application
+    pub running_version: Option<String>,
+    /// mcumgr answered `image list`
+    pub bootloader_reachable: bool,
+    /// Slot of the running image
+    pub active_slot: Option<u32>,
+    /// Whether the running image is confirmed; an unconfirmed image is on a
+    /// test boot and is reverted on the next reset
+    pub confirmed: Option<bool>,
+    /// Slot that boots on the next reset
+    pub pending_slot: Option<u32>,
+    /// Whether the pending image is a test boot (reverted unless confirmed)
+    pub pending_test: Option<bool>,
+    /// Every slot of the primary image, empty ones included
+    pub slots: Option<Vec<FirmwareImage>>,
+}
+
+impl FirmwareInfo {
+    /// Combine the console version and slot list; `None` for an unreachable source
+    pub fn new(
+        running_version: Option<Option<String>>,
+        images: Option<Vec<FirmwareImage>>,
+    ) -> Self {
+        let slots = images.map(|mut images| {
+            for slot in PRIMARY_IMAGE_SLOTS {
+                if !images.iter().any(|i| i.image == 0 && i.slot == slot) {
+                    images.push(FirmwareImage::empty(0, slot));
+                }
+            }
+            images.sort_by_key(|i| (i.image, i.slot));
+            images
+        });
+        let find = |predicate: fn(&FirmwareImage) -> bool| {
+            slots
+                .as_ref()
+                .and_then(|slots| slots.iter().find(|i| i.image == 0 && predicate(i)))
+        };
+        let active = find(|i| i.active);
+        let pending = find(|i| i.pending);
+
+        Self {
+            console_reachable: running_version.is_some(),
+            running_version: running_version.flatten(),
+            bootloader_reachable: slots.is_some(),
+            active_slot: active.map(|i| i.slot),
+            confirmed: active.map(|i| i.confirmed),
+            pending_slot: pending.map(|i| i.slot),
+            pending_test: pending.map(|i| !i.permanent),
+            slots,
+        }
+    }
+
+    /// Format as a summary followed by a slot table
+    pub fn format_human(&self) -> String {
+        let or_unknown = |value: Option<String>| value.unwrap_or_else(|| "unknown".to_string());
+        let reachable = |ok: bool| if ok { "reachable" } else { "not reachable" };
+
+        let mut lines = vec![
+            format!(
+                "Running version: {} (console {})",
+                or_unknown(self.running_version.clone()),
+                reachable(self.console_reachable)
+            ),
+            format!("Bootloader:      {}", reachable(self.bootloader_reachable)),
+            format!(
+                "Active slot:     {}",
+                or_unknown(self.active_slot.map(|slot| match self.confirmed {
+                    Some(false) => format!("{} (test boot, not confirmed)", slot),
+                    _ => format!("{} (confirmed)", slot),
+                }))
+            ),
+            format!(
+                "Pending slot:    {}",
+                match (self.pending_slot, self.pending_test) {
+                    (Some(slot), Some(true)) => format!("{} (test)", slot),
+                    (Some(slot), _) => format!("{} (permanent)", slot),
+                    (None, _) if self.bootloader_reachable => "none".to_string(),
+                    (None, _) => "unknown".to_string(),
+                }
+            ),
+        ];
+
+        if let Some(slots) = &self.slots {
+            lines.push(String::new());
+            lines.extend(slot_table(slots));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Image/Slot/Version/Flags/Hash table, one line per slot after the header
+pub fn slot_table(images: &[FirmwareImage]) -> Vec<String> {
+    let mut lines = vec![format!(
+        "{:<6} {:<5} {:<16} {:<26} {}",
+        "Image", "Slot", "Version", "Flags", "Hash"
+    )];
+    for image in images {
+        let version = match (&image.version, image.empty) {
+            (_, true) => "(empty)",
+            (Some(version), false) => version.as_str(),
+            (None, false) => "unknown",
+        };
+        lines.push(
+            format!(
+                "{:<6} {:<5} {:<16} {:<26} {}",
+                image.image,
+                image.slot,
+                version,
+                image.flags(),
+                image.hash.as_deref().unwrap_or("")
+            )
+            .trim_end()
+            .to_string(),
+        );
+    }
+    lines
+}
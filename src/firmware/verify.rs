@@ -0,0 +1,178 @@
+/*
+ * E-ink Power CLI - Firmware Image Analysis
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Offline inspection of MCUboot firmware images
+//!
+//! Reads the 32-byte image header and walks the TLV trailer that follows the
+//! image body, so a `.bin` can be checked before any hardware is connected.
+
+use crate::error::PowerCliError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+/// `ih_magic` of an MCUboot image header
+pub const IMAGE_MAGIC: u32 = 0x96f3_b83d;
+
+/// Size of the fixed MCUboot image header
+pub const IMAGE_HEADER_SIZE: usize = 32;
+
+/// Header flag marking an image the bootloader must not boot
+pub const IMAGE_F_NON_BOOTABLE: u32 = 0x0000_0010;
+
+/// Magic of the unprotected TLV area
+const IMAGE_TLV_INFO_MAGIC: u16 = 0x6907;
+
+/// Magic of the protected TLV area
+const IMAGE_TLV_PROT_INFO_MAGIC: u16 = 0x6908;
+
+/// TLV types holding a signature (RSA, ECDSA, Ed25519)
+const SIGNATURE_TLV_TYPES: std::ops::RangeInclusive<u16> = 0x20..=0x25;
+
+/// Image version from the MCUboot header (`major.minor.revision+build`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SemVer {
+    pub major: u8,
+    pub minor: u8,
+    pub revision: u16,
+    pub build_num: u32,
+}
+
+impl fmt::Display for SemVer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{}.{}+{}",
+            self.major, self.minor, self.revision, self.build_num
+        )
+    }
+}
+
+/// Result of [`analyze_firmware_image`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareImageInfo {
+    /// Header starts with the MCUboot magic; other fields are meaningless if not
+    pub magic_valid: bool,
+    pub version: SemVer,
+    pub image_size: u32,
+    pub header_size: u16,
+    pub load_addr: u32,
+    pub flags: u32,
+    /// [`IMAGE_F_NON_BOOTABLE`] is set
+    pub non_bootable: bool,
+    /// The TLV trailer carries a signature entry
+    pub has_signature: bool,
+}
+
+impl FirmwareImageInfo {
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        if !self.magic_valid {
+            return "Not an MCUboot image (bad header magic)".to_string();
+        }
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        format!(
+            "Version: {}\nImage Size: {} bytes\nHeader Size: {} bytes\nLoad Address: 0x{:08x}\nFlags: 0x{:08x}\nBootable: {}\nSigned: {}",
+            self.version,
+            self.image_size,
+            self.header_size,
+            self.load_addr,
+            self.flags,
+            yes_no(!self.non_bootable),
+            yes_no(self.has_signature)
+        )
+    }
+}
+
+/// Read and analyze an MCUboot image file
+pub fn analyze_firmware_image(path: &Path) -> Result<FirmwareImageInfo, PowerCliError> {
+    let data = std::fs::read(path).map_err(|e| PowerCliError::FirmwareError {
+        message: format!("Cannot read firmware image {}: {}", path.display(), e),
+    })?;
+    analyze_firmware_bytes(&data)
+}
+
+/// Analyze an MCUboot image already in memory
+pub fn analyze_firmware_bytes(data: &[u8]) -> Result<FirmwareImageInfo, PowerCliError> {
+    if data.len() < IMAGE_HEADER_SIZE {
+        return Err(PowerCliError::FirmwareError {
+            message: format!(
+                "Firmware image too short: {} bytes, header needs {}",
+                data.len(),
+                IMAGE_HEADER_SIZE
+            ),
+        });
+    }
+
+    let header_size = read_u16(data, 8).unwrap_or(0);
+    let protect_tlv_size = read_u16(data, 10).unwrap_or(0);
+    let image_size = read_u32(data, 12).unwrap_or(0);
+    let flags = read_u32(data, 16).unwrap_or(0);
+    let magic_valid = read_u32(data, 0) == Some(IMAGE_MAGIC);
+
+    let tlv_start = header_size as usize + image_size as usize;
+    let has_signature = magic_valid && has_signature_tlv(data, tlv_start, protect_tlv_size);
+
+    Ok(FirmwareImageInfo {
+        magic_valid,
+        version: SemVer {
+            major: data[20],
+            minor: data[21],
+            revision: read_u16(data, 22).unwrap_or(0),
+            build_num: read_u32(data, 24).unwrap_or(0),
+        },
+        image_size,
+        header_size,
+        load_addr: read_u32(data, 4).unwrap_or(0),
+        flags,
+        non_bootable: flags & IMAGE_F_NON_BOOTABLE != 0,
+        has_signature,
+    })
+}
+
+/// Look for a signature entry in the TLV areas starting at `offset`
+///
+/// The protected area (if `protect_tlv_size` is non-zero) comes first and is
+/// followed by the unprotected area, where MCUboot keeps the signature.
+fn has_signature_tlv(data: &[u8], mut offset: usize, protect_tlv_size: u16) -> bool {
+    if protect_tlv_size > 0 {
+        if read_u16(data, offset) != Some(IMAGE_TLV_PROT_INFO_MAGIC) {
+            return false;
+        }
+        offset += protect_tlv_size as usize;
+    }
+
+    if read_u16(data, offset) != Some(IMAGE_TLV_INFO_MAGIC) {
+        return false;
+    }
+    let Some(total) = read_u16(data, offset + 2) else {
+        return false;
+    };
+    let end = offset + total as usize;
+
+    // Each entry: u16 type, u16 length, value
+    let mut entry = offset + 4;
+    while entry + 4 <= end {
+        let (Some(kind), Some(len)) = (read_u16(data, entry), read_u16(data, entry + 2)) else {
+            return false;
+        };
+        if SIGNATURE_TLV_TYPES.contains(&kind) {
+            return true;
+        }
+        entry += 4 + len as usize;
+    }
+    false
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    let bytes = data.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
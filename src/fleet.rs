@@ -0,0 +1,430 @@
+/*
+ * E-ink Power CLI - Fleet Inventory
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `fleet scan` and `fleet diff`: every PMU attached to the host
+//!
+//! A gateway with several PMUs on USB serial adapters gets one inventory:
+//! for each candidate port the firmware version, identity serial number,
+//! battery voltage and rail states of the unit answering on it, or why
+//! none did. Ports are probed one after another, each through the same
+//! connection as a single command, so a port another process holds is
+//! reported busy rather than taken over.
+//!
+//! Device nodes such as `/dev/ttyUSB0` are numbered in the order adapters
+//! come up and change across reboots. Entries carry the adapter's USB
+//! serial number too, and [`diff`] matches units by it when there is one,
+//! so a unit that moved to another node is reported as moved rather than
+//! as one unit gone and another added.
+
+use crate::error::{PowerCliError, Result};
+use crate::json::{self, ResponseParser, OUTPUT_SCHEMA_VERSION};
+use crate::power::control::PowerController;
+use crate::power::rails::PowerRail;
+use crate::state;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serialport::SerialPortType;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// USB adapter a serial port belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsbAdapter {
+    /// Serial number the adapter reports, stable across reboots
+    pub serial_number: Option<String>,
+    /// `vid:pid` in hex
+    pub id: String,
+    pub product: Option<String>,
+}
+
+/// A port to probe
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CandidatePort {
+    pub port: String,
+    pub usb: Option<UsbAdapter>,
+}
+
+/// Ports to probe: `ports` when given, otherwise the USB serial ports on
+/// the host and `configured`, or with `all` every serial port
+///
+/// Built-in UARTs are left out by default since most have nothing on them
+/// and each would take a full timeout to give up on.
+pub fn candidate_ports(ports: &[String], all: bool, configured: &str) -> Vec<CandidatePort> {
+    let found: Vec<(String, Option<UsbAdapter>)> = serialport::available_ports()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|info| {
+            let usb = match info.port_type {
+                SerialPortType::UsbPort(usb) => Some(UsbAdapter {
+                    serial_number: usb.serial_number.filter(|s| !s.trim().is_empty()),
+                    id: format!("{:04x}:{:04x}", usb.vid, usb.pid),
+                    product: usb.product,
+                }),
+                _ => None,
+            };
+            (info.port_name, usb)
+        })
+        .collect();
+    let usb_of = |port: &str| {
+        let resolved = std::fs::canonicalize(port).ok();
+        found
+            .iter()
+            .find(|(name, _)| name == port || resolved.as_deref() == Some(Path::new(name)))
+            .and_then(|(_, usb)| usb.clone())
+    };
+
+    let mut paths: Vec<String> = match ports.is_empty() {
+        false => ports.to_vec(),
+        true => found
+            .iter()
+            .filter(|(_, usb)| all || usb.is_some())
+            .map(|(name, _)| name.clone())
+            .chain(
+                Path::new(configured)
+                    .exists()
+                    .then(|| configured.to_string()),
+            )
+            .collect(),
+    };
+    if ports.is_empty() {
+        paths.sort();
+    }
+    let mut candidates: Vec<CandidatePort> = Vec::new();
+    for port in paths {
+        if candidates.iter().all(|candidate| candidate.port != port) {
+            candidates.push(CandidatePort {
+                usb: usb_of(&port),
+                port,
+            });
+        }
+    }
+    candidates
+}
+
+/// Outcome of probing one port
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeStatus {
+    /// A PMU answered
+    Ok,
+    /// Another process holds the port open
+    Busy,
+    /// The device node does not exist
+    Missing,
+    /// The port opened but no PMU shell answered on it
+    Unresponsive,
+    /// The port could not be used
+    Failed,
+}
+
+impl ProbeStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ProbeStatus::Ok => "ok",
+            ProbeStatus::Busy => "busy",
+            ProbeStatus::Missing => "missing",
+            ProbeStatus::Unresponsive => "unresponsive",
+            ProbeStatus::Failed => "failed",
+        }
+    }
+
+    /// Status of a port whose first command failed with `error`
+    pub fn of_error(error: &PowerCliError) -> Self {
+        match error {
+            PowerCliError::PortBusy { .. } => ProbeStatus::Busy,
+            PowerCliError::DeviceNotFound { .. } => ProbeStatus::Missing,
+            PowerCliError::Timeout { .. }
+            | PowerCliError::DeadlineExceeded { .. }
+            | PowerCliError::ShellUnavailable { .. }
+            | PowerCliError::InBootloader { .. }
+            | PowerCliError::InvalidResponse { .. } => ProbeStatus::Unresponsive,
+            _ => ProbeStatus::Failed,
+        }
+    }
+}
+
+/// One port of the inventory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FleetEntry {
+    pub port: String,
+    /// Serial number of the USB adapter, the key units are matched by
+    pub usb_serial: Option<String>,
+    /// `vid:pid` of the USB adapter
+    pub usb_id: Option<String>,
+    pub status: ProbeStatus,
+    /// Why no PMU answered
+    pub error: Option<String>,
+    pub firmware_version: Option<String>,
+    /// Serial number from the unit identity
+    pub serial_number: Option<String>,
+    pub battery_mv: Option<u16>,
+    /// Switched rail states
+    #[serde(default)]
+    pub rails: BTreeMap<PowerRail, bool>,
+    /// Reads that failed on a PMU that answered
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+impl FleetEntry {
+    fn new(candidate: &CandidatePort, status: ProbeStatus) -> Self {
+        let usb = candidate.usb.as_ref();
+        Self {
+            port: candidate.port.clone(),
+            usb_serial: usb.and_then(|usb| usb.serial_number.clone()),
+            usb_id: usb.map(|usb| usb.id.clone()),
+            status,
+            error: None,
+            firmware_version: None,
+            serial_number: None,
+            battery_mv: None,
+            rails: BTreeMap::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// What identifies the unit across scans: the USB serial number of its
+    /// adapter, or the port without one
+    pub fn key(&self) -> String {
+        match &self.usb_serial {
+            Some(serial) => format!("usb:{}", serial),
+            None => format!("port:{}", self.port),
+        }
+    }
+
+    /// Rail states as `PMIC on, WiFi off`
+    pub fn rail_summary(&self) -> String {
+        self.rails
+            .iter()
+            .map(|(rail, on)| format!("{} {}", rail.name(), if *on { "on" } else { "off" }))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Probe the PMU on `candidate` through `controller`
+///
+/// The first command tells whether a PMU answers at all; the reads after
+/// it record their failures in `errors` and the rest are still taken.
+pub async fn probe(candidate: &CandidatePort, controller: &mut PowerController) -> FleetEntry {
+    let info = match controller.get_system_info().await {
+        Ok(response) => ResponseParser::parse_system_info(&response),
+        Err(e) => {
+            let mut entry = FleetEntry::new(candidate, ProbeStatus::of_error(&e));
+            entry.error = Some(e.to_string());
+            return entry;
+        }
+    };
+    let mut entry = FleetEntry::new(candidate, ProbeStatus::Ok);
+    entry.firmware_version = info.version;
+
+    match controller.read_identity().await {
+        Ok(identity) => entry.serial_number = identity.map(|identity| identity.serial),
+        Err(e) => entry.errors.push(format!("identity: {}", e)),
+    }
+    match controller.battery_read().await {
+        Ok(response) => {
+            entry.battery_mv = ResponseParser::parse_battery_response(&response).voltage_mv
+        }
+        Err(e) => entry.errors.push(format!("battery: {}", e)),
+    }
+    match controller.rail_states().await {
+        Ok(rails) => entry.rails = rails,
+        Err(e) => entry.errors.push(format!("rails: {}", e)),
+    }
+    entry
+}
+
+/// Result of `fleet scan`, and the file `--save` writes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FleetInventory {
+    /// [`OUTPUT_SCHEMA_VERSION`] of the build that wrote it
+    pub schema_version: u32,
+    pub scanned_at: DateTime<Utc>,
+    pub ports: Vec<FleetEntry>,
+}
+
+impl FleetInventory {
+    pub fn new(ports: Vec<FleetEntry>) -> Self {
+        Self {
+            schema_version: OUTPUT_SCHEMA_VERSION,
+            scanned_at: Utc::now(),
+            ports,
+        }
+    }
+
+    /// Ports a PMU answered on
+    pub fn responsive(&self) -> usize {
+        self.ports
+            .iter()
+            .filter(|entry| entry.status == ProbeStatus::Ok)
+            .count()
+    }
+
+    /// Write the inventory to `path` for a later `fleet diff`
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        state::write_atomic(path, format!("{}\n", text).as_bytes())?;
+        Ok(())
+    }
+
+    /// Read an inventory written by `--save`, or a `fleet scan` envelope
+    /// printed with `--format json`
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        if let Ok(inventory) = serde_json::from_str::<Self>(&text) {
+            return Ok(inventory);
+        }
+        let envelope = json::parse_envelope(&text)?;
+        if envelope.command != "fleet scan" {
+            return Err(PowerCliError::InvalidArguments {
+                message: format!(
+                    "{} is a '{}' document, not a fleet inventory",
+                    path.display(),
+                    envelope.command
+                ),
+            });
+        }
+        Ok(serde_json::from_value(envelope.data)?)
+    }
+}
+
+/// What changed about a unit between two inventories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FleetChangeKind {
+    /// Only in the later inventory
+    Added,
+    /// Only in the earlier inventory
+    Removed,
+    /// Same adapter on another port
+    Moved,
+    /// The probe status changed, e.g. the unit stopped answering
+    Status,
+    Firmware,
+    /// The identity serial number changed: another unit on the same adapter
+    Identity,
+}
+
+impl FleetChangeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FleetChangeKind::Added => "added",
+            FleetChangeKind::Removed => "removed",
+            FleetChangeKind::Moved => "moved",
+            FleetChangeKind::Status => "status",
+            FleetChangeKind::Firmware => "firmware",
+            FleetChangeKind::Identity => "identity",
+        }
+    }
+}
+
+/// One change between two inventories
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FleetChange {
+    /// [`FleetEntry::key`] of the unit
+    pub unit: String,
+    /// Port of the unit in the later inventory, or the earlier one if it
+    /// was removed
+    pub port: String,
+    pub kind: FleetChangeKind,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Result of `fleet diff`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FleetDiff {
+    pub before: DateTime<Utc>,
+    pub after: DateTime<Utc>,
+    /// Units in both inventories
+    pub unchanged: usize,
+    pub changes: Vec<FleetChange>,
+}
+
+/// Changes from `before` to `after`, units matched by [`FleetEntry::key`]
+///
+/// Firmware and identity are only compared for units that answered in
+/// both; a unit that stopped answering is a status change.
+pub fn diff(before: &FleetInventory, after: &FleetInventory) -> FleetDiff {
+    let keyed = |inventory: &FleetInventory| -> BTreeMap<String, FleetEntry> {
+        inventory
+            .ports
+            .iter()
+            .map(|entry| (entry.key(), entry.clone()))
+            .collect()
+    };
+    let earlier = keyed(before);
+    let later = keyed(after);
+
+    let mut changes = Vec::new();
+    let mut unchanged = 0;
+    for (key, entry) in &later {
+        let change = |kind, from: Option<&str>, to: Option<&str>| FleetChange {
+            unit: key.clone(),
+            port: entry.port.clone(),
+            kind,
+            before: from.map(String::from),
+            after: to.map(String::from),
+        };
+        let Some(old) = earlier.get(key) else {
+            changes.push(change(FleetChangeKind::Added, None, Some(&entry.port)));
+            continue;
+        };
+        let count = changes.len();
+        if old.port != entry.port {
+            changes.push(change(
+                FleetChangeKind::Moved,
+                Some(&old.port),
+                Some(&entry.port),
+            ));
+        }
+        if old.status != entry.status {
+            changes.push(change(
+                FleetChangeKind::Status,
+                Some(old.status.as_str()),
+                Some(entry.status.as_str()),
+            ));
+        } else if entry.status == ProbeStatus::Ok {
+            if old.firmware_version != entry.firmware_version {
+                changes.push(change(
+                    FleetChangeKind::Firmware,
+                    old.firmware_version.as_deref(),
+                    entry.firmware_version.as_deref(),
+                ));
+            }
+            if old.serial_number != entry.serial_number {
+                changes.push(change(
+                    FleetChangeKind::Identity,
+                    old.serial_number.as_deref(),
+                    entry.serial_number.as_deref(),
+                ));
+            }
+        }
+        if changes.len() == count {
+            unchanged += 1;
+        }
+    }
+    changes.extend(
+        earlier
+            .iter()
+            .filter(|(key, _)| !later.contains_key(*key))
+            .map(|(key, old)| FleetChange {
+                unit: key.clone(),
+                port: old.port.clone(),
+                kind: FleetChangeKind::Removed,
+                before: Some(old.port.clone()),
+                after: None,
+            }),
+    );
+
+    FleetDiff {
+        before: before.scanned_at,
+        after: after.scanned_at,
+        unchanged,
+        changes,
+    }
+}
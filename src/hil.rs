@@ -0,0 +1,558 @@
+/*
+ * E-ink Power CLI - Hardware-in-the-Loop Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `hil-test`: drive a connected devkit through a test plan for firmware CI
+//!
+//! A TOML [`HilPlan`] lists steps run one after another over one
+//! connection:
+//!
+//! ```toml
+//! name = "pmu-candidate"
+//! timeout_s = 60            # per step, unless the step sets its own
+//!
+//! [[step]]
+//! type = "upload-firmware"
+//! file = "zephyr.signed.bin" # relative to the plan file
+//! critical = true
+//!
+//! [[step]]
+//! type = "command"
+//! command = "system info"
+//! expect = ["2.6.0"]
+//!
+//! [[step]]
+//! type = "assert"
+//! command = "battery read"
+//! checks = ["voltage_mv >= 3300", "voltage_mv <= 4300"]
+//! retries = 2
+//!
+//! [[step]]
+//! type = "sleep-test"
+//! duration = "10s"
+//!
+//! [[step]]
+//! type = "raw"
+//! line = "version"
+//! ```
+//!
+//! Every step is checked when the plan is loaded, before anything is sent.
+//! A step that fails, or whose `expect` substrings or `checks` do not hold,
+//! is attempted again up to `retries` times; the run goes on after it
+//! unless it is `critical`, in which case the remaining steps are skipped.
+//! The run produces one [`HilReport`], printed in the selected format and
+//! written as JUnit XML by [`junit_xml`] for the CI system.
+
+use crate::cli::{self, Commands};
+use crate::error::{PowerCliError, Result};
+use crate::json::assertion::{Assertion, AssertionOutcome};
+use crate::power::sleep::parse_sleep_duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Step timeout when neither the step nor the plan sets one
+pub const DEFAULT_STEP_TIMEOUT_S: u64 = 60;
+
+/// Step timeout of `upload-firmware` when neither the step nor the plan
+/// sets one
+pub const DEFAULT_UPLOAD_TIMEOUT_S: u64 = 600;
+
+/// Time a `sleep-test` allows, on top of the sleep, for the controller to
+/// answer again when neither the step nor the plan sets a timeout
+pub const DEFAULT_WAKE_ALLOWANCE_S: u64 = 30;
+
+/// Bytes of a step's output kept in the report
+pub const STEP_OUTPUT_LIMIT: usize = 8192;
+
+/// Keys every step may have
+const STEP_FIELDS: &[&str] = &["type", "name", "timeout_s", "retries", "critical", "expect"];
+
+/// A test plan read from a TOML file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HilPlan {
+    /// Suite name in the report [default: the file name]
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Seconds each step may take
+    #[serde(default)]
+    pub timeout_s: Option<u64>,
+    /// Times a failed step is attempted again
+    #[serde(default)]
+    pub retries: u32,
+    #[serde(rename = "step", default)]
+    pub steps: Vec<HilStep>,
+}
+
+/// One `[[step]]` of a plan
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct HilStep {
+    /// Test case name in the report [default: from the action]
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Seconds the step may take, every attempt included
+    #[serde(default)]
+    pub timeout_s: Option<u64>,
+    /// Times the step is attempted again after failing [default: the plan's]
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// Skip the rest of the plan if this step fails
+    #[serde(default)]
+    pub critical: bool,
+    /// Substrings the step's output must contain
+    #[serde(default)]
+    pub expect: Vec<String>,
+    #[serde(flatten)]
+    pub action: HilAction,
+}
+
+/// What a step does, by its `type`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum HilAction {
+    /// A subcommand as typed after `eink-power-cli`
+    Command { command: String },
+    /// `firmware upload` of an image, which resets the controller into it
+    UploadFirmware {
+        file: PathBuf,
+        /// Upload at the `firmware upload --fast` rate
+        #[serde(default)]
+        fast: bool,
+    },
+    /// `pm sleep --time <duration>`, then wait for the controller to
+    /// answer a ping again
+    SleepTest {
+        duration: String,
+        /// Further `pm sleep` options, e.g. `["--pmic", "--vlls3"]`
+        #[serde(default)]
+        options: Vec<String>,
+    },
+    /// A subcommand run with JSON output, with checks on its data
+    Assert {
+        command: String,
+        checks: Vec<String>,
+    },
+    /// A line sent to the controller shell as typed
+    Raw { line: String },
+}
+
+impl HilAction {
+    /// The step `type` as written in the plan
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HilAction::Command { .. } => "command",
+            HilAction::UploadFirmware { .. } => "upload-firmware",
+            HilAction::SleepTest { .. } => "sleep-test",
+            HilAction::Assert { .. } => "assert",
+            HilAction::Raw { .. } => "raw",
+        }
+    }
+
+    /// Keys a step of this type may have besides the common ones
+    fn fields(&self) -> &'static [&'static str] {
+        match self {
+            HilAction::Command { .. } => &["command"],
+            HilAction::UploadFirmware { .. } => &["file", "fast"],
+            HilAction::SleepTest { .. } => &["duration", "options"],
+            HilAction::Assert { .. } => &["command", "checks"],
+            HilAction::Raw { .. } => &["line"],
+        }
+    }
+
+    /// Short description, used as the default step name
+    pub fn describe(&self) -> String {
+        match self {
+            HilAction::Command { command } | HilAction::Assert { command, .. } => {
+                command.trim().to_string()
+            }
+            HilAction::UploadFirmware { file, .. } => format!("firmware upload {}", file.display()),
+            HilAction::SleepTest { duration, .. } => format!("sleep {}", duration),
+            HilAction::Raw { line } => format!("raw {}", line.trim()),
+        }
+    }
+}
+
+impl HilStep {
+    /// Name in the report
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| self.action.describe())
+    }
+
+    /// The subcommand the step runs: its `command`, `firmware upload` or
+    /// `pm sleep`; `None` for `raw`
+    pub fn command(&self) -> std::result::Result<Option<Commands>, String> {
+        match &self.action {
+            HilAction::Command { command } | HilAction::Assert { command, .. } => {
+                cli::parse_step(command).map(Some)
+            }
+            HilAction::UploadFirmware { file, fast } => {
+                Ok(Some(Commands::Firmware(cli::FirmwareCommands::Upload {
+                    file: file.clone(),
+                    skip_reset: false,
+                    port: None,
+                    baud: None,
+                    fast: fast.then_some(cli::FAST_UPLOAD_BAUD),
+                    transport_ble: None,
+                    transport_udp: None,
+                })))
+            }
+            HilAction::SleepTest { duration, options } => {
+                let line = format!("pm sleep --time {} {}", duration, options.join(" "));
+                cli::parse_step(&line).map(Some)
+            }
+            HilAction::Raw { .. } => Ok(None),
+        }
+    }
+
+    /// The `checks` of an `assert` step, parsed
+    pub fn assertions(&self) -> std::result::Result<Vec<Assertion>, String> {
+        match &self.action {
+            HilAction::Assert { checks, .. } => {
+                checks.iter().map(|c| Assertion::parse(c)).collect()
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Sleep of a `sleep-test`
+    pub fn sleep_duration(&self) -> Option<Duration> {
+        match &self.action {
+            HilAction::SleepTest { duration, .. } => parse_sleep_duration(duration),
+            _ => None,
+        }
+    }
+}
+
+impl HilPlan {
+    /// Read and check a plan file
+    ///
+    /// Relative image paths are taken from the plan's directory, and the
+    /// suite is named after the file unless the plan names it.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let mut plan = Self::parse(&text).map_err(|e| PowerCliError::InvalidArguments {
+            message: format!("test plan {}: {}", path.display(), e),
+        })?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        for step in &mut plan.steps {
+            if let HilAction::UploadFirmware { file, .. } = &mut step.action {
+                if file.is_relative() {
+                    *file = base.join(&*file);
+                }
+            }
+        }
+        if plan.name.is_none() {
+            plan.name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned());
+        }
+        Ok(plan)
+    }
+
+    /// Read a plan, failing on any step that could not run
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let plan: Self = toml::from_str(text).map_err(|e| e.to_string().trim_end().to_string())?;
+        if plan.steps.is_empty() {
+            return Err("the plan has no [[step]]".to_string());
+        }
+        // Steps are read through their `type`, which lets unknown keys by
+        let tables: toml::Table = toml::from_str(text).map_err(|e| e.to_string())?;
+        let tables = tables.get("step").and_then(toml::Value::as_array);
+        for (index, step) in plan.steps.iter().enumerate() {
+            let at = |message: String| format!("step {} ({}): {}", index + 1, step.name(), message);
+            let table = tables
+                .and_then(|steps| steps.get(index))
+                .and_then(toml::Value::as_table);
+            if let Some(key) = table
+                .into_iter()
+                .flat_map(|table| table.keys())
+                .find(|key| {
+                    !STEP_FIELDS.contains(&key.as_str())
+                        && !step.action.fields().contains(&key.as_str())
+                })
+            {
+                return Err(at(format!(
+                    "unknown field `{}` for a {} step",
+                    key,
+                    step.action.kind()
+                )));
+            }
+            step.command().map_err(at)?;
+            match &step.action {
+                HilAction::Assert { checks, .. } if checks.is_empty() => {
+                    return Err(at("an assert step needs at least one check".to_string()));
+                }
+                HilAction::SleepTest { duration, .. } if step.sleep_duration().is_none() => {
+                    return Err(at(format!("invalid duration '{}'", duration)));
+                }
+                HilAction::Raw { line } if line.trim().is_empty() => {
+                    return Err(at("a raw step needs a line to send".to_string()));
+                }
+                _ => {}
+            }
+            step.assertions().map_err(at)?;
+            if step.timeout_s == Some(0) {
+                return Err(at("timeout_s must be at least 1".to_string()));
+            }
+        }
+        if plan.timeout_s == Some(0) {
+            return Err("timeout_s must be at least 1".to_string());
+        }
+        Ok(plan)
+    }
+
+    /// Suite name in the report
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or("hil-test")
+    }
+
+    /// Time `step` may take, every attempt included
+    pub fn timeout(&self, step: &HilStep) -> Duration {
+        let default = match &step.action {
+            HilAction::UploadFirmware { .. } => DEFAULT_UPLOAD_TIMEOUT_S,
+            HilAction::SleepTest { .. } => {
+                step.sleep_duration().unwrap_or_default().as_secs() + DEFAULT_WAKE_ALLOWANCE_S
+            }
+            _ => DEFAULT_STEP_TIMEOUT_S,
+        };
+        Duration::from_secs(step.timeout_s.or(self.timeout_s).unwrap_or(default))
+    }
+
+    /// Times `step` is attempted after failing
+    pub fn retries(&self, step: &HilStep) -> u32 {
+        step.retries.unwrap_or(self.retries)
+    }
+}
+
+/// Outcome of a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HilStepStatus {
+    Passed,
+    Failed,
+    /// Not run because a critical step failed
+    Skipped,
+}
+
+/// An `expect` substring or `assert` check and whether it held
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HilCheck {
+    /// The check as written in the plan
+    pub check: String,
+    pub passed: bool,
+    /// Why it failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl HilCheck {
+    /// Whether `output` contains `expected`
+    pub fn substring(expected: &str, output: &str) -> Self {
+        let passed = output.contains(expected);
+        Self {
+            check: format!("expect \"{}\"", expected),
+            passed,
+            reason: (!passed).then(|| "not in the output".to_string()),
+        }
+    }
+
+    pub fn assertion(assertion: &Assertion, outcome: AssertionOutcome) -> Self {
+        Self {
+            check: assertion.expression.clone(),
+            passed: outcome.passed,
+            reason: outcome.reason,
+        }
+    }
+}
+
+/// Result of one step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HilStepResult {
+    pub name: String,
+    /// Step `type`
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub critical: bool,
+    pub status: HilStepStatus,
+    /// Attempts made; 0 when skipped
+    pub attempts: u32,
+    pub duration_ms: u64,
+    /// Why the last attempt failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Checks of the last attempt
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checks: Vec<HilCheck>,
+    /// What the last attempt printed, at most [`STEP_OUTPUT_LIMIT`] bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+}
+
+impl HilStepResult {
+    /// A step not run
+    pub fn skipped(step: &HilStep) -> Self {
+        Self {
+            name: step.name(),
+            kind: step.action.kind().to_string(),
+            critical: step.critical,
+            status: HilStepStatus::Skipped,
+            attempts: 0,
+            duration_ms: 0,
+            error: None,
+            checks: Vec::new(),
+            output: None,
+        }
+    }
+
+    /// First failure of the step: its error or the first failed check
+    pub fn failure(&self) -> Option<String> {
+        if self.status != HilStepStatus::Failed {
+            return None;
+        }
+        self.error.clone().or_else(|| {
+            self.checks
+                .iter()
+                .find(|check| !check.passed)
+                .map(|check| match &check.reason {
+                    Some(reason) => format!("{} failed: {}", check.check, reason),
+                    None => format!("{} failed", check.check),
+                })
+        })
+    }
+}
+
+/// Outcome of a plan run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HilReport {
+    pub plan: String,
+    pub success: bool,
+    pub started: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub steps: Vec<HilStepResult>,
+}
+
+impl HilReport {
+    /// Report of the steps run so far
+    pub fn new(plan: &str, started: DateTime<Utc>, steps: Vec<HilStepResult>) -> Self {
+        let count =
+            |status: HilStepStatus| steps.iter().filter(|step| step.status == status).count();
+        let (passed, failed, skipped) = (
+            count(HilStepStatus::Passed),
+            count(HilStepStatus::Failed),
+            count(HilStepStatus::Skipped),
+        );
+        Self {
+            plan: plan.to_string(),
+            success: failed == 0 && skipped == 0,
+            started,
+            duration_ms: steps.iter().map(|step| step.duration_ms).sum(),
+            passed,
+            failed,
+            skipped,
+            steps,
+        }
+    }
+}
+
+/// The report as JUnit XML: one test suite, one test case per step
+pub fn junit_xml(report: &HilReport) -> String {
+    let seconds = |ms: u64| format!("{:.3}", ms as f64 / 1000.0);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"eink-power-cli\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">\n",
+        report.steps.len(),
+        report.failed,
+        report.skipped,
+        seconds(report.duration_ms)
+    ));
+    xml.push_str(&format!(
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{}\" timestamp=\"{}\">\n",
+        xml_escape(&report.plan),
+        report.steps.len(),
+        report.failed,
+        report.skipped,
+        seconds(report.duration_ms),
+        report.started.format("%Y-%m-%dT%H:%M:%S")
+    ));
+    for step in &report.steps {
+        xml.push_str(&format!(
+            "    <testcase name=\"{}\" classname=\"{}.{}\" time=\"{}\"",
+            xml_escape(&step.name),
+            xml_escape(&report.plan),
+            step.kind,
+            seconds(step.duration_ms)
+        ));
+        let mut body = String::new();
+        match step.status {
+            HilStepStatus::Passed => {}
+            HilStepStatus::Failed => {
+                let message = step.failure().unwrap_or_default();
+                let mut details: Vec<String> = Vec::new();
+                details.push(format!("attempts: {}", step.attempts));
+                details.extend(step.checks.iter().map(|check| {
+                    let mark = if check.passed { "pass" } else { "FAIL" };
+                    match &check.reason {
+                        Some(reason) => format!("{}: {} ({})", mark, check.check, reason),
+                        None => format!("{}: {}", mark, check.check),
+                    }
+                }));
+                body.push_str(&format!(
+                    "      <failure message=\"{}\" type=\"{}\">{}</failure>\n",
+                    xml_escape(&message),
+                    if step.error.is_some() {
+                        "error"
+                    } else {
+                        "check"
+                    },
+                    xml_escape(&details.join("\n"))
+                ));
+            }
+            HilStepStatus::Skipped => {
+                body.push_str("      <skipped message=\"a critical step failed\"/>\n");
+            }
+        }
+        if let Some(output) = &step.output {
+            body.push_str(&format!(
+                "      <system-out>{}</system-out>\n",
+                xml_escape(output)
+            ));
+        }
+        if body.is_empty() {
+            xml.push_str("/>\n");
+        } else {
+            xml.push_str(">\n");
+            xml.push_str(&body);
+            xml.push_str("    </testcase>\n");
+        }
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// `text` as XML character data or attribute value
+///
+/// Control characters XML 1.0 cannot carry (such as the escape sequences
+/// of coloured output) are dropped.
+pub fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if (c as u32) < 0x20 || c == '\u{fffe}' || c == '\u{ffff}' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,199 @@
+/*
+ * E-ink Power CLI - Command History Log
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Append-only, per-device audit trail of CLI invocations
+//!
+//! Every invocation appends one JSON line recording who ran what, the shell
+//! commands that reached the controller and how it ended. The log rotates to
+//! a single `.1` backup once it exceeds [`DEFAULT_MAX_BYTES`].
+
+use crate::state::{DeviceState, StateLock};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Size at which the history file is rotated
+pub const DEFAULT_MAX_BYTES: u64 = 1024 * 1024;
+
+/// File name of the history log inside a device's state directory
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// Maximum length of the response excerpt kept per entry
+pub const RESPONSE_EXCERPT_LEN: usize = 512;
+
+/// One recorded CLI invocation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    pub user: String,
+    pub invocation: String,
+    pub commands_sent: Vec<String>,
+    pub response: Option<String>,
+    pub exit_status: i32,
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Build an entry for the current process and user
+    pub fn new(
+        commands_sent: &[String],
+        response: Option<&str>,
+        exit_status: i32,
+        error: Option<String>,
+    ) -> Self {
+        let user = std::env::var("SUDO_USER")
+            .or_else(|_| std::env::var("USER"))
+            .or_else(|_| std::env::var("LOGNAME"))
+            .unwrap_or_else(|_| "unknown".to_string());
+
+        Self {
+            timestamp: Utc::now(),
+            user,
+            invocation: std::env::args().collect::<Vec<_>>().join(" "),
+            commands_sent: commands_sent.to_vec(),
+            response: response.map(truncate_excerpt),
+            exit_status,
+            error,
+        }
+    }
+
+    /// Whether any recorded text contains `pattern` (case-insensitive)
+    pub fn matches(&self, pattern: &str) -> bool {
+        let pattern = pattern.to_lowercase();
+        std::iter::once(self.invocation.as_str())
+            .chain(std::iter::once(self.user.as_str()))
+            .chain(self.commands_sent.iter().map(String::as_str))
+            .chain(self.response.as_deref())
+            .chain(self.error.as_deref())
+            .any(|text| text.to_lowercase().contains(&pattern))
+    }
+
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        let status = if self.exit_status == 0 { "✅" } else { "❌" };
+        let mut line = format!(
+            "{} {} {} [{}] {}",
+            status,
+            self.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            self.user,
+            self.exit_status,
+            self.invocation
+        );
+        if !self.commands_sent.is_empty() {
+            line.push_str(&format!("\n      → {}", self.commands_sent.join("; ")));
+        }
+        if let Some(error) = &self.error {
+            line.push_str(&format!("\n      ⚠️  {}", error));
+        }
+        line
+    }
+}
+
+/// Truncate a response to [`RESPONSE_EXCERPT_LEN`] bytes on a char boundary
+fn truncate_excerpt(response: &str) -> String {
+    if response.len() <= RESPONSE_EXCERPT_LEN {
+        return response.to_string();
+    }
+    let mut end = RESPONSE_EXCERPT_LEN;
+    while !response.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}…", &response[..end])
+}
+
+/// History log file for one device
+pub struct HistoryLog {
+    path: PathBuf,
+    max_bytes: u64,
+}
+
+impl HistoryLog {
+    /// Open the log at an explicit path
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+
+    /// Log for a serial device under the state directory
+    pub fn for_device(device: &str) -> Option<Self> {
+        DeviceState::for_device(device).map(|state| Self::new(state.file_path(HISTORY_FILE)))
+    }
+
+    /// Override the rotation threshold
+    #[allow(dead_code)] // Used by tests
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// Path of the active log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of the rotated backup
+    fn rotated_path(&self) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(".1");
+        PathBuf::from(name)
+    }
+
+    /// Append an entry, rotating first if the log is over its size cap
+    ///
+    /// Rotation and the append happen under the log's state lock so
+    /// concurrent invocations cannot lose entries or rotate twice.
+    pub fn append(&self, entry: &HistoryEntry) -> io::Result<()> {
+        let _lock = StateLock::acquire(&self.path)?;
+
+        if fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0) >= self.max_bytes {
+            fs::rename(&self.path, self.rotated_path())?;
+        }
+
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(line.as_bytes())
+    }
+
+    /// Read all entries, oldest first, including the rotated backup
+    ///
+    /// Lines that fail to parse (e.g. a torn write) are skipped.
+    pub fn read(&self) -> io::Result<Vec<HistoryEntry>> {
+        let mut entries = Vec::new();
+        for path in [self.rotated_path(), self.path.clone()] {
+            let file = match fs::File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            for line in BufReader::new(file).lines() {
+                if let Ok(entry) = serde_json::from_str::<HistoryEntry>(&line?) {
+                    entries.push(entry);
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Most recent `last` entries matching the optional filter
+    pub fn query(&self, last: usize, grep: Option<&str>) -> io::Result<Vec<HistoryEntry>> {
+        let mut entries: Vec<HistoryEntry> = self
+            .read()?
+            .into_iter()
+            .filter(|entry| grep.is_none_or(|pattern| entry.matches(pattern)))
+            .collect();
+        let skip = entries.len().saturating_sub(last);
+        Ok(entries.split_off(skip))
+    }
+}
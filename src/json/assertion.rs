@@ -0,0 +1,290 @@
+/*
+ * E-ink Power CLI - Output Assertions
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Checks on the `data` of a command's JSON output
+//!
+//! An assertion is a field path, an operator and a value:
+//!
+//! ```text
+//! voltage_mv >= 3300
+//! rails.pmic == true
+//! firmware_version contains "2.6"
+//! slots[0].confirmed == true
+//! serial exists
+//! ```
+//!
+//! Paths are object keys joined by `.`, with `[n]` for array elements.
+//! Numbers are compared as numbers, so `3300` matches `3300.0`; `<`, `<=`,
+//! `>` and `>=` need numbers on both sides. A bare word on the right is
+//! taken as a string unless it is `true`, `false` or `null`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+/// Comparison of an [`Assertion`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// Substring of a string, or element of an array
+    Contains,
+    /// The field is present and not `null`
+    Exists,
+}
+
+impl Operator {
+    /// Operators as written, longest first so `<=` is not read as `<`
+    const WRITTEN: [(&'static str, Operator); 8] = [
+        ("==", Operator::Eq),
+        ("!=", Operator::Ne),
+        ("<=", Operator::Le),
+        (">=", Operator::Ge),
+        ("<", Operator::Lt),
+        (">", Operator::Gt),
+        ("contains", Operator::Contains),
+        ("exists", Operator::Exists),
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        Self::WRITTEN
+            .iter()
+            .find(|(_, op)| *op == self)
+            .map(|(text, _)| *text)
+            .unwrap_or_default()
+    }
+}
+
+/// One segment of a field path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// A parsed check such as `voltage_mv >= 3300`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assertion {
+    /// The check as written
+    pub expression: String,
+    pub path: Vec<PathSegment>,
+    pub op: Operator,
+    /// Right-hand side; `Null` for [`Operator::Exists`]
+    pub expected: Value,
+}
+
+/// Result of evaluating an [`Assertion`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AssertionOutcome {
+    pub passed: bool,
+    /// Value found at the path; `None` if the path is missing
+    pub actual: Option<Value>,
+    /// Why a failed check failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl Assertion {
+    /// Parse `path op value` or `path exists`
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let text = expression.trim();
+        let (path, op, rest) = Self::split(text).ok_or_else(|| {
+            format!(
+                "'{}' has no operator (==, !=, <, <=, >, >=, contains, exists)",
+                text
+            )
+        })?;
+        let path = parse_path(path.trim()).map_err(|e| format!("'{}': {}", text, e))?;
+        let rest = rest.trim();
+        let expected = match op {
+            Operator::Exists if rest.is_empty() => Value::Null,
+            Operator::Exists => return Err(format!("'{}': nothing may follow exists", text)),
+            _ if rest.is_empty() => return Err(format!("'{}' has no value to compare with", text)),
+            _ => parse_value(rest).map_err(|e| format!("'{}': {}", text, e))?,
+        };
+        if matches!(
+            op,
+            Operator::Lt | Operator::Le | Operator::Gt | Operator::Ge
+        ) && !expected.is_number()
+        {
+            return Err(format!("'{}': {} needs a number", text, op.as_str()));
+        }
+        Ok(Self {
+            expression: text.to_string(),
+            path,
+            op,
+            expected,
+        })
+    }
+
+    /// Path, operator and the text after it; word operators must stand
+    /// apart from the path
+    fn split(text: &str) -> Option<(&str, Operator, &str)> {
+        for (index, _) in text.char_indices() {
+            let rest = &text[index..];
+            for (written, op) in Operator::WRITTEN {
+                if !rest.starts_with(written) {
+                    continue;
+                }
+                let word = written.chars().all(|c| c.is_ascii_alphabetic());
+                let after = &rest[written.len()..];
+                if word
+                    && (!text[..index].ends_with(char::is_whitespace)
+                        || !(after.is_empty() || after.starts_with(char::is_whitespace)))
+                {
+                    continue;
+                }
+                return Some((&text[..index], op, after));
+            }
+        }
+        None
+    }
+
+    /// Check the assertion against the `data` of a JSON envelope
+    pub fn evaluate(&self, data: &Value) -> AssertionOutcome {
+        let actual = lookup(data, &self.path).filter(|value| !value.is_null());
+        let failed = |reason: String| AssertionOutcome {
+            passed: false,
+            actual: actual.cloned(),
+            reason: Some(reason),
+        };
+        let Some(value) = actual else {
+            return match (self.op, &self.expected) {
+                (Operator::Eq, Value::Null) | (Operator::Ne, Value::Null) => AssertionOutcome {
+                    passed: self.op == Operator::Eq,
+                    actual: None,
+                    reason: (self.op == Operator::Ne)
+                        .then(|| format!("{} is null", self.path_text())),
+                },
+                _ => failed(format!("{} is missing", self.path_text())),
+            };
+        };
+
+        let passed = match self.op {
+            Operator::Exists => true,
+            Operator::Eq => equal(value, &self.expected),
+            Operator::Ne => !equal(value, &self.expected),
+            Operator::Contains => match (value, &self.expected) {
+                (Value::String(haystack), Value::String(needle)) => {
+                    haystack.contains(needle.as_str())
+                }
+                (Value::Array(items), expected) => items.iter().any(|item| equal(item, expected)),
+                _ => return failed(format!("{} is not a string or array", self.path_text())),
+            },
+            Operator::Lt | Operator::Le | Operator::Gt | Operator::Ge => {
+                let (Some(actual), Some(expected)) = (value.as_f64(), self.expected.as_f64())
+                else {
+                    return failed(format!("{} is not a number", self.path_text()));
+                };
+                match self.op {
+                    Operator::Lt => actual < expected,
+                    Operator::Le => actual <= expected,
+                    Operator::Gt => actual > expected,
+                    _ => actual >= expected,
+                }
+            }
+        };
+        AssertionOutcome {
+            passed,
+            actual: Some(value.clone()),
+            reason: (!passed).then(|| format!("{} is {}", self.path_text(), value)),
+        }
+    }
+
+    /// The path as written
+    pub fn path_text(&self) -> String {
+        let mut text = String::new();
+        for segment in &self.path {
+            match segment {
+                PathSegment::Key(key) if text.is_empty() => text.push_str(key),
+                PathSegment::Key(key) => {
+                    text.push('.');
+                    text.push_str(key);
+                }
+                PathSegment::Index(index) => text.push_str(&format!("[{}]", index)),
+            }
+        }
+        text
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// `a.b[2].c` as segments
+fn parse_path(path: &str) -> Result<Vec<PathSegment>, String> {
+    if path.is_empty() {
+        return Err("no field to check".to_string());
+    }
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        let (key, mut indices) = match part.find('[') {
+            Some(start) => (&part[..start], &part[start..]),
+            None => (part, ""),
+        };
+        if key.is_empty() && segments.is_empty() && indices.is_empty() {
+            return Err(format!("empty field name in '{}'", path));
+        }
+        if !key.is_empty() {
+            if !key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                return Err(format!("invalid field name '{}'", key));
+            }
+            segments.push(PathSegment::Key(key.to_string()));
+        } else if indices.is_empty() {
+            return Err(format!("empty field name in '{}'", path));
+        }
+        while !indices.is_empty() {
+            let end = indices
+                .find(']')
+                .ok_or_else(|| format!("unclosed [ in '{}'", path))?;
+            let index = indices[1..end]
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid index '{}' in '{}'", &indices[1..end], path))?;
+            segments.push(PathSegment::Index(index));
+            indices = &indices[end + 1..];
+            if !indices.is_empty() && !indices.starts_with('[') {
+                return Err(format!("unexpected '{}' in '{}'", indices, path));
+            }
+        }
+    }
+    Ok(segments)
+}
+
+/// Right-hand side: JSON literal, or a bare word as a string
+fn parse_value(text: &str) -> Result<Value, String> {
+    match serde_json::from_str::<Value>(text) {
+        Ok(value) => Ok(value),
+        Err(_) if text.starts_with('"') => Err(format!("unterminated string {}", text)),
+        Err(_) => Ok(Value::String(text.to_string())),
+    }
+}
+
+fn lookup<'a>(data: &'a Value, path: &[PathSegment]) -> Option<&'a Value> {
+    path.iter().try_fold(data, |value, segment| match segment {
+        PathSegment::Key(key) => value.get(key.as_str()),
+        PathSegment::Index(index) => value.get(*index),
+    })
+}
+
+/// Equality with numbers compared by value
+fn equal(actual: &Value, expected: &Value) -> bool {
+    match (actual.as_f64(), expected.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => actual == expected,
+    }
+}
@@ -0,0 +1,373 @@
+/*
+ * E-ink Power CLI - Parse Coverage
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! How much of a corpus of device transcripts the parsers understand
+//!
+//! The corpus is a directory of replies captured from real controllers, one
+//! per `.txt` file. A file name starts with the shell command, words joined
+//! by `_`, and may go on after a `.` or `_` to tell transcripts apart:
+//! `ltc2959_status.fw23.txt`, `pm_battery_check_low.txt`. Each transcript
+//! goes through the parser of its command in [`PARSERS`], and an output
+//! field counts as populated when it is not `null`.
+//!
+//! A baseline records the share of transcripts that populate each field.
+//! [`CoverageReport::check`] lists the fields that dropped below it, so a
+//! firmware release that rewords a line shows up as a regression instead of
+//! a quiet `null`.
+
+use super::schema::SYSTEM_INFO_IDENTITY_FIELDS;
+use super::ResponseParser;
+use crate::error::{PowerCliError, Result};
+use crate::state;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+/// Baseline file name, looked for in the corpus directory
+pub const BASELINE_FILE: &str = "baseline.json";
+
+/// Parser of a shell command's reply, as JSON
+pub type CorpusParser = fn(&str) -> Value;
+
+/// Parser for each shell command a transcript may hold
+pub const PARSERS: &[(&str, CorpusParser)] = &[
+    ("ltc2959 read", |r| {
+        to_value(ResponseParser::parse_battery_response(r))
+    }),
+    ("ltc2959 status", |r| {
+        to_value(ResponseParser::parse_ltc2959_status(r))
+    }),
+    ("system info", |r| {
+        let mut info = to_value(ResponseParser::parse_system_info(r));
+        // Filled from the NFC EEPROM by `system info --identity`, never parsed
+        if let Some(fields) = info.as_object_mut() {
+            for field in SYSTEM_INFO_IDENTITY_FIELDS {
+                fields.remove(*field);
+            }
+        }
+        info
+    }),
+    ("nfc status", |r| {
+        to_value(ResponseParser::parse_nfc_status(r))
+    }),
+    ("nfc tag_info", |r| {
+        to_value(ResponseParser::parse_nfc_tag_info(r))
+    }),
+    ("gpio get", |r| {
+        to_value(ResponseParser::parse_gpio_response(r, "unknown", 0))
+    }),
+    ("rtc status", |r| {
+        to_value(ResponseParser::parse_rtc_status(r))
+    }),
+    ("pm battery_check", |r| {
+        to_value(ResponseParser::parse_battery_health(r))
+    }),
+    ("pm stats", |r| to_value(ResponseParser::parse_pm_stats(r))),
+    ("pm measure", |r| {
+        to_value(ResponseParser::parse_measurement(r))
+    }),
+    ("pm defaults", |r| {
+        to_value(ResponseParser::parse_rail_defaults(r))
+    }),
+];
+
+fn to_value<T: Serialize>(parsed: T) -> Value {
+    serde_json::to_value(parsed).expect("parser output serializes")
+}
+
+/// Command of the transcript named `file_name`: the longest one its name
+/// starts with
+pub fn parser_for(file_name: &str) -> Option<(&'static str, CorpusParser)> {
+    PARSERS
+        .iter()
+        .filter(|(command, _)| {
+            let label = command.replace(' ', "_");
+            file_name
+                .strip_prefix(&label)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '_']))
+        })
+        .max_by_key(|(command, _)| command.len())
+        .copied()
+}
+
+/// Whether each output field of `parse` on `transcript` is populated, by
+/// dotted path; `Err` with the panic message if the parser panicked
+pub fn populated_fields(
+    parse: CorpusParser,
+    transcript: &str,
+) -> std::result::Result<BTreeMap<String, bool>, String> {
+    let value = panic::catch_unwind(AssertUnwindSafe(|| parse(transcript))).map_err(|payload| {
+        payload
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panicked".to_string())
+    })?;
+    let mut fields = BTreeMap::new();
+    collect_fields("", &value, &mut fields);
+    Ok(fields)
+}
+
+/// Record every leaf of `value` under its dotted path
+fn collect_fields(path: &str, value: &Value, fields: &mut BTreeMap<String, bool>) {
+    match value {
+        Value::Object(members) if !members.is_empty() => {
+            for (key, member) in members {
+                let path = match path {
+                    "" => key.clone(),
+                    _ => format!("{}.{}", path, key),
+                };
+                collect_fields(&path, member, fields);
+            }
+        }
+        Value::Null => {
+            fields.insert(path_or_value(path), false);
+        }
+        _ => {
+            fields.insert(path_or_value(path), true);
+        }
+    }
+}
+
+/// A parser returning a bare value (e.g. `None`) has one unnamed field
+fn path_or_value(path: &str) -> String {
+    match path {
+        "" => "value".to_string(),
+        _ => path.to_string(),
+    }
+}
+
+/// Field population of one command's transcripts
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommandCoverage {
+    pub command: String,
+    pub transcripts: usize,
+    /// Share of all field readings that were populated, 0 to 1
+    pub coverage: f64,
+    /// Share of transcripts that populated each field, 0 to 1
+    pub fields: BTreeMap<String, f64>,
+}
+
+/// A transcript whose parser panicked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParserPanic {
+    pub file: String,
+    pub command: String,
+    pub message: String,
+}
+
+/// A command or field populated less often than the baseline records
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageRegression {
+    pub command: String,
+    /// `None` for the command as a whole
+    pub field: Option<String>,
+    pub baseline: f64,
+    /// `None` if the corpus no longer has it
+    pub current: Option<f64>,
+}
+
+/// `parse-check` result
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageReport {
+    pub corpus: PathBuf,
+    /// By command name
+    pub commands: Vec<CommandCoverage>,
+    /// Transcripts named after no known command
+    pub unmatched: Vec<String>,
+    pub panics: Vec<ParserPanic>,
+    /// Baseline compared against, if any
+    pub baseline: Option<PathBuf>,
+    pub regressions: Vec<CoverageRegression>,
+    /// No parser panicked and nothing fell below the baseline
+    pub passed: bool,
+}
+
+impl CoverageReport {
+    /// Run every transcript in `dir` through its parser
+    pub fn scan(dir: &Path) -> Result<Self> {
+        let mut names: Vec<String> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| name.ends_with(".txt"))
+            .collect();
+        names.sort();
+
+        let mut readings: BTreeMap<&str, Vec<BTreeMap<String, bool>>> = BTreeMap::new();
+        let mut unmatched = Vec::new();
+        let mut panics = Vec::new();
+        for name in names {
+            let stem = name.trim_end_matches(".txt");
+            let Some((command, parse)) = parser_for(stem) else {
+                unmatched.push(name);
+                continue;
+            };
+            let transcript = std::fs::read_to_string(dir.join(&name))?;
+            match populated_fields(parse, &transcript) {
+                Ok(fields) => readings.entry(command).or_default().push(fields),
+                Err(message) => panics.push(ParserPanic {
+                    file: name,
+                    command: command.to_string(),
+                    message,
+                }),
+            }
+        }
+
+        let commands = readings
+            .into_iter()
+            .map(|(command, transcripts)| coverage_of(command, &transcripts))
+            .collect();
+        Ok(Self {
+            corpus: dir.to_path_buf(),
+            commands,
+            unmatched,
+            passed: panics.is_empty(),
+            panics,
+            baseline: None,
+            regressions: Vec::new(),
+        })
+    }
+
+    /// Compare with `baseline`, recorded from `path`
+    ///
+    /// Every command and field must be populated at least as often as
+    /// recorded; new commands and fields are not compared.
+    pub fn check(&mut self, baseline: &CoverageBaseline, path: &Path) {
+        self.baseline = Some(path.to_path_buf());
+        self.regressions.clear();
+        for recorded in &baseline.commands {
+            let current = self
+                .commands
+                .iter()
+                .find(|coverage| coverage.command == recorded.command);
+            if below(current.map(|c| c.coverage), recorded.coverage) {
+                self.regressions.push(CoverageRegression {
+                    command: recorded.command.clone(),
+                    field: None,
+                    baseline: recorded.coverage,
+                    current: current.map(|c| c.coverage),
+                });
+            }
+            let Some(current) = current else {
+                continue;
+            };
+            for (field, &rate) in &recorded.fields {
+                let now = current.fields.get(field).copied();
+                if below(now, rate) {
+                    self.regressions.push(CoverageRegression {
+                        command: recorded.command.clone(),
+                        field: Some(field.clone()),
+                        baseline: rate,
+                        current: now,
+                    });
+                }
+            }
+        }
+        self.passed = self.panics.is_empty() && self.regressions.is_empty();
+    }
+
+    /// Transcripts read, panicked ones included
+    pub fn transcripts(&self) -> usize {
+        self.commands.iter().map(|c| c.transcripts).sum::<usize>() + self.panics.len()
+    }
+
+    /// One line per failure, for the error message
+    pub fn failures(&self) -> Vec<String> {
+        let panics = self
+            .panics
+            .iter()
+            .map(|p| format!("{} parser panicked on {}", p.command, p.file));
+        let regressions = self.regressions.iter().map(|r| {
+            let what = match &r.field {
+                Some(field) => format!("{} {}", r.command, field),
+                None => r.command.clone(),
+            };
+            format!(
+                "{} at {} (baseline {})",
+                what,
+                percent(r.current),
+                percent(Some(r.baseline))
+            )
+        });
+        panics.chain(regressions).collect()
+    }
+}
+
+/// Share as a whole percentage, or `missing`
+pub fn percent(share: Option<f64>) -> String {
+    share.map_or("missing".to_string(), |share| {
+        format!("{:.0}%", share * 100.0)
+    })
+}
+
+/// Whether `current` is lower than `baseline`, allowing for rounding
+fn below(current: Option<f64>, baseline: f64) -> bool {
+    current.is_none_or(|current| current + 1e-9 < baseline)
+}
+
+fn coverage_of(command: &str, transcripts: &[BTreeMap<String, bool>]) -> CommandCoverage {
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let (mut populated, mut readings) = (0, 0);
+    for fields in transcripts {
+        for (field, &set) in fields {
+            *counts.entry(field.clone()).or_default() += usize::from(set);
+            populated += usize::from(set);
+            readings += 1;
+        }
+    }
+    let share = |count: usize, total: usize| match total {
+        0 => 0.0,
+        _ => count as f64 / total as f64,
+    };
+    CommandCoverage {
+        command: command.to_string(),
+        transcripts: transcripts.len(),
+        coverage: share(populated, readings),
+        fields: counts
+            .into_iter()
+            .map(|(field, count)| (field, share(count, transcripts.len())))
+            .collect(),
+    }
+}
+
+/// Coverage recorded with `parse-check --update-baseline`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoverageBaseline {
+    pub commands: Vec<CommandCoverage>,
+}
+
+impl CoverageBaseline {
+    /// Coverage of `report` as the new baseline
+    pub fn of(report: &CoverageReport) -> Self {
+        Self {
+            commands: report.commands.clone(),
+        }
+    }
+
+    /// Write as pretty JSON, so changes to it review well
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        state::write_atomic(path, format!("{}\n", text).as_bytes())?;
+        Ok(())
+    }
+
+    /// Read a baseline; `None` if there is none at `path`
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|e| PowerCliError::InvalidArguments {
+                message: format!("{} is not a parse coverage baseline: {}", path.display(), e),
+            })
+    }
+}
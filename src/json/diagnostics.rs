@@ -0,0 +1,133 @@
+/*
+ * E-ink Power CLI - Parse Diagnostics
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Record of what the [`ResponseParser`](super::ResponseParser) matched
+//!
+//! A `null` field in JSON output can mean the firmware never printed the
+//! line or that our pattern did not match it. Every parser reports one
+//! [`ParseDiagnostic`] per field: always as a debug log, and into the list
+//! returned by [`collect`] while one is running on the same thread.
+
+use log::debug;
+use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+/// What happened to one expected field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParseOutcome {
+    /// The pattern matched and the value was read
+    Matched,
+    /// No line mentions the field
+    Absent,
+    /// A line mentions the field but could not be read
+    Unparsed,
+}
+
+/// Outcome of parsing one field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseDiagnostic {
+    /// Output field, e.g. `voltage_mv`
+    pub field: String,
+    pub outcome: ParseOutcome,
+    /// Pattern tried for the field
+    pub pattern: String,
+    /// Line that matched, or that mentioned the field but did not parse
+    pub line: Option<String>,
+}
+
+thread_local! {
+    static COLLECTOR: RefCell<Option<Vec<ParseDiagnostic>>> = const { RefCell::new(None) };
+}
+
+/// Run `parse` and return what it reported
+///
+/// Collection is per thread, and nested calls share the outermost list.
+pub fn collect<T>(parse: impl FnOnce() -> T) -> (T, Vec<ParseDiagnostic>) {
+    let outer = COLLECTOR.with(|c| c.borrow_mut().replace(Vec::new()));
+    let value = parse();
+    let diagnostics = COLLECTOR.with(|c| {
+        let mut slot = c.borrow_mut();
+        let collected = slot.take().unwrap_or_default();
+        match outer {
+            Some(mut outer) => {
+                outer.extend(collected.iter().cloned());
+                *slot = Some(outer);
+            }
+            None => *slot = None,
+        }
+        collected
+    });
+    (value, diagnostics)
+}
+
+fn record(field: &str, outcome: ParseOutcome, pattern: &Regex, line: Option<&str>) {
+    debug!(
+        "parse {}: {:?} /{}/{}",
+        field,
+        outcome,
+        pattern.as_str(),
+        line.map(|l| format!(" in '{}'", l)).unwrap_or_default()
+    );
+    COLLECTOR.with(|c| {
+        if let Some(diagnostics) = c.borrow_mut().as_mut() {
+            diagnostics.push(ParseDiagnostic {
+                field: field.to_string(),
+                outcome,
+                pattern: pattern.as_str().to_string(),
+                line: line.map(str::to_string),
+            });
+        }
+    });
+}
+
+/// Line of `response` containing byte offset `at`
+pub(crate) fn line_at(response: &str, at: usize) -> &str {
+    let start = response[..at].rfind('\n').map_or(0, |i| i + 1);
+    let end = response[at..].find('\n').map_or(response.len(), |i| at + i);
+    response[start..end].trim()
+}
+
+/// Record that `field` was read from `line`
+pub(crate) fn matched(field: &str, pattern: &Regex, line: &str) {
+    record(field, ParseOutcome::Matched, pattern, Some(line));
+}
+
+/// Record that `field` was not read
+///
+/// The first line containing `label` (case-insensitive) is reported as
+/// unparsed; without one the field is absent.
+pub(crate) fn missed(field: &str, label: &str, pattern: &Regex, response: &str) {
+    let label = label.to_lowercase();
+    match response
+        .lines()
+        .find(|line| line.to_lowercase().contains(&label))
+    {
+        Some(line) => record(field, ParseOutcome::Unparsed, pattern, Some(line.trim())),
+        None => record(field, ParseOutcome::Absent, pattern, None),
+    }
+}
+
+/// Match `pattern` for `field`, recording the outcome
+///
+/// `label` is the text that marks a line as being about the field.
+pub(crate) fn find<'r>(
+    field: &str,
+    label: &str,
+    pattern: &Regex,
+    response: &'r str,
+) -> Option<Captures<'r>> {
+    let caps = pattern.captures(response);
+    match &caps {
+        Some(caps) => {
+            let at = caps.get(0).map_or(0, |m| m.start());
+            matched(field, pattern, line_at(response, at))
+        }
+        None => missed(field, label, pattern, response),
+    }
+    caps
+}
@@ -0,0 +1,1636 @@
+/*
+ * JSON Output Formatting for E-ink Power CLI
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+pub mod assertion;
+pub mod coverage;
+pub mod diagnostics;
+pub mod output;
+pub mod patterns;
+pub mod progress;
+pub mod raw;
+pub mod samples;
+pub mod schema;
+pub mod sections;
+
+use crate::error::PowerCliError;
+use crate::power::battery::ChargingState;
+use crate::power::charger::ChargerStatus;
+use crate::power::gpio::PinNames;
+use crate::power::identity::DeviceIdentity;
+use crate::power::ltc2959::AdcMode;
+use crate::power::rails::{PowerRail, Rail};
+use crate::power::stats::PowerStatsDerived;
+use crate::serial::banner::BootBanner;
+use crate::serial::protocol::classify::{self, ResponseClass};
+use crate::serial::ConnectionStats;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use log::warn;
+#[allow(unused_imports)] // parse_output is used by library consumers
+pub use output::{
+    parse_envelope, parse_output, CommandOutput, ErrorJson, OutputKind, ParsedPayload, TimeoutJson,
+    OUTPUT_SCHEMA_VERSION,
+};
+use regex::Regex;
+pub use sections::SectionedJson;
+use semver::{Prerelease, Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Write;
+
+/// Regex fragment matching a number as printed by any firmware locale.
+///
+/// Accepts an optional sign, `.` or `,` decimal separators and `,`, `.`,
+/// `'`, space or no-break-space digit grouping (e.g. `3,85`, `1.234,5`,
+/// `6 088`). Space-like grouping only joins a leading group of one to three
+/// digits with groups of exactly three, so two adjacent values such as
+/// `12 3456` are not read as one number; a pattern with no unit after the
+/// number should end it with `(?:\D|$)` so the last group cannot stop short.
+/// Captured text must be normalized with [`parse_integer`] or
+/// [`parse_decimal`] before use.
+pub const NUMBER_PATTERN: &str = r"[-+]?(?:\d{1,3}(?:['\x{00A0}\x{202F} ]\d{3})+|\d+)(?:[.,]\d+)*";
+
+/// Split a localized number into its integer and fractional digit strings.
+///
+/// When both `.` and `,` appear, the rightmost one is the decimal separator.
+/// A separator that appears more than once is digit grouping. A single
+/// separator is a decimal separator unless we are in an integer context and
+/// it is followed by exactly three digits, in which case it is grouping.
+fn split_localized(raw: &str, single_is_decimal: bool) -> Option<(bool, String, String)> {
+    let trimmed = raw.trim();
+    let (negative, body) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+
+    // Spaces and apostrophes are only ever used for grouping
+    let body: String = body
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\'' | '\u{00A0}' | '\u{202F}'))
+        .collect();
+
+    if body.is_empty()
+        || !body
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+    {
+        return None;
+    }
+
+    let last_dot = body.rfind('.');
+    let last_comma = body.rfind(',');
+    let decimal_sep = match (last_dot, last_comma) {
+        (Some(d), Some(c)) => Some(if d > c { '.' } else { ',' }),
+        (Some(_), None) | (None, Some(_)) => {
+            let sep = if last_dot.is_some() { '.' } else { ',' };
+            let count = body.matches(sep).count();
+            let frac_len = body.len() - body.rfind(sep).unwrap() - 1;
+            if count > 1 || (!single_is_decimal && frac_len == 3) {
+                None
+            } else {
+                Some(sep)
+            }
+        }
+        (None, None) => None,
+    };
+
+    let (int_part, frac_part) = match decimal_sep {
+        Some(sep) => {
+            let idx = body.rfind(sep).unwrap();
+            (&body[..idx], &body[idx + 1..])
+        }
+        None => (body.as_str(), ""),
+    };
+
+    let int_digits: String = int_part.chars().filter(|c| c.is_ascii_digit()).collect();
+    if int_digits.is_empty() || frac_part.contains(['.', ',']) {
+        return None;
+    }
+
+    Some((negative, int_digits, frac_part.to_string()))
+}
+
+/// Number of fractional digits in a localized number, or 0 if unparseable
+fn decimal_places(raw: &str, single_is_decimal: bool) -> usize {
+    split_localized(raw, single_is_decimal)
+        .map(|(_, _, frac)| frac.len())
+        .unwrap_or(0)
+}
+
+/// Parse a localized number into a float, treating a single separator as decimal
+pub fn parse_decimal(raw: &str) -> Option<f64> {
+    let (negative, int_digits, frac_digits) = split_localized(raw, true)?;
+    // Canonical Rust float syntax is always C-locale
+    let canonical = if frac_digits.is_empty() {
+        int_digits
+    } else {
+        format!("{}.{}", int_digits, frac_digits)
+    };
+    let value: f64 = canonical.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// Parse a localized number that is expected to be integral (e.g. mV, mA)
+///
+/// Fractional values are rounded half away from zero.
+pub fn parse_integer(raw: &str) -> Option<i64> {
+    let (negative, int_digits, frac_digits) = split_localized(raw, false)?;
+    let value = if frac_digits.is_empty() {
+        int_digits.parse::<i64>().ok()?
+    } else {
+        format!("{}.{}", int_digits, frac_digits)
+            .parse::<f64>()
+            .ok()?
+            .round() as i64
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// A register printed in hex, e.g. `0x02`
+fn register_value(raw: &str) -> Option<u8> {
+    let raw = raw.trim();
+    let digits = raw.strip_prefix("0x").or_else(|| raw.strip_prefix("0X"))?;
+    u8::from_str_radix(digits, 16).ok()
+}
+
+/// `true` for a state worded as one of `on`, `false` for one of `off`
+///
+/// Case and a trailing note in parentheses are ignored; any other wording
+/// is `None` rather than a guess.
+fn state_flag(raw: &str, on: &[&str], off: &[&str]) -> Option<bool> {
+    let state = raw
+        .split('(')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+    if on.contains(&state.as_str()) {
+        Some(true)
+    } else if off.contains(&state.as_str()) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// A firmware build time such as `2025-10-09 11:13:59 UTC`
+fn utc_time(raw: &str) -> Option<DateTime<Utc>> {
+    let time = raw.trim().strip_suffix("UTC")?.trim();
+    NaiveDateTime::parse_from_str(time, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|time| time.and_utc())
+}
+
+/// Standard JSON response wrapper
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JsonResponse {
+    /// [`OUTPUT_SCHEMA_VERSION`] of the build that wrote the envelope
+    pub schema_version: u32,
+    pub timestamp: DateTime<Utc>,
+    pub command: String,
+    pub status: String,
+    pub data: Value,
+    /// The controller reply, stored as `--raw` and `--raw-compress` say
+    pub raw_response: Option<String>,
+    /// Set when `raw_response` is cut short or encoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_response_info: Option<raw::RawResponseInfo>,
+    /// Per-field parser outcomes, with `--explain-parse` or `--verbose`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parse_diagnostics: Option<Vec<diagnostics::ParseDiagnostic>>,
+    /// Boot output received while connecting, on the first envelope only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boot_banner: Option<BootBanner>,
+}
+
+impl JsonResponse {
+    pub fn success(command: &str, data: Value) -> Self {
+        Self {
+            schema_version: OUTPUT_SCHEMA_VERSION,
+            timestamp: Utc::now(),
+            command: command.to_string(),
+            status: "success".to_string(),
+            data,
+            raw_response: None,
+            raw_response_info: None,
+            parse_diagnostics: None,
+            boot_banner: None,
+        }
+    }
+
+    /// Success envelope keeping `raw` as the default `--raw` does
+    #[allow(dead_code)] // Used by tests
+    pub fn success_with_raw(command: &str, data: Value, raw: &str) -> Self {
+        Self::success(command, data).with_raw(raw, &raw::RawOptions::default())
+    }
+
+    /// Store `raw` as the reply under `options`
+    pub fn with_raw(mut self, raw: &str, options: &raw::RawOptions) -> Self {
+        (self.raw_response, self.raw_response_info) = raw::store(raw, options);
+        self
+    }
+
+    /// The stored reply as text, decompressed if need be; only the start
+    /// of the reply when [`Self::raw_truncated`]
+    #[allow(dead_code)] // Used by tests
+    pub fn raw_text(&self) -> Result<Option<String>, PowerCliError> {
+        self.raw_response
+            .as_deref()
+            .map(|stored| raw::decode(stored, self.raw_response_info.as_ref()))
+            .transpose()
+            .map_err(|message| PowerCliError::Json(serde::de::Error::custom(message)))
+    }
+
+    /// Whether `raw_response` holds only the start of the reply
+    #[allow(dead_code)] // Used by tests
+    pub fn raw_truncated(&self) -> bool {
+        self.raw_response_info
+            .as_ref()
+            .is_some_and(|info| info.truncated)
+    }
+
+    #[allow(dead_code)] // Used by tests
+    pub fn error(command: &str, error: &str) -> Self {
+        Self::failure(
+            command,
+            ErrorJson {
+                error: error.to_string(),
+                timeout: None,
+                session: None,
+            },
+        )
+    }
+
+    /// Error envelope, with the session context when it was collected
+    pub fn failure(command: &str, error: ErrorJson) -> Self {
+        Self {
+            schema_version: OUTPUT_SCHEMA_VERSION,
+            timestamp: Utc::now(),
+            command: command.to_string(),
+            status: "error".to_string(),
+            data: serde_json::to_value(error).unwrap_or_default(),
+            raw_response: None,
+            raw_response_info: None,
+            parse_diagnostics: None,
+            boot_banner: None,
+        }
+    }
+}
+
+/// Write `value` as one NDJSON record and flush
+///
+/// The record is compact JSON terminated by exactly one `\n`; newlines inside
+/// strings are escaped by the serializer, so each record is a single line.
+/// The writer is flushed so consumers of a pipe see records as they arrive.
+pub fn write_ndjson<W: Write, T: Serialize>(writer: &mut W, value: &T) -> std::io::Result<()> {
+    serde_json::to_writer(&mut *writer, value)?;
+    writer.write_all(b"\n")?;
+    writer.flush()
+}
+
+/// What a `--format json-lines` record is, in its `type` field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordType {
+    /// One reading of a stream (`monitor`, `battery read --watch`)
+    Sample,
+    /// Something that happened during a stream, e.g. a PMU reset
+    Event,
+    /// How far a long operation has got
+    Progress,
+    /// The end of a stream, or the result of a one-shot command
+    Summary,
+    /// The command failed; always the last record
+    Error,
+}
+
+/// `value` with its `type` set to `record_type`
+pub fn record_value<T: Serialize>(record_type: RecordType, value: &T) -> serde_json::Result<Value> {
+    let mut value = serde_json::to_value(value)?;
+    if let Some(object) = value.as_object_mut() {
+        object.insert("type".to_string(), serde_json::to_value(record_type)?);
+    }
+    Ok(value)
+}
+
+/// Write `value` as one JSON Lines record of `record_type` and flush
+///
+/// [`write_ndjson`] of the object with `type` added.
+pub fn write_record<W: Write, T: Serialize>(
+    writer: &mut W,
+    record_type: RecordType,
+    value: &T,
+) -> std::io::Result<()> {
+    write_ndjson(writer, &record_value(record_type, value)?)
+}
+
+/// Battery data structure for JSON output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatteryJson {
+    pub voltage_mv: Option<u16>,
+    pub current_ma: Option<i16>,
+    pub charge_mah: Option<u16>,
+    pub power_mw: Option<i32>,
+    pub temperature_c: Option<f32>,
+}
+
+/// `pm stats` counters and peripheral states
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PowerStatsJson {
+    pub sleep_cycles: Option<u32>,
+    pub wake_cycles: Option<u32>,
+    pub ltc2959_state: Option<String>,
+    pub nfc_state: Option<String>,
+    pub uart_state: Option<String>,
+    pub uptime_ms: Option<u64>,
+}
+
+/// System information for JSON output
+///
+/// The text fields are the firmware's own wording, for display; scripts
+/// should read the typed fields next to them (`version_info`, `build_type`,
+/// `build_time`, `uptime_ms`), whose keys and values do not change with it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SystemInfoJson {
+    pub board: Option<String>,
+    pub soc: Option<String>,
+    /// Raw version string as reported by the firmware
+    pub version: Option<String>,
+    /// Components of [`Self::version`]
+    #[serde(flatten)]
+    pub version_info: FirmwareVersion,
+    pub build_date: Option<String>,
+    /// [`Self::build_date`] as a time, when the firmware prints it in UTC
+    #[serde(default)]
+    pub build_time: Option<DateTime<Utc>>,
+    pub build_type: Option<BuildType>,
+    pub uptime: Option<String>,
+    /// [`Self::uptime`] in milliseconds
+    #[serde(default)]
+    pub uptime_ms: Option<u64>,
+    /// Unit identity from the NFC EEPROM (`system info --identity`)
+    #[serde(default)]
+    pub serial: Option<String>,
+    #[serde(default)]
+    pub hw_rev: Option<u8>,
+    #[serde(default)]
+    pub manufacture_date: Option<NaiveDate>,
+    /// Whether the version meets `system info --expect-version`; absent
+    /// without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_ok: Option<bool>,
+}
+
+impl SystemInfoJson {
+    /// Fill the identity fields; `None` leaves them null
+    pub fn with_identity(self, identity: Option<&DeviceIdentity>) -> Self {
+        Self {
+            serial: identity.map(|id| id.serial.clone()),
+            hw_rev: identity.map(|id| id.hw_rev),
+            manufacture_date: identity.map(|id| id.manufacture_date),
+            ..self
+        }
+    }
+
+    /// Whether the reported version meets `requirement`
+    ///
+    /// See [`firmware_semver`] for how the version is read; an unknown
+    /// version meets nothing.
+    pub fn meets_version(&self, requirement: &VersionReq) -> bool {
+        self.version
+            .as_deref()
+            .and_then(firmware_semver)
+            .is_some_and(|version| requirement.matches(&version))
+    }
+
+    /// Release policy violations of the reported firmware
+    ///
+    /// With `require_production`, the build must be a clean production build.
+    /// With `min_version`, the firmware semver must be at least that version.
+    pub fn policy_violations(
+        &self,
+        require_production: bool,
+        min_version: Option<&str>,
+    ) -> Result<Vec<String>, PowerCliError> {
+        let mut violations = Vec::new();
+
+        if require_production {
+            match self.build_type {
+                Some(BuildType::Production) => {}
+                Some(other) => violations.push(format!(
+                    "build type is {:?}, production build required",
+                    other
+                )),
+                None => {
+                    violations.push("build type is unknown, production build required".to_string())
+                }
+            }
+            if self.version_info.dirty == Some(true) {
+                violations.push(format!(
+                    "firmware was built from a dirty tree ({})",
+                    self.version.as_deref().unwrap_or("unknown version")
+                ));
+            }
+        }
+
+        if let Some(min_version) = min_version {
+            let minimum =
+                parse_semver(min_version).ok_or_else(|| PowerCliError::InvalidCommand {
+                    command: format!("invalid minimum version '{}'", min_version),
+                })?;
+            match self.version_info.semver.as_deref().and_then(parse_semver) {
+                Some(actual) if actual >= minimum => {}
+                Some(_) => violations.push(format!(
+                    "firmware version {} is older than required {}",
+                    self.version_info.semver.as_deref().unwrap_or_default(),
+                    min_version
+                )),
+                None => violations.push(format!(
+                    "firmware version is unknown, {} or newer required",
+                    min_version
+                )),
+            }
+        }
+
+        Ok(violations)
+    }
+}
+
+/// Firmware build type (`Build Type:` in `system info`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildType {
+    Production,
+    Debug,
+    Dev,
+}
+
+impl BuildType {
+    /// Parse a build type, returning `None` for unrecognised values
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "production" | "prod" | "release" => Some(BuildType::Production),
+            "debug" => Some(BuildType::Debug),
+            "dev" | "development" => Some(BuildType::Dev),
+            _ => None,
+        }
+    }
+}
+
+/// Components of a firmware version string
+///
+/// Current firmware reports `2.2.0-+0fa46fb-dirty.298` (semver, git hash,
+/// dirty marker, build number). Older builds report only some of these, so
+/// every component is optional.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareVersion {
+    pub semver: Option<String>,
+    pub git_hash: Option<String>,
+    /// Unknown when the version carries no git information
+    pub dirty: Option<bool>,
+    pub build_number: Option<u32>,
+}
+
+impl FirmwareVersion {
+    /// Split a version string into its components
+    pub fn parse(raw: &str) -> Self {
+        let mut version = Self::default();
+        let raw = raw.trim();
+
+        let rest = match patterns::SEMVER_PREFIX.captures(raw) {
+            Some(caps) => {
+                version.semver = Some(caps[1].to_string());
+                &raw[caps[0].len()..]
+            }
+            None => raw,
+        };
+
+        let mut dirty = false;
+        for token in rest.split(['-', '+', '.', ' ', '(', ')']) {
+            let lower = token.to_ascii_lowercase();
+            let hex = lower.strip_prefix('g').unwrap_or(&lower);
+            if lower == "dirty" {
+                dirty = true;
+            } else if let Ok(build) = lower.parse::<u32>() {
+                version.build_number = Some(build);
+            } else if (7..=40).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                version.git_hash = Some(hex.to_string());
+            }
+        }
+
+        if dirty {
+            version.dirty = Some(true);
+        } else if version.git_hash.is_some() {
+            version.dirty = Some(false);
+        }
+        version
+    }
+}
+
+/// Parse `MAJOR.MINOR[.PATCH]` (optionally `v`-prefixed) for comparison
+pub fn parse_semver(raw: &str) -> Option<(u64, u64, u64)> {
+    let raw = raw.trim().trim_start_matches(['v', 'V']);
+    let core = raw.split(['-', '+']).next()?;
+    let mut parts = core.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    let patch = match parts.next() {
+        Some(patch) => patch.parse().ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((major, minor, patch))
+}
+
+/// Firmware version string as a semver version, for matching requirements
+///
+/// Build suffixes are dropped: `2.2.0-+0fa46fb-dirty.298` reads as `2.2.0`.
+/// A pre-release tag is kept when it starts with a letter and is not a git
+/// hash or dirty marker, so `2.3.0-rc.1-dirty` reads as `2.3.0-rc.1` and,
+/// as in Cargo, only meets a requirement naming a 2.3.0 pre-release. A
+/// missing patch is 0.
+pub fn firmware_semver(raw: &str) -> Option<Version> {
+    let raw = raw.trim();
+    let core = patterns::SEMVER_PREFIX.captures(raw)?;
+    let (major, minor, patch) = parse_semver(&core[1])?;
+    let mut version = Version::new(major, minor, patch);
+
+    let tag = raw[core[0].len()..]
+        .strip_prefix('-')
+        .and_then(|rest| rest.split(['+', '-', ' ']).next())
+        .unwrap_or_default();
+    let is_build = |tag: &str| {
+        let lower = tag.to_ascii_lowercase();
+        let hex = lower.strip_prefix('g').unwrap_or(&lower);
+        lower.starts_with("dirty")
+            || (7..=40).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit())
+    };
+    if tag.starts_with(|c: char| c.is_ascii_alphabetic()) && !is_build(tag) {
+        version.pre = Prerelease::new(tag).ok()?;
+    }
+    Some(version)
+}
+
+/// GPIO status for JSON output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpioJson {
+    pub port: String,
+    pub pin: u8,
+    pub value: Option<u8>,
+    pub direction: Option<String>,
+    pub state: Option<String>,
+    /// `UP`, `DOWN` or `NONE`, if the firmware reports it
+    #[serde(default)]
+    pub pull: Option<String>,
+    /// The pin as typed and in firmware and schematic form
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub names: Option<PinNames>,
+}
+
+/// RTC status information in JSON format
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RtcStatusJson {
+    pub internal_rtc: InternalRtcJson,
+    pub external_rtc: ExternalRtcJson,
+    pub last_wake_source: Option<String>,
+}
+
+/// Internal RTC information
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InternalRtcJson {
+    pub wake_events: Option<u32>,
+    pub status: Option<String>,
+    pub function: Option<String>,
+}
+
+/// External RTC (PCF2131) information
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExternalRtcJson {
+    pub interrupt_events: Option<u32>,
+    pub status: Option<String>,
+    pub connection: Option<String>,
+    pub i2c_address: Option<String>,
+    pub function: Option<String>,
+    pub interrupt_action: Option<String>,
+}
+
+/// NFC status for JSON output
+///
+/// Each text field, as the firmware words it, has a typed companion for
+/// scripts; a companion is `None` when the wording is not one it knows.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NfcJson {
+    pub status_register: Option<String>,
+    /// [`Self::status_register`] as a number
+    pub status_register_value: Option<u8>,
+    pub rf_field: Option<String>,
+    /// Whether a reader's RF field is present, from [`Self::rf_field`]
+    pub rf_field_present: Option<bool>,
+    pub nfc_active: Option<bool>,
+    pub i2c_ready: Option<bool>,
+    pub eeprom_status: Option<String>,
+    /// Whether the EEPROM is ready, from [`Self::eeprom_status`]
+    pub eeprom_ready: Option<bool>,
+    pub sram_status: Option<String>,
+    /// Whether the SRAM mailbox is idle, from [`Self::sram_status`]
+    pub sram_idle: Option<bool>,
+}
+
+/// NFC SRAM mailbox contents for JSON output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SramJson {
+    pub bytes: usize,
+    /// Contents as hex digits
+    pub data: String,
+}
+
+impl SramJson {
+    pub fn new(data: &[u8]) -> Self {
+        Self {
+            bytes: data.len(),
+            data: data.iter().map(|b| format!("{:02X}", b)).collect(),
+        }
+    }
+}
+
+/// NFC tag identification for JSON output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NfcTagInfo {
+    pub tag_type: String,
+    pub uid: String,
+    pub uid_bytes: Vec<u8>,
+    pub ndef_capable: bool,
+    pub memory_size: Option<u16>,
+}
+
+impl NfcTagInfo {
+    /// UID as colon-separated hex bytes (e.g. `04:AB:CD:EF`)
+    pub fn uid_hex(&self) -> String {
+        self.uid_bytes
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    /// UID bytes interpreted as one big-endian decimal number
+    pub fn uid_decimal(&self) -> String {
+        self.uid_bytes
+            .iter()
+            .fold(0u128, |acc, b| (acc << 8) | *b as u128)
+            .to_string()
+    }
+
+    /// UID as contiguous hex digits (e.g. `04ABCDEF`)
+    pub fn uid_raw(&self) -> String {
+        self.uid_bytes
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect()
+    }
+
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        format!(
+            "   Tag Type: {}\n   UID: {}\n   NDEF: {}\n   Memory: {}",
+            self.tag_type,
+            self.uid,
+            if self.ndef_capable { "Yes" } else { "No" },
+            self.memory_size
+                .map(|size| format!("{} bytes", size))
+                .unwrap_or_else(|| "unknown".to_string())
+        )
+    }
+}
+
+/// LTC2959 data for JSON output
+///
+/// As in [`NfcJson`], the text fields are for display and each has a
+/// typed companion for scripts.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Ltc2959Json {
+    pub voltage_mv: Option<u16>,
+    pub current_ma: Option<i16>,
+    pub charge_mah: Option<u16>,
+    pub power_mw: Option<i32>,
+    pub status_register: Option<String>,
+    /// [`Self::status_register`] as a number
+    pub status_register_value: Option<u8>,
+    pub adc_mode: Option<String>,
+    /// [`Self::adc_mode`] as an [`AdcMode`]
+    pub adc_mode_id: Option<AdcMode>,
+    pub coulomb_counter: Option<String>,
+    /// Whether the coulomb counter runs, from [`Self::coulomb_counter`]
+    pub coulomb_counter_enabled: Option<bool>,
+    pub charge_complete: Option<bool>,
+}
+
+/// Overall outcome of the `pm battery_check` health check, healthiest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BatteryVerdict {
+    Healthy,
+    Degraded,
+    Failed,
+}
+
+impl BatteryVerdict {
+    /// Parse a verdict word, e.g. `HEALTHY`, `Degraded`, `FAIL`
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_lowercase().as_str() {
+            "healthy" | "good" | "ok" | "pass" | "passed" => Some(BatteryVerdict::Healthy),
+            "degraded" | "weak" | "warning" | "marginal" => Some(BatteryVerdict::Degraded),
+            "failed" | "fail" | "bad" | "replace" => Some(BatteryVerdict::Failed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BatteryVerdict::Healthy => "healthy",
+            BatteryVerdict::Degraded => "degraded",
+            BatteryVerdict::Failed => "failed",
+        }
+    }
+
+    /// Process exit code, so manufacturing scripts can gate on the verdict
+    pub fn exit_code(self) -> i32 {
+        match self {
+            BatteryVerdict::Healthy => 0,
+            BatteryVerdict::Degraded => 2,
+            BatteryVerdict::Failed => 3,
+        }
+    }
+}
+
+/// Battery health check (`pm battery_check`) for JSON output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatteryHealthJson {
+    /// Load-test result as printed, e.g. `PASS`
+    pub result: Option<String>,
+    pub internal_resistance_mohm: Option<u32>,
+    pub loaded_voltage_mv: Option<u16>,
+    pub unloaded_voltage_mv: Option<u16>,
+    pub verdict: Option<BatteryVerdict>,
+}
+
+impl BatteryHealthJson {
+    /// Format for human-readable display, omitting fields not reported
+    pub fn format_human(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(mv) = self.unloaded_voltage_mv {
+            lines.push(format!("Unloaded Voltage: {} mV", mv));
+        }
+        if let Some(mv) = self.loaded_voltage_mv {
+            lines.push(format!("Loaded Voltage: {} mV", mv));
+        }
+        if let Some(mohm) = self.internal_resistance_mohm {
+            lines.push(format!("Internal Resistance: {} mOhm", mohm));
+        }
+        if let Some(result) = &self.result {
+            lines.push(format!("Load Test: {}", result));
+        }
+        lines.push(format!(
+            "Verdict: {}",
+            self.verdict
+                .map_or("unknown", |v| v.as_str())
+                .to_uppercase()
+        ));
+        lines.join("\n")
+    }
+}
+
+/// Instantaneous battery measurement for JSON output
+///
+/// Produced either by the one-shot `pm measure` command, which forces a
+/// conversion even while the LTC2959 ADC is in smart-sleep, or from an
+/// `ltc2959 read` readout.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MeasurementJson {
+    pub voltage_mv: Option<u16>,
+    pub current_ma: Option<i16>,
+    /// ADC mode reported alongside the sample
+    pub adc_mode: Option<String>,
+    /// Shell command the sample was taken with
+    pub source: String,
+}
+
+impl From<Ltc2959Json> for MeasurementJson {
+    fn from(ltc: Ltc2959Json) -> Self {
+        Self {
+            voltage_mv: ltc.voltage_mv,
+            current_ma: ltc.current_ma,
+            adc_mode: ltc.adc_mode,
+            source: "ltc2959 read".to_string(),
+        }
+    }
+}
+
+/// One `monitor` sample with the debounced charging state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorSampleJson {
+    #[serde(flatten)]
+    pub measurement: MeasurementJson,
+    /// `None` until a sample with a current reading has been seen
+    pub charging_state: Option<ChargingState>,
+    pub charging: bool,
+    /// When the current charging state was entered
+    pub since: Option<DateTime<Utc>>,
+    /// Charger state read with the sample (`monitor --charger`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charger: Option<ChargerStatus>,
+}
+
+/// End-of-session summary of `monitor`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorSummaryJson {
+    pub samples: u64,
+    pub charging_transitions: u32,
+    pub final_state: Option<ChargingState>,
+    /// PMU resets detected during the session
+    #[serde(default)]
+    pub reboots: u32,
+    /// Serial traffic of the session
+    #[serde(default)]
+    pub link: ConnectionStats,
+    /// Layout of the samples `monitor --follow` read, e.g. `compact`
+    #[serde(default)]
+    pub sample_format: Option<String>,
+    /// Console lines `monitor --follow` could not read as a sample
+    #[serde(default)]
+    pub unparsed_lines: u64,
+    /// Wake rates and time asleep over the session, from the power
+    /// counters read at its start and end
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power: Option<PowerStatsDerived>,
+}
+
+/// One `battery read --watch` sample
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatteryWatchSampleJson {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub battery: BatteryJson,
+    /// Charge as a share of `--capacity`; `None` without it
+    pub soc_percent: Option<f64>,
+}
+
+/// End-of-session summary of `battery read --watch`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatteryWatchSummaryJson {
+    pub samples: u64,
+    pub duration_s: u64,
+    pub voltage_min_mv: Option<u16>,
+    pub voltage_max_mv: Option<u16>,
+    pub voltage_avg_mv: Option<f64>,
+    pub current_min_ma: Option<i16>,
+    pub current_max_ma: Option<i16>,
+    pub current_avg_ma: Option<f64>,
+    /// Change of the coulomb counter between the first and last sample
+    pub charge_delta_mah: Option<i32>,
+}
+
+/// Median, mean and range of one field over `--samples` readings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SampleStatsJson {
+    pub median: f64,
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// `battery read --samples`: every reading and their aggregate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatterySamplesJson {
+    /// Median of each field, the value to compare against thresholds
+    pub median: BatteryJson,
+    pub voltage_mv: Option<SampleStatsJson>,
+    pub current_ma: Option<SampleStatsJson>,
+    pub charge_mah: Option<SampleStatsJson>,
+    pub power_mw: Option<SampleStatsJson>,
+    pub temperature_c: Option<SampleStatsJson>,
+    /// Readings asked for with `--samples`
+    pub requested: u32,
+    /// Readings that failed and were left out
+    pub failed: u32,
+    pub samples: Vec<BatteryWatchSampleJson>,
+}
+
+/// `pm battery-check --samples`: every check and their aggregate
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatteryHealthSamplesJson {
+    /// Median of each field; its verdict decides the exit code
+    pub median: BatteryHealthJson,
+    pub internal_resistance_mohm: Option<SampleStatsJson>,
+    pub loaded_voltage_mv: Option<SampleStatsJson>,
+    pub unloaded_voltage_mv: Option<SampleStatsJson>,
+    /// Checks asked for with `--samples`
+    pub requested: u32,
+    /// Checks that failed and were left out
+    pub failed: u32,
+    pub samples: Vec<BatteryHealthJson>,
+}
+
+/// Power rail defaults (`pm defaults`) for JSON output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RailDefaultsJson {
+    pub pmic: Option<bool>,
+    pub wifi: Option<bool>,
+    pub disp: Option<bool>,
+    /// False when the listing is annotated as unsaved or factory defaults
+    pub saved_in_flash: bool,
+}
+
+/// Result of a set operation (rail, GPIO, NFC or battery monitoring)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateChangeJson {
+    /// False when the target was already in the requested state
+    pub changed: bool,
+    /// Rails switched on first because the rail depends on them
+    /// (`--auto-deps`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enabled_first: Vec<PowerRail>,
+    /// Rail switched, for rail commands
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rail: Option<Rail>,
+}
+
+/// Complete set of power rail defaults, as exported to and imported from a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PowerDefaults {
+    pub pmic: bool,
+    pub wifi: bool,
+    pub disp: bool,
+}
+
+impl TryFrom<RailDefaultsJson> for PowerDefaults {
+    type Error = PowerCliError;
+
+    /// Fails if the listing did not report every rail
+    fn try_from(defaults: RailDefaultsJson) -> Result<Self, Self::Error> {
+        match (defaults.pmic, defaults.wifi, defaults.disp) {
+            (Some(pmic), Some(wifi), Some(disp)) => Ok(Self { pmic, wifi, disp }),
+            _ => Err(PowerCliError::InvalidResponse {
+                response: format!("incomplete power rail defaults: {:?}", defaults),
+            }),
+        }
+    }
+}
+
+impl RailDefaultsJson {
+    /// Describe every rail whose value differs from `expected`
+    pub fn mismatches(&self, expected: &RailDefaultsJson) -> Vec<String> {
+        let rails = [
+            ("PMIC", self.pmic, expected.pmic),
+            ("WiFi", self.wifi, expected.wifi),
+            ("DISP", self.disp, expected.disp),
+        ];
+        let state = |value: Option<bool>| match value {
+            Some(true) => "ON",
+            Some(false) => "OFF",
+            None => "unknown",
+        };
+
+        rails
+            .iter()
+            .filter(|(_, actual, wanted)| wanted.is_some() && actual != wanted)
+            .map(|(name, actual, wanted)| {
+                format!(
+                    "{} default is {}, expected {}",
+                    name,
+                    state(*actual),
+                    state(*wanted)
+                )
+            })
+            .collect()
+    }
+}
+
+/// Parse PMU responses into structured JSON data
+pub struct ResponseParser;
+
+impl ResponseParser {
+    /// Whether a set operation changed anything
+    pub fn parse_state_change(response: &str) -> StateChangeJson {
+        StateChangeJson {
+            changed: classify::classify(response) != ResponseClass::Unchanged,
+            enabled_first: Vec::new(),
+            rail: None,
+        }
+    }
+
+    /// Parse battery/LTC2959 response into JSON
+    ///
+    /// Voltage, current, charge and power are accepted in base units (`V`,
+    /// `A`, `Ah`, `W`), milli-units or micro-units and normalized to the
+    /// milli-units used by [`BatteryJson`], rounded half away from zero (so
+    /// `850 µA` becomes 1 mA). When several lines give the same quantity,
+    /// the finest resolution wins.
+    pub fn parse_battery_response(response: &str) -> BatteryJson {
+        let response = &*progress::collapse(response);
+        let mut battery = BatteryJson {
+            voltage_mv: None,
+            current_ma: None,
+            charge_mah: None,
+            power_mw: None,
+            temperature_c: None,
+        };
+
+        // Parse voltage (e.g., "Voltage: 6088 mV" or "Voltage: 3.85 V")
+        battery.voltage_mv =
+            Self::parse_milli_quantity(response, "voltage_mv", "Voltage", &patterns::VOLTAGE)
+                .and_then(|v| u16::try_from(v).ok());
+
+        // Parse current (e.g., "Current: -170 mA", "Current: -0.17 A" or "Current: 850 µA")
+        battery.current_ma =
+            Self::parse_milli_quantity(response, "current_ma", "Current", &patterns::CURRENT)
+                .and_then(|v| i16::try_from(v).ok());
+
+        // Parse charge (e.g., "Charge: 0 mAh" or "Charge: 2.45 Ah")
+        battery.charge_mah =
+            Self::parse_milli_quantity(response, "charge_mah", "Charge", &patterns::CHARGE)
+                .and_then(|v| u16::try_from(v).ok());
+
+        // Parse power (e.g., "Power: -1040 mW" or "Power: -1.04 W")
+        battery.power_mw =
+            Self::parse_milli_quantity(response, "power_mw", "Power", &patterns::POWER)
+                .and_then(|v| i32::try_from(v).ok());
+
+        // Parse temperature (e.g., "Temperature: 23°C" or "Temperature: 23,5 °C")
+        if let Some(caps) = diagnostics::find(
+            "temperature_c",
+            "Temperature:",
+            &patterns::TEMPERATURE,
+            response,
+        ) {
+            battery.temperature_c = parse_decimal(&caps[1]).map(|v| v as f32);
+        }
+
+        battery
+    }
+
+    /// Find a `Label: <number> <unit>` line and convert it to milli-units
+    ///
+    /// `re` is one of the quantity patterns in [`patterns`] (e.g.
+    /// [`patterns::VOLTAGE`]), which also match the `m`, `µ`/`μ` and `u`
+    /// prefixed units. Base-unit values are read with a
+    /// decimal separator, prefixed values as integers (see [`parse_integer`]).
+    /// The converted value is rounded half away from zero to the nearest
+    /// milli-unit, so `3.8505 V` becomes 3851 mV and `850 µA` becomes 1 mA.
+    ///
+    /// If several lines report the same quantity, the one with the finest
+    /// resolution wins (e.g. `3851 mV` over `3.85 V`, `3.8512 V` over
+    /// `3851 mV`) and a warning is logged when their values disagree.
+    /// The outcome is recorded for `field`; a `Label:` line with other
+    /// units is reported as unparsed.
+    fn parse_milli_quantity(response: &str, field: &str, label: &str, re: &Regex) -> Option<i64> {
+        // (value in milli-units, resolution in milli-units, matched text, line)
+        let mut readings: Vec<(f64, f64, String, &str)> = Vec::new();
+        for caps in re.captures_iter(response) {
+            let (scale, value, frac_digits) = match &caps[2] {
+                "" => {
+                    let value = parse_decimal(&caps[1]);
+                    (1000.0, value, decimal_places(&caps[1], true))
+                }
+                "m" => {
+                    let value = parse_integer(&caps[1]).map(|v| v as f64);
+                    (1.0, value, 0)
+                }
+                _ => {
+                    let value = parse_integer(&caps[1]).map(|v| v as f64);
+                    (0.001, value, 0)
+                }
+            };
+            if let Some(value) = value {
+                let resolution = scale / 10f64.powi(frac_digits as i32);
+                let start = caps.get(0).map_or(0, |m| m.start());
+                readings.push((
+                    value * scale,
+                    resolution,
+                    caps[0].trim().to_string(),
+                    diagnostics::line_at(response, start),
+                ));
+            }
+        }
+
+        let Some(best) = readings.iter().min_by(|a, b| a.1.total_cmp(&b.1)).cloned() else {
+            diagnostics::missed(field, &format!("{}:", label), re, response);
+            return None;
+        };
+        diagnostics::matched(field, re, best.3);
+
+        for (value, resolution, text, _) in &readings {
+            if (value - best.0).abs() > resolution.max(best.1) {
+                warn!(
+                    "Conflicting {} readings '{}' and '{}'; using '{}'",
+                    label.to_lowercase(),
+                    text,
+                    best.2,
+                    best.2
+                );
+            }
+        }
+
+        Some(best.0.round() as i64)
+    }
+
+    /// Capture group 1 of `pattern`, trimmed, recording the outcome for `field`
+    fn find_text(field: &str, label: &str, pattern: &Regex, response: &str) -> Option<String> {
+        diagnostics::find(field, label, pattern, response).map(|caps| caps[1].trim().to_string())
+    }
+
+    /// Capture group 1 of `pattern` in the line labeled `key`
+    ///
+    /// Without such a line the whole response is searched, for firmware
+    /// that prints the label inside another line.
+    fn find_field(
+        sections: &SectionedJson,
+        field: &str,
+        key: &str,
+        pattern: &Regex,
+        response: &str,
+    ) -> Option<String> {
+        if let Some(line) = sections.field(key).map(|f| f.line.as_str()) {
+            if let Some(caps) = pattern.captures(line) {
+                diagnostics::matched(field, pattern, line);
+                return Some(caps[1].trim().to_string());
+            }
+        }
+        Self::find_text(field, &format!("{}:", key), pattern, response)
+    }
+
+    /// Parse a `pm measure` one-shot measurement
+    ///
+    /// Voltage and current use the same unit handling as
+    /// [`Self::parse_battery_response`]. The ADC mode line is kept verbatim,
+    /// e.g. `Smart Sleep (forced conversion)` or `Continuous V/I`.
+    pub fn parse_measurement(response: &str) -> MeasurementJson {
+        let response = &*progress::collapse(response);
+        let adc_mode = Self::find_text("adc_mode", "ADC", &patterns::MEASURE_ADC_MODE, response);
+
+        MeasurementJson {
+            voltage_mv: Self::parse_milli_quantity(
+                response,
+                "voltage_mv",
+                "Voltage",
+                &patterns::VOLTAGE,
+            )
+            .and_then(|v| u16::try_from(v).ok()),
+            current_ma: Self::parse_milli_quantity(
+                response,
+                "current_ma",
+                "Current",
+                &patterns::CURRENT,
+            )
+            .and_then(|v| i16::try_from(v).ok()),
+            adc_mode,
+            source: "pm measure".to_string(),
+        }
+    }
+
+    /// Parse system info response into JSON
+    pub fn parse_system_info(response: &str) -> SystemInfoJson {
+        let response = &*progress::collapse(response);
+        let sections = SectionedJson::parse(response);
+        let text = |field: &str, key: &str, pattern: &Regex| {
+            Self::find_field(&sections, field, key, pattern, response)
+        };
+        // Parse version (e.g., "Version: 2.2.0-+0fa46fb-dirty.298")
+        let version = text("version", "Version", &patterns::VERSION);
+        let build_date = text("build_date", "Build", &patterns::BUILD_DATE);
+        // Parse uptime (e.g., "System Uptime: 0:01:07 (67427 ms)")
+        let uptime = text("uptime", "System Uptime", &patterns::UPTIME);
+
+        SystemInfoJson {
+            // Parse board (e.g., "Board: MCXC143VFM E-Ink Power Controller")
+            board: text("board", "Board", &patterns::BOARD),
+            // Parse SoC (e.g., "SoC: NXP MCXC143VFM (ARM Cortex-M0+)")
+            soc: text("soc", "SoC", &patterns::SOC),
+            version_info: version
+                .as_deref()
+                .map(FirmwareVersion::parse)
+                .unwrap_or_default(),
+            version,
+            // Parse build date (e.g., "Build: 2025-10-09 11:13:59 UTC")
+            build_time: build_date.as_deref().and_then(utc_time),
+            build_date,
+            // Parse build type (e.g., "Build Type: Production")
+            build_type: text("build_type", "Build Type", &patterns::BUILD_TYPE)
+                .and_then(|text| BuildType::parse(&text)),
+            uptime_ms: diagnostics::find(
+                "uptime_ms",
+                "System Uptime",
+                &patterns::UPTIME_MS,
+                response,
+            )
+            .and_then(|caps| caps[1].parse().ok()),
+            uptime,
+            serial: None,
+            hw_rev: None,
+            manufacture_date: None,
+            version_ok: None,
+        }
+    }
+
+    /// Parse `pm stats` response into JSON
+    pub fn parse_pm_stats(response: &str) -> PowerStatsJson {
+        let response = &*progress::collapse(response);
+        let count = |field: &str, label: &str, pattern: &Regex| {
+            Self::find_text(field, label, pattern, response).and_then(|n| n.parse().ok())
+        };
+
+        PowerStatsJson {
+            // e.g. "Sleep cycles: 4"
+            sleep_cycles: count("sleep_cycles", "Sleep cycles", &patterns::PM_SLEEP_CYCLES),
+            // e.g. "Wake cycles: 4"
+            wake_cycles: count("wake_cycles", "Wake cycles", &patterns::PM_WAKE_CYCLES),
+            // e.g. "LTC2959: Smart Sleep"
+            ltc2959_state: Self::find_text(
+                "ltc2959_state",
+                "LTC2959",
+                &patterns::PM_LTC2959_STATE,
+                response,
+            ),
+            nfc_state: Self::find_text("nfc_state", "NFC", &patterns::PM_NFC_STATE, response),
+            uart_state: Self::find_text("uart_state", "UART", &patterns::PM_UART_STATE, response),
+            // e.g. "Uptime: 0:01:05 (65000 ms)"
+            uptime_ms: diagnostics::find("uptime_ms", "Uptime", &patterns::UPTIME_MS, response)
+                .and_then(|caps| caps[1].parse().ok()),
+        }
+    }
+
+    /// Parse NFC status response into JSON
+    pub fn parse_nfc_status(response: &str) -> NfcJson {
+        let response = &*progress::collapse(response);
+        let sections = SectionedJson::parse(response);
+        let text = |field: &str, key: &str, pattern: &Regex| {
+            Self::find_field(&sections, field, key, pattern, response)
+        };
+        let yes_no = |field: &str, key: &str, pattern: &Regex| {
+            text(field, key, pattern).map(|value| value == "YES")
+        };
+
+        // Parse status register (e.g., "NTA5332 Status: 0x02")
+        let status_register = text(
+            "status_register",
+            "NTA5332 Status",
+            &patterns::NFC_STATUS_REGISTER,
+        );
+        // Parse RF field (e.g., "RF Field: Absent")
+        let rf_field = text("rf_field", "RF Field", &patterns::RF_FIELD);
+        // Parse EEPROM status (e.g., "EEPROM: Ready")
+        let eeprom_status = text("eeprom_status", "EEPROM", &patterns::EEPROM);
+        // Parse SRAM status (e.g., "SRAM: Idle")
+        let sram_status = text("sram_status", "SRAM", &patterns::SRAM);
+
+        NfcJson {
+            status_register_value: status_register.as_deref().and_then(register_value),
+            status_register,
+            rf_field_present: rf_field
+                .as_deref()
+                .and_then(|field| state_flag(field, &["present", "detected"], &["absent", "none"])),
+            rf_field,
+            // Parse NFC active (e.g., "NFC Active: NO")
+            nfc_active: yes_no("nfc_active", "NFC Active", &patterns::NFC_ACTIVE),
+            // Parse I2C ready (e.g., "I2C Ready: NO")
+            i2c_ready: yes_no("i2c_ready", "I2C Ready", &patterns::I2C_READY),
+            eeprom_ready: eeprom_status
+                .as_deref()
+                .and_then(|status| state_flag(status, &["ready"], &["busy", "not ready", "error"])),
+            eeprom_status,
+            sram_idle: sram_status
+                .as_deref()
+                .and_then(|status| state_flag(status, &["idle"], &["busy", "active", "error"])),
+            sram_status,
+        }
+    }
+
+    /// Parse `nfc tag_info` response into JSON
+    ///
+    /// Returns `None` when the response does not identify a tag (no tag in
+    /// the field). UIDs are accepted with `:`, `-` or space separators, or as
+    /// contiguous hex digits.
+    pub fn parse_nfc_tag_info(response: &str) -> Option<NfcTagInfo> {
+        let response = &*progress::collapse(response);
+        let tag_type = Self::find_text("tag_type", "Tag Type", &patterns::TAG_TYPE, response)?;
+
+        let uid_text = Self::find_text("uid", "UID", &patterns::TAG_UID, response)?;
+        let digits: String = uid_text.chars().filter(|c| c.is_ascii_hexdigit()).collect();
+        let uid_bytes = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .ok()?;
+
+        let ndef_capable =
+            diagnostics::find("ndef_capable", "NDEF", &patterns::NDEF_CAPABLE, response).is_some();
+
+        let memory_size = diagnostics::find(
+            "memory_size",
+            "Memory",
+            &patterns::TAG_MEMORY_SIZE,
+            response,
+        )
+        .and_then(|caps| parse_integer(&caps[1]))
+        .and_then(|v| u16::try_from(v).ok());
+
+        let mut info = NfcTagInfo {
+            tag_type,
+            uid: String::new(),
+            uid_bytes,
+            ndef_capable,
+            memory_size,
+        };
+        info.uid = info.uid_hex();
+        Some(info)
+    }
+
+    /// Parse LTC2959 status response into JSON
+    pub fn parse_ltc2959_status(response: &str) -> Ltc2959Json {
+        let response = &*progress::collapse(response);
+        let sections = SectionedJson::parse(response);
+        let text = |field: &str, key: &str, pattern: &Regex| {
+            Self::find_field(&sections, field, key, pattern, response)
+        };
+        // Also parse any voltage/current/charge data if present
+        let battery_data = Self::parse_battery_response(response);
+
+        // Parse status register (e.g., "LTC2959 Status Register: 0x01")
+        let status_register = text(
+            "status_register",
+            "LTC2959 Status Register",
+            &patterns::LTC2959_STATUS_REGISTER,
+        );
+        // Parse ADC mode (e.g., "ADC Mode: Smart Sleep")
+        let adc_mode = text("adc_mode", "ADC Mode", &patterns::LTC2959_ADC_MODE);
+        // Parse coulomb counter (e.g., "Coulomb Counter: Disabled")
+        let coulomb_counter = text(
+            "coulomb_counter",
+            "Coulomb Counter",
+            &patterns::COULOMB_COUNTER,
+        );
+
+        Ltc2959Json {
+            voltage_mv: battery_data.voltage_mv,
+            current_ma: battery_data.current_ma,
+            charge_mah: battery_data.charge_mah,
+            power_mw: battery_data.power_mw,
+            status_register_value: status_register.as_deref().and_then(register_value),
+            status_register,
+            adc_mode_id: adc_mode.as_deref().and_then(AdcMode::from_name),
+            adc_mode,
+            coulomb_counter_enabled: coulomb_counter
+                .as_deref()
+                .and_then(|counter| state_flag(counter, &["enabled", "on"], &["disabled", "off"])),
+            coulomb_counter,
+            // Parse charge complete flag (e.g., "Charge Complete: NO")
+            charge_complete: text(
+                "charge_complete",
+                "Charge Complete",
+                &patterns::CHARGE_COMPLETE,
+            )
+            .map(|value| matches!(value.to_lowercase().as_str(), "yes" | "true")),
+        }
+    }
+
+    /// Parse a `pm battery_check` health check
+    ///
+    /// Older firmware prints only the verdict (e.g. `Battery: HEALTHY`), in
+    /// which case every other field is `None`. Without a `Verdict:` line the
+    /// last word of the response is tried as the verdict. Resistance may be
+    /// given in `Ω`/`Ohm` or `mΩ`/`mOhm`.
+    pub fn parse_battery_health(response: &str) -> BatteryHealthJson {
+        let response = &*progress::collapse(response);
+        let result = Self::find_text("result", "Result", &patterns::HEALTH_RESULT, response);
+
+        let internal_resistance_mohm = Self::parse_milli_quantity(
+            response,
+            "internal_resistance_mohm",
+            "Internal Resistance",
+            &patterns::INTERNAL_RESISTANCE,
+        )
+        .or_else(|| {
+            Self::parse_milli_quantity(
+                response,
+                "internal_resistance_mohm",
+                "Internal Resistance",
+                &patterns::INTERNAL_RESISTANCE_OHM,
+            )
+        })
+        .and_then(|v| u32::try_from(v).ok());
+
+        // "Loaded Voltage:" does not match inside "Unloaded Voltage:" (case)
+        let loaded_voltage_mv = Self::parse_milli_quantity(
+            response,
+            "loaded_voltage_mv",
+            "Loaded Voltage",
+            &patterns::LOADED_VOLTAGE,
+        )
+        .and_then(|v| u16::try_from(v).ok());
+        let unloaded_voltage_mv = Self::parse_milli_quantity(
+            response,
+            "unloaded_voltage_mv",
+            "Unloaded Voltage",
+            &patterns::UNLOADED_VOLTAGE,
+        )
+        .and_then(|v| u16::try_from(v).ok());
+
+        let verdict_re: &Regex = &patterns::HEALTH_VERDICT;
+        let verdict = verdict_re
+            .captures_iter(response)
+            .find_map(|caps| {
+                let verdict = BatteryVerdict::parse(&caps[1])?;
+                diagnostics::matched("verdict", verdict_re, caps[0].trim());
+                Some(verdict)
+            })
+            .or_else(|| {
+                diagnostics::missed("verdict", "Verdict", verdict_re, response);
+                response
+                    .split_whitespace()
+                    .last()
+                    .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+                    .and_then(BatteryVerdict::parse)
+            });
+
+        BatteryHealthJson {
+            result,
+            internal_resistance_mohm,
+            loaded_voltage_mv,
+            unloaded_voltage_mv,
+            verdict,
+        }
+    }
+
+    /// Parse `pm defaults` listing into JSON
+    ///
+    /// Rails may be listed in any order and under their signal names
+    /// (e.g. `PMIC_EN: ON`, `WiFi: off`, `Display: 1`). A listing annotated
+    /// with "not saved", "unsaved" or "factory" is reported as not saved in flash.
+    pub fn parse_rail_defaults(response: &str) -> RailDefaultsJson {
+        let response = &*progress::collapse(response);
+        let rail_state = |field: &str, label: &str, re: &Regex| {
+            diagnostics::find(field, label, re, response).map(|caps| {
+                matches!(
+                    caps[1].to_lowercase().as_str(),
+                    "on" | "enabled" | "high" | "1"
+                )
+            })
+        };
+
+        let lower = response.to_lowercase();
+        let unsaved = ["not saved", "unsaved", "not stored", "factory"]
+            .iter()
+            .any(|marker| lower.contains(marker));
+        let saved = ["saved", "stored", "flash"]
+            .iter()
+            .any(|marker| lower.contains(marker));
+
+        RailDefaultsJson {
+            pmic: rail_state("pmic", "pmic", &patterns::RAIL_PMIC),
+            wifi: rail_state("wifi", "wifi", &patterns::RAIL_WIFI),
+            disp: rail_state("disp", "disp", &patterns::RAIL_DISP),
+            saved_in_flash: saved && !unsaved,
+        }
+    }
+
+    /// Whether a `pm <rail> status` reply (e.g. `PMIC: ON`) shows the rail
+    /// switched on; `None` if it shows no state
+    pub fn parse_rail_state(response: &str) -> Option<bool> {
+        let response = &*progress::collapse(response);
+        let caps = patterns::RAIL_STATE.captures(response)?;
+        Some(matches!(
+            caps[1].to_lowercase().as_str(),
+            "on" | "enabled" | "high" | "1"
+        ))
+    }
+
+    /// Parse GPIO response into JSON
+    pub fn parse_gpio_response(response: &str, port: &str, pin: u8) -> GpioJson {
+        let response = &*progress::collapse(response);
+        let mut gpio = GpioJson {
+            port: port.to_string(),
+            pin,
+            value: None,
+            direction: None,
+            state: None,
+            pull: None,
+            names: None,
+        };
+
+        // Parse GPIO value (e.g., "GPIO A0: 1" or "Pin value: 0")
+        if let Some(caps) = diagnostics::find("value", "GPIO", &patterns::GPIO_VALUE, response) {
+            if let Ok(value) = caps[1].parse::<u8>() {
+                gpio.value = Some(value);
+            }
+        }
+
+        // Parse direction/state information if present
+        if response.contains("INPUT") {
+            gpio.direction = Some("INPUT".to_string());
+        } else if response.contains("OUTPUT") {
+            gpio.direction = Some("OUTPUT".to_string());
+        }
+
+        if response.contains("HIGH") {
+            gpio.state = Some("HIGH".to_string());
+        } else if response.contains("LOW") {
+            gpio.state = Some("LOW".to_string());
+        }
+
+        // Parse pull resistor (e.g., "PULL-UP", "PULLDOWN" or "NOPULL")
+        if let Some(caps) = diagnostics::find("pull", "pull", &patterns::GPIO_PULL, response) {
+            gpio.pull = Some(match caps.get(1) {
+                Some(direction) => direction.as_str().to_ascii_uppercase(),
+                None => "NONE".to_string(),
+            });
+        }
+
+        gpio
+    }
+
+    /// Parse RTC status response into JSON
+    pub fn parse_rtc_status(response: &str) -> RtcStatusJson {
+        let response = &*progress::collapse(response);
+        let mut rtc = RtcStatusJson {
+            internal_rtc: InternalRtcJson {
+                wake_events: None,
+                status: None,
+                function: Some("Periodic maintenance and battery monitoring".to_string()),
+            },
+            external_rtc: ExternalRtcJson {
+                interrupt_events: None,
+                status: None,
+                connection: Some("INTB# → PTC5 (LLWU_P9) - Active LOW".to_string()),
+                i2c_address: Some("0x53".to_string()),
+                function: Some("Alarms, timers, watchdog, timestamps".to_string()),
+                interrupt_action: None,
+            },
+            last_wake_source: None,
+        };
+
+        // Parse internal RTC wake events
+        if let Some(caps) = diagnostics::find(
+            "internal_rtc.wake_events",
+            "Wake events",
+            &patterns::INTERNAL_RTC_WAKE_EVENTS,
+            response,
+        ) {
+            rtc.internal_rtc.wake_events = Some(
+                parse_integer(&caps[1])
+                    .and_then(|v| u32::try_from(v).ok())
+                    .unwrap_or(0),
+            );
+        }
+
+        // Parse external RTC interrupt events
+        if let Some(caps) = diagnostics::find(
+            "external_rtc.interrupt_events",
+            "Interrupt events",
+            &patterns::EXTERNAL_RTC_INTERRUPT_EVENTS,
+            response,
+        ) {
+            rtc.external_rtc.interrupt_events = Some(
+                parse_integer(&caps[1])
+                    .and_then(|v| u32::try_from(v).ok())
+                    .unwrap_or(0),
+            );
+        }
+
+        // Parse RTC status (e.g., "Internal RTC (LPTMR) Status: Running, Wake events: 12")
+        rtc.internal_rtc.status = Self::find_text(
+            "internal_rtc.status",
+            "Internal RTC",
+            &patterns::INTERNAL_RTC_STATUS,
+            response,
+        );
+        rtc.external_rtc.status = Self::find_text(
+            "external_rtc.status",
+            "External RTC",
+            &patterns::EXTERNAL_RTC_STATUS,
+            response,
+        );
+
+        // Parse interrupt action
+        rtc.external_rtc.interrupt_action = Self::find_text(
+            "external_rtc.interrupt_action",
+            "Interrupt Action",
+            &patterns::INTERRUPT_ACTION,
+            response,
+        );
+
+        // Parse last wake source
+        rtc.last_wake_source = Self::find_text(
+            "last_wake_source",
+            "Last Wake Source",
+            &patterns::LAST_WAKE_SOURCE,
+            response,
+        );
+
+        rtc
+    }
+}
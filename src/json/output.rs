@@ -0,0 +1,475 @@
+/*
+ * E-ink Power CLI - Typed JSON Output
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Typed view of the `data` field of [`JsonResponse`] envelopes
+//!
+//! Each command handler names the parser for its controller reply with a
+//! [`ParsedPayload`]. Reading an envelope back, [`parse_output`] only has
+//! the command name; [`OutputKind`] maps it to the struct printed for it.
+
+use super::coverage::CoverageReport;
+use super::{
+    progress, BatteryHealthJson, BatteryJson, BatteryWatchSampleJson, BatteryWatchSummaryJson,
+    GpioJson, JsonResponse, Ltc2959Json, MeasurementJson, MonitorSampleJson, MonitorSummaryJson,
+    NfcJson, NfcTagInfo, PowerStatsJson, RailDefaultsJson, ResponseParser, RtcStatusJson,
+    SectionedJson, SramJson, StateChangeJson, SystemInfoJson,
+};
+use crate::audit::PowerAudit;
+use crate::battery_log::ExportReport;
+use crate::error::report::SessionContext;
+use crate::error::PowerCliError;
+use crate::firmware::dfu::{DfuCancelReport, DfuModeReport};
+use crate::firmware::slots::FirmwareInfo;
+use crate::firmware::FirmwareImageInfo;
+use crate::fleet::{FleetDiff, FleetInventory};
+use crate::hil::HilReport;
+use crate::history::HistoryEntry;
+use crate::macros::{MacroListing, MacroReport};
+use crate::power::coulomb::ChargeResetReport;
+use crate::power::factory_reset::FactoryResetReport;
+use crate::power::gpio::{GpioConfigReport, GpioScriptReport};
+use crate::power::identity::DeviceIdentity;
+use crate::power::passthrough::{TransferProgress, TransferReport};
+use crate::power::reboot::RebootEvent;
+use crate::power::rtc::RtcCalibration;
+use crate::power::stats::PowerStatsReport;
+use crate::power::timeref::TimeRefReport;
+use crate::power::wake::{SleepReport, WakeMask};
+use crate::schedule::ScheduledCommand;
+use crate::serial::{BaudChange, ConnectionStats, LatencyStats};
+use crate::setup::SetupReport;
+use crate::snapshot::Snapshot;
+use crate::update::UpdateReport;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Version of the envelope and `data` layouts written by this build
+///
+/// Bump when a field is renamed or removed, or its meaning changes.
+pub const OUTPUT_SCHEMA_VERSION: u32 = 3;
+
+/// `data` of `system verify`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SystemVerifyJson {
+    pub passed: bool,
+    pub violations: Vec<String>,
+    pub system: SystemInfoJson,
+}
+
+/// `data` of `rtc get`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RtcCounterJson {
+    pub counter: Option<u32>,
+}
+
+/// `data` of an error envelope
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorJson {
+    pub error: String,
+    /// What arrived before the command timed out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<TimeoutJson>,
+    /// Settings and traffic of the failed session (`--bug-report`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<Box<SessionContext>>,
+}
+
+/// `timeout` of an error envelope: what arrived before the command timed out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeoutJson {
+    pub timeout_s: u64,
+    /// 0 when nothing at all arrived
+    pub bytes_received: usize,
+    /// Start of the partial reply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial: Option<String>,
+}
+
+impl TimeoutJson {
+    /// Details of `error` if it is a [`PowerCliError::Timeout`]
+    pub fn of(error: &PowerCliError) -> Option<Self> {
+        match error {
+            PowerCliError::Timeout {
+                timeout,
+                bytes_received,
+                partial,
+            } => Some(Self {
+                timeout_s: *timeout,
+                bytes_received: *bytes_received,
+                partial: partial.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Output struct used for a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputKind {
+    Measurement,
+    MonitorSample,
+    MonitorSummary,
+    MonitorReboot,
+    RailDefaults,
+    Battery,
+    BatteryWatch,
+    BatteryWatchSummary,
+    BatteryHealth,
+    SystemInfo,
+    SystemVerify,
+    TimeRef,
+    BaudChange,
+    FactoryReset,
+    ChargeReset,
+    DfuMode,
+    DfuCancel,
+    Nfc,
+    NfcTag,
+    NfcSram,
+    NfcTransfer,
+    NfcTransferProgress,
+    Ltc2959,
+    PowerStats,
+    Gpio,
+    GpioConfig,
+    GpioScript,
+    RtcCounter,
+    RtcStatus,
+    RtcCalibration,
+    Latency,
+    Sleep,
+    WakeSources,
+    FirmwareImage,
+    FirmwareInfo,
+    History,
+    LogExport,
+    Identity,
+    Setup,
+    LinkStats,
+    Snapshot,
+    PowerAudit,
+    Schedule,
+    ScheduleList,
+    FleetScan,
+    FleetDiff,
+    Macro,
+    MacroList,
+    ParseCheck,
+    SelfUpdate,
+    HilTest,
+    PowerStatsReport,
+    StateChange,
+    /// Ad-hoc object without a dedicated struct (e.g. `state show`)
+    Untyped,
+    /// Controller response without a dedicated struct, split into sections
+    Sectioned,
+}
+
+impl OutputKind {
+    /// Output struct for the command name recorded in the envelope
+    pub fn for_command(command: &str) -> Self {
+        match command {
+            "pm measure" => Self::Measurement,
+            "monitor" => Self::MonitorSample,
+            "monitor summary" => Self::MonitorSummary,
+            "monitor reboot" => Self::MonitorReboot,
+            "pm battery_check" => Self::BatteryHealth,
+            "battery watch" => Self::BatteryWatch,
+            "battery watch summary" => Self::BatteryWatchSummary,
+            "system verify" => Self::SystemVerify,
+            "system time-ref" => Self::TimeRef,
+            "system set-baud" => Self::BaudChange,
+            "system factory-reset" => Self::FactoryReset,
+            "power coulomb reset" | "ltc2959 zero-charge" => Self::ChargeReset,
+            "system dfu-mode" => Self::DfuMode,
+            "system dfu-mode cancel" => Self::DfuCancel,
+            "nfc tag" => Self::NfcTag,
+            "nfc sram-read" | "nfc sram-write" => Self::NfcSram,
+            "nfc transfer" => Self::NfcTransfer,
+            "nfc transfer progress" => Self::NfcTransferProgress,
+            "gpio config" => Self::GpioConfig,
+            "gpio script" => Self::GpioScript,
+            "rtc get" => Self::RtcCounter,
+            "rtc calibrate" | "rtc calibration" => Self::RtcCalibration,
+            "latency" => Self::Latency,
+            "pm sleep" => Self::Sleep,
+            "pm wake-sources" => Self::WakeSources,
+            "firmware analyze" => Self::FirmwareImage,
+            "firmware info" => Self::FirmwareInfo,
+            "history" => Self::History,
+            "log export" => Self::LogExport,
+            "identity show" | "identity write" => Self::Identity,
+            "setup" => Self::Setup,
+            "stats" | "stats reset" => Self::LinkStats,
+            "snapshot" => Self::Snapshot,
+            "power-audit" => Self::PowerAudit,
+            "schedule at" | "schedule cancel" => Self::Schedule,
+            "schedule list" => Self::ScheduleList,
+            "fleet scan" => Self::FleetScan,
+            "fleet diff" => Self::FleetDiff,
+            "run" => Self::Macro,
+            "run list" => Self::MacroList,
+            "parse-check" => Self::ParseCheck,
+            "self-update" => Self::SelfUpdate,
+            "hil-test" => Self::HilTest,
+            "power stats" => Self::PowerStatsReport,
+            "power pmic" | "power wifi" | "power display" | "pm pmic" | "pm wifi"
+            | "pm display" | "pm imx93" | "pm all" | "gpio set" | "nfc enable" | "nfc disable"
+            | "battery enable" | "battery disable" | "ltc2959 enable" | "ltc2959 disable" => {
+                Self::StateChange
+            }
+            "state show" | "examples" | "migrations" | "ltc2959 read summary" => Self::Untyped,
+            "pm defaults"
+            | "pm defaults show"
+            | "pm defaults save"
+            | "pm defaults import"
+            | "pm defaults pmic"
+            | "pm defaults wifi"
+            | "pm defaults display" => Self::RailDefaults,
+            "battery read" | "ltc2959 read" | "power coulomb" => Self::Battery,
+            "version" | "system info" | "system uptime" => Self::SystemInfo,
+            "nfc status" => Self::Nfc,
+            "battery status" | "ltc2959 status" => Self::Ltc2959,
+            "pm stats" => Self::PowerStats,
+            "gpio get" => Self::Gpio,
+            "rtc status" | "rtc config" | "rtc show" => Self::RtcStatus,
+            _ => Self::Sectioned,
+        }
+    }
+}
+
+/// Parser a command handler picks for its controller reply
+///
+/// Chosen per command with `Commands::payload`, never guessed from the
+/// command name, so `battery status` (an `ltc2959 status` reply) is not
+/// read as a battery measurement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsedPayload {
+    /// `ltc2959 read` measurements
+    Battery,
+    /// `system info` fields
+    System,
+    Nfc,
+    Ltc2959,
+    /// `gpio get` of the pin the command named
+    Gpio {
+        port: String,
+        pin: u8,
+    },
+    Rtc,
+    /// `rtc get`: a bare counter value
+    RtcCounter,
+    PowerStats,
+    Measurement,
+    RailDefaults,
+    StateChange,
+    /// No parser; the reply is kept as sections of lines. Also for commands
+    /// whose output the handler builds itself.
+    Raw,
+}
+
+/// Typed `data` of a [`JsonResponse`]
+///
+/// Serializes to exactly the object the variant holds.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum CommandOutput {
+    Measurement(MeasurementJson),
+    MonitorSample(MonitorSampleJson),
+    MonitorSummary(MonitorSummaryJson),
+    MonitorReboot(RebootEvent),
+    RailDefaults(RailDefaultsJson),
+    Battery(BatteryJson),
+    BatteryWatch(BatteryWatchSampleJson),
+    BatteryWatchSummary(BatteryWatchSummaryJson),
+    BatteryHealth(BatteryHealthJson),
+    SystemInfo(SystemInfoJson),
+    SystemVerify(SystemVerifyJson),
+    TimeRef(TimeRefReport),
+    BaudChange(BaudChange),
+    FactoryReset(FactoryResetReport),
+    ChargeReset(ChargeResetReport),
+    DfuMode(DfuModeReport),
+    DfuCancel(DfuCancelReport),
+    Nfc(NfcJson),
+    NfcTag(NfcTagInfo),
+    NfcSram(SramJson),
+    NfcTransfer(TransferReport),
+    NfcTransferProgress(TransferProgress),
+    Ltc2959(Ltc2959Json),
+    PowerStats(PowerStatsJson),
+    Gpio(GpioJson),
+    GpioConfig(GpioConfigReport),
+    GpioScript(GpioScriptReport),
+    RtcCounter(RtcCounterJson),
+    RtcStatus(RtcStatusJson),
+    RtcCalibration(RtcCalibration),
+    Latency(LatencyStats),
+    Sleep(SleepReport),
+    /// `None` if the firmware did not report its wake configuration
+    WakeSources(Option<WakeMask>),
+    FirmwareImage(FirmwareImageInfo),
+    FirmwareInfo(FirmwareInfo),
+    History(Vec<HistoryEntry>),
+    LogExport(ExportReport),
+    /// `None` if the identity block is unprogrammed
+    Identity(Option<DeviceIdentity>),
+    Setup(SetupReport),
+    LinkStats(ConnectionStats),
+    Snapshot(Box<Snapshot>),
+    PowerAudit(Box<PowerAudit>),
+    Schedule(ScheduledCommand),
+    ScheduleList(Vec<ScheduledCommand>),
+    FleetScan(FleetInventory),
+    FleetDiff(FleetDiff),
+    Macro(MacroReport),
+    MacroList(Vec<MacroListing>),
+    ParseCheck(CoverageReport),
+    SelfUpdate(UpdateReport),
+    HilTest(HilReport),
+    PowerStatsReport(PowerStatsReport),
+    StateChange(StateChangeJson),
+    Untyped(Value),
+    Sectioned(SectionedJson),
+    Error(ErrorJson),
+}
+
+impl CommandOutput {
+    /// Parse a raw controller response with the parser the handler chose
+    pub fn from_response(payload: &ParsedPayload, response: &str) -> Self {
+        match payload {
+            ParsedPayload::Measurement => {
+                Self::Measurement(ResponseParser::parse_measurement(response))
+            }
+            ParsedPayload::RailDefaults => {
+                Self::RailDefaults(ResponseParser::parse_rail_defaults(response))
+            }
+            ParsedPayload::Battery => {
+                Self::Battery(ResponseParser::parse_battery_response(response))
+            }
+            ParsedPayload::System => Self::SystemInfo(ResponseParser::parse_system_info(response)),
+            ParsedPayload::Nfc => Self::Nfc(ResponseParser::parse_nfc_status(response)),
+            ParsedPayload::Ltc2959 => Self::Ltc2959(ResponseParser::parse_ltc2959_status(response)),
+            ParsedPayload::PowerStats => Self::PowerStats(ResponseParser::parse_pm_stats(response)),
+            ParsedPayload::Gpio { port, pin } => {
+                Self::Gpio(ResponseParser::parse_gpio_response(response, port, *pin))
+            }
+            ParsedPayload::RtcCounter => Self::RtcCounter(RtcCounterJson {
+                counter: progress::collapse(response).trim().parse::<u32>().ok(),
+            }),
+            ParsedPayload::Rtc => Self::RtcStatus(ResponseParser::parse_rtc_status(response)),
+            ParsedPayload::StateChange => {
+                Self::StateChange(ResponseParser::parse_state_change(response))
+            }
+            ParsedPayload::Raw => Self::Sectioned(SectionedJson::parse(response)),
+        }
+    }
+
+    /// Deserialize `data` as the struct used for `command`
+    pub fn from_data(command: &str, data: Value) -> Result<Self, PowerCliError> {
+        fn typed<T: DeserializeOwned>(
+            data: Value,
+            wrap: fn(T) -> CommandOutput,
+        ) -> Result<CommandOutput, PowerCliError> {
+            Ok(wrap(serde_json::from_value(data)?))
+        }
+
+        // The generic path writes this shape for any command it cannot parse
+        if data
+            .as_object()
+            .is_some_and(|object| object.len() == 1 && object.contains_key("sections"))
+        {
+            return typed(data, Self::Sectioned);
+        }
+
+        match OutputKind::for_command(command) {
+            OutputKind::Measurement => typed(data, Self::Measurement),
+            OutputKind::MonitorSample => typed(data, Self::MonitorSample),
+            OutputKind::MonitorSummary => typed(data, Self::MonitorSummary),
+            OutputKind::MonitorReboot => typed(data, Self::MonitorReboot),
+            OutputKind::RailDefaults => typed(data, Self::RailDefaults),
+            OutputKind::Battery => typed(data, Self::Battery),
+            OutputKind::BatteryWatch => typed(data, Self::BatteryWatch),
+            OutputKind::BatteryWatchSummary => typed(data, Self::BatteryWatchSummary),
+            OutputKind::BatteryHealth => typed(data, Self::BatteryHealth),
+            OutputKind::SystemInfo => typed(data, Self::SystemInfo),
+            OutputKind::SystemVerify => typed(data, Self::SystemVerify),
+            OutputKind::TimeRef => typed(data, Self::TimeRef),
+            OutputKind::BaudChange => typed(data, Self::BaudChange),
+            OutputKind::FactoryReset => typed(data, Self::FactoryReset),
+            OutputKind::ChargeReset => typed(data, Self::ChargeReset),
+            OutputKind::DfuMode => typed(data, Self::DfuMode),
+            OutputKind::DfuCancel => typed(data, Self::DfuCancel),
+            OutputKind::Nfc => typed(data, Self::Nfc),
+            OutputKind::NfcTag => typed(data, Self::NfcTag),
+            OutputKind::NfcSram => typed(data, Self::NfcSram),
+            OutputKind::NfcTransfer => typed(data, Self::NfcTransfer),
+            OutputKind::NfcTransferProgress => typed(data, Self::NfcTransferProgress),
+            OutputKind::Ltc2959 => typed(data, Self::Ltc2959),
+            OutputKind::PowerStats => typed(data, Self::PowerStats),
+            OutputKind::Gpio => typed(data, Self::Gpio),
+            OutputKind::GpioConfig => typed(data, Self::GpioConfig),
+            OutputKind::GpioScript => typed(data, Self::GpioScript),
+            OutputKind::RtcCounter => typed(data, Self::RtcCounter),
+            OutputKind::RtcStatus => typed(data, Self::RtcStatus),
+            OutputKind::RtcCalibration => typed(data, Self::RtcCalibration),
+            OutputKind::Latency => typed(data, Self::Latency),
+            OutputKind::Sleep => typed(data, Self::Sleep),
+            OutputKind::WakeSources => typed(data, Self::WakeSources),
+            OutputKind::FirmwareImage => typed(data, Self::FirmwareImage),
+            OutputKind::FirmwareInfo => typed(data, Self::FirmwareInfo),
+            OutputKind::History => typed(data, Self::History),
+            OutputKind::LogExport => typed(data, Self::LogExport),
+            OutputKind::Identity => typed(data, Self::Identity),
+            OutputKind::Setup => typed(data, Self::Setup),
+            OutputKind::LinkStats => typed(data, Self::LinkStats),
+            OutputKind::Snapshot => typed(data, |snapshot| Self::Snapshot(Box::new(snapshot))),
+            OutputKind::PowerAudit => typed(data, |audit| Self::PowerAudit(Box::new(audit))),
+            OutputKind::Schedule => typed(data, Self::Schedule),
+            OutputKind::ScheduleList => typed(data, Self::ScheduleList),
+            OutputKind::FleetScan => typed(data, Self::FleetScan),
+            OutputKind::FleetDiff => typed(data, Self::FleetDiff),
+            OutputKind::Macro => typed(data, Self::Macro),
+            OutputKind::MacroList => typed(data, Self::MacroList),
+            OutputKind::ParseCheck => typed(data, Self::ParseCheck),
+            OutputKind::SelfUpdate => typed(data, Self::SelfUpdate),
+            OutputKind::HilTest => typed(data, Self::HilTest),
+            OutputKind::PowerStatsReport => typed(data, Self::PowerStatsReport),
+            OutputKind::StateChange => typed(data, Self::StateChange),
+            OutputKind::Untyped => Ok(Self::Untyped(data)),
+            OutputKind::Sectioned => typed(data, Self::Sectioned),
+        }
+    }
+}
+
+/// Read back one envelope printed with `--format json` or `ndjson`
+///
+/// The envelope must carry [`OUTPUT_SCHEMA_VERSION`]; output from a build
+/// with a different layout is rejected rather than misread.
+/// [`JsonResponse::raw_text`] reads `raw_response` in any form `--raw`
+/// stores it in.
+pub fn parse_envelope(text: &str) -> Result<JsonResponse, PowerCliError> {
+    let envelope: Value = serde_json::from_str(text)?;
+    let version = envelope.get("schema_version").and_then(Value::as_u64);
+    if version != Some(OUTPUT_SCHEMA_VERSION as u64) {
+        return Err(PowerCliError::SchemaVersion {
+            found: version.map_or_else(|| "none".to_string(), |v| v.to_string()),
+            expected: OUTPUT_SCHEMA_VERSION,
+        });
+    }
+    Ok(serde_json::from_value(envelope)?)
+}
+
+/// Read back the `data` of one envelope as [`parse_envelope`] does
+#[allow(dead_code)] // Used by library consumers
+pub fn parse_output(text: &str) -> Result<CommandOutput, PowerCliError> {
+    let response = parse_envelope(text)?;
+    if response.status == "error" {
+        return Ok(CommandOutput::Error(serde_json::from_value(response.data)?));
+    }
+    CommandOutput::from_data(&response.command, response.data)
+}
@@ -0,0 +1,292 @@
+/*
+ * E-ink Power CLI - Response Patterns
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Every regex used to parse controller responses
+//!
+//! Patterns are compiled on first use and reused for the rest of the
+//! process, so monitor loops do not recompile them on every sample.
+//! [`ALL`] lists each one so a test can compile them all up front; a bad
+//! edit then fails the test suite instead of panicking on a device.
+
+use super::NUMBER_PATTERN;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// A lazily compiled pattern
+pub type Pattern = LazyLock<Regex>;
+
+fn compile(pattern: &str) -> Regex {
+    Regex::new(pattern).unwrap_or_else(|e| panic!("invalid response pattern {}: {}", pattern, e))
+}
+
+/// `Label: <number> <unit>` with an optional `m`, `µ`/`μ` or `u` prefix
+fn quantity(label: &str, base_unit: &str) -> Regex {
+    compile(&format!(
+        r"{}:\s*({})\s*([mµμu]?){}\b",
+        regex::escape(label),
+        NUMBER_PATTERN,
+        regex::escape(base_unit)
+    ))
+}
+
+/// `pm defaults` line for a rail listed under any of `names` (`|`-separated)
+fn rail_default(names: &str) -> Regex {
+    compile(&format!(
+        r"(?im)^[^\w\n]*(?:{})(?:_EN)?\w*\s*(?:default)?\s*[:=]\s*(on|off|enabled|disabled|high|low|1|0)\b",
+        names
+    ))
+}
+
+// Section markers in compound responses, e.g. `--- Registers ---`
+pub static SECTION_MARKER: Pattern =
+    LazyLock::new(|| compile(r"^\s*(?:-{3,}|={3,})\s*(.*?)\s*[-=]*\s*$"));
+
+// Battery, LTC2959 and `pm measure` quantities
+pub static VOLTAGE: Pattern = LazyLock::new(|| quantity("Voltage", "V"));
+pub static CURRENT: Pattern = LazyLock::new(|| quantity("Current", "A"));
+pub static CHARGE: Pattern = LazyLock::new(|| quantity("Charge", "Ah"));
+pub static POWER: Pattern = LazyLock::new(|| quantity("Power", "W"));
+pub static TEMPERATURE: Pattern =
+    LazyLock::new(|| compile(&format!(r"Temperature:\s*({})\s*°?\s*C", NUMBER_PATTERN)));
+pub static MEASURE_ADC_MODE: Pattern = LazyLock::new(|| compile(r"(?im)^\W*ADC\s*Mode:\s*(.+)$"));
+
+// `pm battery_check`
+pub static HEALTH_RESULT: Pattern =
+    LazyLock::new(|| compile(r"(?im)^\W*(?:Load Test|Result)\s*:\s*(.+)$"));
+pub static INTERNAL_RESISTANCE: Pattern = LazyLock::new(|| quantity("Internal Resistance", "Ω"));
+pub static INTERNAL_RESISTANCE_OHM: Pattern =
+    LazyLock::new(|| quantity("Internal Resistance", "Ohm"));
+pub static LOADED_VOLTAGE: Pattern = LazyLock::new(|| quantity("Loaded Voltage", "V"));
+pub static UNLOADED_VOLTAGE: Pattern = LazyLock::new(|| quantity("Unloaded Voltage", "V"));
+pub static HEALTH_VERDICT: Pattern =
+    LazyLock::new(|| compile(r"(?im)^\W*(?:Verdict|Health|Battery)\s*:\s*(\w+)"));
+
+// `system info`
+pub static BOARD: Pattern = LazyLock::new(|| compile(r"Board:\s*(.+)"));
+pub static SOC: Pattern = LazyLock::new(|| compile(r"SoC:\s*(.+)"));
+pub static VERSION: Pattern = LazyLock::new(|| compile(r"Version:\s*(.+)"));
+pub static BUILD_DATE: Pattern = LazyLock::new(|| compile(r"Build:\s*(.+)"));
+pub static BUILD_TYPE: Pattern = LazyLock::new(|| compile(r"Build Type:\s*(.+)"));
+pub static UPTIME: Pattern = LazyLock::new(|| compile(r"System Uptime:\s*(.+)"));
+pub static UPTIME_MS: Pattern = LazyLock::new(|| compile(r"(?i)Uptime:[^\n(]*\((\d+)\s*ms\)"));
+pub static UPTIME_HMS: Pattern =
+    LazyLock::new(|| compile(r"(?i)Uptime:\s*(?:(\d+)d\s*)?(\d+):(\d{2}):(\d{2})"));
+pub static SEMVER_PREFIX: Pattern = LazyLock::new(|| compile(r"^[vV]?(\d+\.\d+(?:\.\d+)?)"));
+
+// Reply classification, see `serial::protocol::classify`
+pub static CONTROLLER_ERROR: Pattern = LazyLock::new(|| compile(r"(?:Error|Failed):"));
+pub static ALREADY_IN_STATE: Pattern = LazyLock::new(|| {
+    compile(
+        r"(?i)\balready\s+(?:in\s+(?:the\s+)?requested\s+state|(?:powered\s+|switched\s+|set\s+)?(?:on|off|enabled|disabled|high|low|active|inactive)\b)|\bno\s+change\b",
+    )
+});
+
+// Unsolicited console output
+pub static BOOT_BANNER: Pattern = LazyLock::new(|| compile(r"\*\*\* Booting "));
+pub static BOOT_ZEPHYR_VERSION: Pattern =
+    LazyLock::new(|| compile(r"\*\*\* Booting Zephyr OS build (\S+)"));
+pub static RESET_CAUSE: Pattern = LazyLock::new(|| compile(r"(?i)reset\s+cause:\s*(.+)"));
+
+// Shell prompt on a line of its own, possibly wrapped in color codes
+pub static SHELL_PROMPT_LINE: Pattern = LazyLock::new(|| {
+    compile(
+        r"^\r*(?:\x1b\[[0-9;]*[A-Za-z])*(?:prod|debug|uart):~\$[ \t]*(?:\x1b\[[0-9;]*[A-Za-z])*\s*$",
+    )
+});
+
+// Shell prompt at the start of a line, possibly followed by typed input
+pub static SHELL_PROMPT_START: Pattern =
+    LazyLock::new(|| compile(r"^\r*(?:\x1b\[[0-9;]*[A-Za-z])*(?:prod|debug|uart):~\$"));
+
+// `nfc status` and `nfc tag_info`
+pub static NFC_STATUS_REGISTER: Pattern =
+    LazyLock::new(|| compile(r"NTA5332 Status:\s*(0x[0-9A-Fa-f]+)"));
+pub static RF_FIELD: Pattern = LazyLock::new(|| compile(r"RF Field:\s*(.+)"));
+pub static NFC_ACTIVE: Pattern = LazyLock::new(|| compile(r"NFC Active: (YES|NO)"));
+pub static I2C_READY: Pattern = LazyLock::new(|| compile(r"I2C Ready: (YES|NO)"));
+pub static EEPROM: Pattern = LazyLock::new(|| compile(r"EEPROM:\s*(.+)"));
+pub static SRAM: Pattern = LazyLock::new(|| compile(r"SRAM:\s*(.+)"));
+pub static TAG_TYPE: Pattern = LazyLock::new(|| compile(r"Tag Type:\s*(.+)"));
+pub static TAG_UID: Pattern =
+    LazyLock::new(|| compile(r"UID:\s*([0-9A-Fa-f]{2}(?:[:\- ]?[0-9A-Fa-f]{2})*)"));
+pub static NDEF_CAPABLE: Pattern =
+    LazyLock::new(|| compile(r"(?i)NDEF[^:\n]*:\s*(yes|supported|true|capable)"));
+pub static TAG_MEMORY_SIZE: Pattern = LazyLock::new(|| {
+    compile(&format!(
+        r"(?i)Memory(?: Size)?:\s*({})(?:\D|$)\s*(?:bytes|B)?",
+        NUMBER_PATTERN
+    ))
+});
+
+// `ltc2959 status`
+pub static LTC2959_STATUS_REGISTER: Pattern =
+    LazyLock::new(|| compile(r"LTC2959 Status Register:\s*(0x[0-9A-Fa-f]+)"));
+pub static LTC2959_ADC_MODE: Pattern = LazyLock::new(|| compile(r"ADC Mode:\s*(.+)"));
+pub static COULOMB_COUNTER: Pattern = LazyLock::new(|| compile(r"Coulomb Counter:\s*(.+)"));
+pub static CHARGE_COMPLETE: Pattern =
+    LazyLock::new(|| compile(r"(?i)Charge Complete:\s*(yes|no|true|false)"));
+pub static CC_GPIO_STATE: Pattern =
+    LazyLock::new(|| compile(r"(?i)CC_GPIO[^:\n]*:\s*(high|low|on|off|1|0)\b"));
+
+// Charger state, in any reply of firmware that reports it
+pub static CHARGER_STATE: Pattern =
+    LazyLock::new(|| compile(r"(?im)^[^\w\n]*Charger(?:\s+(?:State|Status))?:\s*(\S.*?)\s*$"));
+
+// `pm stats`
+pub static PM_SLEEP_CYCLES: Pattern =
+    LazyLock::new(|| compile(r"(?i)Sleep (?:cycles|count):\s*(\d+)"));
+pub static PM_WAKE_CYCLES: Pattern =
+    LazyLock::new(|| compile(r"(?i)Wake (?:cycles|count|events):\s*(\d+)"));
+pub static PM_LTC2959_STATE: Pattern =
+    LazyLock::new(|| compile(r"(?im)^[ \t]*LTC2959(?: state)?:[ \t]*(.+)$"));
+pub static PM_NFC_STATE: Pattern =
+    LazyLock::new(|| compile(r"(?im)^[ \t]*NFC(?: state)?:[ \t]*(.+)$"));
+pub static PM_UART_STATE: Pattern =
+    LazyLock::new(|| compile(r"(?im)^[ \t]*UART(?: state)?:[ \t]*(.+)$"));
+
+// `pm monitor` samples: firmware 2.2 on one line, 2.3 labeled on several
+pub static MONITOR_COMPACT: Pattern = LazyLock::new(|| {
+    compile(
+        r"\bV=(-?\d+(?:\.\d+)?)\s*(m?)V\s+I=(-?\d+(?:\.\d+)?)\s*(m?)A\b(?:\s+P=(-?\d+(?:\.\d+)?)\s*(m?)W\b)?",
+    )
+});
+pub static MONITOR_HEADER: Pattern = LazyLock::new(|| compile(r"(?i)\bmonitor sample:?\s*$"));
+pub static MONITOR_FIELD: Pattern = LazyLock::new(|| {
+    compile(r"(?i)^\W*(Voltage|Current|Charge|Power):\s*(-?\d+(?:\.\d+)?)\s*(m?)(V|Ah|A|W)\b")
+});
+
+// `pm defaults`
+pub static RAIL_PMIC: Pattern = LazyLock::new(|| rail_default("pmic"));
+pub static RAIL_WIFI: Pattern = LazyLock::new(|| rail_default("wifi|wl"));
+pub static RAIL_DISP: Pattern = LazyLock::new(|| rail_default("disp|display"));
+
+// `pm <rail> status`, e.g. `PMIC: ON`
+pub static RAIL_STATE: Pattern =
+    LazyLock::new(|| compile(r"(?i)[:=]\s*(on|off|enabled|disabled|high|low|1|0)\b"));
+
+// `gpio get`
+pub static GPIO_VALUE: Pattern =
+    LazyLock::new(|| compile(r"(?:GPIO [A-Z]\d+:\s*|Pin value:\s*)([01])"));
+pub static GPIO_PULL: Pattern = LazyLock::new(|| {
+    compile(r"(?i)\b(?:pull[-_ ]?(up|down)|no[-_ ]?pull|floating|pull[-_ ]?none)\b")
+});
+
+// `gpio batch` and its entry in the `gpio` help listing
+pub static GPIO_BATCH_OP: Pattern = LazyLock::new(|| {
+    compile(
+        r"(?im)^\s*([A-Z]+\d+)\s*=\s*([01])\s*:?\s*(OK|FAIL\w*|ERR\w*)\b(?:[^\n+]*\+(\d+)\s*us)?",
+    )
+});
+pub static GPIO_BATCH_SUBCOMMAND: Pattern = LazyLock::new(|| compile(r"(?m)^\s*batch\b"));
+
+// `rtc status`, RTC commands and calibration
+pub static INTERNAL_RTC_WAKE_EVENTS: Pattern = LazyLock::new(|| {
+    compile(&format!(
+        r"Internal RTC.*?Wake events:\s*({})(?:\D|$)",
+        NUMBER_PATTERN
+    ))
+});
+pub static EXTERNAL_RTC_INTERRUPT_EVENTS: Pattern = LazyLock::new(|| {
+    compile(&format!(
+        r"External RTC.*?Interrupt events:\s*({})(?:\D|$)",
+        NUMBER_PATTERN
+    ))
+});
+pub static INTERNAL_RTC_STATUS: Pattern =
+    LazyLock::new(|| compile(r"Internal RTC.*?Status:\s*([^,|\n]+)"));
+pub static EXTERNAL_RTC_STATUS: Pattern =
+    LazyLock::new(|| compile(r"External RTC.*?Status:\s*([^,|\n]+)"));
+pub static INTERRUPT_ACTION: Pattern = LazyLock::new(|| compile(r"Interrupt Action:\s*(.+)"));
+pub static LAST_WAKE_SOURCE: Pattern = LazyLock::new(|| compile(r"Last Wake Source:\s*(.+)"));
+pub static RTC_COUNTER: Pattern = LazyLock::new(|| {
+    compile(&format!(
+        r"(?i)(?:counter|uptime)[^:\n]*:\s*({})(?:\D|$)",
+        NUMBER_PATTERN
+    ))
+});
+pub static RTC_ALARM: Pattern = LazyLock::new(|| compile(r"(?im)^\W*alarm[^:\n]*:\s*(.+)$"));
+pub static RTC_ACTION: Pattern = LazyLock::new(|| compile(r"(?i)(?:interrupt\s+)?action:\s*(.+)"));
+pub static RTC_OFFSET: Pattern =
+    LazyLock::new(|| compile(r"(?i)offset[^:\n]*:\s*(0x[0-9a-f]+|[-+]?\d+)"));
+
+/// Every pattern with its name
+#[allow(dead_code)] // Used by tests
+pub static ALL: &[(&str, &Pattern)] = &[
+    ("SECTION_MARKER", &SECTION_MARKER),
+    ("VOLTAGE", &VOLTAGE),
+    ("CURRENT", &CURRENT),
+    ("CHARGE", &CHARGE),
+    ("POWER", &POWER),
+    ("TEMPERATURE", &TEMPERATURE),
+    ("MEASURE_ADC_MODE", &MEASURE_ADC_MODE),
+    ("HEALTH_RESULT", &HEALTH_RESULT),
+    ("INTERNAL_RESISTANCE", &INTERNAL_RESISTANCE),
+    ("INTERNAL_RESISTANCE_OHM", &INTERNAL_RESISTANCE_OHM),
+    ("LOADED_VOLTAGE", &LOADED_VOLTAGE),
+    ("UNLOADED_VOLTAGE", &UNLOADED_VOLTAGE),
+    ("HEALTH_VERDICT", &HEALTH_VERDICT),
+    ("BOARD", &BOARD),
+    ("SOC", &SOC),
+    ("VERSION", &VERSION),
+    ("BUILD_DATE", &BUILD_DATE),
+    ("BUILD_TYPE", &BUILD_TYPE),
+    ("UPTIME", &UPTIME),
+    ("UPTIME_MS", &UPTIME_MS),
+    ("UPTIME_HMS", &UPTIME_HMS),
+    ("SEMVER_PREFIX", &SEMVER_PREFIX),
+    ("CONTROLLER_ERROR", &CONTROLLER_ERROR),
+    ("ALREADY_IN_STATE", &ALREADY_IN_STATE),
+    ("BOOT_BANNER", &BOOT_BANNER),
+    ("BOOT_ZEPHYR_VERSION", &BOOT_ZEPHYR_VERSION),
+    ("RESET_CAUSE", &RESET_CAUSE),
+    ("SHELL_PROMPT_LINE", &SHELL_PROMPT_LINE),
+    ("SHELL_PROMPT_START", &SHELL_PROMPT_START),
+    ("NFC_STATUS_REGISTER", &NFC_STATUS_REGISTER),
+    ("RF_FIELD", &RF_FIELD),
+    ("NFC_ACTIVE", &NFC_ACTIVE),
+    ("I2C_READY", &I2C_READY),
+    ("EEPROM", &EEPROM),
+    ("SRAM", &SRAM),
+    ("TAG_TYPE", &TAG_TYPE),
+    ("TAG_UID", &TAG_UID),
+    ("NDEF_CAPABLE", &NDEF_CAPABLE),
+    ("TAG_MEMORY_SIZE", &TAG_MEMORY_SIZE),
+    ("LTC2959_STATUS_REGISTER", &LTC2959_STATUS_REGISTER),
+    ("LTC2959_ADC_MODE", &LTC2959_ADC_MODE),
+    ("COULOMB_COUNTER", &COULOMB_COUNTER),
+    ("CHARGE_COMPLETE", &CHARGE_COMPLETE),
+    ("CC_GPIO_STATE", &CC_GPIO_STATE),
+    ("CHARGER_STATE", &CHARGER_STATE),
+    ("PM_SLEEP_CYCLES", &PM_SLEEP_CYCLES),
+    ("PM_WAKE_CYCLES", &PM_WAKE_CYCLES),
+    ("PM_LTC2959_STATE", &PM_LTC2959_STATE),
+    ("PM_NFC_STATE", &PM_NFC_STATE),
+    ("PM_UART_STATE", &PM_UART_STATE),
+    ("MONITOR_COMPACT", &MONITOR_COMPACT),
+    ("MONITOR_HEADER", &MONITOR_HEADER),
+    ("MONITOR_FIELD", &MONITOR_FIELD),
+    ("RAIL_PMIC", &RAIL_PMIC),
+    ("RAIL_WIFI", &RAIL_WIFI),
+    ("RAIL_DISP", &RAIL_DISP),
+    ("RAIL_STATE", &RAIL_STATE),
+    ("GPIO_VALUE", &GPIO_VALUE),
+    ("GPIO_PULL", &GPIO_PULL),
+    ("GPIO_BATCH_OP", &GPIO_BATCH_OP),
+    ("GPIO_BATCH_SUBCOMMAND", &GPIO_BATCH_SUBCOMMAND),
+    ("INTERNAL_RTC_WAKE_EVENTS", &INTERNAL_RTC_WAKE_EVENTS),
+    (
+        "EXTERNAL_RTC_INTERRUPT_EVENTS",
+        &EXTERNAL_RTC_INTERRUPT_EVENTS,
+    ),
+    ("INTERNAL_RTC_STATUS", &INTERNAL_RTC_STATUS),
+    ("EXTERNAL_RTC_STATUS", &EXTERNAL_RTC_STATUS),
+    ("INTERRUPT_ACTION", &INTERRUPT_ACTION),
+    ("LAST_WAKE_SOURCE", &LAST_WAKE_SOURCE),
+    ("RTC_COUNTER", &RTC_COUNTER),
+    ("RTC_ALARM", &RTC_ALARM),
+    ("RTC_ACTION", &RTC_ACTION),
+    ("RTC_OFFSET", &RTC_OFFSET),
+];
@@ -0,0 +1,74 @@
+/*
+ * E-ink Power CLI - Progress Rewrites
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Collapsing `\r` progress rewrites in console output
+//!
+//! Long firmware operations (flash erase, NFC init) redraw a progress line by
+//! returning the cursor with `\r` and printing over it. In a captured reply
+//! every intermediate state ends up on the same line. [`collapse`] replays
+//! the rewrites as a terminal would and keeps what is left on screen.
+//! Parsers and human output use the collapsed text. The JSON `raw_response`
+//! and the debug trace of the serial traffic keep the original.
+
+use std::borrow::Cow;
+
+/// Erase from the cursor to the end of the line
+const ERASE_TO_END: &[&str] = &["\x1b[K", "\x1b[0K"];
+
+/// Erase the whole line; the cursor stays where it is
+const ERASE_LINE: &str = "\x1b[2K";
+
+/// `text` as a terminal would show it once every `\r` rewrite is drawn
+///
+/// Borrows `text` unchanged when it has no carriage return.
+pub fn collapse(text: &str) -> Cow<'_, str> {
+    if !text.contains('\r') {
+        return Cow::Borrowed(text);
+    }
+    let lines: Vec<String> = text.split('\n').map(render_line).collect();
+    Cow::Owned(lines.join("\n"))
+}
+
+/// Final state of one line; a `\r` right before the line end is not a rewrite
+fn render_line(line: &str) -> String {
+    let line = line.trim_end_matches('\r');
+    if !line.contains('\r') {
+        return line.to_string();
+    }
+
+    let mut screen: Vec<char> = Vec::new();
+    let mut cursor = 0;
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        if c == '\x1b' {
+            if let Some(after) = ERASE_TO_END.iter().find_map(|seq| rest.strip_prefix(seq)) {
+                screen.truncate(cursor);
+                rest = after;
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix(ERASE_LINE) {
+                screen = vec![' '; cursor];
+                rest = after;
+                continue;
+            }
+        }
+        rest = &rest[c.len_utf8()..];
+        if c == '\r' {
+            cursor = 0;
+            continue;
+        }
+        match screen.get_mut(cursor) {
+            Some(cell) => *cell = c,
+            None => screen.push(c),
+        }
+        cursor += 1;
+    }
+    screen
+        .into_iter()
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
@@ -0,0 +1,145 @@
+/*
+ * E-ink Power CLI - Raw Response Storage
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! How the controller reply is kept in the `raw_response` of an envelope
+//!
+//! Replies such as `nfc debug` or `help` run to tens of kilobytes, which
+//! adds up in logs that archive every JSON document. By default
+//! `raw_response` keeps the first [`DEFAULT_RAW_LIMIT`] bytes and
+//! `raw_response_info` records that it was cut and how long the reply was.
+//! `--raw full` keeps the reply whole, `--raw omit` leaves it out and
+//! `--raw-compress` stores it as base64 of its zlib stream.
+//!
+//! [`RawResponseInfo`] is only written when `raw_response` is not the
+//! reply as received, so envelopes of short replies are unchanged;
+//! [`JsonResponse::raw_text`](super::JsonResponse::raw_text) reads any of
+//! the forms back.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use clap::ValueEnum;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+
+/// Bytes of the reply kept by `--raw truncated`
+pub const DEFAULT_RAW_LIMIT: usize = 2048;
+
+/// How much of the reply `raw_response` keeps
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RawMode {
+    /// The reply as received
+    Full,
+    /// The first [`DEFAULT_RAW_LIMIT`] bytes
+    #[default]
+    Truncated,
+    /// No `raw_response`
+    Omit,
+}
+
+/// Encoding of `raw_response` other than plain text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RawEncoding {
+    /// Base64 (standard alphabet, padded) of a zlib stream of the text
+    #[serde(rename = "zlib+base64")]
+    ZlibBase64,
+}
+
+/// Options from `--raw` and `--raw-compress`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RawOptions {
+    pub mode: RawMode,
+    pub compress: bool,
+    /// Bytes kept in [`RawMode::Truncated`]
+    pub limit: usize,
+}
+
+impl Default for RawOptions {
+    fn default() -> Self {
+        Self {
+            mode: RawMode::default(),
+            compress: false,
+            limit: DEFAULT_RAW_LIMIT,
+        }
+    }
+}
+
+/// `raw_response_info`: what `raw_response` holds when it is not the
+/// reply as received
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RawResponseInfo {
+    /// Only the start of the reply is kept
+    #[serde(default)]
+    pub truncated: bool,
+    /// Length of the reply as received, in bytes
+    pub original_length: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<RawEncoding>,
+}
+
+/// `raw_response` and `raw_response_info` for `raw` under `options`
+pub fn store(raw: &str, options: &RawOptions) -> (Option<String>, Option<RawResponseInfo>) {
+    let kept = match options.mode {
+        RawMode::Omit => return (None, None),
+        RawMode::Full => raw,
+        RawMode::Truncated => truncate(raw, options.limit),
+    };
+    let truncated = kept.len() < raw.len();
+    let encoding = options.compress.then_some(RawEncoding::ZlibBase64);
+    let text = match encoding {
+        Some(RawEncoding::ZlibBase64) => compress(kept),
+        None => kept.to_string(),
+    };
+    let info = (truncated || encoding.is_some()).then_some(RawResponseInfo {
+        truncated,
+        original_length: raw.len(),
+        encoding,
+    });
+    (Some(text), info)
+}
+
+/// Text of a stored `raw_response`, decoded according to `info`
+#[allow(dead_code)] // Used by tests
+pub fn decode(stored: &str, info: Option<&RawResponseInfo>) -> Result<String, String> {
+    match info.and_then(|info| info.encoding) {
+        Some(RawEncoding::ZlibBase64) => {
+            let compressed = STANDARD
+                .decode(stored.trim())
+                .map_err(|e| format!("raw_response is not base64: {}", e))?;
+            let mut text = String::new();
+            ZlibDecoder::new(compressed.as_slice())
+                .read_to_string(&mut text)
+                .map_err(|e| format!("raw_response is not a zlib stream of text: {}", e))?;
+            Ok(text)
+        }
+        None => Ok(stored.to_string()),
+    }
+}
+
+/// The longest start of `text` of at most `limit` bytes that ends on a
+/// character boundary
+pub fn truncate(text: &str, limit: usize) -> &str {
+    if text.len() <= limit {
+        return text;
+    }
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+fn compress(text: &str) -> String {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+    // Writing to a Vec cannot fail
+    encoder
+        .write_all(text.as_bytes())
+        .expect("zlib into memory");
+    STANDARD.encode(encoder.finish().expect("zlib into memory"))
+}
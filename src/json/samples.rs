@@ -0,0 +1,332 @@
+/*
+ * E-ink Power CLI - Monitor Sample Formats
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Battery samples the firmware prints on its own after `pm monitor start`
+//!
+//! The sample layout changed between firmware releases: 2.2 prints one line,
+//! `V=7412mV I=-142mA`, and 2.3 a `Monitor sample:` heading with labeled
+//! `Voltage:`/`Current:` lines below it. A [`SampleParser`] offers console
+//! lines to each [`SampleFormat`] in turn and the first that parses them
+//! wins, so a new layout only needs one more format. Lines no format
+//! recognizes are counted, and kept for `--show-unparsed`, rather than
+//! dropped without a trace.
+//!
+//! The parser takes console output as it arrives. A sample that may still
+//! continue in the next chunk is held back until it ends or the output is
+//! [flushed](SampleParser::flush).
+
+use super::patterns;
+use super::BatteryJson;
+use serde::{Deserialize, Serialize};
+
+/// What a [`SampleFormat`] made of the lines offered to it
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attempt {
+    /// A sample taking up the first `lines` lines
+    Parsed { battery: BatteryJson, lines: usize },
+    /// The lines start a sample whose end has not arrived yet
+    Incomplete,
+    /// Not a sample in this format
+    NoMatch,
+}
+
+/// One way the firmware prints monitor samples
+pub trait SampleFormat: Send + Sync {
+    /// Name reported once the format is selected, e.g. `compact`
+    fn name(&self) -> &'static str;
+
+    /// Try to read a sample starting at the first of `lines`
+    ///
+    /// `complete` is true when no more lines follow `lines`, so a sample
+    /// running up to the last line has ended.
+    fn attempt(&self, lines: &[&str], complete: bool) -> Attempt;
+}
+
+/// Firmware 2.2: `V=7412mV I=-142mA`, optionally followed by `P=-1052mW`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompactFormat;
+
+impl SampleFormat for CompactFormat {
+    fn name(&self) -> &'static str {
+        "compact"
+    }
+
+    fn attempt(&self, lines: &[&str], _complete: bool) -> Attempt {
+        let Some(caps) = lines
+            .first()
+            .and_then(|line| patterns::MONITOR_COMPACT.captures(line))
+        else {
+            return Attempt::NoMatch;
+        };
+        let milli = |value: usize, prefix: usize| {
+            caps.get(value)
+                .and_then(|v| to_milli(v.as_str(), caps.get(prefix).map_or("", |p| p.as_str())))
+        };
+        Attempt::Parsed {
+            battery: BatteryJson {
+                voltage_mv: milli(1, 2).and_then(|v| u16::try_from(v).ok()),
+                current_ma: milli(3, 4).and_then(|v| i16::try_from(v).ok()),
+                charge_mah: None,
+                power_mw: milli(5, 6).and_then(|v| i32::try_from(v).ok()),
+                temperature_c: None,
+            },
+            lines: 1,
+        }
+    }
+}
+
+/// Firmware 2.3: an optional `Monitor sample:` heading, then one
+/// `Voltage:`, `Current:`, `Charge:` or `Power:` line per field
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LabeledFormat;
+
+impl SampleFormat for LabeledFormat {
+    fn name(&self) -> &'static str {
+        "labeled"
+    }
+
+    fn attempt(&self, lines: &[&str], complete: bool) -> Attempt {
+        let heading = lines
+            .first()
+            .is_some_and(|line| patterns::MONITOR_HEADER.is_match(line))
+            as usize;
+        let mut battery = BatteryJson {
+            voltage_mv: None,
+            current_ma: None,
+            charge_mah: None,
+            power_mw: None,
+            temperature_c: None,
+        };
+        let mut fields = 0;
+        for line in &lines[heading..] {
+            let Some(caps) = patterns::MONITOR_FIELD.captures(line) else {
+                break;
+            };
+            let value = to_milli(&caps[2], &caps[3]);
+            match caps[1].to_ascii_lowercase().as_str() {
+                "voltage" => battery.voltage_mv = value.and_then(|v| u16::try_from(v).ok()),
+                "current" => battery.current_ma = value.and_then(|v| i16::try_from(v).ok()),
+                "charge" => battery.charge_mah = value.and_then(|v| u16::try_from(v).ok()),
+                _ => battery.power_mw = value.and_then(|v| i32::try_from(v).ok()),
+            }
+            fields += 1;
+        }
+
+        let used = heading + fields;
+        if used == lines.len() && !complete && used > 0 {
+            // The next line may be another field of this sample
+            return Attempt::Incomplete;
+        }
+        if battery.voltage_mv.is_none() && battery.current_ma.is_none() {
+            return Attempt::NoMatch;
+        }
+        Attempt::Parsed {
+            battery,
+            lines: used,
+        }
+    }
+}
+
+/// `value` with unit prefix `prefix` (`m` or none) in thousandths
+fn to_milli(value: &str, prefix: &str) -> Option<i64> {
+    let value: f64 = value.parse().ok()?;
+    let scale = if prefix.is_empty() { 1000.0 } else { 1.0 };
+    Some((value * scale).round() as i64)
+}
+
+/// The formats every parser tries, in order
+pub fn default_formats() -> Vec<Box<dyn SampleFormat>> {
+    vec![Box::new(CompactFormat), Box::new(LabeledFormat)]
+}
+
+/// A sample read from the console
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedSample {
+    pub battery: BatteryJson,
+    /// Name of the format that read it
+    pub format: &'static str,
+    /// Line of the session the sample starts on, counted from 1
+    pub line: u64,
+}
+
+/// A console line no format recognized
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnparsedLine {
+    /// Line of the session, counted from 1
+    pub line: u64,
+    pub text: String,
+}
+
+/// Counts over a parsing session
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SampleParseStats {
+    pub samples: u64,
+    /// Non-blank lines no format recognized
+    pub unparsed: u64,
+}
+
+/// Chain of [`SampleFormat`]s over a stream of console output
+pub struct SampleParser {
+    formats: Vec<Box<dyn SampleFormat>>,
+    /// Text after the last line break
+    partial: String,
+    /// Complete lines not yet resolved
+    held: Vec<String>,
+    /// Lines already resolved
+    resolved: u64,
+    selected: Option<&'static str>,
+    stats: SampleParseStats,
+    keep_unparsed: bool,
+    unparsed: Vec<UnparsedLine>,
+}
+
+impl Default for SampleParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SampleParser {
+    /// Parser trying [`default_formats`]
+    pub fn new() -> Self {
+        Self::with_formats(default_formats())
+    }
+
+    /// Parser trying `formats` in order
+    pub fn with_formats(formats: Vec<Box<dyn SampleFormat>>) -> Self {
+        Self {
+            formats,
+            partial: String::new(),
+            held: Vec::new(),
+            resolved: 0,
+            selected: None,
+            stats: SampleParseStats::default(),
+            keep_unparsed: false,
+            unparsed: Vec::new(),
+        }
+    }
+
+    /// Keep unrecognized lines for [`Self::take_unparsed`]
+    pub fn keep_unparsed(mut self, keep: bool) -> Self {
+        self.keep_unparsed = keep;
+        self
+    }
+
+    /// Format of the first sample of the session, once there is one
+    pub fn format(&self) -> Option<&'static str> {
+        self.selected
+    }
+
+    pub fn stats(&self) -> SampleParseStats {
+        self.stats
+    }
+
+    /// Unrecognized lines kept since the last call
+    pub fn take_unparsed(&mut self) -> Vec<UnparsedLine> {
+        std::mem::take(&mut self.unparsed)
+    }
+
+    /// Add console output; returns the samples it completes
+    pub fn push(&mut self, text: &str) -> Vec<ParsedSample> {
+        self.partial.push_str(text);
+        let Some(end) = self.partial.rfind('\n') else {
+            return Vec::new();
+        };
+        let rest = self.partial.split_off(end + 1);
+        let complete = std::mem::replace(&mut self.partial, rest);
+        self.held.extend(
+            complete
+                .lines()
+                .map(|line| line.trim_end_matches('\r').to_string()),
+        );
+        self.resolve(false)
+    }
+
+    /// Treat the output so far as ended, e.g. once the console has gone
+    /// quiet; returns the samples that were held back
+    pub fn flush(&mut self) -> Vec<ParsedSample> {
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.held.push(line.trim_end_matches('\r').to_string());
+        }
+        self.resolve(true)
+    }
+
+    /// Every sample in `text`, as a whole
+    #[allow(dead_code)] // Used by tests
+    pub fn parse_all(&mut self, text: &str) -> Vec<ParsedSample> {
+        let mut samples = self.push(text);
+        samples.extend(self.flush());
+        samples
+    }
+
+    /// Offer the held lines to the formats in turn
+    fn resolve(&mut self, complete: bool) -> Vec<ParsedSample> {
+        let mut samples = Vec::new();
+        let mut at = 0;
+        while at < self.held.len() {
+            if self.held[at].trim().is_empty() {
+                at += 1;
+                continue;
+            }
+            let lines: Vec<&str> = self.held[at..].iter().map(String::as_str).collect();
+            let mut outcome = None;
+            for format in &self.formats {
+                match format.attempt(&lines, complete) {
+                    Attempt::NoMatch => continue,
+                    attempt => {
+                        outcome = Some((format.name(), attempt));
+                        break;
+                    }
+                }
+            }
+            let line = self.resolved + at as u64 + 1;
+            match outcome {
+                Some((_, Attempt::Incomplete)) => break,
+                Some((format, Attempt::Parsed { battery, lines })) => {
+                    self.select(format);
+                    self.stats.samples += 1;
+                    samples.push(ParsedSample {
+                        battery,
+                        format,
+                        line,
+                    });
+                    at += lines.max(1);
+                }
+                _ => {
+                    self.stats.unparsed += 1;
+                    if self.keep_unparsed {
+                        self.unparsed.push(UnparsedLine {
+                            line,
+                            text: self.held[at].clone(),
+                        });
+                    }
+                    at += 1;
+                }
+            }
+        }
+        self.held.drain(..at);
+        self.resolved += at as u64;
+        samples
+    }
+
+    fn select(&mut self, format: &'static str) {
+        match self.selected {
+            None => {
+                log::info!("Monitor samples are in the {} format", format);
+                self.selected = Some(format);
+            }
+            Some(selected) if selected != format => {
+                log::warn!(
+                    "Monitor sample in the {} format after {} ones",
+                    format,
+                    selected
+                );
+            }
+            Some(_) => {}
+        }
+    }
+}
@@ -0,0 +1,174 @@
+/*
+ * E-ink Power CLI - Parser Schema Examples
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Reference controller responses for every [`ResponseParser`] parser
+//!
+//! Each example is a complete response as printed by the controller shell.
+//! When a firmware release changes a response format, update the example
+//! here; [`ParserSchema::assert_all_fields_populated`] then shows which
+//! fields the parsers no longer extract.
+
+use super::ResponseParser;
+use serde::Serialize;
+use serde_json::Value;
+
+/// One example response per parser
+#[derive(Debug, Clone, Copy)]
+pub struct ParserSchema {
+    pub battery_example: &'static str,
+    pub system_info_example: &'static str,
+    pub nfc_example: &'static str,
+    pub ltc2959_example: &'static str,
+    pub gpio_example: &'static str,
+    pub rtc_example: &'static str,
+    pub battery_health_example: &'static str,
+    pub pm_stats_example: &'static str,
+}
+
+/// Reference responses in the format printed by firmware 2.2.0
+#[allow(dead_code)] // Used by tests
+pub const DEVICE_EXAMPLES: ParserSchema = ParserSchema {
+    battery_example: "📊 LTC2959 Measurements:
+   🔋 Voltage: 6088 mV
+   ⚡ Current: -170 mA
+   🔋 Charge: 1250 mAh
+   ⚡ Power: -1034 mW
+   🌡️ Temperature: 23°C",
+    system_info_example: "🖥️ System Information:
+Board: MCXC143VFM E-Ink Power Controller
+SoC: NXP MCXC143VFM (ARM Cortex-M0+)
+Version: 2.2.0-+0fa46fb-dirty.298
+Build: 2025-10-09 11:13:59 UTC
+Build Type: Debug
+System Uptime: 0:01:07 (67427 ms)",
+    nfc_example: "📡 NFC Status:
+NTA5332 Status: 0x02
+RF Field: Absent
+NFC Active: NO
+I2C Ready: YES
+EEPROM: Ready
+SRAM: Idle",
+    ltc2959_example: "📊 LTC2959 Status:
+LTC2959 Status Register: 0x01
+ADC Mode: Smart Sleep
+Coulomb Counter: Enabled
+Charge Complete: NO
+Voltage: 6088 mV
+Current: -170 mA
+Charge: 1250 mAh
+Power: -1034 mW",
+    gpio_example: "GPIO A0: 1 (INPUT, PULL-UP, HIGH)",
+    rtc_example: "🕐 RTC Status:
+Internal RTC (LPTMR) Status: Running, Wake events: 12
+External RTC (PCF2131) Status: OK, Interrupt events: 3
+Interrupt Action: AUTO
+Last Wake Source: External RTC",
+    battery_health_example: "🔋 Battery Health Check:
+Unloaded Voltage: 3850 mV
+Loaded Voltage: 3712 mV
+Internal Resistance: 138 mOhm
+Load Test: PASS
+Verdict: HEALTHY",
+    pm_stats_example: "📊 Power Management Statistics:
+Sleep cycles: 42
+Wake cycles: 41
+LTC2959: Smart Sleep
+NFC: Idle
+UART: Active
+Uptime: 0:01:07 (67427 ms)",
+};
+
+impl ParserSchema {
+    /// Run every parser on its example and panic if any field is missing
+    ///
+    /// The panic message lists every unpopulated field, e.g.
+    /// `system_info.git_hash`.
+    #[allow(dead_code)] // Used by tests
+    pub fn assert_all_fields_populated(schema: &ParserSchema) {
+        let mut parsed = [
+            (
+                "battery",
+                to_value(ResponseParser::parse_battery_response(
+                    schema.battery_example,
+                )),
+            ),
+            (
+                "system_info",
+                to_value(ResponseParser::parse_system_info(
+                    schema.system_info_example,
+                )),
+            ),
+            (
+                "nfc",
+                to_value(ResponseParser::parse_nfc_status(schema.nfc_example)),
+            ),
+            (
+                "ltc2959",
+                to_value(ResponseParser::parse_ltc2959_status(schema.ltc2959_example)),
+            ),
+            (
+                "gpio",
+                to_value(ResponseParser::parse_gpio_response(
+                    schema.gpio_example,
+                    "A",
+                    0,
+                )),
+            ),
+            (
+                "rtc",
+                to_value(ResponseParser::parse_rtc_status(schema.rtc_example)),
+            ),
+            (
+                "battery_health",
+                to_value(ResponseParser::parse_battery_health(
+                    schema.battery_health_example,
+                )),
+            ),
+            (
+                "pm_stats",
+                to_value(ResponseParser::parse_pm_stats(schema.pm_stats_example)),
+            ),
+        ];
+
+        let mut missing = Vec::new();
+        for (name, value) in &mut parsed {
+            // Filled from the NFC EEPROM by `system info --identity`, never parsed
+            if *name == "system_info" {
+                if let Some(info) = value.as_object_mut() {
+                    for field in SYSTEM_INFO_IDENTITY_FIELDS {
+                        info.remove(*field);
+                    }
+                }
+            }
+            collect_nulls(name, value, &mut missing);
+        }
+        assert!(
+            missing.is_empty(),
+            "parsers left fields unpopulated: {}",
+            missing.join(", ")
+        );
+    }
+}
+
+/// [`SystemInfoJson`](super::SystemInfoJson) fields not read from `system info`
+pub(super) const SYSTEM_INFO_IDENTITY_FIELDS: &[&str] = &["serial", "hw_rev", "manufacture_date"];
+
+fn to_value<T: Serialize>(parsed: T) -> Value {
+    serde_json::to_value(parsed).expect("parser output serializes")
+}
+
+/// Append the dotted path of every `null` in `value`
+fn collect_nulls(path: &str, value: &Value, missing: &mut Vec<String>) {
+    match value {
+        Value::Null => missing.push(path.to_string()),
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                collect_nulls(&format!("{}.{}", path, key), field, missing);
+            }
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,257 @@
+/*
+ * E-ink Power CLI - Response Sections
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Compound controller responses split into named sections
+//!
+//! Replies such as `system info`, `nfc debug` or `mcumgr image list` are
+//! blocks of `Key: value` lines under headings. [`SectionedJson::parse`]
+//! splits a reply into [`Section`]s and reads the pairs of each into an
+//! ordered map, so parsers look fields up by label instead of scanning the
+//! whole reply, and commands without a dedicated struct still get
+//! structured JSON.
+//!
+//! A section starts:
+//! - after a blank line;
+//! - at a `--- Name ---` or `=== Name ===` marker;
+//! - at a `Name:` line without a value that opens a block or has more
+//!   indented lines below it, e.g. `📡 NFC Status:`;
+//! - at a line that is not a pair and has more indented lines below it,
+//!   e.g. `image=0 slot=1`.
+//!
+//! A section whose lines are indented below its heading ends at the first
+//! line that is not. Bullets and emoji before a label are not part of it,
+//! so `📶 RF Field: Absent` is the pair `RF Field`: `Absent`.
+
+use super::{patterns, progress};
+use serde::de::{MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Longest label read as the key of a pair, in characters
+const MAX_KEY_CHARS: usize = 48;
+
+/// One `Key: value` line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub key: String,
+    pub value: String,
+    /// The line as printed, trimmed
+    pub line: String,
+}
+
+/// A block of a response
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Section {
+    /// Heading, `None` for a block without one
+    pub name: Option<String>,
+    /// Pairs in the order printed; a key repeated within the section is
+    /// kept in `lines`
+    #[serde(with = "ordered_map")]
+    pub fields: Vec<Field>,
+    /// Lines that are not pairs, e.g. table rows or notes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lines: Vec<String>,
+}
+
+impl Section {
+    /// Pair with `key`, ignoring case
+    pub fn field(&self, key: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .find(|field| field.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Value of `key`, ignoring case
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.field(key).map(|field| field.value.as_str())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.fields.is_empty() && self.lines.is_empty()
+    }
+
+    fn push(&mut self, line: &str) {
+        let trimmed = line.trim();
+        match split_pair(line) {
+            Some((key, value)) if self.field(key).is_none() => self.fields.push(Field {
+                key: key.to_string(),
+                value: value.to_string(),
+                line: trimmed.to_string(),
+            }),
+            _ => self.lines.push(trimmed.to_string()),
+        }
+    }
+}
+
+/// `data` of a response no dedicated struct describes, and the form the
+/// typed parsers read compound responses through
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionedJson {
+    pub sections: Vec<Section>,
+}
+
+impl SectionedJson {
+    /// Split `response` into sections
+    ///
+    /// Progress rewrites are collapsed first. Sections with no lines, e.g.
+    /// a heading directly followed by another, are dropped.
+    pub fn parse(response: &str) -> Self {
+        let response = progress::collapse(response);
+        let lines: Vec<&str> = response.lines().map(str::trim_end).collect();
+        let mut sections = Vec::new();
+        let mut current = Section::default();
+        // Indentation of the heading, when the section is indented below it
+        let mut nested_under: Option<usize> = None;
+        let mut block_start = true;
+
+        for (i, &line) in lines.iter().enumerate() {
+            if line.is_empty() {
+                close(&mut sections, &mut current);
+                nested_under = None;
+                block_start = true;
+                continue;
+            }
+            let depth = indent(line);
+            if nested_under.is_some_and(|heading| depth <= heading) {
+                close(&mut sections, &mut current);
+                nested_under = None;
+            }
+
+            if let Some(caps) = patterns::SECTION_MARKER.captures(line) {
+                close(&mut sections, &mut current);
+                current.name = heading_name(&caps[1]);
+                nested_under = None;
+                block_start = true;
+                continue;
+            }
+
+            let next_depth = lines[i + 1..]
+                .iter()
+                .find(|next| !next.is_empty())
+                .map(|next| indent(next));
+            let opens_nested = next_depth.is_some_and(|next| next > depth);
+            let is_heading = match split_pair(line) {
+                Some((_, "")) => opens_nested || (block_start && next_depth.is_some()),
+                Some(_) => false,
+                None => opens_nested,
+            };
+            if is_heading {
+                close(&mut sections, &mut current);
+                current.name = heading_name(line);
+                nested_under = opens_nested.then_some(depth);
+            } else {
+                current.push(line);
+            }
+            block_start = false;
+        }
+        close(&mut sections, &mut current);
+        Self { sections }
+    }
+
+    /// First section named `name`, ignoring case
+    #[allow(dead_code)] // Used by tests
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| {
+            section
+                .name
+                .as_deref()
+                .is_some_and(|n| n.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// First pair with `key` in any section, ignoring case
+    pub fn field(&self, key: &str) -> Option<&Field> {
+        self.sections.iter().find_map(|section| section.field(key))
+    }
+
+    /// Value of the first pair with `key` in any section
+    #[allow(dead_code)] // Used by tests
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.field(key).map(|field| field.value.as_str())
+    }
+}
+
+/// Finish `current` and start a new unnamed section
+fn close(sections: &mut Vec<Section>, current: &mut Section) {
+    let section = std::mem::take(current);
+    if !section.is_empty() {
+        sections.push(section);
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// `line` as a heading: without bullets, emoji or a trailing colon
+fn heading_name(line: &str) -> Option<String> {
+    let name = line
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .trim_end()
+        .trim_end_matches(':')
+        .trim_end();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// `line` as `(key, value)` if it is a `Key: value` pair
+///
+/// The key ends at the first colon followed by a space or the end of the
+/// line, so times and prompts (`uart:~$`) in a value are left alone. It
+/// must start with a letter and read as a label, which rules out log lines
+/// such as `[00:01:05.102,000] <inf> nfc: ...`.
+pub fn split_pair(line: &str) -> Option<(&str, &str)> {
+    let text = line.trim_start_matches(|c: char| !c.is_alphanumeric());
+    let (colon, _) = text
+        .char_indices()
+        .find(|&(i, c)| c == ':' && text[i + 1..].chars().next().is_none_or(char::is_whitespace))?;
+    let key = text[..colon].trim_end();
+    let label = key.chars().next().is_some_and(char::is_alphabetic)
+        && key.chars().count() <= MAX_KEY_CHARS
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || " _-/().#&'+".contains(c));
+    label.then(|| (key, text[colon + 1..].trim()))
+}
+
+/// [`Section::fields`] as a JSON object in the order printed
+mod ordered_map {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(fields: &[Field], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(fields.len()))?;
+        for field in fields {
+            map.serialize_entry(&field.key, &field.value)?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Field>, D::Error> {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = Vec<Field>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of field values")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut fields = Vec::new();
+                while let Some((key, value)) = map.next_entry::<String, String>()? {
+                    fields.push(Field {
+                        line: format!("{}: {}", key, value),
+                        key,
+                        value,
+                    });
+                }
+                Ok(fields)
+            }
+        }
+
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
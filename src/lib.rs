@@ -33,12 +33,30 @@
 //! }
 //! ```
 
+pub mod audit;
+pub mod battery_log;
 pub mod cli;
+pub mod config;
 pub mod error;
 pub mod firmware;
+pub mod fleet;
+pub mod hil;
+pub mod history;
 pub mod json;
+pub mod macros;
 pub mod power;
+pub mod provision;
+pub mod render;
+pub mod schedule;
 pub mod serial;
+pub mod setup;
+#[cfg(unix)]
+pub mod simulator;
+pub mod snapshot;
+pub mod state;
+pub mod status;
+pub mod update;
+pub mod util;
 
 // Re-export commonly used types
 pub use error::PowerCliError;
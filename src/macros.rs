@@ -0,0 +1,213 @@
+/*
+ * E-ink Power CLI - Command Macros
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `run`: named command sequences from the configuration file
+//!
+//! The `[macros]` section maps a name to the commands to run, each as typed
+//! after `eink-power-cli`:
+//!
+//! ```toml
+//! [macros]
+//! prep-for-shipping = [
+//!     "pm pmic off",
+//!     "pm wifi off",
+//!     "pm defaults save",
+//!     "board shutdown",
+//! ]
+//! ```
+//!
+//! [`plan`] parses every step before any is sent, so a typo in the last
+//! step does not leave the unit half-configured. The steps then run like
+//! the lines of a batch file and the run ends with one [`MacroReport`].
+
+use crate::cli::{self, Commands};
+use crate::error::{PowerCliError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// A step checked by [`plan`]
+#[derive(Debug, Clone)]
+pub struct MacroStep {
+    /// The step as written in the configuration file
+    pub line: String,
+    pub command: Commands,
+}
+
+/// Steps of the macro `name`, ignoring case since the configuration file
+/// is read with its keys lowercased
+pub fn lookup(macros: &BTreeMap<String, Vec<String>>, name: &str) -> Result<Vec<String>> {
+    macros
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, steps)| steps.clone())
+        .ok_or_else(|| PowerCliError::InvalidArguments {
+            message: match macros.is_empty() {
+                true => format!(
+                    "no macro named '{}': the configuration file has no [macros] section",
+                    name
+                ),
+                false => format!(
+                    "no macro named '{}'; `run --list` shows the macros defined",
+                    name
+                ),
+            },
+        })
+}
+
+/// Parse every step of the macro `name`, failing at the first that does
+/// not parse or cannot run as a step
+pub fn plan(name: &str, steps: &[String]) -> Result<Vec<MacroStep>> {
+    if steps.iter().all(|line| line.trim().is_empty()) {
+        return Err(PowerCliError::InvalidArguments {
+            message: format!("macro '{}' has no steps", name),
+        });
+    }
+    steps
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(index, line)| {
+            let command =
+                cli::parse_step(line).map_err(|message| PowerCliError::InvalidCommand {
+                    command: format!("macro '{}' step {}: {}", name, index + 1, message),
+                })?;
+            Ok(MacroStep {
+                line: line.to_string(),
+                command,
+            })
+        })
+        .collect()
+}
+
+/// Outcome of a macro step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacroStepStatus {
+    Ok,
+    Failed,
+    /// Not attempted because an earlier step failed
+    NotRun,
+}
+
+impl MacroStepStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MacroStepStatus::Ok => "ok",
+            MacroStepStatus::Failed => "failed",
+            MacroStepStatus::NotRun => "not_run",
+        }
+    }
+}
+
+/// Result of one step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MacroStepResult {
+    /// The step as written in the configuration file
+    pub command: String,
+    pub status: MacroStepStatus,
+    /// Why the step failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// Per-step results and overall status of one macro run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MacroReport {
+    pub name: String,
+    pub success: bool,
+    /// Run with `--dry-run`: the steps' commands were not sent
+    pub dry_run: bool,
+    pub steps: Vec<MacroStepResult>,
+}
+
+impl MacroReport {
+    /// Report with every step of `steps` not run yet
+    pub fn new(name: &str, steps: &[MacroStep], dry_run: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            success: false,
+            dry_run,
+            steps: steps
+                .iter()
+                .map(|step| MacroStepResult {
+                    command: step.line.clone(),
+                    status: MacroStepStatus::NotRun,
+                    error: None,
+                    duration_ms: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Record the outcome of step `index`; the run succeeds once every
+    /// step has
+    pub fn record(&mut self, index: usize, elapsed: Duration, error: Option<&PowerCliError>) {
+        if let Some(step) = self.steps.get_mut(index) {
+            step.status = match error {
+                Some(_) => MacroStepStatus::Failed,
+                None => MacroStepStatus::Ok,
+            };
+            step.error = error.map(|e| e.to_string());
+            step.duration_ms = Some(elapsed.as_millis() as u64);
+        }
+        self.success = self
+            .steps
+            .iter()
+            .all(|step| step.status == MacroStepStatus::Ok);
+    }
+
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        let mut lines: Vec<String> = self
+            .steps
+            .iter()
+            .map(|result| {
+                let icon = match result.status {
+                    MacroStepStatus::Ok => "✅",
+                    MacroStepStatus::Failed => "❌",
+                    MacroStepStatus::NotRun => "⏸️ ",
+                };
+                let mut line = format!("{} {}", icon, result.command);
+                match (result.status, &result.error) {
+                    (MacroStepStatus::NotRun, _) => line.push_str(" (not run)"),
+                    (_, Some(error)) => line.push_str(&format!("\n      {}", error)),
+                    _ => {}
+                }
+                line
+            })
+            .collect();
+        lines.push(String::new());
+        lines.push(format!(
+            "{}: {}{}",
+            if self.success { "PASS" } else { "FAIL" },
+            self.name,
+            if self.dry_run { " (dry run)" } else { "" }
+        ));
+        lines.join("\n")
+    }
+}
+
+/// A macro as listed by `run --list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroListing {
+    pub name: String,
+    pub steps: Vec<String>,
+}
+
+/// Every macro in `macros`, by name
+pub fn listing(macros: &BTreeMap<String, Vec<String>>) -> Vec<MacroListing> {
+    macros
+        .iter()
+        .map(|(name, steps)| MacroListing {
+            name: name.clone(),
+            steps: steps.clone(),
+        })
+        .collect()
+}
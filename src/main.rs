@@ -11,19 +11,39 @@
  * Contact: info@dynamicdevices.co.uk
  */
 
-use clap::Parser;
-use log::{debug, error};
+use log::{debug, error, info, warn};
 use std::process;
 
+mod audit;
+mod battery_log;
 mod cli;
+mod config;
 mod error;
 mod firmware;
+mod fleet;
+mod hil;
+mod history;
 mod json;
+mod macros;
 mod power;
+mod provision;
+mod render;
+mod schedule;
 mod serial;
+mod setup;
+#[cfg(unix)]
+mod simulator;
+mod snapshot;
+mod state;
+mod status;
+mod update;
+mod util;
 
 use cli::Cli;
-use error::PowerCliError;
+use error::report::SessionContext;
+use error::{ContextualError, PowerCliError};
+use power::Rail;
+use render::emit;
 
 /// Application version from Cargo.toml
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -34,7 +54,9 @@ const APP_NAME: &str = env!("CARGO_PKG_NAME");
 #[tokio::main]
 async fn main() {
     // Parse command line arguments first to get verbose flag
-    let cli = Cli::parse();
+    let cli = Cli::parse_with_examples();
+    // `simulate ARGS` runs ARGS against a simulator started here
+    let (cli, _simulator) = simulate_in_process(cli);
 
     // Initialize logging based on verbose flag
     let log_level = if cli.verbose {
@@ -43,42 +65,267 @@ async fn main() {
         log::LevelFilter::Warn
     };
 
-    env_logger::Builder::from_default_env()
-        .filter_level(log_level)
-        .init();
+    // Log lines step around the `battery read --watch` status line
+    let mut logger = env_logger::Builder::from_default_env();
+    logger.filter_level(log_level);
+    render::live::init_logger(logger);
+    util::set_progress(util::Progress::for_cli(&cli));
 
-    // Print version header
-    if !cli.quiet {
+    // Print version header (omitted for formats whose output must be pure records)
+    if !cli.quiet
+        && !cli.command.as_ref().is_some_and(cli::Commands::is_brief)
+        && !matches!(
+            cli.format,
+            cli::OutputFormat::Json
+                | cli::OutputFormat::Prometheus
+                | cli::OutputFormat::Ndjson
+                | cli::OutputFormat::JsonLines
+        )
+    {
         println!("{} v{}", APP_NAME, VERSION);
         println!("Copyright (c) 2025 Dynamic Devices Ltd");
         println!();
     }
 
+    if let Some(deprecation) = cli.deprecation.filter(|_| !cli.quiet) {
+        eprintln!("{}", deprecation.warning());
+    }
+
+    let format = cli.format.clone();
+    let command = cli.command.as_ref().map(cli::Commands::name);
+    let (output, append, output_on_error) = (cli.output.clone(), cli.append, cli.output_on_error);
+    if output.is_some() {
+        render::sink::start_capture();
+    }
+
     // Execute the command
-    if let Err(e) = run(cli).await {
+    let result = run(cli).await;
+    let captured = render::sink::take_captured();
+    if let (Some(path), Ok(())) = (&output, &result) {
+        let contents = match (append, &format) {
+            (true, cli::OutputFormat::Csv) => {
+                render::sink::without_repeated_header(path, &captured)
+            }
+            _ => &captured,
+        };
+        if let Err(e) = render::sink::write_file(path, contents, append) {
+            eprintln!("Error: failed to write {}: {}", path.display(), e);
+            process::exit(PowerCliError::Io(e).exit_code());
+        }
+    }
+
+    if let Err(mut e) = result {
         error!("Command failed: {}", e);
 
         // Print user-friendly error message
         eprintln!("Error: {}", e);
+        let session = e.session.take();
+        if let Some(session) = &session {
+            eprintln!("{}", session.format_human());
+        }
+
+        let is_json = matches!(
+            format,
+            cli::OutputFormat::Json | cli::OutputFormat::Ndjson | cli::OutputFormat::JsonLines
+        );
+        let json_lines = format == cli::OutputFormat::JsonLines;
+        let boot_banner = serial::banner::take_for_output();
+        let envelope = || {
+            let error = json::ErrorJson {
+                error: e.error.to_string(),
+                timeout: json::TimeoutJson::of(&e.error),
+                session: session.clone(),
+            };
+            let mut response =
+                json::JsonResponse::failure(command.as_deref().unwrap_or_default(), error);
+            response.boot_banner = boot_banner.clone();
+            let document = match json_lines {
+                true => json::record_value(json::RecordType::Error, &response),
+                false => serde_json::to_value(&response),
+            };
+            document.map(|d| d.to_string()).unwrap_or_default()
+        };
+        // A json-lines stream always ends with a record saying why it stopped
+        if is_json && (session.is_some() || json_lines) {
+            println!("{}", envelope());
+        }
+        if let Some(path) = output.as_ref().filter(|_| output_on_error) {
+            let document = match (is_json, &session) {
+                (true, _) => envelope(),
+                (false, Some(session)) => format!("Error: {}\n{}", e, session.format_human()),
+                (false, None) => format!("Error: {}", e),
+            };
+            if let Err(write_error) =
+                render::sink::write_file(path, format!("{}\n", document).as_bytes(), append)
+            {
+                eprintln!("Error: failed to write {}: {}", path.display(), write_error);
+            }
+        }
 
         // Exit with error code
-        process::exit(1);
+        process::exit(e.exit_code());
     }
 }
 
 /// Main application logic
-async fn run(cli: Cli) -> Result<(), PowerCliError> {
+async fn run(mut cli: Cli) -> Result<(), ContextualError> {
     debug!("Starting eink-power-cli v{}", VERSION);
 
+    cli.validate()
+        .map_err(|message| PowerCliError::InvalidArguments { message })?;
+
+    // Setup writes the configuration file, which need not exist yet
+    if let Some(cli::Commands::Setup {
+        yes,
+        force,
+        ref profile,
+        capacity,
+    }) = cli.command
+    {
+        return Ok(run_setup(&cli, yes, force, profile.clone(), capacity).await?);
+    }
+
+    if let Some(cli::Commands::Simulate {
+        listen_pty,
+        foreground,
+        scenario,
+        ref firmware,
+        nfc_field,
+        ..
+    }) = cli.command
+    {
+        if !listen_pty {
+            return Err(PowerCliError::InvalidArguments {
+                message: "simulate cannot run another simulate".to_string(),
+            }
+            .into());
+        }
+        return Ok(
+            serve_simulator(&cli, foreground, scenario, firmware.as_deref(), nfc_field).await?,
+        );
+    }
+
     // Create serial connection
-    let connection = serial::Connection::new(&cli.device, cli.baud, cli.quiet)?;
-    let mut power_controller = power::control::PowerController::new(connection);
+    let started = std::time::Instant::now();
+    let config = config::Config::load(cli.config.as_deref())?;
+    if let Some(profile) = &config.profile {
+        debug!("Configuration profile: {}", profile);
+    }
+    if config.commands.is_remapped() {
+        info!(
+            "Shell command remap active: {}",
+            config.commands.describe_remaps()
+        );
+        if cli.dry_run && !cli.quiet {
+            println!(
+                "[dry-run] command remap: {}",
+                config.commands.describe_remaps()
+            );
+        }
+    }
+
+    // Macro steps come from the configuration file and are all checked
+    // before the device is opened
+    if let Some(cli::Commands::Run {
+        ref name,
+        list,
+        dry_run,
+        ..
+    }) = cli.command
+    {
+        if list {
+            return Ok(show_macros(&cli, &config)?);
+        }
+        let name = name.clone().unwrap_or_default();
+        let steps = macros::lookup(&config.macros, &name)?;
+        macros::plan(&name, &steps)?;
+        cli.dry_run |= dry_run;
+        cli.command = Some(cli::Commands::Run {
+            name: Some(name),
+            list,
+            dry_run,
+            steps,
+        });
+    }
+
+    let mut power_controller = power_controller(&cli, &config, &cli.device)?;
 
     match cli.command {
+        Some(cli::Commands::History { last, ref grep }) => {
+            Ok(show_history(&cli, last, grep.as_deref())?)
+        }
+        Some(cli::Commands::Stats { reset }) => Ok(show_link_stats(&cli, reset)?),
+        Some(cli::Commands::Log(ref action)) => Ok(manage_log(&cli, action)?),
+        Some(cli::Commands::State(ref action)) => Ok(manage_state(&cli, action)?),
+        Some(cli::Commands::Schedule(ref action)) => Ok(manage_schedule(&cli, action).await?),
+        Some(cli::Commands::Fleet(ref action)) => Ok(run_fleet(&cli, &config, action).await?),
+        Some(cli::Commands::Examples { ref filter }) => Ok(show_examples(&cli, filter.as_deref())?),
+        Some(cli::Commands::Migrations) => Ok(show_migrations(&cli)?),
+        Some(cli::Commands::ParseCheck {
+            ref dir,
+            ref baseline,
+            update_baseline,
+        }) => Ok(parse_check(
+            &cli,
+            dir,
+            baseline.as_deref(),
+            update_baseline,
+        )?),
+        Some(cli::Commands::SelfUpdate {
+            ref channel,
+            ref url,
+            check_only,
+        }) => Ok(self_update(
+            &cli,
+            &config,
+            channel.as_deref(),
+            url.as_deref(),
+            check_only,
+        )?),
         Some(ref cmd) => {
-            debug!("Executing command: {:?}", cmd);
-            execute_command(cmd.clone(), &mut power_controller, &cli).await?;
-            Ok(())
+            let execution = async {
+                if cli.flush_before_command {
+                    let discarded = power_controller.flush_rx_buffer().await?;
+                    debug!("Discarded {} stale bytes before command", discarded);
+                }
+
+                debug!("Executing command: {:?}", cmd);
+                execute_command(cmd.clone(), &mut power_controller, &cli).await
+            };
+            let result = match cli.deadline() {
+                Some(limit) => match tokio::time::timeout(limit, execution).await {
+                    Ok(result) => result,
+                    Err(_) => Err(PowerCliError::DeadlineExceeded {
+                        seconds: limit.as_secs(),
+                        phase: power_controller.connection().phase(),
+                    }
+                    .with_context(format!("while executing command {}", cmd.name()))),
+                },
+                None => execution.await,
+            };
+            power_controller.close().await;
+            emit::flush_if_line_buffered(&cli);
+            if cli.show_banner {
+                show_boot_banner(&cli, power_controller.connection().boot_banner());
+            }
+            let result = result.map_err(|e| match cli.reports_session() {
+                true => e.with_session(session_context(&cli, &config, &power_controller, started)),
+                false => e,
+            });
+
+            if let Some(stats) = power_controller.connection().cache_stats() {
+                info!(
+                    "Response cache: {} hits, {} misses, {} invalidated",
+                    stats.hits, stats.misses, stats.invalidations
+                );
+            }
+            if !cli.dry_run {
+                record_link_stats(&cli, &power_controller);
+            }
+            if !cli.no_history {
+                record_history(&cli, &power_controller, &result);
+            }
+            result
         }
         None => {
             // No command provided, show help
@@ -88,350 +335,1536 @@ async fn run(cli: Cli) -> Result<(), PowerCliError> {
     }
 }
 
-/// Output a response in the requested format
-fn output_response(
+/// Controller for `device` with the connection options of `cli` and
+/// `config`; the port is opened by the first command
+fn power_controller(
     cli: &Cli,
-    command: &str,
-    response: &str,
-    emoji: &str,
-    title: &str,
-) -> Result<(), PowerCliError> {
-    if cli.quiet {
-        return Ok(());
+    config: &config::Config,
+    device: &str,
+) -> Result<power::control::PowerController, PowerCliError> {
+    let mut connection = serial::Connection::new(device, cli.baud, cli.quiet)?;
+    connection.set_timeout(cli.timeout);
+    connection.set_pacing(std::time::Duration::from_millis(
+        cli.pacing_ms
+            .or(config.connection.pacing_ms)
+            .unwrap_or(serial::connection::DEFAULT_PACING_MS),
+    ));
+    connection.set_auto_recover_shell(cli.auto_recover_shell);
+    connection.set_fix_console(cli.fix_console);
+    connection.set_resync(cli.resync.or(config.connection.resync).unwrap_or_default());
+    connection.set_echo_check(
+        cli.verify_echo
+            .or(config.connection.verify_echo)
+            .unwrap_or_default(),
+    );
+    connection.set_timeout_retry(
+        cli.retry_timeout
+            .or(config.connection.retry_timeout)
+            .unwrap_or_default(),
+    );
+    connection.set_steal(cli.steal, config.connection.steal_unit.clone());
+    connection.set_dry_run(cli.dry_run);
+    if !cli.no_cache {
+        connection.enable_response_cache(serial::cache::DEFAULT_CACHE_TTL);
     }
+    connection.set_bootloader_probe(firmware::bootloader_probe(
+        &mcumgr_program(),
+        firmware::McumgrTransport::Serial {
+            port: device.to_string(),
+            baud: cli.baud,
+        },
+    ));
+    let mut controller = power::control::PowerController::new(connection);
+    controller.set_command_map(config.commands.clone());
+    controller.set_rail_graph(config.rails.graph()?);
+    controller.set_timeout_policy(
+        serial::TimeoutPolicy::default()
+            .with_config(&config.timeouts)
+            .with_forced(
+                cli.timeout_given
+                    .then(|| std::time::Duration::from_secs(cli.timeout)),
+            )
+            .with_guards(&config.guards)
+            .without_guards(cli.no_guard),
+    );
+    Ok(controller)
+}
 
-    match cli.format {
-        cli::OutputFormat::Human => {
-            println!("{} {}:", emoji, title);
-            println!("{}", response);
-        }
-        cli::OutputFormat::Json => {
-            // Try to parse the response into structured JSON based on command type
-            let json_data = match command {
-                cmd if cmd.contains("battery") || cmd.contains("coulomb") => {
-                    let battery_data = json::ResponseParser::parse_battery_response(response);
-                    serde_json::to_value(battery_data)?
-                }
-                cmd if cmd.contains("system") || cmd.contains("version") => {
-                    let system_data = json::ResponseParser::parse_system_info(response);
-                    serde_json::to_value(system_data)?
-                }
-                cmd if cmd.contains("nfc") => {
-                    let nfc_data = json::ResponseParser::parse_nfc_status(response);
-                    serde_json::to_value(nfc_data)?
-                }
-                cmd if cmd.contains("ltc2959") => {
-                    let ltc_data = json::ResponseParser::parse_ltc2959_status(response);
-                    serde_json::to_value(ltc_data)?
-                }
-                cmd if cmd.contains("gpio") => {
-                    // For GPIO, we need to extract port and pin from the command
-                    // This is a simplified approach - in a real implementation, you'd pass these as parameters
-                    let gpio_data =
-                        json::ResponseParser::parse_gpio_response(response, "unknown", 0);
-                    serde_json::to_value(gpio_data)?
-                }
-                cmd if cmd.contains("rtc") => {
-                    let rtc_data = json::ResponseParser::parse_rtc_status(response);
-                    serde_json::to_value(rtc_data)?
+/// How the CLI is talking to the controller
+fn connection_settings(
+    cli: &Cli,
+    controller: &power::control::PowerController,
+) -> snapshot::ConnectionSettings {
+    snapshot::ConnectionSettings {
+        device: cli.device.clone(),
+        baud: cli.baud,
+        timeout_s: cli.timeout,
+        pacing_ms: controller.connection().pacing().as_millis() as u64,
+        response_cache: !cli.no_cache,
+        auto_recover_shell: cli.auto_recover_shell,
+    }
+}
+
+/// Settings and traffic of this invocation for `--bug-report`
+fn session_context(
+    cli: &Cli,
+    config: &config::Config,
+    controller: &power::control::PowerController,
+    started: std::time::Instant,
+) -> SessionContext {
+    let config_file = cli
+        .config
+        .clone()
+        .or_else(|| config::Config::default_path().filter(|path| path.exists()));
+    SessionContext::collect(
+        controller.connection(),
+        connection_settings(cli, controller),
+        config_file,
+        config.profile.clone(),
+        started.elapsed(),
+    )
+}
+
+/// Append this invocation to the device's history log
+///
+/// Failures are logged and never affect the outcome of the command.
+fn record_history(
+    cli: &Cli,
+    controller: &power::control::PowerController,
+    result: &Result<(), ContextualError>,
+) {
+    let Some(log) = history::HistoryLog::for_device(&cli.device) else {
+        log::warn!("No state directory available; command history not recorded");
+        return;
+    };
+
+    let connection = controller.connection();
+    let entry = history::HistoryEntry::new(
+        connection.commands_sent(),
+        connection.last_response(),
+        result.as_ref().err().map_or(0, ContextualError::exit_code),
+        result.as_ref().err().map(|e| e.error.to_string()),
+    );
+
+    if let Err(e) = log.append(&entry) {
+        log::warn!(
+            "Failed to write command history to {}: {}",
+            log.path().display(),
+            e
+        );
+    }
+}
+
+/// Inspect or reset the persisted state for the device
+fn manage_state(cli: &Cli, action: &cli::StateCommands) -> Result<(), PowerCliError> {
+    let device_state = state::DeviceState::for_device(&cli.device).ok_or_else(|| {
+        PowerCliError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no state directory available",
+        ))
+    })?;
+
+    match action {
+        cli::StateCommands::Show => {
+            let files = device_state.files()?;
+            if cli.quiet {
+                return Ok(());
+            }
+            match cli.format {
+                cli::OutputFormat::Json
+                | cli::OutputFormat::Ndjson
+                | cli::OutputFormat::JsonLines => {
+                    let entries = files
+                        .iter()
+                        .map(|path| {
+                            let metadata = std::fs::metadata(path)?;
+                            let modified = metadata
+                                .modified()
+                                .ok()
+                                .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339());
+                            Ok(serde_json::json!({
+                                "file": path.file_name().map(|n| n.to_string_lossy()),
+                                "bytes": metadata.len(),
+                                "modified": modified,
+                            }))
+                        })
+                        .collect::<std::io::Result<Vec<_>>>()?;
+                    let data = serde_json::json!({
+                        "directory": device_state.dir(),
+                        "files": entries,
+                    });
+                    let json_response = json::JsonResponse::success("state show", data);
+                    emit::json(cli, &json_response)?;
                 }
                 _ => {
-                    // Generic response - just wrap the raw text
-                    serde_json::json!({
-                        "raw_response": response,
-                        "parsed": false
-                    })
+                    let mut listing = Vec::new();
+                    for path in &files {
+                        let name = path.file_name().unwrap_or_default().to_string_lossy();
+                        let bytes = std::fs::metadata(path)?.len();
+                        let contents = match name.ends_with(".json") {
+                            true => Some(std::fs::read_to_string(path)?),
+                            false => None,
+                        };
+                        listing.push((name.to_string(), bytes, contents));
+                    }
+                    render::print(&render::stored_state(
+                        &cli.output_style(),
+                        device_state.dir(),
+                        &listing,
+                    ));
                 }
-            };
-
-            let json_response = json::JsonResponse::success_with_raw(command, json_data, response);
-            println!("{}", serde_json::to_string_pretty(&json_response)?);
+            }
         }
-        cli::OutputFormat::Csv => {
-            // CSV format - simplified implementation
-            println!("timestamp,command,status,response");
-            println!(
-                "{},{},success,\"{}\"",
-                chrono::Utc::now().to_rfc3339(),
-                command,
-                response.replace("\"", "\"\"")
-            );
+        cli::StateCommands::Clear => {
+            let removed = device_state.clear()?;
+            if !cli.quiet {
+                render::print(&render::state_cleared(
+                    &cli.output_style(),
+                    removed,
+                    device_state.dir(),
+                ));
+            }
         }
     }
 
     Ok(())
 }
 
-/// Execute a specific command
-async fn execute_command(
-    command: cli::Commands,
-    controller: &mut power::control::PowerController,
+/// `fleet scan` and `fleet diff`
+async fn run_fleet(
     cli: &Cli,
+    config: &config::Config,
+    action: &cli::FleetCommands,
 ) -> Result<(), PowerCliError> {
-    use cli::Commands;
-
-    match command {
-        Commands::Version => {
-            let response = controller.get_system_info().await?;
-            output_response(cli, "version", &response, "🔧", "PMU Controller Version")?;
-        }
-        Commands::Ping => {
-            let response = controller.ping().await?;
-            output_response(cli, "ping", &response, "🏓", "Ping response")?;
+    match action {
+        cli::FleetCommands::Scan {
+            ports,
+            all_ports,
+            save,
+        } => {
+            let inventory = scan_fleet(cli, config, ports, *all_ports).await?;
+            if let Some(path) = save {
+                inventory.save(path)?;
+                info!("Fleet inventory saved to {}", path.display());
+            }
+            if !cli.quiet {
+                emit::fleet_inventory(cli, &inventory)?;
+            }
         }
-        Commands::Board(board_cmd) => {
-            use cli::BoardCommands;
-            match board_cmd {
-                BoardCommands::Reset => {
-                    let response = controller
-                        .control_board(power::control::BoardAction::Reset)
-                        .await?;
-                    if !cli.quiet {
-                        println!("🔄 Board reset initiated:");
-                        println!("{}", response);
-                    }
-                }
-                BoardCommands::Shutdown => {
-                    let response = controller
-                        .control_board(power::control::BoardAction::Shutdown)
-                        .await?;
-                    if !cli.quiet {
-                        println!("🔌 Board shutdown initiated:");
-                        println!("{}", response);
-                    }
-                }
+        cli::FleetCommands::Diff {
+            before,
+            after,
+            ports,
+            all_ports,
+        } => {
+            let before = fleet::FleetInventory::load(before)?;
+            let after = match after {
+                Some(path) => fleet::FleetInventory::load(path)?,
+                None => scan_fleet(cli, config, ports, *all_ports).await?,
+            };
+            let diff = fleet::diff(&before, &after);
+            if !cli.quiet {
+                emit::result(cli, "fleet diff", &diff, |style| {
+                    render::fleet_diff(style, &diff)
+                })?;
             }
         }
-        Commands::Ltc2959(ltc2959_cmd) => {
-            use cli::Ltc2959Commands;
-            match ltc2959_cmd {
-                Ltc2959Commands::Init => {
-                    let response = controller.control_ltc2959("init").await?;
-                    if !cli.quiet {
-                        println!("🔋 LTC2959 Initialization:");
-                        println!("{}", response);
-                    }
-                }
-                Ltc2959Commands::Read => {
-                    let response = controller.control_ltc2959("read").await?;
-                    if !cli.quiet {
-                        println!("📊 LTC2959 Readings:");
-                        println!("{}", response);
-                    }
-                }
-                Ltc2959Commands::Status => {
-                    let response = controller.control_ltc2959("status").await?;
-                    if !cli.quiet {
-                        println!("📋 LTC2959 Status:");
-                        println!("{}", response);
-                    }
-                }
-                Ltc2959Commands::Enable => {
-                    let response = controller.control_ltc2959("enable").await?;
-                    if !cli.quiet {
-                        println!("✅ LTC2959 Enabled:");
-                        println!("{}", response);
-                    }
-                }
-                Ltc2959Commands::Disable => {
-                    let response = controller.control_ltc2959("disable").await?;
-                    if !cli.quiet {
-                        println!("❌ LTC2959 Disabled:");
-                        println!("{}", response);
-                    }
-                }
-                Ltc2959Commands::Scan => {
-                    let response = controller.control_ltc2959("scan").await?;
-                    if !cli.quiet {
-                        println!("🔍 LTC2959 I2C Scan:");
-                        println!("{}", response);
-                    }
-                }
-                Ltc2959Commands::SetCharge { charge } => {
-                    let response = controller.control_ltc2959(&format!("set_charge {}", charge)).await?;
-                    if !cli.quiet {
-                        println!("🔋 LTC2959 Set Charge:");
-                        println!("{}", response);
-                    }
-                }
-                Ltc2959Commands::ChargeComplete => {
-                    let response = controller.control_ltc2959("charge_complete").await?;
-                    if !cli.quiet {
-                        println!("🔋 LTC2959 Charge Complete:");
-                        println!("{}", response);
-                    }
-                }
-                Ltc2959Commands::CcGpio { state } => {
-                    let cmd = match state {
-                        cli::PowerState::On => "cc_gpio on",
-                        cli::PowerState::Off => "cc_gpio off",
-                        cli::PowerState::Status => "cc_gpio status",
-                    };
-                    let response = controller.control_ltc2959(cmd).await?;
-                    if !cli.quiet {
-                        println!("🔌 LTC2959 CC_GPIO:");
-                        println!("{}", response);
-                    }
-                }
-                Ltc2959Commands::ProductionReset => {
-                    let response = controller.control_ltc2959("production_reset").await?;
-                    if !cli.quiet {
-                        println!("🏭 LTC2959 Production Reset:");
-                        println!("{}", response);
-                    }
-                }
-                Ltc2959Commands::AdcMode { mode } => {
-                    let response = controller.control_ltc2959(&format!("adc_mode {}", mode)).await?;
-                    if !cli.quiet {
-                        println!("🔧 LTC2959 ADC Mode:");
-                        println!("{}", response);
+    }
+    Ok(())
+}
+
+/// Probe the candidate ports one at a time
+///
+/// A port another process holds is reported busy, never taken over, so
+/// `--steal` does not apply.
+async fn scan_fleet(
+    cli: &Cli,
+    config: &config::Config,
+    ports: &[String],
+    all_ports: bool,
+) -> Result<fleet::FleetInventory, PowerCliError> {
+    let probe_cli = Cli {
+        steal: false,
+        ..cli.clone()
+    };
+    let mut entries = Vec::new();
+    for candidate in fleet::candidate_ports(ports, all_ports, &cli.device) {
+        debug!("Probing {}", candidate.port);
+        let mut controller = power_controller(&probe_cli, config, &candidate.port)?;
+        entries.push(fleet::probe(&candidate, &mut controller).await);
+        controller.close().await;
+    }
+    Ok(fleet::FleetInventory::new(entries))
+}
+
+/// `schedule`: defer a command, list or cancel schedules, or run one
+async fn manage_schedule(cli: &Cli, action: &cli::ScheduleCommands) -> Result<(), PowerCliError> {
+    let file = schedule::stored(&cli.device).ok_or_else(|| {
+        PowerCliError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no state directory available for schedules",
+        ))
+    })?;
+
+    match action {
+        cli::ScheduleCommands::At {
+            at,
+            backend,
+            command,
+        } => {
+            schedule::validate_command(command)
+                .map_err(|message| PowerCliError::InvalidArguments { message })?;
+            let mut entry = schedule::ScheduledCommand::new(
+                *at,
+                command.clone(),
+                schedule::options_of(cli),
+                *backend,
+            );
+            let exe = std::env::current_exe()?;
+            if cli.dry_run {
+                let (program, args) = match backend {
+                    schedule::ScheduleBackend::Detach => {
+                        (exe.display().to_string(), entry.runner_args())
                     }
-                }
-                Ltc2959Commands::RegRead { address } => {
-                    let response = controller.control_ltc2959(&format!("reg_read {}", address)).await?;
-                    if !cli.quiet {
-                        println!("📖 LTC2959 Register Read:");
-                        println!("{}", response);
+                    schedule::ScheduleBackend::Systemd => {
+                        (systemd_run_program(), entry.systemd_run_args(&exe))
                     }
+                };
+                println!("[dry-run] would schedule: {} {}", program, args.join(" "));
+                return Ok(());
+            }
+
+            let now = chrono::Utc::now();
+            file.update(|schedules| {
+                let mut schedules = schedules.unwrap_or_default();
+                schedules.prune(now);
+                schedules.entries.push(entry.clone());
+                schedules
+            })?;
+            let started = match backend {
+                schedule::ScheduleBackend::Detach => {
+                    let log = schedule::log_path(&cli.device, &entry.id).unwrap_or_default();
+                    schedule::spawn_detached(&exe, &entry.runner_args(), &log).map(Some)
+                }
+                schedule::ScheduleBackend::Systemd => {
+                    start_systemd_timer(&entry, &exe).map(|()| None)
                 }
-                Ltc2959Commands::RegWrite { address, value } => {
-                    let response = controller.control_ltc2959(&format!("reg_write {} {}", address, value)).await?;
-                    if !cli.quiet {
-                        println!("✍️ LTC2959 Register Write:");
-                        println!("{}", response);
+            };
+            let stored = file.update(|schedules| {
+                let mut schedules = schedules.unwrap_or_default();
+                if let Some(stored) = schedules.get_mut(&entry.id) {
+                    match &started {
+                        Ok(pid) => stored.pid = *pid,
+                        Err(_) => {
+                            stored.status = schedule::ScheduleStatus::Failed;
+                            stored.finished_at = Some(chrono::Utc::now());
+                        }
                     }
                 }
+                schedules
+            })?;
+            started.map_err(|e| PowerCliError::ControllerError {
+                message: format!("could not start the {:?} backend: {}", backend, e),
+            })?;
+            if let Some(stored) = stored.get(&entry.id) {
+                entry = stored.clone();
+            }
+
+            if !cli.quiet {
+                emit::result(cli, "schedule at", &entry, |style| {
+                    render::schedule(style, &entry)
+                })?;
             }
         }
-        Commands::Power(power_cmd) => {
-            use cli::{PowerCommands, PowerState};
-            match power_cmd {
-                PowerCommands::Pmic { state } => {
-                    let power_state = match state {
-                        PowerState::On => power::control::PowerState::On,
-                        PowerState::Off => power::control::PowerState::Off,
-                        PowerState::Status => power::control::PowerState::Status,
-                    };
-                    let response = controller.control_pmic(power_state).await?;
-                    if !cli.quiet {
-                        println!("⚡ PMIC Control:");
-                        println!("{}", response);
-                    }
-                }
-                PowerCommands::Wifi { state } => {
-                    let power_state = match state {
-                        PowerState::On => power::control::PowerState::On,
-                        PowerState::Off => power::control::PowerState::Off,
-                        PowerState::Status => power::control::PowerState::Status,
-                    };
-                    let response = controller.control_wifi(power_state).await?;
-                    if !cli.quiet {
-                        println!("📶 WiFi Control:");
-                        println!("{}", response);
-                    }
-                }
-                PowerCommands::Disp { state } => {
-                    let power_state = match state {
-                        PowerState::On => power::control::PowerState::On,
-                        PowerState::Off => power::control::PowerState::Off,
-                        PowerState::Status => power::control::PowerState::Status,
-                    };
-                    let response = controller.control_display(power_state).await?;
-                    if !cli.quiet {
-                        println!("🖥️ Display Control:");
-                        println!("{}", response);
-                    }
-                }
-                PowerCommands::Stats => {
-                    let stats = controller.get_power_stats().await?;
-                    if !cli.quiet {
-                        println!("{}", stats.format_human());
-                    }
-                }
-                PowerCommands::Coulomb => {
-                    let response = controller.get_coulomb_counter().await?;
-                    output_response(cli, "power coulomb", &response, "🔋", "Coulomb Counter")?;
-                }
+        cli::ScheduleCommands::List => {
+            let mut entries = file.load()?.unwrap_or_default().entries;
+            entries.sort_by_key(|entry| entry.due);
+            if !cli.quiet {
+                emit::result(cli, "schedule list", &entries, |style| {
+                    render::schedules(style, &cli.device, &entries)
+                })?;
             }
         }
-        Commands::Gpio(gpio_cmd) => {
-            use cli::GpioCommands;
-            match gpio_cmd {
-                GpioCommands::Get { port, pin } => {
-                    let response = controller
-                        .control_gpio(&port, pin, power::control::GpioAction::Get)
-                        .await?;
-                    if !cli.quiet {
-                        println!("📌 GPIO {}{}:", port, pin);
-                        println!("{}", response);
-                    }
-                }
-                GpioCommands::Set { port, pin, value } => {
-                    let response = controller
-                        .control_gpio(&port, pin, power::control::GpioAction::Set(value))
-                        .await?;
-                    if !cli.quiet {
-                        println!("📌 GPIO {}{} set to {}:", port, pin, value);
-                        println!("{}", response);
-                    }
-                }
-                GpioCommands::Config { port, pin, mode } => {
-                    let response = controller.control_gpio_config(&port, pin, &mode).await?;
-                    if !cli.quiet {
-                        println!("📌 GPIO {}{} configured to {}:", port, pin, mode);
-                        println!("{}", response);
+        cli::ScheduleCommands::Cancel { id } => {
+            let mut outcome = Err(format!("no schedule with id '{}'", id));
+            if !cli.dry_run {
+                file.update(|schedules| {
+                    let mut schedules = schedules.unwrap_or_default();
+                    if let Some(entry) = schedules.get_mut(id) {
+                        outcome = match entry.status {
+                            schedule::ScheduleStatus::Pending => {
+                                entry.status = schedule::ScheduleStatus::Cancelled;
+                                entry.finished_at = Some(chrono::Utc::now());
+                                Ok(entry.clone())
+                            }
+                            status => Err(format!(
+                                "schedule '{}' is {} and cannot be cancelled",
+                                id,
+                                status.as_str()
+                            )),
+                        };
                     }
+                    schedules
+                })?;
+            } else if let Some(entry) = file.load()?.unwrap_or_default().get(id) {
+                outcome = Ok(entry.clone());
+            }
+            let entry = outcome.map_err(|message| PowerCliError::InvalidArguments { message })?;
+
+            // A detached runner notices the cancellation when it next polls
+            if let Some(unit) = entry.unit.as_deref().filter(|_| !cli.dry_run) {
+                let stopped = std::process::Command::new("systemctl")
+                    .args(["--user", "stop", &format!("{}.timer", unit)])
+                    .status();
+                if !stopped.is_ok_and(|status| status.success()) {
+                    warn!(
+                        "Could not stop {}.timer; it will find the schedule cancelled",
+                        unit
+                    );
                 }
             }
+            if !cli.quiet {
+                emit::result(cli, "schedule cancel", &entry, |style| {
+                    render::schedule(style, &entry)
+                })?;
+            }
         }
-        Commands::System(system_cmd) => {
-            use cli::{EraseCommands, SystemCommands};
-            match system_cmd {
-                SystemCommands::Info => {
-                    let response = controller.get_system_info_detailed().await?;
-                    output_response(cli, "system info", &response, "🖥️", "System Information")?;
-                }
-                SystemCommands::Reboot { cold } => {
-                    let cmd = if cold { "system reset cold" } else { "system reset" };
-                    let response = controller.pm_command(cmd).await?;
-                    output_response(cli, "system reboot", &response, "🔄", "System Reboot")?;
-                }
-                SystemCommands::Uptime => {
-                    let response = controller.get_system_uptime().await?;
-                    output_response(cli, "system uptime", &response, "⏱️", "System Uptime")?;
-                }
-                SystemCommands::DfuMode { timeout } => {
-                    let response = controller.pm_command(&format!("system dfu-mode {}", timeout)).await?;
-                    output_response(cli, "system dfu-mode", &response, "🔄", "DFU Mode")?;
-                }
-                SystemCommands::Erase(erase_cmd) => {
-                    match erase_cmd {
-                        EraseCommands::App => {
-                            let response = controller.pm_command("system erase app").await?;
-                            output_response(cli, "system erase app", &response, "🗑️", "Erase Application")?;
-                        }
-                        EraseCommands::Defaults => {
-                            let response = controller.pm_command("system erase defaults").await?;
-                            output_response(cli, "system erase defaults", &response, "🗑️", "Erase Defaults")?;
-                        }
-                    }
+        cli::ScheduleCommands::Run { id } => run_schedule(&file, id).await?,
+    }
+
+    Ok(())
+}
+
+/// Wait for schedule `id` to come due, then run its command as a new
+/// invocation; returns early if it is cancelled or already taken
+async fn run_schedule(
+    file: &state::StateFile<schedule::Schedules>,
+    id: &str,
+) -> Result<(), PowerCliError> {
+    let entry = loop {
+        let now = chrono::Utc::now();
+        let mut claimed = None;
+        let schedules = file.update(|schedules| {
+            let mut schedules = schedules.unwrap_or_default();
+            if let Some(entry) = schedules.get_mut(id) {
+                if entry.status == schedule::ScheduleStatus::Pending && entry.due <= now {
+                    entry.status = schedule::ScheduleStatus::Running;
+                    claimed = Some(entry.clone());
                 }
             }
+            schedules
+        })?;
+        match (claimed, schedules.get(id)) {
+            (Some(entry), _) => break entry,
+            (None, Some(entry)) if entry.status == schedule::ScheduleStatus::Pending => {
+                let wait = (entry.due - now).to_std().unwrap_or_default();
+                tokio::time::sleep(wait.min(schedule::CANCEL_POLL)).await;
+            }
+            (None, entry) => {
+                info!(
+                    "Schedule {} is {}; not running it",
+                    id,
+                    entry.map_or("gone", |entry| entry.status.as_str())
+                );
+                return Ok(());
+            }
         }
-        Commands::Battery(battery_cmd) => {
-            use cli::BatteryCommands;
+    };
+
+    info!("Running schedule {}: {}", entry.id, entry.command_line());
+    let status = tokio::process::Command::new(std::env::current_exe()?)
+        .args(&entry.options)
+        .args(&entry.command)
+        .status()
+        .await;
+    file.update(|schedules| {
+        let mut schedules = schedules.unwrap_or_default();
+        if let Some(stored) = schedules.get_mut(id) {
+            let succeeded = status.as_ref().is_ok_and(|status| status.success());
+            stored.status = match succeeded {
+                true => schedule::ScheduleStatus::Done,
+                false => schedule::ScheduleStatus::Failed,
+            };
+            stored.exit_status = status.as_ref().ok().and_then(|status| status.code());
+            stored.finished_at = Some(chrono::Utc::now());
+        }
+        schedules
+    })?;
+    status?;
+    Ok(())
+}
+
+/// Start the systemd transient timer that runs `entry`
+fn start_systemd_timer(
+    entry: &schedule::ScheduledCommand,
+    exe: &std::path::Path,
+) -> std::io::Result<()> {
+    let output = std::process::Command::new(systemd_run_program())
+        .args(entry.systemd_run_args(exe))
+        .stdin(std::process::Stdio::null())
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Display recorded history for the device
+fn show_history(cli: &Cli, last: usize, grep: Option<&str>) -> Result<(), PowerCliError> {
+    let log = history::HistoryLog::for_device(&cli.device).ok_or_else(|| {
+        PowerCliError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no state directory available for command history",
+        ))
+    })?;
+    let entries = log.query(last, grep)?;
+
+    if cli.quiet {
+        return Ok(());
+    }
+
+    emit::result(cli, "history", &entries, |style| {
+        render::history(style, &cli.device, &entries)
+    })
+}
+
+/// Work on battery log files; needs no device
+fn manage_log(cli: &Cli, action: &cli::LogCommands) -> Result<(), PowerCliError> {
+    match action {
+        cli::LogCommands::Export {
+            input,
+            output,
+            format,
+            from,
+            to,
+            capacity,
+            show_unparsed,
+        } => {
+            let report = battery_log::Export {
+                input: input.clone(),
+                output: output.clone(),
+                format: *format,
+                from: *from,
+                to: *to,
+                capacity_mah: *capacity,
+                show_unparsed: *show_unparsed,
+            }
+            .run()?;
+            if !cli.quiet {
+                emit::result(cli, "log export", &report, |style| {
+                    render::log_export(style, &report)
+                })?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Add this invocation's serial traffic to the device's running total
+///
+/// Failures are logged and never affect the outcome of the command.
+/// Write the `monitor` status file; a failed write is only a warning
+fn write_status(file: &status::StatusFile, document: &mut status::StatusDocument) {
+    if let Err(e) = file.write(document) {
+        log::warn!("Failed to write {}: {}", file.path().display(), e);
+    }
+}
+
+/// Next timed status file write; never for a per-sample file or none
+async fn tick_status(file: &mut Option<status::StatusFile>) {
+    match file {
+        Some(file) => file.tick().await,
+        None => std::future::pending().await,
+    }
+}
+
+fn record_link_stats(cli: &Cli, controller: &power::control::PowerController) {
+    let session = controller.connection_stats();
+    if session.is_empty() {
+        return;
+    }
+    let Some(file) = serial::stats::stored(&cli.device) else {
+        return;
+    };
+    let updated = file.update(|total| {
+        let mut total = total.unwrap_or_default();
+        total.merge(session);
+        total
+    });
+    if let Err(e) = updated {
+        log::warn!("Failed to update link statistics: {}", e);
+    }
+}
+
+/// Print the link statistics recorded for the device, optionally resetting
+/// them
+fn show_link_stats(cli: &Cli, reset: bool) -> Result<(), PowerCliError> {
+    let file = serial::stats::stored(&cli.device).ok_or_else(|| {
+        PowerCliError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "no state directory available for link statistics",
+        ))
+    })?;
+    let stats = file.load()?.unwrap_or_default();
+    if reset && !cli.dry_run {
+        file.remove()?;
+    }
+
+    if cli.quiet {
+        return Ok(());
+    }
+
+    emit::link_stats(cli, &stats, reset)
+}
+
+/// Print the registered example invocations matching `filter`
+fn show_examples(cli: &Cli, filter: Option<&str>) -> Result<(), PowerCliError> {
+    let examples = cli::examples::matching(filter);
+
+    if cli.quiet {
+        return Ok(());
+    }
+
+    emit::result(cli, "examples", &examples, |style| {
+        render::examples(style, filter, &examples)
+    })
+}
+
+/// Renamed subcommands and the release dropping their old names
+fn show_migrations(cli: &Cli) -> Result<(), PowerCliError> {
+    if cli.quiet {
+        return Ok(());
+    }
+
+    let deprecations = cli::deprecations::DEPRECATIONS;
+    emit::result(cli, "migrations", &deprecations, |style| {
+        render::migrations(style, deprecations)
+    })
+}
+
+/// `parse-check`: parse coverage of a transcript corpus against its baseline
+fn parse_check(
+    cli: &Cli,
+    dir: &std::path::Path,
+    baseline: Option<&std::path::Path>,
+    update_baseline: bool,
+) -> Result<(), PowerCliError> {
+    use json::coverage::{CoverageBaseline, CoverageReport, BASELINE_FILE};
+
+    let mut report = CoverageReport::scan(dir)?;
+    let path = baseline.map_or_else(|| dir.join(BASELINE_FILE), |path| path.to_path_buf());
+    if update_baseline {
+        let recorded = CoverageBaseline::of(&report);
+        recorded.save(&path)?;
+        info!("Parse coverage baseline saved to {}", path.display());
+        report.check(&recorded, &path);
+    } else {
+        match CoverageBaseline::load(&path)? {
+            Some(recorded) => report.check(&recorded, &path),
+            None => warn!(
+                "No baseline at {}; run with --update-baseline to record one",
+                path.display()
+            ),
+        }
+    }
+
+    if !cli.quiet {
+        emit::result(cli, "parse-check", &report, |style| {
+            render::parse_coverage(style, &report)
+        })?;
+    }
+    if !report.passed {
+        return Err(PowerCliError::ParseCoverage {
+            message: report.failures().join("; "),
+        });
+    }
+    Ok(())
+}
+
+/// `self-update`: install the latest release over this executable, or with
+/// `check_only` report whether there is one
+fn self_update(
+    cli: &Cli,
+    config: &config::Config,
+    channel: Option<&str>,
+    url: Option<&str>,
+    check_only: bool,
+) -> Result<(), PowerCliError> {
+    let url = url
+        .or(config.update.url.as_deref())
+        .ok_or_else(|| PowerCliError::InvalidArguments {
+            message: "no artifact server: pass --url or set url in the [update] section of the configuration file"
+                .to_string(),
+        })?;
+    let channel = channel
+        .or(config.update.channel.as_deref())
+        .unwrap_or(update::DEFAULT_CHANNEL);
+    let source = update::UpdateSource::new(url, channel);
+    let current = semver::Version::parse(VERSION).expect("package version is semver");
+    let executable = std::env::current_exe()?;
+
+    let report = update::self_update(
+        &source,
+        &current,
+        config.update.public_key.as_deref(),
+        check_only,
+        &executable,
+    )?;
+    if !cli.quiet {
+        emit::result(cli, "self-update", &report, |style| {
+            render::self_update(style, &report)
+        })?;
+    }
+    if report.status == update::UpdateStatus::Available {
+        return Err(PowerCliError::UpdateAvailable {
+            current: report.current_version,
+            latest: report.latest_version,
+        });
+    }
+    Ok(())
+}
+
+/// `run --list`: the macros in the configuration file
+fn show_macros(cli: &Cli, config: &config::Config) -> Result<(), PowerCliError> {
+    if cli.quiet {
+        return Ok(());
+    }
+
+    let listing = macros::listing(&config.macros);
+    emit::result(cli, "run list", &listing, |style| {
+        render::macro_listing(style, &listing)
+    })
+}
+
+/// First-run setup: pick the port, save the defaults and check the result
+async fn run_setup(
+    cli: &Cli,
+    yes: bool,
+    force: bool,
+    profile: Option<String>,
+    capacity: Option<u32>,
+) -> Result<(), PowerCliError> {
+    use std::io::IsTerminal;
+
+    let path = cli
+        .config
+        .clone()
+        .or_else(config::Config::default_path)
+        .ok_or_else(|| PowerCliError::InvalidArguments {
+            message: "no configuration directory available; pass --config".to_string(),
+        })?;
+    let wizard = setup::Setup {
+        path,
+        force,
+        baud: cli.baud,
+        answers: setup::SetupAnswers {
+            device: cli.device.clone(),
+            format: cli.format.clone(),
+            timeout: cli.timeout,
+            capacity_mah: capacity,
+            profile,
+        },
+    };
+
+    let report = if yes {
+        wizard.run(None::<&mut setup::TerminalPrompter>).await?
+    } else if std::io::stdin().is_terminal() {
+        wizard.run(Some(&mut setup::terminal())).await?
+    } else {
+        return Err(PowerCliError::InvalidCommand {
+            command: "setup needs --yes when not run interactively".to_string(),
+        });
+    };
+
+    if !cli.quiet {
+        emit::result(cli, "setup", &report, |style| render::setup(style, &report))?;
+    }
+    let failed = report.failed_checks();
+    if !failed.is_empty() {
+        return Err(PowerCliError::ControllerError {
+            message: format!(
+                "configuration saved, but checks failed: {}",
+                failed.join(", ")
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Execute a specific command, recording what was being done on failure
+async fn execute_command(
+    command: cli::Commands,
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+) -> Result<(), ContextualError> {
+    let name = command.name();
+    let result = run_command(command, controller, cli).await;
+    let result = match controller.connection().error_context() {
+        Some(ctx) => result.map_err(|e| e.with_context(ctx)),
+        None => result.map_err(ContextualError::from),
+    };
+    crate::context!(result, "while executing command {}", name)
+}
+
+/// Pin named by a `gpio` command's port and pin arguments
+fn gpio_pin(port: &str, pin: Option<u8>) -> Result<power::gpio::GpioPin, PowerCliError> {
+    power::gpio::GpioPin::from_parts(port, pin)
+        .map_err(|message| PowerCliError::InvalidArguments { message })
+}
+
+/// The pin as the user typed it, e.g. `gpioa 5` or `PTA5`
+fn typed_pin(port: &str, pin: Option<u8>) -> String {
+    match pin {
+        Some(pin) => format!("{} {}", port, pin),
+        None => port.to_string(),
+    }
+}
+
+/// Check the rail dependency model before switching `rail` to `state`
+///
+/// Switching on returns the rails switched on first with `--auto-deps`.
+/// Switching off warns about rails still on that depend on `rail`.
+async fn check_rail_dependencies(
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+    rail: power::rails::PowerRail,
+    state: &cli::PowerState,
+) -> Result<Vec<(power::rails::PowerRail, String)>, PowerCliError> {
+    match state {
+        cli::PowerState::On => controller.prepare_rail_on(rail, cli.auto_deps).await,
+        cli::PowerState::Off => {
+            let dependents = controller.dependents_still_on(rail).await?;
+            if !dependents.is_empty() && !cli.quiet {
+                let names: Vec<&str> = dependents.iter().map(|dep| dep.name()).collect();
+                eprintln!(
+                    "warning: {} still on and depending on {}",
+                    names.join(", "),
+                    rail.name()
+                );
+            }
+            Ok(Vec::new())
+        }
+        cli::PowerState::Status => Ok(Vec::new()),
+    }
+}
+
+/// `power <rail>` and `pm <rail>`: switch a rail, or show its state
+///
+/// `family` is the command the rail was given under, `power` or `pm`.
+async fn switch_rail(
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+    family: &str,
+    rail: Rail,
+    state: cli::PowerState,
+) -> Result<(), PowerCliError> {
+    let enabled_first = match rail.switched_rail() {
+        Some(switched) => check_rail_dependencies(controller, cli, switched, &state).await?,
+        None => Vec::new(),
+    };
+    let response = controller.control_rail(rail, state.into()).await?;
+    let (icon, title) = match rail {
+        Rail::Pmic => ("⚡", "PMIC Control"),
+        Rail::Wifi => ("📶", "WiFi Control"),
+        Rail::Display => ("🖥️", "Display Control"),
+        Rail::Imx93 => ("🖥️", "i.MX93 Power Control"),
+        Rail::All => ("⚡", "All Power Rails"),
+    };
+    match state {
+        cli::PowerState::Status => {
+            if !cli.quiet {
+                emit::titled(cli, icon, title, &response);
+            }
+            Ok(())
+        }
+        _ => emit::rail_change(
+            cli,
+            &format!("{} {}", family, rail),
+            rail,
+            &response,
+            icon,
+            title,
+            &enabled_first,
+        ),
+    }
+}
+
+/// `pm defaults <rail>`: set the state a rail comes up in
+async fn set_rail_default(
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+    rail: Rail,
+    state: cli::PowerState,
+) -> Result<(), PowerCliError> {
+    let response = controller.rail_default(rail, state.into()).await?;
+    emit::response(
+        cli,
+        &format!("pm defaults {}", rail),
+        &json::ParsedPayload::RailDefaults,
+        &response,
+        "⚙️",
+        &format!("{} Default", rail.name()),
+    )
+}
+
+/// Execute a specific command
+async fn run_command(
+    command: cli::Commands,
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+) -> Result<(), PowerCliError> {
+    use cli::Commands;
+
+    let command = command.resolve_alias();
+    let payload = command.payload();
+    match command {
+        Commands::Version => {
+            let response = controller.get_system_info().await?;
+            emit::response(
+                cli,
+                "version",
+                &payload,
+                &response,
+                "🔧",
+                "PMU Controller Version",
+            )?;
+        }
+        Commands::Ping => {
+            let response = controller.ping().await?;
+            emit::response(cli, "ping", &payload, &response, "🏓", "Ping response")?;
+        }
+        Commands::Board(board_cmd) => {
+            use cli::BoardCommands;
+            match board_cmd {
+                BoardCommands::Reset => {
+                    let response = controller
+                        .control_board(power::control::BoardAction::Reset)
+                        .await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "🔄", "Board reset initiated", &response);
+                    }
+                }
+                BoardCommands::Shutdown => {
+                    let response = controller
+                        .control_board(power::control::BoardAction::Shutdown)
+                        .await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "🔌", "Board shutdown initiated", &response);
+                    }
+                }
+            }
+        }
+        Commands::Ltc2959(ltc2959_cmd) => {
+            use cli::Ltc2959Commands;
+            match ltc2959_cmd {
+                Ltc2959Commands::Init => {
+                    let response = controller.control_ltc2959("init").await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "🔋", "LTC2959 Initialization", &response);
+                    }
+                }
+                Ltc2959Commands::Read {
+                    continuous: true,
+                    interval_ms,
+                    duration,
+                } => {
+                    let read = power::ltc2959::ContinuousRead {
+                        interval: std::time::Duration::from_millis(interval_ms),
+                        duration,
+                    };
+                    read_ltc2959_continuous(controller, cli, read).await?;
+                }
+                Ltc2959Commands::Read { .. } => {
+                    let response = controller.control_ltc2959("read").await?;
+                    emit::response(
+                        cli,
+                        "ltc2959 read",
+                        &payload,
+                        &response,
+                        "📊",
+                        "LTC2959 Readings",
+                    )?;
+                }
+                Ltc2959Commands::Status => {
+                    let response = controller.control_ltc2959("status").await?;
+                    emit::response(
+                        cli,
+                        "ltc2959 status",
+                        &payload,
+                        &response,
+                        "📋",
+                        "LTC2959 Status",
+                    )?;
+                }
+                Ltc2959Commands::Enable => {
+                    let response = controller.control_ltc2959("enable").await?;
+                    emit::state_change(cli, "ltc2959 enable", &response, "✅", "LTC2959 Enabled")?;
+                }
+                Ltc2959Commands::Disable => {
+                    let response = controller.control_ltc2959("disable").await?;
+                    emit::state_change(
+                        cli,
+                        "ltc2959 disable",
+                        &response,
+                        "❌",
+                        "LTC2959 Disabled",
+                    )?;
+                }
+                Ltc2959Commands::Scan => {
+                    let response = controller.control_ltc2959("scan").await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "🔍", "LTC2959 I2C Scan", &response);
+                    }
+                }
+                Ltc2959Commands::SetCharge { charge } => {
+                    let response = controller
+                        .control_ltc2959(&format!("set_charge {}", charge))
+                        .await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "🔋", "LTC2959 Set Charge", &response);
+                    }
+                }
+                Ltc2959Commands::ZeroCharge { yes } => {
+                    reset_charge(controller, cli, "ltc2959 zero-charge", yes).await?;
+                }
+                Ltc2959Commands::ChargeComplete => {
+                    let response = controller.control_ltc2959("charge_complete").await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "🔋", "LTC2959 Charge Complete", &response);
+                    }
+                }
+                Ltc2959Commands::Gpio { state } => {
+                    let cmd = match state {
+                        cli::PowerState::On => "cc_gpio on",
+                        cli::PowerState::Off => "cc_gpio off",
+                        cli::PowerState::Status => "cc_gpio status",
+                    };
+                    let response = controller.control_ltc2959(cmd).await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "🔌", "LTC2959 CC_GPIO", &response);
+                    }
+                }
+                Ltc2959Commands::ProductionReset => {
+                    let response = controller.control_ltc2959("production_reset").await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "🏭", "LTC2959 Production Reset", &response);
+                    }
+                }
+                Ltc2959Commands::AdcMode { mode } => {
+                    let response = controller
+                        .control_ltc2959(&format!("adc_mode {}", mode))
+                        .await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "🔧", "LTC2959 ADC Mode", &response);
+                    }
+                }
+                Ltc2959Commands::RegRead { address } => {
+                    let response = controller
+                        .control_ltc2959(&format!("reg_read {}", address))
+                        .await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "📖", "LTC2959 Register Read", &response);
+                    }
+                }
+                Ltc2959Commands::RegWrite { address, value } => {
+                    let response = controller
+                        .control_ltc2959(&format!("reg_write {} {}", address, value))
+                        .await?;
+                    if !cli.quiet {
+                        emit::titled(cli, "✍️", "LTC2959 Register Write", &response);
+                    }
+                }
+            }
+        }
+        Commands::Power(power_cmd) => {
+            use cli::PowerCommands;
+            match power_cmd {
+                PowerCommands::Pmic { state } => {
+                    switch_rail(controller, cli, "power", Rail::Pmic, state).await?;
+                }
+                PowerCommands::Wifi { state } => {
+                    switch_rail(controller, cli, "power", Rail::Wifi, state).await?;
+                }
+                PowerCommands::Display { state } => {
+                    switch_rail(controller, cli, "power", Rail::Display, state).await?;
+                }
+                PowerCommands::Stats => {
+                    let report = controller.power_stats_report().await?;
+                    if !cli.quiet {
+                        emit::result(cli, "power stats", &report, |style| {
+                            render::power_stats_report(style, &report)
+                        })?;
+                    }
+                }
+                PowerCommands::Coulomb { reset: true, yes } => {
+                    reset_charge(controller, cli, "power coulomb reset", yes).await?;
+                }
+                PowerCommands::Coulomb { reset: false, .. } => {
+                    let response = controller.get_coulomb_counter().await?;
+                    emit::response(
+                        cli,
+                        "power coulomb",
+                        &payload,
+                        &response,
+                        "🔋",
+                        "Coulomb Counter",
+                    )?;
+                }
+                PowerCommands::Sequence { rails } => {
+                    let responses = controller.sequence_power_on(&rails).await?;
+                    if !cli.quiet {
+                        render::print(&render::power_sequence(&cli.output_style(), &responses));
+                    }
+                }
+            }
+        }
+        Commands::Gpio(gpio_cmd) => {
+            use cli::GpioCommands;
+            match gpio_cmd {
+                GpioCommands::Get { port, pin } => {
+                    let gpio = gpio_pin(&port, pin)?;
+                    let response = controller
+                        .control_gpio(
+                            &gpio.firmware_port(),
+                            gpio.pin,
+                            power::control::GpioAction::Get,
+                        )
+                        .await?;
+                    if !cli.quiet {
+                        let names = gpio.names(&typed_pin(&port, pin));
+                        let title = format!("GPIO {}", names.describe());
+                        let parsed = json::GpioJson {
+                            names: Some(names),
+                            ..json::ResponseParser::parse_gpio_response(
+                                &response, &gpio.port, gpio.pin,
+                            )
+                        };
+                        emit::result(cli, "gpio get", &parsed, |style| {
+                            render::gpio(style, &parsed)
+                                .unwrap_or_else(|| render::titled(style, "📌", &title, &response))
+                        })?;
+                    }
+                }
+                GpioCommands::Set { port, pin, value } => {
+                    // With the pin in PORT, the second argument is the value
+                    let (pin, value) = match value {
+                        Some(value) => (Some(pin), value),
+                        None => (None, pin),
+                    };
+                    let gpio = gpio_pin(&port, pin)?;
+                    let response = controller
+                        .control_gpio(
+                            &gpio.firmware_port(),
+                            gpio.pin,
+                            power::control::GpioAction::Set(value),
+                        )
+                        .await?;
+                    let names = gpio.names(&typed_pin(&port, pin));
+                    let title = format!("GPIO {} set to {}", names.describe(), value);
+                    emit::state_change(cli, "gpio set", &response, "📌", &title)?;
+                }
+                GpioCommands::Config {
+                    port,
+                    pin,
+                    mode,
+                    pins,
+                    pins_mode,
+                } => {
+                    let (targets, mode, typed) = match (port, pin, mode) {
+                        (Some(port), Some(pin), Some(mode)) => {
+                            let number =
+                                pin.parse::<u8>()
+                                    .map_err(|_| PowerCliError::InvalidArguments {
+                                        message: format!("'{}' is not a pin number", pin),
+                                    })?;
+                            let gpio = gpio_pin(&port, Some(number))?;
+                            (vec![gpio], mode, Some(format!("{} {}", port, pin)))
+                        }
+                        // With the pin in PORT, the second argument is the mode
+                        (Some(port), Some(mode), None) => {
+                            (vec![gpio_pin(&port, None)?], mode, Some(port))
+                        }
+                        _ => (pins, pins_mode.unwrap_or_default(), None),
+                    };
+                    let mut results = Vec::new();
+                    for target in &targets {
+                        let mut result = controller.configure_gpio(target, &mode).await?;
+                        if let Some(typed) = &typed {
+                            result.actual.names = Some(target.names(typed));
+                        }
+                        results.push(result);
+                    }
+                    let report = power::gpio::GpioConfigReport::new(&mode, results);
+
+                    if !cli.quiet {
+                        emit::result(cli, "gpio config", &report, |style| {
+                            render::gpio_config(style, &report)
+                        })?;
+                    }
+                    if let Some(error) = report.mismatch_error() {
+                        return Err(error);
+                    }
+                }
+                GpioCommands::Script { ops, host } => {
+                    let mode = if !host && controller.supports_gpio_batch().await {
+                        power::gpio::GpioScriptMode::Firmware
+                    } else {
+                        power::gpio::GpioScriptMode::Host
+                    };
+                    let report = controller.run_gpio_script(&ops, mode).await?;
+
+                    if !cli.quiet {
+                        emit::result(cli, "gpio script", &report, |style| {
+                            render::gpio_script(style, &report)
+                        })?;
+                    }
+                    if let Some(error) = report.failure_error() {
+                        return Err(error);
+                    }
+                }
+            }
+        }
+        Commands::System(system_cmd) => {
+            use cli::{EraseCommands, SystemCommands};
+            match system_cmd {
+                SystemCommands::Info {
+                    identity: false,
+                    expect_version: None,
+                } => {
+                    let response = controller.get_system_info_detailed().await?;
+                    emit::response(
+                        cli,
+                        "system info",
+                        &payload,
+                        &response,
+                        "🖥️",
+                        "System Information",
+                    )?;
+                }
+                SystemCommands::Info {
+                    identity: read_identity,
+                    expect_version,
+                } => {
+                    let response = controller.get_system_info_detailed().await?;
+                    // A missing identity must not fail the rest of the report
+                    let identity = if read_identity {
+                        controller.read_identity().await.unwrap_or_else(|e| {
+                            log::warn!("Could not read device identity: {}", e);
+                            None
+                        })
+                    } else {
+                        None
+                    };
+                    let (info, diagnostics) = json::diagnostics::collect(|| {
+                        json::ResponseParser::parse_system_info(&response)
+                    });
+                    let mut info = info.with_identity(identity.as_ref());
+                    info.version_ok = expect_version
+                        .as_ref()
+                        .map(|requirement| info.meets_version(requirement));
+                    match cli.format {
+                        cli::OutputFormat::Json
+                        | cli::OutputFormat::Ndjson
+                        | cli::OutputFormat::JsonLines
+                            if !cli.quiet =>
+                        {
+                            let mut json_response = json::JsonResponse::success(
+                                "system info",
+                                serde_json::to_value(&info)?,
+                            )
+                            .with_raw(&response, &cli.raw_options());
+                            if cli.explains_parse() {
+                                json_response.parse_diagnostics = Some(diagnostics);
+                            }
+                            emit::json(cli, &json_response)?;
+                        }
+                        _ => {
+                            emit::response(
+                                cli,
+                                "system info",
+                                &payload,
+                                &response,
+                                "🖥️",
+                                "System Information",
+                            )?;
+                            if !cli.quiet && matches!(cli.format, cli::OutputFormat::Human) {
+                                let style = cli.output_style();
+                                if read_identity {
+                                    render::print(&render::identity(&style, identity.as_ref()));
+                                }
+                                if let (Some(requirement), Some(ok)) =
+                                    (&expect_version, info.version_ok)
+                                {
+                                    render::print(&render::version_check(
+                                        &style,
+                                        info.version.as_deref(),
+                                        requirement,
+                                        ok,
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                    if let (Some(requirement), Some(false)) = (&expect_version, info.version_ok) {
+                        return Err(PowerCliError::VersionMismatch {
+                            version: info.version.unwrap_or_else(|| "unknown".to_string()),
+                            requirement: requirement.to_string(),
+                        });
+                    }
+                }
+                SystemCommands::Reboot { cold } => {
+                    let response = controller.reboot_system(cold).await?;
+                    forget_time_reference(cli);
+                    emit::response(
+                        cli,
+                        "system reboot",
+                        &payload,
+                        &response,
+                        "🔄",
+                        "System Reboot",
+                    )?;
+                }
+                SystemCommands::Uptime => {
+                    let response = controller.get_system_uptime().await?;
+                    emit::response(
+                        cli,
+                        "system uptime",
+                        &payload,
+                        &response,
+                        "⏱️",
+                        "System Uptime",
+                    )?;
+                }
+                SystemCommands::TimeRef { at } => {
+                    let fresh = controller.time_reference().await?;
+                    let file = power::timeref::stored(&cli.device);
+                    let stored = match &file {
+                        Some(file) => file.load()?,
+                        None => None,
+                    };
+                    let report = power::timeref::TimeRefReport::new(stored, fresh, at);
+                    if report.replaced_stale {
+                        log::warn!(
+                            "PMU rebooted since the saved time reference; \
+                             timestamps from before the reboot cannot be translated"
+                        );
+                    }
+                    match &file {
+                        Some(file) if !report.reused => file.store(&report.reference)?,
+                        Some(_) => {}
+                        None => log::warn!("No state directory; time reference not saved"),
+                    }
+                    if !cli.quiet {
+                        emit::result(cli, "system time-ref", &report, |style| {
+                            render::time_ref(style, &report)
+                        })?;
+                    }
+                }
+                SystemCommands::DfuMode {
+                    timeout,
+                    cancel,
+                    yes,
+                } => {
+                    if !cancel
+                        && timeout == 0
+                        && !yes
+                        && !cli.dry_run
+                        && !confirm_destructive(
+                            "A timeout of 0 keeps the bootloader waiting until it is reset. The application will not boot again until 'system dfu-mode --cancel' or a power cycle.",
+                        )?
+                    {
+                        return Err(PowerCliError::InvalidCommand {
+                            command: "infinite DFU mode not confirmed".to_string(),
+                        });
+                    }
+
+                    let transports =
+                        firmware::FirmwareTransports::resolve(&cli.device, cli.baud, None, None);
+                    let mut firmware_manager = firmware_manager(cli, controller, transports)?;
+                    let outcome = async {
+                        if cancel {
+                            let report = firmware_manager.exit_bootloader().await?;
+                            if !cli.quiet {
+                                emit::result(cli, "system dfu-mode cancel", &report, |style| {
+                                    render::dfu_cancel(style, &report)
+                                })?;
+                            }
+                        } else {
+                            let report = firmware_manager.enter_dfu_mode(timeout).await?;
+                            if !cli.quiet {
+                                emit::result(cli, "system dfu-mode", &report, |style| {
+                                    render::dfu_mode(style, &report)
+                                })?;
+                            }
+                        }
+                        Ok::<(), PowerCliError>(())
+                    }
+                    .await;
+                    firmware_manager.close().await;
+                    outcome?;
+                }
+                SystemCommands::Erase(erase_cmd) => match erase_cmd {
+                    EraseCommands::App => {
+                        let response = controller.pm_command("system erase app").await?;
+                        emit::response(
+                            cli,
+                            "system erase app",
+                            &payload,
+                            &response,
+                            "🗑️",
+                            "Erase Application",
+                        )?;
+                    }
+                    EraseCommands::Defaults => {
+                        let response = controller.pm_command("system erase defaults").await?;
+                        emit::response(
+                            cli,
+                            "system erase defaults",
+                            &payload,
+                            &response,
+                            "🗑️",
+                            "Erase Defaults",
+                        )?;
+                    }
+                },
+                SystemCommands::Verify {
+                    require_production,
+                    min_version,
+                } => {
+                    let response = controller.get_system_info_detailed().await?;
+                    let info = json::ResponseParser::parse_system_info(&response);
+                    let violations =
+                        info.policy_violations(require_production, min_version.as_deref())?;
+
+                    if !cli.quiet {
+                        let version = info.version.clone();
+                        let verify = json::output::SystemVerifyJson {
+                            passed: violations.is_empty(),
+                            violations: violations.clone(),
+                            system: info,
+                        };
+                        emit::result(cli, "system verify", &verify, |style| {
+                            render::verify(style, version.as_deref(), &violations)
+                        })?;
+                    }
+
+                    if !violations.is_empty() {
+                        return Err(PowerCliError::FirmwareError {
+                            message: format!("release policy violated: {}", violations.join("; ")),
+                        });
+                    }
+                }
+                SystemCommands::SetBaud { rate, persist } => {
+                    let change = controller.set_baud_rate(rate, persist).await;
+                    if !cli.quiet {
+                        emit::result(cli, "system set-baud", &change, |style| {
+                            render::baud_change(style, &change)
+                        })?;
+                    }
+                    if let Some(message) = change.failure_message() {
+                        return Err(PowerCliError::BaudChange { message });
+                    }
+                }
+                SystemCommands::FactoryReset { skip, yes } => {
+                    if !yes
+                        && !cli.dry_run
+                        && !confirm_destructive(
+                            "This erases stored defaults, resets the coulomb counter, clears the RTC interrupt config and reboots the controller.",
+                        )?
+                    {
+                        return Err(PowerCliError::InvalidCommand {
+                            command: "factory reset not confirmed".to_string(),
+                        });
+                    }
+
+                    controller.set_cancel(util::cancel_on_ctrl_c());
+                    let report = controller.factory_reset(&skip).await;
+                    forget_time_reference(cli);
+                    if !cli.quiet {
+                        emit::result(cli, "system factory-reset", &report, |style| {
+                            render::factory_reset(style, &report)
+                        })?;
+                    }
+                    if let Some(failed) = report.failed_step() {
+                        return Err(PowerCliError::PowerError {
+                            message: format!(
+                                "Factory reset failed at step '{}'",
+                                failed.step.description()
+                            ),
+                        });
+                    }
+                }
+            }
+        }
+        Commands::Battery(battery_cmd) => {
+            use cli::BatteryCommands;
             match battery_cmd {
-                BatteryCommands::Read => {
+                BatteryCommands::Read {
+                    watch: true,
+                    interval,
+                    capacity,
+                    sparkline,
+                    ..
+                } => {
+                    let interval = std::time::Duration::from_secs(interval);
+                    watch_battery(controller, cli, interval, capacity, sparkline).await?;
+                }
+                BatteryCommands::Read {
+                    samples: Some(count),
+                    sample_interval_ms,
+                    ..
+                } => {
+                    let interval = std::time::Duration::from_millis(sample_interval_ms);
+                    let samples = controller.battery_monitor().sample(count, interval).await?;
+                    if !cli.quiet {
+                        emit::result(cli, "battery read", &samples, |style| {
+                            render::battery_samples(style, &samples)
+                        })?;
+                    }
+                }
+                BatteryCommands::Read { .. } => {
                     let response = controller.battery_read().await?;
-                    output_response(cli, "battery read", &response, "🔋", "Battery Measurements")?;
+                    emit::response_with(
+                        cli,
+                        "battery read",
+                        &payload,
+                        &response,
+                        "🔋",
+                        "Battery Measurements",
+                        |style| {
+                            let battery = json::ResponseParser::parse_battery_response(&response);
+                            render::battery(style, &battery)
+                        },
+                    )?;
                 }
-                BatteryCommands::Status => {
-                    let response = controller.battery_status().await?;
-                    output_response(cli, "battery status", &response, "📋", "Battery Status")?;
+                BatteryCommands::Status { brief, deadband } => {
+                    if brief {
+                        let measurement = controller.measure().await?;
+                        let current = measurement.current_ma.ok_or_else(|| {
+                            PowerCliError::InvalidResponse {
+                                response: format!("no current in `{}` reply", measurement.source),
+                            }
+                        })?;
+                        let charger = controller.read_charger(Some(current), deadband).await?;
+                        render::print(charger.brief());
+                    } else {
+                        let response = controller.battery_status().await?;
+                        emit::response(
+                            cli,
+                            "battery status",
+                            &payload,
+                            &response,
+                            "📋",
+                            "Battery Status",
+                        )?;
+                    }
                 }
                 BatteryCommands::Enable => {
                     let response = controller.battery_enable().await?;
-                    output_response(
+                    emit::state_change(
                         cli,
                         "battery enable",
                         &response,
@@ -441,7 +1874,7 @@ async fn execute_command(
                 }
                 BatteryCommands::Disable => {
                     let response = controller.battery_disable().await?;
-                    output_response(
+                    emit::state_change(
                         cli,
                         "battery disable",
                         &response,
@@ -452,14 +1885,18 @@ async fn execute_command(
             }
         }
         Commands::Pm(pm_cmd) => {
-            use cli::{DefaultsCommands, DeviceAction, PowerManagementCommands, PowerState};
+            use cli::{DefaultsCommands, PowerManagementCommands};
             match pm_cmd {
                 PowerManagementCommands::Stats => {
                     let response = controller.pm_stats().await?;
-                    if !cli.quiet {
-                        println!("📊 Power Management Statistics:");
-                        println!("{}", response);
-                    }
+                    emit::response(
+                        cli,
+                        "pm stats",
+                        &payload,
+                        &response,
+                        "📊",
+                        "Power Management Statistics",
+                    )?;
                 }
                 PowerManagementCommands::Sleep {
                     time,
@@ -471,55 +1908,116 @@ async fn execute_command(
                     vlls1,
                     vlls2,
                     vlls3,
+                    force,
                 } => {
-                    let mut cmd_parts = vec!["sleep".to_string()];
+                    use power::sleep::{SleepCommandBuilder, VllsMode};
+                    use power::wake::{console_lockout, SleepReport};
+                    let mut builder = SleepCommandBuilder::new();
                     if let Some(t) = time {
-                        cmd_parts.push(t);
+                        builder = builder.duration(&t);
                     }
                     if pmic {
-                        cmd_parts.push("--pmic".to_string());
+                        builder = builder.pmic_off();
                     }
                     if wifi {
-                        cmd_parts.push("--wifi".to_string());
+                        builder = builder.wifi_off();
                     }
                     if disp {
-                        cmd_parts.push("--disp".to_string());
+                        builder = builder.display_off();
                     }
                     if alloff {
-                        cmd_parts.push("--alloff".to_string());
+                        builder = builder.all_off();
                     }
-                    if vlls0 {
-                        cmd_parts.push("--vlls0".to_string());
+                    for (set, mode) in [
+                        (vlls0, VllsMode::Vlls0),
+                        (vlls1, VllsMode::Vlls1),
+                        (vlls2, VllsMode::Vlls2),
+                        (vlls3, VllsMode::Vlls3),
+                    ] {
+                        if set {
+                            builder = builder.vlls_mode(mode);
+                        }
                     }
-                    if vlls1 {
-                        cmd_parts.push("--vlls1".to_string());
+                    builder.validate()?;
+
+                    // VLLS modes limit the wake sources; check the console can
+                    // still bring the controller back
+                    let mode = builder.mode();
+                    let configured = match mode {
+                        Some(_) => controller.wake_sources().await.unwrap_or_else(|e| {
+                            log::warn!("Could not read wake sources: {}", e);
+                            None
+                        }),
+                        None => None,
+                    };
+                    let effective = configured.as_ref().map(|mask| mask.effective_in(mode));
+                    let bounded = builder.sleep_duration().is_some();
+                    match &effective {
+                        Some(effective) if console_lockout(mode, bounded, effective) => {
+                            let message = "UART wake is disabled for this sleep and it has no time bound; the console will not be able to wake the controller. Run 'pm wake-sources enable uart', give --time, or pass --force";
+                            if !force {
+                                return Err(PowerCliError::InvalidArguments {
+                                    message: message.to_string(),
+                                });
+                            }
+                            log::warn!("{}", message);
+                        }
+                        None if matches!(mode, Some(VllsMode::Vlls0 | VllsMode::Vlls1)) => {
+                            log::warn!("Wake sources unknown; console wake not checked");
+                        }
+                        _ => {}
                     }
-                    if vlls2 {
-                        cmd_parts.push("--vlls2".to_string());
+
+                    let cmd = builder.build();
+                    let response = controller.pm_command(&cmd).await?;
+                    if !cli.quiet {
+                        let report = SleepReport {
+                            command: cmd,
+                            vlls_mode: mode,
+                            duration_s: builder.sleep_duration().map(|d| d.as_secs()),
+                            configured_wake: configured,
+                            effective_wake: effective,
+                            response,
+                        };
+                        emit::result(cli, "pm sleep", &report, |style| {
+                            render::titled(style, "😴", "Entering Low Power Mode", &report.response)
+                        })?;
                     }
-                    if vlls3 {
-                        cmd_parts.push("--vlls3".to_string());
+                }
+                PowerManagementCommands::WakeSources(wake_cmd) => {
+                    use cli::WakeSourcesCommands;
+                    match wake_cmd {
+                        WakeSourcesCommands::Show => {}
+                        WakeSourcesCommands::Enable { source } => {
+                            controller.set_wake_source(source, true).await?;
+                        }
+                        WakeSourcesCommands::Disable { source } => {
+                            controller.set_wake_source(source, false).await?;
+                        }
                     }
-                    let cmd = cmd_parts.join(" ");
-                    let response = controller.pm_command(&cmd).await?;
+                    let mask = controller.wake_sources().await?;
                     if !cli.quiet {
-                        println!("😴 Entering Low Power Mode:");
-                        println!("{}", response);
+                        emit::result(cli, "pm wake-sources", &mask, |style| {
+                            render::wake_sources(style, mask.as_ref())
+                        })?;
                     }
                 }
                 PowerManagementCommands::Wake => {
                     let response = controller.pm_command("wake").await?;
                     if !cli.quiet {
-                        println!("⏰ Last Wake Source:");
-                        println!("{}", response);
+                        emit::titled(cli, "⏰", "Last Wake Source", &response);
                     }
                 }
                 PowerManagementCommands::Measure => {
                     let response = controller.pm_command("measure").await?;
-                    if !cli.quiet {
-                        println!("🔋 Battery Measurement:");
-                        println!("{}", response);
-                    }
+                    emit::response(
+                        cli,
+                        "pm measure",
+                        &payload,
+                        &response,
+                        "🔋",
+                        "Battery Measurement",
+                    )?;
                 }
                 PowerManagementCommands::Monitor { action, interval } => {
                     let cmd = match action {
@@ -534,152 +2032,123 @@ async fn execute_command(
                     };
                     let response = controller.pm_command(&cmd).await?;
                     if !cli.quiet {
-                        println!("📊 Power Monitoring:");
-                        println!("{}", response);
+                        emit::titled(cli, "📊", "Power Monitoring", &response);
                     }
                 }
                 PowerManagementCommands::All { state } => {
-                    let state_str = match state {
-                        PowerState::On => "on",
-                        PowerState::Off => "off",
-                        PowerState::Status => "status",
-                    };
-                    let response = controller.pm_command(&format!("all {}", state_str)).await?;
-                    if !cli.quiet {
-                        println!("⚡ All Power Rails:");
-                        println!("{}", response);
-                    }
+                    switch_rail(controller, cli, "pm", Rail::All, state).await?;
                 }
                 PowerManagementCommands::Pmic { state } => {
-                    let state_str = match state {
-                        PowerState::On => "on",
-                        PowerState::Off => "off",
-                        PowerState::Status => "status",
-                    };
-                    let response = controller.pm_command(&format!("pmic {}", state_str)).await?;
-                    if !cli.quiet {
-                        println!("⚡ PMIC Control:");
-                        println!("{}", response);
-                    }
+                    switch_rail(controller, cli, "pm", Rail::Pmic, state).await?;
                 }
                 PowerManagementCommands::Wifi { state } => {
-                    let state_str = match state {
-                        PowerState::On => "on",
-                        PowerState::Off => "off",
-                        PowerState::Status => "status",
-                    };
-                    let response = controller.pm_command(&format!("wifi {}", state_str)).await?;
+                    switch_rail(controller, cli, "pm", Rail::Wifi, state).await?;
+                }
+                PowerManagementCommands::Display { state } => {
+                    switch_rail(controller, cli, "pm", Rail::Display, state).await?;
+                }
+                PowerManagementCommands::Defaults(defaults_cmd) => match defaults_cmd {
+                    DefaultsCommands::Show => {
+                        let response = controller.pm_command("defaults").await?;
+                        emit::response(
+                            cli,
+                            "pm defaults",
+                            &payload,
+                            &response,
+                            "⚙️",
+                            "Power Rail Defaults",
+                        )?;
+                    }
+                    DefaultsCommands::Save => {
+                        let response = controller.save_rail_defaults().await?;
+                        emit::response(
+                            cli,
+                            "pm defaults save",
+                            &payload,
+                            &response,
+                            "💾",
+                            "Saving Power Rail Defaults",
+                        )?;
+                    }
+                    DefaultsCommands::Export { file } => {
+                        let defaults = controller.export_rail_defaults().await?;
+                        let mut contents = serde_json::to_string_pretty(&defaults)?;
+                        contents.push('\n');
+                        std::fs::write(&file, contents)?;
+                        if !cli.quiet {
+                            render::print(&render::defaults_exported(&cli.output_style(), &file));
+                        }
+                    }
+                    DefaultsCommands::Import { file } => {
+                        let contents = std::fs::read_to_string(&file)?;
+                        let defaults: json::PowerDefaults = serde_json::from_str(&contents)?;
+                        let response = controller.import_rail_defaults(&defaults).await?;
+                        emit::response(
+                            cli,
+                            "pm defaults import",
+                            &payload,
+                            &response,
+                            "📥",
+                            "Importing Power Rail Defaults",
+                        )?;
+                    }
+                    DefaultsCommands::Pmic { state } => {
+                        set_rail_default(controller, cli, Rail::Pmic, state).await?;
+                    }
+                    DefaultsCommands::Wifi { state } => {
+                        set_rail_default(controller, cli, Rail::Wifi, state).await?;
+                    }
+                    DefaultsCommands::Display { state } => {
+                        set_rail_default(controller, cli, Rail::Display, state).await?;
+                    }
+                },
+                PowerManagementCommands::Ltc2959 { action } => {
+                    let response = controller.device_action("ltc2959", action.as_str()).await?;
                     if !cli.quiet {
-                        println!("📶 WiFi Control:");
-                        println!("{}", response);
+                        emit::titled(cli, "🔋", "LTC2959 Control", &response);
                     }
                 }
-                PowerManagementCommands::Disp { state } => {
-                    let state_str = match state {
-                        PowerState::On => "on",
-                        PowerState::Off => "off",
-                        PowerState::Status => "status",
-                    };
-                    let response = controller.pm_command(&format!("disp {}", state_str)).await?;
+                PowerManagementCommands::Nfc { action } => {
+                    let response = controller.device_action("nfc", action.as_str()).await?;
                     if !cli.quiet {
-                        println!("🖥️ Display Control:");
-                        println!("{}", response);
+                        emit::titled(cli, "📡", "NFC Control", &response);
                     }
                 }
-                PowerManagementCommands::Defaults(defaults_cmd) => {
-                    match defaults_cmd {
-                        DefaultsCommands::Show => {
-                            let response = controller.pm_command("defaults").await?;
-                            if !cli.quiet {
-                                println!("⚙️ Power Rail Defaults:");
-                                println!("{}", response);
-                            }
-                        }
-                        DefaultsCommands::Save => {
-                            let response = controller.pm_command("defaults save").await?;
-                            if !cli.quiet {
-                                println!("💾 Saving Power Rail Defaults:");
-                                println!("{}", response);
-                            }
-                        }
-                        DefaultsCommands::Pmic { state } => {
-                            let state_str = match state {
-                                PowerState::On => "on",
-                                PowerState::Off => "off",
-                                PowerState::Status => "status",
-                            };
-                            let response = controller.pm_command(&format!("defaults pmic {}", state_str)).await?;
-                            if !cli.quiet {
-                                println!("⚙️ PMIC Default:");
-                                println!("{}", response);
-                            }
-                        }
-                        DefaultsCommands::Wifi { state } => {
-                            let state_str = match state {
-                                PowerState::On => "on",
-                                PowerState::Off => "off",
-                                PowerState::Status => "status",
-                            };
-                            let response = controller.pm_command(&format!("defaults wifi {}", state_str)).await?;
+                PowerManagementCommands::BatteryCheck {
+                    samples,
+                    sample_interval_ms,
+                } => {
+                    let verdict = match samples {
+                        Some(count) => {
+                            let interval = std::time::Duration::from_millis(sample_interval_ms);
+                            let checks = controller.battery_health_samples(count, interval).await?;
                             if !cli.quiet {
-                                println!("⚙️ WiFi Default:");
-                                println!("{}", response);
+                                emit::result(cli, "pm battery_check", &checks, |style| {
+                                    render::battery_health_samples(style, &checks)
+                                })?;
                             }
+                            checks.median.verdict
                         }
-                        DefaultsCommands::Disp { state } => {
-                            let state_str = match state {
-                                PowerState::On => "on",
-                                PowerState::Off => "off",
-                                PowerState::Status => "status",
-                            };
-                            let response = controller.pm_command(&format!("defaults disp {}", state_str)).await?;
+                        None => {
+                            let health = controller.battery_health_check().await?;
                             if !cli.quiet {
-                                println!("⚙️ Display Default:");
-                                println!("{}", response);
+                                emit::battery_health(cli, &health)?;
                             }
+                            health.verdict
                         }
-                    }
-                }
-                PowerManagementCommands::Ltc2959 { action } => {
-                    let action_str = match action {
-                        DeviceAction::Wake => "wake",
-                        DeviceAction::Sleep => "sleep",
-                    };
-                    let response = controller.pm_command(&format!("ltc2959 {}", action_str)).await?;
-                    if !cli.quiet {
-                        println!("🔋 LTC2959 Control:");
-                        println!("{}", response);
-                    }
-                }
-                PowerManagementCommands::Nfc { action } => {
-                    let action_str = match action {
-                        DeviceAction::Wake => "wake",
-                        DeviceAction::Sleep => "sleep",
                     };
-                    let response = controller.pm_command(&format!("nfc {}", action_str)).await?;
-                    if !cli.quiet {
-                        println!("📡 NFC Control:");
-                        println!("{}", response);
-                    }
-                }
-                PowerManagementCommands::BatteryCheck => {
-                    let response = controller.pm_command("battery_check").await?;
-                    if !cli.quiet {
-                        println!("🔋 Battery Health Check:");
-                        println!("{}", response);
+                    match verdict {
+                        Some(json::BatteryVerdict::Healthy) => {}
+                        Some(verdict) => return Err(PowerCliError::BatteryUnhealthy { verdict }),
+                        None => {
+                            return Err(PowerCliError::InvalidResponse {
+                                response: "battery check reported no verdict".to_string(),
+                            })
+                        }
                     }
                 }
                 PowerManagementCommands::Imx93 { state } => {
-                    let cmd = match state {
-                        PowerState::On => "imx93 on",
-                        PowerState::Off => "imx93 off",
-                        PowerState::Status => "imx93 status",
-                    };
-                    let response = controller.pm_command(cmd).await?;
-                    if !cli.quiet {
-                        println!("🖥️ i.MX93 Power Control:");
-                        println!("{}", response);
-                    }
+                    switch_rail(controller, cli, "pm", Rail::Imx93, state).await?;
                 }
             }
         }
@@ -689,105 +2158,234 @@ async fn execute_command(
                 NfcCommands::Scan => {
                     let response = controller.nfc_command("scan").await?;
                     if !cli.quiet {
-                        println!("🔍 NFC I2C Scan:");
-                        println!("{}", response);
+                        emit::titled(cli, "🔍", "NFC I2C Scan", &response);
                     }
                 }
                 NfcCommands::Status => {
                     let response = controller.nfc_command("status").await?;
-                    if !cli.quiet {
-                        println!("📡 NFC Status:");
-                        println!("{}", response);
-                    }
+                    emit::response_with(
+                        cli,
+                        "nfc status",
+                        &payload,
+                        &response,
+                        "📡",
+                        "NFC Status",
+                        |style| {
+                            let nfc = json::ResponseParser::parse_nfc_status(&response);
+                            render::nfc_status(style, &nfc)
+                        },
+                    )?;
                 }
                 NfcCommands::Init => {
                     let response = controller.nfc_command("init").await?;
                     if !cli.quiet {
-                        println!("🔧 NFC Initialization:");
-                        println!("{}", response);
+                        emit::titled(cli, "🔧", "NFC Initialization", &response);
                     }
                 }
                 NfcCommands::Debug => {
                     let response = controller.nfc_command("debug").await?;
-                    if !cli.quiet {
-                        println!("🐛 NFC Debug:");
-                        println!("{}", response);
-                    }
+                    emit::response(cli, "nfc debug", &payload, &response, "🐛", "NFC Debug")?;
                 }
                 NfcCommands::Rfdbg => {
                     let response = controller.nfc_command("rfdbg").await?;
-                    if !cli.quiet {
-                        println!("📡 NFC RF Diagnostic:");
-                        println!("{}", response);
-                    }
+                    emit::response(
+                        cli,
+                        "nfc rfdbg",
+                        &payload,
+                        &response,
+                        "📡",
+                        "NFC RF Diagnostic",
+                    )?;
                 }
                 NfcCommands::Ed => {
                     let response = controller.nfc_command("ed").await?;
                     if !cli.quiet {
-                        println!("📡 NFC Field Detection:");
-                        println!("{}", response);
+                        emit::titled(cli, "📡", "NFC Field Detection", &response);
                     }
                 }
                 NfcCommands::Enable => {
                     let response = controller.nfc_command("enable").await?;
-                    if !cli.quiet {
-                        println!("✅ NFC RF Enabled:");
-                        println!("{}", response);
-                    }
+                    emit::state_change(cli, "nfc enable", &response, "✅", "NFC RF Enabled")?;
                 }
                 NfcCommands::Disable => {
                     let response = controller.nfc_command("disable").await?;
-                    if !cli.quiet {
-                        println!("❌ NFC RF Disabled:");
-                        println!("{}", response);
-                    }
+                    emit::state_change(cli, "nfc disable", &response, "❌", "NFC RF Disabled")?;
                 }
                 NfcCommands::Reset => {
                     let response = controller.nfc_command("reset").await?;
                     if !cli.quiet {
-                        println!("🔄 NFC Reset:");
-                        println!("{}", response);
+                        emit::titled(cli, "🔄", "NFC Reset", &response);
                     }
                 }
                 NfcCommands::Info => {
                     let response = controller.nfc_command("info").await?;
                     if !cli.quiet {
-                        println!("ℹ️ NFC Device Information:");
-                        println!("{}", response);
+                        emit::titled(cli, "ℹ️", "NFC Device Information", &response);
                     }
                 }
                 NfcCommands::FieldDetect => {
                     let response = controller.nfc_command("field_detect").await?;
                     if !cli.quiet {
-                        println!("📡 NFC Field Detection:");
-                        println!("{}", response);
+                        emit::titled(cli, "📡", "NFC Field Detection", &response);
+                    }
+                }
+                NfcCommands::Tag { uid_format } => {
+                    let response = controller.nfc_command("tag_info").await?;
+                    let mut tag =
+                        json::ResponseParser::parse_nfc_tag_info(&response).ok_or_else(|| {
+                            PowerCliError::NfcError {
+                                message: format!("No NFC tag detected: {}", response.trim()),
+                            }
+                        })?;
+                    tag.uid = match uid_format {
+                        cli::UidFormat::Hex => tag.uid_hex(),
+                        cli::UidFormat::Decimal => tag.uid_decimal(),
+                        cli::UidFormat::Raw => tag.uid_raw(),
+                    };
+
+                    match cli.format {
+                        cli::OutputFormat::Json
+                        | cli::OutputFormat::Ndjson
+                        | cli::OutputFormat::JsonLines => {
+                            if !cli.quiet {
+                                let json_response = json::JsonResponse::success(
+                                    "nfc tag",
+                                    serde_json::to_value(&tag)?,
+                                )
+                                .with_raw(&response, &cli.raw_options());
+                                emit::json(cli, &json_response)?;
+                            }
+                        }
+                        _ => emit::response(
+                            cli,
+                            "nfc tag",
+                            &payload,
+                            &tag.format_human(),
+                            "🏷️",
+                            "NFC Tag",
+                        )?,
+                    }
+                }
+                NfcCommands::SramRead => {
+                    let bytes = controller.sram_mailbox().read().await?;
+                    if !cli.quiet {
+                        let sram = json::SramJson::new(&bytes);
+                        emit::result(cli, "nfc sram-read", &sram, |style| {
+                            render::sram(style, "NFC SRAM Mailbox", &sram)
+                        })?;
+                    }
+                }
+                NfcCommands::SramWrite { data_hex, file } => {
+                    let data = match file {
+                        Some(file) => std::fs::read(file)?,
+                        None => {
+                            power::passthrough::parse_hex(data_hex.as_deref().unwrap_or_default())?
+                        }
+                    };
+                    controller.sram_mailbox().write(&data).await?;
+                    if !cli.quiet {
+                        let sram = json::SramJson::new(&data);
+                        let title = format!("NFC SRAM Written ({} bytes)", data.len());
+                        emit::result(cli, "nfc sram-write", &sram, |style| {
+                            render::sram(style, &title, &sram)
+                        })?;
+                    }
+                }
+                NfcCommands::Transfer {
+                    direction,
+                    file,
+                    timeout,
+                } => {
+                    if cli.dry_run {
+                        return Err(PowerCliError::InvalidCommand {
+                            command: "nfc transfer waits on a phone and cannot run with --dry-run"
+                                .to_string(),
+                        });
+                    }
+                    let timeout = std::time::Duration::from_secs(timeout);
+                    // Progress output failing is no reason to abort the transfer
+                    let progress = |progress| {
+                        let _ = emit::transfer_progress(cli, &progress);
+                    };
+                    let mut mailbox = controller.sram_mailbox();
+                    let mut report = match direction {
+                        power::passthrough::TransferDirection::ToPhone => {
+                            let data = std::fs::read(&file)?;
+                            mailbox.send(&data, timeout, progress).await?
+                        }
+                        power::passthrough::TransferDirection::FromPhone => {
+                            let (data, report) = mailbox.receive(timeout, progress).await?;
+                            std::fs::write(&file, data)?;
+                            report
+                        }
+                    };
+                    report.file = file.display().to_string();
+                    if !cli.quiet {
+                        emit::result(cli, "nfc transfer", &report, |style| {
+                            render::nfc_transfer(style, &report)
+                        })?;
                     }
                 }
             }
         }
         Commands::Rtc(rtc_cmd) => {
-            use cli::{ExternalRtcAction, RtcCommands};
+            use cli::RtcCommands;
             match rtc_cmd {
                 RtcCommands::Status => {
                     let response = controller.rtc_status().await?;
-                    output_response(cli, "rtc status", &response, "🕐", "RTC Status")?;
+                    emit::response_with(
+                        cli,
+                        "rtc status",
+                        &payload,
+                        &response,
+                        "🕐",
+                        "RTC Status",
+                        |style| {
+                            let rtc = json::ResponseParser::parse_rtc_status(&response);
+                            render::rtc_status(style, &rtc)
+                        },
+                    )?;
                 }
                 RtcCommands::Get => {
-                    let response = controller.rtc_get().await?;
-                    output_response(cli, "rtc get", &response, "🕐", "RTC Counter")?;
+                    let counter = controller.rtc_get().await?;
+                    emit::response(
+                        cli,
+                        "rtc get",
+                        &payload,
+                        &counter.to_string(),
+                        "🕐",
+                        "RTC Counter",
+                    )?;
                 }
                 RtcCommands::Config { action } => {
-                    let action_str = match action {
-                        ExternalRtcAction::None => "none",
-                        ExternalRtcAction::Wake => "wake",
-                        ExternalRtcAction::Auto => "auto",
-                    };
-                    let response = controller.rtc_config(action_str).await?;
-                    output_response(cli, "rtc config", &response, "⚙️", "RTC Configuration")?;
+                    let response = controller.rtc_config(action.as_str()).await?;
+                    emit::response(
+                        cli,
+                        "rtc config",
+                        &payload,
+                        &response,
+                        "⚙️",
+                        "RTC Configuration",
+                    )?;
                 }
                 RtcCommands::Show => {
                     let response = controller.rtc_show_config().await?;
-                    output_response(cli, "rtc show", &response, "📋", "RTC Configuration")?;
+                    emit::response(
+                        cli,
+                        "rtc show",
+                        &payload,
+                        &response,
+                        "📋",
+                        "RTC Configuration",
+                    )?;
+                }
+                RtcCommands::Calibrate { ppm_offset } => {
+                    let calibration = controller.rtc_calibrate(ppm_offset).await?;
+                    emit::rtc_calibration(cli, "rtc calibrate", &calibration)?;
+                }
+                RtcCommands::CalibrationRead => {
+                    let calibration = controller.rtc_calibration_read().await?;
+                    emit::rtc_calibration(cli, "rtc calibration", &calibration)?;
                 }
             }
         }
@@ -802,8 +2400,8 @@ async fn execute_command(
                     };
                     let response = controller.control_comm("bt_wake", state_str).await?;
                     if !cli.quiet {
-                        println!("📡 BT_WAKE_HOST:");
-                        println!("{}", response);
+                        let title = power::gpio::comm_signal_title("bt_wake");
+                        emit::titled(cli, "📡", &title, &response);
                     }
                 }
                 CommCommands::WlWake { state } => {
@@ -814,8 +2412,8 @@ async fn execute_command(
                     };
                     let response = controller.control_comm("wl_wake", state_str).await?;
                     if !cli.quiet {
-                        println!("📡 WL_WAKE_HOST:");
-                        println!("{}", response);
+                        let title = power::gpio::comm_signal_title("wl_wake");
+                        emit::titled(cli, "📡", &title, &response);
                     }
                 }
             }
@@ -823,50 +2421,1219 @@ async fn execute_command(
         Commands::Firmware(firmware_cmd) => {
             use cli::FirmwareCommands;
 
-            // Extract port and baud from the command
-            let (port, baud) = match firmware_cmd {
-                FirmwareCommands::Upload { ref port, baud, .. } => {
-                    (port.clone(), baud.unwrap_or(115200))
+            // Offline: no connection or mcumgr needed
+            if let FirmwareCommands::Analyze { ref file } = firmware_cmd {
+                let info = firmware::analyze_firmware_image(file)?;
+                if !cli.quiet {
+                    emit::result(cli, "firmware analyze", &info, |style| {
+                        render::firmware_analysis(style, file, &info)
+                    })?;
+                }
+                if !info.magic_valid {
+                    return Err(PowerCliError::FirmwareError {
+                        message: format!("{} is not an MCUboot image", file.display()),
+                    });
+                }
+                return Ok(());
+            }
+
+            // mcumgr follows the global --device/--baud unless the upload overrides it
+            let transports = match firmware_cmd {
+                FirmwareCommands::Upload {
+                    ref port,
+                    baud,
+                    ref transport_ble,
+                    ref transport_udp,
+                    ..
+                } => {
+                    let resolved = firmware::FirmwareTransports::resolve(
+                        &cli.device,
+                        cli.baud,
+                        port.as_deref(),
+                        baud,
+                    );
+                    if let Some(address) = transport_ble {
+                        resolved.with_mcumgr(firmware::McumgrTransport::Ble {
+                            address: address.clone(),
+                        })
+                    } else if let Some(endpoint) = transport_udp {
+                        resolved.with_mcumgr(firmware::McumgrTransport::udp_from_str(endpoint)?)
+                    } else {
+                        resolved
+                    }
                 }
-                _ => (None, 115200),
+                _ => firmware::FirmwareTransports::resolve(&cli.device, cli.baud, None, None),
             };
 
-            let connection = serial::Connection::new(&cli.device, cli.baud, cli.quiet)?;
-            let mut firmware_manager = firmware::FirmwareManager::new(connection, port, baud);
+            let mut firmware_manager = firmware_manager(cli, controller, transports)?;
+            firmware_manager.set_cancel(util::cancel_on_ctrl_c());
+            if let FirmwareCommands::Upload {
+                fast: Some(rate), ..
+            } = firmware_cmd
+            {
+                firmware_manager.set_fast_baud(rate);
+            }
 
-            match firmware_cmd {
-                FirmwareCommands::List => {
-                    let response = firmware_manager.list_images().await?;
-                    output_response(cli, "firmware list", &response, "📋", "Firmware Images")?;
+            let outcome = async {
+                match firmware_cmd {
+                    FirmwareCommands::List => {
+                        let response = firmware_manager.list_images().await?;
+                        emit::response_with(
+                            cli,
+                            "firmware list",
+                            &payload,
+                            &response,
+                            "📋",
+                            "Firmware Images",
+                            |style| {
+                                let images = firmware::slots::parse_image_list(&response);
+                                render::firmware_images(style, &images)
+                            },
+                        )?;
+                    }
+                    FirmwareCommands::Info => {
+                        let info = firmware_manager.get_info().await?;
+                        if !cli.quiet {
+                            emit::result(cli, "firmware info", &info, |style| {
+                                render::firmware_info(style, &info)
+                            })?;
+                        }
+                    }
+                    FirmwareCommands::Analyze { .. } => unreachable!("handled before connecting"),
+                    FirmwareCommands::Reset => {
+                        let response = firmware_manager.reset_to_bootloader().await?;
+                        emit::response(
+                            cli,
+                            "firmware reset",
+                            &payload,
+                            &response,
+                            "🔄",
+                            "Bootloader Reset",
+                        )?;
+                    }
+                    FirmwareCommands::Upload {
+                        file, skip_reset, ..
+                    } => {
+                        let machine_readable = matches!(
+                            cli.format,
+                            cli::OutputFormat::Json
+                                | cli::OutputFormat::Ndjson
+                                | cli::OutputFormat::JsonLines
+                        );
+                        if machine_readable {
+                            // Progress events and the summary are the whole output
+                            firmware_manager.set_json_events(Box::new(render::sink::Output));
+                            firmware_manager
+                                .set_record_types(cli.format == cli::OutputFormat::JsonLines);
+                        }
+                        let response = firmware_manager
+                            .upload_firmware(file.as_path(), skip_reset)
+                            .await?;
+                        if !machine_readable {
+                            emit::response(
+                                cli,
+                                "firmware upload",
+                                &payload,
+                                &response,
+                                "⬆️",
+                                "Firmware Upload",
+                            )?;
+                        }
+                    }
                 }
-                FirmwareCommands::Info => {
-                    let response = firmware_manager.get_info().await?;
-                    output_response(
-                        cli,
-                        "firmware info",
-                        &response,
-                        "ℹ️",
-                        "Firmware Information",
-                    )?;
+                Ok::<(), PowerCliError>(())
+            }
+            .await;
+            firmware_manager.close().await;
+            outcome?;
+        }
+        Commands::Identity(identity_cmd) => {
+            use cli::IdentityCommands;
+            let (command, identity) = match identity_cmd {
+                IdentityCommands::Show => ("identity show", controller.read_identity().await?),
+                IdentityCommands::Write {
+                    serial,
+                    hw_rev,
+                    date,
+                    yes,
+                } => {
+                    let date = date.unwrap_or_else(|| chrono::Local::now().date_naive());
+                    let identity = power::identity::DeviceIdentity::new(&serial, hw_rev, date)?;
+                    if !yes
+                        && !cli.dry_run
+                        && !confirm_destructive(&format!(
+                            "This overwrites the unit identity in the NFC EEPROM with serial {}.",
+                            identity.serial
+                        ))?
+                    {
+                        return Err(PowerCliError::InvalidCommand {
+                            command: "identity write not confirmed".to_string(),
+                        });
+                    }
+                    controller.write_identity(&identity).await?;
+                    ("identity write", Some(identity))
                 }
-                FirmwareCommands::Reset => {
-                    let response = firmware_manager.reset_to_bootloader().await?;
-                    output_response(cli, "firmware reset", &response, "🔄", "Bootloader Reset")?;
+            };
+            if !cli.quiet {
+                emit::result(cli, command, &identity, |style| {
+                    render::identity(style, identity.as_ref())
+                })?;
+            }
+        }
+        Commands::Snapshot { output, redact } => {
+            let connection = connection_settings(cli, controller);
+            let transports =
+                firmware::FirmwareTransports::resolve(&cli.device, cli.baud, None, None);
+            let mut firmware_manager = firmware_manager(cli, controller, transports)?;
+            let mut report =
+                snapshot::Snapshot::take(controller, &mut firmware_manager, connection).await;
+            firmware_manager.close().await;
+            if redact {
+                report.redact();
+            }
+            let failed = report.failed_sections();
+            if !failed.is_empty() {
+                log::warn!("Snapshot sections failed: {}", failed.join(", "));
+            }
+            if let Some(file) = &output {
+                let envelope =
+                    json::JsonResponse::success("snapshot", serde_json::to_value(&report)?);
+                let mut contents = serde_json::to_string_pretty(&envelope)?;
+                contents.push('\n');
+                std::fs::write(file, contents)?;
+            }
+            if !cli.quiet {
+                emit::result(cli, "snapshot", &report, |style| {
+                    render::snapshot(style, &report)
+                })?;
+            }
+        }
+        Commands::PowerAudit { sleep_sample_s } => {
+            let report = audit::PowerAudit::take(controller, sleep_sample_s).await;
+            let failed = report.failed_sections();
+            if !failed.is_empty() {
+                log::warn!("Power audit sections failed: {}", failed.join(", "));
+            }
+            if !cli.quiet {
+                emit::result(cli, "power-audit", &report, |style| {
+                    render::power_audit(style, &report)
+                })?;
+            }
+        }
+        Commands::Provision {
+            manifest,
+            resume,
+            output,
+            yes,
+        } => {
+            let manifest = provision::ProvisionManifest::load(&manifest)?;
+            if !yes
+                && !manifest.auto_confirm
+                && !cli.dry_run
+                && !confirm_destructive(&format!(
+                    "This overwrites what the unit stores with the manifest: {}.",
+                    manifest
+                        .steps()
+                        .iter()
+                        .map(|step| step.description().to_lowercase())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ))?
+            {
+                return Err(PowerCliError::InvalidCommand {
+                    command: "provisioning not confirmed".to_string(),
+                });
+            }
+
+            let progress = provision::stored(&cli.device).filter(|_| !cli.dry_run);
+            let earlier = match (&progress, resume) {
+                (Some(file), true) => file.load()?,
+                _ => None,
+            };
+            match &earlier {
+                Some(earlier) if earlier.manifest != manifest => {
+                    return Err(PowerCliError::InvalidArguments {
+                        message: "the manifest changed since the failed run; provision without --resume to start over".to_string(),
+                    });
+                }
+                None if resume => log::warn!("No failed run to resume; running every step"),
+                _ => {}
+            }
+
+            let cancel = util::cancel_on_ctrl_c();
+            controller.set_cancel(cancel.clone());
+            let transports =
+                firmware::FirmwareTransports::resolve(&cli.device, cli.baud, None, None);
+            let mut firmware_manager = firmware_manager(cli, controller, transports)?;
+            firmware_manager.set_cancel(cancel);
+            let record = provision::run(
+                controller,
+                &mut firmware_manager,
+                &manifest,
+                earlier.as_ref(),
+                |record| {
+                    if let Some(file) = &progress {
+                        if let Err(e) = file.store(record) {
+                            log::warn!("Could not save provisioning progress: {}", e);
+                        }
+                    }
+                },
+            )
+            .await;
+            firmware_manager.close().await;
+            if record.success {
+                if let Some(file) = &progress {
+                    file.remove()?;
                 }
-                FirmwareCommands::Upload {
-                    file, skip_reset, ..
-                } => {
-                    let response = firmware_manager
-                        .upload_firmware(file.as_path(), skip_reset)
-                        .await?;
-                    output_response(cli, "firmware upload", &response, "⬆️", "Firmware Upload")?;
+            }
+
+            if let Some(file) = &output {
+                let envelope =
+                    json::JsonResponse::success("provision", serde_json::to_value(&record)?);
+                let mut contents = serde_json::to_string_pretty(&envelope)?;
+                contents.push('\n');
+                std::fs::write(file, contents)?;
+            }
+            if !cli.quiet {
+                emit::result(cli, "provision", &record, |style| {
+                    render::provision(style, &record)
+                })?;
+            }
+            if let Some(failed) = record.failed_step() {
+                return Err(PowerCliError::PowerError {
+                    message: format!(
+                        "Provisioning failed at step '{}'; fix the cause and run again with --resume",
+                        failed.step.description()
+                    ),
+                });
+            }
+        }
+        Commands::Latency { samples } => {
+            let stats = controller.measure_latency(samples.unwrap_or(5)).await?;
+            if stats.avg_ms > 500.0 {
+                log::warn!(
+                    "Average round trip of {:.0} ms is unusually high; the serial link may be behind a saturated USB hub",
+                    stats.avg_ms
+                );
+            }
+            if !cli.quiet {
+                emit::latency(cli, &stats)?;
+            }
+        }
+        Commands::Monitor {
+            follow: true,
+            interval,
+            deadband,
+            debounce,
+            show_unparsed,
+            console_log,
+            ..
+        } => {
+            let follow = FollowMonitor {
+                interval,
+                deadband,
+                debounce,
+                show_unparsed,
+                console_log,
+            };
+            follow_monitor(controller, cli, follow).await?;
+        }
+        Commands::Monitor {
+            interval,
+            continuous,
+            source,
+            deadband,
+            debounce,
+            charger,
+            status_file,
+            status_file_interval,
+            ..
+        } => {
+            if !cli.quiet {
+                emit::monitor_header(cli);
+            }
+            let mut tracker = power::battery::ChargingTracker::new(deadband, debounce);
+            let mut samples = 0u64;
+            let interrupted = util::cancel_on_ctrl_c();
+            let mut status_file = status_file.map(|path| {
+                status::StatusFile::new(
+                    path,
+                    std::time::Duration::from_secs(interval),
+                    status_file_interval.map(std::time::Duration::from_secs),
+                )
+            });
+            let mut status = status::StatusDocument::new(&cli.device);
+            if status_file.is_some() {
+                status.read_firmware(controller).await;
+            }
+            // Counters at the start, for the power metrics of the session
+            let power_start = match continuous && !cli.quiet {
+                true => session_power_counters(controller).await,
+                false => None,
+            };
+            let sampled: Result<(), PowerCliError> = async {
+                loop {
+                    // Uptime probes only make sense across several samples
+                    if continuous {
+                        match controller.check_for_reboot().await {
+                            Ok(Some(event)) => {
+                                forget_time_reference(cli);
+                                if !cli.quiet {
+                                    emit::reboot_event(cli, &event)?;
+                                }
+                            }
+                            Ok(None) => {}
+                            Err(e) => log::warn!("Uptime probe failed: {}", e),
+                        }
+                    }
+                    let measurement = match source {
+                        cli::MonitorSource::Measure => controller.measure().await?,
+                        cli::MonitorSource::Ltc2959 => controller.ltc2959_measurement().await?,
+                    };
+                    samples += 1;
+                    let charger = match charger {
+                        true => Some(
+                            controller
+                                .read_charger(measurement.current_ma, deadband)
+                                .await?,
+                        ),
+                        false => None,
+                    };
+                    let reported = charger
+                        .as_ref()
+                        .filter(|charger| charger.is_reported())
+                        .map(|charger| charger.state);
+                    let transition = measurement.current_ma.and_then(|current| {
+                        tracker.update_with(current, reported, chrono::Utc::now())
+                    });
+                    if status_file.is_some() {
+                        status.sampled_at = Some(chrono::Utc::now());
+                        status.battery = Some(measurement.clone());
+                        status.charging_state = tracker.state();
+                        status.read_power_state(controller).await;
+                    }
+                    if let Some(transition) = &transition {
+                        info!(
+                            "Charging state {} -> {}",
+                            transition.from.as_str(),
+                            transition.to.as_str()
+                        );
+                    }
+                    if !cli.quiet {
+                        if let Some(transition) = &transition {
+                            emit::charging_transition(cli, transition, &measurement);
+                        }
+                        let sample = json::MonitorSampleJson {
+                            measurement,
+                            charging_state: tracker.state(),
+                            charging: tracker.is_charging(),
+                            since: tracker.since(),
+                            charger,
+                        };
+                        emit::monitor_sample(cli, &sample)?;
+                    }
+                    if let Some(file) = status_file.as_ref().filter(|file| !file.is_timed()) {
+                        write_status(file, &mut status);
+                    }
+                    if !continuous {
+                        break;
+                    }
+                    // Ctrl-C ends the session with a summary instead of killing it
+                    let wake =
+                        tokio::time::Instant::now() + std::time::Duration::from_secs(interval);
+                    loop {
+                        tokio::select! {
+                            _ = tokio::time::sleep_until(wake) => break,
+                            _ = tick_status(&mut status_file) => {
+                                if let Some(file) = &status_file {
+                                    write_status(file, &mut status);
+                                }
+                            }
+                            _ = interrupted.cancelled() => return Ok(()),
+                        }
+                    }
+                }
+                Ok(())
+            }
+            .await;
+            if let Some(file) = &status_file {
+                if let Err(e) = file.mark_stale(&mut status) {
+                    log::warn!("Failed to mark {} stale: {}", file.path().display(), e);
+                }
+            }
+            sampled?;
+            if continuous && !cli.quiet {
+                let power = match power_start {
+                    Some(start) => session_power_counters(controller)
+                        .await
+                        .map(|end| power::stats::PowerStatsDerived::between(&start, &end)),
+                    None => None,
+                };
+                let summary = json::MonitorSummaryJson {
+                    samples,
+                    charging_transitions: tracker.transitions(),
+                    final_state: tracker.state(),
+                    reboots: controller.reboots(),
+                    link: controller.connection_stats().clone(),
+                    sample_format: None,
+                    unparsed_lines: 0,
+                    power,
+                };
+                emit::monitor_summary(cli, &summary)?;
+            }
+        }
+        Commands::Batch { file } => {
+            let contents = std::fs::read_to_string(&file)?;
+            for (index, line) in contents.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let location = format!("{}:{}", file.display(), index + 1);
+
+                // Global options come from the batch invocation
+                let batch_cmd =
+                    cli::parse_step(line).map_err(|message| PowerCliError::InvalidCommand {
+                        command: format!("{}: {}", location, message),
+                    })?;
+
+                let words: Vec<String> = line.split_whitespace().map(String::from).collect();
+                if let Some(deprecation) =
+                    cli::deprecations::find(&words, &batch_cmd).filter(|_| !cli.quiet)
+                {
+                    eprintln!("{}: {}", location, deprecation.warning());
+                }
+
+                debug!("Batch {}: {}", location, line);
+                if let Err(e) = Box::pin(run_command(batch_cmd, controller, cli)).await {
+                    error!("Batch stopped at {}: {}", location, line);
+                    return Err(e);
+                }
+            }
+        }
+        Commands::Run { name, steps, .. } => {
+            let name = name.unwrap_or_default();
+            let plan = macros::plan(&name, &steps)?;
+            let mut report = macros::MacroReport::new(&name, &plan, cli.dry_run);
+
+            // Steps print their own results, except in formats where the
+            // report is the whole output
+            let mut step_cli = cli.clone();
+            step_cli.quiet |= matches!(
+                cli.format,
+                cli::OutputFormat::Csv | cli::OutputFormat::Prometheus
+            );
+            let mut failure = None;
+            for (index, step) in plan.into_iter().enumerate() {
+                let location = format!("macro '{}' step {}", name, index + 1);
+                let words: Vec<String> = step.line.split_whitespace().map(String::from).collect();
+                if let Some(deprecation) =
+                    cli::deprecations::find(&words, &step.command).filter(|_| !cli.quiet)
+                {
+                    eprintln!("{}: {}", location, deprecation.warning());
+                }
+
+                debug!("Run {}: {}", location, step.line);
+                let started = std::time::Instant::now();
+                let result = Box::pin(run_command(step.command, controller, &step_cli)).await;
+                report.record(index, started.elapsed(), result.as_ref().err());
+                if let Err(e) = result {
+                    error!("Run stopped at {}: {}", location, step.line);
+                    failure = Some(e);
+                    break;
                 }
             }
+
+            if !cli.quiet {
+                emit::macro_report(cli, &report)?;
+            }
+            if let Some(e) = failure {
+                return Err(e);
+            }
+        }
+        Commands::HilTest { plan, junit } => {
+            let plan = hil::HilPlan::load(&plan)?;
+            let started = chrono::Utc::now();
+            let mut steps = Vec::with_capacity(plan.steps.len());
+            let mut stopped = false;
+            for (index, step) in plan.steps.iter().enumerate() {
+                if stopped {
+                    steps.push(hil::HilStepResult::skipped(step));
+                    continue;
+                }
+                debug!("HIL step {}: {}", index + 1, step.name());
+                let result = run_hil_step(&plan, step, controller, cli).await;
+                if cli.format == cli::OutputFormat::Human && !cli.quiet {
+                    render::print(&render::hil_step(&cli.output_style(), &result));
+                    emit::flush_if_line_buffered(cli);
+                }
+                if result.status == hil::HilStepStatus::Failed && step.critical {
+                    error!("Critical step {} failed; skipping the rest", step.name());
+                    stopped = true;
+                }
+                steps.push(result);
+            }
+
+            let report = hil::HilReport::new(plan.name(), started, steps);
+            // The XML is for the CI system, so it is written whatever the outcome
+            if let Some(path) = &junit {
+                state::write_atomic(path, hil::junit_xml(&report).as_bytes())?;
+            }
+            emit::result(cli, "hil-test", &report, |style| {
+                render::hil_test(style, &report)
+            })?;
+            if !report.success {
+                return Err(PowerCliError::HilTest {
+                    message: format!(
+                        "{} of {} steps failed, {} skipped",
+                        report.failed,
+                        report.steps.len(),
+                        report.skipped
+                    ),
+                });
+            }
         }
-        _ => {
+        command => {
             println!("Command not yet implemented: {:?}", command);
         }
     }
 
     Ok(())
 }
+
+/// Run one `hil-test` step with its retries, within its timeout
+///
+/// What the step prints is captured for its `expect` checks and the report
+/// rather than shown.
+async fn run_hil_step(
+    plan: &hil::HilPlan,
+    step: &hil::HilStep,
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+) -> hil::HilStepResult {
+    let timeout = plan.timeout(step);
+    let retries = plan.retries(step);
+    let started = std::time::Instant::now();
+
+    // Steps print in the human format for `expect`; checks read JSON
+    let mut step_cli = cli.clone();
+    step_cli.quiet = false;
+    step_cli.output = None;
+    step_cli.format = match step.action {
+        hil::HilAction::Assert { .. } => cli::OutputFormat::Json,
+        _ => cli::OutputFormat::Human,
+    };
+    let assertions = step.assertions().unwrap_or_default();
+
+    let mut attempts = 0;
+    loop {
+        attempts += 1;
+        let remaining = timeout.saturating_sub(started.elapsed());
+        let outer = render::sink::start_nested_capture();
+        let attempt =
+            tokio::time::timeout(remaining, hil_attempt(step, controller, &step_cli)).await;
+        let output = String::from_utf8_lossy(&render::sink::end_nested_capture(outer)).into_owned();
+
+        let error = match attempt {
+            Ok(Ok(())) => None,
+            Ok(Err(e)) => Some(e.to_string()),
+            Err(_) => {
+                // Drop whatever the interrupted exchange left for the next step
+                if let Err(e) = controller.flush_rx_buffer().await {
+                    debug!("Could not flush after the timeout: {}", e);
+                }
+                Some(format!("timed out after {}s", timeout.as_secs()))
+            }
+        };
+        let mut checks = Vec::new();
+        if error.is_none() {
+            checks.extend(
+                step.expect
+                    .iter()
+                    .map(|expected| hil::HilCheck::substring(expected, &output)),
+            );
+            if !assertions.is_empty() {
+                // The data of the last envelope printed, i.e. the command's result
+                let data = serde_json::Deserializer::from_str(&output)
+                    .into_iter::<serde_json::Value>()
+                    .filter_map(|document| document.ok())
+                    .last()
+                    .and_then(|mut envelope| envelope.get_mut("data").map(|data| data.take()))
+                    .unwrap_or_default();
+                checks.extend(
+                    assertions
+                        .iter()
+                        .map(|a| hil::HilCheck::assertion(a, a.evaluate(&data))),
+                );
+            }
+        }
+
+        let passed = error.is_none() && checks.iter().all(|check| check.passed);
+        if !passed && attempts <= retries && started.elapsed() < timeout {
+            warn!(
+                "Step {} failed (attempt {} of {}), retrying",
+                step.name(),
+                attempts,
+                retries + 1
+            );
+            continue;
+        }
+        return hil::HilStepResult {
+            name: step.name(),
+            kind: step.action.kind().to_string(),
+            critical: step.critical,
+            status: match passed {
+                true => hil::HilStepStatus::Passed,
+                false => hil::HilStepStatus::Failed,
+            },
+            attempts,
+            duration_ms: started.elapsed().as_millis() as u64,
+            error,
+            checks,
+            output: (!output.is_empty())
+                .then(|| json::raw::truncate(&output, hil::STEP_OUTPUT_LIMIT).to_string()),
+        };
+    }
+}
+
+/// One attempt at a `hil-test` step
+async fn hil_attempt(
+    step: &hil::HilStep,
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+) -> Result<(), PowerCliError> {
+    if let hil::HilAction::Raw { line } = &step.action {
+        let response = controller.raw_command(line).await?;
+        render::print(&response);
+        return Ok(());
+    }
+    let command = step
+        .command()
+        .map_err(|message| PowerCliError::InvalidCommand { command: message })?;
+    if let Some(command) = command {
+        Box::pin(run_command(command, controller, cli)).await?;
+    }
+
+    if let Some(duration) = step.sleep_duration() {
+        // Asleep, the controller does not answer; wait it out, then until
+        // it does
+        tokio::time::sleep(duration).await;
+        loop {
+            match controller.ping().await {
+                Ok(_) => break,
+                Err(e) => {
+                    debug!("Not awake yet: {}", e);
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                }
+            }
+        }
+        render::print(&format!("Awake after {}s", duration.as_secs()));
+    }
+    Ok(())
+}
+
+/// Zero the accumulated charge after confirming the value it discards
+///
+/// The discarded charge is recorded before the readback is checked, since
+/// the write has happened either way. A readback other than 0 fails the
+/// command after the report is printed.
+async fn reset_charge(
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+    command: &str,
+    yes: bool,
+) -> Result<(), PowerCliError> {
+    let previous = controller.read_charge().await?;
+    let discarded = previous.map_or("an unreadable charge".to_string(), |mah| {
+        format!("{} mAh", mah)
+    });
+    if !yes
+        && !cli.dry_run
+        && !confirm_destructive(&format!(
+            "This discards the accumulated charge of {} and sets the coulomb counter to 0.",
+            discarded
+        ))?
+    {
+        return Err(PowerCliError::InvalidCommand {
+            command: "charge reset not confirmed".to_string(),
+        });
+    }
+
+    let report = controller.zero_charge(previous).await?;
+    if !cli.dry_run {
+        record_charge_reset(cli, previous);
+    }
+    if !cli.quiet {
+        emit::result(cli, command, &report, |style| {
+            render::charge_reset(style, &report)
+        })?;
+    }
+    if !report.verified && !cli.dry_run {
+        return Err(PowerCliError::PowerError {
+            message: format!(
+                "charge reset not verified: the counter reads {} after writing 0",
+                report
+                    .charge_mah
+                    .map_or("nothing".to_string(), |mah| format!("{} mAh", mah))
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Add the charge discarded by a reset to the device's coulomb delta file
+fn record_charge_reset(cli: &Cli, previous_charge_mah: Option<u16>) {
+    let Some(file) = power::coulomb::stored(&cli.device) else {
+        log::warn!("No state directory available; discarded charge not recorded");
+        return;
+    };
+    let now = chrono::Utc::now();
+    let recorded = file.update(|delta| {
+        let mut delta = delta.unwrap_or_default();
+        delta.record(now, previous_charge_mah);
+        delta
+    });
+    if let Err(e) = recorded {
+        log::warn!(
+            "Failed to record the discarded charge in {}: {}",
+            file.path().display(),
+            e
+        );
+    }
+}
+
+/// Drop the saved PMU time reference after the PMU rebooted
+fn forget_time_reference(cli: &Cli) {
+    let Some(file) = power::timeref::stored(&cli.device).filter(|_| !cli.dry_run) else {
+        return;
+    };
+    match file.remove() {
+        Ok(true) => info!("Dropped the saved time reference after the PMU reboot"),
+        Ok(false) => {}
+        Err(e) => log::warn!("Failed to remove {}: {}", file.path().display(), e),
+    }
+}
+
+/// `battery read --watch`: read every `interval` until Ctrl-C, then summarize
+async fn watch_battery(
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+    interval: std::time::Duration,
+    capacity: Option<u32>,
+    sparkline: usize,
+) -> Result<(), PowerCliError> {
+    use power::battery::{BatteryWatchStats, VoltageHistory};
+
+    let mut stats = BatteryWatchStats::new();
+    let mut voltages = VoltageHistory::new(sparkline);
+    let mut monitor = controller.battery_monitor();
+    let mut readings = monitor.watch(interval);
+    let interrupted = util::cancel_on_ctrl_c();
+    if !cli.quiet {
+        emit::battery_watch_header(cli);
+    }
+    let result = loop {
+        let battery = tokio::select! {
+            reading = readings.next() => reading,
+            _ = interrupted.cancelled() => break Ok(()),
+        };
+        let battery = match battery {
+            Ok(battery) => battery,
+            Err(e) => break Err(e),
+        };
+        stats.record(&battery);
+        voltages.push(battery.voltage_mv);
+        if !cli.quiet {
+            let sample = json::BatteryWatchSampleJson::new(battery, capacity);
+            emit::battery_watch_sample(cli, "battery watch", &sample, &voltages)?;
+        }
+    };
+    // Keep the last reading on screen and start below it
+    render::live::finish();
+    if !cli.quiet {
+        emit::battery_watch_summary(cli, &stats.summary())?;
+    }
+    result
+}
+
+/// Quiet time on the console that ends one read of `pm monitor` output
+const FOLLOW_QUIET_WINDOW: std::time::Duration = std::time::Duration::from_millis(200);
+
+/// Options of `monitor --follow`
+struct FollowMonitor {
+    interval: u64,
+    deadband: u16,
+    debounce: u32,
+    show_unparsed: bool,
+    console_log: Option<std::path::PathBuf>,
+}
+
+/// `monitor --follow`: start `pm monitor` and read the samples the firmware
+/// prints until Ctrl-C, then stop it and summarize
+async fn follow_monitor(
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+    follow: FollowMonitor,
+) -> Result<(), PowerCliError> {
+    use json::samples::SampleParser;
+
+    let mut console_log = follow
+        .console_log
+        .as_deref()
+        .map(battery_log::ConsoleLog::append)
+        .transpose()?;
+    let mut tracker = power::battery::ChargingTracker::new(follow.deadband, follow.debounce);
+    let mut parser = SampleParser::new().keep_unparsed(follow.show_unparsed);
+    let mut samples = 0u64;
+    let interrupted = util::cancel_on_ctrl_c();
+    controller
+        .pm_command(&format!("monitor start {}", follow.interval))
+        .await?;
+    if !cli.quiet {
+        emit::monitor_header(cli);
+    }
+    let followed: Result<(), PowerCliError> = async {
+        loop {
+            let output = tokio::select! {
+                output = controller.read_console(FOLLOW_QUIET_WINDOW) => output?,
+                _ = interrupted.cancelled() => return Ok(()),
+            };
+            if let Some(log) = console_log.as_mut() {
+                log.record(chrono::Utc::now(), &output)?;
+            }
+            // A quiet console ends any sample still held back
+            let had_format = parser.format().is_some();
+            let parsed = if output.is_empty() {
+                parser.flush()
+            } else {
+                parser.push(&output)
+            };
+            if !cli.quiet {
+                for unparsed in parser.take_unparsed() {
+                    emit::unparsed_line(cli, &unparsed);
+                }
+                if let Some(format) = parser.format().filter(|_| !had_format) {
+                    emit::sample_format(cli, format);
+                }
+            }
+            for sample in parsed {
+                samples += 1;
+                let measurement = json::MeasurementJson {
+                    voltage_mv: sample.battery.voltage_mv,
+                    current_ma: sample.battery.current_ma,
+                    adc_mode: None,
+                    source: "pm monitor".to_string(),
+                };
+                let transition = measurement
+                    .current_ma
+                    .and_then(|current| tracker.update(current, chrono::Utc::now()));
+                if cli.quiet {
+                    continue;
+                }
+                if let Some(transition) = &transition {
+                    emit::charging_transition(cli, transition, &measurement);
+                }
+                let sample = json::MonitorSampleJson {
+                    measurement,
+                    charging_state: tracker.state(),
+                    charging: tracker.is_charging(),
+                    since: tracker.since(),
+                    charger: None,
+                };
+                emit::monitor_sample(cli, &sample)?;
+            }
+        }
+    }
+    .await;
+    // Stopped whichever way the session ended, so the firmware does not
+    // keep printing into the next command's reply
+    let stopped = controller.pm_command("monitor stop").await;
+    if let Some(log) = console_log.as_mut() {
+        log.finish()?;
+    }
+    followed?;
+    stopped?;
+    if !cli.quiet {
+        let stats = parser.stats();
+        let summary = json::MonitorSummaryJson {
+            samples,
+            charging_transitions: tracker.transitions(),
+            final_state: tracker.state(),
+            reboots: controller.reboots(),
+            link: controller.connection_stats().clone(),
+            sample_format: parser.format().map(String::from),
+            unparsed_lines: stats.unparsed,
+            power: None,
+        };
+        emit::monitor_summary(cli, &summary)?;
+    }
+    Ok(())
+}
+
+/// Power counters for the metrics of a `monitor` session; `None` on
+/// firmware without them
+async fn session_power_counters(
+    controller: &mut power::control::PowerController,
+) -> Option<power::stats::PowerCounters> {
+    match controller.power_counters().await {
+        Ok(counters) => Some(counters),
+        Err(e) => {
+            debug!("No power counters for the session summary: {}", e);
+            None
+        }
+    }
+}
+
+/// `ltc2959 read --continuous`: stream readings in continuous ADC mode
+/// until the duration is up or Ctrl-C, then summarize
+async fn read_ltc2959_continuous(
+    controller: &mut power::control::PowerController,
+    cli: &Cli,
+    read: power::ltc2959::ContinuousRead,
+) -> Result<(), PowerCliError> {
+    use power::battery::{VoltageHistory, DEFAULT_SPARKLINE_SAMPLES};
+
+    let mut voltages = VoltageHistory::new(DEFAULT_SPARKLINE_SAMPLES);
+    let interrupted = util::cancel_on_ctrl_c();
+    if !cli.quiet {
+        emit::battery_watch_header(cli);
+    }
+    let result = read
+        .run(controller, &interrupted, |sample| {
+            voltages.push(sample.battery.voltage_mv);
+            if cli.quiet {
+                return Ok(());
+            }
+            emit::battery_watch_sample(cli, "ltc2959 read", &sample, &voltages)
+        })
+        .await;
+    // Keep the last reading on screen and start below it
+    render::live::finish();
+    let result = result?;
+    if !cli.quiet {
+        emit::ltc2959_continuous_summary(cli, &result)?;
+    }
+    Ok(())
+}
+
+/// Simulator faults for `simulate` options
+#[cfg(unix)]
+fn simulator_faults(
+    scenario: cli::SimScenario,
+    firmware: Option<&str>,
+    nfc_field: bool,
+) -> simulator::Faults {
+    let mut faults = simulator::Faults::scenario(scenario);
+    if let Some(version) = firmware {
+        faults.firmware_version = version.to_string();
+    }
+    faults.nfc_field |= nfc_field;
+    faults
+}
+
+/// For `simulate ARGS`, start a simulator and parse ARGS as the invocation
+/// to run against it; other commands are returned unchanged
+#[cfg(unix)]
+fn simulate_in_process(cli: Cli) -> (Cli, Option<simulator::PmuSimulator>) {
+    let Some(cli::Commands::Simulate {
+        listen_pty: false,
+        scenario,
+        ref firmware,
+        nfc_field,
+        ref invocation,
+        ..
+    }) = cli.command
+    else {
+        return (cli, None);
+    };
+    let simulator = simulator::PmuSimulator::with_faults(simulator_faults(
+        scenario,
+        firmware.as_deref(),
+        nfc_field,
+    ));
+    let args = std::iter::once(APP_NAME.to_string())
+        .chain(invocation.iter().cloned())
+        .collect();
+    let mut inner = Cli::parse_invocation(args);
+    inner.device = simulator.device().to_string();
+    debug!("Simulating the controller on {}", inner.device);
+    (inner, Some(simulator))
+}
+
+#[cfg(not(unix))]
+fn simulate_in_process(cli: Cli) -> (Cli, Option<()>) {
+    (cli, None)
+}
+
+/// `simulate --pty`: print the path of a simulator's pseudo-terminal and
+/// serve it, from this process with `--foreground` and otherwise from a
+/// copy of it left running in the background
+#[cfg(unix)]
+async fn serve_simulator(
+    cli: &Cli,
+    foreground: bool,
+    scenario: cli::SimScenario,
+    firmware: Option<&str>,
+    nfc_field: bool,
+) -> Result<(), PowerCliError> {
+    use clap::ValueEnum;
+    use std::io::{BufRead, Write};
+    use std::os::unix::process::CommandExt;
+
+    let scenario_name = scenario
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+    if foreground {
+        let simulator =
+            simulator::PmuSimulator::with_faults(simulator_faults(scenario, firmware, nfc_field));
+        println!("{}", simulator.device());
+        std::io::stdout().flush()?;
+        if !cli.quiet {
+            eprintln!(
+                "Simulating a {} controller on {}; press Ctrl-C to stop",
+                scenario_name,
+                simulator.device()
+            );
+        }
+        tokio::signal::ctrl_c().await?;
+        return Ok(());
+    }
+
+    let mut command = process::Command::new(std::env::current_exe()?);
+    command.args([
+        "--quiet",
+        "simulate",
+        "--pty",
+        "--foreground",
+        "--sim-scenario",
+    ]);
+    command.arg(&scenario_name);
+    if let Some(version) = firmware {
+        command.args(["--sim-firmware", version]);
+    }
+    if nfc_field {
+        command.arg("--sim-nfc-field");
+    }
+    // Its own process group, so Ctrl-C in this shell leaves it running
+    let mut child = command
+        .stdin(process::Stdio::null())
+        .stdout(process::Stdio::piped())
+        .stderr(process::Stdio::null())
+        .process_group(0)
+        .spawn()?;
+    let mut device = String::new();
+    if let Some(stdout) = child.stdout.take() {
+        std::io::BufReader::new(stdout).read_line(&mut device)?;
+    }
+    let device = device.trim();
+    if device.is_empty() {
+        return Err(PowerCliError::DeviceNotFound {
+            device: "simulator pseudo-terminal".to_string(),
+        });
+    }
+    println!("{}", device);
+    if !cli.quiet {
+        eprintln!(
+            "Simulating a {} controller on {} as process {}; stop it with `kill {}`",
+            scenario_name,
+            device,
+            child.id(),
+            child.id()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn serve_simulator(
+    _cli: &Cli,
+    _foreground: bool,
+    _scenario: cli::SimScenario,
+    _firmware: Option<&str>,
+    _nfc_field: bool,
+) -> Result<(), PowerCliError> {
+    Err(PowerCliError::InvalidArguments {
+        message: "simulate needs a Unix pseudo-terminal".to_string(),
+    })
+}
+
+/// mcumgr executable, overridable with `EINK_POWER_CLI_MCUMGR`
+fn mcumgr_program() -> String {
+    std::env::var("EINK_POWER_CLI_MCUMGR").unwrap_or_else(|_| "mcumgr".to_string())
+}
+
+/// systemd-run executable, overridable with `EINK_POWER_CLI_SYSTEMD_RUN`
+fn systemd_run_program() -> String {
+    std::env::var("EINK_POWER_CLI_SYSTEMD_RUN").unwrap_or_else(|_| "systemd-run".to_string())
+}
+
+/// Firmware manager on its own connection to the device, with the
+/// controller's pacing and command map
+fn firmware_manager(
+    cli: &Cli,
+    controller: &power::control::PowerController,
+    transports: firmware::FirmwareTransports,
+) -> Result<firmware::FirmwareManager, PowerCliError> {
+    let mut connection = serial::Connection::new(&cli.device, cli.baud, cli.quiet)?;
+    connection.set_pacing(controller.connection().pacing());
+    connection.set_auto_recover_shell(cli.auto_recover_shell);
+    connection.set_resync(controller.connection().resync_mode());
+    connection.set_echo_check(controller.connection().echo_check());
+    connection.set_timeout_retry(controller.connection().timeout_retry());
+    connection.set_console_quirks(controller.connection().console_quirks().to_vec());
+    connection.set_steal(
+        cli.steal,
+        controller.connection().steal_unit().map(String::from),
+    );
+    connection.set_dry_run(cli.dry_run);
+    connection.set_shell_check(!cli.allow_bootloader);
+    connection.set_bootloader_probe(firmware::bootloader_probe(
+        &mcumgr_program(),
+        firmware::McumgrTransport::Serial {
+            port: cli.device.clone(),
+            baud: cli.baud,
+        },
+    ));
+    let mut manager = firmware::FirmwareManager::new(connection, transports);
+    manager.set_command_map(controller.command_map().clone());
+    manager.set_mcumgr_program(&mcumgr_program());
+    Ok(manager)
+}
+
+/// Print the boot banner received on the connection for `--show-banner`
+///
+/// JSON output carries it in the first envelope instead.
+fn show_boot_banner(cli: &Cli, boot_banner: Option<&serial::banner::BootBanner>) {
+    let is_json = matches!(
+        cli.format,
+        cli::OutputFormat::Json | cli::OutputFormat::Ndjson | cli::OutputFormat::JsonLines
+    );
+    if cli.quiet || is_json {
+        return;
+    }
+    match boot_banner {
+        Some(boot_banner) => eprintln!("📟 Boot banner:\n{}", boot_banner.text),
+        None => eprintln!("📟 No boot banner received (the PMU did not just reset)"),
+    }
+}
+
+/// Ask the user to confirm a destructive operation
+///
+/// Fails when stdin is not a terminal so scripts must pass `--yes`.
+fn confirm_destructive(warning: &str) -> Result<bool, PowerCliError> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() {
+        return Err(PowerCliError::InvalidCommand {
+            command: "destructive operation needs --yes when not run interactively".to_string(),
+        });
+    }
+
+    eprintln!("⚠️  {}", warning);
+    eprint!("Type 'yes' to continue: ");
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim().eq_ignore_ascii_case("yes"))
+}
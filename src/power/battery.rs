@@ -4,24 +4,99 @@
  * All rights reserved.
  */
 
-use crate::error::Result;
+use crate::error::{PowerCliError, Result};
+use crate::json::{
+    BatteryHealthJson, BatteryHealthSamplesJson, BatteryJson, BatterySamplesJson,
+    BatteryWatchSampleJson, BatteryWatchSummaryJson, ResponseParser, SampleStatsJson,
+};
+use crate::power::charger::{ChargerState, ChargerStatus};
 use crate::serial::{Connection, Protocol};
-use log::{debug, info};
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
+use std::borrow::BorrowMut;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::{Interval, MissedTickBehavior};
+
+/// Default band around 0 mA that counts as idle
+pub const DEFAULT_DEADBAND_MA: u16 = 5;
+
+/// Default number of consecutive samples a new state must hold
+pub const DEFAULT_DEBOUNCE_SAMPLES: u32 = 2;
+
+/// Default number of voltage samples kept for the `--watch` sparkline
+pub const DEFAULT_SPARKLINE_SAMPLES: usize = 20;
+
+/// Default gap between `--samples` readings
+pub const DEFAULT_SAMPLE_INTERVAL_MS: u64 = 200;
+
+/// Ticker firing every `interval`, starting immediately
+///
+/// A read that overruns the interval delays the next one rather than
+/// firing a burst to catch up.
+pub fn ticker(interval: Duration) -> Interval {
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    ticker
+}
 
 /// Battery monitoring interface
-#[allow(dead_code)] // Future use - comprehensive battery monitoring
-pub struct BatteryMonitor {
-    protocol: Protocol,
+///
+/// Owns its protocol when built with [`BatteryMonitor::new`]; a
+/// [`PowerController`](crate::power::control::PowerController) lends its own
+/// through `battery_monitor()`.
+pub struct BatteryMonitor<P = Protocol> {
+    protocol: P,
 }
 
 impl BatteryMonitor {
     /// Create a new battery monitor instance
-    #[allow(dead_code)] // Future use
+    #[allow(dead_code)] // Used by tests
     pub fn new(connection: Connection) -> Self {
-        Self {
-            protocol: Protocol::new(connection),
+        Self::with_protocol(Protocol::new(connection))
+    }
+}
+
+impl<P: BorrowMut<Protocol>> BatteryMonitor<P> {
+    /// Monitor on an existing protocol, owned or borrowed
+    pub fn with_protocol(protocol: P) -> Self {
+        Self { protocol }
+    }
+
+    /// Read and parse the LTC2959 measurements
+    pub async fn read(&mut self) -> Result<BatteryJson> {
+        let response = self
+            .protocol
+            .borrow_mut()
+            .execute_battery_command("read")
+            .await?;
+        Ok(ResponseParser::parse_battery_response(&response))
+    }
+
+    /// Stream of readings, one per `interval` starting immediately
+    ///
+    /// A read that overruns the interval delays the next one rather than
+    /// firing a burst to catch up.
+    pub fn watch(&mut self, interval: Duration) -> BatteryWatch<'_, P> {
+        BatteryWatch {
+            monitor: self,
+            ticker: ticker(interval),
+        }
+    }
+
+    /// Take `count` readings from the watch stream, `interval` apart
+    ///
+    /// See [`SampleRun`] for readings that fail.
+    pub async fn sample(&mut self, count: u32, interval: Duration) -> Result<BatterySamplesJson> {
+        let mut run = SampleRun::new(count);
+        let mut readings = self.watch(interval);
+        for _ in 0..count {
+            let reading = readings.next().await;
+            run.record(reading.map(|battery| BatteryWatchSampleJson::new(battery, None)));
         }
+        let (samples, failed) = run.finish()?;
+        Ok(BatterySamplesJson::new(count, failed, samples))
     }
 
     /// Read current battery status
@@ -29,7 +104,11 @@ impl BatteryMonitor {
     pub async fn read_status(&mut self) -> Result<BatteryStatus> {
         info!("Reading battery status");
 
-        let response = self.protocol.execute_battery_command("read").await?;
+        let response = self
+            .protocol
+            .borrow_mut()
+            .execute_battery_command("read")
+            .await?;
         self.parse_battery_response(&response)
     }
 
@@ -38,7 +117,10 @@ impl BatteryMonitor {
     pub async fn get_device_status(&mut self) -> Result<String> {
         info!("Getting battery device status");
 
-        self.protocol.execute_battery_command("status").await
+        self.protocol
+            .borrow_mut()
+            .execute_battery_command("status")
+            .await
     }
 
     /// Enable battery monitoring
@@ -46,7 +128,10 @@ impl BatteryMonitor {
     pub async fn enable_monitoring(&mut self) -> Result<String> {
         info!("Enabling battery monitoring");
 
-        self.protocol.execute_battery_command("enable").await
+        self.protocol
+            .borrow_mut()
+            .execute_battery_command("enable")
+            .await
     }
 
     /// Disable battery monitoring
@@ -54,7 +139,10 @@ impl BatteryMonitor {
     pub async fn disable_monitoring(&mut self) -> Result<String> {
         info!("Disabling battery monitoring");
 
-        self.protocol.execute_battery_command("disable").await
+        self.protocol
+            .borrow_mut()
+            .execute_battery_command("disable")
+            .await
     }
 
     /// Parse battery response into structured data
@@ -70,16 +158,297 @@ impl BatteryMonitor {
         //    🔋 Charge: 2450 mAh
         //    🌡️  Temperature: 23°C
 
+        let current = ChargingState::from_current(125, DEFAULT_DEADBAND_MA);
         Ok(BatteryStatus {
             voltage_mv: 3850,
             current_ma: 125,
             charge_mah: 2450,
             temperature_c: 23,
+            charger: ChargerStatus::resolve(&[], Some(current)),
             timestamp: chrono::Utc::now(),
         })
     }
 }
 
+/// Readings from [`BatteryMonitor::watch`]
+pub struct BatteryWatch<'a, P = Protocol> {
+    monitor: &'a mut BatteryMonitor<P>,
+    ticker: Interval,
+}
+
+impl<P: BorrowMut<Protocol>> BatteryWatch<'_, P> {
+    /// Wait for the next tick and read
+    pub async fn next(&mut self) -> Result<BatteryJson> {
+        self.ticker.tick().await;
+        // A cached reply would repeat the previous reading
+        let protocol: &mut Protocol = self.monitor.protocol.borrow_mut();
+        protocol.connection_mut().invalidate_cache();
+        self.monitor.read().await
+    }
+}
+
+impl BatteryWatchSampleJson {
+    /// Stamp a reading; `capacity_mah` gives the state of charge
+    pub fn new(battery: BatteryJson, capacity_mah: Option<u32>) -> Self {
+        let soc_percent = battery
+            .charge_mah
+            .zip(capacity_mah.filter(|&c| c > 0))
+            .map(|(charge, capacity)| charge as f64 * 100.0 / capacity as f64);
+        Self {
+            timestamp: Utc::now(),
+            battery,
+            soc_percent,
+        }
+    }
+}
+
+/// Readings of a `--samples` run
+///
+/// A reading that fails is left out and the run carries on; the run as a
+/// whole fails only when fewer than half of the readings succeed.
+#[derive(Debug)]
+pub struct SampleRun<T> {
+    requested: u32,
+    readings: Vec<T>,
+    failed: u32,
+    last_error: Option<PowerCliError>,
+}
+
+impl<T> SampleRun<T> {
+    pub fn new(requested: u32) -> Self {
+        Self {
+            requested,
+            readings: Vec::new(),
+            failed: 0,
+            last_error: None,
+        }
+    }
+
+    /// Add the outcome of the next reading
+    pub fn record(&mut self, reading: Result<T>) {
+        match reading {
+            Ok(reading) => self.readings.push(reading),
+            Err(e) => {
+                self.failed += 1;
+                warn!(
+                    "Sample {} of {} failed: {}",
+                    self.readings.len() as u32 + self.failed,
+                    self.requested,
+                    e
+                );
+                self.last_error = Some(e);
+            }
+        }
+    }
+
+    /// Successful readings and how many failed
+    pub fn finish(self) -> Result<(Vec<T>, u32)> {
+        if self.readings.len() * 2 >= self.requested as usize && !self.readings.is_empty() {
+            return Ok((self.readings, self.failed));
+        }
+        Err(PowerCliError::BatteryError {
+            message: format!(
+                "only {} of {} samples succeeded (last error: {})",
+                self.readings.len(),
+                self.requested,
+                self.last_error
+                    .map_or_else(|| "none".to_string(), |e| e.to_string())
+            ),
+        })
+    }
+}
+
+impl SampleStatsJson {
+    /// Statistics of `values`; `None` when there are none
+    ///
+    /// The median of an even number of values is the mean of the middle two.
+    pub fn of(values: impl IntoIterator<Item = f64>) -> Option<Self> {
+        let mut values: Vec<f64> = values.into_iter().collect();
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(f64::total_cmp);
+        let n = values.len();
+        let median = if n % 2 == 1 {
+            values[n / 2]
+        } else {
+            (values[n / 2 - 1] + values[n / 2]) / 2.0
+        };
+        Some(Self {
+            median,
+            mean: values.iter().sum::<f64>() / n as f64,
+            min: values[0],
+            max: values[n - 1],
+        })
+    }
+}
+
+impl BatterySamplesJson {
+    /// Aggregate the readings of a `--samples` run
+    pub fn new(requested: u32, failed: u32, samples: Vec<BatteryWatchSampleJson>) -> Self {
+        let stats = |field: fn(&BatteryJson) -> Option<f64>| {
+            SampleStatsJson::of(samples.iter().filter_map(|sample| field(&sample.battery)))
+        };
+        let voltage_mv = stats(|b| b.voltage_mv.map(f64::from));
+        let current_ma = stats(|b| b.current_ma.map(f64::from));
+        let charge_mah = stats(|b| b.charge_mah.map(f64::from));
+        let power_mw = stats(|b| b.power_mw.map(f64::from));
+        let temperature_c = stats(|b| b.temperature_c.map(f64::from));
+        let median = |stats: Option<SampleStatsJson>| stats.map(|s| s.median.round());
+        Self {
+            median: BatteryJson {
+                voltage_mv: median(voltage_mv).map(|v| v as u16),
+                current_ma: median(current_ma).map(|v| v as i16),
+                charge_mah: median(charge_mah).map(|v| v as u16),
+                power_mw: median(power_mw).map(|v| v as i32),
+                temperature_c: temperature_c.map(|s| s.median as f32),
+            },
+            voltage_mv,
+            current_ma,
+            charge_mah,
+            power_mw,
+            temperature_c,
+            requested,
+            failed,
+            samples,
+        }
+    }
+}
+
+impl BatteryHealthSamplesJson {
+    /// Aggregate the checks of a `--samples` run
+    ///
+    /// The median verdict of an even number of checks is the worse of the
+    /// middle two, so a tie never passes.
+    pub fn new(requested: u32, failed: u32, samples: Vec<BatteryHealthJson>) -> Self {
+        let stats = |field: fn(&BatteryHealthJson) -> Option<f64>| {
+            SampleStatsJson::of(samples.iter().filter_map(field))
+        };
+        let internal_resistance_mohm = stats(|h| h.internal_resistance_mohm.map(f64::from));
+        let loaded_voltage_mv = stats(|h| h.loaded_voltage_mv.map(f64::from));
+        let unloaded_voltage_mv = stats(|h| h.unloaded_voltage_mv.map(f64::from));
+        let median = |stats: Option<SampleStatsJson>| stats.map(|s| s.median.round());
+
+        let mut verdicts: Vec<_> = samples.iter().filter_map(|h| h.verdict).collect();
+        verdicts.sort();
+        let verdict = verdicts.get(verdicts.len() / 2).copied();
+        let result = samples
+            .iter()
+            .find(|h| verdict.is_some() && h.verdict == verdict)
+            .and_then(|h| h.result.clone());
+
+        Self {
+            median: BatteryHealthJson {
+                result,
+                internal_resistance_mohm: median(internal_resistance_mohm).map(|v| v as u32),
+                loaded_voltage_mv: median(loaded_voltage_mv).map(|v| v as u16),
+                unloaded_voltage_mv: median(unloaded_voltage_mv).map(|v| v as u16),
+                verdict,
+            },
+            internal_resistance_mohm,
+            loaded_voltage_mv,
+            unloaded_voltage_mv,
+            requested,
+            failed,
+            samples,
+        }
+    }
+}
+
+/// Last N voltage readings for the `--watch` sparkline
+#[derive(Debug, Clone)]
+pub struct VoltageHistory {
+    capacity: usize,
+    samples: VecDeque<u16>,
+}
+
+impl VoltageHistory {
+    /// Keep at most `capacity` readings (at least one)
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Add a reading, dropping the oldest when full; `None` is skipped
+    pub fn push(&mut self, voltage_mv: Option<u16>) {
+        let Some(voltage_mv) = voltage_mv else {
+            return;
+        };
+        if self.samples.len() == self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(voltage_mv);
+    }
+
+    /// Readings oldest first
+    pub fn samples(&self) -> Vec<u16> {
+        self.samples.iter().copied().collect()
+    }
+}
+
+/// Running min/max/average over a `--watch` session
+#[derive(Debug, Clone)]
+pub struct BatteryWatchStats {
+    started: DateTime<Utc>,
+    summary: BatteryWatchSummaryJson,
+    voltage: (u64, u64),
+    current: (i64, u64),
+    first_charge_mah: Option<u16>,
+}
+
+impl Default for BatteryWatchStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BatteryWatchStats {
+    pub fn new() -> Self {
+        Self {
+            started: Utc::now(),
+            summary: BatteryWatchSummaryJson::default(),
+            voltage: (0, 0),
+            current: (0, 0),
+            first_charge_mah: None,
+        }
+    }
+
+    /// Add one reading
+    pub fn record(&mut self, battery: &BatteryJson) {
+        let summary = &mut self.summary;
+        summary.samples += 1;
+        if let Some(mv) = battery.voltage_mv {
+            summary.voltage_min_mv = Some(summary.voltage_min_mv.map_or(mv, |v| v.min(mv)));
+            summary.voltage_max_mv = Some(summary.voltage_max_mv.map_or(mv, |v| v.max(mv)));
+            self.voltage.0 += mv as u64;
+            self.voltage.1 += 1;
+            summary.voltage_avg_mv = Some(self.voltage.0 as f64 / self.voltage.1 as f64);
+        }
+        if let Some(ma) = battery.current_ma {
+            summary.current_min_ma = Some(summary.current_min_ma.map_or(ma, |v| v.min(ma)));
+            summary.current_max_ma = Some(summary.current_max_ma.map_or(ma, |v| v.max(ma)));
+            self.current.0 += ma as i64;
+            self.current.1 += 1;
+            summary.current_avg_ma = Some(self.current.0 as f64 / self.current.1 as f64);
+        }
+        if let Some(mah) = battery.charge_mah {
+            let first = *self.first_charge_mah.get_or_insert(mah);
+            summary.charge_delta_mah = Some(mah as i32 - first as i32);
+        }
+    }
+
+    /// Summary up to now
+    pub fn summary(&self) -> BatteryWatchSummaryJson {
+        BatteryWatchSummaryJson {
+            duration_s: (Utc::now() - self.started).num_seconds().max(0) as u64,
+            ..self.summary.clone()
+        }
+    }
+}
+
 /// Battery status information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BatteryStatus {
@@ -91,6 +460,8 @@ pub struct BatteryStatus {
     pub charge_mah: u32,
     /// Battery temperature in Celsius
     pub temperature_c: i16,
+    /// Charger state, from the firmware or else the sign of the current
+    pub charger: ChargerStatus,
     /// Timestamp of measurement
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
@@ -102,10 +473,10 @@ impl BatteryStatus {
         (self.voltage_mv as i32 * self.current_ma as i32) / 1000
     }
 
-    /// Check if battery is charging
+    /// Check if battery is charging, trickle charge included
     #[allow(dead_code)] // Future use
     pub fn is_charging(&self) -> bool {
-        self.current_ma > 0
+        self.charger.state.is_charging()
     }
 
     /// Check if battery voltage is low
@@ -127,3 +498,137 @@ impl BatteryStatus {
         )
     }
 }
+
+/// Charging state derived from the sign of the battery current
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChargingState {
+    Charging,
+    Discharging,
+    /// Current within the deadband around 0 mA
+    Idle,
+}
+
+impl ChargingState {
+    /// Classify one current reading; `|current_ma| <= deadband_ma` is idle
+    pub fn from_current(current_ma: i16, deadband_ma: u16) -> Self {
+        if current_ma.unsigned_abs() <= deadband_ma {
+            ChargingState::Idle
+        } else if current_ma > 0 {
+            ChargingState::Charging
+        } else {
+            ChargingState::Discharging
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChargingState::Charging => "charging",
+            ChargingState::Discharging => "discharging",
+            ChargingState::Idle => "idle",
+        }
+    }
+}
+
+/// Change of [`ChargingState`] reported by [`ChargingTracker`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChargingTransition {
+    pub from: ChargingState,
+    pub to: ChargingState,
+    /// Time of the first sample in the new state
+    pub at: DateTime<Utc>,
+}
+
+/// Debounced charging state over a series of current samples
+///
+/// A new state is only taken once `debounce` consecutive samples agree on
+/// it, so a current hovering at the deadband edge does not flap. The first
+/// sample sets the initial state without counting as a transition.
+#[derive(Debug, Clone)]
+pub struct ChargingTracker {
+    deadband_ma: u16,
+    debounce: u32,
+    state: Option<(ChargingState, DateTime<Utc>)>,
+    candidate: Option<(ChargingState, DateTime<Utc>, u32)>,
+    transitions: u32,
+}
+
+impl ChargingTracker {
+    /// `debounce` of 0 is treated as 1 (switch on the first sample)
+    pub fn new(deadband_ma: u16, debounce: u32) -> Self {
+        Self {
+            deadband_ma,
+            debounce: debounce.max(1),
+            state: None,
+            candidate: None,
+            transitions: 0,
+        }
+    }
+
+    /// Feed one current sample taken at `at`
+    pub fn update(&mut self, current_ma: i16, at: DateTime<Utc>) -> Option<ChargingTransition> {
+        self.update_with(current_ma, None, at)
+    }
+
+    /// Feed one current sample with the charger state read alongside it
+    ///
+    /// A charger state that tells charging from discharging decides over
+    /// the current, so trickle charge within the deadband counts as
+    /// charging and a terminated charge as idle.
+    pub fn update_with(
+        &mut self,
+        current_ma: i16,
+        charger: Option<ChargerState>,
+        at: DateTime<Utc>,
+    ) -> Option<ChargingTransition> {
+        let observed = charger
+            .and_then(ChargerState::charging_state)
+            .unwrap_or_else(|| ChargingState::from_current(current_ma, self.deadband_ma));
+        let Some((current, _)) = self.state else {
+            self.state = Some((observed, at));
+            return None;
+        };
+
+        if observed == current {
+            self.candidate = None;
+            return None;
+        }
+
+        let (since, count) = match self.candidate {
+            Some((state, since, count)) if state == observed => (since, count + 1),
+            _ => (at, 1),
+        };
+        if count < self.debounce {
+            self.candidate = Some((observed, since, count));
+            return None;
+        }
+
+        self.state = Some((observed, since));
+        self.candidate = None;
+        self.transitions += 1;
+        Some(ChargingTransition {
+            from: current,
+            to: observed,
+            at: since,
+        })
+    }
+
+    /// Current state; `None` until the first sample
+    pub fn state(&self) -> Option<ChargingState> {
+        self.state.map(|(state, _)| state)
+    }
+
+    /// When the current state was entered
+    pub fn since(&self) -> Option<DateTime<Utc>> {
+        self.state.map(|(_, since)| since)
+    }
+
+    pub fn is_charging(&self) -> bool {
+        self.state() == Some(ChargingState::Charging)
+    }
+
+    /// Transitions seen so far, not counting the initial state
+    pub fn transitions(&self) -> u32 {
+        self.transitions
+    }
+}
@@ -0,0 +1,229 @@
+/*
+ * E-ink Power CLI - Charger State
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! What the battery charger is doing, as the firmware reports it
+//!
+//! The sign of the battery current cannot tell trickle charge or a
+//! terminated charge from a unit idling on external power: the current sits
+//! near 0 mA in all three. Firmware that knows the charger state says so in
+//! a `Charger: <state>` line, in the LTC2959 `Charge Complete` flag or
+//! through the CC_GPIO input the charger drives on termination.
+//! [`ChargerStatus::resolve`] takes the first state reported and falls back
+//! to the current when there is none, and lists the sources that disagree
+//! with the state taken.
+
+use crate::json::patterns;
+use crate::power::battery::ChargingState;
+use serde::{Deserialize, Serialize};
+
+/// `source` of a state taken from the battery current
+pub const CURRENT_SOURCE: &str = "current";
+
+/// Charger state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChargerState {
+    /// Constant-current or constant-voltage charge
+    Charging,
+    /// Pre-charge of a deeply discharged cell, or topping off before
+    /// termination
+    Trickle,
+    /// Charge terminated, the cell is full
+    Complete,
+    Discharging,
+    /// The charger stopped on a fault such as a safety timer or temperature
+    Fault,
+    Unknown,
+}
+
+impl ChargerState {
+    /// Parse a charger state as the firmware prints it, e.g. `Trickle`,
+    /// `CHARGE COMPLETE` or `Not charging`
+    pub fn parse(raw: &str) -> Option<Self> {
+        let word = raw
+            .split(['(', ','])
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .replace(['-', '_'], " ");
+        match word.as_str() {
+            "charging" | "fast charge" | "fast charging" | "cc" | "cv" | "constant current"
+            | "constant voltage" => Some(ChargerState::Charging),
+            "trickle" | "trickle charge" | "trickle charging" | "precharge" | "pre charge"
+            | "top off" | "topping off" | "terminating" => Some(ChargerState::Trickle),
+            "complete" | "charge complete" | "charged" | "done" | "full" => {
+                Some(ChargerState::Complete)
+            }
+            "discharging" | "not charging" | "no input" | "on battery" => {
+                Some(ChargerState::Discharging)
+            }
+            "unknown" => Some(ChargerState::Unknown),
+            _ if word.contains("fault") || word.contains("error") || word.contains("timeout") => {
+                Some(ChargerState::Fault)
+            }
+            _ => None,
+        }
+    }
+
+    /// The state the current reading suggests; a current within the
+    /// deadband is unknown
+    pub fn from_current(state: ChargingState) -> Self {
+        match state {
+            ChargingState::Charging => ChargerState::Charging,
+            ChargingState::Discharging => ChargerState::Discharging,
+            ChargingState::Idle => ChargerState::Unknown,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChargerState::Charging => "charging",
+            ChargerState::Trickle => "trickle",
+            ChargerState::Complete => "complete",
+            ChargerState::Discharging => "discharging",
+            ChargerState::Fault => "fault",
+            ChargerState::Unknown => "unknown",
+        }
+    }
+
+    /// Whether the cell is taking charge
+    pub fn is_charging(self) -> bool {
+        matches!(self, ChargerState::Charging | ChargerState::Trickle)
+    }
+
+    /// The state as the current sign would put it, for tracking
+    /// transitions; `None` when the charger state says nothing about it
+    pub fn charging_state(self) -> Option<ChargingState> {
+        match self {
+            ChargerState::Charging | ChargerState::Trickle => Some(ChargingState::Charging),
+            ChargerState::Complete => Some(ChargingState::Idle),
+            ChargerState::Discharging => Some(ChargingState::Discharging),
+            ChargerState::Fault | ChargerState::Unknown => None,
+        }
+    }
+
+    /// Whether a current reading in `current` state contradicts this one
+    ///
+    /// A charging current during trickle charge may be lost in the
+    /// deadband, so only readings flowing the other way count.
+    fn contradicts(self, current: ChargingState) -> bool {
+        match self {
+            ChargerState::Charging | ChargerState::Trickle => current == ChargingState::Discharging,
+            ChargerState::Complete => current != ChargingState::Idle,
+            ChargerState::Discharging => current == ChargingState::Charging,
+            ChargerState::Fault | ChargerState::Unknown => false,
+        }
+    }
+}
+
+/// Charger state the reply to `command` reports, if any
+///
+/// A `Charger:` line wins over the flags: `Charge Complete: YES` in
+/// `ltc2959 status`, or a high CC_GPIO input. A flag that is not set says
+/// nothing, since the charger may be charging or absent.
+pub fn reported_state(response: &str) -> Option<ChargerState> {
+    if let Some(state) = patterns::CHARGER_STATE
+        .captures(response)
+        .and_then(|caps| ChargerState::parse(&caps[1]))
+    {
+        return Some(state);
+    }
+    let flag_set = |caps: regex::Captures| {
+        matches!(
+            caps[1].to_lowercase().as_str(),
+            "yes" | "true" | "high" | "on" | "1"
+        )
+    };
+    let complete = patterns::CHARGE_COMPLETE
+        .captures(response)
+        .is_some_and(flag_set)
+        || patterns::CC_GPIO_STATE
+            .captures(response)
+            .is_some_and(flag_set);
+    complete.then_some(ChargerState::Complete)
+}
+
+/// One source's view of the charger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChargerReport<'a> {
+    /// Shell command the state was read with
+    pub source: &'a str,
+    pub state: ChargerState,
+}
+
+/// Charger state, where it came from and what disagrees with it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChargerStatus {
+    pub state: ChargerState,
+    /// Shell command the state was read with, or `current` when it comes
+    /// from the sign of the battery current
+    pub source: String,
+    /// State of the battery current, when there was a reading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_state: Option<ChargingState>,
+    /// Sources, the current included, that disagree with `state`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<String>,
+}
+
+impl ChargerStatus {
+    /// Take the first of `reports`, or the state of the current without
+    /// any; `current` is the state of the battery current reading
+    pub fn resolve(reports: &[ChargerReport], current: Option<ChargingState>) -> Self {
+        let Some(taken) = reports.first() else {
+            return Self {
+                state: current.map_or(ChargerState::Unknown, ChargerState::from_current),
+                source: CURRENT_SOURCE.to_string(),
+                current_state: current,
+                conflicts: Vec::new(),
+            };
+        };
+
+        let mut conflicts: Vec<String> = reports[1..]
+            .iter()
+            .filter(|report| report.state != taken.state)
+            .map(|report| {
+                format!(
+                    "`{}` reports {} but `{}` reports {}",
+                    report.source,
+                    report.state.as_str(),
+                    taken.source,
+                    taken.state.as_str()
+                )
+            })
+            .collect();
+        if let Some(current) = current.filter(|&current| taken.state.contradicts(current)) {
+            conflicts.push(format!(
+                "the battery current says {} but `{}` reports {}",
+                current.as_str(),
+                taken.source,
+                taken.state.as_str()
+            ));
+        }
+        Self {
+            state: taken.state,
+            source: taken.source.to_string(),
+            current_state: current,
+            conflicts,
+        }
+    }
+
+    /// Whether the state comes from the firmware rather than the current
+    pub fn is_reported(&self) -> bool {
+        self.source != CURRENT_SOURCE
+    }
+
+    /// One word for `battery status --brief`: the charger state, or the
+    /// state of the current (`charging`, `discharging` or `idle`) when the
+    /// firmware reports none
+    pub fn brief(&self) -> &'static str {
+        match (self.is_reported(), self.current_state) {
+            (false, Some(current)) => current.as_str(),
+            _ => self.state.as_str(),
+        }
+    }
+}
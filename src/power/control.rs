@@ -4,14 +4,54 @@
  * All rights reserved.
  */
 
-use crate::error::Result;
-use crate::serial::{Connection, Protocol};
-use log::{debug, info};
+use crate::error::{PowerCliError, Result};
+use crate::json::{
+    patterns, BatteryHealthJson, BatteryHealthSamplesJson, MeasurementJson, PowerDefaults,
+    ResponseParser,
+};
+use crate::power::battery::{self, BatteryMonitor, ChargingState};
+use crate::power::charger::{self, ChargerReport, ChargerStatus};
+use crate::power::coulomb::ChargeResetReport;
+use crate::power::factory_reset::{
+    FactoryResetReport, FactoryResetStatus, FactoryResetStep, FactoryResetStepResult,
+};
+use crate::power::gpio::{
+    GpioConfigResult, GpioConfigStatus, GpioMode, GpioOpResult, GpioOpStatus, GpioPin, GpioScript,
+    GpioScriptMode, GpioScriptReport,
+};
+use crate::power::identity::{
+    self, DeviceIdentity, EEPROM_BLOCK_SIZE, IDENTITY_BLOCKS, IDENTITY_FIRST_BLOCK, IDENTITY_LEN,
+};
+use crate::power::passthrough::SramMailbox;
+use crate::power::rails::{PowerRail, PowerRailGraph, Rail};
+use crate::power::reboot::{self, RebootDetector, RebootEvent, SessionState};
+use crate::power::rtc::{RtcCalibration, MAX_CALIBRATION_PPM};
+use crate::power::stats::{PowerCounters, PowerStatsReport};
+use crate::power::timeref::{self, TimeReference, TARGET_ACCURACY_MS, TIME_REF_SAMPLES};
+use crate::power::wake::{WakeMask, WakeSource};
+use crate::serial::{
+    BaudChange, CommandMap, Connection, ConnectionStats, LatencyStats, Protocol, TimeoutPolicy,
+};
+use crate::util::{self, CancellationToken};
+use clap::ValueEnum;
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+/// Time the controller needs to come back after a factory reset reboot
+const FACTORY_RESET_REBOOT_WAIT: std::time::Duration = std::time::Duration::from_secs(5);
 
 /// Power controller interface
 pub struct PowerController {
     protocol: Protocol,
+    reboots: RebootDetector,
+    session: SessionState,
+    /// Whether the firmware has `gpio batch`, once asked
+    gpio_batch: Option<bool>,
+    rail_graph: PowerRailGraph,
+    /// Stops waits for the controller early, e.g. on Ctrl-C
+    cancel: CancellationToken,
 }
 
 impl PowerController {
@@ -19,46 +59,165 @@ impl PowerController {
     pub fn new(connection: Connection) -> Self {
         Self {
             protocol: Protocol::new(connection),
+            reboots: RebootDetector::new(),
+            session: SessionState::default(),
+            gpio_batch: None,
+            rail_graph: PowerRailGraph::default(),
+            cancel: CancellationToken::new(),
         }
     }
 
-    /// Control PMIC power
-    pub async fn control_pmic(&mut self, state: PowerState) -> Result<String> {
-        info!("Controlling PMIC power: {:?}", state);
+    /// Check rail switching against `graph` instead of the board topology
+    pub fn set_rail_graph(&mut self, graph: PowerRailGraph) {
+        self.rail_graph = graph;
+    }
 
-        let state_str = match state {
-            PowerState::On => "on",
-            PowerState::Off => "off",
-            PowerState::Status => "status",
-        };
+    /// Stop waiting for the controller once `cancel` is cancelled
+    pub fn set_cancel(&mut self, cancel: CancellationToken) {
+        self.cancel = cancel;
+    }
 
-        self.protocol.execute_power_command("pmic", state_str).await
+    /// Use remapped shell root commands (for forked firmware)
+    pub fn set_command_map(&mut self, commands: CommandMap) {
+        self.protocol.set_command_map(commands);
     }
 
-    /// Control WiFi power
-    pub async fn control_wifi(&mut self, state: PowerState) -> Result<String> {
-        info!("Controlling WiFi power: {:?}", state);
+    /// Per-command timeouts over the connection timeout
+    pub fn set_timeout_policy(&mut self, timeouts: TimeoutPolicy) {
+        self.protocol.set_timeout_policy(timeouts);
+    }
 
-        let state_str = match state {
-            PowerState::On => "on",
-            PowerState::Off => "off",
-            PowerState::Status => "status",
-        };
+    /// Shell root commands in use
+    pub fn command_map(&self) -> &CommandMap {
+        self.protocol.command_map()
+    }
 
-        self.protocol.execute_power_command("wifi", state_str).await
+    /// Underlying serial connection
+    pub fn connection(&self) -> &Connection {
+        self.protocol.connection()
     }
 
-    /// Control display power
-    pub async fn control_display(&mut self, state: PowerState) -> Result<String> {
-        info!("Controlling display power: {:?}", state);
+    /// Traffic counters of the serial connection for this session
+    pub fn connection_stats(&self) -> &ConnectionStats {
+        self.connection().stats()
+    }
 
-        let state_str = match state {
-            PowerState::On => "on",
-            PowerState::Off => "off",
-            PowerState::Status => "status",
-        };
+    /// Flush and close the serial connection
+    pub async fn close(&mut self) {
+        self.protocol.connection_mut().close().await;
+    }
+
+    /// Discard stale bytes waiting on the serial connection
+    pub async fn flush_rx_buffer(&mut self) -> Result<usize> {
+        debug!("Flushing receive buffer");
+        self.protocol.flush_rx_buffer().await
+    }
+
+    /// Switch `rail` or read its state (`pm <rail> on|off|status`)
+    pub async fn control_rail(&mut self, rail: Rail, state: PowerState) -> Result<String> {
+        info!("Controlling {} power: {:?}", rail.name(), state);
+        self.protocol
+            .execute_power_command(rail, state.as_shell_str())
+            .await
+    }
 
-        self.protocol.execute_power_command("disp", state_str).await
+    /// Switched state of the PMIC, WiFi and display rails (`pm <rail>
+    /// status`); a rail whose reply shows no state is left out
+    pub async fn rail_states(&mut self) -> Result<BTreeMap<PowerRail, bool>> {
+        self.states_of(&[PowerRail::Pmic, PowerRail::Wifi, PowerRail::Display])
+            .await
+    }
+
+    /// States of the switched rails among `rails`; others are left out
+    async fn states_of(&mut self, rails: &[PowerRail]) -> Result<BTreeMap<PowerRail, bool>> {
+        let mut states = BTreeMap::new();
+        for &rail in rails {
+            let Some(shell_rail) = rail.shell_rail() else {
+                continue;
+            };
+            let response = self
+                .protocol
+                .execute_power_command(shell_rail, "status")
+                .await?;
+            if let Some(on) = ResponseParser::parse_rail_state(&response) {
+                states.insert(rail, on);
+            }
+        }
+        Ok(states)
+    }
+
+    /// Make sure the rails `rail` depends on are on before switching it on
+    ///
+    /// With `auto_deps` the ones that are off are switched on in order and
+    /// returned with the controller's responses; otherwise switching is
+    /// refused with a [`PowerCliError::PowerError`] naming them.
+    pub async fn prepare_rail_on(
+        &mut self,
+        rail: PowerRail,
+        auto_deps: bool,
+    ) -> Result<Vec<(PowerRail, String)>> {
+        let prerequisites = self.rail_graph.prerequisites(rail);
+        let states = self.states_of(&prerequisites).await?;
+        let missing = self.rail_graph.missing_prerequisites(rail, &states);
+        if missing.is_empty() {
+            return Ok(Vec::new());
+        }
+        if !auto_deps {
+            let names: Vec<&str> = missing.iter().map(|dep| dep.name()).collect();
+            let commands: Vec<String> = missing
+                .iter()
+                .filter_map(|dep| dep.to_possible_value())
+                .map(|dep| format!("'power {} on'", dep.get_name()))
+                .collect();
+            return Err(PowerCliError::PowerError {
+                message: format!(
+                    "{} needs {} on first; run {} or pass --auto-deps",
+                    rail.name(),
+                    names.join(", "),
+                    commands.join(", then ")
+                ),
+            });
+        }
+        info!("Switching on {:?} ahead of {}", missing, rail.name());
+        self.sequence_power_on(&missing).await
+    }
+
+    /// Rails depending on `rail` that are still on
+    pub async fn dependents_still_on(&mut self, rail: PowerRail) -> Result<Vec<PowerRail>> {
+        let dependents = self.rail_graph.dependents(rail);
+        let states = self.states_of(&dependents).await?;
+        Ok(self.rail_graph.dependents_on(rail, &states))
+    }
+
+    /// Power on `rails` in dependency order
+    ///
+    /// The requested rails are sorted with the rail dependency model
+    /// (adding any dependencies that were not listed) and switched on one
+    /// by one. Returns each rail with the controller's response.
+    pub async fn sequence_power_on(
+        &mut self,
+        rails: &[PowerRail],
+    ) -> Result<Vec<(PowerRail, String)>> {
+        let graph = self.rail_graph.clone();
+        let order = graph.sort_power_on(rails);
+        graph.validate_sequence(&order)?;
+        debug!("Power-on sequence: {:?}", order);
+
+        let mut responses = Vec::new();
+        for rail in order {
+            info!("Powering on {}", rail.name());
+            let response = match rail {
+                PowerRail::Pmic => self.control_rail(Rail::Pmic, PowerState::On).await?,
+                PowerRail::Wifi => self.control_rail(Rail::Wifi, PowerState::On).await?,
+                PowerRail::Display => self.control_rail(Rail::Display, PowerState::On).await?,
+                PowerRail::Nfc => self.device_action("nfc", "wake").await?,
+                PowerRail::Ltc2959 => self.device_action("ltc2959", "wake").await?,
+                // No enable of its own; it comes up with the PMIC
+                PowerRail::Imx93 => "Supplied by PMIC".to_string(),
+            };
+            responses.push((rail, response));
+        }
+        Ok(responses)
     }
 
     /// Get power statistics
@@ -66,7 +225,35 @@ impl PowerController {
         info!("Getting power statistics");
 
         let response = self.protocol.execute_system_command("power stats").await?;
-        self.parse_power_stats(&response)
+        let stats = PowerStats::parse(&response);
+        if stats.is_empty() && !self.connection().is_dry_run() {
+            return Err(PowerCliError::InvalidResponse { response });
+        }
+        Ok(stats)
+    }
+
+    /// Power statistics with the uptime and the metrics derived from both
+    ///
+    /// Without the uptime the counters are still reported, without metrics.
+    pub async fn power_stats_report(&mut self) -> Result<PowerStatsReport> {
+        let stats = self.get_power_stats().await?;
+        let uptime_ms = match self.get_system_uptime().await {
+            Ok(response) => reboot::parse_uptime_ms(&response),
+            Err(e) => {
+                warn!("Cannot read the uptime for derived power statistics: {}", e);
+                None
+            }
+        };
+        Ok(PowerStatsReport::new(stats, uptime_ms))
+    }
+
+    /// Power counters with the uptime they were read at
+    pub async fn power_counters(&mut self) -> Result<PowerCounters> {
+        let stats = self.get_power_stats().await?;
+        let response = self.get_system_uptime().await?;
+        let uptime_ms = reboot::parse_uptime_ms(&response)
+            .ok_or(PowerCliError::InvalidResponse { response })?;
+        Ok(PowerCounters::new(&stats, uptime_ms))
     }
 
     /// Get system information
@@ -81,6 +268,16 @@ impl PowerController {
         self.protocol.execute_system_command("ping").await
     }
 
+    /// Send `line` to the controller shell as typed
+    ///
+    /// The line may change anything, so cached replies are dropped.
+    pub async fn raw_command(&mut self, line: &str) -> Result<String> {
+        debug!("Sending raw line: {}", line);
+        let response = self.protocol.execute_system_command(line.trim()).await;
+        self.invalidate_cache();
+        response
+    }
+
     /// Control GPIO pin
     pub async fn control_gpio(
         &mut self,
@@ -120,6 +317,34 @@ impl PowerController {
         self.protocol.execute_ltc2959_command(command).await
     }
 
+    /// Accumulated charge from `ltc2959 read`; `None` if the reply has none
+    pub async fn read_charge(&mut self) -> Result<Option<u16>> {
+        let response = self.control_ltc2959("read").await?;
+        Ok(ResponseParser::parse_ltc2959_status(&response).charge_mah)
+    }
+
+    /// Write 0 to the accumulated charge and read it back
+    ///
+    /// `previous_charge_mah` is the value read before, for the report. A
+    /// readback other than 0 is reported as unverified rather than failing.
+    pub async fn zero_charge(
+        &mut self,
+        previous_charge_mah: Option<u16>,
+    ) -> Result<ChargeResetReport> {
+        info!("Zeroing accumulated charge");
+        let response = self.control_ltc2959("set_charge 0").await?;
+        let charge_mah = match self.connection().is_dry_run() {
+            true => None,
+            false => self.read_charge().await?,
+        };
+        Ok(ChargeResetReport {
+            previous_charge_mah,
+            charge_mah,
+            verified: charge_mah == Some(0),
+            response,
+        })
+    }
+
     /// Get coulomb counter readings (power coulomb command)
     pub async fn get_coulomb_counter(&mut self) -> Result<String> {
         debug!("Getting coulomb counter readings");
@@ -138,10 +363,62 @@ impl PowerController {
         self.protocol.execute_system_command("system uptime").await
     }
 
-    /// Reboot the system
-    pub async fn reboot_system(&mut self) -> Result<String> {
-        debug!("Rebooting system");
-        self.protocol.execute_system_command("system reset").await
+    /// Map PMU uptime onto the host clock
+    ///
+    /// Reads the uptime [`TIME_REF_SAMPLES`] times and keeps the reading
+    /// with the shortest round trip, which has the smallest error.
+    pub async fn time_reference(&mut self) -> Result<TimeReference> {
+        let mut best: Option<TimeReference> = None;
+        for _ in 0..TIME_REF_SAMPLES {
+            let response = self.get_system_uptime().await?;
+            let uptime_ms = reboot::parse_uptime_ms(&response).ok_or_else(|| {
+                PowerCliError::InvalidResponse {
+                    response: format!("no uptime in {:?}", response),
+                }
+            })?;
+            let round_trip = self
+                .protocol
+                .connection()
+                .last_round_trip()
+                .ok_or_else(|| PowerCliError::InvalidResponse {
+                    response: "uptime reply has no round-trip timing".to_string(),
+                })?;
+            let reference = TimeReference::from_exchange(
+                uptime_ms,
+                timeref::uptime_resolution_ms(&response),
+                round_trip,
+            );
+            debug!(
+                "Uptime {} ms, round trip {:.1} ms",
+                uptime_ms, reference.round_trip_ms
+            );
+            if best
+                .as_ref()
+                .is_none_or(|best| reference.round_trip_ms < best.round_trip_ms)
+            {
+                best = Some(reference);
+            }
+        }
+
+        let reference = best.expect("at least one sample");
+        if reference.uncertainty_ms > TARGET_ACCURACY_MS {
+            warn!(
+                "Time reference is only accurate to ±{:.0} ms",
+                reference.uncertainty_ms
+            );
+        }
+        Ok(reference)
+    }
+
+    /// Reboot the system, optionally with a cold reset
+    pub async fn reboot_system(&mut self, cold: bool) -> Result<String> {
+        debug!("Rebooting system (cold: {})", cold);
+        let cmd = if cold {
+            "system reset cold"
+        } else {
+            "system reset"
+        };
+        self.pm_command(cmd).await
     }
 
     /// Battery read (maps to ltc2959 read)
@@ -150,6 +427,11 @@ impl PowerController {
         self.protocol.execute_ltc2959_command("read").await
     }
 
+    /// Battery monitor sharing this controller's connection
+    pub fn battery_monitor(&mut self) -> BatteryMonitor<&mut Protocol> {
+        BatteryMonitor::with_protocol(&mut self.protocol)
+    }
+
     /// Battery status (maps to ltc2959 status)
     pub async fn battery_status(&mut self) -> Result<String> {
         debug!("Getting battery status");
@@ -176,7 +458,349 @@ impl PowerController {
 
     pub async fn pm_command(&mut self, cmd: &str) -> Result<String> {
         debug!("Executing PM command: {}", cmd);
-        self.protocol.execute_pm_command(cmd).await
+        let response = self.protocol.execute_pm_command(cmd).await?;
+        self.session.record_pm_command(cmd);
+        Ok(response)
+    }
+
+    /// Probe uptime and report a reset since the previous probe
+    ///
+    /// A reset is seen as uptime going backwards or as the boot banner on
+    /// the console. After one, cached responses are dropped and session
+    /// settings the firmware forgot (`pm monitor start`) are sent again.
+    pub async fn check_for_reboot(&mut self) -> Result<Option<RebootEvent>> {
+        let response = self.get_system_uptime().await?;
+        let banner = self.protocol.connection_mut().take_boot_banner();
+        let Some(mut event) = self
+            .reboots
+            .observe(reboot::parse_uptime_ms(&response), banner)
+        else {
+            return Ok(None);
+        };
+        if banner {
+            event.reset_cause = self
+                .protocol
+                .connection()
+                .boot_banner()
+                .and_then(|banner| banner.reset_cause.clone());
+        }
+
+        warn!("{}", event.message());
+        self.protocol.connection_mut().invalidate_cache();
+        for command in self.session.pm_commands() {
+            info!("Re-applying 'pm {}' after PMU reboot", command);
+            if let Err(e) = self.protocol.execute_pm_command(&command).await {
+                warn!("Could not re-apply 'pm {}': {}", command, e);
+            }
+        }
+        Ok(Some(event))
+    }
+
+    /// PMU resets detected by [`Self::check_for_reboot`] so far
+    pub fn reboots(&self) -> u32 {
+        self.reboots.reboots()
+    }
+
+    /// Drop every cached response, e.g. after new firmware was uploaded
+    /// over another connection
+    pub fn invalidate_cache(&mut self) {
+        self.protocol.connection_mut().invalidate_cache();
+    }
+
+    /// Read the firmware wake-source mask (`pm wake config`)
+    ///
+    /// `None` if the reply has no wake configuration, e.g. in dry-run mode.
+    pub async fn wake_sources(&mut self) -> Result<Option<WakeMask>> {
+        let response = self.protocol.execute_pm_command("wake config").await?;
+        Ok(WakeMask::parse(&response))
+    }
+
+    /// Allow or stop `source` waking the controller
+    pub async fn set_wake_source(&mut self, source: WakeSource, enabled: bool) -> Result<String> {
+        let action = if enabled { "enable" } else { "disable" };
+        info!("{} wake source {}", action, source.as_str());
+        self.protocol
+            .execute_pm_command(&format!("wake {} {}", action, source.as_str()))
+            .await
+    }
+
+    /// Run the firmware battery health check (`pm battery_check`)
+    pub async fn battery_health_check(&mut self) -> Result<BatteryHealthJson> {
+        info!("Running battery health check");
+        let response = self.protocol.execute_pm_command("battery_check").await?;
+        Ok(ResponseParser::parse_battery_health(&response))
+    }
+
+    /// Run the health check `count` times, `interval` apart
+    ///
+    /// See [`SampleRun`](crate::power::battery::SampleRun) for checks that
+    /// fail.
+    pub async fn battery_health_samples(
+        &mut self,
+        count: u32,
+        interval: Duration,
+    ) -> Result<BatteryHealthSamplesJson> {
+        let mut run = battery::SampleRun::new(count);
+        let mut ticker = battery::ticker(interval);
+        for _ in 0..count {
+            ticker.tick().await;
+            self.protocol.connection_mut().invalidate_cache();
+            run.record(self.battery_health_check().await);
+        }
+        let (samples, failed) = run.finish()?;
+        Ok(BatteryHealthSamplesJson::new(count, failed, samples))
+    }
+
+    /// Measure serial round-trip latency using `samples` pings
+    pub async fn measure_latency(&mut self, samples: u8) -> Result<LatencyStats> {
+        debug!("Measuring round-trip latency over {} pings", samples);
+        self.protocol.measure_round_trip_latency(samples).await
+    }
+
+    /// Switch the controller console and the local port to `rate`
+    ///
+    /// Rolls back to the current rate if the controller does not answer at
+    /// the new one; see [`BaudChange::failure_message`] for the outcome.
+    pub async fn set_baud_rate(&mut self, rate: u32, persist: bool) -> BaudChange {
+        info!("Changing console baud rate to {}", rate);
+        self.protocol.change_baud_rate(rate, persist).await
+    }
+
+    /// Take a one-shot `pm measure` reading
+    ///
+    /// Works even while the coulomb counter ADC is in smart-sleep.
+    pub async fn measure(&mut self) -> Result<MeasurementJson> {
+        debug!("Taking one-shot measurement");
+        let response = self.protocol.execute_pm_command("measure").await?;
+        Ok(ResponseParser::parse_measurement(&response))
+    }
+
+    /// Charger state reported by `ltc2959 status` and the CC_GPIO input,
+    /// or else the sign of `current_ma` beyond `deadband_ma`
+    ///
+    /// Firmware without one of the commands answers it with an error and
+    /// the other, or the current, decides. Conflicts are logged as well as
+    /// returned.
+    pub async fn read_charger(
+        &mut self,
+        current_ma: Option<i16>,
+        deadband_ma: u16,
+    ) -> Result<ChargerStatus> {
+        // The state moves on between samples
+        self.protocol.connection_mut().invalidate_cache();
+        let mut replies = Vec::new();
+        for command in ["status", "cc_gpio status"] {
+            match self.protocol.execute_ltc2959_command(command).await {
+                Ok(response) => replies.push((format!("ltc2959 {}", command), response)),
+                Err(PowerCliError::ControllerError { message }) => {
+                    debug!("No charger state from ltc2959 {}: {}", command, message)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        let reports: Vec<ChargerReport> = replies
+            .iter()
+            .filter_map(|(source, response)| {
+                Some(ChargerReport {
+                    source,
+                    state: charger::reported_state(response)?,
+                })
+            })
+            .collect();
+        let current = current_ma.map(|current| ChargingState::from_current(current, deadband_ma));
+        let status = ChargerStatus::resolve(&reports, current);
+        for conflict in &status.conflicts {
+            warn!("Charger state conflict: {}", conflict);
+        }
+        Ok(status)
+    }
+
+    /// Output the controller prints on its own within `window`, such as
+    /// the samples of a running `pm monitor`
+    pub async fn read_console(&mut self, window: Duration) -> Result<String> {
+        self.protocol.connection_mut().read_output(window).await
+    }
+
+    /// Sample voltage and current from the LTC2959 (`ltc2959 read`)
+    pub async fn ltc2959_measurement(&mut self) -> Result<MeasurementJson> {
+        debug!("Sampling LTC2959");
+        let response = self.protocol.execute_ltc2959_command("read").await?;
+        Ok(ResponseParser::parse_ltc2959_status(&response).into())
+    }
+
+    /// Wake or sleep a peripheral such as the LTC2959 or NFC controller
+    pub async fn device_action(&mut self, device: &str, action: &str) -> Result<String> {
+        debug!("Setting {} to {}", device, action);
+        self.protocol
+            .execute_device_action_command(device, action)
+            .await
+    }
+
+    /// Save the pending power rail defaults to flash and verify them
+    ///
+    /// Re-reads the defaults after saving and fails if they are not marked as
+    /// saved or differ from the values that were pending before the save.
+    pub async fn save_rail_defaults(&mut self) -> Result<String> {
+        debug!("Saving power rail defaults");
+        let requested = ResponseParser::parse_rail_defaults(&self.pm_command("defaults").await?);
+
+        let save_response = self.pm_command("defaults save").await?;
+
+        let readback = self.pm_command("defaults").await?;
+        let saved = ResponseParser::parse_rail_defaults(&readback);
+
+        let mut problems = saved.mismatches(&requested);
+        if !saved.saved_in_flash {
+            problems.push("defaults are not reported as saved in flash".to_string());
+        }
+        if !problems.is_empty() {
+            return Err(PowerCliError::PowerError {
+                message: format!(
+                    "Defaults save verification failed: {}\n{}",
+                    problems.join("; "),
+                    readback
+                ),
+            });
+        }
+
+        Ok(format!("{}\n{}", save_response, readback))
+    }
+
+    /// Return the controller to factory state
+    ///
+    /// Runs every [`FactoryResetStep`] not listed in `skip`, in order, and
+    /// stops at the first failure. The verify step re-reads the defaults and
+    /// the coulomb counter once the controller is back from its reboot.
+    pub async fn factory_reset(&mut self, skip: &[FactoryResetStep]) -> FactoryResetReport {
+        let mut steps = Vec::new();
+        let mut failed = false;
+
+        for step in FactoryResetStep::ALL {
+            let (status, detail) = if failed {
+                (FactoryResetStatus::NotRun, None)
+            } else if skip.contains(&step) {
+                (FactoryResetStatus::Skipped, None)
+            } else {
+                info!("Factory reset: {}", step.description());
+                match self.factory_reset_step(step, skip).await {
+                    Ok(detail) => (FactoryResetStatus::Ok, Some(detail)),
+                    Err(e) => {
+                        failed = true;
+                        (FactoryResetStatus::Failed, Some(e.to_string()))
+                    }
+                }
+            };
+            steps.push(FactoryResetStepResult {
+                step,
+                status,
+                detail,
+            });
+        }
+
+        FactoryResetReport {
+            success: !failed,
+            steps,
+        }
+    }
+
+    async fn factory_reset_step(
+        &mut self,
+        step: FactoryResetStep,
+        skip: &[FactoryResetStep],
+    ) -> Result<String> {
+        match step {
+            FactoryResetStep::EraseDefaults => self.pm_command("system erase defaults").await,
+            FactoryResetStep::ChargeReset => self.control_ltc2959("production_reset").await,
+            FactoryResetStep::RtcConfig => self.rtc_config("none").await,
+            FactoryResetStep::Reboot => {
+                // The controller may reset before it gets to reply
+                let response = match self.pm_command("system reset").await {
+                    Err(PowerCliError::Timeout { .. }) => String::new(),
+                    other => other?,
+                };
+                if !self.connection().is_dry_run() {
+                    let label = "Waiting for the controller to reboot";
+                    util::countdown(FACTORY_RESET_REBOOT_WAIT, label, &self.cancel)
+                        .await
+                        .or_cancelled("waiting for the controller to reboot")?;
+                    self.flush_rx_buffer().await?;
+                }
+                Ok(response)
+            }
+            FactoryResetStep::Verify => {
+                let mut checks = Vec::new();
+                let mut problems = Vec::new();
+
+                if !skip.contains(&FactoryResetStep::EraseDefaults) {
+                    let defaults =
+                        ResponseParser::parse_rail_defaults(&self.pm_command("defaults").await?);
+                    if defaults.saved_in_flash {
+                        problems.push("defaults are still stored in flash".to_string());
+                    } else {
+                        checks.push("defaults erased".to_string());
+                    }
+                }
+                if !skip.contains(&FactoryResetStep::ChargeReset) {
+                    let ltc =
+                        ResponseParser::parse_ltc2959_status(&self.control_ltc2959("read").await?);
+                    match ltc.charge_mah {
+                        Some(0) => checks.push("charge register reset".to_string()),
+                        Some(charge) => problems
+                            .push(format!("charge register reads {} mAh, expected 0", charge)),
+                        None => problems.push("charge register could not be read".to_string()),
+                    }
+                }
+
+                if self.connection().is_dry_run() {
+                    return Ok("verification not possible in dry-run".to_string());
+                }
+                if !problems.is_empty() {
+                    return Err(PowerCliError::PowerError {
+                        message: format!(
+                            "Factory reset verification failed: {}",
+                            problems.join("; ")
+                        ),
+                    });
+                }
+                Ok(checks.join(", "))
+            }
+        }
+    }
+
+    /// Read the power rail defaults as a complete set
+    pub async fn export_rail_defaults(&mut self) -> Result<PowerDefaults> {
+        debug!("Exporting power rail defaults");
+        ResponseParser::parse_rail_defaults(&self.pm_command("defaults").await?).try_into()
+    }
+
+    /// Apply a set of power rail defaults and save them to flash
+    ///
+    /// The save is verified as in [`Self::save_rail_defaults`].
+    pub async fn import_rail_defaults(&mut self, defaults: &PowerDefaults) -> Result<String> {
+        debug!("Importing power rail defaults: {:?}", defaults);
+        let state = |on: bool| if on { PowerState::On } else { PowerState::Off };
+        for (rail, on) in [
+            (Rail::Pmic, defaults.pmic),
+            (Rail::Wifi, defaults.wifi),
+            (Rail::Display, defaults.disp),
+        ] {
+            self.rail_default(rail, state(on)).await?;
+        }
+        self.save_rail_defaults().await
+    }
+
+    /// Set the state `rail` comes up in (`pm defaults <rail> on|off`)
+    ///
+    /// Takes effect on the next boot once saved with
+    /// [`Self::save_rail_defaults`].
+    pub async fn rail_default(&mut self, rail: Rail, state: PowerState) -> Result<String> {
+        debug!("Setting {} default to {:?}", rail.name(), state);
+        self.pm_command(&format!(
+            "defaults {} {}",
+            rail.as_shell_str(),
+            state.as_shell_str()
+        ))
+        .await
     }
 
     /// Execute NFC commands
@@ -185,30 +809,126 @@ impl PowerController {
         self.protocol.execute_nfc_command(cmd).await
     }
 
+    /// NFC SRAM mailbox sharing this controller's connection
+    pub fn sram_mailbox(&mut self) -> SramMailbox<&mut Protocol> {
+        SramMailbox::with_protocol(&mut self.protocol)
+    }
+
+    /// Read the unit identity from the NFC EEPROM
+    ///
+    /// `None` if the identity block is unprogrammed or invalid.
+    pub async fn read_identity(&mut self) -> Result<Option<DeviceIdentity>> {
+        info!("Reading device identity from NFC EEPROM");
+        let bytes = self.read_identity_block().await?;
+        Ok(DeviceIdentity::decode(&bytes))
+    }
+
+    /// Program the unit identity into the NFC EEPROM and read it back
+    ///
+    /// Fails with [`PowerCliError::NfcError`] if the readback differs.
+    pub async fn write_identity(&mut self, identity: &DeviceIdentity) -> Result<()> {
+        info!("Writing device identity {}", identity.serial);
+        let encoded = identity.encode();
+        for (block, data) in (IDENTITY_FIRST_BLOCK..).zip(encoded.chunks(EEPROM_BLOCK_SIZE)) {
+            self.protocol
+                .execute_nfc_command(&format!(
+                    "eeprom write 0x{:03X} {}",
+                    block,
+                    identity::format_block(data)
+                ))
+                .await?;
+        }
+
+        if self.connection().is_dry_run() {
+            return Ok(());
+        }
+        let readback = self.read_identity_block().await?;
+        if readback.get(..IDENTITY_LEN) != Some(&encoded[..]) {
+            return Err(PowerCliError::NfcError {
+                message: format!(
+                    "identity readback mismatch: wrote {}, read {}",
+                    identity::format_block(&encoded),
+                    identity::format_block(&readback)
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    async fn read_identity_block(&mut self) -> Result<Vec<u8>> {
+        let response = self
+            .protocol
+            .execute_nfc_command(&format!(
+                "eeprom read 0x{:03X} {}",
+                IDENTITY_FIRST_BLOCK, IDENTITY_BLOCKS
+            ))
+            .await?;
+        Ok(identity::parse_eeprom_dump(&response))
+    }
+
     /// Get RTC status (internal + external PCF2131)
     pub async fn rtc_status(&mut self) -> Result<String> {
         info!("Getting RTC status");
-        self.protocol.execute_rtc_command("status").await
+        Ok(self.protocol.execute_rtc_command("status").await?.raw)
     }
 
     /// Configure external RTC interrupt action
     pub async fn rtc_config(&mut self, action: &str) -> Result<String> {
         info!("Configuring external RTC action: {}", action);
-        self.protocol
+        Ok(self
+            .protocol
             .execute_rtc_command(&format!("config {}", action))
-            .await
+            .await?
+            .raw)
     }
 
     /// Show external RTC interrupt configuration
     pub async fn rtc_show_config(&mut self) -> Result<String> {
         info!("Getting external RTC configuration");
-        self.protocol.execute_rtc_command("show").await
+        Ok(self.protocol.execute_rtc_command("show").await?.raw)
+    }
+
+    /// Program the external RTC crystal offset
+    ///
+    /// Sends `rtc calibrate <register>` with the PCF2131 register value
+    /// closest to `ppm`.
+    pub async fn rtc_calibrate(&mut self, ppm: i16) -> Result<RtcCalibration> {
+        if ppm.abs() > MAX_CALIBRATION_PPM {
+            return Err(PowerCliError::InvalidCommand {
+                command: format!(
+                    "RTC offset {} ppm is outside ±{} ppm",
+                    ppm, MAX_CALIBRATION_PPM
+                ),
+            });
+        }
+
+        let calibration = RtcCalibration::for_ppm(ppm);
+        debug!(
+            "Calibrating RTC: {} ppm -> register {}",
+            ppm, calibration.register_value
+        );
+        self.protocol
+            .execute_rtc_command(&format!("calibrate {}", calibration.register_value))
+            .await?;
+        Ok(calibration)
+    }
+
+    /// Read the external RTC crystal offset register
+    pub async fn rtc_calibration_read(&mut self) -> Result<RtcCalibration> {
+        debug!("Reading RTC calibration");
+        let response = self.protocol.execute_rtc_command("calibration").await?;
+        RtcCalibration::parse(&response.raw).ok_or(PowerCliError::InvalidResponse {
+            response: response.raw,
+        })
     }
 
     /// Get internal RTC counter value (uptime)
-    pub async fn rtc_get(&mut self) -> Result<String> {
+    pub async fn rtc_get(&mut self) -> Result<u32> {
         info!("Getting internal RTC counter value");
-        self.protocol.execute_rtc_command("get").await
+        let response = self.protocol.execute_rtc_command("get").await?;
+        response.counter.ok_or(PowerCliError::InvalidResponse {
+            response: response.raw,
+        })
     }
 
     /// Control communication signal
@@ -218,43 +938,129 @@ impl PowerController {
     }
 
     /// Execute GPIO config command
-    pub async fn control_gpio_config(
-        &mut self,
-        port: &str,
-        pin: u8,
-        mode: &str,
-    ) -> Result<String> {
+    pub async fn control_gpio_config(&mut self, port: &str, pin: u8, mode: &str) -> Result<String> {
         info!("Configuring GPIO {}{} mode: {}", port, pin, mode);
         let command = format!("gpio config {} {} {}", port, pin, mode);
         self.protocol.execute_system_command(&command).await
     }
 
-    /// Parse power statistics response
-    fn parse_power_stats(&self, response: &str) -> Result<PowerStats> {
-        debug!("Parsing power stats: {}", response);
+    /// Configure a GPIO pin and read it back to check it took the mode
+    ///
+    /// The readback is skipped in dry-run mode and the result is unverified.
+    pub async fn configure_gpio(&mut self, gpio: &GpioPin, mode: &str) -> Result<GpioConfigResult> {
+        let port = gpio.firmware_port();
+        let response = self.control_gpio_config(&port, gpio.pin, mode).await?;
+        let requested = GpioMode::parse(mode);
+        let readback = if self.connection().is_dry_run() {
+            String::new()
+        } else {
+            self.control_gpio(&port, gpio.pin, GpioAction::Get).await?
+        };
+        let actual = ResponseParser::parse_gpio_response(&readback, &gpio.port, gpio.pin);
+        let result = GpioConfigResult::check(requested, actual, response);
+        if result.status == GpioConfigStatus::Mismatch {
+            warn!("GPIO {} did not take mode '{}'", result.pin_name(), mode);
+        }
+        Ok(result)
+    }
 
-        // TODO: Implement actual parsing based on controller response format
-        // This is a placeholder implementation
-        Ok(PowerStats {
-            active_time_ms: 123456,
-            sleep_count: 42,
-            wake_count: 38,
-            rtc_wake_count: 15,
-            nfc_wake_count: 12,
-            uart_wake_count: 11,
-            timestamp: chrono::Utc::now(),
-        })
+    /// Whether the firmware has `gpio batch`, from the `gpio -h` listing
+    ///
+    /// Asked once per session. Firmware that rejects the help request, and
+    /// dry runs, count as not having it.
+    pub async fn supports_gpio_batch(&mut self) -> bool {
+        if self.connection().is_dry_run() {
+            return false;
+        }
+        if let Some(supported) = self.gpio_batch {
+            return supported;
+        }
+        let supported = match self.protocol.execute_system_command("gpio -h").await {
+            Ok(listing) => patterns::GPIO_BATCH_SUBCOMMAND.is_match(&listing),
+            Err(e) => {
+                debug!("No GPIO subcommand listing: {}", e);
+                false
+            }
+        };
+        self.gpio_batch = Some(supported);
+        supported
+    }
+
+    /// Run a GPIO script, as one `gpio batch` transaction when `mode` is
+    /// [`GpioScriptMode::Firmware`], otherwise step by step from the host
+    ///
+    /// Host steps stop at the first pin the firmware refuses; the rest are
+    /// reported as skipped.
+    pub async fn run_gpio_script(
+        &mut self,
+        script: &GpioScript,
+        mode: GpioScriptMode,
+    ) -> Result<GpioScriptReport> {
+        if mode == GpioScriptMode::Firmware {
+            info!("Running GPIO script as one batch: {}", script);
+            let command = format!("gpio batch {}", script.batch_args());
+            let response = self.protocol.execute_system_command(&command).await?;
+            return Ok(GpioScriptReport::from_batch(script, &response));
+        }
+
+        info!("Running GPIO script from the host: {}", script);
+        let started = Instant::now();
+        let mut results = Vec::new();
+        let mut failed = false;
+        for (pin, value, requested) in script.steps() {
+            let mut result = GpioOpResult {
+                op: format!("{}={}", pin, value),
+                status: GpioOpStatus::Skipped,
+                at_us: None,
+                gap_us: None,
+                requested_gap_us: requested.as_micros() as u64,
+                error: None,
+            };
+            if !failed {
+                if !requested.is_zero() {
+                    tokio::time::sleep(requested).await;
+                }
+                match self
+                    .control_gpio(&pin.firmware_port(), pin.pin, GpioAction::Set(value))
+                    .await
+                {
+                    Ok(_) => {
+                        result.status = GpioOpStatus::Ok;
+                        result.at_us = Some(started.elapsed().as_micros() as u64);
+                    }
+                    Err(e) => {
+                        warn!("GPIO script stopped at {}: {}", result.op, e);
+                        result.status = GpioOpStatus::Failed;
+                        result.error = Some(e.to_string());
+                        failed = true;
+                    }
+                }
+            }
+            results.push(result);
+        }
+        Ok(GpioScriptReport::new(script, GpioScriptMode::Host, results))
     }
 }
 
 /// Power states
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum PowerState {
     On,
     Off,
     Status,
 }
 
+impl PowerState {
+    /// Argument of the firmware's rail commands
+    pub fn as_shell_str(self) -> &'static str {
+        match self {
+            PowerState::On => "on",
+            PowerState::Off => "off",
+            PowerState::Status => "status",
+        }
+    }
+}
+
 /// GPIO actions
 #[derive(Debug, Clone)]
 pub enum GpioAction {
@@ -270,35 +1076,84 @@ pub enum BoardAction {
 }
 
 /// Power management statistics
+///
+/// Counters the firmware does not print are `None`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PowerStats {
     /// Active time in milliseconds
-    pub active_time_ms: u32,
+    pub active_time_ms: Option<u32>,
     /// Number of sleep cycles
-    pub sleep_count: u32,
+    pub sleep_count: Option<u32>,
     /// Number of wake events
-    pub wake_count: u32,
+    pub wake_count: Option<u32>,
     /// RTC wake events
-    pub rtc_wake_count: u32,
+    pub rtc_wake_count: Option<u32>,
     /// NFC wake events
-    pub nfc_wake_count: u32,
+    pub nfc_wake_count: Option<u32>,
     /// UART wake events
-    pub uart_wake_count: u32,
+    pub uart_wake_count: Option<u32>,
     /// Timestamp of measurement
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
 impl PowerStats {
-    /// Format for human-readable display
-    pub fn format_human(&self) -> String {
-        format!(
-            "⚡ Power Management Statistics:\n   Active time: {} ms\n   Sleep count: {}\n   Wake events: {}\n   RTC wake events: {}\n   NFC wake events: {}\n   UART wake events: {}",
+    /// Parse the reply to `power stats`
+    ///
+    /// Reads `Label: <number>` lines: `Active time: 3600000 ms`,
+    /// `Sleep count`, `Wake count` and per-source lines such as `RTC wakes`
+    /// or `RTC wake events`.
+    pub fn parse(response: &str) -> Self {
+        debug!("Parsing power stats: {}", response);
+        let mut stats = Self {
+            active_time_ms: None,
+            sleep_count: None,
+            wake_count: None,
+            rtc_wake_count: None,
+            nfc_wake_count: None,
+            uart_wake_count: None,
+            timestamp: chrono::Utc::now(),
+        };
+        for line in response.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let Some(count) = value
+                .split_whitespace()
+                .next()
+                .and_then(|n| n.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let source = key
+                .strip_suffix(" wakes")
+                .or_else(|| key.strip_suffix(" wake count"))
+                .or_else(|| key.strip_suffix(" wake events"));
+            let field = match (key.as_str(), source) {
+                ("active time", _) => &mut stats.active_time_ms,
+                ("sleep count" | "sleep cycles", _) => &mut stats.sleep_count,
+                ("wake count" | "wake events" | "wake cycles", _) => &mut stats.wake_count,
+                (_, Some("rtc")) => &mut stats.rtc_wake_count,
+                (_, Some("nfc")) => &mut stats.nfc_wake_count,
+                (_, Some("uart")) => &mut stats.uart_wake_count,
+                _ => continue,
+            };
+            *field = Some(count);
+        }
+        stats
+    }
+
+    /// Whether no counter was found
+    pub fn is_empty(&self) -> bool {
+        [
             self.active_time_ms,
             self.sleep_count,
             self.wake_count,
             self.rtc_wake_count,
             self.nfc_wake_count,
-            self.uart_wake_count
-        )
+            self.uart_wake_count,
+        ]
+        .iter()
+        .all(Option::is_none)
     }
 }
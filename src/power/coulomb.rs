@@ -0,0 +1,69 @@
+/*
+ * E-ink Power CLI - Coulomb Counter Reset
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Zeroing the LTC2959 accumulated charge after a battery swap
+//!
+//! `power coulomb --reset` writes 0 with `ltc2959 set_charge` and reads the
+//! counter back, without the rest of `ltc2959 production_reset`. The charge
+//! it discarded is added to [`COULOMB_DELTA_FILE`] in the device state
+//! directory, so readings taken before the swap can still be related to
+//! the new ones.
+
+use crate::state::{DeviceState, StateFile, StatePayload};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// State file holding the discarded charge
+pub const COULOMB_DELTA_FILE: &str = "coulomb_delta.json";
+
+/// Stored charge resets for a serial device; `None` without a state directory
+pub fn stored(device: &str) -> Option<StateFile<CoulombDelta>> {
+    DeviceState::for_device(device).map(|state| state.file(COULOMB_DELTA_FILE))
+}
+
+/// One reset of the accumulated charge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChargeReset {
+    pub at: DateTime<Utc>,
+    /// Charge read just before the reset; `None` if it could not be read
+    pub previous_charge_mah: Option<u16>,
+}
+
+/// Charge discarded by resets of the counter, oldest reset first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CoulombDelta {
+    /// Sum of the charge read before each reset
+    pub discarded_mah: u64,
+    pub resets: Vec<ChargeReset>,
+}
+
+impl StatePayload for CoulombDelta {
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl CoulombDelta {
+    /// Add a reset that discarded `previous_charge_mah`
+    pub fn record(&mut self, at: DateTime<Utc>, previous_charge_mah: Option<u16>) {
+        self.discarded_mah += previous_charge_mah.map_or(0, u64::from);
+        self.resets.push(ChargeReset {
+            at,
+            previous_charge_mah,
+        });
+    }
+}
+
+/// Outcome of `power coulomb --reset`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChargeResetReport {
+    /// Charge that was discarded; `None` if it could not be read
+    pub previous_charge_mah: Option<u16>,
+    /// Charge read back after writing 0
+    pub charge_mah: Option<u16>,
+    /// Whether the readback was 0
+    pub verified: bool,
+    /// Controller reply to `ltc2959 set_charge 0`
+    pub response: String,
+}
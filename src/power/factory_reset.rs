@@ -0,0 +1,116 @@
+/*
+ * E-ink Power CLI - Factory Reset
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Steps and report of the `system factory-reset` composite command
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// One step of a factory reset, in execution order
+///
+/// The serialized names are stable; RMA tooling archives them.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FactoryResetStep {
+    /// Erase power rail defaults stored in flash
+    EraseDefaults,
+    /// Production-reset the LTC2959 coulomb counter
+    ChargeReset,
+    /// Clear the external RTC interrupt action
+    RtcConfig,
+    /// Reboot the controller
+    Reboot,
+    /// Check that defaults are erased and the charge register is reset
+    Verify,
+}
+
+impl FactoryResetStep {
+    /// All steps, in execution order
+    pub const ALL: [FactoryResetStep; 5] = [
+        FactoryResetStep::EraseDefaults,
+        FactoryResetStep::ChargeReset,
+        FactoryResetStep::RtcConfig,
+        FactoryResetStep::Reboot,
+        FactoryResetStep::Verify,
+    ];
+
+    /// Short description for human-readable output
+    pub fn description(self) -> &'static str {
+        match self {
+            FactoryResetStep::EraseDefaults => "Erase stored defaults",
+            FactoryResetStep::ChargeReset => "Reset coulomb counter",
+            FactoryResetStep::RtcConfig => "Clear RTC interrupt config",
+            FactoryResetStep::Reboot => "Reboot controller",
+            FactoryResetStep::Verify => "Verify final state",
+        }
+    }
+}
+
+/// Outcome of a factory reset step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FactoryResetStatus {
+    Ok,
+    Failed,
+    /// Omitted with `--skip`
+    Skipped,
+    /// Not attempted because an earlier step failed
+    NotRun,
+}
+
+/// Result of one step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactoryResetStepResult {
+    pub step: FactoryResetStep,
+    pub status: FactoryResetStatus,
+    /// Controller response, or the reason for a failure
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Per-step report of a factory reset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FactoryResetReport {
+    pub success: bool,
+    pub steps: Vec<FactoryResetStepResult>,
+}
+
+impl FactoryResetReport {
+    /// First step that failed, if any
+    pub fn failed_step(&self) -> Option<&FactoryResetStepResult> {
+        self.steps
+            .iter()
+            .find(|s| s.status == FactoryResetStatus::Failed)
+    }
+
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        self.steps
+            .iter()
+            .map(|result| {
+                let icon = match result.status {
+                    FactoryResetStatus::Ok => "✅",
+                    FactoryResetStatus::Failed => "❌",
+                    FactoryResetStatus::Skipped => "⏭️ ",
+                    FactoryResetStatus::NotRun => "⏸️ ",
+                };
+                let mut line = format!("{} {}", icon, result.step.description());
+                match (result.status, &result.detail) {
+                    (FactoryResetStatus::Skipped, _) => line.push_str(" (skipped)"),
+                    (FactoryResetStatus::NotRun, _) => line.push_str(" (not run)"),
+                    (_, Some(detail)) if !detail.trim().is_empty() => {
+                        for detail_line in detail.trim().lines() {
+                            line.push_str(&format!("\n      {}", detail_line));
+                        }
+                    }
+                    _ => {}
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
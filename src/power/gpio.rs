@@ -0,0 +1,610 @@
+/*
+ * E-ink Power CLI - GPIO Configuration
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! GPIO pin configuration, its readback check and timed pin scripts
+//!
+//! `gpio config` only acknowledges the command. Whether the pin changed
+//! mode is checked by reading it back with `gpio get`, which reports the
+//! direction and, on newer firmware, the pull resistor
+//! (e.g. `GPIO A0: 1 (INPUT, PULL-UP, HIGH)`).
+//!
+//! `gpio script` drives several pins in order with delays in between, such
+//! as `a0=1,5ms,a1=1,2ms,b3=0`. Firmware with `gpio batch` runs the whole
+//! script as one transaction; otherwise the host sends one `gpio set` per
+//! pin and waits between them.
+//!
+//! Pins go by three names: the firmware shell calls the ports `gpioa` to
+//! `gpioe`, while the schematic and firmware logs write `PTA5`. Any of
+//! `gpioa 5`, `A5`, `pta5` and `PTA5` is accepted, case-insensitively, and
+//! sent in the firmware form.
+
+use crate::error::PowerCliError;
+use crate::json::{patterns, GpioJson};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Longest delay allowed in a GPIO script
+pub const MAX_SCRIPT_DELAY: Duration = Duration::from_secs(10);
+
+/// Port letters of the controller, `gpioa` to `gpioe` in the firmware
+pub const GPIO_PORTS: &str = "ABCDE";
+
+/// Pins per port, numbered from 0
+pub const GPIO_PINS_PER_PORT: u8 = 32;
+
+/// One pin, written `a0`, `B3`, `pta5`, `PTA5` or `gpioa5` on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpioPin {
+    /// Port letter, upper case
+    pub port: String,
+    pub pin: u8,
+}
+
+/// Port letter and pin number, if any, of a port or pin spelling
+///
+/// The error says what is wrong without repeating the spelling.
+fn parse_pin_name(s: &str) -> Result<(char, Option<u8>), String> {
+    let lower = s.trim().to_ascii_lowercase();
+    let rest = lower
+        .strip_prefix("gpio")
+        .or_else(|| lower.strip_prefix("pt"))
+        .unwrap_or(&lower);
+    let split = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    let (letters, number) = rest.split_at(split);
+
+    let mut chars = letters.chars();
+    let port = match (chars.next(), chars.next()) {
+        (Some(port), None) => port.to_ascii_uppercase(),
+        (None, _) => return Err("no port letter".to_string()),
+        (Some(_), Some(_)) => {
+            return Err("ambiguous; name one port, e.g. A5, PTA5 or gpioa 5".to_string())
+        }
+    };
+    if !GPIO_PORTS.contains(port) {
+        return Err(format!(
+            "port {} is out of range; the controller has ports A to E",
+            port
+        ));
+    }
+    if number.is_empty() {
+        return Ok((port, None));
+    }
+    if !number.chars().all(|c| c.is_ascii_digit()) {
+        return Err("unexpected characters after the pin number".to_string());
+    }
+    match number.parse::<u8>() {
+        Ok(pin) if pin < GPIO_PINS_PER_PORT => Ok((port, Some(pin))),
+        _ => Err(pin_out_of_range(number)),
+    }
+}
+
+fn pin_out_of_range(pin: impl fmt::Display) -> String {
+    format!(
+        "pin {} is out of range; ports have pins 0 to {}",
+        pin,
+        GPIO_PINS_PER_PORT - 1
+    )
+}
+
+impl GpioPin {
+    /// Pin from a port and a pin number given separately (`gpioa 5`), or
+    /// from a port argument naming the whole pin (`PTA5`) when `pin` is `None`
+    pub fn from_parts(port: &str, pin: Option<u8>) -> Result<Self, String> {
+        let (letter, named) =
+            parse_pin_name(port).map_err(|reason| format!("'{}': {}", port.trim(), reason))?;
+        let pin = match (named, pin) {
+            (Some(pin), None) => pin,
+            (None, Some(pin)) if pin < GPIO_PINS_PER_PORT => pin,
+            (None, Some(pin)) => return Err(pin_out_of_range(pin)),
+            (Some(_), Some(pin)) => {
+                return Err(format!(
+                    "'{}' already names a pin, so pin {} is ambiguous; give the port alone",
+                    port.trim(),
+                    pin
+                ))
+            }
+            (None, None) => {
+                return Err(format!(
+                    "'{}' needs a pin number, e.g. {} 5",
+                    port.trim(),
+                    port.trim()
+                ))
+            }
+        };
+        Ok(Self {
+            port: letter.to_string(),
+            pin,
+        })
+    }
+
+    /// Port as the firmware shell names it, e.g. `gpioa`
+    pub fn firmware_port(&self) -> String {
+        format!("gpio{}", self.port.to_ascii_lowercase())
+    }
+
+    /// Name in the schematic and firmware logs, e.g. `PTA5`
+    pub fn schematic_name(&self) -> String {
+        format!("PT{}{}", self.port, self.pin)
+    }
+
+    /// Names of the pin, with `typed` as the user spelled it
+    pub fn names(&self, typed: &str) -> PinNames {
+        PinNames {
+            typed: typed.to_string(),
+            firmware: format!("{} {}", self.firmware_port(), self.pin),
+            schematic: self.schematic_name(),
+        }
+    }
+}
+
+impl FromStr for GpioPin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            |reason: &str| format!("'{}' is not a pin like a0 or PTB3: {}", s.trim(), reason);
+        match parse_pin_name(s).map_err(|reason| invalid(&reason))? {
+            (port, Some(pin)) => Ok(Self {
+                port: port.to_string(),
+                pin,
+            }),
+            (_, None) => Err(invalid("no pin number")),
+        }
+    }
+}
+
+impl fmt::Display for GpioPin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.port, self.pin)
+    }
+}
+
+/// A pin as it was typed and in its firmware and schematic forms
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinNames {
+    /// As typed on the command line, e.g. `pta5`
+    pub typed: String,
+    /// As sent to the firmware, e.g. `gpioa 5`
+    pub firmware: String,
+    /// As in the schematic, e.g. `PTA5`
+    pub schematic: String,
+}
+
+impl PinNames {
+    /// `pta5 → gpioa 5 (PTA5)`
+    pub fn describe(&self) -> String {
+        format!("{} → {} ({})", self.typed, self.firmware, self.schematic)
+    }
+}
+
+/// Signal names of the `comm` commands and the pins that carry them
+pub const COMM_SIGNALS: [(&str, &str, &str); 2] = [
+    ("bt_wake", "BT_WAKE_HOST", "PTC1"),
+    ("wl_wake", "WL_WAKE_HOST", "PTC3"),
+];
+
+/// Heading of a `comm` signal with its pin, e.g. `BT_WAKE_HOST (PTC1, gpioc 1)`
+pub fn comm_signal_title(signal: &str) -> String {
+    let Some((_, name, pin)) = COMM_SIGNALS.iter().find(|(id, _, _)| *id == signal) else {
+        return signal.to_ascii_uppercase();
+    };
+    match pin.parse::<GpioPin>() {
+        Ok(gpio) => format!(
+            "{} ({}, {} {})",
+            name,
+            gpio.schematic_name(),
+            gpio.firmware_port(),
+            gpio.pin
+        ),
+        Err(_) => name.to_string(),
+    }
+}
+
+/// Direction and pull a `gpio config` mode asks for
+///
+/// Fields the mode does not mention are `None` and are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GpioMode {
+    /// `INPUT` or `OUTPUT`
+    pub direction: Option<String>,
+    /// `UP`, `DOWN` or `NONE`
+    pub pull: Option<String>,
+}
+
+impl GpioMode {
+    /// Read a mode such as `input`, `output` or `input-pullup`
+    pub fn parse(mode: &str) -> Self {
+        let mut parsed = Self::default();
+        let lower = mode.to_ascii_lowercase();
+        for word in lower.split(|c: char| !c.is_ascii_alphanumeric()) {
+            match word {
+                "in" | "input" => parsed.direction = Some("INPUT".to_string()),
+                "out" | "output" => parsed.direction = Some("OUTPUT".to_string()),
+                "up" | "pullup" | "pu" => parsed.pull = Some("UP".to_string()),
+                "down" | "pulldown" | "pd" => parsed.pull = Some("DOWN".to_string()),
+                "no" | "none" | "nopull" | "float" | "floating" => {
+                    parsed.pull = Some("NONE".to_string())
+                }
+                _ => {}
+            }
+        }
+        parsed
+    }
+}
+
+/// Outcome of checking one pin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GpioConfigStatus {
+    /// The readback reports every requested setting
+    Verified,
+    /// The readback reports a different setting than requested
+    Mismatch,
+    /// The readback does not report a requested setting
+    Unverified,
+}
+
+/// Requested and read-back configuration of one pin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpioConfigResult {
+    pub requested: GpioMode,
+    pub actual: GpioJson,
+    pub status: GpioConfigStatus,
+    /// Reply to `gpio config`
+    pub response: String,
+}
+
+impl GpioConfigResult {
+    /// Compare the readback with the request
+    pub fn check(requested: GpioMode, actual: GpioJson, response: String) -> Self {
+        let outcomes = [
+            (&requested.direction, &actual.direction),
+            (&requested.pull, &actual.pull),
+        ]
+        .into_iter()
+        .filter_map(|(want, got)| want.as_ref().map(|want| (want, got.as_ref())));
+
+        let mut status = GpioConfigStatus::Verified;
+        for (want, got) in outcomes {
+            match got {
+                Some(got) if got != want => status = GpioConfigStatus::Mismatch,
+                None if status == GpioConfigStatus::Verified => {
+                    status = GpioConfigStatus::Unverified
+                }
+                _ => {}
+            }
+        }
+        if requested == GpioMode::default() {
+            status = GpioConfigStatus::Unverified;
+        }
+
+        Self {
+            requested,
+            actual,
+            status,
+            response,
+        }
+    }
+
+    /// Pin name, e.g. `A5`
+    pub fn pin_name(&self) -> String {
+        format!("{}{}", self.actual.port, self.actual.pin)
+    }
+
+    /// One line of requested-vs-actual settings
+    pub fn format_human(&self) -> String {
+        let show = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+        let icon = match self.status {
+            GpioConfigStatus::Verified => "✅",
+            GpioConfigStatus::Mismatch => "❌",
+            GpioConfigStatus::Unverified => "❔",
+        };
+        format!(
+            "{} {}: requested {} pull {}, actual {} pull {}",
+            icon,
+            self.pin_name(),
+            show(&self.requested.direction),
+            show(&self.requested.pull),
+            show(&self.actual.direction),
+            show(&self.actual.pull)
+        )
+    }
+}
+
+/// Result of `gpio config` over one or more pins
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GpioConfigReport {
+    pub mode: String,
+    /// No pin reads back a different setting than requested
+    pub success: bool,
+    pub pins: Vec<GpioConfigResult>,
+}
+
+impl GpioConfigReport {
+    pub fn new(mode: &str, pins: Vec<GpioConfigResult>) -> Self {
+        Self {
+            mode: mode.to_string(),
+            success: pins
+                .iter()
+                .all(|pin| pin.status != GpioConfigStatus::Mismatch),
+            pins,
+        }
+    }
+
+    /// Error naming every pin that did not take the mode
+    pub fn mismatch_error(&self) -> Option<PowerCliError> {
+        let failed: Vec<String> = self
+            .pins
+            .iter()
+            .filter(|pin| pin.status == GpioConfigStatus::Mismatch)
+            .map(GpioConfigResult::pin_name)
+            .collect();
+        (!failed.is_empty()).then(|| PowerCliError::GpioError {
+            message: format!(
+                "GPIO {} did not take mode '{}'",
+                failed.join(", "),
+                self.mode
+            ),
+        })
+    }
+}
+
+/// One step of a GPIO script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GpioOp {
+    /// Drive a pin low (0) or high (1), written `a0=1`
+    Set { pin: GpioPin, value: u8 },
+    /// Wait before the next step, written `5ms`, `500us` or `1s`
+    Delay(Duration),
+}
+
+impl FromStr for GpioOp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Some((pin, value)) = s.split_once('=') {
+            let pin: GpioPin = pin.parse()?;
+            return match value.trim() {
+                "0" => Ok(Self::Set { pin, value: 0 }),
+                "1" => Ok(Self::Set { pin, value: 1 }),
+                value => Err(format!("'{}' is not a pin value (0 or 1)", value)),
+            };
+        }
+
+        let split = s
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(|| format!("'{}' needs a unit (us, ms or s)", s))?;
+        let (count, unit) = s.split_at(split);
+        let count: u64 = count.parse().map_err(|_| {
+            format!(
+                "'{}' is neither a pin setting like a0=1 nor a delay like 5ms",
+                s
+            )
+        })?;
+        let delay = match unit {
+            "us" => Duration::from_micros(count),
+            "ms" => Duration::from_millis(count),
+            "s" => Duration::from_secs(count),
+            _ => return Err(format!("'{}' has an unknown unit (use us, ms or s)", s)),
+        };
+        if delay > MAX_SCRIPT_DELAY {
+            return Err(format!(
+                "'{}' is longer than the {} s limit",
+                s,
+                MAX_SCRIPT_DELAY.as_secs()
+            ));
+        }
+        Ok(Self::Delay(delay))
+    }
+}
+
+impl fmt::Display for GpioOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Set { pin, value } => write!(f, "{}={}", pin, value),
+            Self::Delay(delay) if delay.subsec_micros() % 1000 == 0 => {
+                write!(f, "{}ms", delay.as_millis())
+            }
+            Self::Delay(delay) => write!(f, "{}us", delay.as_micros()),
+        }
+    }
+}
+
+/// Comma-separated GPIO steps, e.g. `a0=1,5ms,a1=1,2ms,b3=0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GpioScript {
+    pub ops: Vec<GpioOp>,
+}
+
+impl FromStr for GpioScript {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ops = s
+            .split(',')
+            .enumerate()
+            .map(|(i, op)| match op.trim() {
+                "" => Err(format!("step {} is empty", i + 1)),
+                op => op.parse().map_err(|e| format!("step {}: {}", i + 1, e)),
+            })
+            .collect::<Result<Vec<GpioOp>, String>>()?;
+        if !ops.iter().any(|op| matches!(op, GpioOp::Set { .. })) {
+            return Err("the script sets no pin".to_string());
+        }
+        Ok(Self { ops })
+    }
+}
+
+impl fmt::Display for GpioScript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ops: Vec<String> = self.ops.iter().map(GpioOp::to_string).collect();
+        write!(f, "{}", ops.join(","))
+    }
+}
+
+impl GpioScript {
+    /// Arguments of `gpio batch`: the steps separated by spaces
+    pub fn batch_args(&self) -> String {
+        let ops: Vec<String> = self.ops.iter().map(GpioOp::to_string).collect();
+        ops.join(" ")
+    }
+
+    /// Pin settings, each with the total delay requested before it since
+    /// the previous setting
+    pub fn steps(&self) -> Vec<(GpioPin, u8, Duration)> {
+        let mut steps = Vec::new();
+        let mut waited = Duration::ZERO;
+        for op in &self.ops {
+            match op {
+                GpioOp::Delay(delay) => waited += *delay,
+                GpioOp::Set { pin, value } => {
+                    steps.push((pin.clone(), *value, waited));
+                    waited = Duration::ZERO;
+                }
+            }
+        }
+        steps
+    }
+}
+
+/// Where a GPIO script ran
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GpioScriptMode {
+    /// One `gpio batch` transaction timed by the firmware
+    Firmware,
+    /// One `gpio set` per pin, delays and timing on the host
+    Host,
+}
+
+/// Outcome of one pin setting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GpioOpStatus {
+    Ok,
+    Failed,
+    /// Not attempted after an earlier step failed
+    Skipped,
+    /// The batch reply did not mention the step
+    Unconfirmed,
+}
+
+/// One pin setting of a script and when it took effect
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GpioOpResult {
+    /// The step, e.g. `A0=1`
+    pub op: String,
+    pub status: GpioOpStatus,
+    /// Time since the script started: reported by the firmware for a batch,
+    /// when the acknowledgement arrived for host timing
+    pub at_us: Option<u64>,
+    /// Time since the previous pin setting
+    pub gap_us: Option<u64>,
+    /// Delay the script asked for before this step
+    pub requested_gap_us: u64,
+    /// Firmware error for a failed step
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of `gpio script`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GpioScriptReport {
+    pub script: String,
+    pub mode: GpioScriptMode,
+    /// Every step took effect
+    pub success: bool,
+    pub ops: Vec<GpioOpResult>,
+}
+
+/// Whole reply line of the step in `caps`, so a failure keeps the
+/// firmware's reason
+fn reply_line(response: &str, caps: &regex::Captures) -> String {
+    let pin = caps.get(1).map_or(0, |m| m.start());
+    let start = response[..pin].rfind('\n').map_or(0, |i| i + 1);
+    let end = response[pin..]
+        .find('\n')
+        .map_or(response.len(), |i| pin + i);
+    response[start..end].trim().to_string()
+}
+
+impl GpioScriptReport {
+    /// Report from per-step outcomes; `gap_us` is filled in from `at_us`
+    pub fn new(script: &GpioScript, mode: GpioScriptMode, mut ops: Vec<GpioOpResult>) -> Self {
+        let mut previous = None;
+        for op in &mut ops {
+            op.gap_us = match (previous, op.at_us) {
+                (Some(previous), Some(at)) => Some(at.saturating_sub(previous)),
+                _ => None,
+            };
+            if op.at_us.is_some() {
+                previous = op.at_us;
+            }
+        }
+        Self {
+            script: script.to_string(),
+            mode,
+            success: ops.iter().all(|op| op.status == GpioOpStatus::Ok),
+            ops,
+        }
+    }
+
+    /// Report from the reply to `gpio batch`
+    ///
+    /// Steps the reply does not mention are unconfirmed.
+    pub fn from_batch(script: &GpioScript, response: &str) -> Self {
+        let mut replies = patterns::GPIO_BATCH_OP.captures_iter(response);
+        let ops = script
+            .steps()
+            .into_iter()
+            .map(|(pin, value, requested)| {
+                let reply = replies.by_ref().find(|caps| {
+                    caps[1].eq_ignore_ascii_case(&pin.to_string()) && caps[2] == value.to_string()
+                });
+                let (status, at_us, error) = match reply {
+                    Some(caps) if caps[3].eq_ignore_ascii_case("OK") => (
+                        GpioOpStatus::Ok,
+                        caps.get(4).and_then(|m| m.as_str().parse().ok()),
+                        None,
+                    ),
+                    Some(caps) => (
+                        GpioOpStatus::Failed,
+                        None,
+                        Some(reply_line(response, &caps)),
+                    ),
+                    None => (GpioOpStatus::Unconfirmed, None, None),
+                };
+                GpioOpResult {
+                    op: format!("{}={}", pin, value),
+                    status,
+                    at_us,
+                    gap_us: None,
+                    requested_gap_us: requested.as_micros() as u64,
+                    error,
+                }
+            })
+            .collect();
+        Self::new(script, GpioScriptMode::Firmware, ops)
+    }
+
+    /// Error naming every step that did not take effect
+    pub fn failure_error(&self) -> Option<PowerCliError> {
+        let failed: Vec<&str> = self
+            .ops
+            .iter()
+            .filter(|op| op.status != GpioOpStatus::Ok)
+            .map(|op| op.op.as_str())
+            .collect();
+        (!failed.is_empty()).then(|| PowerCliError::GpioError {
+            message: format!("GPIO script steps not confirmed: {}", failed.join(", ")),
+        })
+    }
+}
@@ -0,0 +1,171 @@
+/*
+ * E-ink Power CLI - Shared Controller Handle
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Cloneable handle to a [`PowerController`] for concurrent tasks
+//!
+//! [`PowerController::into_handle`] moves the controller onto a task of its
+//! own. Every [`PowerHandle`] clone queues requests for that task, which runs
+//! them one at a time over the serial port and sends each result back to the
+//! task that asked. The queue is bounded, so requesters wait once it is full.
+//! When the last handle is dropped the task closes the connection and ends.
+//!
+//! ```rust,no_run
+//! use eink_power_cli::power::PowerController;
+//! use eink_power_cli::serial::Connection;
+//!
+//! # async fn example() -> eink_power_cli::error::Result<()> {
+//! let controller = PowerController::new(Connection::new("/dev/ttyLP2", 115200, true)?);
+//! let handle = controller.into_handle();
+//! let battery = handle.clone();
+//! let reading = tokio::spawn(async move { battery.battery_read().await });
+//! handle.pm_command("stats").await?;
+//! reading.await.unwrap()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{PowerCliError, Result};
+use crate::json::MeasurementJson;
+use crate::power::control::{PowerController, PowerState};
+use crate::power::rails::Rail;
+use log::debug;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Requests that can wait for the controller task before senders block
+pub const DEFAULT_QUEUE_LEN: usize = 32;
+
+/// Time a request may take, waiting in the queue included
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Future of an operation borrowing the controller
+pub type Operation<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Queued request; sends its own result back to the requester
+type Job = Box<
+    dyn for<'a> FnOnce(&'a mut PowerController) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>
+        + Send,
+>;
+
+/// Cheap, cloneable access to a controller running on its own task
+#[derive(Clone)]
+pub struct PowerHandle {
+    jobs: mpsc::Sender<Job>,
+    timeout: Duration,
+}
+
+impl PowerController {
+    /// Move the controller onto its own task and return a handle to it
+    ///
+    /// Must be called within a Tokio runtime. Use [`PowerHandle::spawn`] to
+    /// wait for the connection to close before the runtime shuts down.
+    #[allow(dead_code)] // Library API for async applications
+    pub fn into_handle(self) -> PowerHandle {
+        PowerHandle::spawn(self, DEFAULT_QUEUE_LEN).0
+    }
+}
+
+#[allow(dead_code)] // Library API for async applications
+impl PowerHandle {
+    /// Run `controller` on a new task taking up to `queue_len` waiting requests
+    ///
+    /// The task ends once every handle is dropped and the connection is
+    /// closed; await the returned [`JoinHandle`] to know it has.
+    pub fn spawn(controller: PowerController, queue_len: usize) -> (Self, JoinHandle<()>) {
+        let (jobs, queue) = mpsc::channel(queue_len.max(1));
+        let task = tokio::spawn(serve(controller, queue));
+        let handle = Self {
+            jobs,
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+        };
+        (handle, task)
+    }
+
+    /// This handle with a different per-request timeout
+    ///
+    /// A request that times out while running still finishes on the
+    /// controller task before the next one starts; only its result is lost.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Run `operation` on the controller once the requests before it are done
+    ///
+    /// e.g. `handle.call(|controller| Box::pin(controller.wake_sources()))`
+    pub async fn call<T, F>(&self, operation: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: for<'a> FnOnce(&'a mut PowerController) -> Operation<'a, T> + Send + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        let job: Job = Box::new(move |controller| {
+            Box::pin(async move {
+                // Skipped when the requester timed out while this was queued
+                if !reply.is_closed() {
+                    let _ = reply.send(operation(controller).await);
+                }
+            })
+        });
+
+        let request = async {
+            self.jobs.send(job).await.map_err(|_| stopped())?;
+            result.await.map_err(|_| stopped())?
+        };
+        tokio::time::timeout(self.timeout, request)
+            .await
+            .map_err(|_| PowerCliError::timeout(self.timeout, &[]))?
+    }
+
+    /// Ping the controller
+    pub async fn ping(&self) -> Result<String> {
+        self.call(|controller| Box::pin(controller.ping())).await
+    }
+
+    /// Battery read (maps to ltc2959 read)
+    pub async fn battery_read(&self) -> Result<String> {
+        self.call(|controller| Box::pin(controller.battery_read()))
+            .await
+    }
+
+    /// Parsed battery measurement
+    pub async fn measure(&self) -> Result<MeasurementJson> {
+        self.call(|controller| Box::pin(controller.measure())).await
+    }
+
+    /// Run a `pm` command, e.g. `stats`
+    pub async fn pm_command(&self, command: &str) -> Result<String> {
+        let command = command.to_string();
+        self.call(move |controller| Box::pin(async move { controller.pm_command(&command).await }))
+            .await
+    }
+
+    /// Switch `rail` or read its state
+    pub async fn control_rail(&self, rail: Rail, state: PowerState) -> Result<String> {
+        self.call(move |controller| Box::pin(controller.control_rail(rail, state)))
+            .await
+    }
+}
+
+/// Run queued requests in order until every handle is gone
+async fn serve(mut controller: PowerController, mut jobs: mpsc::Receiver<Job>) {
+    while let Some(job) = jobs.recv().await {
+        job(&mut controller).await;
+    }
+    debug!("Last controller handle dropped, closing the connection");
+    controller.close().await;
+}
+
+/// Error for a request whose controller task is gone
+fn stopped() -> PowerCliError {
+    PowerCliError::Io(std::io::Error::new(
+        std::io::ErrorKind::BrokenPipe,
+        "controller task has stopped",
+    ))
+}
@@ -0,0 +1,207 @@
+/*
+ * E-ink Power CLI - Device Identity
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Unit identity stored in the NTA5332 EEPROM at production
+//!
+//! The identity occupies the last [`IDENTITY_BLOCKS`] 4-byte blocks of the
+//! NFC EEPROM user memory:
+//!
+//! | Offset | Size | Field                                   |
+//! |--------|------|-----------------------------------------|
+//! | 0      | 2    | Magic `ID`                              |
+//! | 2      | 1    | Layout version ([`IDENTITY_VERSION`])   |
+//! | 3      | 1    | Hardware revision                       |
+//! | 4      | 12   | Serial number, ASCII, NUL-padded        |
+//! | 16     | 3    | Manufacture date: year - 2000, month, day |
+//! | 19     | 1    | CRC-8 (poly 0x07) over bytes 0-18       |
+//!
+//! Erased EEPROM reads as all `0xFF` (or all zero on some parts); such a
+//! block, or one that fails any check, is reported as no identity.
+
+use crate::error::{PowerCliError, Result};
+use chrono::{Datelike, NaiveDate};
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+/// First EEPROM block of the identity
+pub const IDENTITY_FIRST_BLOCK: u16 = 0x1FB;
+
+/// Number of EEPROM blocks the identity spans
+pub const IDENTITY_BLOCKS: u16 = 5;
+
+/// Size of one NTA5332 EEPROM block
+pub const EEPROM_BLOCK_SIZE: usize = 4;
+
+/// Size of the encoded identity
+pub const IDENTITY_LEN: usize = IDENTITY_BLOCKS as usize * EEPROM_BLOCK_SIZE;
+
+/// Layout version written by this build
+pub const IDENTITY_VERSION: u8 = 1;
+
+/// Longest serial number the layout holds
+pub const MAX_SERIAL_LEN: usize = 12;
+
+const MAGIC: [u8; 2] = *b"ID";
+
+/// Serial number, hardware revision and manufacture date of one unit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceIdentity {
+    pub serial: String,
+    pub hw_rev: u8,
+    pub manufacture_date: NaiveDate,
+}
+
+impl DeviceIdentity {
+    /// Validate fields for writing
+    ///
+    /// The serial must be 1 to [`MAX_SERIAL_LEN`] ASCII letters, digits or
+    /// `-`, and the date must fall in 2000-2255.
+    pub fn new(serial: &str, hw_rev: u8, manufacture_date: NaiveDate) -> Result<Self> {
+        let serial_ok = !serial.is_empty()
+            && serial.len() <= MAX_SERIAL_LEN
+            && serial
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !serial_ok {
+            return Err(PowerCliError::InvalidArguments {
+                message: format!(
+                    "serial '{}' must be 1-{} ASCII letters, digits or '-'",
+                    serial, MAX_SERIAL_LEN
+                ),
+            });
+        }
+        if !(2000..=2255).contains(&manufacture_date.year()) {
+            return Err(PowerCliError::InvalidArguments {
+                message: format!("manufacture date {} must be in 2000-2255", manufacture_date),
+            });
+        }
+        Ok(Self {
+            serial: serial.to_string(),
+            hw_rev,
+            manufacture_date,
+        })
+    }
+
+    /// Encode in the EEPROM layout
+    pub fn encode(&self) -> [u8; IDENTITY_LEN] {
+        let mut bytes = [0u8; IDENTITY_LEN];
+        bytes[..2].copy_from_slice(&MAGIC);
+        bytes[2] = IDENTITY_VERSION;
+        bytes[3] = self.hw_rev;
+        let serial = self.serial.as_bytes();
+        let len = serial.len().min(MAX_SERIAL_LEN);
+        bytes[4..4 + len].copy_from_slice(&serial[..len]);
+        bytes[16] = (self.manufacture_date.year() - 2000) as u8;
+        bytes[17] = self.manufacture_date.month() as u8;
+        bytes[18] = self.manufacture_date.day() as u8;
+        bytes[19] = crc8(&bytes[..19]);
+        bytes
+    }
+
+    /// Decode the EEPROM layout; `None` if unprogrammed or invalid
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..IDENTITY_LEN)?;
+        if bytes.iter().all(|&b| b == 0xFF) || bytes.iter().all(|&b| b == 0) {
+            return None;
+        }
+        if bytes[..2] != MAGIC {
+            warn!("NFC identity block has no identity magic; treating as unprogrammed");
+            return None;
+        }
+        if bytes[2] != IDENTITY_VERSION {
+            warn!("Unknown NFC identity layout version {}", bytes[2]);
+            return None;
+        }
+        if crc8(&bytes[..19]) != bytes[19] {
+            warn!("NFC identity block fails its CRC; ignoring it");
+            return None;
+        }
+
+        let serial_bytes = &bytes[4..16];
+        let end = serial_bytes
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(MAX_SERIAL_LEN);
+        let serial = std::str::from_utf8(&serial_bytes[..end]).ok()?;
+        let date =
+            NaiveDate::from_ymd_opt(2000 + bytes[16] as i32, bytes[17] as u32, bytes[18] as u32)?;
+        Self::new(serial, bytes[3], date).ok()
+    }
+
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        format!(
+            "Serial: {}\nHardware revision: {}\nManufactured: {}",
+            self.serial, self.hw_rev, self.manufacture_date
+        )
+    }
+}
+
+/// CRC-8 with polynomial 0x07 and zero initial value
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |crc, &byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Bytes of an `nfc eeprom read` dump
+///
+/// Each line is `<block>: <hex bytes>`; only two-digit hex tokens after
+/// the colon are taken, so headers and status lines are skipped.
+pub fn parse_eeprom_dump(response: &str) -> Vec<u8> {
+    response
+        .lines()
+        .filter_map(|line| line.split_once(':').map(|(_, data)| data))
+        .flat_map(str::split_whitespace)
+        .filter(|token| token.len() == 2)
+        .filter_map(|token| u8::from_str_radix(token, 16).ok())
+        .collect()
+}
+
+/// `nfc eeprom write` argument for one block
+pub fn format_block(block: &[u8]) -> String {
+    block.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// Whether `command` reads or writes EEPROM blocks holding the identity
+///
+/// Takes `[nfc] eeprom read|write <block> ...`; a block that cannot be read
+/// as a number counts as touching the identity. A read covers the given
+/// block count, a write one block.
+pub fn touches_identity(command: &str) -> bool {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    let words = match words.as_slice() {
+        ["nfc", rest @ ..] => rest,
+        words => words,
+    };
+    let (block, count) = match words {
+        ["eeprom", "read", block] | ["eeprom", "write", block, ..] => (*block, "1"),
+        ["eeprom", "read", block, count, ..] => (*block, *count),
+        _ => return false,
+    };
+    match (parse_number(block), parse_number(count)) {
+        (Some(first), Some(count)) => {
+            let identity =
+                u32::from(IDENTITY_FIRST_BLOCK)..u32::from(IDENTITY_FIRST_BLOCK + IDENTITY_BLOCKS);
+            first < identity.end && first.saturating_add(count) > identity.start
+        }
+        _ => true,
+    }
+}
+
+/// Decimal or `0x` hexadecimal block number or count
+fn parse_number(text: &str) -> Option<u32> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
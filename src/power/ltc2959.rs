@@ -0,0 +1,263 @@
+/*
+ * E-ink Power CLI - LTC2959 Continuous Reads
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `ltc2959 read --continuous`: fast current sampling for bench profiling
+//!
+//! In its default smart-sleep mode the LTC2959 converts only now and then,
+//! so readings taken faster than that repeat themselves. A continuous read
+//! switches the ADC to continuous V/I conversion, streams readings at the
+//! requested cadence and switches it back afterwards. Left in continuous
+//! mode the gauge alone draws more than the whole board asleep, so the
+//! previous mode is restored whichever way the read ends: on completion,
+//! on a failed reading and on Ctrl-C. An [`AdcModeGuard`] that is dropped
+//! without restoring logs the command that puts it right.
+
+use crate::error::{PowerCliError, Result};
+use crate::json::{
+    BatteryJson, BatteryWatchSampleJson, BatteryWatchSummaryJson, ResponseParser, SampleStatsJson,
+};
+use crate::power::battery::BatteryWatchStats;
+use crate::power::control::PowerController;
+use crate::util::CancellationToken;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::time::{sleep_until, Instant};
+
+/// LTC2959 ADC mode, as set with `ltc2959 adc_mode <0-6>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AdcMode {
+    Sleep,
+    SmartSleep,
+    ContinuousV,
+    ContinuousI,
+    ContinuousVI,
+    SingleV,
+    SingleI,
+}
+
+/// Mode a continuous read switches to
+pub const CONTINUOUS_MODE: AdcMode = AdcMode::ContinuousVI;
+
+impl AdcMode {
+    pub const ALL: [AdcMode; 7] = [
+        AdcMode::Sleep,
+        AdcMode::SmartSleep,
+        AdcMode::ContinuousV,
+        AdcMode::ContinuousI,
+        AdcMode::ContinuousVI,
+        AdcMode::SingleV,
+        AdcMode::SingleI,
+    ];
+
+    /// Argument of `ltc2959 adc_mode`
+    pub fn value(self) -> u8 {
+        self as u8
+    }
+
+    /// Name shown by `ltc2959 status`
+    pub fn name(self) -> &'static str {
+        match self {
+            AdcMode::Sleep => "Sleep",
+            AdcMode::SmartSleep => "Smart Sleep",
+            AdcMode::ContinuousV => "Continuous V",
+            AdcMode::ContinuousI => "Continuous I",
+            AdcMode::ContinuousVI => "Continuous V/I",
+            AdcMode::SingleV => "Single V",
+            AdcMode::SingleI => "Single I",
+        }
+    }
+
+    /// Mode shown as `name` by `ltc2959 status`
+    ///
+    /// Case and a trailing note in parentheses, e.g. `(forced conversion)`,
+    /// are ignored.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.split('(').next().unwrap_or_default().trim();
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Set the ADC mode
+pub async fn set_adc_mode(controller: &mut PowerController, mode: AdcMode) -> Result<String> {
+    debug!("Setting LTC2959 ADC mode to {}", mode.name());
+    controller
+        .control_ltc2959(&format!("adc_mode {}", mode.value()))
+        .await
+}
+
+/// ADC mode from `ltc2959 status`
+pub async fn adc_mode(controller: &mut PowerController) -> Result<AdcMode> {
+    let response = controller.battery_status().await?;
+    let name = ResponseParser::parse_ltc2959_status(&response)
+        .adc_mode
+        .ok_or(PowerCliError::InvalidResponse { response })?;
+    AdcMode::from_name(&name).ok_or_else(|| PowerCliError::BatteryError {
+        message: format!(
+            "unknown LTC2959 ADC mode '{}'; it could not be restored after the read",
+            name
+        ),
+    })
+}
+
+/// ADC mode to put back before the LTC2959 is left alone
+///
+/// Call [`AdcModeGuard::restore`] on every path out. A guard dropped
+/// without a successful restore, e.g. because the restore itself failed,
+/// warns that the gauge may be left converting continuously.
+#[derive(Debug)]
+pub struct AdcModeGuard {
+    previous: AdcMode,
+    restored: bool,
+}
+
+impl AdcModeGuard {
+    /// Remember `previous` to restore
+    pub fn new(previous: AdcMode) -> Self {
+        Self {
+            previous,
+            restored: false,
+        }
+    }
+
+    /// Switch the ADC back to the previous mode
+    pub async fn restore(mut self, controller: &mut PowerController) -> Result<AdcMode> {
+        set_adc_mode(controller, self.previous).await?;
+        self.restored = true;
+        Ok(self.previous)
+    }
+}
+
+impl Drop for AdcModeGuard {
+    fn drop(&mut self) {
+        if !self.restored {
+            warn!(
+                "LTC2959 ADC mode was not restored to {}; restore it with `eink-power-cli ltc2959 adc-mode {}`",
+                self.previous.name(),
+                self.previous.value()
+            );
+        }
+    }
+}
+
+/// Result of `ltc2959 read --continuous`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContinuousReadJson {
+    /// Interval asked for between readings
+    pub interval_ms: u64,
+    /// Mode the ADC was in before, and is back in
+    pub restored_adc_mode: AdcMode,
+    /// Stopped with Ctrl-C before the duration was up
+    pub cancelled: bool,
+    /// Voltage, current and charge over the readings
+    #[serde(flatten)]
+    pub summary: BatteryWatchSummaryJson,
+    /// Time actually observed between consecutive readings
+    pub intervals_ms: Option<SampleStatsJson>,
+    /// Largest difference between an observed and the requested interval
+    pub jitter_ms: Option<f64>,
+}
+
+/// A continuous read: how often and for how long
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContinuousRead {
+    pub interval: Duration,
+    /// `None` reads until cancelled
+    pub duration: Option<Duration>,
+}
+
+impl ContinuousRead {
+    /// Switch to continuous mode, pass each reading to `on_sample` until
+    /// the duration is up or `cancel` fires, then restore the ADC mode
+    ///
+    /// The previous mode is restored even when a reading or `on_sample`
+    /// fails; that error is returned once it has been.
+    pub async fn run<F>(
+        &self,
+        controller: &mut PowerController,
+        cancel: &CancellationToken,
+        on_sample: F,
+    ) -> Result<ContinuousReadJson>
+    where
+        F: FnMut(BatteryWatchSampleJson) -> Result<()>,
+    {
+        let guard = AdcModeGuard::new(adc_mode(controller).await?);
+        let streamed = match set_adc_mode(controller, CONTINUOUS_MODE).await {
+            Ok(_) => self.stream(controller, cancel, on_sample).await,
+            Err(e) => Err(e),
+        };
+        let restored = guard.restore(controller).await;
+        let (streamed, restored_adc_mode) = (streamed?, restored?);
+
+        let interval_ms = self.interval.as_secs_f64() * 1000.0;
+        let jitter_ms = streamed
+            .intervals_ms
+            .iter()
+            .map(|gap| (gap - interval_ms).abs())
+            .max_by(f64::total_cmp);
+        Ok(ContinuousReadJson {
+            interval_ms: self.interval.as_millis() as u64,
+            restored_adc_mode,
+            cancelled: streamed.cancelled,
+            summary: streamed.stats.summary(),
+            intervals_ms: SampleStatsJson::of(streamed.intervals_ms),
+            jitter_ms,
+        })
+    }
+
+    /// Read every interval until the duration is up or `cancel` fires
+    async fn stream<F>(
+        &self,
+        controller: &mut PowerController,
+        cancel: &CancellationToken,
+        mut on_sample: F,
+    ) -> Result<Streamed>
+    where
+        F: FnMut(BatteryWatchSampleJson) -> Result<()>,
+    {
+        let deadline = self.duration.map(|duration| Instant::now() + duration);
+        let mut stats = BatteryWatchStats::new();
+        let mut intervals_ms = Vec::new();
+        let mut last_read: Option<Instant> = None;
+        let mut monitor = controller.battery_monitor();
+        let mut readings = monitor.watch(self.interval);
+        let cancelled = loop {
+            let until_deadline = async {
+                match deadline {
+                    Some(deadline) => sleep_until(deadline).await,
+                    None => std::future::pending().await,
+                }
+            };
+            let battery: BatteryJson = tokio::select! {
+                reading = readings.next() => reading?,
+                _ = until_deadline => break false,
+                _ = cancel.cancelled() => break true,
+            };
+            let now = Instant::now();
+            if let Some(last) = last_read.replace(now) {
+                intervals_ms.push((now - last).as_secs_f64() * 1000.0);
+            }
+            stats.record(&battery);
+            on_sample(BatteryWatchSampleJson::new(battery, None))?;
+        };
+        Ok(Streamed {
+            cancelled,
+            stats,
+            intervals_ms,
+        })
+    }
+}
+
+/// What [`ContinuousRead::stream`] saw
+struct Streamed {
+    cancelled: bool,
+    stats: BatteryWatchStats,
+    /// Time between consecutive readings
+    intervals_ms: Vec<f64>,
+}
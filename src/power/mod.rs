@@ -7,9 +7,25 @@
 //! Power management module for battery monitoring and power control
 
 pub mod battery;
+pub mod charger;
 pub mod control;
+pub mod coulomb;
+pub mod factory_reset;
+pub mod gpio;
+pub mod handle;
+pub mod identity;
+pub mod ltc2959;
+pub mod passthrough;
+pub mod rails;
+pub mod reboot;
+pub mod rtc;
+pub mod sleep;
+pub mod stats;
+pub mod timeref;
+pub mod wake;
 
 #[allow(unused_imports)]
 pub use battery::BatteryMonitor;
 #[allow(unused_imports)]
 pub use control::PowerController;
+pub use rails::Rail;
@@ -0,0 +1,659 @@
+/*
+ * E-ink Power CLI - NFC Pass-Through
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Data transfers with a phone through the NTA5332 SRAM mailbox
+//!
+//! In pass-through mode the 64-byte SRAM is a mailbox between the I2C side
+//! (the PMU, on our behalf) and a phone in the RF field: one side fills it,
+//! the chip raises [`SRAM_DATA_READY`] and the other side empties it. The
+//! firmware exposes the mailbox as `nfc sram read|write` and the chip's
+//! session registers as `nfc session read|write`.
+//!
+//! A transfer moves a blob as a series of mailboxes:
+//!
+//! | Byte | Data mailbox      | End mailbox                             |
+//! |------|-------------------|-----------------------------------------|
+//! | 0    | Sequence number   | Sequence number                         |
+//! | 1    | Payload length    | `0xFF`                                  |
+//! | 2..  | Payload, 0-padded | Total length, CRC-32 (both u32, big-endian) |
+//!
+//! The receiver checks the end mailbox against what it put together. A
+//! phone receiving a blob answers with an acknowledgement mailbox (`0xFE`
+//! in byte 1) carrying the length and CRC-32 it got, so either direction
+//! ends with a checksum checked on the host.
+//!
+//! When the phone leaves the field the chip drops pass-through mode and
+//! the mailbox contents. The transfer waits for the field to return, sets
+//! the pass-through flags again and repeats the current mailbox, up to
+//! [`MAX_MAILBOX_RETRIES`] times per mailbox.
+
+use crate::error::{PowerCliError, Result};
+use crate::power::identity;
+use crate::serial::Protocol;
+use clap::ValueEnum;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::borrow::BorrowMut;
+use std::time::{Duration, Instant};
+
+/// Size of the SRAM mailbox
+pub const MAILBOX_SIZE: usize = 64;
+
+/// Payload of one data mailbox, after the sequence and length bytes
+pub const MAILBOX_PAYLOAD: usize = MAILBOX_SIZE - 2;
+
+/// Largest blob a transfer carries: 255 data mailboxes
+pub const MAX_TRANSFER_LEN: usize = MAILBOX_PAYLOAD * 255;
+
+/// Times one mailbox is repeated after the field dropped before giving up
+pub const MAX_MAILBOX_RETRIES: u32 = 3;
+
+/// Default wait for the field and for the phone to answer each mailbox
+pub const DEFAULT_TRANSFER_TIMEOUT_S: u64 = 30;
+
+/// Gap between reads of the status register
+pub const STATUS_POLL: Duration = Duration::from_millis(20);
+
+/// Session register with the field and mailbox flags in byte 0
+pub const STATUS_REG: u8 = 0xA0;
+
+/// Session register with the pass-through configuration in byte 1
+pub const CONFIG_REG: u8 = 0xA1;
+
+/// [`STATUS_REG`] byte 0: an RF field is present
+pub const NFC_FIELD_OK: u8 = 0x01;
+
+/// [`STATUS_REG`] byte 0: the mailbox holds data for the other side
+pub const SRAM_DATA_READY: u8 = 0x20;
+
+/// [`CONFIG_REG`] byte 1: SRAM enabled as the mailbox
+pub const SRAM_ENABLE: u8 = 0x02;
+
+/// [`CONFIG_REG`] byte 1: arbiter in pass-through mode
+pub const PASS_THROUGH: u8 = 0x0C;
+
+/// [`CONFIG_REG`] byte 1: set for RF to I2C, clear for I2C to RF
+pub const PT_TRANSFER_DIR: u8 = 0x01;
+
+/// [`CONFIG_REG`] byte 1 bits a transfer owns
+pub const PASS_THROUGH_MASK: u8 = SRAM_ENABLE | PASS_THROUGH | PT_TRANSFER_DIR;
+
+const END_MARKER: u8 = 0xFF;
+const ACK_MARKER: u8 = 0xFE;
+
+/// Which way a transfer moves data
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransferDirection {
+    /// Send the file to the phone
+    ToPhone,
+    /// Receive from the phone into the file
+    FromPhone,
+}
+
+impl TransferDirection {
+    /// [`CONFIG_REG`] byte 1 for pass-through in this direction
+    pub fn config(self) -> u8 {
+        match self {
+            TransferDirection::ToPhone => SRAM_ENABLE | PASS_THROUGH,
+            TransferDirection::FromPhone => SRAM_ENABLE | PASS_THROUGH | PT_TRANSFER_DIR,
+        }
+    }
+}
+
+/// One mailbox of a transfer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mailbox {
+    Data {
+        seq: u8,
+        payload: Vec<u8>,
+    },
+    /// Last mailbox from the sender
+    End {
+        seq: u8,
+        len: u32,
+        crc: u32,
+    },
+    /// Answer of a phone to [`Mailbox::End`]
+    Ack {
+        seq: u8,
+        len: u32,
+        crc: u32,
+    },
+}
+
+impl Mailbox {
+    pub fn seq(&self) -> u8 {
+        match self {
+            Mailbox::Data { seq, .. } | Mailbox::End { seq, .. } | Mailbox::Ack { seq, .. } => *seq,
+        }
+    }
+
+    /// SRAM contents carrying this mailbox
+    pub fn encode(&self) -> [u8; MAILBOX_SIZE] {
+        let mut bytes = [0u8; MAILBOX_SIZE];
+        bytes[0] = self.seq();
+        match self {
+            Mailbox::Data { payload, .. } => {
+                bytes[1] = payload.len() as u8;
+                bytes[2..2 + payload.len()].copy_from_slice(payload);
+            }
+            Mailbox::End { len, crc, .. } | Mailbox::Ack { len, crc, .. } => {
+                bytes[1] = if matches!(self, Mailbox::End { .. }) {
+                    END_MARKER
+                } else {
+                    ACK_MARKER
+                };
+                bytes[2..6].copy_from_slice(&len.to_be_bytes());
+                bytes[6..10].copy_from_slice(&crc.to_be_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Mailbox carried by SRAM contents
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let invalid = || PowerCliError::NfcError {
+            message: format!("invalid mailbox: {}", identity::format_block(bytes)),
+        };
+        let bytes = bytes.get(..MAILBOX_SIZE).ok_or_else(invalid)?;
+        let word = |at: usize| u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+        let seq = bytes[0];
+        match bytes[1] {
+            END_MARKER => Ok(Mailbox::End {
+                seq,
+                len: word(2),
+                crc: word(6),
+            }),
+            ACK_MARKER => Ok(Mailbox::Ack {
+                seq,
+                len: word(2),
+                crc: word(6),
+            }),
+            len if len as usize <= MAILBOX_PAYLOAD => Ok(Mailbox::Data {
+                seq,
+                payload: bytes[2..2 + len as usize].to_vec(),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Mailboxes sending `data`: the data mailboxes, then the end mailbox
+pub fn mailboxes(data: &[u8]) -> Result<Vec<Mailbox>> {
+    if data.len() > MAX_TRANSFER_LEN {
+        return Err(PowerCliError::InvalidArguments {
+            message: format!(
+                "{} bytes is more than a transfer carries ({} bytes)",
+                data.len(),
+                MAX_TRANSFER_LEN
+            ),
+        });
+    }
+    let mut mailboxes: Vec<Mailbox> = data
+        .chunks(MAILBOX_PAYLOAD)
+        .enumerate()
+        .map(|(seq, payload)| Mailbox::Data {
+            seq: seq as u8,
+            payload: payload.to_vec(),
+        })
+        .collect();
+    mailboxes.push(Mailbox::End {
+        seq: mailboxes.len() as u8,
+        len: data.len() as u32,
+        crc: crc32(data),
+    });
+    Ok(mailboxes)
+}
+
+/// Bytes from hex digits, e.g. `0102A0FF`
+pub fn parse_hex(text: &str) -> Result<Vec<u8>> {
+    let digits = text.trim().trim_start_matches("0x");
+    let invalid = || PowerCliError::InvalidArguments {
+        message: format!("'{}' is not an even number of hex digits", text),
+    };
+    if digits.is_empty() || !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+        return Err(invalid());
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(&digits[at..at + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+/// CRC-32 (IEEE 802.3, as zlib computes it)
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+/// What a mailbox added to a [`Reassembly`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Received {
+    /// The next data mailbox
+    Data,
+    /// The previous mailbox again, sent twice around a field drop
+    Repeated,
+    /// The end mailbox, matching the data put together
+    Complete(Vec<u8>),
+}
+
+/// Receiving side of a transfer: puts the data mailboxes back together in
+/// sequence and checks them against the end mailbox
+#[derive(Debug, Default)]
+pub struct Reassembly {
+    data: Vec<u8>,
+    next_seq: u8,
+}
+
+impl Reassembly {
+    pub fn push(&mut self, mailbox: Mailbox) -> Result<Received> {
+        let seq = mailbox.seq();
+        match mailbox {
+            Mailbox::Data { payload, .. } if seq == self.next_seq => {
+                self.data.extend_from_slice(&payload);
+                self.next_seq = self.next_seq.wrapping_add(1);
+                Ok(Received::Data)
+            }
+            Mailbox::End { len, crc, .. } if seq == self.next_seq => {
+                let got = crc32(&self.data);
+                if len as usize != self.data.len() || crc != got {
+                    return Err(PowerCliError::NfcError {
+                        message: format!(
+                            "checksum mismatch: sender has {} bytes with CRC-32 {:08X}, received {} bytes with {:08X}",
+                            len,
+                            crc,
+                            self.data.len(),
+                            got
+                        ),
+                    });
+                }
+                Ok(Received::Complete(std::mem::take(&mut self.data)))
+            }
+            _ if self.next_seq > 0 && seq == self.next_seq - 1 => Ok(Received::Repeated),
+            mailbox => Err(PowerCliError::NfcError {
+                message: format!(
+                    "mailbox out of sequence: expected {}, got {:?}",
+                    self.next_seq, mailbox
+                ),
+            }),
+        }
+    }
+}
+
+/// Progress of a transfer after each mailbox
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferProgress {
+    /// Mailboxes moved so far
+    pub mailboxes: usize,
+    /// Mailboxes in the whole transfer, known when sending
+    pub total: Option<usize>,
+    /// Payload bytes moved so far
+    pub bytes: usize,
+    pub retries: u32,
+}
+
+/// Outcome of `nfc transfer`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferReport {
+    pub direction: TransferDirection,
+    pub file: String,
+    pub bytes: usize,
+    /// Mailboxes moved, the end mailbox and acknowledgement included
+    pub mailboxes: usize,
+    /// Mailboxes repeated after the field dropped
+    pub retries: u32,
+    /// CRC-32 both sides agreed on
+    pub crc32: String,
+    pub duration_ms: u64,
+}
+
+/// Counters of a transfer in progress
+struct Tally<F> {
+    progress: F,
+    mailboxes: usize,
+    total: Option<usize>,
+    bytes: usize,
+    retries: u32,
+}
+
+impl<F: FnMut(TransferProgress)> Tally<F> {
+    fn moved(&mut self, bytes: usize, retries: u32) {
+        self.mailboxes += 1;
+        self.bytes += bytes;
+        self.retries += retries;
+        (self.progress)(TransferProgress {
+            mailboxes: self.mailboxes,
+            total: self.total,
+            bytes: self.bytes,
+            retries: self.retries,
+        });
+    }
+}
+
+/// SRAM mailbox and pass-through transfers
+///
+/// Owns its protocol when built with [`SramMailbox::with_protocol`] on a
+/// [`Protocol`]; a [`PowerController`](crate::power::control::PowerController)
+/// lends its own through `sram_mailbox()`.
+pub struct SramMailbox<P = Protocol> {
+    protocol: P,
+}
+
+impl<P: BorrowMut<Protocol>> SramMailbox<P> {
+    pub fn with_protocol(protocol: P) -> Self {
+        Self { protocol }
+    }
+
+    async fn nfc(&mut self, command: &str) -> Result<String> {
+        self.protocol
+            .borrow_mut()
+            .execute_nfc_command(command)
+            .await
+    }
+
+    /// Read the whole mailbox
+    pub async fn read(&mut self) -> Result<Vec<u8>> {
+        let response = self.nfc("sram read").await?;
+        let mut bytes = identity::parse_eeprom_dump(&response);
+        if bytes.len() < MAILBOX_SIZE {
+            return Err(PowerCliError::NfcError {
+                message: format!(
+                    "SRAM read returned {} of {} bytes: {}",
+                    bytes.len(),
+                    MAILBOX_SIZE,
+                    response.trim()
+                ),
+            });
+        }
+        bytes.truncate(MAILBOX_SIZE);
+        Ok(bytes)
+    }
+
+    /// Write `data` to the start of the mailbox
+    pub async fn write(&mut self, data: &[u8]) -> Result<String> {
+        if data.is_empty() || data.len() > MAILBOX_SIZE {
+            return Err(PowerCliError::InvalidArguments {
+                message: format!(
+                    "SRAM write takes 1 to {} bytes, not {}",
+                    MAILBOX_SIZE,
+                    data.len()
+                ),
+            });
+        }
+        self.nfc(&format!("sram write {}", identity::format_block(data)))
+            .await
+    }
+
+    /// Byte 0 of the status session register
+    pub async fn status(&mut self) -> Result<u8> {
+        let response = self
+            .nfc(&format!("session read 0x{:02X}", STATUS_REG))
+            .await?;
+        identity::parse_eeprom_dump(&response)
+            .first()
+            .copied()
+            .ok_or_else(|| PowerCliError::NfcError {
+                message: format!("unreadable session register: {}", response.trim()),
+            })
+    }
+
+    /// Set the pass-through bits of the configuration session register
+    async fn configure(&mut self, config: u8) -> Result<()> {
+        debug!("Pass-through configuration 0x{:02X}", config);
+        self.nfc(&format!(
+            "session write 0x{:02X} 1 0x{:02X} 0x{:02X}",
+            CONFIG_REG, PASS_THROUGH_MASK, config
+        ))
+        .await?;
+        Ok(())
+    }
+
+    async fn wait_for_field(&mut self, timeout: Duration) -> Result<()> {
+        let started = Instant::now();
+        while self.status().await? & NFC_FIELD_OK == 0 {
+            if started.elapsed() >= timeout {
+                return Err(PowerCliError::NfcError {
+                    message: format!(
+                        "no RF field within {} s; hold the phone on the antenna",
+                        timeout.as_secs()
+                    ),
+                });
+            }
+            tokio::time::sleep(STATUS_POLL).await;
+        }
+        Ok(())
+    }
+
+    /// Poll the status until the data-ready flag is `ready`
+    ///
+    /// `false` if the field dropped first.
+    async fn wait_for_data_ready(&mut self, ready: bool, timeout: Duration) -> Result<bool> {
+        let started = Instant::now();
+        loop {
+            let status = self.status().await?;
+            if status & NFC_FIELD_OK == 0 {
+                return Ok(false);
+            }
+            if (status & SRAM_DATA_READY != 0) == ready {
+                return Ok(true);
+            }
+            if started.elapsed() >= timeout {
+                return Err(PowerCliError::NfcError {
+                    message: format!(
+                        "the phone did not {} the mailbox within {} s",
+                        if ready { "fill" } else { "empty" },
+                        timeout.as_secs()
+                    ),
+                });
+            }
+            tokio::time::sleep(STATUS_POLL).await;
+        }
+    }
+
+    /// Wait out a field drop during mailbox `index` and set pass-through
+    /// up again, counting the attempt
+    async fn recover(
+        &mut self,
+        direction: TransferDirection,
+        index: usize,
+        attempts: &mut u32,
+        timeout: Duration,
+    ) -> Result<()> {
+        *attempts += 1;
+        if *attempts > MAX_MAILBOX_RETRIES {
+            return Err(PowerCliError::NfcError {
+                message: format!(
+                    "RF field lost {} times during mailbox {}; giving up",
+                    attempts, index
+                ),
+            });
+        }
+        warn!(
+            "RF field lost during mailbox {}; retrying ({}/{})",
+            index, attempts, MAX_MAILBOX_RETRIES
+        );
+        self.wait_for_field(timeout).await?;
+        self.configure(direction.config()).await
+    }
+
+    /// Put `mailbox` in the SRAM and wait for the phone to take it;
+    /// returns the retries it needed
+    async fn send_mailbox(
+        &mut self,
+        mailbox: &Mailbox,
+        index: usize,
+        timeout: Duration,
+    ) -> Result<u32> {
+        let mut attempts = 0;
+        loop {
+            self.write(&mailbox.encode()).await?;
+            if self.wait_for_data_ready(false, timeout).await? {
+                return Ok(attempts);
+            }
+            self.recover(TransferDirection::ToPhone, index, &mut attempts, timeout)
+                .await?;
+        }
+    }
+
+    /// Wait for the phone to fill the SRAM and take the mailbox; returns
+    /// it with the retries it needed
+    async fn receive_mailbox(&mut self, index: usize, timeout: Duration) -> Result<(Mailbox, u32)> {
+        let mut attempts = 0;
+        while !self.wait_for_data_ready(true, timeout).await? {
+            self.recover(TransferDirection::FromPhone, index, &mut attempts, timeout)
+                .await?;
+        }
+        Ok((Mailbox::decode(&self.read().await?)?, attempts))
+    }
+
+    /// Send `data` to the phone and check its acknowledgement
+    pub async fn send(
+        &mut self,
+        data: &[u8],
+        timeout: Duration,
+        progress: impl FnMut(TransferProgress),
+    ) -> Result<TransferReport> {
+        let mailboxes = mailboxes(data)?;
+        let mut tally = Tally {
+            progress,
+            mailboxes: 0,
+            total: Some(mailboxes.len() + 1),
+            bytes: 0,
+            retries: 0,
+        };
+        let started = Instant::now();
+        let result = self.send_all(data, &mailboxes, timeout, &mut tally).await;
+        self.release().await;
+        result?;
+        Ok(TransferReport {
+            direction: TransferDirection::ToPhone,
+            file: String::new(),
+            bytes: data.len(),
+            mailboxes: tally.mailboxes,
+            retries: tally.retries,
+            crc32: format!("{:08X}", crc32(data)),
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
+    }
+
+    async fn send_all<F: FnMut(TransferProgress)>(
+        &mut self,
+        data: &[u8],
+        mailboxes: &[Mailbox],
+        timeout: Duration,
+        tally: &mut Tally<F>,
+    ) -> Result<()> {
+        info!("Waiting for an RF field to send {} bytes", data.len());
+        self.wait_for_field(timeout).await?;
+        self.configure(TransferDirection::ToPhone.config()).await?;
+        for (index, mailbox) in mailboxes.iter().enumerate() {
+            let retries = self.send_mailbox(mailbox, index, timeout).await?;
+            let bytes = match mailbox {
+                Mailbox::Data { payload, .. } => payload.len(),
+                _ => 0,
+            };
+            tally.moved(bytes, retries);
+        }
+
+        // The phone answers the end mailbox in the other direction
+        self.configure(TransferDirection::FromPhone.config())
+            .await?;
+        let (ack, retries) = self.receive_mailbox(mailboxes.len(), timeout).await?;
+        tally.moved(0, retries);
+        let (len, crc) = (data.len() as u32, crc32(data));
+        match ack {
+            Mailbox::Ack {
+                len: got_len,
+                crc: got_crc,
+                ..
+            } if (got_len, got_crc) == (len, crc) => Ok(()),
+            Mailbox::Ack {
+                len: got_len,
+                crc: got_crc,
+                ..
+            } => Err(PowerCliError::NfcError {
+                message: format!(
+                    "checksum mismatch: sent {} bytes with CRC-32 {:08X}, the phone received {} bytes with {:08X}",
+                    len, crc, got_len, got_crc
+                ),
+            }),
+            other => Err(PowerCliError::NfcError {
+                message: format!("expected the phone's acknowledgement, got {:?}", other),
+            }),
+        }
+    }
+
+    /// Receive a blob from the phone, checked against its end mailbox
+    pub async fn receive(
+        &mut self,
+        timeout: Duration,
+        progress: impl FnMut(TransferProgress),
+    ) -> Result<(Vec<u8>, TransferReport)> {
+        let mut tally = Tally {
+            progress,
+            mailboxes: 0,
+            total: None,
+            bytes: 0,
+            retries: 0,
+        };
+        let started = Instant::now();
+        let result = self.receive_all(timeout, &mut tally).await;
+        self.release().await;
+        let data = result?;
+        let report = TransferReport {
+            direction: TransferDirection::FromPhone,
+            file: String::new(),
+            bytes: data.len(),
+            mailboxes: tally.mailboxes,
+            retries: tally.retries,
+            crc32: format!("{:08X}", crc32(&data)),
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+        Ok((data, report))
+    }
+
+    async fn receive_all<F: FnMut(TransferProgress)>(
+        &mut self,
+        timeout: Duration,
+        tally: &mut Tally<F>,
+    ) -> Result<Vec<u8>> {
+        info!("Waiting for an RF field to receive");
+        self.wait_for_field(timeout).await?;
+        self.configure(TransferDirection::FromPhone.config())
+            .await?;
+        let mut reassembly = Reassembly::default();
+        loop {
+            let (mailbox, retries) = self.receive_mailbox(tally.mailboxes, timeout).await?;
+            let bytes = match &mailbox {
+                Mailbox::Data { payload, .. } => payload.len(),
+                _ => 0,
+            };
+            match reassembly.push(mailbox)? {
+                Received::Data => tally.moved(bytes, retries),
+                Received::Repeated => debug!("Ignoring a repeated mailbox"),
+                Received::Complete(data) => {
+                    tally.moved(0, retries);
+                    return Ok(data);
+                }
+            }
+        }
+    }
+
+    /// Leave pass-through mode, on error paths too
+    async fn release(&mut self) {
+        if let Err(e) = self.configure(0).await {
+            warn!("Could not leave pass-through mode: {}", e);
+        }
+    }
+}
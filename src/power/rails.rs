@@ -0,0 +1,365 @@
+/*
+ * E-ink Power CLI - Power Rail Topology
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Power rails switched by the MCXC143 and the dependencies between them
+//!
+//! Switching a rail on while a rail it depends on is off (the display
+//! without the PMIC) is electrically invalid, and the firmware does not
+//! always refuse it. The [`PowerRailGraph`] is consulted before any rail
+//! is switched: the board topology by default, or the rules of the
+//! `[rails]` configuration section, such as `disp requires pmic`.
+//!
+//! A [`Rail`] names the target of the firmware's rail commands; its
+//! [`Rail::as_shell_str`] is the only place the shell spellings are kept.
+
+use crate::error::{PowerCliError, Result};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Target of `pm <rail> on|off|status` and `pm defaults <rail> on|off`
+///
+/// Shown and serialized by its name (`display`); the firmware spells it
+/// [`Self::as_shell_str`] (`disp`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rail {
+    Pmic,
+    Wifi,
+    #[serde(alias = "disp")]
+    Display,
+    Imx93,
+    /// Every rail at once
+    All,
+}
+
+impl Rail {
+    pub const ALL: [Rail; 5] = [
+        Rail::Pmic,
+        Rail::Wifi,
+        Rail::Display,
+        Rail::Imx93,
+        Rail::All,
+    ];
+
+    /// Rail name in firmware shell commands
+    pub fn as_shell_str(self) -> &'static str {
+        match self {
+            Rail::Pmic => "pmic",
+            Rail::Wifi => "wifi",
+            Rail::Display => "disp",
+            Rail::Imx93 => "imx93",
+            Rail::All => "all",
+        }
+    }
+
+    /// Name in commands and JSON, e.g. `display`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Rail::Pmic => "pmic",
+            Rail::Wifi => "wifi",
+            Rail::Display => "display",
+            Rail::Imx93 => "imx93",
+            Rail::All => "all",
+        }
+    }
+
+    /// Name used in messages
+    pub fn name(self) -> &'static str {
+        match self {
+            Rail::Pmic => "PMIC",
+            Rail::Wifi => "WiFi",
+            Rail::Display => "Display",
+            Rail::Imx93 => "i.MX93",
+            Rail::All => "All rails",
+        }
+    }
+
+    /// The single rail this switches, checked against the dependency graph
+    /// before switching
+    ///
+    /// `None` for `imx93` and `all`, which switch rails as the firmware
+    /// sequences them.
+    pub fn switched_rail(self) -> Option<PowerRail> {
+        match self {
+            Rail::Pmic => Some(PowerRail::Pmic),
+            Rail::Wifi => Some(PowerRail::Wifi),
+            Rail::Display => Some(PowerRail::Display),
+            Rail::Imx93 | Rail::All => None,
+        }
+    }
+}
+
+impl fmt::Display for Rail {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Rail {
+    type Err = String;
+
+    /// A rail by its name or shell name, in any case
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let name = s.trim();
+        Self::ALL
+            .into_iter()
+            .find(|rail| {
+                rail.as_str().eq_ignore_ascii_case(name)
+                    || rail.as_shell_str().eq_ignore_ascii_case(name)
+            })
+            .ok_or_else(|| {
+                format!(
+                    "unknown rail '{}' (expected pmic, wifi, display, imx93 or all)",
+                    name
+                )
+            })
+    }
+}
+
+/// Power rail (or powered domain) on the E-Ink controller board
+#[derive(
+    ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerRail {
+    /// i.MX93 PMIC enable (PMIC_EN)
+    Pmic,
+    /// WiFi module enable (WIFI_EN)
+    Wifi,
+    /// E-ink display enable (DISP_EN)
+    #[value(alias = "disp")]
+    Display,
+    /// NFC controller
+    Nfc,
+    /// LTC2959 coulomb counter
+    Ltc2959,
+    /// i.MX93 application processor, supplied by the PMIC
+    Imx93,
+}
+
+impl PowerRail {
+    /// Rail whose `pm <rail> status` shows this rail's state
+    pub fn shell_rail(self) -> Option<Rail> {
+        match self {
+            PowerRail::Pmic => Some(Rail::Pmic),
+            PowerRail::Wifi => Some(Rail::Wifi),
+            PowerRail::Display => Some(Rail::Display),
+            _ => None,
+        }
+    }
+
+    /// Name in commands and JSON, e.g. `display`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PowerRail::Pmic => "pmic",
+            PowerRail::Wifi => "wifi",
+            PowerRail::Display => "display",
+            PowerRail::Nfc => "nfc",
+            PowerRail::Ltc2959 => "ltc2959",
+            PowerRail::Imx93 => "imx93",
+        }
+    }
+
+    /// Name used in messages
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerRail::Pmic => "PMIC",
+            PowerRail::Wifi => "WiFi",
+            PowerRail::Display => "Display",
+            PowerRail::Nfc => "NFC",
+            PowerRail::Ltc2959 => "LTC2959",
+            PowerRail::Imx93 => "i.MX93",
+        }
+    }
+}
+
+/// Dependency DAG between power rails
+///
+/// An edge `rail → dependency` means `rail` must not be enabled before
+/// `dependency`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PowerRailGraph {
+    dependencies: BTreeMap<PowerRail, Vec<PowerRail>>,
+}
+
+impl Default for PowerRailGraph {
+    /// Topology of the E-Ink controller board
+    ///
+    /// The WiFi module, the display and the i.MX93 are all supplied from
+    /// the PMIC outputs. The NFC controller and the LTC2959 sit in the
+    /// MCXC143's always-on domain and have no dependencies.
+    fn default() -> Self {
+        let mut graph = Self::empty();
+        for rail in [PowerRail::Wifi, PowerRail::Display, PowerRail::Imx93] {
+            graph
+                .add_dependency(rail, PowerRail::Pmic)
+                .expect("default rail topology is acyclic");
+        }
+        graph
+    }
+}
+
+impl PowerRailGraph {
+    /// Graph without any dependencies
+    pub fn empty() -> Self {
+        Self {
+            dependencies: BTreeMap::new(),
+        }
+    }
+
+    /// Graph from rules of the form `<rail> requires <rail>`, e.g.
+    /// `disp requires pmic`
+    pub fn from_rules<S: AsRef<str>>(rules: &[S]) -> Result<Self> {
+        let mut graph = Self::empty();
+        for rule in rules {
+            let rule = rule.as_ref();
+            let invalid = |reason: String| PowerCliError::PowerError {
+                message: format!("rail dependency '{}': {}", rule, reason),
+            };
+            let rail = |name: &str| {
+                PowerRail::from_str(name, true)
+                    .map_err(|_| invalid(format!("unknown rail '{}'", name)))
+            };
+            match rule.split_whitespace().collect::<Vec<_>>().as_slice() {
+                [dependent, "requires", dependency] => graph
+                    .add_dependency(rail(dependent)?, rail(dependency)?)
+                    .map_err(|e| match e {
+                        PowerCliError::PowerError { message } => invalid(message),
+                        other => other,
+                    })?,
+                _ => return Err(invalid("expected '<rail> requires <rail>'".to_string())),
+            }
+        }
+        Ok(graph)
+    }
+
+    /// Record that `rail` depends on `dependency`
+    ///
+    /// Fails if the edge would create a cycle.
+    pub fn add_dependency(&mut self, rail: PowerRail, dependency: PowerRail) -> Result<()> {
+        if rail == dependency || self.depends_on(dependency, rail) {
+            return Err(PowerCliError::PowerError {
+                message: format!(
+                    "{} cannot depend on {}: rail dependencies would form a cycle",
+                    rail.name(),
+                    dependency.name()
+                ),
+            });
+        }
+        let deps = self.dependencies.entry(rail).or_default();
+        if !deps.contains(&dependency) {
+            deps.push(dependency);
+        }
+        Ok(())
+    }
+
+    /// Direct dependencies of `rail`
+    pub fn dependencies(&self, rail: PowerRail) -> &[PowerRail] {
+        self.dependencies
+            .get(&rail)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Whether `rail` depends on `other`, directly or transitively
+    pub fn depends_on(&self, rail: PowerRail, other: PowerRail) -> bool {
+        self.dependencies(rail)
+            .iter()
+            .any(|&dep| dep == other || self.depends_on(dep, other))
+    }
+
+    /// Rails `rail` depends on that are off in `states`, in power-on order
+    ///
+    /// Rails missing from `states` are assumed to be on.
+    pub fn missing_prerequisites(
+        &self,
+        rail: PowerRail,
+        states: &BTreeMap<PowerRail, bool>,
+    ) -> Vec<PowerRail> {
+        self.prerequisites(rail)
+            .into_iter()
+            .filter(|&dep| states.get(&dep) == Some(&false))
+            .collect()
+    }
+
+    /// Rails depending on `rail`, directly or transitively, that are on in
+    /// `states`
+    pub fn dependents_on(
+        &self,
+        rail: PowerRail,
+        states: &BTreeMap<PowerRail, bool>,
+    ) -> Vec<PowerRail> {
+        states
+            .iter()
+            .filter(|&(&other, &on)| on && self.depends_on(other, rail))
+            .map(|(&other, _)| other)
+            .collect()
+    }
+
+    /// Rails `rail` depends on, directly or transitively, in power-on
+    /// order
+    pub fn prerequisites(&self, rail: PowerRail) -> Vec<PowerRail> {
+        self.sort_power_on(&[rail])
+            .into_iter()
+            .filter(|&dep| dep != rail)
+            .collect()
+    }
+
+    /// Rails depending on `rail`, directly or transitively
+    pub fn dependents(&self, rail: PowerRail) -> Vec<PowerRail> {
+        PowerRail::value_variants()
+            .iter()
+            .copied()
+            .filter(|&other| self.depends_on(other, rail))
+            .collect()
+    }
+
+    /// Check that no rail in a power-on order comes before a dependency
+    ///
+    /// Dependencies that do not appear in `on_order` are assumed to be
+    /// already on.
+    pub fn validate_sequence(&self, on_order: &[PowerRail]) -> Result<()> {
+        for (index, &rail) in on_order.iter().enumerate() {
+            for &dep in self.dependencies(rail) {
+                if on_order[index + 1..].contains(&dep) {
+                    return Err(PowerCliError::PowerError {
+                        message: format!(
+                            "{} must be powered on after {}, which it depends on",
+                            rail.name(),
+                            dep.name()
+                        ),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Order `rails` so every rail follows its dependencies
+    ///
+    /// Missing dependencies are added ahead of the rails needing them, and
+    /// the requested order is otherwise kept.
+    pub fn sort_power_on(&self, rails: &[PowerRail]) -> Vec<PowerRail> {
+        let mut order = Vec::new();
+        for &rail in rails {
+            self.visit(rail, &mut order);
+        }
+        order
+    }
+
+    fn visit(&self, rail: PowerRail, order: &mut Vec<PowerRail>) {
+        if order.contains(&rail) {
+            return;
+        }
+        for &dep in self.dependencies(rail) {
+            self.visit(dep, order);
+        }
+        order.push(rail);
+    }
+}
@@ -0,0 +1,167 @@
+/*
+ * E-ink Power CLI - Reboot Detection
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Noticing PMU resets during a long-running session
+//!
+//! A watchdog reset is otherwise invisible to the host: the console comes
+//! back, commands keep working and only counters such as `pm stats` jump
+//! backwards. Two signs give it away:
+//!
+//! * the uptime from `system uptime` is lower than at the previous probe
+//! * the console printed the Zephyr boot banner (`*** Booting Zephyr OS ...`)
+//!   between two commands
+//!
+//! [`RebootDetector`] turns either into one [`RebootEvent`].
+//! [`SessionState`] remembers the commands that must be sent again after a
+//! reset because the firmware forgets them.
+
+use crate::json::patterns;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Uptime in milliseconds from a `system uptime` reply
+///
+/// Uses the `(67427 ms)` part when present, otherwise `H:MM:SS` with an
+/// optional `Nd` day prefix.
+pub fn parse_uptime_ms(response: &str) -> Option<u64> {
+    if let Some(ms) = patterns::UPTIME_MS
+        .captures(response)
+        .and_then(|caps| caps[1].parse().ok())
+    {
+        return Some(ms);
+    }
+    let caps = patterns::UPTIME_HMS.captures(response)?;
+    let field = |i: usize| {
+        caps.get(i)
+            .map_or(Some(0), |m| m.as_str().parse::<u64>().ok())
+    };
+    let secs = ((field(1)? * 24 + field(2)?) * 60 + field(3)?) * 60 + field(4)?;
+    Some(secs * 1000)
+}
+
+/// `H:MM:SS` as the firmware prints uptime
+pub fn format_uptime(ms: u64) -> String {
+    let secs = ms / 1000;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// What showed the reset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RebootEvidence {
+    /// Uptime went backwards
+    Uptime,
+    /// The boot banner appeared on the console
+    BootBanner,
+}
+
+/// One detected PMU reset
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RebootEvent {
+    pub evidence: RebootEvidence,
+    /// Uptime at the last probe before the reset
+    pub previous_uptime_ms: Option<u64>,
+    /// Uptime at the first probe after it
+    pub uptime_ms: Option<u64>,
+    /// Resets seen so far in this session, this one included
+    pub reboots: u32,
+    pub detected_at: DateTime<Utc>,
+    /// Reset cause from the boot banner, when one was printed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reset_cause: Option<String>,
+}
+
+impl RebootEvent {
+    /// One-line explanation, e.g. `PMU rebooted (uptime went from 1:00:05 to 0:00:03)`
+    pub fn message(&self) -> String {
+        let message = match (self.previous_uptime_ms, self.uptime_ms) {
+            (Some(previous), Some(now)) => format!(
+                "PMU rebooted (uptime went from {} to {})",
+                format_uptime(previous),
+                format_uptime(now)
+            ),
+            _ => "PMU rebooted (boot banner on the console)".to_string(),
+        };
+        match &self.reset_cause {
+            Some(cause) => format!("{}, reset cause: {}", message, cause),
+            None => message,
+        }
+    }
+}
+
+/// Tracks uptime across probes and counts resets
+#[derive(Debug, Default)]
+pub struct RebootDetector {
+    /// A probe has been fed; a banner before that predates the session
+    started: bool,
+    last_uptime_ms: Option<u64>,
+    reboots: u32,
+}
+
+impl RebootDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed one probe: the uptime it read, if any, and whether the boot
+    /// banner was seen since the previous probe
+    ///
+    /// Returns an event for a reset; both signs of the same reset count once.
+    pub fn observe(&mut self, uptime_ms: Option<u64>, banner: bool) -> Option<RebootEvent> {
+        let previous = self.last_uptime_ms;
+        let banner = std::mem::replace(&mut self.started, true) && banner;
+        if uptime_ms.is_some() {
+            self.last_uptime_ms = uptime_ms;
+        }
+
+        let evidence = match (previous, uptime_ms) {
+            (Some(previous), Some(now)) if now < previous => RebootEvidence::Uptime,
+            _ if banner => {
+                // The pre-reset uptime is no baseline for the next probe
+                self.last_uptime_ms = uptime_ms;
+                RebootEvidence::BootBanner
+            }
+            _ => return None,
+        };
+        self.reboots += 1;
+        Some(RebootEvent {
+            evidence,
+            previous_uptime_ms: previous,
+            uptime_ms,
+            reboots: self.reboots,
+            detected_at: Utc::now(),
+            reset_cause: None,
+        })
+    }
+
+    /// Resets detected so far
+    pub fn reboots(&self) -> u32 {
+        self.reboots
+    }
+}
+
+/// Settings this session gave the firmware that a reset clears
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SessionState {
+    /// `pm` subcommand that started firmware-side monitoring
+    monitor: Option<String>,
+}
+
+impl SessionState {
+    /// Note a `pm` subcommand that completed
+    pub fn record_pm_command(&mut self, command: &str) {
+        if command.starts_with("monitor start") {
+            self.monitor = Some(command.to_string());
+        } else if command.starts_with("monitor stop") {
+            self.monitor = None;
+        }
+    }
+
+    /// `pm` subcommands to send again after a reset
+    pub fn pm_commands(&self) -> Vec<String> {
+        self.monitor.iter().cloned().collect()
+    }
+}
@@ -0,0 +1,113 @@
+/*
+ * E-ink Power CLI - External RTC Calibration
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! PCF2131 crystal frequency offset calibration
+//!
+//! The PCF2131 `OFFSET` register holds a 7-bit two's complement correction
+//! in steps of 4.34 ppm, i.e. about -277.8 ppm to +273.4 ppm.
+
+use crate::json::patterns;
+use serde::{Deserialize, Serialize};
+
+/// Frequency correction per `OFFSET` register step
+pub const PCF2131_PPM_PER_STEP: f64 = 4.34;
+
+/// Largest correction the CLI accepts, in ppm
+pub const MAX_CALIBRATION_PPM: i16 = 100;
+
+/// Register value whose correction is closest to `ppm`
+///
+/// Rounds half away from zero and clamps to the 7-bit register range.
+pub fn ppm_to_pcf2131_offset(ppm: i16) -> i8 {
+    let steps = (ppm as f64 / PCF2131_PPM_PER_STEP).round();
+    steps.clamp(-64.0, 63.0) as i8
+}
+
+/// Correction in ppm applied by an `OFFSET` register value
+pub fn pcf2131_offset_to_ppm(register: i8) -> f64 {
+    register as f64 * PCF2131_PPM_PER_STEP
+}
+
+/// Result of translating a ppm offset into the PCF2131 register
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RtcCalibration {
+    /// Offset asked for (absent when read back from the device)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ppm_requested: Option<i16>,
+    pub register_value: i8,
+    /// Correction the register value actually applies
+    pub ppm_actual: f64,
+    /// `ppm_requested - ppm_actual`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub residual_ppm: Option<f64>,
+}
+
+impl RtcCalibration {
+    /// Calibration for a requested ppm offset
+    pub fn for_ppm(ppm: i16) -> Self {
+        let register_value = ppm_to_pcf2131_offset(ppm);
+        let ppm_actual = pcf2131_offset_to_ppm(register_value);
+        Self {
+            ppm_requested: Some(ppm),
+            register_value,
+            ppm_actual,
+            residual_ppm: Some(ppm as f64 - ppm_actual),
+        }
+    }
+
+    /// Calibration currently programmed in the register
+    pub fn from_register(register_value: i8) -> Self {
+        Self {
+            ppm_requested: None,
+            register_value,
+            ppm_actual: pcf2131_offset_to_ppm(register_value),
+            residual_ppm: None,
+        }
+    }
+
+    /// Raw 7-bit register encoding
+    pub fn register_bits(&self) -> u8 {
+        (self.register_value as u8) & 0x7F
+    }
+
+    /// Parse the register value from an `rtc calibration` response
+    ///
+    /// Accepts e.g. `Offset register: -5` or `OFFSET: 0x7B` (raw 7-bit value).
+    pub fn parse(response: &str) -> Option<Self> {
+        let caps = patterns::RTC_OFFSET.captures(response)?;
+        let value = &caps[1];
+        let register = match value.strip_prefix("0x").or(value.strip_prefix("0X")) {
+            Some(hex) => {
+                let bits = u8::from_str_radix(hex, 16).ok()? & 0x7F;
+                // Sign-extend the 7-bit two's complement value
+                ((bits << 1) as i8) >> 1
+            }
+            None => value
+                .parse::<i8>()
+                .ok()
+                .filter(|v| (-64..=63).contains(v))?,
+        };
+        Some(Self::from_register(register))
+    }
+
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(ppm) = self.ppm_requested {
+            lines.push(format!("Requested: {:+} ppm", ppm));
+        }
+        lines.push(format!(
+            "Register: {} (0x{:02X})",
+            self.register_value,
+            self.register_bits()
+        ));
+        lines.push(format!("Applied: {:+.2} ppm", self.ppm_actual));
+        if let Some(residual) = self.residual_ppm {
+            lines.push(format!("Residual error: {:+.2} ppm", residual));
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,185 @@
+/*
+ * E-ink Power CLI - Sleep Command Builder
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Typed builder for the `pm sleep` shell command
+
+use crate::error::{PowerCliError, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// MCXC143VFM very-low-leakage stop mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VllsMode {
+    /// ~150 nA, external wake only
+    Vlls0,
+    /// ~200 nA, internal RTC wake enabled
+    Vlls1,
+    /// ~350 nA, more RAM retention
+    Vlls2,
+    /// ~412 nA, full RAM, most wake sources
+    Vlls3,
+}
+
+impl VllsMode {
+    /// Shell flag selecting this mode
+    pub fn as_flag(self) -> &'static str {
+        match self {
+            VllsMode::Vlls0 => "--vlls0",
+            VllsMode::Vlls1 => "--vlls1",
+            VllsMode::Vlls2 => "--vlls2",
+            VllsMode::Vlls3 => "--vlls3",
+        }
+    }
+}
+
+/// Parse a sleep duration such as `30s`, `5m`, `2h`, `1d` or `1d12h30m`
+///
+/// A bare number is taken as seconds.
+pub fn parse_sleep_duration(time: &str) -> Option<Duration> {
+    let time = time.trim();
+    if time.is_empty() {
+        return None;
+    }
+    if let Ok(secs) = time.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+    for c in time.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            _ => return None,
+        };
+        let value: u64 = digits.parse().ok()?;
+        total = total.checked_add(value.checked_mul(unit)?)?;
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        return None;
+    }
+    Some(Duration::from_secs(total))
+}
+
+/// Builder for `sleep [time] [--pmic] [--wifi] [--disp] [--alloff] [--vllsN]`
+#[derive(Debug, Clone, Default)]
+pub struct SleepCommandBuilder {
+    time: Option<String>,
+    pmic_off: bool,
+    wifi_off: bool,
+    display_off: bool,
+    all_off: bool,
+    vlls_modes: Vec<VllsMode>,
+}
+
+impl SleepCommandBuilder {
+    /// Create an empty builder (plain `sleep`)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sleep for the given duration (e.g. `30s`, `1d12h30m`)
+    pub fn duration(mut self, time_str: &str) -> Self {
+        self.time = Some(time_str.trim().to_string());
+        self
+    }
+
+    /// Turn off the PMIC before sleeping
+    pub fn pmic_off(mut self) -> Self {
+        self.pmic_off = true;
+        self
+    }
+
+    /// Turn off WiFi before sleeping
+    pub fn wifi_off(mut self) -> Self {
+        self.wifi_off = true;
+        self
+    }
+
+    /// Turn off the display before sleeping
+    pub fn display_off(mut self) -> Self {
+        self.display_off = true;
+        self
+    }
+
+    /// Turn off all peripherals before sleeping
+    pub fn all_off(mut self) -> Self {
+        self.all_off = true;
+        self
+    }
+
+    /// Select a VLLS mode
+    pub fn vlls_mode(mut self, mode: VllsMode) -> Self {
+        if !self.vlls_modes.contains(&mode) {
+            self.vlls_modes.push(mode);
+        }
+        self
+    }
+
+    /// Selected VLLS mode, if exactly one is set
+    pub fn mode(&self) -> Option<VllsMode> {
+        match self.vlls_modes.as_slice() {
+            [mode] => Some(*mode),
+            _ => None,
+        }
+    }
+
+    /// Sleep duration, if set and valid
+    pub fn sleep_duration(&self) -> Option<Duration> {
+        self.time.as_deref().and_then(parse_sleep_duration)
+    }
+
+    /// Check that at most one VLLS mode is set and the duration parses
+    pub fn validate(&self) -> Result<()> {
+        if self.vlls_modes.len() > 1 {
+            let flags: Vec<&str> = self.vlls_modes.iter().map(|m| m.as_flag()).collect();
+            return Err(PowerCliError::InvalidCommand {
+                command: format!("only one VLLS mode may be set, got {}", flags.join(" ")),
+            });
+        }
+        if let Some(time) = &self.time {
+            if parse_sleep_duration(time).is_none() {
+                return Err(PowerCliError::InvalidCommand {
+                    command: format!(
+                        "invalid sleep duration '{}' (expected e.g. 30s, 5m, 2h, 1d12h30m)",
+                        time
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the `pm` subcommand line
+    pub fn build(&self) -> String {
+        let mut parts = vec!["sleep"];
+        if let Some(time) = &self.time {
+            parts.push(time);
+        }
+        if self.pmic_off {
+            parts.push("--pmic");
+        }
+        if self.wifi_off {
+            parts.push("--wifi");
+        }
+        if self.display_off {
+            parts.push("--disp");
+        }
+        if self.all_off {
+            parts.push("--alloff");
+        }
+        parts.extend(self.vlls_modes.iter().map(|m| m.as_flag()));
+        parts.join(" ")
+    }
+}
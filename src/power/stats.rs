@@ -0,0 +1,209 @@
+/*
+ * E-ink Power CLI - Derived Power Statistics
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Rates and shares computed host-side from the firmware's power counters
+//!
+//! The firmware only counts: time awake, sleeps, wakes and wakes per source.
+//! Read together with the uptime, the counters give wakes per hour, the
+//! share of time asleep and the average sleep; two readings give the same
+//! over the time between them, as at the end of a `monitor` session.
+//!
+//! The counters are 32-bit. One lower at the end than at the start has
+//! wrapped, and modular subtraction still gives the right difference once;
+//! the active-time counter wraps every 49.7 days, so over a longer window
+//! the time split is left out. A PMU reset between the readings restarts
+//! the counters, and the metrics are then taken since that boot.
+
+use crate::json::PowerStatsJson;
+use crate::power::control::PowerStats;
+use crate::power::wake::WakeSource;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const MS_PER_HOUR: f64 = 3_600_000.0;
+
+/// One reading of the power counters, at `uptime_ms`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PowerCounters {
+    pub uptime_ms: u64,
+    pub active_time_ms: Option<u32>,
+    pub sleep_count: Option<u32>,
+    pub wake_count: Option<u32>,
+    /// Wakes of each source the firmware counts separately
+    pub wakes_by_source: BTreeMap<WakeSource, u32>,
+}
+
+impl PowerCounters {
+    /// Counters of `power stats` read at `uptime_ms`
+    pub fn new(stats: &PowerStats, uptime_ms: u64) -> Self {
+        let wakes_by_source = [
+            (WakeSource::Rtc, stats.rtc_wake_count),
+            (WakeSource::Nfc, stats.nfc_wake_count),
+            (WakeSource::Uart, stats.uart_wake_count),
+        ]
+        .into_iter()
+        .filter_map(|(source, count)| Some((source, count?)))
+        .collect();
+        Self {
+            uptime_ms,
+            active_time_ms: stats.active_time_ms,
+            sleep_count: stats.sleep_count,
+            wake_count: stats.wake_count,
+            wakes_by_source,
+        }
+    }
+
+    /// Counters of `pm stats`, which has no time split or sources; `None`
+    /// without its uptime
+    pub fn from_pm_stats(stats: &PowerStatsJson) -> Option<Self> {
+        Some(Self {
+            uptime_ms: stats.uptime_ms?,
+            active_time_ms: None,
+            sleep_count: stats.sleep_cycles,
+            wake_count: stats.wake_cycles,
+            wakes_by_source: BTreeMap::new(),
+        })
+    }
+
+    /// The same counters as they stood at boot
+    fn at_boot(&self) -> Self {
+        Self {
+            uptime_ms: 0,
+            active_time_ms: self.active_time_ms.map(|_| 0),
+            sleep_count: self.sleep_count.map(|_| 0),
+            wake_count: self.wake_count.map(|_| 0),
+            wakes_by_source: self.wakes_by_source.keys().map(|s| (*s, 0)).collect(),
+        }
+    }
+}
+
+/// Metrics over the time a pair of [`PowerCounters`] spans
+///
+/// A metric is `None` when a counter it needs is missing or its divisor
+/// is zero.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PowerStatsDerived {
+    /// Time the metrics cover
+    pub window_ms: u64,
+    /// Whether the window starts at the last boot
+    pub since_boot: bool,
+    pub wakes_per_hour: Option<f64>,
+    pub wakes_per_hour_by_source: BTreeMap<WakeSource, f64>,
+    /// Share of the wakes each source caused
+    pub wake_share_percent: BTreeMap<WakeSource, f64>,
+    pub asleep_percent: Option<f64>,
+    pub active_percent: Option<f64>,
+    pub average_sleep_ms: Option<f64>,
+    /// A counter was lower at the end and was taken to have wrapped
+    pub counter_wrapped: bool,
+}
+
+impl PowerStatsDerived {
+    /// Metrics since the boot `counters` were read in
+    pub fn since_boot(counters: &PowerCounters) -> Self {
+        Self {
+            since_boot: true,
+            ..Self::between(&counters.at_boot(), counters)
+        }
+    }
+
+    /// Metrics over the time from `start` to `end`
+    pub fn between(start: &PowerCounters, end: &PowerCounters) -> Self {
+        let Some(window_ms) = end.uptime_ms.checked_sub(start.uptime_ms) else {
+            // Uptime went backwards: the PMU reset and restarted its counters
+            return Self::since_boot(end);
+        };
+        let mut counter_wrapped = false;
+        let mut delta = |start: Option<u32>, end: Option<u32>| {
+            let (start, end) = (start?, end?);
+            counter_wrapped |= end < start;
+            Some(end.wrapping_sub(start))
+        };
+
+        let wakes = delta(start.wake_count, end.wake_count);
+        let sleeps = delta(start.sleep_count, end.sleep_count);
+        let source_wakes: BTreeMap<WakeSource, u32> = end
+            .wakes_by_source
+            .iter()
+            .filter_map(|(source, &count)| {
+                let before = start.wakes_by_source.get(source).copied();
+                Some((*source, delta(before, Some(count))?))
+            })
+            .collect();
+        // Read a moment apart from the uptime, the active time can run
+        // slightly past the window
+        let active_ms = delta(start.active_time_ms, end.active_time_ms)
+            .filter(|_| window_ms <= u64::from(u32::MAX))
+            .map(|ms| u64::from(ms).min(window_ms));
+
+        let per_hour =
+            |count: u32| (window_ms > 0).then(|| f64::from(count) * MS_PER_HOUR / window_ms as f64);
+        let total_wakes = wakes
+            .filter(|&total| total > 0)
+            .or_else(|| Some(source_wakes.values().sum::<u32>()).filter(|&sum| sum > 0));
+        let active_percent = active_ms
+            .filter(|_| window_ms > 0)
+            .map(|ms| ms as f64 * 100.0 / window_ms as f64);
+
+        Self {
+            window_ms,
+            since_boot: false,
+            wakes_per_hour: wakes.and_then(per_hour),
+            wakes_per_hour_by_source: source_wakes
+                .iter()
+                .filter_map(|(source, &count)| Some((*source, per_hour(count)?)))
+                .collect(),
+            wake_share_percent: match total_wakes {
+                Some(total) => source_wakes
+                    .iter()
+                    .map(|(source, &count)| (*source, f64::from(count) * 100.0 / f64::from(total)))
+                    .collect(),
+                None => BTreeMap::new(),
+            },
+            asleep_percent: active_percent.map(|active| 100.0 - active),
+            active_percent,
+            average_sleep_ms: match (active_ms, sleeps) {
+                (Some(active), Some(sleeps)) if sleeps > 0 => {
+                    Some((window_ms - active) as f64 / f64::from(sleeps))
+                }
+                _ => None,
+            },
+            counter_wrapped,
+        }
+    }
+
+    /// Whether no metric could be computed
+    pub fn is_empty(&self) -> bool {
+        self.wakes_per_hour.is_none()
+            && self.wakes_per_hour_by_source.is_empty()
+            && self.active_percent.is_none()
+            && self.average_sleep_ms.is_none()
+    }
+}
+
+/// Result of `power stats`: the counters, the uptime they were read at and
+/// the metrics derived from both
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerStatsReport {
+    #[serde(flatten)]
+    pub stats: PowerStats,
+    /// `None` if `system uptime` could not be read
+    pub uptime_ms: Option<u64>,
+    /// `None` without the uptime
+    pub derived: Option<PowerStatsDerived>,
+}
+
+impl PowerStatsReport {
+    pub fn new(stats: PowerStats, uptime_ms: Option<u64>) -> Self {
+        let derived = uptime_ms
+            .map(|uptime_ms| PowerStatsDerived::since_boot(&PowerCounters::new(&stats, uptime_ms)));
+        Self {
+            stats,
+            uptime_ms,
+            derived,
+        }
+    }
+}
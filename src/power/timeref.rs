@@ -0,0 +1,153 @@
+/*
+ * E-ink Power CLI - PMU Time Reference
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Mapping PMU uptime onto the host clock
+//!
+//! Zephyr log timestamps count milliseconds since the PMU booted. A single
+//! `system uptime` reply, stamped with the host clock, is enough to work out
+//! when that boot happened. Every later PMU timestamp is then an offset from
+//! it. The PMU reads its uptime some time between the command leaving the
+//! host and the first reply byte arriving. The host time is taken halfway
+//! through that round trip, and half the round trip bounds the error.
+//!
+//! The reference is kept in the device state directory until the PMU
+//! reboots, which starts its uptime again from zero.
+
+use crate::json::patterns;
+use crate::serial::connection::RoundTrip;
+use crate::state::{DeviceState, StateFile, StatePayload};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+
+/// State file holding the reference
+pub const TIME_REF_FILE: &str = "time_ref.json";
+
+/// Uptime reads per reference; the one with the shortest round trip is kept
+pub const TIME_REF_SAMPLES: usize = 3;
+
+/// Error the reference should stay within
+pub const TARGET_ACCURACY_MS: f64 = 100.0;
+
+/// How much later a new boot estimate may fall before the PMU is taken to
+/// have rebooted
+///
+/// Well above the error of a reference and well below a real reboot cycle.
+pub const REBOOT_TOLERANCE_MS: i64 = 1000;
+
+/// Resolution of the uptime in a `system uptime` reply
+///
+/// Milliseconds when the reply carries `(67427 ms)`, otherwise the seconds
+/// of `H:MM:SS`.
+pub fn uptime_resolution_ms(response: &str) -> u64 {
+    if patterns::UPTIME_MS.is_match(response) {
+        1
+    } else {
+        1000
+    }
+}
+
+/// Stored reference for a serial device; `None` without a state directory
+pub fn stored(device: &str) -> Option<StateFile<TimeReference>> {
+    DeviceState::for_device(device).map(|state| state.file(TIME_REF_FILE))
+}
+
+/// Host wall-clock time of the PMU boot, from one uptime read
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeReference {
+    /// Estimated host time at PMU uptime 0
+    pub pmu_boot_wallclock: DateTime<Utc>,
+    /// Uptime the PMU reported
+    pub uptime_ms: u64,
+    /// Host time the uptime is taken to have been read
+    pub host_time: DateTime<Utc>,
+    /// From sending `system uptime` to the first byte of the reply
+    pub round_trip_ms: f64,
+    /// Bound on the boot estimate's error: half the round trip plus the
+    /// uptime resolution
+    pub uncertainty_ms: f64,
+}
+
+impl StatePayload for TimeReference {
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl TimeReference {
+    /// Reference from an uptime read with the timing of its exchange
+    ///
+    /// Assumes the command and the reply take equally long on the wire.
+    pub fn from_exchange(uptime_ms: u64, resolution_ms: u64, round_trip: RoundTrip) -> Self {
+        let half = round_trip.first_byte / 2;
+        let host_time = round_trip.sent_at + TimeDelta::from_std(half).unwrap_or(TimeDelta::zero());
+        let round_trip_ms = round_trip.first_byte.as_secs_f64() * 1000.0;
+        Self {
+            pmu_boot_wallclock: host_time - TimeDelta::milliseconds(uptime_ms as i64),
+            uptime_ms,
+            host_time,
+            round_trip_ms,
+            uncertainty_ms: round_trip_ms / 2.0 + resolution_ms as f64,
+        }
+    }
+
+    /// Host time of PMU timestamp `pmu_ms` (milliseconds since boot)
+    pub fn wallclock_at(&self, pmu_ms: u64) -> DateTime<Utc> {
+        self.pmu_boot_wallclock + TimeDelta::milliseconds(pmu_ms as i64)
+    }
+
+    /// Whether `later`, taken after this reference, shows a PMU reboot in
+    /// between
+    pub fn rebooted_before(&self, later: &TimeReference) -> bool {
+        let shift = later.pmu_boot_wallclock - self.pmu_boot_wallclock;
+        later.uptime_ms < self.uptime_ms || shift.num_milliseconds() > REBOOT_TOLERANCE_MS
+    }
+}
+
+/// PMU timestamp translated with a [`TimeReference`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeTranslation {
+    pub pmu_ms: u64,
+    pub wallclock: DateTime<Utc>,
+}
+
+/// Result of `system time-ref`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeRefReport {
+    #[serde(flatten)]
+    pub reference: TimeReference,
+    /// The stored reference was used instead of the new reading
+    pub reused: bool,
+    /// A stored reference from before a PMU reboot was dropped
+    pub replaced_stale: bool,
+    /// The `--at` timestamp on the host clock
+    pub translation: Option<TimeTranslation>,
+}
+
+impl TimeRefReport {
+    /// Choose between the stored reference and a fresh reading
+    ///
+    /// Translating with `--at` keeps a stored reference that is still valid,
+    /// so repeated lookups agree with each other. Otherwise the fresh
+    /// reading wins. It also replaces a stored reference from before a
+    /// reboot.
+    pub fn new(stored: Option<TimeReference>, fresh: TimeReference, at: Option<u64>) -> Self {
+        let replaced_stale = stored
+            .as_ref()
+            .is_some_and(|stored| stored.rebooted_before(&fresh));
+        let (reference, reused) = match stored {
+            Some(stored) if at.is_some() && !replaced_stale => (stored, true),
+            _ => (fresh, false),
+        };
+        let translation = at.map(|pmu_ms| TimeTranslation {
+            pmu_ms,
+            wallclock: reference.wallclock_at(pmu_ms),
+        });
+        Self {
+            reference,
+            reused,
+            replaced_stale,
+            translation,
+        }
+    }
+}
@@ -0,0 +1,173 @@
+/*
+ * E-ink Power CLI - Wake Sources
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Wake-source configuration of the controller's low-leakage wake unit
+//!
+//! The firmware keeps a mask of the sources allowed to wake it from sleep
+//! (`pm wake config`). The VLLS mode then limits which of those still work:
+//! VLLS0 stops the LPO clock, so the internal timer cannot wake it. If the
+//! console UART is not in the effective mask and the sleep has no duration,
+//! nothing the host can send will bring the PMU back.
+
+use crate::power::sleep::VllsMode;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Source that can wake the controller from sleep
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WakeSource {
+    /// Console UART receive line
+    Uart,
+    /// Internal low-power timer (internal RTC)
+    Lptmr,
+    /// External PCF2131 RTC interrupt
+    Rtc,
+    /// NFC field detect
+    Nfc,
+    /// User button
+    Button,
+}
+
+impl WakeSource {
+    /// All sources, in mask bit order
+    pub const ALL: [WakeSource; 5] = [
+        WakeSource::Uart,
+        WakeSource::Lptmr,
+        WakeSource::Rtc,
+        WakeSource::Nfc,
+        WakeSource::Button,
+    ];
+
+    /// Bit of this source in the firmware wake mask
+    pub fn bit(self) -> u8 {
+        1 << WakeSource::ALL.iter().position(|s| *s == self).unwrap_or(0)
+    }
+
+    /// Name used by the `pm wake` shell command
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WakeSource::Uart => "uart",
+            WakeSource::Lptmr => "lptmr",
+            WakeSource::Rtc => "rtc",
+            WakeSource::Nfc => "nfc",
+            WakeSource::Button => "button",
+        }
+    }
+
+    /// Sources that still work in `mode` (`None` for plain LLS sleep)
+    pub fn available_in(mode: Option<VllsMode>) -> WakeMask {
+        match mode {
+            Some(VllsMode::Vlls0) => WakeMask::from_bits(!WakeSource::Lptmr.bit()),
+            _ => WakeMask::from_bits(u8::MAX),
+        }
+    }
+}
+
+/// Set of enabled wake sources
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WakeMask {
+    /// Raw firmware mask
+    pub mask: u8,
+    /// The same mask as source names
+    pub sources: Vec<WakeSource>,
+}
+
+impl WakeMask {
+    /// Mask from firmware bits; unknown bits are dropped
+    pub fn from_bits(bits: u8) -> Self {
+        let sources: Vec<WakeSource> = WakeSource::ALL
+            .into_iter()
+            .filter(|s| bits & s.bit() != 0)
+            .collect();
+        Self {
+            mask: sources.iter().fold(0, |mask, s| mask | s.bit()),
+            sources,
+        }
+    }
+
+    pub fn contains(&self, source: WakeSource) -> bool {
+        self.mask & source.bit() != 0
+    }
+
+    /// Sources of this mask that still work in `mode`
+    pub fn effective_in(&self, mode: Option<VllsMode>) -> Self {
+        Self::from_bits(self.mask & WakeSource::available_in(mode).mask)
+    }
+
+    /// Parse the reply to `pm wake config`
+    ///
+    /// Per-source lines (`UART: enabled`) take precedence over a
+    /// `Wake mask: 0x0B` line. Returns `None` if neither is present.
+    pub fn parse(response: &str) -> Option<Self> {
+        let mut bits = 0u8;
+        let mut found = false;
+        let mut raw_mask = None;
+
+        for line in response.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim().to_lowercase();
+
+            if key.ends_with("mask") {
+                let hex = value.trim_start_matches("0x");
+                raw_mask = u8::from_str_radix(hex, 16).ok();
+                continue;
+            }
+            let Some(source) = WakeSource::ALL.into_iter().find(|s| key == s.as_str()) else {
+                continue;
+            };
+            found = true;
+            if matches!(value.as_str(), "enabled" | "on" | "yes" | "1") {
+                bits |= source.bit();
+            }
+        }
+
+        if found {
+            Some(Self::from_bits(bits))
+        } else {
+            raw_mask.map(Self::from_bits)
+        }
+    }
+
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        let mut lines = vec![format!("Wake mask: 0x{:02X}", self.mask)];
+        for source in WakeSource::ALL {
+            let icon = if self.contains(source) { "✅" } else { "❌" };
+            lines.push(format!("{} {}", icon, source.as_str().to_uppercase()));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Whether a sleep would leave the host unable to wake the controller
+///
+/// Only VLLS0 and VLLS1, the lowest-power modes, are checked. A duration
+/// only counts as a bound if a timer source (LPTMR or RTC) can still wake
+/// the controller.
+pub fn console_lockout(mode: Option<VllsMode>, bounded: bool, effective: &WakeMask) -> bool {
+    let timer_wake = effective.contains(WakeSource::Lptmr) || effective.contains(WakeSource::Rtc);
+    matches!(mode, Some(VllsMode::Vlls0 | VllsMode::Vlls1))
+        && !(bounded && timer_wake)
+        && !effective.contains(WakeSource::Uart)
+}
+
+/// Outcome of `pm sleep`, with the wake sources that apply during it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SleepReport {
+    /// `pm` subcommand line sent
+    pub command: String,
+    pub vlls_mode: Option<VllsMode>,
+    pub duration_s: Option<u64>,
+    /// Mask configured in the firmware; `None` if it could not be read
+    pub configured_wake: Option<WakeMask>,
+    /// Configured mask limited to what the VLLS mode keeps running
+    pub effective_wake: Option<WakeMask>,
+    pub response: String,
+}
@@ -0,0 +1,618 @@
+/*
+ * E-ink Power CLI - Unit Provisioning
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `provision`: bring a unit from the line to shipping state in one run
+//!
+//! A TOML [`ProvisionManifest`] says what the unit should end up with:
+//! firmware version, identity, rail defaults, battery capacity, RTC
+//! interrupt action and health thresholds. Each section is one
+//! [`ProvisionStep`], run in a fixed order with the same typed client
+//! methods the single commands use; a section left out of the manifest is
+//! skipped. The run stops at the first failed step and produces one
+//! [`ProvisionRecord`], which is what gets archived per unit.
+//!
+//! The record is kept in the device state directory while it is failing, so
+//! a run with `--resume` repeats only the steps that have not passed yet.
+
+use crate::cli::ExternalRtcAction;
+use crate::error::{PowerCliError, Result};
+use crate::firmware::FirmwareManager;
+use crate::json::{self, BatteryVerdict, PowerDefaults, ResponseParser};
+use crate::power::control::PowerController;
+use crate::power::identity::DeviceIdentity;
+use crate::state::{DeviceState, StateFile, StatePayload};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::info;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// State file holding the record of the last failed run
+pub const PROVISION_STATE_FILE: &str = "provision.json";
+
+/// Stored record of the last failed run for a serial device; `None`
+/// without a state directory
+pub fn stored(device: &str) -> Option<StateFile<ProvisionRecord>> {
+    DeviceState::for_device(device).map(|state| state.file(PROVISION_STATE_FILE))
+}
+
+/// What a unit should end up with, read from a TOML file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProvisionManifest {
+    /// Accept the destructive steps without asking
+    #[serde(default)]
+    pub auto_confirm: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub firmware: Option<FirmwareTarget>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity: Option<IdentityTarget>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<PowerDefaults>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub battery: Option<BatteryTarget>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rtc: Option<RtcTarget>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health: Option<HealthThresholds>,
+}
+
+/// `[firmware]`: the version to run, and the image that provides it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FirmwareTarget {
+    /// Oldest acceptable version, e.g. `2.5.0`
+    pub version: String,
+    /// Image uploaded when the unit runs an older version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+}
+
+/// `[identity]`: as written by `identity write`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IdentityTarget {
+    pub serial: String,
+    pub hw_rev: u8,
+    /// Manufacture date as `"YYYY-MM-DD"` (default: the day of the run)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<NaiveDate>,
+}
+
+/// `[battery]`: the fitted battery, fully charged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatteryTarget {
+    /// Accumulated charge the gauge starts counting down from
+    pub capacity_mah: u16,
+}
+
+/// `[rtc]`: the external RTC interrupt action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RtcTarget {
+    pub action: ExternalRtcAction,
+}
+
+/// `[health]`: what the unit must pass before it ships
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HealthThresholds {
+    /// Require a healthy `pm battery_check` verdict
+    #[serde(default = "default_battery_check")]
+    pub battery_check: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_voltage_mv: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_voltage_mv: Option<u16>,
+    /// Largest battery drain accepted, e.g. to catch a shorted rail
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_discharge_ma: Option<u16>,
+}
+
+fn default_battery_check() -> bool {
+    true
+}
+
+impl ProvisionManifest {
+    /// Read and check a manifest file
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|e| PowerCliError::InvalidArguments {
+            message: format!("manifest {}: {}", path.display(), e),
+        })
+    }
+
+    /// Read a manifest, failing on values no step could use
+    pub fn parse(text: &str) -> std::result::Result<Self, String> {
+        let manifest: Self =
+            toml::from_str(text).map_err(|e| e.to_string().trim_end().to_string())?;
+        if let Some(firmware) = &manifest.firmware {
+            firmware.version()?;
+        }
+        if let Some(identity) = &manifest.identity {
+            identity.identity().map_err(|e| e.to_string())?;
+        }
+        Ok(manifest)
+    }
+
+    /// Steps the manifest asks for, in run order
+    pub fn steps(&self) -> Vec<ProvisionStep> {
+        ProvisionStep::ALL
+            .into_iter()
+            .filter(|step| self.includes(*step))
+            .collect()
+    }
+
+    fn includes(&self, step: ProvisionStep) -> bool {
+        match step {
+            ProvisionStep::Firmware => self.firmware.is_some(),
+            ProvisionStep::Identity => self.identity.is_some(),
+            ProvisionStep::Defaults => self.defaults.is_some(),
+            ProvisionStep::Gauge => self.battery.is_some(),
+            ProvisionStep::Rtc => self.rtc.is_some(),
+            ProvisionStep::Health => self.health.is_some(),
+        }
+    }
+}
+
+impl FirmwareTarget {
+    pub fn version(&self) -> std::result::Result<Version, String> {
+        Version::parse(&self.version)
+            .map_err(|e| format!("invalid firmware version '{}': {}", self.version, e))
+    }
+}
+
+impl IdentityTarget {
+    /// The identity to write; without a date, dated today
+    pub fn identity(&self) -> Result<DeviceIdentity> {
+        let date = self
+            .date
+            .unwrap_or_else(|| chrono::Local::now().date_naive());
+        DeviceIdentity::new(&self.serial, self.hw_rev, date)
+    }
+}
+
+/// One step of provisioning, in execution order
+///
+/// The serialized names are stable; the MES archives them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProvisionStep {
+    /// Check the firmware version and upload the image if it is older
+    Firmware,
+    /// Program the unit identity into the NFC EEPROM
+    Identity,
+    /// Apply and save the power rail defaults
+    Defaults,
+    /// Reset the LTC2959 for the fitted battery and set its charge
+    Gauge,
+    /// Set the external RTC interrupt action
+    Rtc,
+    /// Check the battery against the health thresholds
+    Health,
+}
+
+impl ProvisionStep {
+    /// All steps, in execution order
+    pub const ALL: [ProvisionStep; 6] = [
+        ProvisionStep::Firmware,
+        ProvisionStep::Identity,
+        ProvisionStep::Defaults,
+        ProvisionStep::Gauge,
+        ProvisionStep::Rtc,
+        ProvisionStep::Health,
+    ];
+
+    /// Short description for human-readable output
+    pub fn description(self) -> &'static str {
+        match self {
+            ProvisionStep::Firmware => "Firmware version",
+            ProvisionStep::Identity => "Program identity",
+            ProvisionStep::Defaults => "Power rail defaults",
+            ProvisionStep::Gauge => "Battery gauge",
+            ProvisionStep::Rtc => "RTC interrupt action",
+            ProvisionStep::Health => "Health checks",
+        }
+    }
+}
+
+/// Outcome of a provisioning step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProvisionStatus {
+    Ok,
+    Failed,
+    /// Passed in the run being resumed and not repeated
+    Resumed,
+    /// Not in the manifest
+    Skipped,
+    /// Not attempted because an earlier step failed
+    NotRun,
+}
+
+impl ProvisionStatus {
+    /// Whether the step needs no more work
+    pub fn passed(self) -> bool {
+        matches!(self, ProvisionStatus::Ok | ProvisionStatus::Resumed)
+    }
+}
+
+/// Result of one step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvisionStepResult {
+    pub step: ProvisionStep,
+    pub status: ProvisionStatus,
+    /// What the step found or did, or the reason it failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    /// Time the step took in this run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+/// Pass/fail record of one provisioning run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProvisionRecord {
+    pub success: bool,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Version of this CLI
+    pub cli_version: String,
+    /// Serial number programmed, from the manifest
+    pub serial: Option<String>,
+    /// Firmware version the unit was left running
+    pub firmware_version: Option<String>,
+    pub manifest: ProvisionManifest,
+    pub steps: Vec<ProvisionStepResult>,
+}
+
+impl StatePayload for ProvisionRecord {
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl ProvisionRecord {
+    /// First step that failed, if any
+    pub fn failed_step(&self) -> Option<&ProvisionStepResult> {
+        self.steps
+            .iter()
+            .find(|s| s.status == ProvisionStatus::Failed)
+    }
+
+    /// Whether `step` passed in this run or the one it resumed
+    pub fn passed(&self, step: ProvisionStep) -> bool {
+        self.steps
+            .iter()
+            .any(|result| result.step == step && result.status.passed())
+    }
+
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        let mut lines: Vec<String> = self
+            .steps
+            .iter()
+            .map(|result| {
+                let icon = match result.status {
+                    ProvisionStatus::Ok => "✅",
+                    ProvisionStatus::Resumed => "↩️ ",
+                    ProvisionStatus::Failed => "❌",
+                    ProvisionStatus::Skipped => "⏭️ ",
+                    ProvisionStatus::NotRun => "⏸️ ",
+                };
+                let mut line = format!("{} {}", icon, result.step.description());
+                match (result.status, &result.detail) {
+                    (ProvisionStatus::Skipped, _) => line.push_str(" (not in manifest)"),
+                    (ProvisionStatus::NotRun, _) => line.push_str(" (not run)"),
+                    (ProvisionStatus::Resumed, _) => line.push_str(" (passed earlier)"),
+                    (_, Some(detail)) if !detail.trim().is_empty() => {
+                        for detail_line in detail.trim().lines() {
+                            line.push_str(&format!("\n      {}", detail_line));
+                        }
+                    }
+                    _ => {}
+                }
+                line
+            })
+            .collect();
+        lines.push(String::new());
+        lines.push(match (&self.serial, self.success) {
+            (Some(serial), true) => format!("PASS: {}", serial),
+            (Some(serial), false) => format!("FAIL: {}", serial),
+            (None, true) => "PASS".to_string(),
+            (None, false) => "FAIL".to_string(),
+        });
+        lines.join("\n")
+    }
+}
+
+/// Run every step of `manifest`, in order, stopping at the first failure
+///
+/// Steps that passed in `earlier`, a failed run of the same manifest, are
+/// not repeated. `on_step` is given the record so far after each step, so
+/// it can be saved before the next one starts.
+pub async fn run<F>(
+    controller: &mut PowerController,
+    firmware: &mut FirmwareManager,
+    manifest: &ProvisionManifest,
+    earlier: Option<&ProvisionRecord>,
+    mut on_step: F,
+) -> ProvisionRecord
+where
+    F: FnMut(&ProvisionRecord),
+{
+    let mut record = ProvisionRecord {
+        success: false,
+        started_at: Utc::now(),
+        finished_at: Utc::now(),
+        cli_version: env!("CARGO_PKG_VERSION").to_string(),
+        serial: manifest.identity.as_ref().map(|id| id.serial.clone()),
+        firmware_version: earlier.and_then(|earlier| earlier.firmware_version.clone()),
+        manifest: manifest.clone(),
+        steps: Vec::new(),
+    };
+    let mut failed = false;
+
+    for step in ProvisionStep::ALL {
+        let earlier_result = earlier
+            .filter(|earlier| earlier.passed(step))
+            .and_then(|earlier| earlier.steps.iter().find(|result| result.step == step));
+        let result = if failed {
+            skipped(step, ProvisionStatus::NotRun)
+        } else if !manifest.includes(step) {
+            skipped(step, ProvisionStatus::Skipped)
+        } else if let Some(earlier_result) = earlier_result {
+            ProvisionStepResult {
+                status: ProvisionStatus::Resumed,
+                duration_ms: None,
+                ..earlier_result.clone()
+            }
+        } else {
+            info!("Provisioning: {}", step.description());
+            let started = Instant::now();
+            let outcome = run_step(controller, firmware, manifest, step, &mut record).await;
+            let duration_ms = Some(started.elapsed().as_millis() as u64);
+            let (status, detail) = match outcome {
+                Ok(detail) => (ProvisionStatus::Ok, detail),
+                Err(e) => {
+                    failed = true;
+                    (ProvisionStatus::Failed, e.to_string())
+                }
+            };
+            ProvisionStepResult {
+                step,
+                status,
+                detail: Some(detail),
+                duration_ms,
+            }
+        };
+        let ran = matches!(result.status, ProvisionStatus::Ok | ProvisionStatus::Failed);
+        record.steps.push(result);
+        if ran {
+            record.finished_at = Utc::now();
+            on_step(&record);
+        }
+    }
+
+    record.success = !failed;
+    record.finished_at = Utc::now();
+    record
+}
+
+fn skipped(step: ProvisionStep, status: ProvisionStatus) -> ProvisionStepResult {
+    ProvisionStepResult {
+        step,
+        status,
+        detail: None,
+        duration_ms: None,
+    }
+}
+
+async fn run_step(
+    controller: &mut PowerController,
+    firmware: &mut FirmwareManager,
+    manifest: &ProvisionManifest,
+    step: ProvisionStep,
+    record: &mut ProvisionRecord,
+) -> Result<String> {
+    match step {
+        ProvisionStep::Firmware => {
+            let Some(target) = &manifest.firmware else {
+                return Ok(String::new());
+            };
+            let (version, detail) = update_firmware(controller, firmware, target).await?;
+            record.firmware_version = version;
+            Ok(detail)
+        }
+        ProvisionStep::Identity => {
+            let Some(target) = &manifest.identity else {
+                return Ok(String::new());
+            };
+            let identity = target.identity()?;
+            controller.write_identity(&identity).await?;
+            Ok(format!(
+                "serial {}, hardware revision {}, manufactured {}",
+                identity.serial, identity.hw_rev, identity.manufacture_date
+            ))
+        }
+        ProvisionStep::Defaults => {
+            let Some(defaults) = &manifest.defaults else {
+                return Ok(String::new());
+            };
+            controller.import_rail_defaults(defaults).await
+        }
+        ProvisionStep::Gauge => {
+            let Some(battery) = &manifest.battery else {
+                return Ok(String::new());
+            };
+            provision_gauge(controller, battery.capacity_mah).await
+        }
+        ProvisionStep::Rtc => {
+            let Some(rtc) = &manifest.rtc else {
+                return Ok(String::new());
+            };
+            controller.rtc_config(rtc.action.as_str()).await
+        }
+        ProvisionStep::Health => {
+            let Some(thresholds) = &manifest.health else {
+                return Ok(String::new());
+            };
+            check_health(controller, thresholds).await
+        }
+    }
+}
+
+/// Version the unit runs, from `version`
+async fn firmware_version(controller: &mut PowerController) -> Result<Option<String>> {
+    let response = controller.get_system_info().await?;
+    Ok(ResponseParser::parse_system_info(&response).version)
+}
+
+/// Upload the image unless the unit already runs `target.version` or later;
+/// returns the version left running and what was done
+async fn update_firmware(
+    controller: &mut PowerController,
+    firmware: &mut FirmwareManager,
+    target: &FirmwareTarget,
+) -> Result<(Option<String>, String)> {
+    let wanted = target
+        .version()
+        .map_err(|message| PowerCliError::InvalidArguments { message })?;
+    let running = firmware_version(controller).await?;
+    let meets = |version: &Option<String>| {
+        version
+            .as_deref()
+            .and_then(json::firmware_semver)
+            .is_some_and(|version| version >= wanted)
+    };
+    if meets(&running) {
+        let shown = running.as_deref().unwrap_or_default();
+        return Ok((running.clone(), format!("{} meets {}", shown, wanted)));
+    }
+
+    let shown = running.as_deref().unwrap_or("an unknown version");
+    let Some(file) = &target.file else {
+        return Err(PowerCliError::FirmwareError {
+            message: format!(
+                "unit runs {}, older than {}, and the manifest names no image to upload",
+                shown, wanted
+            ),
+        });
+    };
+    if controller.connection().is_dry_run() {
+        return Ok((running, format!("would upload {}", file.display())));
+    }
+    info!("Unit runs {}; uploading {}", shown, file.display());
+    // The upload opens the port itself
+    controller.close().await;
+    let uploaded = firmware.upload_firmware(file, false).await;
+    firmware.close().await;
+    uploaded?;
+
+    controller.invalidate_cache();
+    controller.flush_rx_buffer().await?;
+    let now = firmware_version(controller).await?;
+    if !meets(&now) {
+        return Err(PowerCliError::FirmwareError {
+            message: format!(
+                "unit runs {} after uploading {}, older than {}",
+                now.as_deref().unwrap_or("an unknown version"),
+                file.display(),
+                wanted
+            ),
+        });
+    }
+    let detail = format!(
+        "upgraded from {} to {}",
+        shown,
+        now.as_deref().unwrap_or("an unknown version")
+    );
+    Ok((now, detail))
+}
+
+/// Reset the gauge for a fresh battery and set the charge to its capacity
+async fn provision_gauge(controller: &mut PowerController, capacity_mah: u16) -> Result<String> {
+    controller.control_ltc2959("production_reset").await?;
+    controller
+        .control_ltc2959(&format!("set_charge {}", capacity_mah))
+        .await?;
+    if controller.connection().is_dry_run() {
+        return Ok(format!("charge set to {} mAh", capacity_mah));
+    }
+    match controller.read_charge().await? {
+        Some(charge) if charge == capacity_mah => {
+            Ok(format!("charge set to {} mAh (verified)", capacity_mah))
+        }
+        Some(charge) => Err(PowerCliError::BatteryError {
+            message: format!(
+                "charge register reads {} mAh after setting {} mAh",
+                charge, capacity_mah
+            ),
+        }),
+        None => Err(PowerCliError::BatteryError {
+            message: "charge register could not be read back".to_string(),
+        }),
+    }
+}
+
+/// Check the battery against every threshold; fails listing each one missed
+async fn check_health(
+    controller: &mut PowerController,
+    thresholds: &HealthThresholds,
+) -> Result<String> {
+    let mut checks = Vec::new();
+    let mut problems = Vec::new();
+
+    if thresholds.battery_check {
+        let health = controller.battery_health_check().await?;
+        match health.verdict {
+            Some(BatteryVerdict::Healthy) => checks.push("battery healthy".to_string()),
+            Some(verdict) => {
+                problems.push(format!("battery check verdict is {}", verdict.as_str()))
+            }
+            None => problems.push("battery check reported no verdict".to_string()),
+        }
+    }
+
+    let limits = [
+        thresholds.min_voltage_mv,
+        thresholds.max_voltage_mv,
+        thresholds.max_discharge_ma,
+    ];
+    if limits.iter().any(Option::is_some) {
+        let measurement = controller.measure().await?;
+        match (measurement.voltage_mv, limits[0], limits[1]) {
+            (None, None, None) => {}
+            (None, _, _) => problems.push("no battery voltage reading".to_string()),
+            (Some(mv), Some(min), _) if mv < min => {
+                problems.push(format!("battery at {} mV, below {} mV", mv, min))
+            }
+            (Some(mv), _, Some(max)) if mv > max => {
+                problems.push(format!("battery at {} mV, above {} mV", mv, max))
+            }
+            (Some(mv), _, _) => checks.push(format!("battery at {} mV", mv)),
+        }
+        if let Some(max) = thresholds.max_discharge_ma {
+            match measurement.current_ma {
+                Some(ma) if ma < 0 && ma.unsigned_abs() > max => {
+                    problems.push(format!("drawing {} mA, more than {} mA", -ma, max))
+                }
+                Some(ma) => checks.push(format!("current {} mA", ma)),
+                None => problems.push("no battery current reading".to_string()),
+            }
+        }
+    }
+
+    if controller.connection().is_dry_run() {
+        return Ok("health checks not possible in dry-run".to_string());
+    }
+    if !problems.is_empty() {
+        return Err(PowerCliError::BatteryError {
+            message: format!("health check failed: {}", problems.join("; ")),
+        });
+    }
+    Ok(checks.join(", "))
+}
@@ -0,0 +1,769 @@
+/*
+ * E-ink Power CLI - Result Output
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Writing a command result in the format selected with `--format`
+//!
+//! The human format comes from the renderers in the parent module; JSON,
+//! NDJSON, CSV and Prometheus output is built here.
+
+use super::OutputStyle;
+use crate::cli::{Cli, OutputFormat};
+use crate::error::PowerCliError;
+use crate::fleet::FleetInventory;
+use crate::json::samples::UnparsedLine;
+use crate::json::{
+    self, diagnostics, progress, CommandOutput, JsonResponse, ParsedPayload, RecordType,
+    ResponseParser,
+};
+use crate::macros::{MacroReport, MacroStepStatus};
+use crate::power::battery::{ChargingTransition, VoltageHistory};
+use crate::power::ltc2959::ContinuousReadJson;
+use crate::power::passthrough::TransferProgress;
+use crate::power::rails::{PowerRail, Rail};
+use crate::power::reboot::RebootEvent;
+use crate::power::rtc::RtcCalibration;
+use crate::serial::{banner, ConnectionStats, LatencyStats};
+use serde::Serialize;
+use std::io::{IsTerminal, Write};
+
+/// Print a JSON document, compact on one line for NDJSON output
+///
+/// Documents of a command typed by its deprecated name get
+/// `"deprecated": true`. The first document after connecting to a PMU that
+/// had just reset carries its `boot_banner`. With `--format json-lines` the
+/// document is a [`RecordType::Summary`] record: the result of the command.
+pub fn json<T: Serialize>(cli: &Cli, value: &T) -> Result<(), PowerCliError> {
+    record(cli, RecordType::Summary, value)
+}
+
+/// Like [`json()`] for a document of a stream, marked as `record_type` with
+/// `--format json-lines`
+pub fn record<T: Serialize>(
+    cli: &Cli,
+    record_type: RecordType,
+    value: &T,
+) -> Result<(), PowerCliError> {
+    let boot_banner = banner::take_for_output();
+    if cli.deprecation.is_some() || boot_banner.is_some() {
+        let mut value = serde_json::to_value(value)?;
+        if let Some(object) = value.as_object_mut() {
+            if cli.deprecation.is_some() {
+                object.insert("deprecated".to_string(), true.into());
+            }
+            if let Some(boot_banner) = boot_banner {
+                object.insert(
+                    "boot_banner".to_string(),
+                    serde_json::to_value(boot_banner)?,
+                );
+            }
+            return print_json(cli, record_type, &value);
+        }
+    }
+    print_json(cli, record_type, value)
+}
+
+fn print_json<T: Serialize>(
+    cli: &Cli,
+    record_type: RecordType,
+    value: &T,
+) -> Result<(), PowerCliError> {
+    match cli.format {
+        OutputFormat::Ndjson => json::write_ndjson(&mut super::sink::Output, value)?,
+        OutputFormat::JsonLines => {
+            json::write_record(&mut super::sink::Output, record_type, value)?
+        }
+        _ => super::print(&serde_json::to_string_pretty(value)?),
+    }
+    Ok(())
+}
+
+/// Flush stdout when `--line-buffered` is set so pipes see output immediately
+pub fn flush_if_line_buffered(cli: &Cli) {
+    if cli.line_buffered {
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Print a reply under a heading whatever the format
+///
+/// For replies that have no machine-readable form yet.
+pub fn titled(cli: &Cli, icon: &str, title: &str, body: &str) {
+    titled_with(cli, icon, title, body, |_| None);
+}
+
+/// Like [`titled`], rendered from the parsed reply when `render` recognizes it
+pub fn titled_with(
+    cli: &Cli,
+    icon: &str,
+    title: &str,
+    response: &str,
+    render: impl FnOnce(&OutputStyle) -> Option<String>,
+) {
+    let style = cli.output_style();
+    super::print(&render(&style).unwrap_or_else(|| super::titled(&style, icon, title, response)));
+}
+
+/// Print `value` in a JSON envelope, or its human rendering in any other format
+///
+/// For results without a CSV or Prometheus form of their own.
+pub fn result<T: Serialize>(
+    cli: &Cli,
+    command: &str,
+    value: &T,
+    human: impl FnOnce(&OutputStyle) -> String,
+) -> Result<(), PowerCliError> {
+    match cli.format {
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            json(
+                cli,
+                &JsonResponse::success(command, serde_json::to_value(value)?),
+            )?;
+        }
+        _ => super::print(&human(&cli.output_style())),
+    }
+    flush_if_line_buffered(cli);
+    Ok(())
+}
+
+/// Print a controller reply in the selected format
+///
+/// `payload` is the parser for the JSON data. The human format shows the
+/// reply under a heading.
+pub fn response(
+    cli: &Cli,
+    command: &str,
+    payload: &ParsedPayload,
+    response: &str,
+    icon: &str,
+    title: &str,
+) -> Result<(), PowerCliError> {
+    response_with(cli, command, payload, response, icon, title, |_| None)
+}
+
+/// Print the reply to a set operation in the selected format
+///
+/// A reply saying the target was already in the requested state is noted
+/// in the heading, and the JSON data carries `changed: false`.
+pub fn state_change(
+    cli: &Cli,
+    command: &str,
+    response: &str,
+    icon: &str,
+    title: &str,
+) -> Result<(), PowerCliError> {
+    let payload = &ParsedPayload::StateChange;
+    response_with(cli, command, payload, response, icon, title, |style| {
+        let change = ResponseParser::parse_state_change(response);
+        (!change.changed).then(|| {
+            let title = format!("{} (already in requested state)", title);
+            super::titled(style, icon, &title, response)
+        })
+    })
+}
+
+/// Like [`state_change`] for a rail, switched on after the rails it depends on
+///
+/// The JSON data names the rail as `rail`. The rails switched on first are
+/// listed ahead of the reply, and in the JSON data as `enabled_first`.
+pub fn rail_change(
+    cli: &Cli,
+    command: &str,
+    rail: Rail,
+    response: &str,
+    icon: &str,
+    title: &str,
+    enabled_first: &[(PowerRail, String)],
+) -> Result<(), PowerCliError> {
+    if cli.quiet {
+        return Ok(());
+    }
+    match cli.format {
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            let (parsed, diagnostics) =
+                diagnostics::collect(|| ResponseParser::parse_state_change(response));
+            let change = json::StateChangeJson {
+                enabled_first: enabled_first.iter().map(|(rail, _)| *rail).collect(),
+                rail: Some(rail),
+                ..parsed
+            };
+            let mut json_response = JsonResponse::success(command, serde_json::to_value(change)?)
+                .with_raw(response, &cli.raw_options());
+            if cli.explains_parse() {
+                json_response.parse_diagnostics = Some(diagnostics);
+            }
+            json(cli, &json_response)?;
+            flush_if_line_buffered(cli);
+            Ok(())
+        }
+        OutputFormat::Human if !enabled_first.is_empty() => {
+            super::print(&super::rail_prerequisites(
+                &cli.output_style(),
+                enabled_first,
+            ));
+            state_change(cli, command, response, icon, title)
+        }
+        _ => state_change(cli, command, response, icon, title),
+    }
+}
+
+/// Like [`response`], with the human format rendered from the parsed reply
+///
+/// `render` returns `None` for a reply it does not recognize, which is then
+/// shown under the heading as it came.
+pub fn response_with(
+    cli: &Cli,
+    command: &str,
+    payload: &ParsedPayload,
+    response: &str,
+    icon: &str,
+    title: &str,
+    render: impl FnOnce(&OutputStyle) -> Option<String>,
+) -> Result<(), PowerCliError> {
+    if cli.quiet {
+        return Ok(());
+    }
+
+    match cli.format {
+        OutputFormat::Human => {
+            let style = cli.output_style();
+            let text =
+                render(&style).unwrap_or_else(|| super::titled(&style, icon, title, response));
+            super::print(&text);
+            if cli.explain_parse {
+                let (_, diagnostics) =
+                    diagnostics::collect(|| CommandOutput::from_response(payload, response));
+                if let Some(text) = super::parse_diagnostics(&style, &diagnostics) {
+                    super::print(&text);
+                }
+            }
+        }
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            let (output, diagnostics) =
+                diagnostics::collect(|| CommandOutput::from_response(payload, response));
+            let json_data = serde_json::to_value(output)?;
+
+            // raw_response keeps the reply byte for byte, progress rewrites
+            // included, as far as --raw allows
+            let mut json_response =
+                JsonResponse::success(command, json_data).with_raw(response, &cli.raw_options());
+            if cli.explains_parse() {
+                json_response.parse_diagnostics = Some(diagnostics);
+            }
+            json(cli, &json_response)?;
+        }
+        OutputFormat::Csv if *payload == ParsedPayload::RailDefaults => {
+            let defaults = ResponseParser::parse_rail_defaults(response);
+            let state = |value: Option<bool>| match value {
+                Some(true) => "on",
+                Some(false) => "off",
+                None => "",
+            };
+            super::print("timestamp,command,status,pmic,wifi,disp,saved_in_flash");
+            super::print(&format!(
+                "{},{},success,{},{},{},{}",
+                chrono::Utc::now().to_rfc3339(),
+                command,
+                state(defaults.pmic),
+                state(defaults.wifi),
+                state(defaults.disp),
+                defaults.saved_in_flash
+            ));
+        }
+        OutputFormat::Csv | OutputFormat::Prometheus => {
+            // CSV format - simplified implementation
+            // Values are formatted with Rust's std formatting, which never
+            // localizes numbers, so the output is stable regardless of host locale
+            super::print("timestamp,command,status,response");
+            super::print(&format!(
+                "{},{},success,\"{}\"",
+                chrono::Utc::now().to_rfc3339(),
+                command,
+                progress::collapse(response).replace("\"", "\"\"")
+            ));
+        }
+    }
+
+    flush_if_line_buffered(cli);
+    Ok(())
+}
+
+/// Print an RTC calibration in the selected format
+pub fn rtc_calibration(
+    cli: &Cli,
+    command: &str,
+    calibration: &RtcCalibration,
+) -> Result<(), PowerCliError> {
+    if cli.quiet {
+        return Ok(());
+    }
+    result(cli, command, calibration, |style| {
+        super::rtc_calibration(style, calibration)
+    })
+}
+
+/// Print serial latency statistics in the selected format
+pub fn latency(cli: &Cli, stats: &LatencyStats) -> Result<(), PowerCliError> {
+    match cli.format {
+        OutputFormat::Human => super::print(&super::latency(&cli.output_style(), stats)),
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            let json_response = JsonResponse::success("latency", serde_json::to_value(stats)?);
+            json(cli, &json_response)?;
+        }
+        OutputFormat::Csv => {
+            super::print("min_ms,max_ms,avg_ms,std_dev_ms");
+            super::print(&format!(
+                "{},{},{:.3},{:.3}",
+                stats.min_ms, stats.max_ms, stats.avg_ms, stats.std_dev_ms
+            ));
+        }
+        OutputFormat::Prometheus => {
+            let mut lines = vec![
+                "# HELP eink_serial_rtt_ms Serial round-trip time to the PMU shell in milliseconds"
+                    .to_string(),
+                "# TYPE eink_serial_rtt_ms summary".to_string(),
+            ];
+            for q in [0.5, 0.9, 0.99] {
+                lines.push(format!(
+                    "eink_serial_rtt_ms{{quantile=\"{}\"}} {:.3}",
+                    q,
+                    stats.quantile_ms(q)
+                ));
+            }
+            lines.push(format!(
+                "eink_serial_rtt_ms_sum {:.3}",
+                stats.samples_ms.iter().sum::<f64>()
+            ));
+            lines.push(format!(
+                "eink_serial_rtt_ms_count {}",
+                stats.samples_ms.len()
+            ));
+            super::print(&lines.join("\n"));
+        }
+    }
+    Ok(())
+}
+
+/// Print a battery health check in the selected format
+pub fn battery_health(cli: &Cli, health: &json::BatteryHealthJson) -> Result<(), PowerCliError> {
+    match cli.format {
+        OutputFormat::Human => super::print(&super::battery_health(&cli.output_style(), health)),
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            let json_response =
+                JsonResponse::success("pm battery_check", serde_json::to_value(health)?);
+            json(cli, &json_response)?;
+        }
+        OutputFormat::Csv | OutputFormat::Prometheus => {
+            let field = |value: Option<String>| value.unwrap_or_default();
+            super::print("timestamp,result,internal_resistance_mohm,loaded_voltage_mv,unloaded_voltage_mv,verdict");
+            super::print(&format!(
+                "{},\"{}\",{},{},{},{}",
+                chrono::Utc::now().to_rfc3339(),
+                field(health.result.clone()).replace('"', "\"\""),
+                field(health.internal_resistance_mohm.map(|v| v.to_string())),
+                field(health.loaded_voltage_mv.map(|v| v.to_string())),
+                field(health.unloaded_voltage_mv.map(|v| v.to_string())),
+                health.verdict.map_or("", |v| v.as_str())
+            ));
+        }
+    }
+    flush_if_line_buffered(cli);
+    Ok(())
+}
+
+/// Print the CSV header of `monitor`
+pub fn monitor_header(cli: &Cli) {
+    if matches!(cli.format, OutputFormat::Csv) {
+        super::print("timestamp,voltage_mv,current_ma,adc_mode,source,charging,charger");
+    }
+}
+
+/// Print one monitor sample as a single line in the selected format
+pub fn monitor_sample(cli: &Cli, sample: &json::MonitorSampleJson) -> Result<(), PowerCliError> {
+    let timestamp = chrono::Local::now();
+    let measurement = &sample.measurement;
+    match cli.format {
+        OutputFormat::Human => {
+            super::print(&super::monitor_sample(
+                &cli.output_style(),
+                sample,
+                timestamp,
+            ));
+        }
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            let json_response = JsonResponse::success("monitor", serde_json::to_value(sample)?);
+            record(cli, RecordType::Sample, &json_response)?;
+        }
+        OutputFormat::Prometheus => {
+            if let Some(voltage) = measurement.voltage_mv {
+                super::print(&format!("eink_battery_voltage_mv {}", voltage));
+            }
+            if let Some(current) = measurement.current_ma {
+                super::print(&format!("eink_battery_current_ma {}", current));
+            }
+            if let Some(charger) = &sample.charger {
+                super::print(&format!(
+                    "eink_charger_state{{state=\"{}\"}} 1\neink_charger_conflicts {}",
+                    charger.state.as_str(),
+                    charger.conflicts.len()
+                ));
+            }
+        }
+        OutputFormat::Csv => {
+            let field = |v: Option<String>| v.unwrap_or_default();
+            super::print(&format!(
+                "{},{},{},{},{},{},{}",
+                timestamp.to_rfc3339(),
+                field(measurement.voltage_mv.map(|v| v.to_string())),
+                field(measurement.current_ma.map(|v| v.to_string())),
+                field(measurement.adc_mode.clone()),
+                measurement.source,
+                sample.charging,
+                field(
+                    sample
+                        .charger
+                        .as_ref()
+                        .map(|c| c.state.as_str().to_string())
+                )
+            ));
+        }
+    }
+    flush_if_line_buffered(cli);
+    Ok(())
+}
+
+/// Print the event line for a charging-state change; human format only
+pub fn charging_transition(
+    cli: &Cli,
+    transition: &ChargingTransition,
+    measurement: &json::MeasurementJson,
+) {
+    if matches!(cli.format, OutputFormat::Human) {
+        super::print(&super::charging_transition(
+            &cli.output_style(),
+            transition,
+            measurement,
+        ));
+    }
+}
+
+/// Note the sample format `monitor --follow` selected; human format only
+pub fn sample_format(cli: &Cli, format: &str) {
+    if matches!(cli.format, OutputFormat::Human) {
+        super::print(
+            &cli.output_style()
+                .prefixed("🔎", &format!("Monitor samples in the {} format", format)),
+        );
+    }
+}
+
+/// Print a console line that is not a sample on stderr
+pub fn unparsed_line(cli: &Cli, unparsed: &UnparsedLine) {
+    eprintln!("{}", super::unparsed_line(&cli.output_style(), unparsed));
+}
+
+/// Report a PMU reset detected during monitoring
+pub fn reboot_event(cli: &Cli, event: &RebootEvent) -> Result<(), PowerCliError> {
+    match cli.format {
+        OutputFormat::Human => super::print(&super::reboot_event(&cli.output_style(), event)),
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            let json_response =
+                JsonResponse::success("monitor reboot", serde_json::to_value(event)?);
+            record(cli, RecordType::Event, &json_response)?;
+        }
+        // Counted in the summary; the warning on stderr explains it
+        OutputFormat::Prometheus | OutputFormat::Csv => {}
+    }
+    flush_if_line_buffered(cli);
+    Ok(())
+}
+
+/// Print the end-of-session monitor summary
+pub fn monitor_summary(cli: &Cli, summary: &json::MonitorSummaryJson) -> Result<(), PowerCliError> {
+    match cli.format {
+        OutputFormat::Human => super::print(&super::monitor_summary(&cli.output_style(), summary)),
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            let json_response =
+                JsonResponse::success("monitor summary", serde_json::to_value(summary)?);
+            json(cli, &json_response)?;
+        }
+        OutputFormat::Prometheus => {
+            super::print(&format!(
+                "eink_battery_charging_transitions_total {}\neink_pmu_reboots_total {}\n{}",
+                summary.charging_transitions,
+                summary.reboots,
+                link_metrics(&summary.link)
+            ));
+        }
+        // CSV output is one row per sample
+        OutputFormat::Csv => {}
+    }
+    flush_if_line_buffered(cli);
+    Ok(())
+}
+
+/// Print the per-step results of a macro run in the selected format
+///
+/// CSV output is one row per step, Prometheus output the overall status
+/// and a status per step.
+pub fn macro_report(cli: &Cli, report: &MacroReport) -> Result<(), PowerCliError> {
+    match cli.format {
+        OutputFormat::Csv => {
+            let quoted = |text: &str| format!("\"{}\"", text.replace('"', "\"\""));
+            super::print("macro,step,command,status,duration_ms,error");
+            for (index, step) in report.steps.iter().enumerate() {
+                super::print(&format!(
+                    "{},{},{},{},{},{}",
+                    quoted(&report.name),
+                    index + 1,
+                    quoted(&step.command),
+                    step.status.as_str(),
+                    step.duration_ms.map_or(String::new(), |ms| ms.to_string()),
+                    quoted(step.error.as_deref().unwrap_or_default())
+                ));
+            }
+        }
+        OutputFormat::Prometheus => {
+            let mut lines = vec![
+                "# HELP eink_macro_success Whether every step of the macro succeeded".to_string(),
+                "# TYPE eink_macro_success gauge".to_string(),
+                format!(
+                    "eink_macro_success{{macro=\"{}\"}} {}",
+                    report.name, report.success as u8
+                ),
+                "# HELP eink_macro_step_success Whether the macro step ran and succeeded"
+                    .to_string(),
+                "# TYPE eink_macro_step_success gauge".to_string(),
+            ];
+            lines.extend(report.steps.iter().enumerate().map(|(index, step)| {
+                format!(
+                    "eink_macro_step_success{{macro=\"{}\",step=\"{}\",status=\"{}\"}} {}",
+                    report.name,
+                    index + 1,
+                    step.status.as_str(),
+                    (step.status == MacroStepStatus::Ok) as u8
+                )
+            }));
+            super::print(&lines.join("\n"));
+        }
+        _ => {
+            return result(cli, "run", report, |style| {
+                super::macro_report(style, report)
+            })
+        }
+    }
+    flush_if_line_buffered(cli);
+    Ok(())
+}
+
+/// Print a fleet inventory in the selected format
+pub fn fleet_inventory(cli: &Cli, inventory: &FleetInventory) -> Result<(), PowerCliError> {
+    if !matches!(cli.format, OutputFormat::Csv) {
+        return result(cli, "fleet scan", inventory, |style| {
+            super::fleet_inventory(style, inventory)
+        });
+    }
+    let quoted = |text: &str| format!("\"{}\"", text.replace('"', "\"\""));
+    super::print(
+        "port,usb_serial,usb_id,status,firmware_version,serial_number,battery_mv,rails,error",
+    );
+    for entry in &inventory.ports {
+        let rails: Vec<String> = entry
+            .rails
+            .iter()
+            .map(|(rail, on)| format!("{}={}", rail.as_str(), if *on { "on" } else { "off" }))
+            .collect();
+        let error = entry.error.iter().chain(&entry.errors);
+        super::print(&format!(
+            "{},{},{},{},{},{},{},{},{}",
+            quoted(&entry.port),
+            quoted(entry.usb_serial.as_deref().unwrap_or_default()),
+            entry.usb_id.as_deref().unwrap_or_default(),
+            entry.status.as_str(),
+            quoted(entry.firmware_version.as_deref().unwrap_or_default()),
+            quoted(entry.serial_number.as_deref().unwrap_or_default()),
+            entry.battery_mv.map_or(String::new(), |mv| mv.to_string()),
+            rails.join(" "),
+            quoted(&error.cloned().collect::<Vec<_>>().join("; "))
+        ));
+    }
+    flush_if_line_buffered(cli);
+    Ok(())
+}
+
+/// Print the link statistics of `stats`; `reset` notes that they were
+/// cleared after printing
+pub fn link_stats(cli: &Cli, stats: &ConnectionStats, reset: bool) -> Result<(), PowerCliError> {
+    if matches!(cli.format, OutputFormat::Prometheus) {
+        super::print(&link_metrics(stats));
+        return Ok(());
+    }
+    let command = if reset { "stats reset" } else { "stats" };
+    result(cli, command, stats, |style| {
+        let text = super::link_stats(style, &cli.device, stats);
+        match reset {
+            true => format!("{}\n{}", text, super::link_stats_reset(style, &cli.device)),
+            false => text,
+        }
+    })
+}
+
+/// Prometheus counters of the serial link
+fn link_metrics(stats: &ConnectionStats) -> String {
+    let mut lines = vec![
+        format!("eink_serial_bytes_sent_total {}", stats.bytes_sent),
+        format!("eink_serial_bytes_received_total {}", stats.bytes_received),
+        format!("eink_serial_commands_total {}", stats.commands),
+        format!("eink_serial_timeouts_total {}", stats.timeouts),
+        format!("eink_serial_retries_total {}", stats.retries),
+        format!("eink_serial_reconnects_total {}", stats.reconnects),
+    ];
+    lines.extend(stats.families.iter().map(|(root, count)| {
+        format!(
+            "eink_serial_commands_by_root_total{{root=\"{}\"}} {}",
+            root, count
+        )
+    }));
+    lines.join("\n")
+}
+
+/// Report `nfc transfer` progress after a mailbox
+///
+/// Human output goes to stderr, leaving stdout to the result; NDJSON output
+/// gets a document per mailbox.
+pub fn transfer_progress(cli: &Cli, progress: &TransferProgress) -> Result<(), PowerCliError> {
+    match cli.format {
+        OutputFormat::Human if !cli.quiet => {
+            eprintln!(
+                "{}",
+                super::transfer_progress(&cli.output_style(), progress)
+            );
+        }
+        OutputFormat::Ndjson | OutputFormat::JsonLines if !cli.quiet => {
+            let json_response =
+                JsonResponse::success("nfc transfer progress", serde_json::to_value(progress)?);
+            record(cli, RecordType::Progress, &json_response)?;
+            flush_if_line_buffered(cli);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Print the CSV header of `battery read --watch`
+pub fn battery_watch_header(cli: &Cli) {
+    if matches!(cli.format, OutputFormat::Csv) {
+        super::print("timestamp,voltage_mv,current_ma,charge_mah,power_mw,soc_percent");
+    }
+}
+
+/// Print one `battery read --watch` sample
+///
+/// Human output on a terminal redraws a single line in place; anywhere else
+/// each sample gets a line of its own.
+pub fn battery_watch_sample(
+    cli: &Cli,
+    command: &str,
+    sample: &json::BatteryWatchSampleJson,
+    voltages: &VoltageHistory,
+) -> Result<(), PowerCliError> {
+    let battery = &sample.battery;
+    let style = cli.output_style();
+    match cli.format {
+        OutputFormat::Human if std::io::stdout().is_terminal() && !super::sink::capturing() => {
+            super::live::show(&super::battery_live(&style, sample, voltages));
+        }
+        OutputFormat::Human => super::print(&super::battery_watch_line(&style, sample)),
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            let json_response = JsonResponse::success(command, serde_json::to_value(sample)?);
+            record(cli, RecordType::Sample, &json_response)?;
+        }
+        OutputFormat::Prometheus => {
+            let gauges = [
+                ("eink_battery_voltage_mv", battery.voltage_mv.map(f64::from)),
+                ("eink_battery_current_ma", battery.current_ma.map(f64::from)),
+                ("eink_battery_charge_mah", battery.charge_mah.map(f64::from)),
+                ("eink_battery_power_mw", battery.power_mw.map(f64::from)),
+                ("eink_battery_soc_percent", sample.soc_percent),
+            ];
+            for (name, value) in gauges {
+                if let Some(value) = value {
+                    super::print(&format!("{} {}", name, value));
+                }
+            }
+        }
+        OutputFormat::Csv => {
+            let field = |v: Option<String>| v.unwrap_or_default();
+            super::print(&format!(
+                "{},{},{},{},{},{}",
+                sample.timestamp.to_rfc3339(),
+                field(battery.voltage_mv.map(|v| v.to_string())),
+                field(battery.current_ma.map(|v| v.to_string())),
+                field(battery.charge_mah.map(|v| v.to_string())),
+                field(battery.power_mw.map(|v| v.to_string())),
+                field(sample.soc_percent.map(|v| format!("{:.1}", v)))
+            ));
+        }
+    }
+    flush_if_line_buffered(cli);
+    Ok(())
+}
+
+/// Print the end-of-session `battery read --watch` summary
+pub fn battery_watch_summary(
+    cli: &Cli,
+    summary: &json::BatteryWatchSummaryJson,
+) -> Result<(), PowerCliError> {
+    match cli.format {
+        OutputFormat::Human => {
+            super::print(&super::battery_watch_summary(&cli.output_style(), summary))
+        }
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            let json_response =
+                JsonResponse::success("battery watch summary", serde_json::to_value(summary)?);
+            json(cli, &json_response)?;
+        }
+        OutputFormat::Prometheus => {
+            super::print(&format!(
+                "eink_battery_watch_samples_total {}",
+                summary.samples
+            ));
+        }
+        // CSV output is one row per sample
+        OutputFormat::Csv => {}
+    }
+    flush_if_line_buffered(cli);
+    Ok(())
+}
+
+/// Print the `ltc2959 read --continuous` summary
+pub fn ltc2959_continuous_summary(
+    cli: &Cli,
+    result: &ContinuousReadJson,
+) -> Result<(), PowerCliError> {
+    match cli.format {
+        OutputFormat::Human => {
+            super::print(&super::ltc2959_continuous(&cli.output_style(), result))
+        }
+        OutputFormat::Json | OutputFormat::Ndjson | OutputFormat::JsonLines => {
+            let json_response =
+                JsonResponse::success("ltc2959 read summary", serde_json::to_value(result)?);
+            json(cli, &json_response)?;
+        }
+        OutputFormat::Prometheus => {
+            super::print(&format!(
+                "eink_ltc2959_read_samples_total {}",
+                result.summary.samples
+            ));
+            if let Some(jitter_ms) = result.jitter_ms {
+                super::print(&format!("eink_ltc2959_read_jitter_ms {}", jitter_ms));
+            }
+        }
+        // CSV output is one row per sample
+        OutputFormat::Csv => {}
+    }
+    flush_if_line_buffered(cli);
+    Ok(())
+}
@@ -0,0 +1,94 @@
+/*
+ * E-ink Power CLI - Live Status Line
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! One status line on the terminal, redrawn in place
+//!
+//! [`show`] returns the cursor to the start of the line and overwrites it,
+//! so a bench session shows a single updating reading instead of scrolling.
+//! Log records go to stderr on the same terminal; [`LiveLogger`] clears the
+//! line before a record and draws it again below, so a warning mid-session
+//! ends up on a line of its own instead of spliced into the reading.
+
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Clear from the cursor to the end of the line
+const CLEAR_LINE: &str = "\x1b[K";
+
+/// The line currently on screen, if any
+static LINE: Mutex<Option<String>> = Mutex::new(None);
+
+fn draw(line: &str) {
+    let mut stdout = std::io::stdout().lock();
+    let _ = write!(stdout, "\r{}{}", line, CLEAR_LINE);
+    let _ = stdout.flush();
+}
+
+fn erase() {
+    let mut stdout = std::io::stdout().lock();
+    let _ = write!(stdout, "\r{}", CLEAR_LINE);
+    let _ = stdout.flush();
+}
+
+/// Replace the status line with `line`
+pub fn show(line: &str) {
+    let mut current = LINE.lock().unwrap_or_else(|e| e.into_inner());
+    draw(line);
+    *current = Some(line.to_string());
+}
+
+/// Leave the status line on screen and move below it
+///
+/// Does nothing if no line is shown.
+pub fn finish() {
+    let mut current = LINE.lock().unwrap_or_else(|e| e.into_inner());
+    if current.take().is_some() {
+        let mut stdout = std::io::stdout().lock();
+        let _ = writeln!(stdout);
+        let _ = stdout.flush();
+    }
+}
+
+/// `env_logger` output that steps around the status line
+pub struct LiveLogger {
+    inner: env_logger::Logger,
+}
+
+impl log::Log for LiveLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.inner.matches(record) {
+            return;
+        }
+        let current = LINE.lock().unwrap_or_else(|e| e.into_inner());
+        if current.is_some() {
+            erase();
+        }
+        self.inner.log(record);
+        self.inner.flush();
+        if let Some(line) = current.as_deref() {
+            draw(line);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install `builder`'s logger wrapped in a [`LiveLogger`]
+///
+/// Takes the place of `builder.init()`.
+pub fn init_logger(mut builder: env_logger::Builder) {
+    let inner = builder.build();
+    let max_level = inner.filter();
+    if log::set_boxed_logger(Box::new(LiveLogger { inner })).is_ok() {
+        log::set_max_level(max_level);
+    }
+}
@@ -0,0 +1,1896 @@
+/*
+ * E-ink Power CLI - Human Output Rendering
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Text shown to users in the human output format
+//!
+//! Every function here builds the text for one kind of result from its typed
+//! struct and returns it; nothing is printed until [`print()`]. [`emit`]
+//! picks between this text and the machine-readable formats.
+//!
+//! [`OutputStyle`] decides how the text is decorated: emoji or plain ASCII
+//! markers, ANSI color, and the width lines are cut to. Results parsed from
+//! a controller reply return `None` when the reply held none of their
+//! values, so the caller can show the reply as it came instead.
+
+pub mod emit;
+pub mod live;
+pub mod sink;
+
+use crate::audit::PowerAudit;
+use crate::battery_log::ExportReport;
+use crate::cli::deprecations::Deprecation;
+use crate::cli::examples::Example;
+use crate::firmware::dfu::{DfuCancelReport, DfuModeReport};
+use crate::firmware::slots::{self, FirmwareImage, FirmwareInfo};
+use crate::firmware::FirmwareImageInfo;
+use crate::fleet::{FleetChangeKind, FleetDiff, FleetInventory, ProbeStatus};
+use crate::hil::{HilReport, HilStepResult, HilStepStatus};
+use crate::history::HistoryEntry;
+use crate::json::coverage::{self, CoverageReport};
+use crate::json::diagnostics::{ParseDiagnostic, ParseOutcome};
+use crate::json::progress;
+use crate::json::samples::UnparsedLine;
+use crate::json::{
+    BatteryHealthJson, BatteryHealthSamplesJson, BatteryJson, BatterySamplesJson,
+    BatteryWatchSampleJson, BatteryWatchSummaryJson, GpioJson, MeasurementJson, MonitorSampleJson,
+    MonitorSummaryJson, NfcJson, RtcStatusJson, SampleStatsJson, SramJson,
+};
+use crate::macros::{MacroListing, MacroReport};
+use crate::power::battery::{ChargingState, ChargingTransition, VoltageHistory};
+use crate::power::control::PowerStats;
+use crate::power::coulomb::ChargeResetReport;
+use crate::power::factory_reset::FactoryResetReport;
+use crate::power::gpio::{
+    GpioConfigReport, GpioOpStatus, GpioScriptMode, GpioScriptReport, PinNames,
+};
+use crate::power::identity::DeviceIdentity;
+use crate::power::ltc2959::ContinuousReadJson;
+use crate::power::passthrough::{TransferDirection, TransferProgress, TransferReport};
+use crate::power::rails::PowerRail;
+use crate::power::reboot::{self, RebootEvent};
+use crate::power::rtc::RtcCalibration;
+use crate::power::stats::{PowerStatsDerived, PowerStatsReport};
+use crate::power::timeref::TimeRefReport;
+use crate::power::wake::WakeMask;
+use crate::provision::ProvisionRecord;
+use crate::schedule::{ScheduleStatus, ScheduledCommand};
+use crate::serial::ConnectionStats;
+use crate::serial::{BaudChange, LatencyStats};
+use crate::setup::SetupReport;
+use crate::snapshot::{Section, Snapshot};
+use crate::update::{UpdateReport, UpdateStatus};
+use chrono::{DateTime, Local, Utc};
+use std::fmt::Display;
+use std::io::Write;
+use std::path::Path;
+
+/// ASCII stand-ins for status markers; other icons are dropped without emoji
+const ASCII_ICONS: &[(&str, &str)] = &[
+    ("✅", "[OK]"),
+    ("❌", "[FAIL]"),
+    ("⚠️", "[WARN]"),
+    ("➖", "[--]"),
+];
+
+/// ANSI colors of the status markers
+const STATUS_COLORS: &[(&str, &str)] = &[("✅", "32"), ("❌", "31"), ("⚠️", "33")];
+
+/// ANSI bold, used for headings
+const BOLD: &str = "1";
+
+/// Indent of the lines under a heading
+const INDENT: &str = "   ";
+
+/// How human output is decorated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputStyle {
+    /// Emoji icons; otherwise ASCII markers for statuses and no icon elsewhere
+    pub emoji: bool,
+    /// ANSI color for headings and status markers
+    pub color: bool,
+    /// Lines longer than this are cut short; `None` leaves them whole
+    pub width: Option<usize>,
+}
+
+impl Default for OutputStyle {
+    fn default() -> Self {
+        Self {
+            emoji: true,
+            color: false,
+            width: None,
+        }
+    }
+}
+
+impl OutputStyle {
+    /// Plain ASCII without color
+    #[allow(dead_code)] // Used by tests
+    pub const fn ascii() -> Self {
+        Self {
+            emoji: false,
+            color: false,
+            width: None,
+        }
+    }
+
+    /// `icon` as shown in this style, possibly empty
+    pub fn icon(&self, icon: &str) -> String {
+        let text = if self.emoji {
+            icon
+        } else {
+            lookup(ASCII_ICONS, icon).unwrap_or("")
+        };
+        match lookup(STATUS_COLORS, icon) {
+            Some(code) if self.color && !text.is_empty() => paint(code, text),
+            _ => text.to_string(),
+        }
+    }
+
+    /// `🔋 Battery Measurements:`
+    pub fn heading(&self, icon: &str, title: &str) -> String {
+        let title = format!("{}:", title);
+        let title = if self.color {
+            paint(BOLD, &title)
+        } else {
+            title
+        };
+        self.prefixed(icon, &title)
+    }
+
+    /// `text` after the icon, or alone if the style drops the icon
+    pub fn prefixed(&self, icon: &str, text: &str) -> String {
+        match self.icon(icon) {
+            icon if icon.is_empty() => text.to_string(),
+            icon => format!("{} {}", icon, text),
+        }
+    }
+
+    /// `line` cut to the width, ending in `...` if anything was dropped
+    pub fn fit(&self, line: &str) -> String {
+        match self.width {
+            Some(width) if line.chars().count() > width => {
+                let kept: String = line.chars().take(width.saturating_sub(3)).collect();
+                format!("{}...", kept)
+            }
+            _ => line.to_string(),
+        }
+    }
+
+    /// `°C` with emoji, `C` in plain ASCII
+    fn celsius(&self) -> &'static str {
+        if self.emoji {
+            "°C"
+        } else {
+            "C"
+        }
+    }
+
+    /// `±` with emoji, `+/-` in plain ASCII
+    fn plus_minus(&self) -> &'static str {
+        if self.emoji {
+            "±"
+        } else {
+            "+/-"
+        }
+    }
+}
+
+fn lookup(table: &[(&str, &'static str)], key: &str) -> Option<&'static str> {
+    table.iter().find(|(k, _)| *k == key).map(|(_, v)| *v)
+}
+
+fn paint(code: &str, text: &str) -> String {
+    format!("\x1b[{}m{}\x1b[0m", code, text)
+}
+
+/// Write rendered text to stdout, or to the `--output` capture
+pub fn print(text: &str) {
+    if sink::capturing() {
+        let _ = writeln!(sink::Output, "{}", text);
+    } else {
+        println!("{}", text);
+    }
+}
+
+/// Sparkline levels, lowest first
+const SPARK_LEVELS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const SPARK_LEVELS_ASCII: &[char] = &['_', '.', '-', '~', '=', '+', '*', '#'];
+
+/// Heading followed by `body` as a terminal would show it
+///
+/// `\r` progress rewrites in the body are collapsed to their final state.
+pub fn titled(style: &OutputStyle, icon: &str, title: &str, body: &str) -> String {
+    format!(
+        "{}\n{}",
+        style.heading(icon, title),
+        progress::collapse(body)
+    )
+}
+
+/// Heading followed by indented `Label: value` lines
+///
+/// Rows without a value are left out; `None` if no row has one.
+fn fields(
+    style: &OutputStyle,
+    icon: &str,
+    title: &str,
+    rows: &[(&str, Option<String>)],
+) -> Option<String> {
+    let lines: Vec<String> = rows
+        .iter()
+        .filter_map(|(label, value)| {
+            let value = value.as_ref()?;
+            Some(style.fit(&format!("{}{}: {}", INDENT, label, value)))
+        })
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "{}\n{}",
+        style.heading(icon, title),
+        lines.join("\n")
+    ))
+}
+
+fn with_unit<T: Display>(value: Option<T>, unit: &str) -> Option<String> {
+    value.map(|v| format!("{} {}", v, unit))
+}
+
+fn yes_no(value: Option<bool>) -> Option<String> {
+    value.map(|v| if v { "yes" } else { "no" }.to_string())
+}
+
+/// `battery read`
+pub fn battery(style: &OutputStyle, battery: &BatteryJson) -> Option<String> {
+    fields(
+        style,
+        "🔋",
+        "Battery Measurements",
+        &[
+            ("Voltage", with_unit(battery.voltage_mv, "mV")),
+            ("Current", with_unit(battery.current_ma, "mA")),
+            ("Charge", with_unit(battery.charge_mah, "mAh")),
+            ("Power", with_unit(battery.power_mw, "mW")),
+            (
+                "Temperature",
+                battery
+                    .temperature_c
+                    .map(|c| format!("{:.1} {}", c, style.celsius())),
+            ),
+        ],
+    )
+}
+
+/// `battery read --samples`: the medians with their mean and range
+pub fn battery_samples(style: &OutputStyle, samples: &BatterySamplesJson) -> String {
+    let stats = |stats: Option<SampleStatsJson>, unit: &str| stats.map(|s| sample_stats(&s, unit));
+    let rows = [
+        ("Voltage", stats(samples.voltage_mv, "mV")),
+        ("Current", stats(samples.current_ma, "mA")),
+        ("Charge", stats(samples.charge_mah, "mAh")),
+        ("Power", stats(samples.power_mw, "mW")),
+        ("Temperature", stats(samples.temperature_c, style.celsius())),
+    ];
+    let title = format!(
+        "Battery Measurements ({})",
+        sample_count(samples.samples.len(), samples.failed)
+    );
+    fields(style, "🔋", &title, &rows).unwrap_or_else(|| style.heading("🔋", &title))
+}
+
+/// `pm battery-check --samples`: the median check and the spread
+pub fn battery_health_samples(style: &OutputStyle, checks: &BatteryHealthSamplesJson) -> String {
+    let mut lines = vec![checks.median.format_human()];
+    for (label, stats, unit) in [
+        ("Unloaded Voltage", checks.unloaded_voltage_mv, "mV"),
+        ("Loaded Voltage", checks.loaded_voltage_mv, "mV"),
+        (
+            "Internal Resistance",
+            checks.internal_resistance_mohm,
+            "mOhm",
+        ),
+    ] {
+        if let Some(stats) = stats {
+            lines.push(format!("{} spread: {}", label, sample_stats(&stats, unit)));
+        }
+    }
+    let title = format!(
+        "Battery Health Check ({})",
+        sample_count(checks.samples.len(), checks.failed)
+    );
+    titled(style, "🔋", &title, &lines.join("\n"))
+}
+
+/// `median 3850 mV (mean 3848.2, 3840-3856)`
+fn sample_stats(stats: &SampleStatsJson, unit: &str) -> String {
+    format!(
+        "median {} {} (mean {:.1}, {}-{})",
+        stats.median, unit, stats.mean, stats.min, stats.max
+    )
+}
+
+/// `median of 4 samples, 1 failed`
+fn sample_count(succeeded: usize, failed: u32) -> String {
+    let mut text = format!("median of {} samples", succeeded);
+    if failed > 0 {
+        text.push_str(&format!(", {} failed", failed));
+    }
+    text
+}
+
+/// `power stats`
+pub fn power_stats(style: &OutputStyle, stats: &PowerStats) -> String {
+    let count = |count: Option<u32>| count.map(|n| n.to_string());
+    let rows = [
+        ("Active time", with_unit(stats.active_time_ms, "ms")),
+        ("Sleep count", count(stats.sleep_count)),
+        ("Wake events", count(stats.wake_count)),
+        ("RTC wake events", count(stats.rtc_wake_count)),
+        ("NFC wake events", count(stats.nfc_wake_count)),
+        ("UART wake events", count(stats.uart_wake_count)),
+    ];
+    fields(style, "⚡", "Power Management Statistics", &rows).unwrap_or_default()
+}
+
+/// `power stats`: the counters, then the metrics derived with the uptime
+pub fn power_stats_report(style: &OutputStyle, report: &PowerStatsReport) -> String {
+    let mut parts = vec![power_stats(style, &report.stats)];
+    match &report.derived {
+        Some(derived) if !derived.is_empty() => parts.push(power_stats_derived(style, derived)),
+        Some(_) => {}
+        None => parts.push(format!("{}No uptime, so no rates or time split", INDENT)),
+    }
+    parts.join("\n")
+}
+
+/// Wakes per hour and share by source as a table, then the time split
+pub fn power_stats_derived(style: &OutputStyle, derived: &PowerStatsDerived) -> String {
+    let window = reboot::format_uptime(derived.window_ms);
+    let title = match derived.since_boot {
+        true => format!("Derived Power Statistics (since boot, {})", window),
+        false => format!("Derived Power Statistics (over {})", window),
+    };
+    let mut lines = vec![style.heading("📈", &title)];
+    let rate = |rate: Option<f64>| rate.map_or("-".to_string(), |r| format!("{:.2}", r));
+    if !derived.wakes_per_hour_by_source.is_empty() || derived.wakes_per_hour.is_some() {
+        lines.push(format!(
+            "{}{:<8} {:>8} {:>7}",
+            INDENT, "Source", "Wakes/h", "Share"
+        ));
+        for (source, per_hour) in &derived.wakes_per_hour_by_source {
+            let share = derived
+                .wake_share_percent
+                .get(source)
+                .map_or("-".to_string(), |share| format!("{:.1}%", share));
+            lines.push(format!(
+                "{}{:<8} {:>8} {:>7}",
+                INDENT,
+                source.as_str().to_uppercase(),
+                rate(Some(*per_hour)),
+                share
+            ));
+        }
+        lines.push(format!(
+            "{}{:<8} {:>8}",
+            INDENT,
+            "All",
+            rate(derived.wakes_per_hour)
+        ));
+    }
+    if let (Some(asleep), Some(active)) = (derived.asleep_percent, derived.active_percent) {
+        lines.push(format!(
+            "{}Asleep: {:.1}% (active {:.1}%)",
+            INDENT, asleep, active
+        ));
+    }
+    if let Some(ms) = derived.average_sleep_ms {
+        lines.push(format!("{}Average sleep: {:.1} s", INDENT, ms / 1000.0));
+    }
+    if derived.counter_wrapped {
+        lines.push(format!("{}Counters wrapped around in this window", INDENT));
+    }
+    lines
+        .iter()
+        .map(|line| style.fit(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `gpio get`
+pub fn gpio(style: &OutputStyle, gpio: &GpioJson) -> Option<String> {
+    fields(
+        style,
+        "📌",
+        &format!("GPIO {}{}", gpio.port, gpio.pin),
+        &[
+            ("Value", gpio.value.map(|v| v.to_string())),
+            ("Level", gpio.state.clone()),
+            ("Direction", gpio.direction.clone()),
+            ("Pull", gpio.pull.clone()),
+            ("Pin", gpio.names.as_ref().map(PinNames::describe)),
+        ],
+    )
+}
+
+/// `firmware list`
+pub fn firmware_images(style: &OutputStyle, images: &[FirmwareImage]) -> Option<String> {
+    if images.is_empty() {
+        return None;
+    }
+    let table: Vec<String> = slots::slot_table(images)
+        .iter()
+        .map(|line| style.fit(&format!("{}{}", INDENT, line)))
+        .collect();
+    Some(format!(
+        "{}\n{}",
+        style.heading("📋", "Firmware Images"),
+        table.join("\n")
+    ))
+}
+
+/// `firmware info`
+pub fn firmware_info(style: &OutputStyle, info: &FirmwareInfo) -> String {
+    titled(style, "ℹ️", "Firmware Information", &info.format_human())
+}
+
+/// `firmware analyze`
+pub fn firmware_analysis(style: &OutputStyle, file: &Path, info: &FirmwareImageInfo) -> String {
+    format!(
+        "{}\n{}",
+        style.prefixed("🔍", &format!("Firmware Image: {}", file.display())),
+        info.format_human()
+    )
+}
+
+/// `nfc status`
+pub fn nfc_status(style: &OutputStyle, nfc: &NfcJson) -> Option<String> {
+    fields(
+        style,
+        "📡",
+        "NFC Status",
+        &[
+            ("Status register", nfc.status_register.clone()),
+            ("RF field", nfc.rf_field.clone()),
+            ("NFC active", yes_no(nfc.nfc_active)),
+            ("I2C ready", yes_no(nfc.i2c_ready)),
+            ("EEPROM", nfc.eeprom_status.clone()),
+            ("SRAM", nfc.sram_status.clone()),
+        ],
+    )
+}
+
+/// `nfc sram-read` and `nfc sram-write`: the bytes, 16 to a row
+pub fn sram(style: &OutputStyle, title: &str, sram: &SramJson) -> String {
+    let rows: Vec<String> = sram
+        .data
+        .as_bytes()
+        .chunks(32)
+        .enumerate()
+        .map(|(row, digits)| {
+            let bytes: Vec<&str> = digits
+                .chunks(2)
+                .map(|pair| std::str::from_utf8(pair).unwrap_or_default())
+                .collect();
+            format!("0x{:02X}: {}", row * 16, bytes.join(" "))
+        })
+        .collect();
+    titled(style, "📦", title, &rows.join("\n"))
+}
+
+/// One progress line of `nfc transfer`
+pub fn transfer_progress(style: &OutputStyle, progress: &TransferProgress) -> String {
+    let mut text = match progress.total {
+        Some(total) => format!("Mailbox {}/{}", progress.mailboxes, total),
+        None => format!("Mailbox {}", progress.mailboxes),
+    };
+    text.push_str(&format!(", {} bytes", progress.bytes));
+    if progress.retries > 0 {
+        text.push_str(&format!(", {} retried after field loss", progress.retries));
+    }
+    style.prefixed("📡", &text)
+}
+
+/// `nfc transfer` once the checksum is verified
+pub fn nfc_transfer(style: &OutputStyle, report: &TransferReport) -> String {
+    let moved = match report.direction {
+        TransferDirection::ToPhone => format!("Sent {} bytes from {}", report.bytes, report.file),
+        TransferDirection::FromPhone => {
+            format!("Received {} bytes into {}", report.bytes, report.file)
+        }
+    };
+    let body = format!(
+        "{}\nMailboxes: {} ({} retried after field loss)\nCRC-32: {} (verified)\nDuration: {:.1} s",
+        moved,
+        report.mailboxes,
+        report.retries,
+        report.crc32,
+        report.duration_ms as f64 / 1000.0
+    );
+    titled(style, "📲", "NFC Transfer", &body)
+}
+
+/// `rtc status`
+///
+/// Only what the controller reported; the fixed wiring details stay in the
+/// JSON output.
+pub fn rtc_status(style: &OutputStyle, rtc: &RtcStatusJson) -> Option<String> {
+    let clock = |status: &Option<String>, events: Option<u32>, what: &str| match (status, events) {
+        (Some(status), Some(n)) => Some(format!("{}, {} {}", status, n, what)),
+        (Some(status), None) => Some(status.clone()),
+        (None, Some(n)) => Some(format!("{} {}", n, what)),
+        (None, None) => None,
+    };
+    fields(
+        style,
+        "🕐",
+        "RTC Status",
+        &[
+            (
+                "Internal RTC (LPTMR)",
+                clock(
+                    &rtc.internal_rtc.status,
+                    rtc.internal_rtc.wake_events,
+                    "wake events",
+                ),
+            ),
+            (
+                "External RTC (PCF2131)",
+                clock(
+                    &rtc.external_rtc.status,
+                    rtc.external_rtc.interrupt_events,
+                    "interrupt events",
+                ),
+            ),
+            (
+                "Interrupt action",
+                rtc.external_rtc.interrupt_action.clone(),
+            ),
+            ("Last wake source", rtc.last_wake_source.clone()),
+        ],
+    )
+}
+
+/// `rtc calibrate` and `rtc calibration`
+pub fn rtc_calibration(style: &OutputStyle, calibration: &RtcCalibration) -> String {
+    titled(style, "🕐", "RTC Calibration", &calibration.format_human())
+}
+
+/// Unit identity from the NFC EEPROM
+pub fn identity(style: &OutputStyle, identity: Option<&DeviceIdentity>) -> String {
+    let body = match identity {
+        Some(identity) => identity.format_human(),
+        None => "Not programmed".to_string(),
+    };
+    titled(style, "🏷️", "Device Identity", &body)
+}
+
+/// Parser outcome of each field, shown after a reply with `--explain-parse`
+///
+/// `None` if the command has no parser.
+pub fn parse_diagnostics(style: &OutputStyle, diagnostics: &[ParseDiagnostic]) -> Option<String> {
+    if diagnostics.is_empty() {
+        return None;
+    }
+    let mut lines = vec![style.prefixed("🔍", "Parse diagnostics:")];
+    for diagnostic in diagnostics {
+        let icon = match diagnostic.outcome {
+            ParseOutcome::Matched => "✅",
+            ParseOutcome::Absent => "➖",
+            ParseOutcome::Unparsed => "⚠️",
+        };
+        let text = match &diagnostic.line {
+            Some(line) => format!("{}: '{}'", diagnostic.field, line),
+            None => format!("{}: no line found", diagnostic.field),
+        };
+        lines.push(format!("{}{}", INDENT, style.prefixed(icon, &text)));
+    }
+    Some(lines.join("\n"))
+}
+
+/// `power sequence`
+pub fn power_sequence(style: &OutputStyle, responses: &[(PowerRail, String)]) -> String {
+    let mut lines = vec![style.heading("⚡", "Power-On Sequence")];
+    for (rail, response) in responses {
+        lines.push(format!("{}: {}", rail.name(), response.trim()));
+    }
+    lines.join("\n")
+}
+
+/// Rails switched on with `--auto-deps` ahead of the one requested
+pub fn rail_prerequisites(style: &OutputStyle, responses: &[(PowerRail, String)]) -> String {
+    let mut lines = vec![style.heading("🔗", "Enabled First")];
+    for (rail, response) in responses {
+        lines.push(format!("{}: {}", rail.name(), response.trim()));
+    }
+    lines.join("\n")
+}
+
+/// Result of configuring one or more GPIO pins
+pub fn gpio_config(style: &OutputStyle, report: &GpioConfigReport) -> String {
+    report
+        .pins
+        .iter()
+        .map(|result| {
+            format!(
+                "{}\n{}\n{}",
+                style.heading(
+                    "📌",
+                    &format!("GPIO {} configured to {}", result.pin_name(), report.mode)
+                ),
+                result.response,
+                result.format_human()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `gpio script`: each pin setting with when it took effect
+pub fn gpio_script(style: &OutputStyle, report: &GpioScriptReport) -> String {
+    let ms = |us: u64| format!("{:.3} ms", us as f64 / 1000.0);
+    let mode = match report.mode {
+        GpioScriptMode::Firmware => "firmware batch",
+        GpioScriptMode::Host => "host timed",
+    };
+    let mut lines = vec![style.heading("📌", &format!("GPIO Script ({})", mode))];
+    for op in &report.ops {
+        let (icon, detail) = match op.status {
+            GpioOpStatus::Ok => {
+                let mut detail = op.at_us.map(|at| format!("at {}", ms(at)));
+                if let Some(gap) = op.gap_us {
+                    detail = detail.map(|at| {
+                        format!("{} (+{}, asked {})", at, ms(gap), ms(op.requested_gap_us))
+                    });
+                }
+                ("✅", detail.unwrap_or_else(|| "ok".to_string()))
+            }
+            GpioOpStatus::Failed => ("❌", op.error.clone().unwrap_or_default()),
+            GpioOpStatus::Skipped => ("➖", "skipped".to_string()),
+            GpioOpStatus::Unconfirmed => ("⚠️", "not confirmed by the firmware".to_string()),
+        };
+        lines.push(style.prefixed(icon, &format!("{} {}", op.op, detail)));
+    }
+    lines.join("\n")
+}
+
+/// `system verify`
+pub fn verify(style: &OutputStyle, version: Option<&str>, violations: &[String]) -> String {
+    let mut lines = vec![
+        style.heading("🔍", "Firmware Verification"),
+        format!("Version: {}", version.unwrap_or("unknown")),
+    ];
+    for violation in violations {
+        lines.push(style.prefixed("❌", violation));
+    }
+    if violations.is_empty() {
+        lines.push(style.prefixed("✅", "Firmware meets release policy"));
+    }
+    lines.join("\n")
+}
+
+/// `system info --expect-version`
+pub fn version_check(
+    style: &OutputStyle,
+    version: Option<&str>,
+    requirement: &semver::VersionReq,
+    ok: bool,
+) -> String {
+    let version = version.unwrap_or("unknown");
+    if ok {
+        style.prefixed("✅", &format!("Firmware {} meets {}", version, requirement))
+    } else {
+        style.prefixed(
+            "❌",
+            &format!("Firmware {} does not meet {}", version, requirement),
+        )
+    }
+}
+
+/// `system set-baud`
+pub fn baud_change(style: &OutputStyle, change: &BaudChange) -> String {
+    titled(style, "⚡", "Console Baud Rate", &change.format_human())
+}
+
+/// `system factory-reset`
+pub fn factory_reset(style: &OutputStyle, report: &FactoryResetReport) -> String {
+    titled(style, "🏭", "Factory Reset", &report.format_human())
+}
+
+/// `provision`
+pub fn provision(style: &OutputStyle, record: &ProvisionRecord) -> String {
+    titled(style, "🏭", "Provisioning", &record.format_human())
+}
+
+/// `run`
+pub fn macro_report(style: &OutputStyle, report: &MacroReport) -> String {
+    titled(style, "▶️", "Macro", &report.format_human())
+}
+
+/// `run --list`
+pub fn macro_listing(style: &OutputStyle, macros: &[MacroListing]) -> String {
+    let mut lines = vec![style.heading("▶️", "Macros")];
+    if macros.is_empty() {
+        lines.push(format!(
+            "{}No [macros] section in the configuration file",
+            INDENT
+        ));
+    }
+    for listing in macros {
+        lines.push(format!("{}{}", INDENT, listing.name));
+        lines.extend(
+            listing
+                .steps
+                .iter()
+                .map(|step| format!("{}{}{}", INDENT, INDENT, step)),
+        );
+    }
+    lines.join("\n")
+}
+
+/// `parse-check`: coverage by command, with the fields not always populated
+pub fn parse_coverage(style: &OutputStyle, report: &CoverageReport) -> String {
+    let mut lines = vec![style.heading(
+        "🧪",
+        &format!("Parse Coverage ({} transcripts)", report.transcripts()),
+    )];
+    let regressed = |command: &str, field: Option<&str>| {
+        report
+            .regressions
+            .iter()
+            .find(|r| r.command == command && r.field.as_deref() == field)
+            .map(|r| format!(" (baseline {})", coverage::percent(Some(r.baseline))))
+            .unwrap_or_default()
+    };
+    for command in &report.commands {
+        lines.push(style.fit(&format!(
+            "{}{}: {} of fields, transcripts: {}{}",
+            INDENT,
+            command.command,
+            coverage::percent(Some(command.coverage)),
+            command.transcripts,
+            regressed(&command.command, None)
+        )));
+        for (field, &share) in command.fields.iter().filter(|(_, &share)| share < 1.0) {
+            lines.push(style.fit(&format!(
+                "{}{}{} {}{}",
+                INDENT,
+                INDENT,
+                field,
+                coverage::percent(Some(share)),
+                regressed(&command.command, Some(field))
+            )));
+        }
+    }
+    for regression in &report.regressions {
+        let known = report
+            .commands
+            .iter()
+            .find(|c| c.command == regression.command)
+            .is_some_and(|c| {
+                regression
+                    .field
+                    .as_ref()
+                    .is_none_or(|field| c.fields.contains_key(field))
+            });
+        if !known {
+            let what = match &regression.field {
+                Some(field) => format!("{} {}", regression.command, field),
+                None => regression.command.clone(),
+            };
+            lines.push(style.fit(&format!(
+                "{}{}: missing (baseline {})",
+                INDENT,
+                what,
+                coverage::percent(Some(regression.baseline))
+            )));
+        }
+    }
+    for panic in &report.panics {
+        lines.push(style.prefixed(
+            "💥",
+            &format!(
+                "{} panicked on {}: {}",
+                panic.command, panic.file, panic.message
+            ),
+        ));
+    }
+    if !report.unmatched.is_empty() {
+        lines.push(style.fit(&format!(
+            "{}No parser for: {}",
+            INDENT,
+            report.unmatched.join(", ")
+        )));
+    }
+    lines.push(match (&report.baseline, report.passed) {
+        (None, true) => format!("{}No baseline compared", INDENT),
+        (Some(_), true) => style.prefixed("✅", "At or above the baseline"),
+        (_, false) => style.prefixed("❌", "Below the baseline or a parser panicked"),
+    });
+    lines.join("\n")
+}
+
+/// `self-update`
+pub fn self_update(style: &OutputStyle, report: &UpdateReport) -> String {
+    let outcome = match report.status {
+        UpdateStatus::UpToDate => style.prefixed(
+            "✅",
+            &format!("{} is the latest release", report.current_version),
+        ),
+        UpdateStatus::Available => style.prefixed(
+            "⬆️",
+            &format!(
+                "Update available: {} -> {}",
+                report.current_version, report.latest_version
+            ),
+        ),
+        UpdateStatus::Updated => style.prefixed(
+            "✅",
+            &format!(
+                "Updated {} -> {}",
+                report.current_version, report.latest_version
+            ),
+        ),
+    };
+    let signature = match report.signature_verified {
+        true => "SHA-256 and minisign signature",
+        false => "SHA-256",
+    };
+    let mut lines = vec![fields(
+        style,
+        "📦",
+        "Self-Update",
+        &[
+            ("Channel", Some(report.channel.clone())),
+            ("Target", Some(report.target.clone())),
+            ("Release", report.artifact_url.clone()),
+            (
+                "Verified",
+                (report.status == UpdateStatus::Updated).then(|| signature.to_string()),
+            ),
+            (
+                "Executable",
+                report.executable.as_ref().map(|p| p.display().to_string()),
+            ),
+            (
+                "Previous",
+                report.backup.as_ref().map(|p| p.display().to_string()),
+            ),
+        ],
+    )
+    .unwrap_or_default()];
+    lines.push(outcome);
+    lines.join("\n")
+}
+
+/// `hil-test`: one line for a finished step
+pub fn hil_step(style: &OutputStyle, step: &HilStepResult) -> String {
+    let icon = match step.status {
+        HilStepStatus::Passed => "✅",
+        HilStepStatus::Failed => "❌",
+        HilStepStatus::Skipped => "➖",
+    };
+    let attempts = match step.attempts {
+        0 | 1 => String::new(),
+        n => format!(", {} attempts", n),
+    };
+    let mut line = format!(
+        "{} [{}] ({:.1}s{})",
+        step.name,
+        step.kind,
+        step.duration_ms as f64 / 1000.0,
+        attempts
+    );
+    if let Some(failure) = step.failure() {
+        line.push_str(&format!(": {}", failure));
+    }
+    style.fit(&style.prefixed(icon, &line))
+}
+
+/// `hil-test`: the totals, under the steps that failed
+///
+/// Each step's line is printed by [`hil_step`] as it finishes.
+pub fn hil_test(style: &OutputStyle, report: &HilReport) -> String {
+    let mut lines = vec![style.heading("🧪", &format!("Test Plan {}", report.plan))];
+    lines.extend(
+        report
+            .steps
+            .iter()
+            .filter(|step| step.status == HilStepStatus::Failed)
+            .map(|step| format!("{}{}", INDENT, hil_step(style, step))),
+    );
+    let totals = format!(
+        "{} passed, {} failed, {} skipped in {:.1}s",
+        report.passed,
+        report.failed,
+        report.skipped,
+        report.duration_ms as f64 / 1000.0
+    );
+    lines.push(match report.success {
+        true => style.prefixed("✅", &totals),
+        false => style.prefixed("❌", &totals),
+    });
+    lines.join("\n")
+}
+
+/// `fleet scan`: one line per port
+pub fn fleet_inventory(style: &OutputStyle, inventory: &FleetInventory) -> String {
+    let mut lines = vec![style.heading(
+        "🗄️",
+        &format!(
+            "Fleet ({} ports, {} answering)",
+            inventory.ports.len(),
+            inventory.responsive()
+        ),
+    )];
+    if inventory.ports.is_empty() {
+        lines.push(format!("{}No serial ports to probe", INDENT));
+        return lines.join("\n");
+    }
+    let port_width = inventory
+        .ports
+        .iter()
+        .map(|entry| entry.port.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max("Port".len());
+    let row = |port: &str, usb: &str, status: &str, rest: &str| {
+        let line = format!(
+            "{}{:<port_width$}  {:<16} {:<13} {}",
+            INDENT, port, usb, status, rest
+        );
+        style.fit(line.trim_end())
+    };
+    lines.push(row(
+        "Port",
+        "USB serial",
+        "Status",
+        &format!(
+            "{:<16} {:<13} {:<8} {}",
+            "Firmware", "Serial", "Battery", "Rails"
+        ),
+    ));
+    for entry in &inventory.ports {
+        let usb = entry.usb_serial.as_deref().unwrap_or("-");
+        let rest = match entry.status {
+            ProbeStatus::Ok => format!(
+                "{:<16} {:<13} {:<8} {}",
+                entry.firmware_version.as_deref().unwrap_or("unknown"),
+                entry.serial_number.as_deref().unwrap_or("-"),
+                entry
+                    .battery_mv
+                    .map_or("-".to_string(), |mv| format!("{} mV", mv)),
+                entry.rail_summary()
+            ),
+            _ => entry.error.clone().unwrap_or_default(),
+        };
+        lines.push(row(&entry.port, usb, entry.status.as_str(), &rest));
+        lines.extend(
+            entry
+                .errors
+                .iter()
+                .map(|error| style.fit(&format!("{}{}⚠️ {}", INDENT, INDENT, error))),
+        );
+    }
+    lines.join("\n")
+}
+
+/// `fleet diff`: one line per change
+pub fn fleet_diff(style: &OutputStyle, diff: &FleetDiff) -> String {
+    let time = |at: DateTime<Utc>| at.with_timezone(&Local).format("%Y-%m-%d %H:%M");
+    let mut lines = vec![style.heading(
+        "🗄️",
+        &format!(
+            "Fleet Changes ({} to {})",
+            time(diff.before),
+            time(diff.after)
+        ),
+    )];
+    if diff.changes.is_empty() {
+        lines.push(format!("{}No changes", INDENT));
+    }
+    for change in &diff.changes {
+        let what = match change.kind {
+            FleetChangeKind::Added => "added".to_string(),
+            FleetChangeKind::Removed => "removed".to_string(),
+            kind => format!(
+                "{} {} -> {}",
+                kind.as_str(),
+                change.before.as_deref().unwrap_or("none"),
+                change.after.as_deref().unwrap_or("none")
+            ),
+        };
+        lines.push(style.fit(&format!(
+            "{}{} ({}): {}",
+            INDENT, change.unit, change.port, what
+        )));
+    }
+    lines.push(format!("{}{} unchanged", INDENT, diff.unchanged));
+    lines.join("\n")
+}
+
+/// `power coulomb --reset`
+pub fn charge_reset(style: &OutputStyle, report: &ChargeResetReport) -> String {
+    let mah =
+        |charge: Option<u16>| charge.map_or("unreadable".to_string(), |c| format!("{} mAh", c));
+    let readback = match report.verified {
+        true => "0 mAh (verified)".to_string(),
+        false => format!("{} (not verified)", mah(report.charge_mah)),
+    };
+    fields(
+        style,
+        "🔋",
+        "Charge Reset",
+        &[
+            ("Discarded", Some(mah(report.previous_charge_mah))),
+            ("Readback", Some(readback)),
+        ],
+    )
+    .unwrap_or_default()
+}
+
+/// `system dfu-mode`, with the window deadline on the local clock
+pub fn dfu_mode(style: &OutputStyle, report: &DfuModeReport) -> String {
+    let window = match (report.deadline, report.remaining_s) {
+        (Some(deadline), Some(remaining)) => format!(
+            "{} s, {} s left (until {})",
+            report.timeout_s,
+            remaining,
+            deadline.with_timezone(&Local).format("%H:%M:%S")
+        ),
+        _ => "until reset (system dfu-mode --cancel or power cycle)".to_string(),
+    };
+    let bootloader = if report.enumerated {
+        "responding to mcumgr"
+    } else {
+        "not responding to mcumgr"
+    };
+    fields(
+        style,
+        "🔄",
+        "DFU Mode",
+        &[
+            ("Window", Some(window)),
+            ("Bootloader", Some(bootloader.to_string())),
+        ],
+    )
+    .unwrap_or_default()
+}
+
+/// `system dfu-mode --cancel`
+pub fn dfu_cancel(style: &OutputStyle, report: &DfuCancelReport) -> String {
+    style.prefixed(
+        "🔄",
+        &format!("Left DFU mode ({})", report.method.description()),
+    )
+}
+
+/// `log export`
+pub fn log_export(style: &OutputStyle, report: &ExportReport) -> String {
+    let read = &report.read;
+    let range = match (report.from, report.to) {
+        (None, None) => None,
+        (from, to) => Some(format!(
+            "{} to {}",
+            from.map_or("start".to_string(), |from| from.to_rfc3339()),
+            to.map_or("end".to_string(), |to| to.to_rfc3339())
+        )),
+    };
+    let mut text = fields(
+        style,
+        "📤",
+        "Battery Log Export",
+        &[
+            (
+                "Input",
+                Some(format!("{} ({} lines)", report.input.display(), read.lines)),
+            ),
+            (
+                "Output",
+                Some(format!(
+                    "{} ({})",
+                    report.output.display(),
+                    report.format.description()
+                )),
+            ),
+            ("Time range", range),
+            (
+                "Rows",
+                Some(format!(
+                    "{} written, {} outside the time range",
+                    report.written, report.filtered
+                )),
+            ),
+            (
+                "Skipped",
+                (read.skipped > 0).then(|| format!("{} unreadable lines", read.skipped)),
+            ),
+            (
+                "Ignored",
+                (read.ignored > 0).then(|| format!("{} lines without readings", read.ignored)),
+            ),
+            ("Sample format", report.sample_format.clone()),
+        ],
+    )
+    .unwrap_or_default();
+    for unparsed in &report.unparsed {
+        text.push_str(&format!("\n{}", unparsed_line(style, unparsed)));
+    }
+    text
+}
+
+/// A console line that is not a monitor sample, for `--show-unparsed`
+pub fn unparsed_line(style: &OutputStyle, unparsed: &UnparsedLine) -> String {
+    style.prefixed(
+        "❓",
+        &format!("Unparsed line {}: {}", unparsed.line, unparsed.text),
+    )
+}
+
+/// `pm wake-sources`
+pub fn wake_sources(style: &OutputStyle, mask: Option<&WakeMask>) -> String {
+    let body = match mask {
+        Some(mask) => mask.format_human(),
+        None => "Wake configuration not reported by firmware".to_string(),
+    };
+    titled(style, "⏰", "Wake Sources", &body)
+}
+
+/// `pm defaults export`
+pub fn defaults_exported(style: &OutputStyle, file: &Path) -> String {
+    style.prefixed(
+        "💾",
+        &format!("Power rail defaults exported to {}", file.display()),
+    )
+}
+
+/// `pm battery_check`
+pub fn battery_health(style: &OutputStyle, health: &BatteryHealthJson) -> String {
+    titled(style, "🔋", "Battery Health Check", &health.format_human())
+}
+
+/// `latency`
+pub fn latency(style: &OutputStyle, stats: &LatencyStats) -> String {
+    titled(
+        style,
+        "⏱️",
+        "Serial Round-Trip Latency",
+        &stats.format_human(),
+    )
+}
+
+/// One `monitor` sample as a single line
+pub fn monitor_sample(
+    style: &OutputStyle,
+    sample: &MonitorSampleJson,
+    timestamp: DateTime<Local>,
+) -> String {
+    let measurement = &sample.measurement;
+    let value = |v: Option<String>| v.unwrap_or_else(|| "n/a".to_string());
+    let charger = sample
+        .charger
+        .as_ref()
+        .map(|charger| {
+            let conflict = match charger.conflicts.is_empty() {
+                true => "",
+                false => " (conflicting)",
+            };
+            format!("  charger {}{}", charger.state.as_str(), conflict)
+        })
+        .unwrap_or_default();
+    style.prefixed(
+        "📊",
+        &format!(
+            "{}  {}  {}  [{}]{}",
+            timestamp.format("%H:%M:%S"),
+            value(with_unit(measurement.voltage_mv, "mV")),
+            value(with_unit(measurement.current_ma, "mA")),
+            value(measurement.adc_mode.clone()),
+            charger
+        ),
+    )
+}
+
+/// Event line for a charging-state change
+pub fn charging_transition(
+    style: &OutputStyle,
+    transition: &ChargingTransition,
+    measurement: &MeasurementJson,
+) -> String {
+    let icon = match transition.to {
+        ChargingState::Charging => "⚡",
+        ChargingState::Discharging => "🔋",
+        ChargingState::Idle => "💤",
+    };
+    let voltage = measurement
+        .voltage_mv
+        .map(|mv| format!(", V={:.2}V", mv as f64 / 1000.0))
+        .unwrap_or_default();
+    style.prefixed(
+        icon,
+        &format!(
+            "{} started at {}{}",
+            transition.to.as_str(),
+            transition.at.with_timezone(&Local).format("%H:%M:%S"),
+            voltage
+        ),
+    )
+}
+
+/// PMU reset detected during monitoring
+pub fn reboot_event(style: &OutputStyle, event: &RebootEvent) -> String {
+    style.prefixed("🔄", &event.message())
+}
+
+/// End-of-session `monitor` summary
+pub fn monitor_summary(style: &OutputStyle, summary: &MonitorSummaryJson) -> String {
+    let mut text = style.prefixed(
+        "📈",
+        &format!(
+            "Monitor summary: {} samples, {} charging transitions, final state {}",
+            summary.samples,
+            summary.charging_transitions,
+            summary.final_state.map_or("unknown", |s| s.as_str())
+        ),
+    );
+    if summary.reboots > 0 {
+        text.push_str(&format!(
+            "\n{}{}",
+            INDENT,
+            style.prefixed(
+                "⚠️",
+                &format!(
+                    "PMU rebooted {} time(s) during the session",
+                    summary.reboots
+                )
+            )
+        ));
+    }
+    if let Some(power) = summary.power.as_ref().filter(|power| !power.is_empty()) {
+        text.push('\n');
+        text.push_str(&power_stats_derived(style, power));
+    }
+    if let Some(format) = &summary.sample_format {
+        text.push_str(&format!(
+            "\n{}Samples in the {} format, {} unparsed lines",
+            INDENT, format, summary.unparsed_lines
+        ));
+    } else if summary.unparsed_lines > 0 {
+        text.push_str(&format!(
+            "\n{}No samples recognized in {} console lines",
+            INDENT, summary.unparsed_lines
+        ));
+    }
+    let link = &summary.link;
+    if !link.is_empty() {
+        text.push_str(&format!(
+            "\n{}Link: {} commands, {} timeouts, {} retries, {} reconnects{}",
+            INDENT,
+            link.commands,
+            link.timeouts,
+            link.retries,
+            link.reconnects,
+            link.avg_latency_ms
+                .map(|ms| format!(", average latency {:.1} ms", ms))
+                .unwrap_or_default()
+        ));
+    }
+    text
+}
+
+/// `stats`: serial traffic counted since the last reset
+pub fn link_stats(style: &OutputStyle, device: &str, stats: &ConnectionStats) -> String {
+    let title = format!("Link Statistics ({})", device);
+    if stats.is_empty() {
+        return format!(
+            "{}\n{}No traffic recorded",
+            style.heading("📶", &title),
+            INDENT
+        );
+    }
+    let families: Vec<String> = stats
+        .families
+        .iter()
+        .map(|(root, count)| format!("{} {}", root, count))
+        .collect();
+    let rows = [
+        (
+            "Since",
+            stats
+                .since
+                .map(|since| since.format("%Y-%m-%d %H:%M:%S UTC").to_string()),
+        ),
+        ("Commands", Some(stats.commands.to_string())),
+        ("Bytes sent", Some(stats.bytes_sent.to_string())),
+        ("Bytes received", Some(stats.bytes_received.to_string())),
+        ("Timeouts", Some(stats.timeouts.to_string())),
+        ("Retries", Some(stats.retries.to_string())),
+        ("Reconnects", Some(stats.reconnects.to_string())),
+        (
+            "Average latency",
+            stats
+                .avg_latency_ms
+                .map(|ms| format!("{:.1} ms over {} replies", ms, stats.timed_replies)),
+        ),
+        (
+            "By command",
+            Some(families.join(", ")).filter(|f| !f.is_empty()),
+        ),
+    ];
+    fields(style, "📶", &title, &rows).unwrap_or_default()
+}
+
+/// `stats --reset`
+pub fn link_stats_reset(style: &OutputStyle, device: &str) -> String {
+    style.prefixed("🧹", &format!("Link statistics for {} reset", device))
+}
+
+/// One character per reading, scaled between the lowest and highest
+///
+/// A flat series sits in the middle of the range.
+pub fn sparkline(style: &OutputStyle, samples: &[u16]) -> String {
+    let levels = if style.emoji {
+        SPARK_LEVELS
+    } else {
+        SPARK_LEVELS_ASCII
+    };
+    let (Some(&low), Some(&high)) = (samples.iter().min(), samples.iter().max()) else {
+        return String::new();
+    };
+    let top = levels.len() - 1;
+    samples
+        .iter()
+        .map(|&v| match high - low {
+            0 => levels[top / 2],
+            range => levels[(v - low) as usize * top / range as usize],
+        })
+        .collect()
+}
+
+/// `V=7.41V I=-142mA P=-1.05W SoC=78%`; unknown values show as `?`
+pub fn battery_compact(sample: &BatteryWatchSampleJson) -> String {
+    let battery = &sample.battery;
+    let value = |v: Option<String>| v.unwrap_or_else(|| "?".to_string());
+    let mut text = format!(
+        "V={} I={} P={}",
+        value(
+            battery
+                .voltage_mv
+                .map(|mv| format!("{:.2}V", mv as f64 / 1000.0))
+        ),
+        value(battery.current_ma.map(|ma| format!("{}mA", ma))),
+        value(
+            battery
+                .power_mw
+                .map(|mw| format!("{:.2}W", mw as f64 / 1000.0))
+        )
+    );
+    if let Some(soc) = sample.soc_percent {
+        text.push_str(&format!(" SoC={:.0}%", soc));
+    }
+    text
+}
+
+/// Line redrawn in place by `battery read --watch` on a terminal
+pub fn battery_live(
+    style: &OutputStyle,
+    sample: &BatteryWatchSampleJson,
+    voltages: &VoltageHistory,
+) -> String {
+    style.fit(&format!(
+        "{} {}",
+        battery_compact(sample),
+        sparkline(style, &voltages.samples())
+    ))
+}
+
+/// `battery read --watch` sample on its own line, for pipes and logs
+pub fn battery_watch_line(style: &OutputStyle, sample: &BatteryWatchSampleJson) -> String {
+    style.prefixed(
+        "🔋",
+        &format!(
+            "{}  {}",
+            sample.timestamp.with_timezone(&Local).format("%H:%M:%S"),
+            battery_compact(sample)
+        ),
+    )
+}
+
+/// End-of-session `battery read --watch` summary
+pub fn battery_watch_summary(style: &OutputStyle, summary: &BatteryWatchSummaryJson) -> String {
+    let volts = |mv: f64| format!("{:.3} V", mv / 1000.0);
+    let range = |min: Option<String>, max: Option<String>, avg: Option<String>| {
+        Some(format!("min {}, max {}, avg {}", min?, max?, avg?))
+    };
+    let title = format!(
+        "Watch Summary ({} samples over {})",
+        summary.samples,
+        reboot::format_uptime(summary.duration_s * 1000)
+    );
+    fields(
+        style,
+        "📈",
+        &title,
+        &[
+            (
+                "Voltage",
+                range(
+                    summary.voltage_min_mv.map(|mv| volts(mv as f64)),
+                    summary.voltage_max_mv.map(|mv| volts(mv as f64)),
+                    summary.voltage_avg_mv.map(volts),
+                ),
+            ),
+            (
+                "Current",
+                range(
+                    with_unit(summary.current_min_ma, "mA"),
+                    with_unit(summary.current_max_ma, "mA"),
+                    summary.current_avg_ma.map(|ma| format!("{:.1} mA", ma)),
+                ),
+            ),
+            ("Charge change", with_unit(summary.charge_delta_mah, "mAh")),
+        ],
+    )
+    .unwrap_or_else(|| style.heading("📈", &title))
+}
+
+/// `ltc2959 read --continuous` summary: current range and sampling jitter
+pub fn ltc2959_continuous(style: &OutputStyle, result: &ContinuousReadJson) -> String {
+    let summary = &result.summary;
+    let title = format!(
+        "Continuous Read ({} samples over {})",
+        summary.samples,
+        reboot::format_uptime(summary.duration_s * 1000)
+    );
+    let current = || {
+        Some(format!(
+            "min {} mA, max {} mA, avg {:.1} mA",
+            summary.current_min_ma?, summary.current_max_ma?, summary.current_avg_ma?
+        ))
+    };
+    let intervals = result.intervals_ms.map(|gaps| {
+        format!(
+            "{} ms asked, {:.0}-{:.0} ms seen, jitter {:.0} ms",
+            result.interval_ms,
+            gaps.min,
+            gaps.max,
+            result.jitter_ms.unwrap_or_default()
+        )
+    });
+    let restored = format!(
+        "{} (mode {})",
+        result.restored_adc_mode.name(),
+        result.restored_adc_mode.value()
+    );
+    fields(
+        style,
+        "📈",
+        &title,
+        &[
+            ("Current", current()),
+            ("Interval", intervals),
+            ("Charge change", with_unit(summary.charge_delta_mah, "mAh")),
+            ("ADC mode restored", Some(restored)),
+            (
+                "Stopped",
+                result.cancelled.then(|| "with Ctrl-C".to_string()),
+            ),
+        ],
+    )
+    .unwrap_or_else(|| style.heading("📈", &title))
+}
+
+/// `system time-ref`, with times on the local clock
+pub fn time_ref(style: &OutputStyle, report: &TimeRefReport) -> String {
+    let local = |time: DateTime<Utc>| {
+        time.with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M:%S%.3f %:z")
+            .to_string()
+    };
+    let reference = &report.reference;
+    let translation = report.translation.as_ref().map(|translation| {
+        (
+            format!("PMU {} ms", translation.pmu_ms),
+            local(translation.wallclock),
+        )
+    });
+    let mut rows = vec![
+        ("PMU booted", Some(local(reference.pmu_boot_wallclock))),
+        (
+            "Uptime",
+            Some(format!(
+                "{} ({} ms) at {}",
+                reboot::format_uptime(reference.uptime_ms),
+                reference.uptime_ms,
+                local(reference.host_time)
+            )),
+        ),
+        (
+            "Accuracy",
+            Some(format!(
+                "{}{:.0} ms (round trip {:.1} ms)",
+                style.plus_minus(),
+                reference.uncertainty_ms,
+                reference.round_trip_ms
+            )),
+        ),
+        (
+            "Reference",
+            Some(
+                if report.reused {
+                    "saved earlier"
+                } else {
+                    "new"
+                }
+                .to_string(),
+            ),
+        ),
+    ];
+    if let Some((label, time)) = &translation {
+        rows.push((label, Some(time.clone())));
+    }
+    fields(style, "⏱️", "PMU Time Reference", &rows).unwrap_or_default()
+}
+
+/// `history`
+pub fn history(style: &OutputStyle, device: &str, entries: &[HistoryEntry]) -> String {
+    let mut lines = vec![style.heading("📜", &format!("Command History ({})", device))];
+    if entries.is_empty() {
+        lines.push(format!("{}No recorded commands", INDENT));
+    }
+    lines.extend(entries.iter().map(HistoryEntry::format_human));
+    lines.join("\n")
+}
+
+/// `schedule list`
+pub fn schedules(style: &OutputStyle, device: &str, entries: &[ScheduledCommand]) -> String {
+    let mut lines = vec![style.heading("🕒", &format!("Scheduled Commands ({})", device))];
+    if entries.is_empty() {
+        lines.push(format!("{}No scheduled commands", INDENT));
+    }
+    lines.extend(entries.iter().map(ScheduledCommand::format_human));
+    lines.join("\n")
+}
+
+/// `schedule at` and `schedule cancel`
+pub fn schedule(style: &OutputStyle, entry: &ScheduledCommand) -> String {
+    let due = entry.due.with_timezone(&Local);
+    let (icon, action) = match entry.status {
+        ScheduleStatus::Cancelled => ("🚫", "Cancelled"),
+        _ => ("🕒", "Scheduled"),
+    };
+    style.prefixed(
+        icon,
+        &format!(
+            "{} '{}' for {} (id {})",
+            action,
+            entry.command_line(),
+            due.format("%Y-%m-%d %H:%M:%S"),
+            entry.id
+        ),
+    )
+}
+
+/// `examples`
+pub fn examples(style: &OutputStyle, filter: Option<&str>, examples: &[&Example]) -> String {
+    let mut lines = vec![style.heading("💡", "Examples")];
+    if examples.is_empty() {
+        lines.push(format!(
+            "{}No examples match '{}'",
+            INDENT,
+            filter.unwrap_or_default()
+        ));
+    }
+    lines.extend(examples.iter().map(|example| example.format_human()));
+    lines.join("\n")
+}
+
+/// `migrations`: one line per renamed subcommand
+pub fn migrations(style: &OutputStyle, deprecations: &[Deprecation]) -> String {
+    let mut lines = vec![style.heading("🔀", "Renamed Commands")];
+    lines.extend(deprecations.iter().map(|deprecation| {
+        format!(
+            "{}{} -> {} (old name removed in {})",
+            INDENT, deprecation.old, deprecation.new, deprecation.removed_in
+        )
+    }));
+    lines.join("\n")
+}
+
+/// `state show`: each file with its size, JSON files with their contents
+pub fn stored_state(
+    style: &OutputStyle,
+    dir: &Path,
+    files: &[(String, u64, Option<String>)],
+) -> String {
+    let mut lines = vec![style.heading("🗂️", &format!("Stored State ({})", dir.display()))];
+    if files.is_empty() {
+        lines.push(format!("{}No stored state", INDENT));
+    }
+    for (name, bytes, contents) in files {
+        lines.push(format!("{}{} ({} bytes)", INDENT, name, bytes));
+        for line in contents.iter().flat_map(|c| c.lines()) {
+            lines.push(format!("{}{}{}", INDENT, INDENT, line));
+        }
+    }
+    lines.join("\n")
+}
+
+/// `setup`: the file written and the checks of the new setup
+pub fn setup(style: &OutputStyle, report: &SetupReport) -> String {
+    let mut lines = vec![style.heading(
+        "⚙️",
+        &format!("Configuration Saved ({})", report.config_path.display()),
+    )];
+    let toml = report.config.to_toml().unwrap_or_default();
+    lines.extend(toml.lines().map(|line| match line {
+        "" => String::new(),
+        line => format!("{}{}", INDENT, line),
+    }));
+    lines.push(style.heading("🩺", "Setup Checks"));
+    for check in &report.checks {
+        let icon = if check.passed { "✅" } else { "❌" };
+        lines.push(style.prefixed(icon, &format!("{}: {}", check.name, check.detail)));
+    }
+    lines.join("\n")
+}
+
+/// One `snapshot` section: its data, or the error that replaced it
+fn snapshot_section<T>(
+    style: &OutputStyle,
+    icon: &str,
+    title: &str,
+    section: &Section<T>,
+    render: impl FnOnce(&T) -> Option<String>,
+) -> String {
+    let body = match (&section.data, &section.error) {
+        (Some(data), _) => match render(data) {
+            Some(text) => return text,
+            None => "Nothing reported".to_string(),
+        },
+        (None, Some(error)) => style.prefixed("❌", error),
+        (None, None) => "Nothing reported".to_string(),
+    };
+    format!("{}\n{}{}", style.heading(icon, title), INDENT, body)
+}
+
+/// `snapshot`: every section in turn, failed ones with their error
+pub fn snapshot(style: &OutputStyle, snapshot: &Snapshot) -> String {
+    let connection = &snapshot.connection;
+    let on_off = |on: Option<bool>| on.map(|on| if on { "ON" } else { "OFF" }.to_string());
+    let mut parts = vec![
+        style.prefixed(
+            "🧾",
+            &format!(
+                "Snapshot taken {} by eink-power-cli {}{}",
+                snapshot.taken_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                snapshot.cli_version,
+                if snapshot.redacted { " (redacted)" } else { "" }
+            ),
+        ),
+        fields(
+            style,
+            "🔌",
+            "Connection",
+            &[
+                ("Device", Some(connection.device.clone())),
+                ("Baud rate", Some(connection.baud.to_string())),
+                ("Timeout", Some(format!("{} s", connection.timeout_s))),
+                ("Pacing", Some(format!("{} ms", connection.pacing_ms))),
+                ("Response cache", yes_no(Some(connection.response_cache))),
+                (
+                    "Shell recovery",
+                    yes_no(Some(connection.auto_recover_shell)),
+                ),
+            ],
+        )
+        .unwrap_or_default(),
+        snapshot_section(
+            style,
+            "🖥️",
+            "System Information",
+            &snapshot.system,
+            |system| {
+                fields(
+                    style,
+                    "🖥️",
+                    "System Information",
+                    &[
+                        ("Board", system.board.clone()),
+                        ("SoC", system.soc.clone()),
+                        ("Version", system.version.clone()),
+                        ("Build date", system.build_date.clone()),
+                        ("Build type", system.build_type.map(|b| format!("{:?}", b))),
+                        ("Uptime", snapshot.uptime_ms.data.map(reboot::format_uptime)),
+                    ],
+                )
+            },
+        ),
+    ];
+    if let Some(error) = &snapshot.uptime_ms.error {
+        parts.push(style.prefixed("⚠️", &format!("Uptime: {}", error)));
+    }
+    parts.extend([
+        match snapshot.identity.error {
+            Some(_) => {
+                snapshot_section(style, "🏷️", "Device Identity", &snapshot.identity, |_| None)
+            }
+            None => identity(style, snapshot.identity.data.as_ref()),
+        },
+        snapshot_section(
+            style,
+            "📋",
+            "Firmware Images",
+            &snapshot.firmware_images,
+            |images| firmware_images(style, images),
+        ),
+        snapshot_section(
+            style,
+            "🔋",
+            "Battery Measurements",
+            &snapshot.battery,
+            |data| battery(style, data),
+        ),
+        snapshot_section(
+            style,
+            "📊",
+            "Power Management Statistics",
+            &snapshot.power_stats,
+            |stats| {
+                fields(
+                    style,
+                    "📊",
+                    "Power Management Statistics",
+                    &[
+                        ("Sleep cycles", stats.sleep_cycles.map(|n| n.to_string())),
+                        ("Wake cycles", stats.wake_cycles.map(|n| n.to_string())),
+                        ("LTC2959", stats.ltc2959_state.clone()),
+                        ("NFC", stats.nfc_state.clone()),
+                        ("UART", stats.uart_state.clone()),
+                    ],
+                )
+            },
+        ),
+        snapshot_section(
+            style,
+            "⚙️",
+            "Power Rail Defaults",
+            &snapshot.rail_defaults,
+            |defaults| {
+                fields(
+                    style,
+                    "⚙️",
+                    "Power Rail Defaults",
+                    &[
+                        ("PMIC", on_off(defaults.pmic)),
+                        ("WiFi", on_off(defaults.wifi)),
+                        ("Display", on_off(defaults.disp)),
+                        ("Saved in flash", yes_no(Some(defaults.saved_in_flash))),
+                    ],
+                )
+            },
+        ),
+        snapshot_section(style, "🕐", "RTC Status", &snapshot.rtc, |rtc| {
+            rtc_status(style, rtc)
+        }),
+        snapshot_section(style, "📡", "NFC Status", &snapshot.nfc, |nfc| {
+            nfc_status(style, nfc)
+        }),
+    ]);
+    parts.join("\n")
+}
+
+/// `power-audit`: rails, sleep states, wake sources, the estimate and every
+/// finding
+pub fn power_audit(style: &OutputStyle, audit: &PowerAudit) -> String {
+    let on_off = |on: bool| if on { "ON" } else { "OFF" };
+    let rails = audit.rails.data.as_ref();
+    let defaults = audit.rail_defaults.data.as_ref();
+    let rail = |rail: PowerRail, default: Option<bool>| {
+        let now = rails.and_then(|states| states.get(&rail).copied());
+        match (now, default) {
+            (Some(now), Some(default)) => {
+                Some(format!("{} (default {})", on_off(now), on_off(default)))
+            }
+            (Some(now), None) => Some(on_off(now).to_string()),
+            (None, Some(default)) => Some(format!("default {}", on_off(default))),
+            (None, None) => None,
+        }
+    };
+    let stats = audit.power_stats.data.as_ref();
+    let firmware = match &audit.firmware_version.data {
+        Some(version) => format!(" against firmware {}", version),
+        None => String::new(),
+    };
+
+    let mut parts = vec![style.prefixed(
+        "🧾",
+        &format!(
+            "Power audit taken {} by eink-power-cli {}{}",
+            audit.taken_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            audit.cli_version,
+            firmware
+        ),
+    )];
+    parts.extend(fields(
+        style,
+        "⚡",
+        "Power Rails",
+        &[
+            ("PMIC", rail(PowerRail::Pmic, defaults.and_then(|d| d.pmic))),
+            ("WiFi", rail(PowerRail::Wifi, defaults.and_then(|d| d.wifi))),
+            (
+                "Display",
+                rail(PowerRail::Display, defaults.and_then(|d| d.disp)),
+            ),
+        ],
+    ));
+    parts.extend(fields(
+        style,
+        "💤",
+        "Sleep States",
+        &[
+            ("LTC2959 ADC mode", audit.adc_mode().map(str::to_string)),
+            (
+                "NFC",
+                stats.and_then(|s| s.nfc_state.clone()).or_else(|| {
+                    let nfc = audit.nfc.data.as_ref();
+                    yes_no(nfc.and_then(|nfc| nfc.nfc_active)).map(|a| format!("active: {}", a))
+                }),
+            ),
+            ("UART", stats.and_then(|s| s.uart_state.clone())),
+            (
+                "Sleep cycles",
+                stats.and_then(|s| s.sleep_cycles).map(|n| n.to_string()),
+            ),
+        ],
+    ));
+    if let Some(derived) = audit.power_derived.as_ref().filter(|d| !d.is_empty()) {
+        parts.push(power_stats_derived(style, derived));
+    }
+    if let Some(mask) = &audit.wake_sources.data {
+        parts.push(wake_sources(style, Some(mask)));
+    }
+    if let Some(sample) = audit.sleep_sample.as_ref().and_then(|s| s.data.as_ref()) {
+        let title = format!("Sleep Current ({} s)", sample.duration_s);
+        let rows = [
+            (
+                "Estimated sleep current",
+                audit
+                    .estimated_sleep_current_ma
+                    .map(|ma| format!("{:.0} mA", ma)),
+            ),
+            (
+                "Battery current",
+                sample.current_ma.map(|s| sample_stats(&s, "mA")),
+            ),
+            (
+                "Readings",
+                Some(sample_count(sample.readings as usize, sample.failed)),
+            ),
+        ];
+        parts.extend(fields(style, "🔋", &title, &rows));
+    }
+    for (name, error) in audit.section_errors() {
+        parts.push(style.prefixed("❌", &format!("{}: {}", name, error)));
+    }
+    parts.push(style.heading("🩺", "Low-Power Checklist"));
+    if audit.findings.is_empty() {
+        parts.push(style.prefixed("✅", "Nothing found"));
+    }
+    for finding in &audit.findings {
+        parts.push(style.prefixed("⚠️", &finding.detail));
+    }
+    parts.join("\n")
+}
+
+/// `state clear`
+pub fn state_cleared(style: &OutputStyle, removed: usize, dir: &Path) -> String {
+    style.prefixed(
+        "🧹",
+        &format!("Removed {} state file(s) from {}", removed, dir.display()),
+    )
+}
@@ -0,0 +1,105 @@
+/*
+ * E-ink Power CLI - Output Sink
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Where command output goes: stdout, or a file written once it is complete
+//!
+//! With `--output <file>` everything the command prints is held in memory
+//! from [`start_capture`] and only written out by [`write_file`] once the
+//! command has succeeded. The file is replaced by rename, so readers (and a
+//! failed command) never see a half-written result.
+
+use crate::state::write_atomic;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Output held back for `--output`; `None` while printing to stdout
+static CAPTURED: Mutex<Option<Vec<u8>>> = Mutex::new(None);
+
+fn captured() -> std::sync::MutexGuard<'static, Option<Vec<u8>>> {
+    CAPTURED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Hold all further output in memory instead of printing it
+pub fn start_capture() {
+    captured().get_or_insert_with(Vec::new);
+}
+
+/// Whether output is being held for a file
+pub fn capturing() -> bool {
+    captured().is_some()
+}
+
+/// Stop capturing and return what was held
+pub fn take_captured() -> Vec<u8> {
+    captured().take().unwrap_or_default()
+}
+
+/// Capture output of one part of a command, setting aside what is already
+/// held; pass the result to [`end_nested_capture`]
+pub fn start_nested_capture() -> Option<Vec<u8>> {
+    captured().replace(Vec::new())
+}
+
+/// Return the output held since [`start_nested_capture`] and go back to
+/// what was captured (or printed) before it
+pub fn end_nested_capture(outer: Option<Vec<u8>>) -> Vec<u8> {
+    let mut held = captured();
+    let inner = held.take().unwrap_or_default();
+    *held = outer;
+    inner
+}
+
+/// Command output: stdout, or the capture buffer after [`start_capture`]
+pub struct Output;
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match captured().as_mut() {
+            Some(held) => {
+                held.extend_from_slice(buf);
+                Ok(buf.len())
+            }
+            None => io::stdout().write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match captured().as_ref() {
+            Some(_) => Ok(()),
+            None => io::stdout().flush(),
+        }
+    }
+}
+
+/// `contents` without its first line when that repeats the first line of
+/// `path`, so appended CSV gets a single header
+pub fn without_repeated_header<'a>(path: &Path, contents: &'a [u8]) -> &'a [u8] {
+    let Some(end) = contents.iter().position(|&b| b == b'\n') else {
+        return contents;
+    };
+    let mut first_line = Vec::new();
+    let read =
+        File::open(path).and_then(|file| BufReader::new(file).read_until(b'\n', &mut first_line));
+    match read {
+        Ok(_) if first_line == contents[..=end] => &contents[end + 1..],
+        _ => contents,
+    }
+}
+
+/// Write `contents` to `path` by rename, or add them to its end with `append`
+///
+/// An append is a single write to a file opened for appending, so lines
+/// from concurrent invocations do not interleave.
+pub fn write_file(path: &Path, contents: &[u8], append: bool) -> io::Result<()> {
+    if !append {
+        return write_atomic(path, contents);
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
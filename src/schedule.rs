@@ -0,0 +1,356 @@
+/*
+ * E-ink Power CLI - Deferred Commands
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `schedule`: run a command once at a later time without cron
+//!
+//! A schedule is recorded in `schedules.json` in the device state directory
+//! and carried out by the hidden `schedule run <id>`, started either as a
+//! detached process that waits for the due time or as a systemd transient
+//! timer. At the due time the runner starts the CLI again with the recorded
+//! arguments, so the command takes the normal path, history and link
+//! statistics included. A schedule cancelled in the meantime is not run.
+
+use crate::cli::{BatteryCommands, Cli, Commands, Ltc2959Commands, OutputFormat};
+use crate::power::sleep::parse_sleep_duration;
+use crate::state::{DeviceState, StateFile, StatePayload};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// State file holding the schedules of a device
+pub const SCHEDULES_FILE: &str = "schedules.json";
+
+/// Finished and cancelled schedules are dropped this long after they ended
+pub const KEEP_FINISHED: TimeDelta = TimeDelta::days(7);
+
+/// How often a waiting runner checks whether it was cancelled
+pub const CANCEL_POLL: Duration = Duration::from_secs(5);
+
+/// Schedules of a serial device; `None` without a state directory
+pub fn stored(device: &str) -> Option<StateFile<Schedules>> {
+    DeviceState::for_device(device).map(|state| state.file(SCHEDULES_FILE))
+}
+
+/// Log file of schedule `id`, holding the output of its run
+pub fn log_path(device: &str, id: &str) -> Option<PathBuf> {
+    DeviceState::for_device(device).map(|state| state.file_path(&format!("schedule-{}.log", id)))
+}
+
+/// How the deferred command is started at the due time
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleBackend {
+    /// A detached process that waits for the due time
+    #[default]
+    Detach,
+    /// A systemd transient timer (`systemd-run --user --on-calendar`)
+    Systemd,
+}
+
+/// Where a schedule stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleStatus {
+    Pending,
+    Running,
+    /// Ran and exited with status 0
+    Done,
+    /// Ran and exited with an error, or could not be started
+    Failed,
+    Cancelled,
+}
+
+impl ScheduleStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScheduleStatus::Pending => "pending",
+            ScheduleStatus::Running => "running",
+            ScheduleStatus::Done => "done",
+            ScheduleStatus::Failed => "failed",
+            ScheduleStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// Whether the schedule has ended one way or another
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            ScheduleStatus::Done | ScheduleStatus::Failed | ScheduleStatus::Cancelled
+        )
+    }
+}
+
+/// One deferred command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledCommand {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub due: DateTime<Utc>,
+    /// Command as typed after `eink-power-cli`, e.g. `power disp off`
+    pub command: Vec<String>,
+    /// Global options the command runs with, e.g. `--device /dev/ttyLP2`
+    pub options: Vec<String>,
+    pub backend: ScheduleBackend,
+    pub status: ScheduleStatus,
+    /// Process waiting for the due time, then running the command
+    pub pid: Option<u32>,
+    /// Transient systemd unit of `--backend systemd`
+    pub unit: Option<String>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub exit_status: Option<i32>,
+}
+
+impl ScheduledCommand {
+    /// Pending schedule of `command` at `due`
+    pub fn new(
+        due: DateTime<Utc>,
+        command: Vec<String>,
+        options: Vec<String>,
+        backend: ScheduleBackend,
+    ) -> Self {
+        let id = uuid::Uuid::new_v4().simple().to_string()[..8].to_string();
+        Self {
+            unit: (backend == ScheduleBackend::Systemd)
+                .then(|| format!("eink-power-cli-schedule-{}", id)),
+            id,
+            created_at: Utc::now(),
+            due,
+            command,
+            options,
+            backend,
+            status: ScheduleStatus::Pending,
+            pid: None,
+            finished_at: None,
+            exit_status: None,
+        }
+    }
+
+    /// The command as typed
+    pub fn command_line(&self) -> String {
+        self.command.join(" ")
+    }
+
+    /// Arguments of the hidden runner, `schedule run <id>` with the options
+    pub fn runner_args(&self) -> Vec<String> {
+        let mut args = self.options.clone();
+        args.extend(["schedule", "run", &self.id].map(String::from));
+        args
+    }
+
+    /// `systemd-run` arguments starting `exe` as the runner at the due time
+    pub fn systemd_run_args(&self, exe: &Path) -> Vec<String> {
+        let mut args = vec![
+            "--user".to_string(),
+            format!("--unit={}", self.unit.as_deref().unwrap_or(&self.id)),
+            format!("--on-calendar={}", self.due.format("%Y-%m-%d %H:%M:%S UTC")),
+            "--timer-property=AccuracySec=1s".to_string(),
+        ];
+        if let Ok(dir) = std::env::var(crate::state::STATE_DIR_ENV) {
+            args.push(format!("--setenv={}={}", crate::state::STATE_DIR_ENV, dir));
+        }
+        args.push("--".to_string());
+        args.push(exe.display().to_string());
+        args.extend(self.runner_args());
+        args
+    }
+
+    /// One line per schedule for the human format
+    pub fn format_human(&self) -> String {
+        let mut line = format!(
+            "{} {} [{}] {}",
+            self.id,
+            self.due.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S"),
+            self.status.as_str(),
+            self.command_line()
+        );
+        if let Some(code) = self.exit_status {
+            line.push_str(&format!(" (exit {})", code));
+        }
+        line
+    }
+}
+
+/// Every schedule of a device, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Schedules {
+    pub entries: Vec<ScheduledCommand>,
+}
+
+impl StatePayload for Schedules {
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl Schedules {
+    pub fn get(&self, id: &str) -> Option<&ScheduledCommand> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut ScheduledCommand> {
+        self.entries.iter_mut().find(|entry| entry.id == id)
+    }
+
+    /// Drop schedules that ended more than [`KEEP_FINISHED`] before `now`
+    pub fn prune(&mut self, now: DateTime<Utc>) {
+        self.entries.retain(|entry| {
+            !entry.status.is_finished()
+                || entry
+                    .finished_at
+                    .unwrap_or(entry.due)
+                    .checked_add_signed(KEEP_FINISHED)
+                    .is_none_or(|until| until > now)
+        });
+    }
+}
+
+/// Parse the time a command is due, relative to `now`
+///
+/// Accepts `+<duration>` (`+90s`, `+1h30m`), a time of day `HH:MM[:SS]`
+/// (today, or tomorrow once it has passed), a local date and time
+/// `YYYY-MM-DD HH:MM[:SS]` and RFC 3339.
+pub fn parse_due(text: &str, now: DateTime<Local>) -> Result<DateTime<Utc>, String> {
+    let text = text.trim();
+    let due = if let Some(offset) = text.strip_prefix('+') {
+        let offset = parse_sleep_duration(offset)
+            .filter(|offset| !offset.is_zero())
+            .ok_or_else(|| format!("'{}' is not a duration like +90s or +1h30m", text))?;
+        let offset = TimeDelta::from_std(offset).map_err(|_| format!("'{}' is too far", text))?;
+        now.checked_add_signed(offset)
+            .ok_or_else(|| format!("'{}' is too far", text))?
+            .with_timezone(&Utc)
+    } else if let Some(time) = ["%H:%M", "%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(text, format).ok())
+    {
+        let today = local(now.date_naive().and_time(time))?;
+        if today > now {
+            today.with_timezone(&Utc)
+        } else {
+            let tomorrow = now.date_naive().succ_opt().unwrap_or(NaiveDate::MAX);
+            local(tomorrow.and_time(time))?.with_timezone(&Utc)
+        }
+    } else if let Ok(due) = DateTime::parse_from_rfc3339(text) {
+        due.with_timezone(&Utc)
+    } else if let Some(datetime) = [
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+    {
+        local(datetime)?.with_timezone(&Utc)
+    } else {
+        return Err(format!(
+            "'{}' is not a time like +90s, 22:00 or 2025-12-24 22:00",
+            text
+        ));
+    };
+
+    if due <= now.with_timezone(&Utc) {
+        return Err(format!("{} is in the past", text));
+    }
+    Ok(due)
+}
+
+fn local(datetime: NaiveDateTime) -> Result<DateTime<Local>, String> {
+    datetime
+        .and_local_timezone(Local)
+        .earliest()
+        .ok_or_else(|| format!("{} does not exist in the local time zone", datetime))
+}
+
+/// Parse `command` as the CLI would and reject commands that cannot be
+/// deferred
+pub fn validate_command(command: &[String]) -> Result<Commands, String> {
+    let args = std::iter::once(env!("CARGO_PKG_NAME")).chain(command.iter().map(String::as_str));
+    let parsed = match Cli::try_parse_from(args) {
+        Ok(Cli {
+            command: Some(parsed),
+            ..
+        }) => parsed,
+        Ok(_) => return Err("no command to schedule".to_string()),
+        Err(e) => return Err(e.to_string().lines().next().unwrap_or_default().to_string()),
+    };
+    let runs_until_stopped = matches!(
+        parsed,
+        Commands::Monitor {
+            continuous: true,
+            ..
+        } | Commands::Monitor { follow: true, .. }
+            | Commands::Battery(BatteryCommands::Read { watch: true, .. })
+            | Commands::Ltc2959(Ltc2959Commands::Read {
+                continuous: true,
+                duration: None,
+                ..
+            })
+    );
+    if matches!(
+        parsed,
+        Commands::Schedule(_) | Commands::Setup { .. } | Commands::Simulate { .. }
+    ) || runs_until_stopped
+    {
+        return Err(format!("'{}' cannot be scheduled", parsed.name()));
+    }
+    Ok(parsed)
+}
+
+/// Global options of `cli` that the deferred command must run with
+pub fn options_of(cli: &Cli) -> Vec<String> {
+    let mut options = vec![
+        "--device".to_string(),
+        cli.device.clone(),
+        "--baud".to_string(),
+        cli.baud.to_string(),
+    ];
+    if let Some(config) = &cli.config {
+        options.extend(["--config".to_string(), config.display().to_string()]);
+    }
+    if cli.timeout_given {
+        options.extend(["--timeout".to_string(), cli.timeout.to_string()]);
+    }
+    if cli.format != OutputFormat::Human {
+        let format = cli.format.to_possible_value().expect("no skipped formats");
+        options.extend(["--format".to_string(), format.get_name().to_string()]);
+    }
+    if let Some(pacing) = cli.pacing_ms {
+        options.extend(["--pacing-ms".to_string(), pacing.to_string()]);
+    }
+    for (set, flag) in [
+        (cli.verbose, "--verbose"),
+        (cli.auto_recover_shell, "--auto-recover-shell"),
+        (cli.fix_console, "--fix-console"),
+        (cli.no_history, "--no-history"),
+        (cli.auto_deps, "--auto-deps"),
+    ] {
+        if set {
+            options.push(flag.to_string());
+        }
+    }
+    options
+}
+
+/// Start `exe` with `args` in its own process group, writing to `log`
+///
+/// The process outlives this one; returns its id.
+pub fn spawn_detached(exe: &Path, args: &[String], log: &Path) -> io::Result<u32> {
+    let out = File::create(log)?;
+    let err = out.try_clone()?;
+    let child = Command::new(exe)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(out)
+        .stderr(err)
+        .process_group(0)
+        .spawn()?;
+    Ok(child.id())
+}
@@ -0,0 +1,73 @@
+/*
+ * E-ink Power CLI - Boot Banner
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! The banner the PMU prints when it boots
+//!
+//! A controller that has just reset prints the Zephyr banner and a few log
+//! lines (firmware version, reset cause) before its first prompt. The
+//! connection keeps that text as a [`BootBanner`] instead of discarding it
+//! with the other unsolicited output. A banner received before the first
+//! command's reply is also held for the first JSON envelope written.
+
+use crate::json::patterns::{self, Pattern};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+/// Banner received while connecting, until the first envelope takes it
+static UNREPORTED: Mutex<Option<BootBanner>> = Mutex::new(None);
+
+/// Boot output of the PMU
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootBanner {
+    /// Lines from `*** Booting ...` up to the first prompt
+    pub text: String,
+    /// Zephyr build, e.g. `v3.7.0`
+    pub zephyr_version: Option<String>,
+    /// Firmware version from a `Version:` line
+    pub firmware_version: Option<String>,
+    /// Reset cause from a `Reset cause:` line, e.g. `watchdog`
+    pub reset_cause: Option<String>,
+}
+
+impl BootBanner {
+    /// The banner in console `output`, if it has one
+    pub fn parse(output: &str) -> Option<Self> {
+        let found = patterns::BOOT_BANNER.find(output)?;
+        let line_start = output[..found.start()].rfind('\n').map_or(0, |at| at + 1);
+        let lines: Vec<&str> = output[line_start..]
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .take_while(|line| !patterns::SHELL_PROMPT_START.is_match(line))
+            .collect();
+        let text = lines.join("\n").trim().to_string();
+
+        let capture = |pattern: &Pattern| {
+            pattern
+                .captures(&text)
+                .map(|caps| caps[1].trim().to_string())
+        };
+        Some(Self {
+            zephyr_version: capture(&patterns::BOOT_ZEPHYR_VERSION),
+            firmware_version: capture(&patterns::VERSION),
+            reset_cause: capture(&patterns::RESET_CAUSE),
+            text,
+        })
+    }
+}
+
+fn unreported() -> std::sync::MutexGuard<'static, Option<BootBanner>> {
+    UNREPORTED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Hold `banner` for the next JSON envelope
+pub fn hold_for_output(banner: BootBanner) {
+    *unreported() = Some(banner);
+}
+
+/// The held banner, once; `None` if there is none or it was taken already
+pub fn take_for_output() -> Option<BootBanner> {
+    unreported().take()
+}
@@ -0,0 +1,177 @@
+/*
+ * E-ink Power CLI - Response Cache
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Short-lived memoization of read-only status queries
+//!
+//! Composite flows (factory reset, sequenced power-on, defaults import) ask
+//! for the same status several times within one invocation. Only the
+//! queries listed in [`is_cacheable`] are remembered, for a short TTL, and
+//! any other command drops the cached entries it may have made stale, as
+//! listed in a table; a command missing from it drops them all.
+
+use log::debug;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// TTL used by the CLI for one invocation
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(2);
+
+/// Whether `command` only reads state and may be answered from the cache
+pub fn is_cacheable(command: &str) -> bool {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    matches!(
+        words.as_slice(),
+        ["version"]
+            | ["system", "info"]
+            | ["pm", "defaults"]
+            | ["pm", "stats"]
+            | ["pm", "status"]
+            | ["pm", _, "status"]
+            | ["ltc2959", "read" | "status"]
+            | ["nfc", "status" | "info"]
+            | ["rtc", "status" | "calibration"]
+            | ["gpio", "get", ..]
+    )
+}
+
+/// Cached queries each state-changing command may make stale
+///
+/// A sent command matches the first entry whose words it starts with, and
+/// drops the cached queries starting with any of the listed prefixes. A
+/// command no entry matches drops everything: a wrong guess here would
+/// answer with stale state, so only commands known to be harmless keep
+/// entries cached.
+const INVALIDATES: &[(&str, &[&str])] = &[
+    // Queries that are not cached change nothing
+    ("ping", &[]),
+    ("system uptime", &[]),
+    ("power stats", &[]),
+    // Coulomb counter commands change the charge the LTC2959 reports
+    ("power coulomb", &["ltc2959", "pm stats"]),
+    // Rails are seen by `pm` and change the current the LTC2959 measures;
+    // GPIOs drive rail enables on the board
+    ("power", &["pm", "ltc2959", "gpio"]),
+    ("pm", &["pm", "ltc2959", "gpio"]),
+    ("gpio", &["gpio", "pm", "ltc2959"]),
+    // The gauge state is part of `pm stats`
+    ("ltc2959", &["ltc2959", "pm"]),
+    ("battery", &["ltc2959", "pm"]),
+    ("nfc", &["nfc", "pm"]),
+    ("rtc", &["rtc", "pm"]),
+];
+
+/// Whether `command` starts with the words of `prefix`
+fn starts_with_words(command: &str, prefix: &str) -> bool {
+    let mut words = command.split_whitespace();
+    prefix
+        .split_whitespace()
+        .all(|word| words.next() == Some(word))
+}
+
+/// Whether sending `command` may change what a cached `entry` would return
+fn invalidates(command: &str, entry: &str) -> bool {
+    // Resets, sleep and erases affect every subsystem
+    let global = ["reset", "reboot", "sleep", "erase", "shutdown", "dfu"];
+    if command
+        .split_whitespace()
+        .any(|word| global.contains(&word))
+    {
+        return true;
+    }
+    match INVALIDATES
+        .iter()
+        .find(|(sent, _)| starts_with_words(command, sent))
+    {
+        Some((_, stale)) => stale.iter().any(|prefix| starts_with_words(entry, prefix)),
+        None => true,
+    }
+}
+
+/// Cache hit statistics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u32,
+    pub misses: u32,
+    pub invalidations: u32,
+}
+
+/// Responses to status queries, keyed by command line
+#[derive(Debug)]
+pub struct ResponseCache {
+    ttl: Duration,
+    entries: HashMap<String, (Instant, String)>,
+    stats: CacheStats,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: HashMap::new(),
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Cached response to `command` if it is still fresh
+    pub fn get(&mut self, command: &str) -> Option<String> {
+        if !is_cacheable(command) {
+            return None;
+        }
+        match self.entries.get(command) {
+            Some((at, response)) if at.elapsed() < self.ttl => {
+                self.stats.hits += 1;
+                debug!("Response cache hit: {}", command);
+                Some(response.clone())
+            }
+            _ => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Cached response to `command` without checking its age or counting a hit
+    pub fn peek(&self, command: &str) -> Option<&str> {
+        self.entries
+            .get(command)
+            .map(|(_, response)| response.as_str())
+    }
+
+    /// Note that `command` is about to go to the controller
+    ///
+    /// Drops every entry the command may make stale. Call before sending,
+    /// so a failed command still invalidates.
+    pub fn before_send(&mut self, command: &str) {
+        if is_cacheable(command) {
+            return;
+        }
+        let before = self.entries.len();
+        self.entries.retain(|entry, _| !invalidates(command, entry));
+        let dropped = before - self.entries.len();
+        if dropped > 0 {
+            self.stats.invalidations += dropped as u32;
+            debug!("'{}' invalidated {} cached responses", command, dropped);
+        }
+    }
+
+    /// Remember the response to a cacheable `command`
+    pub fn store(&mut self, command: &str, response: &str) {
+        if is_cacheable(command) {
+            self.entries
+                .insert(command.to_string(), (Instant::now(), response.to_string()));
+        }
+    }
+
+    /// Drop every entry, e.g. after the controller rebooted
+    pub fn clear(&mut self) {
+        self.stats.invalidations += self.entries.len() as u32;
+        self.entries.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
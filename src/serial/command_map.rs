@@ -0,0 +1,147 @@
+/*
+ * E-ink Power CLI - Shell Command Map
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Table of shell root commands, remappable for forked firmware
+//!
+//! Every command the CLI sends starts with the root command of its family
+//! (`pm pmic on`, `ltc2959 read`, ...). Firmware forks that rename those roots
+//! can be supported from the config file without changing the CLI:
+//!
+//! ```toml
+//! [commands]
+//! power = "pwr"
+//! ltc2959 = "gauge"
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Family of shell commands sharing a root command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandFamily {
+    Power,
+    Pm,
+    Ltc2959,
+    Nfc,
+    Gpio,
+    Rtc,
+    System,
+    Board,
+    Comm,
+}
+
+impl CommandFamily {
+    /// All families, in declaration order
+    pub const ALL: [CommandFamily; 9] = [
+        CommandFamily::Power,
+        CommandFamily::Pm,
+        CommandFamily::Ltc2959,
+        CommandFamily::Nfc,
+        CommandFamily::Gpio,
+        CommandFamily::Rtc,
+        CommandFamily::System,
+        CommandFamily::Board,
+        CommandFamily::Comm,
+    ];
+
+    /// Root command used by the stock firmware
+    pub fn default_root(self) -> &'static str {
+        match self {
+            CommandFamily::Power => "power",
+            CommandFamily::Pm => "pm",
+            CommandFamily::Ltc2959 => "ltc2959",
+            CommandFamily::Nfc => "nfc",
+            CommandFamily::Gpio => "gpio",
+            CommandFamily::Rtc => "rtc",
+            CommandFamily::System => "system",
+            CommandFamily::Board => "board",
+            CommandFamily::Comm => "comm",
+        }
+    }
+
+    /// Family whose stock root command is `root`
+    pub fn from_root(root: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.default_root() == root)
+    }
+}
+
+/// Root command overrides, keyed by family
+///
+/// Families without an entry use [`CommandFamily::default_root`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CommandMap {
+    roots: BTreeMap<CommandFamily, String>,
+}
+
+impl CommandMap {
+    /// Override the root command of `family`
+    #[allow(dead_code)] // Used by tests and library callers
+    pub fn with_root(mut self, family: CommandFamily, root: &str) -> Self {
+        self.roots.insert(family, root.trim().to_string());
+        self
+    }
+
+    /// Whether no family has an entry
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Root command for `family`
+    pub fn root(&self, family: CommandFamily) -> &str {
+        self.roots
+            .get(&family)
+            .map(String::as_str)
+            .unwrap_or_else(|| family.default_root())
+    }
+
+    /// Build `<root> <args>` for a family
+    pub fn command(&self, family: CommandFamily, args: &str) -> String {
+        let args = args.trim();
+        if args.is_empty() {
+            self.root(family).to_string()
+        } else {
+            format!("{} {}", self.root(family), args)
+        }
+    }
+
+    /// Remap the root of an already-built command line
+    ///
+    /// Commands whose first word is not a stock root (e.g. `version`) are
+    /// returned unchanged.
+    pub fn apply(&self, command: &str) -> String {
+        let command = command.trim();
+        let (first, rest) = command.split_once(' ').unwrap_or((command, ""));
+        match CommandFamily::from_root(first) {
+            Some(family) => self.command(family, rest),
+            None => command.to_string(),
+        }
+    }
+
+    /// Families whose root differs from the stock firmware, as `(family, root)`
+    pub fn remapped(&self) -> Vec<(CommandFamily, &str)> {
+        self.roots
+            .iter()
+            .filter(|(family, root)| family.default_root() != root.as_str())
+            .map(|(family, root)| (*family, root.as_str()))
+            .collect()
+    }
+
+    /// Whether any family is remapped
+    pub fn is_remapped(&self) -> bool {
+        !self.remapped().is_empty()
+    }
+
+    /// Human-readable summary of active remaps, e.g. `power→pwr, ltc2959→gauge`
+    pub fn describe_remaps(&self) -> String {
+        self.remapped()
+            .iter()
+            .map(|(family, root)| format!("{}→{}", family.default_root(), root))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
@@ -5,19 +5,297 @@
  */
 
 use crate::error::{PowerCliError, Result};
-use log::{debug, info};
-use std::time::Duration;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use crate::json::patterns;
+use crate::serial::banner::{self, BootBanner};
+use crate::serial::cache::{CacheStats, ResponseCache};
+use crate::serial::console::{self, ConsoleQuirk};
+use crate::serial::holders::{self, StolenUnit};
+use crate::serial::mock::MockSerial;
+use crate::serial::protocol::classify::{classify, ResponseClass};
+use crate::serial::protocol::framing::{encode_frame, FRAME_HEADER_LEN, MAX_FRAME_PAYLOAD};
+use crate::serial::stats::ConnectionStats;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::time::timeout;
-use tokio_serial::{SerialPortBuilderExt, SerialStream};
+use tokio_serial::SerialPortBuilderExt;
+
+/// Quiet window used to drain stale bytes before each command
+const PRE_COMMAND_DRAIN_WINDOW: Duration = Duration::from_millis(10);
+
+/// Quiet window used by an explicit [`Connection::flush_rx_buffer`]
+const FLUSH_WINDOW: Duration = Duration::from_millis(200);
+
+/// Number of sent commands remembered for the session log
+const MAX_COMMANDS_RECORDED: usize = 64;
+
+/// Raw bytes received that are kept for error reports
+pub const RECEIVED_TAIL_LEN: usize = 500;
+
+/// Keystrokes that re-enable the firmware shell when the console has dropped
+/// into log-only mode (Ctrl-C followed by Enter)
+const SHELL_RECOVERY_SEQUENCE: &[u8] = b"\x03\r\n";
+
+/// Keystrokes that discard a half-written input line: Ctrl-U clears the
+/// line and Ctrl-C makes the Zephyr shell print a fresh prompt
+const RESYNC_SEQUENCE: &[u8] = b"\x15\x03";
+
+/// How long to wait for the fresh prompt after [`RESYNC_SEQUENCE`]
+const RESYNC_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long the shell may take to echo a typed command
+const ECHO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Times a command is typed before a corrupted echo is given up on
+pub const ECHO_ATTEMPTS: u32 = 3;
+
+/// Length of the console excerpt included in [`PowerCliError::ShellUnavailable`]
+const SHELL_SNIPPET_LEN: usize = 200;
+
+/// How long the line must stay quiet after a prompt for the reply to be
+/// complete; output within it shows the prompt text was part of the reply
+const PROMPT_GRACE_WINDOW: Duration = Duration::from_millis(10);
+
+/// How long the line must stay quiet to end a reply that has no prompt
+const QUIET_WINDOW: Duration = Duration::from_millis(100);
+
+/// Default minimum gap between consecutive commands
+pub const DEFAULT_PACING_MS: u64 = 5;
+
+/// Shell message printed when a command line overran its input buffer
+const BUFFER_FULL_MARKER: &str = "buffer full";
+
+/// Time the controller needs to reprogram its UART after a baud change
+const BAUD_SWITCH_SETTLE: Duration = Duration::from_millis(100);
+
+/// Console baud rates the controller firmware can switch to
+pub const SUPPORTED_BAUD_RATES: &[u32] =
+    &[9600, 19200, 38400, 57600, 115200, 230400, 460800, 921600];
+
+/// Check run when the console stays silent on connect
+///
+/// Returns true if the MCUboot serial recovery bootloader answered. The port
+/// is closed while the probe runs so it can use the device itself.
+pub type BootloaderProbe = Box<dyn FnMut() -> bool + Send>;
+
+/// Whether `line` is a shell prompt (`prod:~$`, `debug:~$` or Zephyr's
+/// default `uart:~$`) on a line of its own
+pub fn is_prompt_line(line: &str) -> bool {
+    patterns::SHELL_PROMPT_LINE.is_match(line)
+}
+
+/// Whether the last non-empty line of `received` is a shell prompt
+///
+/// Prompt text elsewhere, such as `Last wake: uart:~$ input` or a prompt
+/// line followed by more output, does not end a reply.
+pub fn ends_with_prompt(received: &str) -> bool {
+    received
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .is_some_and(is_prompt_line)
+}
+
+/// When to clear the shell input line before a command
+///
+/// Electrical noise can leave stray bytes in the PMU's input buffer, which
+/// the next command is then appended to.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ResyncMode {
+    /// Before every command
+    Always,
+    /// Before the first command after an error or a dropped command
+    #[default]
+    AfterError,
+    /// Never
+    Never,
+}
+
+/// Which commands have their echo checked before they run
+///
+/// A checked command is typed without the newline and only run once the
+/// shell has echoed it unchanged.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EchoCheck {
+    /// Every command
+    Always,
+    /// Commands that switch rails off, erase or write (see [`is_destructive`])
+    #[default]
+    Destructive,
+    /// None
+    Never,
+}
+
+/// Whether `received` holds more of the reply to `command` than its echo
+fn reply_started(received: &str, command: &str) -> bool {
+    let text = received.trim_start();
+    let rest = text.strip_prefix(command.trim()).unwrap_or(text);
+    !rest.trim().is_empty()
+}
+
+/// Which command timeouts are retried
+///
+/// A command is sent once more after a timeout it allows, with the input
+/// line cleared first.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeoutRetry {
+    /// Timeouts with no data at all from a shell that echoes commands: not
+    /// even the echo came back, so the command never reached the shell
+    #[default]
+    Silent,
+    /// Every timeout, a partial reply included
+    Always,
+    /// None
+    Never,
+}
+
+/// Whether a corrupted `command` could do damage: it switches something
+/// off, erases storage, writes a register or memory, or resets to
+/// production defaults
+pub fn is_destructive(command: &str) -> bool {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    words.last() == Some(&"off")
+        || words
+            .iter()
+            .any(|word| ["erase", "write", "reg_write", "production_reset"].contains(word))
+}
+
+/// Byte stream to the controller: a serial port, or a mock in tests
+pub trait SerialIo: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> SerialIo for T {}
 
 /// Serial connection to the power management controller
 pub struct Connection {
     device_path: String,
     baud_rate: u32,
     timeout_duration: Duration,
-    stream: Option<SerialStream>,
+    stream: Option<Box<dyn SerialIo>>,
     quiet: bool,
+    commands_sent: Vec<String>,
+    last_response: Option<String>,
+    /// Context of the last failed [`Connection::send_command`]
+    failed_send: Option<String>,
+    /// Command of the last failed [`Connection::send_command`]
+    failed_command: Option<String>,
+    /// Last [`RECEIVED_TAIL_LEN`] bytes read from the port
+    received_tail: Vec<u8>,
+    /// Memoized status queries; `None` sends every command
+    cache: Option<ResponseCache>,
+    auto_recover_shell: bool,
+    dry_run: bool,
+    shell_check: bool,
+    bootloader_probe: Option<BootloaderProbe>,
+    phase: Phase,
+    /// Minimum gap between the end of one command and the next
+    pacing: Duration,
+    /// When the last [`Connection::send_command`] reply was read
+    last_command_at: Option<Instant>,
+    /// Nothing is written before then, set by [`Connection::hold_off`]
+    guard_until: Option<Instant>,
+    /// The shell has echoed a command, so a missing echo means it was dropped
+    shell_echoes: bool,
+    /// Found by the handshake; replies are normalized for them
+    console_quirks: Vec<ConsoleQuirk>,
+    /// Send the shell commands that undo `console_quirks`
+    fix_console: bool,
+    /// The boot banner appeared since [`Connection::take_boot_banner`]
+    boot_banner_seen: bool,
+    /// Latest boot banner received
+    boot_banner: Option<BootBanner>,
+    resync: ResyncMode,
+    echo_check: EchoCheck,
+    timeout_retry: TimeoutRetry,
+    /// A command failed, so the input line may hold leftovers
+    needs_resync: bool,
+    /// Timing of the last command that went out on the wire
+    last_round_trip: Option<RoundTrip>,
+    stats: ConnectionStats,
+    /// The port was opened before, so opening it again is a reconnect
+    opened: bool,
+    /// Stop the systemd unit of another process holding the port
+    steal: bool,
+    /// Unit stopped for `steal` when the holder runs in none
+    steal_unit: Option<String>,
+    /// Unit stopped for `steal`; started again when dropped. Declared
+    /// after `stream` so the port is closed first.
+    stolen: Option<StolenUnit>,
+}
+
+/// When a command was written and how long the first reply byte took
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundTrip {
+    /// Host wall-clock time the write finished
+    pub sent_at: DateTime<Utc>,
+    /// From then to the first byte of the reply
+    pub first_byte: Duration,
+}
+
+/// What the connection is busy with, reported when the overall deadline expires
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Opening the port and the `ping` handshake
+    Connect,
+    /// Sending a command and reading its reply
+    Command,
+    /// Checking the controller answers after a change (e.g. new baud rate)
+    Verification,
+    /// Reopening the port after the link was dropped on purpose
+    Reconnect,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Phase::Connect => "connect",
+            Phase::Command => "command",
+            Phase::Verification => "verification",
+            Phase::Reconnect => "reconnect",
+        })
+    }
+}
+
+/// Result of probing the controller shell with `ping` on connect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellState {
+    /// A prompt or pong came back
+    Ready,
+    /// Nothing was received before the timeout
+    Silent,
+    /// Output arrived but none of it came from the shell
+    LogOnly { snippet: String },
+}
+
+impl ShellState {
+    /// Classify the raw console output seen after sending `ping`
+    pub fn classify(raw: &str) -> Self {
+        if raw.trim().is_empty() {
+            return ShellState::Silent;
+        }
+
+        let has_prompt = raw.lines().any(is_prompt_line);
+        let has_pong = raw.to_lowercase().contains("pong");
+        if has_prompt || has_pong {
+            return ShellState::Ready;
+        }
+
+        // Keep the tail, which is the most recent console output
+        let trimmed = raw.trim();
+        let mut start = trimmed.len().saturating_sub(SHELL_SNIPPET_LEN);
+        while !trimmed.is_char_boundary(start) {
+            start += 1;
+        }
+        ShellState::LogOnly {
+            snippet: trimmed[start..].to_string(),
+        }
+    }
 }
 
 impl Connection {
@@ -29,17 +307,287 @@ impl Connection {
             timeout_duration: Duration::from_secs(3),
             stream: None,
             quiet,
+            commands_sent: Vec::new(),
+            last_response: None,
+            failed_send: None,
+            failed_command: None,
+            received_tail: Vec::new(),
+            cache: None,
+            auto_recover_shell: false,
+            dry_run: false,
+            shell_check: true,
+            bootloader_probe: None,
+            phase: Phase::Connect,
+            pacing: Duration::from_millis(DEFAULT_PACING_MS),
+            last_command_at: None,
+            guard_until: None,
+            shell_echoes: false,
+            console_quirks: Vec::new(),
+            fix_console: false,
+            boot_banner_seen: false,
+            boot_banner: None,
+            resync: ResyncMode::default(),
+            echo_check: EchoCheck::default(),
+            timeout_retry: TimeoutRetry::default(),
+            needs_resync: false,
+            last_round_trip: None,
+            stats: ConnectionStats::starting_now(),
+            opened: false,
+            steal: false,
+            steal_unit: None,
+            stolen: None,
         })
     }
 
+    /// Connection over an in-memory [`MockSerial`] script
+    ///
+    /// The connection starts out connected and skips the `ping` handshake,
+    /// so the script only needs the commands under test.
+    #[allow(dead_code)] // Used by tests
+    pub fn mock(serial: MockSerial) -> Self {
+        let mut connection =
+            Self::new("mock", 115200, true).expect("creating a connection cannot fail");
+        connection.stream = Some(Box::new(serial));
+        connection.opened = true;
+        connection
+    }
+
+    /// Answer repeated status queries from a cache for `ttl`
+    ///
+    /// See [`crate::serial::cache`] for which commands are cached and what
+    /// invalidates them. Ignored in dry-run mode.
+    pub fn enable_response_cache(&mut self, ttl: Duration) {
+        if !self.dry_run {
+            self.cache = Some(ResponseCache::new(ttl));
+        }
+    }
+
+    /// Response cache statistics, if the cache is enabled
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(ResponseCache::stats)
+    }
+
+    /// Reply to `command` held in the response cache, however old
+    pub fn cached_response(&self, command: &str) -> Option<&str> {
+        self.cache.as_ref()?.peek(command)
+    }
+
+    /// Traffic counters since the connection was created or last reset
+    pub fn stats(&self) -> &ConnectionStats {
+        &self.stats
+    }
+
+    /// Start the traffic counters again from zero
+    #[allow(dead_code)] // Used by tests
+    pub fn reset_stats(&mut self) {
+        self.stats = ConnectionStats::starting_now();
+    }
+
+    /// Drop every cached response, e.g. after the controller rebooted
+    pub fn invalidate_cache(&mut self) {
+        if let Some(cache) = self.cache.as_mut() {
+            cache.clear();
+        }
+    }
+
+    /// Whether the controller printed its boot banner since the last call
+    pub fn take_boot_banner(&mut self) -> bool {
+        std::mem::take(&mut self.boot_banner_seen)
+    }
+
+    /// Boot banner received on this connection, the latest if the
+    /// controller reset more than once
+    pub fn boot_banner(&self) -> Option<&BootBanner> {
+        self.boot_banner.as_ref()
+    }
+
     /// Set command timeout
-    #[allow(dead_code)] // Future use
     pub fn set_timeout(&mut self, timeout_secs: u64) {
         self.timeout_duration = Duration::from_secs(timeout_secs);
     }
 
+    /// Command timeout
+    pub fn timeout(&self) -> Duration {
+        self.timeout_duration
+    }
+
+    /// Set command timeout to `timeout`, e.g. for one slow command
+    pub fn set_timeout_duration(&mut self, timeout: Duration) {
+        self.timeout_duration = timeout;
+    }
+
+    /// Minimum gap between consecutive commands
+    ///
+    /// Keeps back-to-back commands from overrunning the shell input buffer.
+    /// The first command of a session is never delayed.
+    pub fn set_pacing(&mut self, pacing: Duration) {
+        self.pacing = pacing;
+    }
+
+    /// Minimum gap between consecutive commands
+    pub fn pacing(&self) -> Duration {
+        self.pacing
+    }
+
+    /// Write nothing for `window` from now
+    ///
+    /// For commands after which the PMU drops or reinitializes its UART
+    /// (`pm sleep`, `system reset`): bytes sent meanwhile are lost. The next
+    /// command waits out the window; a longer window already running is kept.
+    pub fn hold_off(&mut self, window: Duration) {
+        if self.dry_run {
+            return;
+        }
+        let until = Instant::now() + window;
+        if self.guard_until.is_none_or(|current| current < until) {
+            self.guard_until = Some(until);
+        }
+    }
+
+    /// Wait out the window of the last [`Connection::hold_off`]
+    async fn wait_for_guard(&mut self) {
+        let Some(until) = self.guard_until.take() else {
+            return;
+        };
+        let wait = until.saturating_duration_since(Instant::now());
+        if !wait.is_zero() {
+            debug!(
+                "Guard time: waiting {} ms for the PMU UART before sending",
+                wait.as_millis()
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Try to re-enable the shell automatically if it is found in log-only mode
+    pub fn set_auto_recover_shell(&mut self, enabled: bool) {
+        self.auto_recover_shell = enabled;
+    }
+
+    /// Stop the systemd unit of another process holding the port for as
+    /// long as this connection lives; `unit` overrides the holder's unit
+    pub fn set_steal(&mut self, steal: bool, unit: Option<String>) {
+        self.steal = steal;
+        self.steal_unit = unit;
+    }
+
+    /// Unit `--steal` stops when the holder runs in none
+    pub fn steal_unit(&self) -> Option<&str> {
+        self.steal_unit.as_deref()
+    }
+
+    /// When to clear the shell input line before a command
+    pub fn set_resync(&mut self, mode: ResyncMode) {
+        self.resync = mode;
+    }
+
+    /// Which commands have their echo checked before they run
+    pub fn set_echo_check(&mut self, check: EchoCheck) {
+        self.echo_check = check;
+    }
+
+    /// Which commands have their echo checked
+    pub fn echo_check(&self) -> EchoCheck {
+        self.echo_check
+    }
+
+    /// When the shell input line is cleared before a command
+    pub fn resync_mode(&self) -> ResyncMode {
+        self.resync
+    }
+
+    /// Which command timeouts are retried
+    pub fn set_timeout_retry(&mut self, retry: TimeoutRetry) {
+        self.timeout_retry = retry;
+    }
+
+    /// Which command timeouts are retried
+    pub fn timeout_retry(&self) -> TimeoutRetry {
+        self.timeout_retry
+    }
+
+    /// Clear the input line before the next command, unless resync is off
+    ///
+    /// For errors found above the connection, such as a reply the shell
+    /// rejected.
+    pub fn request_resync(&mut self) {
+        self.needs_resync = true;
+    }
+
+    /// Print commands instead of sending them; the device is never opened
+    pub fn set_dry_run(&mut self, enabled: bool) {
+        self.dry_run = enabled;
+    }
+
+    /// Skip the `ping` handshake on connect (e.g. when the PMU may be in
+    /// its bootloader)
+    pub fn set_shell_check(&mut self, enabled: bool) {
+        self.shell_check = enabled;
+    }
+
+    /// Undo console quirks found on connect with the firmware's shell
+    /// settings, rather than only working around them
+    pub fn set_fix_console(&mut self, enabled: bool) {
+        self.fix_console = enabled;
+    }
+
+    /// Console quirks found on connect, which replies are normalized for
+    pub fn console_quirks(&self) -> &[ConsoleQuirk] {
+        &self.console_quirks
+    }
+
+    /// Normalize replies for `quirks` without probing, e.g. on a second
+    /// connection to a console already probed
+    pub fn set_console_quirks(&mut self, quirks: Vec<ConsoleQuirk>) {
+        self.console_quirks = quirks;
+    }
+
+    /// Probe for the bootloader when the console does not answer on connect
+    pub fn set_bootloader_probe(&mut self, probe: BootloaderProbe) {
+        self.bootloader_probe = Some(probe);
+    }
+
+    /// Phase in progress, or the last one entered if idle
+    pub fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Whether commands are printed instead of sent
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Local serial port baud rate
+    pub fn baud_rate(&self) -> u32 {
+        self.baud_rate
+    }
+
+    /// Close the port and use `baud_rate` from the next connect
+    pub fn set_baud_rate(&mut self, baud_rate: u32) {
+        self.baud_rate = baud_rate;
+        self.stream = None;
+    }
+
+    /// Close the port and reopen it at `baud_rate`, skipping the shell handshake
+    pub fn reopen_at(&mut self, baud_rate: u32) -> Result<()> {
+        debug!(
+            "Reopening {} at {} baud (was {})",
+            self.device_path, baud_rate, self.baud_rate
+        );
+        self.baud_rate = baud_rate;
+        if self.dry_run {
+            return Ok(());
+        }
+        self.stream = None;
+        self.open_stream()
+    }
+
     /// Connect to the serial device
     pub async fn connect(&mut self) -> Result<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+
         debug!(
             "Connecting to {} at {} baud",
             self.device_path, self.baud_rate
@@ -53,6 +601,8 @@ impl Connection {
             );
         }
 
+        self.phase = Phase::Connect;
+
         // Check if device exists
         if !std::path::Path::new(&self.device_path).exists() {
             return Err(PowerCliError::DeviceNotFound {
@@ -60,7 +610,57 @@ impl Connection {
             });
         }
 
-        // Open serial port
+        self.claim_port().await?;
+        self.open_stream()?;
+        debug!("Successfully connected to {}", self.device_path);
+
+        if self.shell_check {
+            self.check_shell().await?;
+            if self.fix_console && !self.console_quirks.is_empty() {
+                self.fix_console().await?;
+            }
+        } else {
+            debug!("Skipping shell handshake");
+        }
+        self.phase = Phase::Command;
+        Ok(())
+    }
+
+    /// Refuse a port another process holds open, or stop that process's
+    /// unit with `steal`
+    async fn claim_port(&mut self) -> Result<()> {
+        if self.stolen.is_some() {
+            return Ok(());
+        }
+        let holders = holders::holders(&self.device_path);
+        let Some(holder) = holders.first() else {
+            return Ok(());
+        };
+        let busy = || PowerCliError::PortBusy {
+            device: self.device_path.clone(),
+            holder: holder.to_string(),
+        };
+        if !self.steal {
+            return Err(busy());
+        }
+        let unit =
+            holders::unit_to_steal(self.steal_unit.as_deref(), &holders).ok_or_else(|| {
+                warn!(
+                    "{} runs in no systemd service; set [connection] steal_unit",
+                    holder
+                );
+                busy()
+            })?;
+        let device = self.device_path.clone();
+        let stolen = tokio::task::spawn_blocking(move || StolenUnit::stop(&unit, &device))
+            .await
+            .map_err(std::io::Error::other)??;
+        self.stolen = Some(stolen);
+        Ok(())
+    }
+
+    /// Open the serial port
+    fn open_stream(&mut self) -> Result<()> {
         let stream = tokio_serial::new(&self.device_path, self.baud_rate)
             .data_bits(tokio_serial::DataBits::Eight)
             .parity(tokio_serial::Parity::None)
@@ -68,78 +668,701 @@ impl Connection {
             .flow_control(tokio_serial::FlowControl::None)
             .open_native_async()?;
 
-        self.stream = Some(stream);
-        debug!("Successfully connected to {}", self.device_path);
+        if self.opened {
+            self.stats.reconnects += 1;
+        }
+        self.opened = true;
+        self.stream = Some(Box::new(stream));
+        Ok(())
+    }
+
+    /// Verify the controller shell answers, recovering it if requested
+    ///
+    /// A silent controller is left for the following command to time out on
+    /// as before; only a console that streams logs without a prompt is
+    /// treated as an unavailable shell.
+    async fn check_shell(&mut self) -> Result<()> {
+        let snippet = match self.probe_shell().await? {
+            ShellState::Ready => return Ok(()),
+            ShellState::Silent => return self.check_bootloader().await,
+            ShellState::LogOnly { snippet } => snippet,
+        };
 
+        if !self.auto_recover_shell {
+            return Err(PowerCliError::ShellUnavailable { snippet });
+        }
+
+        warn!("PMU shell appears to be disabled; sending shell-enable sequence");
+        let stream = self.stream.as_mut().unwrap();
+        stream.write_all(SHELL_RECOVERY_SEQUENCE).await?;
+        stream.flush().await?;
+        let output = Self::read_available_static(stream, FLUSH_WINDOW).await?;
+        self.stats.bytes_sent += SHELL_RECOVERY_SEQUENCE.len() as u64;
+        self.note_received(&output);
+
+        match self.probe_shell().await? {
+            ShellState::Ready => {
+                info!("PMU shell recovered");
+                Ok(())
+            }
+            ShellState::Silent => Err(PowerCliError::ShellUnavailable { snippet }),
+            ShellState::LogOnly { snippet } => Err(PowerCliError::ShellUnavailable { snippet }),
+        }
+    }
+
+    /// Fail with [`PowerCliError::InBootloader`] if a silent controller is
+    /// sitting in its bootloader
+    ///
+    /// The probe runs mcumgr to completion, so it runs on the blocking pool.
+    async fn check_bootloader(&mut self) -> Result<()> {
+        let Some(mut probe) = self.bootloader_probe.take() else {
+            return Ok(());
+        };
+
+        debug!("Console silent; probing for the bootloader");
+        self.stream = None;
+        let (probe, in_bootloader) = tokio::task::spawn_blocking(move || {
+            let answered = probe();
+            (probe, answered)
+        })
+        .await
+        .map_err(std::io::Error::other)?;
+        self.bootloader_probe = Some(probe);
+        if in_bootloader {
+            return Err(PowerCliError::InBootloader {
+                device: self.device_path.clone(),
+            });
+        }
+        self.open_stream()
+    }
+
+    /// Send `ping` and classify what comes back
+    ///
+    /// The reply is also the probe for [`console`] quirks, so it is read as
+    /// received rather than normalized.
+    async fn probe_shell(&mut self) -> Result<ShellState> {
+        self.stats.record_command("ping");
+        let earlier = std::mem::take(&mut self.console_quirks);
+        match self.exchange("ping").await {
+            Ok((raw, _)) => {
+                self.note_console_quirks(console::detect(&raw, "ping", "pong"));
+                Ok(ShellState::classify(&raw))
+            }
+            Err(PowerCliError::Timeout { .. }) => {
+                self.console_quirks = earlier;
+                Ok(ShellState::Silent)
+            }
+            Err(e) => {
+                self.console_quirks = earlier;
+                Err(e)
+            }
+        }
+    }
+
+    /// Keep the quirks the handshake found, saying so in verbose output
+    fn note_console_quirks(&mut self, quirks: Vec<ConsoleQuirk>) {
+        if !quirks.is_empty() && quirks != self.console_quirks {
+            info!(
+                "PMU console quirks: {}; replies are normalized{}",
+                console::describe(&quirks),
+                if self.fix_console {
+                    ""
+                } else {
+                    " (--fix-console restores the shell settings)"
+                }
+            );
+        }
+        self.console_quirks = quirks;
+    }
+
+    /// Turn off the shell settings behind the console quirks found, then
+    /// probe again
+    ///
+    /// Firmware without a setting answers its command as unknown; that
+    /// quirk is left to the normalization.
+    async fn fix_console(&mut self) -> Result<()> {
+        let quirks = self.console_quirks.clone();
+        for quirk in &quirks {
+            let command = quirk.fix();
+            self.record_command(command);
+            let (reply, _) = self.exchange(command).await?;
+            let reply = self.clean_response(&reply, command);
+            if classify(&reply) == ResponseClass::Failure {
+                warn!(
+                    "Cannot fix {}: the firmware rejected '{}': {}",
+                    quirk, command, reply
+                );
+            } else {
+                info!("Sent '{}' to fix {}", command, quirk);
+            }
+        }
+        self.probe_shell().await?;
+        if !self.console_quirks.is_empty() {
+            warn!(
+                "PMU console quirks remain: {}",
+                console::describe(&self.console_quirks)
+            );
+        }
         Ok(())
     }
 
     /// Send a command and wait for response
     pub async fn send_command(&mut self, command: &str) -> Result<String> {
+        self.failed_send = None;
+        self.failed_command = None;
+        self.last_round_trip = None;
+        if let Some(response) = self.cache.as_mut().and_then(|cache| cache.get(command)) {
+            self.last_response = Some(response.clone());
+            return Ok(response);
+        }
+
+        let response = match self.transact_paced(command).await {
+            Ok(reply) => reply,
+            Err(e) => {
+                let ctx = format!("while sending '{}' to {}", command, self.device_path);
+                debug!("{}: {}", ctx, e);
+                self.failed_send = Some(ctx);
+                self.failed_command = Some(command.to_string());
+                self.needs_resync = true;
+                return Err(e);
+            }
+        };
+
+        // Clean up the response by removing the command echo and prompt
+        let cleaned_response = self.clean_response(&response, command);
+        if let Some(cache) = self.cache.as_mut() {
+            cache.store(command, &cleaned_response);
+        }
+        self.last_response = Some(cleaned_response.clone());
+        Ok(cleaned_response)
+    }
+
+    /// Send a command after the pacing gap and read the raw reply
+    ///
+    /// The input line is cleared first as the [`ResyncMode`] asks. If the
+    /// shell reports a full input buffer or does not echo the command, the
+    /// command was dropped or merged with another and is sent once more.
+    async fn transact_paced(&mut self, command: &str) -> Result<String> {
+        self.wait_for_guard().await;
+        let resync = match self.resync {
+            ResyncMode::Always => true,
+            ResyncMode::AfterError => self.needs_resync,
+            ResyncMode::Never => false,
+        };
+        if resync {
+            self.resync().await?;
+        }
+        for attempt in 1..=2 {
+            if let Some(wait) = self
+                .last_command_at
+                .and_then(|at| self.pacing.checked_sub(at.elapsed()))
+            {
+                tokio::time::sleep(wait).await;
+            }
+            let (raw, _) = self.transact_retrying(command).await?;
+            self.last_command_at = Some(Instant::now());
+            if self.dry_run || !self.is_overrun(&raw, command) {
+                return Ok(raw);
+            }
+            if attempt == 1 {
+                self.stats.retries += 1;
+                warn!(
+                    "PMU shell dropped '{}' (input buffer overrun); sending it again",
+                    command
+                );
+                if self.resync != ResyncMode::Never {
+                    self.resync().await?;
+                }
+            }
+        }
+        Err(PowerCliError::InvalidResponse {
+            response: format!(
+                "shell dropped '{}' twice; try a larger --pacing-ms",
+                command
+            ),
+        })
+    }
+
+    /// [`Connection::transact`], sent once more after a timeout the
+    /// [`TimeoutRetry`] allows
+    async fn transact_retrying(&mut self, command: &str) -> Result<(String, Option<Duration>)> {
+        let error = match self.transact(command).await {
+            Err(e) if self.retries_timeout(&e) => e,
+            other => return other,
+        };
+        self.stats.retries += 1;
+        warn!("{} waiting for '{}'; sending it again", error, command);
+        if self.resync != ResyncMode::Never {
+            self.resync().await?;
+        }
+        self.transact(command).await
+    }
+
+    /// Whether `error` is a timeout [`TimeoutRetry`] allows a retry of
+    ///
+    /// With no data at all the command is known not to have run when the
+    /// shell echoes commands, since not even the echo came back. The link
+    /// check after a baud change or reconnect is not retried: its timeout
+    /// is the answer.
+    fn retries_timeout(&self, error: &PowerCliError) -> bool {
+        let PowerCliError::Timeout { bytes_received, .. } = error else {
+            return false;
+        };
+        if self.phase != Phase::Command {
+            return false;
+        }
+        match self.timeout_retry {
+            TimeoutRetry::Always => true,
+            TimeoutRetry::Silent => *bytes_received == 0 && self.shell_echoes,
+            TimeoutRetry::Never => false,
+        }
+    }
+
+    /// Clear the shell input line and wait for a fresh prompt
+    ///
+    /// Bytes already in the PMU's input buffer are discarded rather than
+    /// run with the next command. A shell that prints no prompt is left for
+    /// that command to fail on.
+    pub async fn resync(&mut self) -> Result<()> {
+        self.needs_resync = false;
+        if self.dry_run {
+            return Ok(());
+        }
+        let Some(stream) = self.stream.as_mut() else {
+            // The next command opens the port and checks the shell anyway
+            return Ok(());
+        };
+
+        debug!("Clearing the PMU shell input line");
+        stream.write_all(RESYNC_SEQUENCE).await?;
+        stream.flush().await?;
+        self.stats.bytes_sent += RESYNC_SEQUENCE.len() as u64;
+        let mut buffer = Vec::new();
+        let mut first_byte = None;
+        let read = timeout(
+            RESYNC_TIMEOUT,
+            Self::read_reply(stream, "", &mut buffer, Instant::now(), &mut first_byte),
+        )
+        .await;
+        if let Ok(result) = read {
+            result?;
+        }
+        self.note_received(&buffer);
+        let output = String::from_utf8_lossy(&buffer);
+        self.note_boot_banner(&output);
+        if ends_with_prompt(&output) {
+            debug!("PMU shell input line cleared");
+        } else {
+            warn!("PMU shell printed no fresh prompt after clearing the input line");
+        }
+        Ok(())
+    }
+
+    /// Whether `raw` shows the shell dropped `command`
+    fn is_overrun(&self, raw: &str, command: &str) -> bool {
+        raw.to_lowercase().contains(BUFFER_FULL_MARKER)
+            || (self.shell_echoes && !Self::echoes(raw, command))
+    }
+
+    /// Whether `raw` starts with the echo of `command`
+    fn echoes(raw: &str, command: &str) -> bool {
+        raw.lines()
+            .find(|line| !line.trim().is_empty())
+            .is_some_and(|line| line.trim() == command.trim())
+    }
+
+    /// Send a command and read the raw reply
+    ///
+    /// Also returns the time from the end of the write to the first byte of
+    /// the reply, if any byte arrived.
+    async fn transact(&mut self, command: &str) -> Result<(String, Option<Duration>)> {
+        if self.dry_run {
+            self.record_command(command);
+            println!("[dry-run] {}", command);
+            return Ok((String::new(), None));
+        }
+
         // Auto-connect if not already connected
         if self.stream.is_none() {
             debug!("Auto-connecting to device before sending command");
             self.connect().await?;
         }
 
-        let stream = self.stream.as_mut().unwrap();
+        self.record_command(command);
+        let verify = match self.echo_check {
+            EchoCheck::Always => true,
+            EchoCheck::Destructive => is_destructive(command),
+            EchoCheck::Never => false,
+        };
+        // A shell that has not echoed a command yet may not echo at all
+        if verify && !self.shell_echoes {
+            warn!(
+                "PMU shell has not echoed a command yet; sending '{}' without checking its echo",
+                command
+            );
+        }
+        if verify && self.shell_echoes {
+            self.exchange_verified(command).await
+        } else {
+            self.exchange(command).await
+        }
+    }
+
+    /// Write a command on the open stream and read the raw reply
+    async fn exchange(&mut self, command: &str) -> Result<(String, Option<Duration>)> {
+        self.drain_stale().await?;
         debug!("Sending command: {}", command);
+        self.write_raw(format!("{}\n", command).as_bytes()).await?;
+        self.read_response(command, Vec::new()).await
+    }
 
-        // Send command with newline
-        let command_with_newline = format!("{}\n", command);
-        stream.write_all(command_with_newline.as_bytes()).await?;
-        stream.flush().await?;
+    /// Type a command, check the shell echoes it unchanged, then press Enter
+    ///
+    /// The echo is normalized for the console quirks found on connect. A
+    /// corrupted echo means the firmware received a corrupted command; the
+    /// line is discarded before it runs and the command typed again, up to
+    /// [`ECHO_ATTEMPTS`] times.
+    async fn exchange_verified(&mut self, command: &str) -> Result<(String, Option<Duration>)> {
+        for attempt in 1..=ECHO_ATTEMPTS {
+            self.drain_stale().await?;
+            debug!("Sending command, checking its echo: {}", command);
+            self.write_raw(command.as_bytes()).await?;
+            let echo_len = console::echo_len(command.len(), &self.console_quirks);
+            let echo = self.read_echo(echo_len).await?;
+            let echo = console::normalize_echo(&echo, command.as_bytes(), &self.console_quirks);
+            if echo == command.as_bytes() {
+                self.write_raw(b"\n").await?;
+                return self.read_response(command, echo).await;
+            }
 
-        // Read response with timeout
-        let response = timeout(self.timeout_duration, async {
-            let mut buffer = Vec::new();
-            let mut temp_buf = [0u8; 1024];
+            warn!(
+                "PMU echoed '{}' for '{}' (attempt {} of {}); discarding the line",
+                String::from_utf8_lossy(&echo).escape_debug(),
+                command,
+                attempt,
+                ECHO_ATTEMPTS
+            );
+            self.resync().await?;
+            if echo.is_empty() {
+                return Err(PowerCliError::timeout(ECHO_TIMEOUT, &echo));
+            }
+            if attempt < ECHO_ATTEMPTS {
+                self.stats.retries += 1;
+            }
+        }
+        Err(PowerCliError::InvalidResponse {
+            response: format!(
+                "echo of '{}' was corrupted {} times; the command was not run",
+                command, ECHO_ATTEMPTS
+            ),
+        })
+    }
 
-            loop {
+    /// Read the echo of `len` typed bytes, or what arrived of it
+    async fn read_echo(&mut self, len: usize) -> Result<Vec<u8>> {
+        let stream = self.stream.as_mut().ok_or(PowerCliError::NotConnected)?;
+        let mut echo = Vec::new();
+        let mut temp_buf = [0u8; 256];
+        timeout(ECHO_TIMEOUT, async {
+            while echo.len() < len {
                 match stream.read(&mut temp_buf).await {
-                    Ok(0) => break, // EOF
-                    Ok(n) => {
-                        buffer.extend_from_slice(&temp_buf[..n]);
-                        let response_str = String::from_utf8_lossy(&buffer);
-
-                        // Look for shell prompt indicating end of response
-                        if response_str.contains("prod:~$") || response_str.contains("debug:~$") {
-                            break;
-                        }
-
-                        // Also break on timeout if we have some data
-                        if !buffer.is_empty() && !response_str.trim().is_empty() {
-                            // Give a small additional delay for any remaining data
-                            tokio::time::sleep(Duration::from_millis(100)).await;
-                            break;
-                        }
-                    }
+                    Ok(0) => break,
+                    Ok(n) => echo.extend_from_slice(&temp_buf[..n]),
                     Err(e) => return Err(PowerCliError::Io(e)),
                 }
             }
-
-            Ok(String::from_utf8_lossy(&buffer).to_string())
+            Ok(())
         })
         .await
-        .map_err(|_| PowerCliError::Timeout {
-            timeout: self.timeout_duration.as_secs(),
-        })??;
+        .unwrap_or(Ok(()))?;
+        self.note_received(&echo);
+        Ok(echo)
+    }
+
+    /// Write `bytes` to the open stream
+    async fn write_raw(&mut self, bytes: &[u8]) -> Result<()> {
+        let stream = self.stream.as_mut().ok_or(PowerCliError::NotConnected)?;
+        stream.write_all(bytes).await?;
+        stream.flush().await?;
+        self.stats.bytes_sent += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Drop any unsolicited output (logs, late replies) so it is not
+    /// mistaken for the response to the next command
+    async fn drain_stale(&mut self) -> Result<()> {
+        let stream = self.stream.as_mut().ok_or(PowerCliError::NotConnected)?;
+        let stale = Self::read_available_static(stream, PRE_COMMAND_DRAIN_WINDOW).await?;
+        self.note_received(&stale);
+        if !stale.is_empty() {
+            let stale = String::from_utf8_lossy(&stale);
+            debug!(
+                "Discarded {} stale bytes before command: {}",
+                stale.len(),
+                stale
+            );
+            self.note_boot_banner(&stale);
+        }
+        Ok(())
+    }
+
+    /// Read the reply to `command`, which was just sent
+    ///
+    /// `echo` is the part of the reply already read; it is not counted again.
+    async fn read_response(
+        &mut self,
+        command: &str,
+        echo: Vec<u8>,
+    ) -> Result<(String, Option<Duration>)> {
+        let stream = self.stream.as_mut().ok_or(PowerCliError::NotConnected)?;
+        let sent_at = Instant::now();
+        let sent_wallclock = Utc::now();
+        let mut first_byte = None;
+
+        // Read response with timeout; output still arriving at the deadline
+        // is kept, so a console streaming logs is not mistaken for a silent one
+        let mut buffer = Vec::new();
+        let read = timeout(
+            self.timeout_duration,
+            Self::read_reply(stream, command, &mut buffer, sent_at, &mut first_byte),
+        )
+        .await;
+        match read {
+            Ok(result) => result?,
+            Err(_) if reply_started(&String::from_utf8_lossy(&buffer), command) => {
+                self.stats.timeouts += 1;
+                debug!(
+                    "Reply still arriving after {:?}; using the {} bytes received",
+                    self.timeout_duration,
+                    buffer.len()
+                );
+            }
+            Err(_) => {
+                self.stats.timeouts += 1;
+                self.note_received(&buffer);
+                let received = [echo, buffer].concat();
+                if !received.is_empty() {
+                    debug!(
+                        "No reply to '{}' after {:?}, only: {}",
+                        command,
+                        self.timeout_duration,
+                        String::from_utf8_lossy(&received).escape_debug()
+                    );
+                }
+                return Err(PowerCliError::timeout(self.timeout_duration, &received));
+            }
+        }
+        self.note_received(&buffer);
+        let response = String::from_utf8_lossy(&[echo, buffer].concat()).to_string();
+        let response = console::normalize(&response, &self.console_quirks);
 
         debug!("Received response: {}", response);
+        if let Some(first_byte) = first_byte {
+            self.stats.record_latency(first_byte);
+        }
+        self.last_round_trip = first_byte.map(|first_byte| RoundTrip {
+            sent_at: sent_wallclock,
+            first_byte,
+        });
+        if Self::echoes(&response, command) {
+            self.shell_echoes = true;
+        }
+        self.note_boot_banner(&response);
+        Ok((response, first_byte))
+    }
 
-        // Clean up the response by removing the command echo and prompt
-        let cleaned_response = self.clean_response(&response, command);
-        Ok(cleaned_response)
+    /// Read the reply to `command` into `buffer` until it is complete
+    ///
+    /// A reply is complete when the line goes quiet: for
+    /// [`PROMPT_GRACE_WINDOW`] after a shell prompt that ends the output
+    /// received so far, or for [`QUIET_WINDOW`] after output without one.
+    /// The echo of `command` alone is not a reply; a slow command may take
+    /// longer than the window to print anything after it.
+    /// Prompt text inside the reply (an NFC dump mentioning `uart:`, a
+    /// scrollback line) is followed by more output within the window and does
+    /// not cut the reply short. Sets `first_byte` when the first byte arrives.
+    async fn read_reply(
+        stream: &mut dyn SerialIo,
+        command: &str,
+        buffer: &mut Vec<u8>,
+        sent_at: Instant,
+        first_byte: &mut Option<Duration>,
+    ) -> Result<()> {
+        let mut temp_buf = [0u8; 1024];
+        loop {
+            let received = String::from_utf8_lossy(buffer);
+            let window = if ends_with_prompt(&received) {
+                Some(PROMPT_GRACE_WINDOW)
+            } else if reply_started(&received, command) {
+                Some(QUIET_WINDOW)
+            } else {
+                None
+            };
+            let read = match window {
+                Some(window) => match timeout(window, stream.read(&mut temp_buf)).await {
+                    Ok(read) => read,
+                    Err(_) => return Ok(()),
+                },
+                None => stream.read(&mut temp_buf).await,
+            };
+            match read {
+                Ok(0) => return Ok(()), // EOF
+                Ok(n) => {
+                    first_byte.get_or_insert_with(|| sent_at.elapsed());
+                    buffer.extend_from_slice(&temp_buf[..n]);
+                }
+                Err(e) => return Err(PowerCliError::Io(e)),
+            }
+        }
+    }
+
+    /// Record a boot banner in console output; cached responses describe
+    /// the controller before the reset and are dropped
+    ///
+    /// A banner before the first command's reply was printed while
+    /// connecting and is held for the first JSON envelope.
+    fn note_boot_banner(&mut self, output: &str) {
+        let Some(banner) = BootBanner::parse(output) else {
+            return;
+        };
+        if self.last_response.is_none() {
+            info!("PMU boot banner while connecting; the controller has just reset");
+            banner::hold_for_output(banner.clone());
+        } else {
+            warn!("PMU boot banner on the console; the controller has restarted");
+        }
+        self.boot_banner_seen = true;
+        self.boot_banner = Some(banner);
+        self.invalidate_cache();
+    }
+
+    /// Measure serial round-trip latency by sending `ping` `samples` times
+    ///
+    /// Each sample is the time from writing the command to the first byte of
+    /// the reply.
+    pub async fn measure_round_trip_latency(&mut self, samples: u8) -> Result<LatencyStats> {
+        if samples == 0 {
+            return Err(PowerCliError::InvalidCommand {
+                command: "latency sample count must be at least 1".to_string(),
+            });
+        }
+
+        let mut round_trips = Vec::with_capacity(samples as usize);
+        for i in 0..samples {
+            let (response, first_byte) = self.transact("ping").await?;
+            let rtt = first_byte.ok_or_else(|| PowerCliError::InvalidResponse {
+                response: format!("no reply to ping {}: {:?}", i + 1, response),
+            })?;
+            debug!("Ping {} round trip: {:?}", i + 1, rtt);
+            round_trips.push(rtt);
+        }
+
+        Ok(LatencyStats::from_samples(&round_trips).expect("at least one sample"))
+    }
+
+    /// Send `command` as a binary frame and return the payload of the reply frame
+    ///
+    /// Used instead of [`Connection::send_command`] when the controller
+    /// speaks the binary protocol. Replies are not cached.
+    pub async fn send_frame(&mut self, command: &str) -> Result<Vec<u8>> {
+        if command.len() > MAX_FRAME_PAYLOAD {
+            return Err(PowerCliError::InvalidCommand {
+                command: format!(
+                    "{} byte command does not fit a frame (max {})",
+                    command.len(),
+                    MAX_FRAME_PAYLOAD
+                ),
+            });
+        }
+        self.failed_send = None;
+        self.failed_command = None;
+        self.wait_for_guard().await;
+        let reply = self.transact_frame(command).await;
+        if let Err(e) = &reply {
+            let ctx = format!("while sending '{}' to {}", command, self.device_path);
+            debug!("{}: {}", ctx, e);
+            self.failed_send = Some(ctx);
+            self.failed_command = Some(command.to_string());
+        }
+        reply
+    }
+
+    /// Write `command` as a frame and read the reply frame
+    async fn transact_frame(&mut self, command: &str) -> Result<Vec<u8>> {
+        if self.dry_run {
+            self.record_command(command);
+            println!("[dry-run] {}", command);
+            return Ok(Vec::new());
+        }
+
+        if self.stream.is_none() {
+            debug!("Auto-connecting to device before sending frame");
+            self.connect().await?;
+        }
+
+        self.record_command(command);
+        let stream = self.stream.as_mut().ok_or(PowerCliError::NotConnected)?;
+        debug!("Sending frame: {}", command);
+        let frame = encode_frame(command.as_bytes());
+        stream.write_all(&frame).await?;
+        stream.flush().await?;
+        self.stats.bytes_sent += frame.len() as u64;
+        self.read_frame().await
+    }
+
+    /// Read one length-prefixed frame and return its payload
+    ///
+    /// Reads the 2-byte header, then exactly that many payload bytes, all
+    /// within the command timeout. On a timeout the bytes read so far are
+    /// in the error.
+    pub async fn read_frame(&mut self) -> Result<Vec<u8>> {
+        let stream = self.stream.as_mut().ok_or(PowerCliError::NotConnected)?;
+        let mut received = Vec::new();
+        let read = timeout(self.timeout_duration, async {
+            let mut needed = FRAME_HEADER_LEN;
+            while received.len() < needed {
+                let mut chunk = vec![0u8; needed - received.len()];
+                let n = stream.read(&mut chunk).await?;
+                if n == 0 {
+                    return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+                }
+                received.extend_from_slice(&chunk[..n]);
+                if needed == FRAME_HEADER_LEN && received.len() == FRAME_HEADER_LEN {
+                    needed += u16::from_be_bytes([received[0], received[1]]) as usize;
+                }
+            }
+            Ok(())
+        })
+        .await;
+        match read {
+            Ok(result) => result?,
+            Err(_) => {
+                self.stats.timeouts += 1;
+                self.note_received(&received);
+                return Err(PowerCliError::timeout(self.timeout_duration, &received));
+            }
+        }
+        let payload = received.split_off(FRAME_HEADER_LEN);
+        self.stats.bytes_received += FRAME_HEADER_LEN as u64;
+        self.note_received(&payload);
+
+        debug!("Received {} byte frame", payload.len());
+        Ok(payload)
     }
 
     /// Send a command with a short timeout (for commands that may cause connection loss)
     pub async fn send_command_with_short_timeout(&mut self, command: &str) -> Result<String> {
+        if self.dry_run {
+            return self.send_command(command).await;
+        }
+
         // Auto-connect if not already connected
         if self.stream.is_none() {
             debug!("Auto-connecting to device before sending command");
             self.connect().await?;
         }
 
+        self.record_command(command);
         let stream = self.stream.as_mut().unwrap();
         debug!("Sending command with short timeout: {}", command);
 
@@ -147,11 +1370,12 @@ impl Connection {
         let command_with_newline = format!("{}\n", command);
         stream.write_all(command_with_newline.as_bytes()).await?;
         stream.flush().await?;
+        self.stats.bytes_sent += command_with_newline.len() as u64;
 
         // Use a longer timeout (2000ms) for commands that may cause connection loss
         // This gives the board enough time to complete shutdown sequence before connection is lost
         let short_timeout = Duration::from_millis(2000);
-        let response = timeout(short_timeout, async {
+        let received = timeout(short_timeout, async {
             let mut buffer = Vec::new();
             let mut temp_buf = [0u8; 1024];
 
@@ -166,12 +1390,153 @@ impl Connection {
             String::from_utf8_lossy(&buffer).to_string()
         })
         .await
-        .unwrap_or_else(|_| "Command sent (timeout expected for reset commands)".to_string());
+        .ok();
+        self.note_received(received.as_deref().unwrap_or_default().as_bytes());
+        let response = received
+            .unwrap_or_else(|| "Command sent (timeout expected for reset commands)".to_string());
 
         debug!("Received response (short timeout): {}", response);
+        self.last_response = Some(response.clone());
         Ok(response)
     }
 
+    /// Switch the controller console and the local port to `rate`
+    ///
+    /// `command` is the firmware's baud-change command line. After sending it
+    /// the port is reopened at `rate` and checked with `ping`; if that fails
+    /// the port goes back to the original rate and is checked again. Every
+    /// transition is logged and recorded in the returned [`BaudChange`].
+    pub async fn change_baud_rate(&mut self, command: &str, rate: u32) -> BaudChange {
+        let original = self.baud_rate;
+        let mut change = BaudChange::new(original, rate);
+        if rate == original {
+            debug!("Console already at {} baud", rate);
+            change.success = true;
+            return change;
+        }
+
+        // The acknowledgement may be cut short by the switch itself, so only
+        // a failure to send counts
+        let requested = self.send_command_with_short_timeout(command).await;
+        if let Ok(response) = &requested {
+            debug!("Baud change acknowledgement: {}", response);
+        }
+        let requested = requested.map(|_| ());
+        let sent = requested.is_ok();
+        change.record(BaudStage::Requested, original, rate, requested);
+        if !sent {
+            return change;
+        }
+
+        // Unknown until the controller answers at one rate or the other
+        change.link_rate = None;
+        self.phase = Phase::Reconnect;
+        tokio::time::sleep(BAUD_SWITCH_SETTLE).await;
+        let switched = match self.reopen_at(rate) {
+            Ok(()) => self.verify_link().await,
+            Err(e) => Err(e),
+        };
+        if switched.is_ok() {
+            change.record(BaudStage::Switched, original, rate, switched);
+            change.success = true;
+            change.link_rate = Some(rate);
+            return change;
+        }
+        change.record(BaudStage::Switched, original, rate, switched);
+
+        self.phase = Phase::Reconnect;
+        let rolled_back = match self.reopen_at(original) {
+            Ok(()) => self.verify_link().await,
+            Err(e) => Err(e),
+        };
+        if rolled_back.is_ok() {
+            change.link_rate = Some(original);
+        }
+        change.record(BaudStage::RolledBack, rate, original, rolled_back);
+        change
+    }
+
+    /// Check the controller answers `ping` at the current rate
+    async fn verify_link(&mut self) -> Result<()> {
+        if self.dry_run {
+            return Ok(());
+        }
+        self.phase = Phase::Verification;
+        let response = self.send_command("ping").await;
+        self.phase = Phase::Command;
+        let response = response?;
+        if response.to_lowercase().contains("pong") {
+            Ok(())
+        } else {
+            Err(PowerCliError::InvalidResponse { response })
+        }
+    }
+
+    /// Discard any bytes waiting in the receive buffer
+    ///
+    /// Reads until no data has arrived for 200 ms and returns the number of
+    /// bytes thrown away. Useful after operations that make the controller
+    /// print unsolicited output, such as a board reset.
+    pub async fn flush_rx_buffer(&mut self) -> Result<usize> {
+        if self.dry_run {
+            return Ok(0);
+        }
+
+        if self.stream.is_none() {
+            debug!("Auto-connecting to device before flushing receive buffer");
+            self.connect().await?;
+        }
+
+        let stream = self.stream.as_mut().unwrap();
+        let discarded = Self::read_available_static(stream, FLUSH_WINDOW).await?;
+        debug!("Flushed {} bytes from receive buffer", discarded.len());
+        self.note_received(&discarded);
+
+        Ok(discarded.len())
+    }
+
+    /// Console output the controller prints on its own, e.g. `pm monitor`
+    /// samples
+    ///
+    /// Waits up to `window` for output and then reads until the console has
+    /// been quiet for as long; empty if nothing arrived.
+    pub async fn read_output(&mut self, window: Duration) -> Result<String> {
+        if self.dry_run {
+            tokio::time::sleep(window).await;
+            return Ok(String::new());
+        }
+        if self.stream.is_none() {
+            self.connect().await?;
+        }
+
+        let stream = self.stream.as_mut().ok_or(PowerCliError::NotConnected)?;
+        let output = Self::read_available_static(stream, window).await?;
+        self.note_received(&output);
+        let output = String::from_utf8_lossy(&output).into_owned();
+        self.note_boot_banner(&output);
+        Ok(output)
+    }
+
+    /// Read whatever arrives until the line has been quiet for `window`
+    ///
+    /// Bounded to a few windows in total so a chattering controller cannot
+    /// stall the caller indefinitely.
+    async fn read_available_static(stream: &mut dyn SerialIo, window: Duration) -> Result<Vec<u8>> {
+        let mut buffer = Vec::new();
+        let mut temp_buf = [0u8; 1024];
+        let deadline = tokio::time::Instant::now() + window * 5;
+
+        while tokio::time::Instant::now() < deadline {
+            match timeout(window, stream.read(&mut temp_buf)).await {
+                Ok(Ok(0)) | Err(_) => break,
+                Ok(Ok(n)) => buffer.extend_from_slice(&temp_buf[..n]),
+                Ok(Err(e)) => return Err(PowerCliError::Io(e)),
+            }
+        }
+
+        Ok(buffer)
+    }
+
     /// Clean up the response by removing command echo and shell prompt
     fn clean_response(&self, response: &str, command: &str) -> String {
         let mut lines: Vec<&str> = response.lines().collect();
@@ -182,36 +1547,308 @@ impl Connection {
         }
 
         // Remove shell prompt (usually the last line)
-        if !lines.is_empty() {
-            let last_line = lines[lines.len() - 1].trim();
-            if last_line.contains("prod:~$") || last_line.contains("debug:~$") {
-                lines.pop();
-            }
+        if lines.last().is_some_and(|line| is_prompt_line(line)) {
+            lines.pop();
         }
 
         // Join remaining lines and trim
         lines.join("\n").trim().to_string()
     }
 
+    /// Remember a command for the session log
+    ///
+    /// Every command sent passes through here, so this is also where the
+    /// response cache drops entries the command may make stale and where
+    /// commands are counted.
+    fn record_command(&mut self, command: &str) {
+        if let Some(cache) = self.cache.as_mut() {
+            cache.before_send(command);
+        }
+        if !self.dry_run {
+            self.stats.record_command(command);
+        }
+        if self.commands_sent.len() == MAX_COMMANDS_RECORDED {
+            self.commands_sent.remove(0);
+        }
+        self.commands_sent.push(command.to_string());
+    }
+
+    /// Context of the last `send_command` if it failed, e.g.
+    /// `while sending 'version' to /dev/ttyLP2`
+    ///
+    /// Kept beside the error rather than inside it so callers can still
+    /// match on the [`PowerCliError`] variant.
+    pub fn error_context(&self) -> Option<&str> {
+        self.failed_send.as_deref()
+    }
+
+    /// Command of the last `send_command` if it failed, whether or not it
+    /// reached the port
+    pub fn failed_command(&self) -> Option<&str> {
+        self.failed_command.as_deref()
+    }
+
+    /// Shell commands sent during this session, oldest first
+    pub fn commands_sent(&self) -> &[String] {
+        &self.commands_sent
+    }
+
+    /// Cleaned response to the most recent command, if any
+    pub fn last_response(&self) -> Option<&str> {
+        self.last_response.as_deref()
+    }
+
+    /// Last raw bytes read from the port, at most [`RECEIVED_TAIL_LEN`]
+    pub fn received_tail(&self) -> &[u8] {
+        &self.received_tail
+    }
+
+    /// Count `bytes` read from the port and keep the tail for error reports
+    fn note_received(&mut self, bytes: &[u8]) {
+        self.stats.bytes_received += bytes.len() as u64;
+        self.received_tail.extend_from_slice(bytes);
+        let excess = self.received_tail.len().saturating_sub(RECEIVED_TAIL_LEN);
+        self.received_tail.drain(..excess);
+    }
+
+    /// Timing of the last [`Connection::send_command`] that reached the wire
+    ///
+    /// `None` for a cached or dry-run reply, or one that never arrived.
+    pub fn last_round_trip(&self) -> Option<RoundTrip> {
+        self.last_round_trip
+    }
+
     /// Check if connection is active
     #[allow(dead_code)] // Future use
     pub fn is_connected(&self) -> bool {
         self.stream.is_some()
     }
 
-    /// Disconnect from the serial device
-    #[allow(dead_code)] // Future use
-    pub async fn disconnect(&mut self) {
-        if let Some(_stream) = self.stream.take() {
-            debug!("Disconnected from {}", self.device_path);
+    /// Flush pending output and close the port
+    ///
+    /// Every exit path calls this before the connection is dropped, so the
+    /// port is released (and a stopped holder started again) before the
+    /// next command opens it. Closing twice is harmless.
+    pub async fn close(&mut self) {
+        if let Some(mut stream) = self.stream.take() {
+            if let Err(e) = stream.flush().await {
+                debug!("Flushing {} before close failed: {}", self.device_path, e);
+            }
+            drop(stream);
+            debug!("Closed {}", self.device_path);
         }
+        self.stolen = None;
+    }
+
+    /// Former name of [`Self::close`]
+    #[allow(dead_code)] // Kept for library callers
+    #[deprecated(
+        since = "2.5.0",
+        note = "use `close`, which also flushes pending output"
+    )]
+    pub async fn disconnect(&mut self) {
+        self.close().await;
     }
 }
 
 impl Drop for Connection {
+    /// Best-effort close for a connection nobody called [`Connection::close`] on
+    ///
+    /// The flush is polled once without waiting. Debug builds panic instead
+    /// when `EINK_POWER_CLI_ASSERT_CLOSED` is set, so tests catch the leak.
     fn drop(&mut self) {
-        if self.stream.is_some() {
-            debug!("Connection automatically closed on drop");
+        let Some(mut stream) = self.stream.take() else {
+            return;
+        };
+        if cfg!(debug_assertions)
+            && std::env::var_os("EINK_POWER_CLI_ASSERT_CLOSED").is_some()
+            && !std::thread::panicking()
+        {
+            panic!("connection to {} dropped without close()", self.device_path);
+        }
+
+        warn!(
+            "Connection to {} was not closed; closing it on drop",
+            self.device_path
+        );
+        let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+        match std::pin::Pin::new(&mut stream).poll_flush(&mut context) {
+            std::task::Poll::Ready(Ok(())) => {}
+            std::task::Poll::Ready(Err(e)) => {
+                warn!("Flushing {} on drop failed: {}", self.device_path, e)
+            }
+            std::task::Poll::Pending => {
+                warn!("Output to {} still pending when closed", self.device_path)
+            }
+        }
+    }
+}
+
+/// Round-trip latency statistics from [`Connection::measure_round_trip_latency`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    pub min_ms: u64,
+    pub max_ms: u64,
+    pub avg_ms: f64,
+    pub std_dev_ms: f64,
+    /// Individual round trips in milliseconds, in the order measured
+    pub samples_ms: Vec<f64>,
+}
+
+impl LatencyStats {
+    /// Compute statistics over the given round trips (population std dev)
+    pub fn from_samples(samples: &[Duration]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let samples_ms: Vec<f64> = samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        let n = samples_ms.len() as f64;
+        let avg_ms = samples_ms.iter().sum::<f64>() / n;
+        let variance = samples_ms.iter().map(|v| (v - avg_ms).powi(2)).sum::<f64>() / n;
+
+        Some(Self {
+            min_ms: samples.iter().min()?.as_millis() as u64,
+            max_ms: samples.iter().max()?.as_millis() as u64,
+            avg_ms,
+            std_dev_ms: variance.sqrt(),
+            samples_ms,
+        })
+    }
+
+    /// Round trip at quantile `q` (0.0..=1.0) using nearest-rank
+    pub fn quantile_ms(&self, q: f64) -> f64 {
+        let mut sorted = self.samples_ms.clone();
+        sorted.sort_by(f64::total_cmp);
+        let rank = (q.clamp(0.0, 1.0) * sorted.len() as f64).ceil() as usize;
+        sorted[rank.saturating_sub(1).min(sorted.len() - 1)]
+    }
+
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        format!(
+            "Samples: {}\nMin: {} ms\nMax: {} ms\nAverage: {:.1} ms\nStd Dev: {:.1} ms",
+            self.samples_ms.len(),
+            self.min_ms,
+            self.max_ms,
+            self.avg_ms,
+            self.std_dev_ms
+        )
+    }
+}
+
+/// Step of a console baud-rate change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BaudStage {
+    /// Baud-change command sent at the old rate
+    Requested,
+    /// Local port reopened at the new rate and checked with `ping`
+    Switched,
+    /// Local port returned to the old rate after the new one failed
+    RolledBack,
+    /// Local port returned to the old rate after a temporary change
+    Restored,
+}
+
+/// One recorded rate transition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaudTransition {
+    pub stage: BaudStage,
+    pub from: u32,
+    pub to: u32,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of [`Connection::change_baud_rate`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaudChange {
+    pub success: bool,
+    pub original_rate: u32,
+    pub requested_rate: u32,
+    /// Whether the new rate was asked to survive a controller reset
+    pub persist: bool,
+    /// Rate the controller last answered at; `None` if it stopped answering
+    pub link_rate: Option<u32>,
+    pub transitions: Vec<BaudTransition>,
+}
+
+impl BaudChange {
+    fn new(original_rate: u32, requested_rate: u32) -> Self {
+        Self {
+            success: false,
+            original_rate,
+            requested_rate,
+            persist: false,
+            link_rate: Some(original_rate),
+            transitions: Vec::new(),
+        }
+    }
+
+    /// Log a transition and add it to the record
+    pub fn record(&mut self, stage: BaudStage, from: u32, to: u32, result: Result<()>) {
+        let error = result.err().map(|e| e.to_string());
+        match &error {
+            None => info!("Baud {:?}: {} -> {} ok", stage, from, to),
+            Some(e) => warn!("Baud {:?}: {} -> {} failed: {}", stage, from, to, e),
+        }
+        self.transitions.push(BaudTransition {
+            stage,
+            from,
+            to,
+            ok: error.is_none(),
+            error,
+        });
+    }
+
+    /// Why the change failed and how to recover; `None` on success
+    pub fn failure_message(&self) -> Option<String> {
+        if self.success {
+            return None;
+        }
+        let (original, requested) = (self.original_rate, self.requested_rate);
+        Some(match self.link_rate {
+            Some(rate) if rate == original => format!(
+                "controller did not switch to {} baud; link restored at {} baud",
+                requested, original
+            ),
+            _ => {
+                let power_cycle = if self.persist {
+                    "the rate was persisted, so a power cycle will not restore it"
+                } else {
+                    "or power-cycle the controller to return to its default rate"
+                };
+                format!(
+                    "controller stopped answering while switching from {} to {} baud.\n\
+                     Recover manually: retry with `--baud {}` and run `system set-baud {}`, \
+                     {}",
+                    original, requested, requested, original, power_cycle
+                )
+            }
+        })
+    }
+
+    /// Format for human-readable display
+    pub fn format_human(&self) -> String {
+        let mut lines = vec![format!(
+            "{} -> {} baud{}: {}",
+            self.original_rate,
+            self.requested_rate,
+            if self.persist { " (persistent)" } else { "" },
+            if self.success { "OK" } else { "FAILED" }
+        )];
+        for t in &self.transitions {
+            let outcome = match &t.error {
+                None => "ok".to_string(),
+                Some(e) => format!("failed: {}", e),
+            };
+            lines.push(format!(
+                "  {:?}: {} -> {} {}",
+                t.stage, t.from, t.to, outcome
+            ));
         }
+        lines.join("\n")
     }
 }
@@ -0,0 +1,209 @@
+/*
+ * E-ink Power CLI - Console Quirks
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Consoles left misconfigured by a terminal program
+//!
+//! After a session in minicom or similar, the PMU shell can be left with
+//! local echo on, so every line it prints arrives twice, or with CRLF
+//! translation on, so every line ending arrives twice and a blank line
+//! follows each line. The reply to the `ping` of the connect handshake is
+//! known (the echoed command, `pong`, a prompt), so it is the probe:
+//! [`detect`] compares it with what a sane console prints, and every later
+//! reply goes through [`normalize`] before its echo and prompt are stripped.
+//! The echo checked before a destructive command runs goes through
+//! [`normalize_echo`].
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A way the console departs from one echo and one line ending per line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsoleQuirk {
+    /// Every line arrives twice
+    DuplicatedLines,
+    /// Every line ending arrives twice, leaving a blank line after each line
+    DoubledLineEndings,
+}
+
+impl ConsoleQuirk {
+    /// Shell command that turns the setting behind the quirk off, on
+    /// firmware that has it
+    pub fn fix(self) -> &'static str {
+        match self {
+            ConsoleQuirk::DuplicatedLines => "shell local_echo off",
+            ConsoleQuirk::DoubledLineEndings => "shell crlf off",
+        }
+    }
+}
+
+impl fmt::Display for ConsoleQuirk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConsoleQuirk::DuplicatedLines => "every line duplicated (local echo)",
+            ConsoleQuirk::DoubledLineEndings => "doubled line endings (CRLF translation)",
+        })
+    }
+}
+
+/// `quirks` as a comma-separated list
+pub fn describe(quirks: &[ConsoleQuirk]) -> String {
+    quirks
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Complete lines of `raw` without their line endings, and the text after
+/// the last one (usually the prompt)
+fn split_lines(raw: &str) -> (Vec<&str>, &str) {
+    let mut lines: Vec<&str> = raw.split('\n').collect();
+    let tail = lines.pop().unwrap_or_default();
+    let lines = lines
+        .into_iter()
+        .map(|line| line.trim_end_matches('\r'))
+        .collect();
+    (lines, tail)
+}
+
+/// Quirks shown by `raw`, the reply to the probe `command` whose output is
+/// `expected`
+///
+/// Output before the echo (or `expected` when the shell does not echo),
+/// such as log lines or a boot banner, is left out of the comparison.
+pub fn detect(raw: &str, command: &str, expected: &str) -> Vec<ConsoleQuirk> {
+    let (lines, _) = split_lines(raw);
+    let Some(start) = lines
+        .iter()
+        .position(|line| line.trim() == command.trim() || line.trim() == expected)
+    else {
+        return Vec::new();
+    };
+    let lines = &lines[start..];
+
+    let mut quirks = Vec::new();
+    // A probe reply has no blank lines of its own
+    let doubled = lines
+        .iter()
+        .enumerate()
+        .all(|(index, line)| line.trim().is_empty() == (index % 2 == 1))
+        && lines.len() >= 2;
+    if doubled {
+        quirks.push(ConsoleQuirk::DoubledLineEndings);
+    }
+    let content: Vec<&str> = lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+    let duplicated = content.len() >= 2
+        && content.len().is_multiple_of(2)
+        && content.chunks(2).all(|pair| pair[0] == pair[1]);
+    if duplicated {
+        quirks.push(ConsoleQuirk::DuplicatedLines);
+    }
+    quirks
+}
+
+/// `raw` as a console without `quirks` would have printed it
+///
+/// Doubled line endings leave `2n - 1` blank lines where there were
+/// `n - 1`; duplicated lines come in pairs, so each pair is kept once. A
+/// line without its twin (a log line printed between them) is kept as is.
+pub fn normalize(raw: &str, quirks: &[ConsoleQuirk]) -> String {
+    if quirks.is_empty() {
+        return raw.to_string();
+    }
+    let (lines, tail) = split_lines(raw);
+
+    let mut kept: Vec<&str> = Vec::with_capacity(lines.len());
+    if quirks.contains(&ConsoleQuirk::DoubledLineEndings) {
+        let mut blanks = 0;
+        for line in &lines {
+            if line.trim().is_empty() {
+                blanks += 1;
+                continue;
+            }
+            kept.extend(std::iter::repeat_n("", blanks / 2));
+            blanks = 0;
+            kept.push(line);
+        }
+        kept.extend(std::iter::repeat_n("", blanks / 2));
+    } else {
+        kept = lines;
+    }
+
+    if quirks.contains(&ConsoleQuirk::DuplicatedLines) {
+        let mut deduplicated = Vec::with_capacity(kept.len() / 2 + 1);
+        let mut index = 0;
+        while index < kept.len() {
+            deduplicated.push(kept[index]);
+            let twin = kept.get(index + 1).is_some_and(|next| *next == kept[index]);
+            index += if twin { 2 } else { 1 };
+        }
+        kept = deduplicated;
+    }
+
+    let mut normalized = String::with_capacity(raw.len());
+    for line in kept {
+        normalized.push_str(line);
+        normalized.push_str("\r\n");
+    }
+    normalized.push_str(tail);
+    normalized
+}
+
+/// Bytes the shell echoes for `typed_len` bytes typed without Enter on a
+/// console with `quirks`
+pub fn echo_len(typed_len: usize, quirks: &[ConsoleQuirk]) -> usize {
+    if quirks.contains(&ConsoleQuirk::DuplicatedLines) {
+        typed_len * 2
+    } else {
+        typed_len
+    }
+}
+
+/// `echo` of `typed`, typed without Enter, as a console without `quirks`
+/// would have echoed it
+///
+/// With local echo every piece of the line arrives twice as it is echoed,
+/// so `pm` may come back as `pmpm` or `ppmm`. An echo that is no such
+/// doubling of `typed` is returned as received, to be reported as
+/// corrupted. Doubled line endings leave an echo alone: nothing typed ends
+/// a line.
+pub fn normalize_echo(echo: &[u8], typed: &[u8], quirks: &[ConsoleQuirk]) -> Vec<u8> {
+    if quirks.contains(&ConsoleQuirk::DuplicatedLines) && doubles(echo, typed) {
+        typed.to_vec()
+    } else {
+        echo.to_vec()
+    }
+}
+
+/// Whether `echo` is `typed` split into pieces, each printed twice
+fn doubles(echo: &[u8], typed: &[u8]) -> bool {
+    if echo.len() != typed.len() * 2 {
+        return false;
+    }
+    // `reached[j]`: the first `j` typed bytes account for the first `2j`
+    // echoed ones
+    let mut reached = vec![false; typed.len() + 1];
+    reached[0] = true;
+    for start in 0..typed.len() {
+        if !reached[start] {
+            continue;
+        }
+        for end in start + 1..=typed.len() {
+            let piece = &typed[start..end];
+            let first = start * 2..start + end;
+            let second = start + end..end * 2;
+            if &echo[first] == piece && &echo[second] == piece {
+                reached[end] = true;
+            }
+        }
+    }
+    reached[typed.len()]
+}
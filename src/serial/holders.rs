@@ -0,0 +1,183 @@
+/*
+ * E-ink Power CLI - Port Holders
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Other processes with the serial port open
+//!
+//! On development boards a login getty often owns the PMU console, and its
+//! prompts interleave with our commands. Before opening the port the
+//! connection looks through the open file descriptors in `/proc`, as
+//! `fuser` does, and refuses to share the port with another process.
+//!
+//! `--steal` stops the systemd unit of the holder instead and starts it
+//! again when the connection is dropped, on error paths included.
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+/// How long a stopped unit gets to close the port
+pub const RELEASE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A process holding the serial port open
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortHolder {
+    pub pid: u32,
+    /// Executable name (`/proc/<pid>/comm`)
+    pub name: String,
+    /// Command line, arguments separated by spaces
+    pub cmdline: String,
+    /// systemd unit the process runs in, e.g. `serial-getty@ttyLP2.service`
+    pub unit: Option<String>,
+}
+
+impl fmt::Display for PortHolder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (pid {}", self.name, self.pid)?;
+        if let Some(unit) = &self.unit {
+            write!(f, ", unit {}", unit)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// Processes other than this one holding `device` open
+pub fn holders(device: &str) -> Vec<PortHolder> {
+    find_holders(Path::new("/proc"), Path::new(device), std::process::id())
+}
+
+/// Processes in the `/proc` tree at `proc_root`, other than `own_pid`,
+/// with a file descriptor open on `device`
+///
+/// Processes whose descriptors cannot be read (another user's, without
+/// root) are skipped.
+pub fn find_holders(proc_root: &Path, device: &Path, own_pid: u32) -> Vec<PortHolder> {
+    let device = fs::canonicalize(device).unwrap_or_else(|_| device.to_path_buf());
+    let Ok(entries) = fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+    let mut holders: Vec<PortHolder> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+            (pid != own_pid && holds(&entry.path(), &device)).then(|| describe(&entry.path(), pid))
+        })
+        .collect();
+    holders.sort_by_key(|holder| holder.pid);
+    holders
+}
+
+fn holds(process: &Path, device: &Path) -> bool {
+    let Ok(fds) = fs::read_dir(process.join("fd")) else {
+        return false;
+    };
+    fds.flatten()
+        .filter_map(|fd| fs::read_link(fd.path()).ok())
+        .any(|target| target == device)
+}
+
+fn describe(process: &Path, pid: u32) -> PortHolder {
+    let read = |name: &str| fs::read_to_string(process.join(name)).unwrap_or_default();
+    let cmdline = read("cmdline")
+        .split('\0')
+        .filter(|arg| !arg.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    PortHolder {
+        pid,
+        name: read("comm").trim().to_string(),
+        cmdline,
+        unit: unit_of(&read("cgroup")),
+    }
+}
+
+/// systemd service from the contents of `/proc/<pid>/cgroup`
+///
+/// Only the innermost cgroup counts: a process in a scope or slice below a
+/// service (an app under `user@1000.service`) does not belong to that
+/// service, and stopping it would take the whole user session down.
+fn unit_of(cgroup: &str) -> Option<String> {
+    cgroup
+        .lines()
+        .filter_map(|line| line.rsplit(':').next())
+        .filter_map(|path| path.rsplit('/').next())
+        .find(|unit| unit.ends_with(".service"))
+        .map(String::from)
+}
+
+/// systemctl executable, overridable with `EINK_POWER_CLI_SYSTEMCTL`
+fn systemctl_program() -> String {
+    std::env::var("EINK_POWER_CLI_SYSTEMCTL").unwrap_or_else(|_| "systemctl".to_string())
+}
+
+fn systemctl(action: &str, unit: &str) -> io::Result<()> {
+    let output = Command::new(systemctl_program())
+        .args([action, unit])
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "systemctl {} {} failed: {}",
+            action,
+            unit,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// A systemd unit stopped for `--steal`, started again on drop
+#[derive(Debug)]
+pub struct StolenUnit {
+    unit: String,
+}
+
+impl StolenUnit {
+    /// Stop `unit` and wait until `device` has no other holders
+    ///
+    /// Blocks for up to [`RELEASE_TIMEOUT`]; async callers run it with
+    /// `spawn_blocking`.
+    pub fn stop(unit: &str, device: &str) -> io::Result<Self> {
+        info!("Stopping {} to take over {}", unit, device);
+        systemctl("stop", unit)?;
+        let stolen = Self {
+            unit: unit.to_string(),
+        };
+
+        let started = Instant::now();
+        while !holders(device).is_empty() {
+            if started.elapsed() > RELEASE_TIMEOUT {
+                return Err(io::Error::other(format!(
+                    "{} still held after stopping {}",
+                    device, unit
+                )));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        Ok(stolen)
+    }
+}
+
+impl Drop for StolenUnit {
+    fn drop(&mut self) {
+        info!("Restarting {}", self.unit);
+        if let Err(e) = systemctl("start", &self.unit) {
+            warn!("{}", e);
+        }
+    }
+}
+
+/// Unit to stop for `--steal`: the configured one, else the unit of the
+/// first holder that runs in one
+pub fn unit_to_steal(configured: Option<&str>, holders: &[PortHolder]) -> Option<String> {
+    configured
+        .map(String::from)
+        .or_else(|| holders.iter().find_map(|holder| holder.unit.clone()))
+}
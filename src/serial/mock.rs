@@ -0,0 +1,301 @@
+/*
+ * E-ink Power CLI - Mock Serial Transport
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! In-memory serial transport for testing without hardware
+//!
+//! A [`MockSerial`] holds a script of expected commands and the reply to each.
+//! Every newline-terminated line written to it must match the next expected
+//! command; the scripted reply then becomes readable. Expectations left over
+//! when the mock is dropped fail the test. As in the firmware shell, Ctrl-U
+//! and Ctrl-C discard the line being written and Ctrl-C answers with a fresh
+//! prompt. A line written without its newline is echoed, twice with
+//! [`MockSerialBuilder::local_echo`]; [`MockSerialBuilder::silent`] echoes
+//! nothing. [`MockSerial::wire`] keeps every byte written for tests that
+//! check what reached the line.
+//!
+//! ```ignore
+//! let serial = MockSerial::builder()
+//!     .expect("ping", "ping\r\npong\r\nprod:~$ ")
+//!     .build();
+//! let mut connection = Connection::mock(serial);
+//! ```
+
+use crate::error::PowerCliError;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::Sleep;
+
+/// What the shell prints when Ctrl-C abandons the input line
+const FRESH_PROMPT: &str = "\r\nprod:~$ ";
+
+/// Scripted reply to one command
+#[derive(Debug)]
+pub enum MockResponse {
+    /// Reply immediately with these bytes
+    Ok(String),
+    /// Fail the next read with this error
+    Err(PowerCliError),
+    /// Reply with these bytes after a delay
+    Delay(Duration, String),
+    /// Reply in pieces, each this long after the command
+    Chunks(Vec<(Duration, String)>),
+}
+
+/// Reply that has been triggered but not fully read yet
+enum Pending {
+    Data(Vec<u8>),
+    Error(PowerCliError),
+    Delayed(Pin<Box<Sleep>>, String),
+}
+
+/// `reply` becoming readable `delay` from now
+fn delayed(delay: Duration, reply: String) -> Pending {
+    Pending::Delayed(Box::pin(tokio::time::sleep(delay)), reply)
+}
+
+/// In-memory serial port following a script of commands and replies
+pub struct MockSerial {
+    expected: VecDeque<(String, MockResponse)>,
+    /// Expected commands not yet received
+    pub remaining: Vec<String>,
+    written: Vec<u8>,
+    /// Bytes of `written` already echoed
+    echoed: usize,
+    /// Times each typed piece is echoed
+    echo_copies: usize,
+    /// Every byte written, shared with [`MockSerial::wire`] handles
+    wire: Arc<Mutex<Vec<u8>>>,
+    pending: VecDeque<Pending>,
+    read_waker: Option<Waker>,
+}
+
+impl MockSerial {
+    /// Start building a script
+    #[allow(dead_code)] // Used by tests
+    pub fn builder() -> MockSerialBuilder {
+        MockSerialBuilder::default()
+    }
+
+    /// Every byte written to the mock, readable after it has moved into a
+    /// connection
+    #[allow(dead_code)] // Used by tests
+    pub fn wire(&self) -> Arc<Mutex<Vec<u8>>> {
+        Arc::clone(&self.wire)
+    }
+
+    /// Match a complete line written by the client against the script
+    ///
+    /// The echo at the start of the scripted reply is dropped if the line
+    /// was echoed as it was typed.
+    fn handle_line(&mut self, line: &str, echoed: bool) {
+        let (command, response) = match self.expected.pop_front() {
+            Some(expectation) => expectation,
+            None => panic!("MockSerial: unexpected command '{}'", line),
+        };
+        assert_eq!(line, command, "MockSerial: command out of order");
+        self.remaining.remove(0);
+
+        let response = match response {
+            MockResponse::Ok(reply) if echoed => {
+                MockResponse::Ok(reply.strip_prefix(line).unwrap_or(&reply).to_string())
+            }
+            response => response,
+        };
+        match response {
+            MockResponse::Ok(reply) => self.pending.push_back(Pending::Data(reply.into_bytes())),
+            MockResponse::Err(e) => self.pending.push_back(Pending::Error(e)),
+            MockResponse::Delay(delay, reply) => self.pending.push_back(delayed(delay, reply)),
+            MockResponse::Chunks(chunks) => self.pending.extend(
+                chunks
+                    .into_iter()
+                    .map(|(delay, chunk)| delayed(delay, chunk)),
+            ),
+        }
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl AsyncRead for MockSerial {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        loop {
+            match this.pending.front_mut() {
+                None => {
+                    this.read_waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+                Some(Pending::Delayed(sleep, reply)) => {
+                    if sleep.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                    let reply = std::mem::take(reply).into_bytes();
+                    this.pending[0] = Pending::Data(reply);
+                }
+                Some(Pending::Data(data)) => {
+                    let n = data.len().min(buf.remaining());
+                    buf.put_slice(&data[..n]);
+                    data.drain(..n);
+                    if data.is_empty() {
+                        this.pending.pop_front();
+                    }
+                    return Poll::Ready(Ok(()));
+                }
+                Some(Pending::Error(_)) => {
+                    let Some(Pending::Error(e)) = this.pending.pop_front() else {
+                        unreachable!()
+                    };
+                    return Poll::Ready(Err(io::Error::other(e)));
+                }
+            }
+        }
+    }
+}
+
+impl AsyncWrite for MockSerial {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.wire.lock().unwrap().extend_from_slice(buf);
+        for &byte in buf {
+            match byte {
+                // Ctrl-U clears the line; Ctrl-C also prints a fresh prompt
+                0x15 => {
+                    self.written.clear();
+                    self.echoed = 0;
+                }
+                0x03 => {
+                    self.written.clear();
+                    self.echoed = 0;
+                    self.pending
+                        .push_back(Pending::Data(FRESH_PROMPT.as_bytes().to_vec()));
+                    if let Some(waker) = self.read_waker.take() {
+                        waker.wake();
+                    }
+                }
+                b'\n' => {
+                    let line = String::from_utf8_lossy(&self.written).trim().to_string();
+                    let echoed = self.echoed > 0;
+                    self.written.clear();
+                    self.echoed = 0;
+                    if !line.is_empty() {
+                        self.handle_line(&line, echoed);
+                    }
+                }
+                _ => self.written.push(byte),
+            }
+        }
+        // A line typed without Enter is echoed as the shell would
+        if self.echoed < self.written.len() && self.echo_copies > 0 {
+            let typed = self.written[self.echoed..].repeat(self.echo_copies);
+            self.echoed = self.written.len();
+            self.pending.push_back(Pending::Data(typed));
+            if let Some(waker) = self.read_waker.take() {
+                waker.wake();
+            }
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for MockSerial {
+    fn drop(&mut self) {
+        if !self.remaining.is_empty() && !std::thread::panicking() {
+            panic!(
+                "MockSerial dropped with unconsumed expectations: {:?}",
+                self.remaining
+            );
+        }
+    }
+}
+
+/// Builder for [`MockSerial`]
+#[derive(Default)]
+pub struct MockSerialBuilder {
+    expected: VecDeque<(String, MockResponse)>,
+    echo_copies: Option<usize>,
+}
+
+#[allow(dead_code)] // Used by tests
+impl MockSerialBuilder {
+    /// Expect `command` and reply with `reply`
+    pub fn expect(self, command: &str, reply: &str) -> Self {
+        self.respond(command, MockResponse::Ok(reply.to_string()))
+    }
+
+    /// Expect `command` and fail the following read with `error`
+    pub fn expect_error(self, command: &str, error: PowerCliError) -> Self {
+        self.respond(command, MockResponse::Err(error))
+    }
+
+    /// Expect `command` and reply with `reply` after `delay`
+    pub fn expect_delayed(self, command: &str, delay: Duration, reply: &str) -> Self {
+        self.respond(command, MockResponse::Delay(delay, reply.to_string()))
+    }
+
+    /// Expect `command` and reply with `chunks`, each after its delay from
+    /// the command
+    pub fn expect_chunks(self, command: &str, chunks: &[(Duration, &str)]) -> Self {
+        let chunks = chunks
+            .iter()
+            .map(|(delay, chunk)| (*delay, chunk.to_string()))
+            .collect();
+        self.respond(command, MockResponse::Chunks(chunks))
+    }
+
+    /// Expect `command` with an arbitrary scripted response
+    pub fn respond(mut self, command: &str, response: MockResponse) -> Self {
+        self.expected.push_back((command.to_string(), response));
+        self
+    }
+
+    /// Echo typed text twice, as a shell with local echo left on
+    pub fn local_echo(mut self) -> Self {
+        self.echo_copies = Some(2);
+        self
+    }
+
+    /// Echo nothing typed, as a shell with echo off
+    pub fn silent(mut self) -> Self {
+        self.echo_copies = Some(0);
+        self
+    }
+
+    /// Finish the script
+    pub fn build(self) -> MockSerial {
+        MockSerial {
+            remaining: self.expected.iter().map(|(cmd, _)| cmd.clone()).collect(),
+            expected: self.expected,
+            written: Vec::new(),
+            echoed: 0,
+            echo_copies: self.echo_copies.unwrap_or(1),
+            wire: Arc::default(),
+            pending: VecDeque::new(),
+            read_waker: None,
+        }
+    }
+}
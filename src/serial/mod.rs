@@ -6,8 +6,21 @@
 
 //! Serial communication module for interfacing with the MCXC143VFM power controller
 
+pub mod banner;
+pub mod cache;
+pub mod command_map;
 pub mod connection;
+pub mod console;
+pub mod holders;
+pub mod mock;
 pub mod protocol;
+pub mod stats;
+pub mod timeouts;
 
-pub use connection::Connection;
+pub use command_map::{CommandFamily, CommandMap};
+pub use connection::{BaudChange, Connection, EchoCheck, LatencyStats, ResyncMode, TimeoutRetry};
+#[allow(unused_imports)] // Used by tests
+pub use mock::{MockResponse, MockSerial};
 pub use protocol::Protocol;
+pub use stats::ConnectionStats;
+pub use timeouts::TimeoutPolicy;
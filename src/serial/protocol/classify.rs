@@ -0,0 +1,40 @@
+/*
+ * E-ink Power CLI - Reply Classification
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Sorting a controller reply into success, no-op or failure
+//!
+//! [`CLASSES`] is checked in order and the first matching row wins. Replies
+//! matching no row are successes. The already-in-state row comes first
+//! because older firmware reports a no-op as an error (`Error: WiFi already
+//! enabled`), which scripts switching a rail on twice must not see as a
+//! failure.
+
+use crate::json::patterns::{self, Pattern};
+
+/// What a reply says about the command that produced it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseClass {
+    /// The command ran
+    Success,
+    /// The target was already in the requested state; nothing changed
+    Unchanged,
+    /// The controller rejected the command or it failed
+    Failure,
+}
+
+/// Reply patterns in the order they are checked
+pub static CLASSES: &[(ResponseClass, &Pattern)] = &[
+    (ResponseClass::Unchanged, &patterns::ALREADY_IN_STATE),
+    (ResponseClass::Failure, &patterns::CONTROLLER_ERROR),
+];
+
+/// Class of the first row of [`CLASSES`] matching `response`
+pub fn classify(response: &str) -> ResponseClass {
+    CLASSES
+        .iter()
+        .find(|(_, pattern)| pattern.is_match(response))
+        .map_or(ResponseClass::Success, |(class, _)| *class)
+}
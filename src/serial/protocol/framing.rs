@@ -0,0 +1,50 @@
+/*
+ * E-ink Power CLI - Binary Framing
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Length-prefixed frames for the binary controller protocol
+//!
+//! Each frame is a 2-byte big-endian payload length followed by the payload,
+//! so a frame carries at most [`MAX_FRAME_PAYLOAD`] bytes.
+
+/// Size of the length header in front of every payload
+pub const FRAME_HEADER_LEN: usize = 2;
+
+/// Largest payload a frame can carry
+pub const MAX_FRAME_PAYLOAD: usize = u16::MAX as usize;
+
+/// Prepend the length header to `payload`
+///
+/// # Panics
+///
+/// If `payload` is longer than [`MAX_FRAME_PAYLOAD`].
+pub fn encode_frame(payload: &[u8]) -> Vec<u8> {
+    let len = u16::try_from(payload.len()).unwrap_or_else(|_| {
+        panic!(
+            "frame payload of {} bytes exceeds {}",
+            payload.len(),
+            MAX_FRAME_PAYLOAD
+        )
+    });
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Split the first complete frame off `buf`
+///
+/// Returns `(payload, remainder)`, or `None` if `buf` does not yet hold a
+/// whole frame.
+#[allow(dead_code)] // Used by tests
+pub fn decode_frame(buf: &[u8]) -> Option<(&[u8], &[u8])> {
+    let header = buf.get(..FRAME_HEADER_LEN)?;
+    let len = u16::from_be_bytes([header[0], header[1]]) as usize;
+    let end = FRAME_HEADER_LEN + len;
+    if buf.len() < end {
+        return None;
+    }
+    Some((&buf[FRAME_HEADER_LEN..end], &buf[end..]))
+}
@@ -0,0 +1,435 @@
+/*
+ * E-ink Power CLI - Communication Protocol
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+pub mod classify;
+pub mod framing;
+
+use crate::error::{PowerCliError, Result};
+use crate::json::{parse_integer, patterns};
+use crate::power::rails::Rail;
+use crate::serial::{
+    BaudChange, CommandFamily, CommandMap, Connection, LatencyStats, TimeoutPolicy,
+};
+use classify::ResponseClass;
+use log::debug;
+use serde_json::Value;
+
+/// Protocol handler for communicating with the power management controller
+pub struct Protocol {
+    connection: Connection,
+    commands: CommandMap,
+    timeouts: TimeoutPolicy,
+    binary_mode: bool,
+}
+
+impl Protocol {
+    /// Create a new protocol instance
+    pub fn new(connection: Connection) -> Self {
+        Self {
+            connection,
+            commands: CommandMap::default(),
+            timeouts: TimeoutPolicy::default(),
+            binary_mode: false,
+        }
+    }
+
+    /// Send commands as length-prefixed binary frames instead of text lines
+    ///
+    /// Text mode is the default; only firmware that speaks the binary
+    /// protocol understands frames.
+    #[allow(dead_code)] // For future firmware with the binary protocol
+    pub fn enable_binary_mode(&mut self, enabled: bool) {
+        self.binary_mode = enabled;
+    }
+
+    /// Send a shell command in the current mode and return the reply text
+    ///
+    /// The command gets its own timeout from the [`TimeoutPolicy`]; the
+    /// connection timeout is restored afterwards. Commands with a guard time
+    /// hold off the next write, whether or not their reply arrived.
+    async fn send_command(&mut self, command: &str) -> Result<String> {
+        let default = self.connection.timeout();
+        let (limit, source) = self.timeouts.timeout_for(command, default);
+        debug!(
+            "Timeout for '{}': {:.1} s ({})",
+            command,
+            limit.as_secs_f64(),
+            source
+        );
+        self.connection.set_timeout_duration(limit);
+        let response = self.send_in_mode(command).await;
+        self.connection.set_timeout_duration(default);
+        if let Some(guard) = self.timeouts.guard_for(command) {
+            debug!(
+                "Guard time after '{}': {} ms before the next command",
+                command,
+                guard.as_millis()
+            );
+            self.connection.hold_off(guard);
+        }
+        response
+    }
+
+    async fn send_in_mode(&mut self, command: &str) -> Result<String> {
+        if !self.binary_mode {
+            return self.connection.send_command(command).await;
+        }
+        let payload = self.connection.send_frame(command).await?;
+        String::from_utf8(payload).map_err(|e| PowerCliError::InvalidResponse {
+            response: format!("reply frame is not UTF-8: {}", e),
+        })
+    }
+
+    /// Per-command timeouts over the connection timeout
+    pub fn set_timeout_policy(&mut self, timeouts: TimeoutPolicy) {
+        self.timeouts = timeouts;
+    }
+
+    /// Use remapped shell root commands (for forked firmware)
+    pub fn set_command_map(&mut self, commands: CommandMap) {
+        self.commands = commands;
+    }
+
+    /// Shell root commands in use
+    pub fn command_map(&self) -> &CommandMap {
+        &self.commands
+    }
+
+    /// Underlying serial connection
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Underlying serial connection, mutable
+    pub fn connection_mut(&mut self) -> &mut Connection {
+        &mut self.connection
+    }
+
+    /// Discard stale bytes waiting on the connection
+    pub async fn flush_rx_buffer(&mut self) -> Result<usize> {
+        self.connection.flush_rx_buffer().await
+    }
+
+    /// Execute a system command
+    pub async fn execute_system_command(&mut self, command: &str) -> Result<String> {
+        let command = self.commands.apply(command);
+        debug!("Executing system command: {}", command);
+
+        let response = self.send_command(&command).await?;
+        self.parse_response(&response)
+    }
+
+    /// Execute a power control command
+    /// Note: PMU firmware uses 'pm' command, not 'power' command
+    pub async fn execute_power_command(&mut self, rail: Rail, state: &str) -> Result<String> {
+        let command = self.commands.command(
+            CommandFamily::Pm,
+            &format!("{} {}", rail.as_shell_str(), state),
+        );
+        debug!("Executing power command: {}", command);
+
+        let response = self.send_command(&command).await?;
+        self.parse_response(&response)
+    }
+
+    /// Execute a battery monitoring command
+    #[allow(dead_code)] // Future use
+    pub async fn execute_battery_command(&mut self, command: &str) -> Result<String> {
+        let full_command = self.commands.command(CommandFamily::Ltc2959, command);
+
+        debug!("Executing battery command: {}", full_command);
+
+        let response = self.send_command(&full_command).await?;
+        self.parse_response(&response)
+    }
+
+    /// Execute a GPIO command
+    pub async fn execute_gpio_command(
+        &mut self,
+        action: &str,
+        port: &str,
+        pin: u8,
+        value: Option<u8>,
+    ) -> Result<String> {
+        let command = match action {
+            "get" => self
+                .commands
+                .command(CommandFamily::Gpio, &format!("get {} {}", port, pin)),
+            "set" => {
+                let val = value.ok_or_else(|| PowerCliError::InvalidCommand {
+                    command: "GPIO set requires a value".to_string(),
+                })?;
+                self.commands.command(
+                    CommandFamily::Gpio,
+                    &format!("set {} {} {}", port, pin, val),
+                )
+            }
+            _ => {
+                return Err(PowerCliError::InvalidCommand {
+                    command: format!("Unknown GPIO action: {}", action),
+                })
+            }
+        };
+
+        debug!("Executing GPIO command: {}", command);
+
+        let response = self.send_command(&command).await?;
+        self.parse_response(&response)
+    }
+
+    /// Execute an NFC command
+    pub async fn execute_nfc_command(&mut self, command: &str) -> Result<String> {
+        let full_command = self.commands.command(CommandFamily::Nfc, command);
+        debug!("Executing NFC command: {}", full_command);
+
+        let response = self.send_command(&full_command).await?;
+        self.parse_response(&response)
+    }
+
+    /// Execute a board control command
+    pub async fn execute_board_command(&mut self, command: &str) -> Result<String> {
+        let full_command = self.commands.command(CommandFamily::Board, command);
+        debug!("Executing board command: {}", full_command);
+
+        // Special handling for reset and shutdown commands - they will cause connection loss
+        if command == "reset" || command == "shutdown" {
+            return self
+                .execute_board_power_command(&full_command, command)
+                .await;
+        }
+
+        let response = self.send_command(&full_command).await?;
+        self.parse_response(&response)
+    }
+
+    /// Execute board power command (reset/shutdown) with special handling for connection loss
+    async fn execute_board_power_command(&mut self, command: &str, action: &str) -> Result<String> {
+        debug!("Executing board {} command with short timeout", action);
+
+        // Send the command but don't wait for a full response since the board will power off
+        let _response = self
+            .connection
+            .send_command_with_short_timeout(command)
+            .await?;
+
+        // Return appropriate success message based on action
+        match action {
+            "reset" => Ok(
+                "Board reset sequence initiated. Connection will be lost during power cycle."
+                    .to_string(),
+            ),
+            "shutdown" => Ok(
+                "Board shutdown sequence initiated. System will power off permanently.".to_string(),
+            ),
+            _ => Ok(format!("Board {} command executed", action)),
+        }
+    }
+
+    /// Execute an LTC2959 coulomb counter command
+    pub async fn execute_ltc2959_command(&mut self, command: &str) -> Result<String> {
+        let full_command = self.commands.command(CommandFamily::Ltc2959, command);
+        debug!("Executing LTC2959 command: {}", full_command);
+
+        let response = self.send_command(&full_command).await?;
+        self.parse_response(&response)
+    }
+
+    /// Parse the response from the controller
+    ///
+    /// A rejected command may have been mangled by stray input, so the
+    /// input line is cleared before the next one. A reply saying the target
+    /// was already in the requested state is a success.
+    fn parse_response(&mut self, response: &str) -> Result<String> {
+        debug!("Parsing response: {}", response);
+
+        match classify::classify(response) {
+            ResponseClass::Failure => {
+                self.connection.request_resync();
+                Err(PowerCliError::ControllerError {
+                    message: response.to_string(),
+                })
+            }
+            ResponseClass::Unchanged => {
+                debug!("Already in the requested state");
+                Ok(response.to_string())
+            }
+            ResponseClass::Success => Ok(response.to_string()),
+        }
+    }
+
+    /// Parse battery data from response
+    #[allow(dead_code)] // Future use
+    pub fn parse_battery_data(&self, response: &str) -> Result<BatteryData> {
+        debug!("Parsing battery data from: {}", response);
+
+        // TODO: Implement actual parsing based on controller response format
+        // This is a placeholder implementation
+        Ok(BatteryData {
+            voltage_mv: 3850,
+            current_ma: 125,
+            charge_mah: 2450,
+            temperature_c: 23,
+        })
+    }
+
+    /// Format response as JSON
+    #[allow(dead_code)] // Future use
+    pub fn format_as_json(&self, data: &str) -> Result<Value> {
+        // TODO: Implement JSON formatting
+        // For now, create a simple JSON structure
+        let json = serde_json::json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "status": "success",
+            "data": data
+        });
+
+        Ok(json)
+    }
+
+    /// Execute a power management command
+    pub async fn execute_pm_command(&mut self, command: &str) -> Result<String> {
+        let full_command = self.commands.command(CommandFamily::Pm, command);
+        debug!("Executing PM command: {}", full_command);
+
+        let response = self.send_command(&full_command).await?;
+        self.parse_response(&response)
+    }
+
+    /// Measure round-trip latency of the serial link
+    pub async fn measure_round_trip_latency(&mut self, samples: u8) -> Result<LatencyStats> {
+        self.connection.measure_round_trip_latency(samples).await
+    }
+
+    /// Switch the controller console to `rate` and follow it locally
+    pub async fn change_baud_rate(&mut self, rate: u32, persist: bool) -> BaudChange {
+        let command = self.commands.apply(&baud_command(rate, persist));
+        debug!("Changing console baud rate: {}", command);
+
+        let mut change = self.connection.change_baud_rate(&command, rate).await;
+        change.persist = persist;
+        change
+    }
+
+    /// Execute a wake/sleep style action on a peripheral (e.g. `ltc2959 wake`)
+    ///
+    /// The command is sent as-is, without the `pm` prefix.
+    pub async fn execute_device_action_command(
+        &mut self,
+        device: &str,
+        action: &str,
+    ) -> Result<String> {
+        let command = self.commands.apply(&device_action_command(device, action));
+        debug!("Executing device action command: {}", command);
+
+        let response = self.send_command(&command).await?;
+        self.parse_response(&response)
+    }
+
+    /// Execute a communication control command
+    pub async fn execute_comm_command(&mut self, signal: &str, state: &str) -> Result<String> {
+        let command = self
+            .commands
+            .command(CommandFamily::Comm, &format!("{} {}", signal, state));
+        debug!("Executing comm command: {}", command);
+
+        let response = self.send_command(&command).await?;
+        self.parse_response(&response)
+    }
+
+    /// Execute an RTC command
+    pub async fn execute_rtc_command(&mut self, command: &str) -> Result<RtcResponse> {
+        let full_command = self.commands.command(CommandFamily::Rtc, command);
+        debug!("Executing RTC command: {}", full_command);
+
+        let response = self.send_command(&full_command).await?;
+        let raw = self.parse_response(&response)?;
+        Ok(RtcResponse::parse(&raw))
+    }
+}
+
+/// Shell command line for a peripheral action, e.g. `ltc2959 sleep`
+pub fn device_action_command(device: &str, action: &str) -> String {
+    format!("{} {}", device.trim(), action.trim())
+}
+
+/// Shell command line switching the console to `rate`, e.g. `system baud 921600`
+///
+/// With `persist` the firmware keeps the rate across resets.
+pub fn baud_command(rate: u32, persist: bool) -> String {
+    if persist {
+        format!("system baud {} --persist", rate)
+    } else {
+        format!("system baud {}", rate)
+    }
+}
+
+/// Structured response to an RTC command
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // Not every field is consumed by the CLI yet
+pub struct RtcResponse {
+    /// Response text as returned by the controller
+    pub raw: String,
+    /// Internal RTC counter value, if reported
+    pub counter: Option<u32>,
+    /// Whether an alarm is armed, if reported
+    pub alarm_set: Option<bool>,
+    /// External RTC interrupt action, if reported
+    pub config_action: Option<String>,
+}
+
+impl RtcResponse {
+    /// Parse an RTC command response
+    ///
+    /// Recognizes `... counter: 12345` / `Uptime: 12345` lines (or a bare
+    /// number), `Alarm: Set|Not set|Enabled|Disabled` and
+    /// `Interrupt Action: WAKE`. Fields the controller did not report are `None`.
+    pub fn parse(raw: &str) -> Self {
+        let counter = patterns::RTC_COUNTER
+            .captures(raw)
+            .and_then(|caps| parse_integer(&caps[1]))
+            .or_else(|| parse_integer(raw.trim()))
+            .and_then(|v| u32::try_from(v).ok());
+
+        let alarm_set = patterns::RTC_ALARM.captures(raw).and_then(|caps| {
+            let value = caps[1].trim().to_lowercase();
+            if value.starts_with("not")
+                || ["no", "none", "off", "disabled", "inactive", "cleared"]
+                    .iter()
+                    .any(|v| value.starts_with(v))
+            {
+                Some(false)
+            } else if ["yes", "set", "on", "enabled", "active", "armed"]
+                .iter()
+                .any(|v| value.starts_with(v))
+            {
+                Some(true)
+            } else {
+                None
+            }
+        });
+
+        let config_action = patterns::RTC_ACTION
+            .captures(raw)
+            .map(|caps| caps[1].trim().to_string());
+
+        Self {
+            raw: raw.to_string(),
+            counter,
+            alarm_set,
+            config_action,
+        }
+    }
+}
+
+/// Battery monitoring data structure
+#[derive(Debug, Clone)]
+#[allow(dead_code)] // Future use
+pub struct BatteryData {
+    pub voltage_mv: u16,
+    pub current_ma: i16,
+    pub charge_mah: u32,
+    pub temperature_c: i16,
+}
@@ -0,0 +1,120 @@
+/*
+ * E-ink Power CLI - Connection Statistics
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Counters of the traffic on a serial connection
+//!
+//! A [`Connection`](crate::serial::Connection) counts what it sends and
+//! receives, how often the controller timed out, how often a command had to
+//! be sent again and how often the port was reopened. Counting is plain
+//! integer arithmetic on values the connection already has at hand, so it
+//! costs nothing on the wire.
+//!
+//! At the end of every invocation the session counters are added to a
+//! running total in the device state directory, which `stats` prints and
+//! `stats --reset` starts again.
+
+use crate::state::{DeviceState, StateFile, StatePayload};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// State file holding the running total
+pub const STATS_FILE: &str = "link_stats.json";
+
+/// Running total for a serial device; `None` without a state directory
+pub fn stored(device: &str) -> Option<StateFile<ConnectionStats>> {
+    DeviceState::for_device(device).map(|state| state.file(STATS_FILE))
+}
+
+/// Cumulative counters of one connection, or of several added together
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionStats {
+    /// When counting started
+    pub since: Option<DateTime<Utc>>,
+    pub bytes_sent: u64,
+    /// Including stale output drained before a command
+    pub bytes_received: u64,
+    /// Commands written to the port; cached and dry-run replies are not
+    /// counted
+    pub commands: u64,
+    pub timeouts: u64,
+    /// Commands sent again after the shell dropped them
+    pub retries: u64,
+    /// Times the port was opened again after the first connect
+    pub reconnects: u64,
+    /// Replies whose first byte was timed
+    pub timed_replies: u64,
+    /// Sum of the time to the first reply byte over the timed replies
+    pub latency_total_us: u64,
+    /// Mean time to the first reply byte; `None` before the first reply
+    pub avg_latency_ms: Option<f64>,
+    /// Commands per root command (`pm`, `ltc2959`, `ping`, ...)
+    pub families: BTreeMap<String, u64>,
+}
+
+impl StatePayload for ConnectionStats {
+    const SCHEMA_VERSION: u32 = 1;
+}
+
+impl ConnectionStats {
+    /// Empty counters starting now
+    pub fn starting_now() -> Self {
+        Self {
+            since: Some(Utc::now()),
+            ..Default::default()
+        }
+    }
+
+    /// Whether nothing has been counted
+    pub fn is_empty(&self) -> bool {
+        self.commands == 0 && self.bytes_sent == 0 && self.bytes_received == 0
+    }
+
+    /// Count a command going out on the wire
+    pub fn record_command(&mut self, command: &str) {
+        self.commands += 1;
+        let root = command.split_whitespace().next().unwrap_or_default();
+        match self.families.get_mut(root) {
+            Some(count) => *count += 1,
+            None => {
+                self.families.insert(root.to_string(), 1);
+            }
+        }
+    }
+
+    /// Count the time from a command to the first byte of its reply
+    pub fn record_latency(&mut self, first_byte: Duration) {
+        self.timed_replies += 1;
+        self.latency_total_us += first_byte.as_micros() as u64;
+        self.update_average();
+    }
+
+    /// Add `other` to these counters, keeping the earlier start
+    pub fn merge(&mut self, other: &ConnectionStats) {
+        self.since = match (self.since, other.since) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.bytes_sent += other.bytes_sent;
+        self.bytes_received += other.bytes_received;
+        self.commands += other.commands;
+        self.timeouts += other.timeouts;
+        self.retries += other.retries;
+        self.reconnects += other.reconnects;
+        self.timed_replies += other.timed_replies;
+        self.latency_total_us += other.latency_total_us;
+        for (root, count) in &other.families {
+            *self.families.entry(root.clone()).or_default() += count;
+        }
+        self.update_average();
+    }
+
+    fn update_average(&mut self) {
+        self.avg_latency_ms = (self.timed_replies > 0)
+            .then(|| self.latency_total_us as f64 / self.timed_replies as f64 / 1000.0);
+    }
+}
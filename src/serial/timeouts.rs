@@ -0,0 +1,178 @@
+/*
+ * E-ink Power CLI - Command Timeouts
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! How long to wait for each shell command
+//!
+//! Commands differ by orders of magnitude: `ping` answers in well under a
+//! second while `pm system erase app` takes around 20 s. Rather than one
+//! timeout long enough for the slowest command, each command gets the
+//! timeout of its longest matching entry, in this order:
+//!
+//! 1. `--timeout` on the command line, for every command
+//! 2. the `[timeouts]` table of the configuration file
+//! 3. [`DEFAULT_TIMEOUTS`]
+//! 4. the connection timeout (`[connection] timeout`, default 3 s)
+//!
+//! An entry such as `system erase` matches commands whose words start with
+//! it, from the first word or from the second, so the shell root (`pm`, or
+//! a remapped name) may be left out.
+//!
+//! The same table holds guard times: after `pm sleep` or `system reset` the
+//! PMU drops or reinitializes its UART, and bytes sent in the meantime are
+//! lost. The connection sends nothing until the guard time of the command
+//! has passed, taken from the `[guards]` table of the configuration file
+//! (milliseconds, `0` for none) or [`DEFAULT_GUARDS`]. `--no-guard` turns
+//! guards off. Baud changes have their own settle time and need no entry.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Worst-case durations of the slow commands, in seconds
+pub const DEFAULT_TIMEOUTS: &[(&str, u64)] =
+    &[("system erase", 30), ("nfc init", 8), ("battery_check", 10)];
+
+/// Time the PMU UART is unusable after these commands, in milliseconds
+pub const DEFAULT_GUARDS: &[(&str, u64)] = &[("pm sleep", 500), ("system reset", 1500)];
+
+/// Where a command's timeout came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeoutSource {
+    /// `--timeout`
+    CommandLine,
+    /// `[timeouts]` entry of the configuration file
+    Config(String),
+    /// [`DEFAULT_TIMEOUTS`] entry
+    Table(&'static str),
+    /// The connection timeout
+    Default,
+}
+
+impl fmt::Display for TimeoutSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimeoutSource::CommandLine => write!(f, "--timeout"),
+            TimeoutSource::Config(entry) => write!(f, "config [timeouts] \"{}\"", entry),
+            TimeoutSource::Table(entry) => write!(f, "built-in \"{}\"", entry),
+            TimeoutSource::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// Per-command timeouts over the connection timeout
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimeoutPolicy {
+    /// `[timeouts]` entries by command prefix
+    config: BTreeMap<String, Duration>,
+    /// `--timeout`, which applies to every command
+    forced: Option<Duration>,
+    /// `[guards]` entries by command prefix
+    guards: BTreeMap<String, Duration>,
+    /// `--no-guard`
+    no_guard: bool,
+}
+
+impl TimeoutPolicy {
+    /// Add the `[timeouts]` entries of the configuration file (seconds)
+    pub fn with_config(mut self, entries: &BTreeMap<String, u64>) -> Self {
+        self.config = entries
+            .iter()
+            .map(|(prefix, secs)| (normalize(prefix), Duration::from_secs(*secs)))
+            .collect();
+        self
+    }
+
+    /// Use `timeout` for every command, as `--timeout` does
+    pub fn with_forced(mut self, timeout: Option<Duration>) -> Self {
+        self.forced = timeout;
+        self
+    }
+
+    /// Add the `[guards]` entries of the configuration file (milliseconds)
+    pub fn with_guards(mut self, entries: &BTreeMap<String, u64>) -> Self {
+        self.guards = entries
+            .iter()
+            .map(|(prefix, ms)| (normalize(prefix), Duration::from_millis(*ms)))
+            .collect();
+        self
+    }
+
+    /// Send commands right after those that reset the UART, as `--no-guard` does
+    pub fn without_guards(mut self, disabled: bool) -> Self {
+        self.no_guard = disabled;
+        self
+    }
+
+    /// Time to send nothing after `command`, if any
+    pub fn guard_for(&self, command: &str) -> Option<Duration> {
+        if self.no_guard {
+            return None;
+        }
+        let from_config = longest_match(
+            command,
+            self.guards
+                .iter()
+                .map(|(prefix, guard)| (prefix.as_str(), *guard)),
+        );
+        let (_, guard) = from_config.or_else(|| {
+            longest_match(
+                command,
+                DEFAULT_GUARDS
+                    .iter()
+                    .map(|(prefix, ms)| (*prefix, Duration::from_millis(*ms))),
+            )
+        })?;
+        Some(guard).filter(|guard| !guard.is_zero())
+    }
+
+    /// Timeout for `command` and where it came from; `default` is the
+    /// connection timeout
+    pub fn timeout_for(&self, command: &str, default: Duration) -> (Duration, TimeoutSource) {
+        if let Some(timeout) = self.forced {
+            return (timeout, TimeoutSource::CommandLine);
+        }
+        let from_config = longest_match(
+            command,
+            self.config
+                .iter()
+                .map(|(prefix, timeout)| (prefix.as_str(), *timeout)),
+        );
+        if let Some((prefix, timeout)) = from_config {
+            return (timeout, TimeoutSource::Config(prefix.to_string()));
+        }
+        let from_table = longest_match(
+            command,
+            DEFAULT_TIMEOUTS
+                .iter()
+                .map(|(prefix, secs)| (*prefix, Duration::from_secs(*secs))),
+        );
+        match from_table {
+            Some((prefix, timeout)) => (timeout, TimeoutSource::Table(prefix)),
+            None => (default, TimeoutSource::Default),
+        }
+    }
+}
+
+/// Words of `prefix`, single-spaced
+fn normalize(prefix: &str) -> String {
+    prefix.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Entry with the most words among those matching `command`
+fn longest_match<'a>(
+    command: &str,
+    entries: impl Iterator<Item = (&'a str, Duration)>,
+) -> Option<(&'a str, Duration)> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    entries
+        .filter(|(prefix, _)| {
+            let prefix: Vec<&str> = prefix.split_whitespace().collect();
+            !prefix.is_empty()
+                && (words.starts_with(&prefix)
+                    || words.get(1..).is_some_and(|rest| rest.starts_with(&prefix)))
+        })
+        .max_by_key(|(prefix, _)| prefix.split_whitespace().count())
+}
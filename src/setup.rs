@@ -0,0 +1,374 @@
+/*
+ * E-ink Power CLI - First-Run Setup
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `setup`: from a fresh install to a working configuration file
+//!
+//! The wizard picks the serial port, checks that the controller answers on
+//! it, asks for the default options and saves them with [`Config::save`], so
+//! the file reads back through [`Config::load`] unchanged. It finishes by
+//! checking the new setup end to end.
+//!
+//! Questions go to stderr and answers come from stdin, which leaves stdout
+//! for the report. [`Prompter`] works over any reader and writer so the
+//! questions can be answered from a script in tests. With `--yes` nothing is
+//! asked and the answers come from the command line.
+
+use crate::cli::OutputFormat;
+use crate::config::Config;
+use crate::error::{PowerCliError, Result};
+use crate::json::ResponseParser;
+use crate::power::control::PowerController;
+use crate::serial::Connection;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Instant;
+
+/// Serial ports found on the host, sorted by path
+pub fn serial_ports() -> Vec<String> {
+    let mut ports: Vec<String> = serialport::available_ports()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|port| port.port_name)
+        .collect();
+    ports.sort();
+    ports.dedup();
+    ports
+}
+
+/// Prompter on the terminal: questions on stderr, answers from stdin
+pub type TerminalPrompter = Prompter<io::StdinLock<'static>, io::Stderr>;
+
+/// Prompter on the terminal
+pub fn terminal() -> TerminalPrompter {
+    Prompter::new(io::stdin().lock(), io::stderr())
+}
+
+/// Questions on `output`, answers read a line at a time from `input`
+pub struct Prompter<R, W> {
+    input: R,
+    output: W,
+}
+
+impl<R: BufRead, W: Write> Prompter<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self { input, output }
+    }
+
+    /// Tell the user something between questions
+    pub fn say(&mut self, text: &str) -> io::Result<()> {
+        writeln!(self.output, "{}", text)
+    }
+
+    /// Ask `question`; an empty answer takes `default`
+    ///
+    /// Fails at the end of the input rather than asking forever.
+    pub fn ask(&mut self, question: &str, default: &str) -> io::Result<String> {
+        if default.is_empty() {
+            write!(self.output, "{}: ", question)?;
+        } else {
+            write!(self.output, "{} [{}]: ", question, default)?;
+        }
+        self.output.flush()?;
+
+        let mut answer = String::new();
+        if self.input.read_line(&mut answer)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "input ended before setup finished",
+            ));
+        }
+        let answer = answer.trim();
+        Ok(if answer.is_empty() { default } else { answer }.to_string())
+    }
+
+    /// Ask until the answer parses; an empty answer takes `default`, or
+    /// leaves the value unset when there is none
+    pub fn ask_parsed<T>(&mut self, question: &str, default: Option<T>) -> io::Result<Option<T>>
+    where
+        T: FromStr + ToString + Clone,
+    {
+        let shown = default.as_ref().map(T::to_string).unwrap_or_default();
+        loop {
+            let answer = self.ask(question, &shown)?;
+            if answer.is_empty() {
+                return Ok(default);
+            }
+            match answer.parse() {
+                Ok(value) => return Ok(Some(value)),
+                Err(_) => writeln!(self.output, "'{}' is not a valid answer", answer)?,
+            }
+        }
+    }
+
+    /// Yes/no question
+    pub fn confirm(&mut self, question: &str, default: bool) -> io::Result<bool> {
+        let hint = if default { "Y/n" } else { "y/N" };
+        loop {
+            let answer = self.ask(&format!("{} ({})", question, hint), "")?;
+            match answer.to_ascii_lowercase().as_str() {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => writeln!(self.output, "Please answer y or n")?,
+            }
+        }
+    }
+
+    /// Pick one of `ports` by number, or type a path
+    pub fn choose_port(&mut self, ports: &[String], default: &str) -> io::Result<String> {
+        if ports.is_empty() {
+            writeln!(self.output, "No serial ports found")?;
+        } else {
+            writeln!(self.output, "Serial ports:")?;
+            for (i, port) in ports.iter().enumerate() {
+                writeln!(self.output, "  {}) {}", i + 1, port)?;
+            }
+        }
+        let answer = self.ask("Serial port (number or path)", default)?;
+        Ok(match answer.parse::<usize>() {
+            Ok(n) if (1..=ports.len()).contains(&n) => ports[n - 1].clone(),
+            _ => answer,
+        })
+    }
+
+    /// Output format, by its `--format` name
+    pub fn choose_format(&mut self, default: &OutputFormat) -> io::Result<OutputFormat> {
+        let names: Vec<String> = OutputFormat::value_variants()
+            .iter()
+            .filter_map(|format| format.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        let default = format_name(default);
+        loop {
+            let answer = self.ask(&format!("Output format ({})", names.join(", ")), &default)?;
+            match OutputFormat::from_str(&answer, true) {
+                Ok(format) => return Ok(format),
+                Err(_) => writeln!(self.output, "'{}' is not an output format", answer)?,
+            }
+        }
+    }
+}
+
+/// `--format` name of `format`
+fn format_name(format: &OutputFormat) -> String {
+    format
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
+}
+
+/// What the wizard saves
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetupAnswers {
+    pub device: String,
+    pub format: OutputFormat,
+    pub timeout: u64,
+    pub capacity_mah: Option<u32>,
+    pub profile: Option<String>,
+}
+
+impl SetupAnswers {
+    /// Ask for the defaults, offering the current answers
+    pub fn ask<R: BufRead, W: Write>(&self, prompter: &mut Prompter<R, W>) -> io::Result<Self> {
+        let format = prompter.choose_format(&self.format)?;
+        let timeout = prompter
+            .ask_parsed("Command timeout in seconds", Some(self.timeout))?
+            .unwrap_or(self.timeout);
+        let capacity_mah = prompter.ask_parsed("Battery capacity in mAh", self.capacity_mah)?;
+        let profile = prompter.ask("Profile name", self.profile.as_deref().unwrap_or(""))?;
+        Ok(Self {
+            device: self.device.clone(),
+            format,
+            timeout,
+            capacity_mah,
+            profile: Some(profile).filter(|profile| !profile.is_empty()),
+        })
+    }
+
+    /// `config` with these answers filled in; other settings are kept
+    pub fn apply(&self, mut config: Config) -> Config {
+        config.profile = self.profile.clone();
+        config.connection.device = Some(self.device.clone());
+        config.connection.timeout = Some(self.timeout);
+        config.output.format = Some(self.format.clone());
+        config.battery.capacity_mah = self.capacity_mah;
+        config
+    }
+}
+
+/// Outcome of one check of the new setup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetupCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl SetupCheck {
+    fn new(name: &str, result: std::result::Result<String, String>) -> Self {
+        let passed = result.is_ok();
+        Self {
+            name: name.to_string(),
+            passed,
+            detail: result.unwrap_or_else(|e| e),
+        }
+    }
+}
+
+/// Result of `setup`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetupReport {
+    /// Configuration file written
+    pub config_path: PathBuf,
+    /// Contents of the file
+    pub config: Config,
+    pub checks: Vec<SetupCheck>,
+}
+
+impl SetupReport {
+    /// Checks that did not pass
+    pub fn failed_checks(&self) -> Vec<&str> {
+        self.checks
+            .iter()
+            .filter(|check| !check.passed)
+            .map(|check| check.name.as_str())
+            .collect()
+    }
+}
+
+/// Check the link, the firmware and the battery gauge on the new setup
+///
+/// Stops after the link check if the controller does not answer.
+pub async fn run_checks(controller: &mut PowerController) -> Vec<SetupCheck> {
+    let started = Instant::now();
+    let link = controller
+        .ping()
+        .await
+        .map(|_| format!("PMU answered in {} ms", started.elapsed().as_millis()))
+        .map_err(|e| e.to_string());
+    let mut checks = vec![SetupCheck::new("Serial link", link)];
+    if !checks[0].passed {
+        return checks;
+    }
+
+    let firmware = controller
+        .get_system_info()
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|response| {
+            let info = ResponseParser::parse_system_info(&response);
+            match (info.version_info.semver, info.build_type) {
+                (Some(version), Some(build)) => Ok(format!("{} ({:?} build)", version, build)),
+                (Some(version), None) => Ok(version),
+                _ => Err("version not reported".to_string()),
+            }
+        });
+    checks.push(SetupCheck::new("Firmware", firmware));
+
+    let gauge = controller
+        .battery_read()
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(
+            |response| match ResponseParser::parse_battery_response(&response).voltage_mv {
+                Some(mv) => Ok(format!("{} mV", mv)),
+                None => Err("no voltage in the reply".to_string()),
+            },
+        );
+    checks.push(SetupCheck::new("Battery gauge", gauge));
+    checks
+}
+
+/// The `setup` command
+pub struct Setup {
+    /// Configuration file to write
+    pub path: PathBuf,
+    /// Replace an existing file
+    pub force: bool,
+    pub baud: u32,
+    /// Used as they are with `--yes`, offered as defaults otherwise
+    pub answers: SetupAnswers,
+}
+
+impl Setup {
+    /// Run the wizard, asking through `prompter` or, without one, taking
+    /// the answers as given
+    ///
+    /// Nothing is written if the file exists and `force` is not set, or if
+    /// the controller does not answer and the user does not save anyway.
+    pub async fn run<R: BufRead, W: Write>(
+        self,
+        mut prompter: Option<&mut Prompter<R, W>>,
+    ) -> Result<SetupReport> {
+        if self.path.exists() && !self.force {
+            return Err(PowerCliError::InvalidArguments {
+                message: format!(
+                    "{} already exists; pass --force to replace it",
+                    self.path.display()
+                ),
+            });
+        }
+
+        let mut answers = self.answers.clone();
+        if let Some(prompter) = prompter.as_deref_mut() {
+            answers.device = prompter.choose_port(&serial_ports(), &answers.device)?;
+        }
+
+        let mut connection = Connection::new(&answers.device, self.baud, true)?;
+        connection.set_timeout(answers.timeout);
+        connection.enable_response_cache(crate::serial::cache::DEFAULT_CACHE_TTL);
+        let mut controller = PowerController::new(connection);
+        let report = self.configure(&mut controller, answers, prompter).await;
+        controller.close().await;
+        report
+    }
+
+    /// Check the controller answers, take the remaining answers and save
+    async fn configure<R: BufRead, W: Write>(
+        self,
+        controller: &mut PowerController,
+        mut answers: SetupAnswers,
+        mut prompter: Option<&mut Prompter<R, W>>,
+    ) -> Result<SetupReport> {
+        if let Err(e) = controller.ping().await {
+            let save_anyway = match prompter.as_deref_mut() {
+                Some(prompter) => {
+                    prompter.say(&format!(
+                        "No answer from the PMU on {}: {}",
+                        answers.device, e
+                    ))?;
+                    prompter.confirm("Save the configuration anyway?", false)?
+                }
+                None => false,
+            };
+            if !save_anyway {
+                return Err(e);
+            }
+        }
+
+        if let Some(prompter) = prompter {
+            answers = answers.ask(prompter)?;
+        }
+
+        // Keep settings the wizard does not ask about, such as [commands]
+        let existing = match self.path.exists() {
+            true => Config::load(Some(&self.path)).unwrap_or_default(),
+            false => Config::default(),
+        };
+        let config = answers.apply(existing);
+        config.save(&self.path)?;
+
+        let checks = run_checks(controller).await;
+        Ok(SetupReport {
+            config_path: self.path,
+            config,
+            checks,
+        })
+    }
+}
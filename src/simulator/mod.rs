@@ -0,0 +1,1123 @@
+/*
+ * E-ink Power CLI - PMU Simulator
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Scripted PMU shell on a pseudo-terminal, for tests and `simulate`
+//!
+//! The simulator holds the master side of a PTY pair and answers on it like
+//! the controller firmware: it echoes each command, prints a canned reply
+//! and finishes with the shell prompt. Clients open [`PmuSimulator::device`]
+//! as an ordinary serial port. [`Faults`] bend that behaviour to exercise
+//! timeouts, log noise, truncated replies and prompt variants;
+//! [`Faults::scenario`] picks a set of them for demos without a board.
+//!
+//! Every one of the firmware's [`ROOT_COMMANDS`] has replies here, so a
+//! command the CLI gains works against the simulator too.
+
+pub mod smp;
+
+use crate::cli::SimScenario;
+use crate::power::passthrough::crc32;
+use serialport::{SerialPort, TTYPort};
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Prompt printed by production firmware
+pub const PROD_PROMPT: &str = "prod:~$ ";
+
+/// Prompt printed by debug firmware
+#[allow(dead_code)] // Used by tests
+pub const DEBUG_PROMPT: &str = "debug:~$ ";
+
+/// Firmware version the simulator reports unless told otherwise
+pub const FIRMWARE_VERSION: &str = "2.5.0-+1234abc.42";
+
+pub const VERSION_REPLY: &str = "Board: MCXC143VFM E-Ink Power Controller
+SoC: NXP MCXC143VFM (ARM Cortex-M0+)
+Version: 2.5.0-+1234abc.42
+Build: 2025-10-09 11:13:59 UTC
+Build Type: Production";
+
+pub const BATTERY_REPLY: &str = "📊 LTC2959 Measurements:
+   🔋 Voltage: 3850 mV
+   ⚡ Current: -125 mA
+   🔋 Charge: 2450 mAh
+   ⚡ Power: -481 mW";
+
+/// Accumulated charge in [`BATTERY_REPLY`] until `ltc2959 set_charge`
+pub const INITIAL_CHARGE_MAH: u16 = 2450;
+
+pub const GPIO_REPLY: &str = "GPIO A5: 1";
+
+pub const PM_STATS_REPLY: &str = "📊 Power Management Statistics:
+PMIC: ON
+WiFi: OFF
+Display: ON
+Sleep cycles: 4
+LTC2959: Smart Sleep
+NFC: Sleep";
+
+/// Counters a quarter of [`INITIAL_UPTIME`] awake, with wakes by source
+pub const POWER_STATS_REPLY: &str = "Power statistics:
+Active time: 900000 ms
+Sleep count: 4
+Wake count: 4
+RTC wakes: 2
+NFC wakes: 1
+UART wakes: 1";
+
+pub const DEFAULTS_REPLY: &str = "Power rail defaults (saved in flash):
+PMIC: ON
+WiFi: OFF
+DISP: ON";
+
+pub const RTC_REPLY: &str = "🕐 RTC Status:
+Internal RTC (LPTMR) Status: Running, Wake events: 12
+External RTC (PCF2131) Status: OK, Interrupt events: 3
+Interrupt Action: AUTO
+Last Wake Source: External RTC";
+
+/// `pm battery_check` reply; the verdict line comes from [`Faults::battery_verdict`]
+pub const BATTERY_CHECK_REPLY: &str = "🔋 Battery Health Check:
+Unloaded Voltage: 3850 mV
+Loaded Voltage: 3712 mV
+Internal Resistance: 138 mOhm
+Load Test: PASS";
+
+/// `system erase app` reply, redrawing each slot's progress bar with `\r`
+pub const ERASE_APP_REPLY: &str = "Erasing application slots
+slot0: [          ]   0%\rslot0: [#####     ]  50%\rslot0: [##########] 100%
+slot1: [          ]   0%\rslot1: [##########] 100%\r\x1b[Kslot1: erased
+Erased 2 slots (475136 bytes) in 3.2 s";
+
+/// Blocks of NTA5332 EEPROM user memory, erased at start
+pub const EEPROM_BLOCKS: usize = 512;
+
+/// Banner the firmware prints when it boots
+pub const BOOT_BANNER: &str = "*** Booting Zephyr OS build v3.7.0 ***";
+
+/// Log lines the firmware prints after [`BOOT_BANNER`]
+pub const BOOT_LOG: &str = "[00:00:00.001,000] <inf> main: E-Ink Power Controller
+[00:00:00.001,000] <inf> main: Version: 2.5.0-+1234abc.42
+[00:00:00.002,000] <inf> main: Reset cause: watchdog";
+
+/// LTC2959 ADC mode names by `ltc2959 adc_mode` value
+pub const ADC_MODE_NAMES: [&str; 7] = [
+    "Sleep",
+    "Smart Sleep",
+    "Continuous V",
+    "Continuous I",
+    "Continuous V/I",
+    "Single V",
+    "Single I",
+];
+
+/// Uptime the simulated PMU has when the simulator starts
+pub const INITIAL_UPTIME: Duration = Duration::from_secs(3600);
+
+/// Time between `Faults::monitor_output` lines
+const MONITOR_LINE_GAP: Duration = Duration::from_millis(20);
+
+/// Time the UART drops input after `pm sleep` replies, while it is
+/// reinitialized
+pub const SLEEP_UART_REINIT: Duration = Duration::from_millis(300);
+
+/// Log line the firmware prints asynchronously
+pub const LOG_LINE: &str = "[00:01:07.427,000] <inf> power_mgmt: battery check";
+
+/// Battery voltage in [`BATTERY_REPLY`]
+pub const BATTERY_MV: u16 = 3850;
+
+/// Battery voltage and charge of [`SimScenario::LowBattery`]
+pub const LOW_BATTERY_MV: u16 = 3350;
+pub const LOW_BATTERY_CHARGE_MAH: u16 = 180;
+
+/// Top-level shell commands of the controller firmware, as `help` lists them
+pub const ROOT_COMMANDS: [(&str, &str); 12] = [
+    ("board", "Board control commands"),
+    ("comm", "Communication signal commands"),
+    ("gpio", "GPIO commands"),
+    ("help", "Prints the help message"),
+    ("ltc2959", "LTC2959 coulomb counter commands"),
+    ("nfc", "NTA5332 NFC commands"),
+    ("ping", "Connectivity test"),
+    ("pm", "Power management commands"),
+    ("power", "Power statistics commands"),
+    ("rtc", "RTC commands"),
+    ("system", "System commands"),
+    ("version", "Show firmware version"),
+];
+
+/// Ways the simulator departs from a healthy unit on a clean line
+#[derive(Debug, Clone)]
+pub struct Faults {
+    /// Wait before replying to any command other than `ping`
+    pub reply_delay: Duration,
+    /// Wait between the echo and the rest of the reply to any command other
+    /// than `ping`, as during a slow command
+    pub echo_stall: Duration,
+    /// Log lines printed between the echo and the reply
+    pub log_lines: Vec<String>,
+    /// Cut the reply body after this many bytes and drop the prompt
+    pub truncate_at: Option<usize>,
+    /// Prompt printed after each reply
+    pub prompt: String,
+    /// Shell disabled: print only log output, never echo or prompt
+    pub shell_disabled: bool,
+    /// Commands left unanswered after `system baud`, as if the host had not
+    /// followed the rate change
+    pub ignored_after_baud: usize,
+    /// Verdict printed by `pm battery_check`
+    pub battery_verdict: String,
+    /// Wait before every reply, `ping` included: each reply stays within
+    /// the timeout while a series of them adds up
+    pub slow_drip: Duration,
+    /// Mask reported by `pm wake config`
+    pub wake_mask: u8,
+    /// Acknowledge `nfc eeprom write` without storing the data
+    pub eeprom_read_only: bool,
+    /// Acknowledge `ltc2959 set_charge` without storing the charge
+    pub charge_read_only: bool,
+    /// Commands arriving sooner than this after the previous reply overrun
+    /// the shell input buffer: only half the line is echoed and run
+    pub min_command_gap: Duration,
+    /// Reset after replying to this many commands: print the boot banner
+    /// and restart uptime from zero
+    pub reboot_after: Option<usize>,
+    /// Firmware has `gpio batch`
+    pub gpio_batch: bool,
+    /// Bytes left in the shell input buffer after replying to this many
+    /// commands, as electrical noise on the line would
+    pub line_noise: Option<(usize, String)>,
+    /// Lines after the handshake `ping` whose second byte arrives as `~`
+    pub corrupted_lines: usize,
+    /// Reply to switching a rail to the state `pm <rail> status` already
+    /// shows, as firmware that reports no-ops does
+    pub unchanged_reply: Option<String>,
+    /// Voltages of the first `ltc2959 read`s in turn; `None` answers with
+    /// an error
+    pub battery_voltages: Vec<Option<u16>>,
+    /// Phone in the RF field for pass-through transfers, with the blob it
+    /// sends to the device
+    pub phone: Option<Vec<u8>>,
+    /// Mailboxes, counted from 0 over a transfer, during which the phone
+    /// leaves the field once
+    pub field_drops: Vec<usize>,
+    /// Just reset: the boot banner and log precede the first reply
+    pub booting: bool,
+    /// LTC2959 ADC mode in `ltc2959 status` and `pm stats` until
+    /// `ltc2959 adc_mode`
+    pub adc_mode: String,
+    /// NFC state in `pm stats`
+    pub nfc_state: String,
+    /// Console lines printed one by one after `pm monitor start`, until
+    /// `pm monitor stop`
+    pub monitor_output: Vec<String>,
+    /// Version in `version`, `system info` and the boot log
+    pub firmware_version: String,
+    /// Battery voltage in `ltc2959 read` and `pm measure`
+    pub battery_mv: u16,
+    /// Accumulated charge until `ltc2959 set_charge`
+    pub charge_mah: u16,
+    /// Phone in the NFC RF field, as `nfc status` reports it
+    pub nfc_field: bool,
+    /// In the MCUboot serial recovery bootloader: the shell is silent and
+    /// only SMP echo requests are answered
+    pub bootloader: bool,
+    /// Commands answered as unknown, as by firmware without them
+    pub unsupported: Vec<String>,
+    /// `Charger:` line in `ltc2959 status`, as firmware reporting the
+    /// charger state prints it
+    pub charger: Option<String>,
+    /// Level of the CC_GPIO charge complete input in
+    /// `ltc2959 cc_gpio status`; `None` answers it as unknown
+    pub cc_gpio: Option<bool>,
+    /// Local echo left on by a terminal program: every line printed twice,
+    /// until `shell local_echo off`
+    pub duplicated_lines: bool,
+    /// CRLF translation left on by a terminal program: every line ending
+    /// printed twice, until `shell crlf off`
+    pub doubled_line_endings: bool,
+}
+
+impl Default for Faults {
+    fn default() -> Self {
+        Self {
+            reply_delay: Duration::ZERO,
+            echo_stall: Duration::ZERO,
+            log_lines: Vec::new(),
+            truncate_at: None,
+            prompt: PROD_PROMPT.to_string(),
+            shell_disabled: false,
+            ignored_after_baud: 0,
+            battery_verdict: "HEALTHY".to_string(),
+            slow_drip: Duration::ZERO,
+            wake_mask: 0x1F,
+            eeprom_read_only: false,
+            charge_read_only: false,
+            min_command_gap: Duration::ZERO,
+            reboot_after: None,
+            gpio_batch: false,
+            line_noise: None,
+            corrupted_lines: 0,
+            unchanged_reply: None,
+            battery_voltages: Vec::new(),
+            phone: None,
+            field_drops: Vec::new(),
+            booting: false,
+            adc_mode: "Smart Sleep".to_string(),
+            nfc_state: "Sleep".to_string(),
+            monitor_output: Vec::new(),
+            firmware_version: FIRMWARE_VERSION.to_string(),
+            battery_mv: BATTERY_MV,
+            charge_mah: INITIAL_CHARGE_MAH,
+            nfc_field: false,
+            bootloader: false,
+            unsupported: Vec::new(),
+            charger: None,
+            cc_gpio: None,
+            duplicated_lines: false,
+            doubled_line_endings: false,
+        }
+    }
+}
+
+impl Faults {
+    /// The unit `scenario` describes
+    pub fn scenario(scenario: SimScenario) -> Self {
+        let normal = Self::default();
+        match scenario {
+            SimScenario::Normal => normal,
+            SimScenario::LowBattery => Self {
+                battery_mv: LOW_BATTERY_MV,
+                charge_mah: LOW_BATTERY_CHARGE_MAH,
+                battery_verdict: "DEGRADED".to_string(),
+                ..normal
+            },
+            SimScenario::BootloaderMode => Self {
+                bootloader: true,
+                ..normal
+            },
+            SimScenario::FlakySerial => Self {
+                log_lines: vec![LOG_LINE.to_string()],
+                corrupted_lines: 1,
+                line_noise: Some((3, "\x1b[0".to_string())),
+                slow_drip: Duration::from_millis(40),
+                ..normal
+            },
+        }
+    }
+}
+
+/// Canned reply to a shell command
+pub fn reply_for(command: &str) -> String {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    match words.as_slice() {
+        ["ping"] => "pong".to_string(),
+        ["help"] => help(),
+        ["version"] | ["system", "info"] => VERSION_REPLY.to_string(),
+        ["ltc2959", "read"] | ["pm", "measure"] => BATTERY_REPLY.to_string(),
+        ["gpio", "get", ..] => GPIO_REPLY.to_string(),
+        ["gpio", "set", port, pin, value] => format!(
+            "GPIO {}{} set to {}",
+            port.trim_start_matches("gpio").to_ascii_uppercase(),
+            pin,
+            value
+        ),
+        ["pm", "stats"] => PM_STATS_REPLY.to_string(),
+        ["pm", "defaults"] => DEFAULTS_REPLY.to_string(),
+        ["pm", "defaults", "save"] => "Defaults saved to flash".to_string(),
+        ["pm", "defaults", rail, state] => format!(
+            "{} default set to {}",
+            rail.to_uppercase(),
+            state.to_uppercase()
+        ),
+        ["rtc", "config", action] => {
+            format!("External RTC action set to {}", action.to_uppercase())
+        }
+        ["rtc", "status"] => RTC_REPLY.to_string(),
+        ["rtc", "get"] => "Internal RTC counter: 3600".to_string(),
+        ["rtc", "show"] => "External RTC interrupt action: AUTO".to_string(),
+        ["rtc", "calibration"] => "PCF2131 offset: 0x00".to_string(),
+        ["rtc", "calibrate", value] => format!("PCF2131 offset set to {}", value),
+        ["power", "stats"] => POWER_STATS_REPLY.to_string(),
+        ["power", "coulomb"] => format!("Coulomb counter: {} mAh", INITIAL_CHARGE_MAH),
+        ["ltc2959", action @ ("enable" | "disable")] => format!("LTC2959 {}d", action),
+        [device @ ("ltc2959" | "nfc"), action @ ("sleep" | "wake")] => {
+            format!("{} {}", device.to_uppercase(), action)
+        }
+        ["nfc", "info"] => format!(
+            "NTA5332 NFC tag\nI2C address: 0x54\nUser memory: {} bytes",
+            EEPROM_BLOCKS * 4
+        ),
+        ["nfc", "scan"] => "Scanning I2C bus\nNTA5332 found at 0x54".to_string(),
+        ["nfc", "init"] => "NFC initialized".to_string(),
+        ["nfc", action @ ("enable" | "disable")] => format!("NFC {}d", action),
+        ["nfc", "reset"] => "NFC reset".to_string(),
+        ["nfc", "debug"] => "Session 0xA0: 00 00 00 00\nSession 0xA1: 00 00 00 00".to_string(),
+        ["nfc", "ed"] => "ED pin: LOW".to_string(),
+        ["nfc", "tag_info"] => "No tag in the field".to_string(),
+        ["board", action @ ("reset" | "shutdown")] => format!("Board {} in 1 s", action),
+        ["comm", signal, state] => format!("{} {}", signal.to_uppercase(), state.to_uppercase()),
+        ["pm", "system", "erase", "app"] => ERASE_APP_REPLY.to_string(),
+        ["pm", "sleep", ..] => "Entering low power mode".to_string(),
+        ["pm", "monitor", "start", ..] => "Power monitoring started".to_string(),
+        ["pm", "monitor", "stop"] => "Power monitoring stopped".to_string(),
+        ["pm", "wake", action @ ("enable" | "disable"), source] => {
+            format!("Wake source {} {}d", source, action)
+        }
+        ["shell", setting @ ("local_echo" | "crlf"), state @ ("on" | "off")] => {
+            format!("Shell {} {}", setting, state)
+        }
+        ["system", "baud", rate, ..] => format!("Console switching to {} baud", rate),
+        ["system", "dfu-mode", timeout] => format!("Entering DFU mode (timeout {} s)", timeout),
+        ["pm", rail, "status"] if ["pmic", "wifi", "disp"].contains(rail) => {
+            format!(
+                "{}: {}",
+                rail.to_uppercase(),
+                if *rail == "wifi" { "OFF" } else { "ON" }
+            )
+        }
+        ["pm", rail, state] if ["pmic", "wifi", "disp"].contains(rail) => {
+            format!("{} power {}", rail.to_uppercase(), state.to_uppercase())
+        }
+        _ => format!("Error: unknown command '{}'", command),
+    }
+}
+
+/// `help`: the root commands with their descriptions
+fn help() -> String {
+    let mut listing = "Available commands:".to_string();
+    for (command, description) in ROOT_COMMANDS {
+        listing.push_str(&format!("\n  {:<9}:{}", command, description));
+    }
+    listing
+}
+
+/// `nfc status`, `nfc field_detect` or `nfc rfdbg` with the phone in the
+/// field or not
+fn nfc_status(command: &str, field: bool) -> String {
+    let (present, yes) = match field {
+        true => ("Present", "YES"),
+        false => ("Absent", "NO"),
+    };
+    match command {
+        "nfc status" => format!(
+            "NTA5332 Status: 0x{:02X}\nRF Field: {}\nNFC Active: {}\nI2C Ready: YES\nEEPROM: Ready\nSRAM: Idle",
+            if field { 0x03 } else { 0x02 },
+            present,
+            yes
+        ),
+        _ => format!("RF Field: {}", present),
+    }
+}
+
+/// Commands after which the firmware resets
+fn resets(command: &str) -> bool {
+    matches!(command, "system reset" | "pm system reset")
+}
+
+/// Simulated PMU running on a background thread
+pub struct PmuSimulator {
+    device: String,
+    received: Arc<Mutex<Vec<String>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PmuSimulator {
+    /// Start a well-behaved simulator
+    #[allow(dead_code)] // Used by tests
+    pub fn start() -> Self {
+        Self::with_faults(Faults::default())
+    }
+
+    /// Start a simulator with injected faults
+    pub fn with_faults(faults: Faults) -> Self {
+        let (master, slave) = TTYPort::pair().expect("create PTY pair");
+        let device = slave.name().expect("PTY slave has a path");
+        // Clients open the slave by path; keep only the master here
+        drop(slave);
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let received = Arc::clone(&received);
+            let stop = Arc::clone(&stop);
+            std::thread::spawn(move || run(master, faults, received, stop))
+        };
+
+        Self {
+            device,
+            received,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Serial device path clients should open
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    /// Commands received so far, in order
+    #[allow(dead_code)] // Used by tests
+    pub fn received(&self) -> Vec<String> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+impl Drop for PmuSimulator {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(
+    mut port: TTYPort,
+    faults: Faults,
+    received: Arc<Mutex<Vec<String>>>,
+    stop: Arc<AtomicBool>,
+) {
+    let mut line = Vec::new();
+    // Bytes of `line` echoed as they were typed
+    let mut echoed = 0;
+    let mut lines_started = 0;
+    let mut buf = [0u8; 256];
+    let mut ignoring = 0;
+    let mut eeprom = vec![0xFFu8; EEPROM_BLOCKS * 4];
+    let mut last_reply: Option<Instant> = None;
+    let mut replies = 0;
+    let mut gauge = Gauge::new(&faults);
+    let mut nfc = PassThrough::new(&faults);
+    // Boot time, shifted so uptime starts at INITIAL_UPTIME
+    let mut booted = Instant::now() - INITIAL_UPTIME;
+    // Next `monitor_output` line while `pm monitor` runs
+    let mut monitoring: Option<usize> = None;
+    // Input is lost until then, after `pm sleep`
+    let mut reinit_until: Option<Instant> = None;
+    // Terminal settings left on until the shell commands turning them off
+    let mut duplicated_lines = faults.duplicated_lines;
+    let mut doubled_line_endings = faults.doubled_line_endings;
+
+    while !stop.load(Ordering::Relaxed) {
+        if let Some(next) = monitoring.filter(|&next| next < faults.monitor_output.len()) {
+            std::thread::sleep(MONITOR_LINE_GAP);
+            let _ = port.write_all(format!("{}\r\n", faults.monitor_output[next]).as_bytes());
+            let _ = port.flush();
+            monitoring = Some(next + 1);
+        }
+        let n = match port.read(&mut buf) {
+            Ok(n) => n,
+            Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+            Err(_) => {
+                // No client has the slave open yet
+                std::thread::sleep(Duration::from_millis(10));
+                continue;
+            }
+        };
+        if reinit_until.is_some_and(|until| Instant::now() < until) {
+            line.clear();
+            echoed = 0;
+            continue;
+        }
+
+        for &byte in &buf[..n] {
+            match byte {
+                // Ctrl-U clears the line; Ctrl-C also prints a fresh prompt
+                0x15 => {
+                    line.clear();
+                    echoed = 0;
+                }
+                0x03 => {
+                    line.clear();
+                    echoed = 0;
+                    if !faults.shell_disabled && !faults.bootloader {
+                        let _ = port.write_all(format!("\r\n{}", faults.prompt).as_bytes());
+                        let _ = port.flush();
+                    }
+                }
+                b'\n' | b'\r' => {}
+                _ => {
+                    if line.is_empty() {
+                        lines_started += 1;
+                    }
+                    let corrupt = line.len() == 1
+                        && (2..=faults.corrupted_lines + 1).contains(&lines_started);
+                    line.push(if corrupt { b'~' } else { byte });
+                }
+            }
+            if byte != b'\n' && byte != b'\r' {
+                continue;
+            }
+            let command = String::from_utf8_lossy(&line).trim().to_string();
+            let typed_echo = std::mem::take(&mut echoed) > 0;
+            line.clear();
+            if command.is_empty() {
+                continue;
+            }
+
+            received.lock().unwrap().push(command.clone());
+            if faults.bootloader {
+                // Only SMP frames get an answer
+                if let Some(reply) = smp::echo_reply(&command) {
+                    let _ = port.write_all(reply.as_bytes());
+                    let _ = port.flush();
+                }
+                continue;
+            }
+            if ignoring > 0 {
+                ignoring -= 1;
+                continue;
+            }
+            if command.starts_with("system baud") {
+                ignoring = faults.ignored_after_baud;
+            }
+            if command.starts_with("pm monitor start") {
+                monitoring = Some(0);
+            } else if command == "pm monitor stop" {
+                monitoring = None;
+            }
+            let overrun = last_reply.is_some_and(|at| at.elapsed() < faults.min_command_gap);
+            let mut output = if overrun {
+                let mut end = command.len() / 2;
+                while !command.is_char_boundary(end) {
+                    end -= 1;
+                }
+                render(
+                    &command[..end],
+                    &faults,
+                    &mut eeprom,
+                    &mut gauge,
+                    &mut nfc,
+                    booted.elapsed(),
+                )
+            } else {
+                render(
+                    &command,
+                    &faults,
+                    &mut eeprom,
+                    &mut gauge,
+                    &mut nfc,
+                    booted.elapsed(),
+                )
+            };
+            if typed_echo {
+                // Echoed already; Enter only moves to the next line
+                if let Some((_, rest)) = output.split_once("\r\n") {
+                    output = format!("\r\n{}", rest);
+                }
+            }
+            if !faults.reply_delay.is_zero() && command != "ping" {
+                std::thread::sleep(faults.reply_delay);
+            }
+            std::thread::sleep(faults.slow_drip);
+            if faults.booting && replies == 0 {
+                output = format!("{}{}{}", boot_output(&faults), faults.prompt, output);
+            }
+            output = misconfigured(&output, duplicated_lines, doubled_line_endings);
+            if !faults.unsupported.contains(&command) {
+                match command.as_str() {
+                    "shell local_echo off" => duplicated_lines = false,
+                    "shell crlf off" => doubled_line_endings = false,
+                    _ => {}
+                }
+            }
+            if !faults.echo_stall.is_zero() && command != "ping" {
+                if let Some(end) = output.find("\r\n") {
+                    let _ = port.write_all(&output.as_bytes()[..end + 2]);
+                    let _ = port.flush();
+                    output.drain(..end + 2);
+                    std::thread::sleep(faults.echo_stall);
+                }
+            }
+            let _ = port.write_all(output.as_bytes());
+            let _ = port.flush();
+            last_reply = Some(Instant::now());
+            replies += 1;
+            if command.starts_with("pm sleep") {
+                reinit_until = Some(Instant::now() + SLEEP_UART_REINIT);
+            }
+            if let Some((_, noise)) = faults
+                .line_noise
+                .as_ref()
+                .filter(|(after, _)| *after == replies)
+            {
+                line.extend_from_slice(noise.as_bytes());
+            }
+            if faults.reboot_after == Some(replies) || resets(&command) {
+                std::thread::sleep(Duration::from_millis(20));
+                let _ = port.write_all(format!("\r\n{}", boot_output(&faults)).as_bytes());
+                let _ = port.flush();
+                booted = Instant::now();
+            }
+        }
+        // A line typed without Enter is echoed as it arrives, twice with
+        // local echo on
+        let typing = !buf[..n].iter().any(|&b| b == b'\n' || b == b'\r');
+        let silent = faults.shell_disabled || faults.bootloader;
+        if typing && echoed < line.len() && !silent && ignoring == 0 {
+            let copies = if duplicated_lines { 2 } else { 1 };
+            let _ = port.write_all(&line[echoed..].repeat(copies));
+            let _ = port.flush();
+            echoed = line.len();
+        }
+    }
+}
+
+/// `output` as a console with local echo or CRLF translation left on
+/// prints it; the prompt after the last line is printed once
+fn misconfigured(output: &str, duplicated_lines: bool, doubled_line_endings: bool) -> String {
+    if !duplicated_lines && !doubled_line_endings {
+        return output.to_string();
+    }
+    let ending = if doubled_line_endings {
+        "\r\n\r\n"
+    } else {
+        "\r\n"
+    };
+    let mut lines: Vec<&str> = output.split("\r\n").collect();
+    let prompt = lines.pop().unwrap_or_default();
+    let mut printed = String::with_capacity(output.len() * 4);
+    for line in lines {
+        for _ in 0..if duplicated_lines { 2 } else { 1 } {
+            printed.push_str(line);
+            printed.push_str(ending);
+        }
+    }
+    printed.push_str(prompt);
+    printed
+}
+
+/// [`BOOT_BANNER`] and [`BOOT_LOG`] as the console shows them
+fn boot_output(faults: &Faults) -> String {
+    let log = BOOT_LOG.replace(FIRMWARE_VERSION, &faults.firmware_version);
+    format!("{}\r\n{}\r\n", BOOT_BANNER, log.replace('\n', "\r\n"))
+}
+
+/// Reply to `nfc eeprom read|write`, updating the simulated EEPROM
+fn eeprom_command(words: &[&str], faults: &Faults, eeprom: &mut [u8]) -> String {
+    let block = |text: &str| {
+        usize::from_str_radix(text.trim_start_matches("0x"), 16)
+            .ok()
+            .filter(|&block| block < EEPROM_BLOCKS)
+    };
+    match words {
+        ["read", start, count] => {
+            let (Some(start), Ok(count)) = (block(start), count.parse::<usize>()) else {
+                return "Error: invalid EEPROM read".to_string();
+            };
+            (start..(start + count).min(EEPROM_BLOCKS))
+                .map(|b| {
+                    let bytes: Vec<String> = eeprom[b * 4..b * 4 + 4]
+                        .iter()
+                        .map(|byte| format!("{:02X}", byte))
+                        .collect();
+                    format!("0x{:03X}: {}", b, bytes.join(" "))
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        ["write", at, data] if data.len() == 8 => {
+            let Some(at) = block(at) else {
+                return "Error: invalid EEPROM block".to_string();
+            };
+            let Ok(value) = u32::from_str_radix(data, 16) else {
+                return "Error: invalid EEPROM data".to_string();
+            };
+            if !faults.eeprom_read_only {
+                eeprom[at * 4..at * 4 + 4].copy_from_slice(&value.to_be_bytes());
+            }
+            format!("Block 0x{:03X} written", at)
+        }
+        _ => "Error: invalid EEPROM command".to_string(),
+    }
+}
+
+/// Session register with the field and mailbox flags
+const STATUS_REG: &str = "0xA0";
+/// Session register with the pass-through configuration
+const CONFIG_REG: &str = "0xA1";
+const NFC_FIELD_OK: u8 = 0x01;
+const SRAM_DATA_READY: u8 = 0x20;
+/// SRAM enabled and arbiter in pass-through mode
+const PASS_THROUGH_ON: u8 = 0x0E;
+/// Set for RF to I2C (phone to device)
+const PT_TRANSFER_DIR: u8 = 0x01;
+/// Status reads without a field after the phone leaves it
+const FIELD_GONE_READS: usize = 3;
+
+/// NTA5332 SRAM mailbox in pass-through mode, with the phone on the RF side
+///
+/// The phone acts when the host reads the status register: it takes a
+/// mailbox the host filled, or fills the mailbox when sending. Leaving the
+/// field clears the pass-through configuration and the SRAM, as the chip
+/// does.
+#[derive(Debug)]
+struct PassThrough {
+    config: u8,
+    sram: Vec<u8>,
+    data_ready: bool,
+    field_gone: usize,
+    /// Mailboxes the phone has taken or handed over
+    moved: usize,
+    /// Mailboxes a field drop already hit
+    dropped: Vec<usize>,
+    /// Data the phone received
+    received: Vec<u8>,
+    /// Mailboxes the phone still has to send
+    outbox: VecDeque<Vec<u8>>,
+}
+
+impl PassThrough {
+    fn new(faults: &Faults) -> Self {
+        let blob = faults.phone.clone().unwrap_or_default();
+        let mut outbox: VecDeque<Vec<u8>> = blob
+            .chunks(62)
+            .enumerate()
+            .map(|(seq, payload)| {
+                let mut mailbox = vec![seq as u8, payload.len() as u8];
+                mailbox.extend_from_slice(payload);
+                mailbox
+            })
+            .collect();
+        outbox.push_back(closing_mailbox(outbox.len(), 0xFF, &blob));
+        Self {
+            config: 0,
+            sram: vec![0; 64],
+            data_ready: false,
+            field_gone: 0,
+            moved: 0,
+            dropped: Vec::new(),
+            received: Vec::new(),
+            outbox,
+        }
+    }
+
+    /// The phone leaves the field for a few status reads
+    fn drop_field(&mut self) {
+        self.dropped.push(self.moved);
+        self.field_gone = FIELD_GONE_READS;
+        self.config = 0;
+        self.data_ready = false;
+        self.sram.fill(0);
+    }
+
+    /// Byte 0 of the status register, after the phone had its turn
+    fn status(&mut self, faults: &Faults) -> u8 {
+        if faults.phone.is_none() {
+            return 0;
+        }
+        if self.field_gone > 0 {
+            self.field_gone -= 1;
+            return 0;
+        }
+        if self.config & PASS_THROUGH_ON == PASS_THROUGH_ON {
+            let drop =
+                faults.field_drops.contains(&self.moved) && !self.dropped.contains(&self.moved);
+            let to_phone = self.config & PT_TRANSFER_DIR == 0;
+            if to_phone && self.data_ready {
+                if drop {
+                    self.drop_field();
+                    return 0;
+                }
+                self.take_mailbox();
+            } else if !to_phone && !self.data_ready {
+                if let Some(next) = self.outbox.front() {
+                    if drop {
+                        self.drop_field();
+                        return 0;
+                    }
+                    self.sram = next.clone();
+                    self.sram.resize(64, 0);
+                    self.data_ready = true;
+                }
+            }
+        }
+        NFC_FIELD_OK | if self.data_ready { SRAM_DATA_READY } else { 0 }
+    }
+
+    /// The phone reads the mailbox the host filled
+    fn take_mailbox(&mut self) {
+        self.data_ready = false;
+        self.moved += 1;
+        match self.sram[1] {
+            0xFF => {
+                let ack = closing_mailbox(self.sram[0] as usize, 0xFE, &self.received);
+                self.outbox = VecDeque::from([ack]);
+            }
+            len => {
+                let payload = &self.sram[2..2 + (len as usize).min(62)];
+                self.received.extend_from_slice(payload);
+            }
+        }
+    }
+
+    fn command(&mut self, words: &[&str], faults: &Faults) -> String {
+        match words {
+            ["session", "read", STATUS_REG] => {
+                format!("Session 0xA0: {:02X} 00 00 00", self.status(faults))
+            }
+            ["session", "read", CONFIG_REG] => {
+                format!("Session 0xA1: 00 {:02X} 00 00", self.config)
+            }
+            ["session", "write", CONFIG_REG, "1", mask, value] => {
+                let byte = |text: &str| u8::from_str_radix(text.trim_start_matches("0x"), 16);
+                let (Ok(mask), Ok(value)) = (byte(mask), byte(value)) else {
+                    return "Error: invalid session register value".to_string();
+                };
+                self.config = (self.config & !mask) | (value & mask);
+                format!("Session 0xA1 byte 1: 0x{:02X}", self.config)
+            }
+            ["sram", "read"] => {
+                if self.config & PT_TRANSFER_DIR != 0 && self.data_ready {
+                    self.data_ready = false;
+                    self.outbox.pop_front();
+                    self.moved += 1;
+                }
+                self.sram
+                    .chunks(16)
+                    .enumerate()
+                    .map(|(row, bytes)| {
+                        let bytes: Vec<String> =
+                            bytes.iter().map(|b| format!("{:02X}", b)).collect();
+                        format!("0x{:02X}: {}", row * 16, bytes.join(" "))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            ["sram", "write", data] if data.len().is_multiple_of(2) && data.len() <= 128 => {
+                let Ok(bytes) = (0..data.len())
+                    .step_by(2)
+                    .map(|at| u8::from_str_radix(&data[at..at + 2], 16))
+                    .collect::<Result<Vec<u8>, _>>()
+                else {
+                    return "Error: invalid SRAM data".to_string();
+                };
+                self.sram[..bytes.len()].copy_from_slice(&bytes);
+                if self.config & PASS_THROUGH_ON == PASS_THROUGH_ON
+                    && self.config & PT_TRANSFER_DIR == 0
+                {
+                    self.data_ready = true;
+                }
+                format!("SRAM written ({} bytes)", bytes.len())
+            }
+            _ => format!("Error: unknown command 'nfc {}'", words.join(" ")),
+        }
+    }
+}
+
+/// End (`0xFF`) or acknowledgement (`0xFE`) mailbox for `data`
+fn closing_mailbox(seq: usize, marker: u8, data: &[u8]) -> Vec<u8> {
+    let mut mailbox = vec![seq as u8, marker];
+    mailbox.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    mailbox.extend_from_slice(&crc32(data).to_be_bytes());
+    mailbox
+}
+
+/// `gpio -h` listing
+fn gpio_help(batch: bool) -> String {
+    let mut listing = "gpio - GPIO commands\nSubcommands:\n  conf   :Configure GPIO pin\n  get    :Get GPIO pin value\n  set    :Set GPIO pin value".to_string();
+    if batch {
+        listing.push_str("\n  batch  :Set GPIO pins with delays in one transaction");
+    }
+    listing
+}
+
+/// `gpio batch` reply: each setting with its time since the start, 40 us
+/// per setting on top of the requested delays
+fn gpio_batch(args: &str) -> String {
+    let mut lines = Vec::new();
+    let mut at_us = 0;
+    for op in args.split_whitespace() {
+        if let Some(ms) = op.strip_suffix("ms") {
+            at_us += ms.parse::<u64>().unwrap_or(0) * 1000;
+        } else if let Some(us) = op.strip_suffix("us") {
+            at_us += us.parse::<u64>().unwrap_or(0);
+        } else {
+            lines.push(format!("{} OK +{}us", op, at_us));
+            at_us += 40;
+        }
+    }
+    lines.push("Batch complete".to_string());
+    lines.join("\n")
+}
+
+/// LTC2959 state the simulated firmware keeps between commands
+struct Gauge {
+    /// `ltc2959 read`s answered so far
+    reads: usize,
+    charge_mah: u16,
+    adc_mode: String,
+}
+
+impl Gauge {
+    fn new(faults: &Faults) -> Self {
+        Self {
+            reads: 0,
+            charge_mah: faults.charge_mah,
+            adc_mode: faults.adc_mode.clone(),
+        }
+    }
+}
+
+/// Everything the console prints in answer to `command`
+fn render(
+    command: &str,
+    faults: &Faults,
+    eeprom: &mut [u8],
+    gauge: &mut Gauge,
+    nfc: &mut PassThrough,
+    uptime: Duration,
+) -> String {
+    if faults.shell_disabled {
+        return format!("{}\r\n", LOG_LINE);
+    }
+
+    let mut body = if faults.unsupported.iter().any(|c| c == command) {
+        format!("Error: unknown command '{}'", command)
+    } else if command == "pm battery_check" {
+        format!(
+            "{}\nVerdict: {}",
+            BATTERY_CHECK_REPLY, faults.battery_verdict
+        )
+    } else if command == "system uptime" {
+        let secs = uptime.as_secs();
+        format!(
+            "System Uptime: {}:{:02}:{:02} ({} ms)",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            uptime.as_millis()
+        )
+    } else if command == "gpio -h" {
+        gpio_help(faults.gpio_batch)
+    } else if let Some(args) = command
+        .strip_prefix("gpio batch ")
+        .filter(|_| faults.gpio_batch)
+    {
+        gpio_batch(args)
+    } else if let Some(reply) = faults
+        .unchanged_reply
+        .as_ref()
+        .filter(|_| ["pm pmic on", "pm wifi off", "pm disp on"].contains(&command))
+    {
+        reply.clone()
+    } else if let Some(voltage) = faults
+        .battery_voltages
+        .get(gauge.reads)
+        .filter(|_| command == "ltc2959 read")
+    {
+        gauge.reads += 1;
+        match voltage {
+            Some(mv) => BATTERY_REPLY.replace("3850 mV", &format!("{} mV", mv)),
+            None => "Error: I2C read failed".to_string(),
+        }
+    } else if let Some(arg) = command.strip_prefix("ltc2959 set_charge ") {
+        match arg.parse() {
+            Ok(charge) => {
+                if !faults.charge_read_only {
+                    gauge.charge_mah = charge;
+                }
+                format!("Accumulated charge set to {} mAh", charge)
+            }
+            Err(_) => format!("Error: invalid charge '{}'", arg),
+        }
+    } else if command == "ltc2959 production_reset" {
+        if !faults.charge_read_only {
+            gauge.charge_mah = 0;
+        }
+        "LTC2959 reset for fresh battery".to_string()
+    } else if let Some(arg) = command.strip_prefix("ltc2959 adc_mode ") {
+        match arg
+            .parse::<usize>()
+            .ok()
+            .and_then(|mode| ADC_MODE_NAMES.get(mode))
+        {
+            Some(name) => {
+                gauge.adc_mode = name.to_string();
+                format!("ADC mode set to {} ({})", arg, name)
+            }
+            None => format!("Error: invalid ADC mode '{}'", arg),
+        }
+    } else if command == "pm stats" {
+        PM_STATS_REPLY
+            .replace(
+                "LTC2959: Smart Sleep",
+                &format!("LTC2959: {}", gauge.adc_mode),
+            )
+            .replace("NFC: Sleep", &format!("NFC: {}", faults.nfc_state))
+    } else if command == "ltc2959 status" {
+        let mut status = format!(
+            "📊 LTC2959 Status:\nADC Mode: {}\nCoulomb Counter: Enabled",
+            gauge.adc_mode
+        );
+        if let Some(charger) = &faults.charger {
+            status.push_str(&format!("\nCharger: {}", charger));
+        }
+        status
+    } else if let Some(high) = faults
+        .cc_gpio
+        .filter(|_| command == "ltc2959 cc_gpio status")
+    {
+        format!("CC_GPIO: {}", if high { "HIGH" } else { "LOW" })
+    } else if ["nfc status", "nfc field_detect", "nfc rfdbg"].contains(&command) {
+        nfc_status(command, faults.nfc_field)
+    } else if command == "pm wake config" {
+        format!("⏰ Wake Sources:\nWake mask: 0x{:02X}", faults.wake_mask)
+    } else if let Some(args) = command.strip_prefix("nfc eeprom ") {
+        let words: Vec<&str> = args.split_whitespace().collect();
+        eeprom_command(&words, faults, eeprom)
+    } else if let Some(args) = command
+        .strip_prefix("nfc ")
+        .filter(|args| args.starts_with("sram ") || args.starts_with("session "))
+    {
+        let words: Vec<&str> = args.split_whitespace().collect();
+        nfc.command(&words, faults)
+    } else {
+        reply_for(command)
+    };
+    if ["version", "system info"].contains(&command) {
+        body = body.replace(FIRMWARE_VERSION, &faults.firmware_version);
+    }
+    if ["ltc2959 read", "pm measure"].contains(&command) {
+        body = body.replace(
+            &format!("{} mV", BATTERY_MV),
+            &format!("{} mV", faults.battery_mv),
+        );
+    }
+    if command == "ltc2959 read" {
+        body = body.replace(
+            &format!("{} mAh", INITIAL_CHARGE_MAH),
+            &format!("{} mAh", gauge.charge_mah),
+        );
+    }
+    if let Some(len) = faults.truncate_at {
+        let mut end = len.min(body.len());
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        body.truncate(end);
+    }
+
+    let mut output = format!("{}\r\n", command);
+    for log_line in &faults.log_lines {
+        output.push_str(log_line);
+        output.push_str("\r\n");
+    }
+    output.push_str(&body.replace('\n', "\r\n"));
+    output.push_str("\r\n");
+    if faults.truncate_at.is_none() {
+        output.push_str(&faults.prompt);
+    }
+    output
+}
@@ -0,0 +1,150 @@
+/*
+ * E-ink Power CLI - Simulated Bootloader SMP
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Just enough of MCUboot serial recovery to answer `mcumgr echo`
+//!
+//! SMP over a serial console sends each packet as a line: the frame start
+//! bytes, then base64 of a big-endian length, the packet and its
+//! CRC-16/XMODEM. An echo request is an 8-byte SMP header and the CBOR map
+//! `{"d": <text>}`; the reply carries `{"r": <text>}`. Packets long enough
+//! to need continuation frames are not supported.
+
+/// First bytes of an SMP frame on the console
+pub const FRAME_START: &str = "\x06\x09";
+
+/// SMP write request and write response
+const OP_WRITE: u8 = 2;
+const OP_WRITE_RSP: u8 = 3;
+
+/// Reply frame to an SMP echo request frame, `None` for anything else
+pub fn echo_reply(line: &str) -> Option<String> {
+    let body = decode(line.strip_prefix(FRAME_START)?.trim())?;
+    let (length, rest) = body.split_first_chunk::<2>()?;
+    let length = u16::from_be_bytes(*length) as usize;
+    if length != rest.len() || length < 10 {
+        return None;
+    }
+    let (packet, crc) = rest.split_at(length - 2);
+    if crc16(packet).to_be_bytes() != crc {
+        return None;
+    }
+    let (header, payload) = packet.split_at(8);
+    // Group 0 (default) command 0 (echo)
+    if header[0] & 0x07 != OP_WRITE || header[4..6] != [0, 0] || header[7] != 0 {
+        return None;
+    }
+    let text = echo_text(payload)?;
+
+    let mut reply_payload = vec![0xA1, 0x61, b'r'];
+    reply_payload.extend(text_header(text.len()));
+    reply_payload.extend_from_slice(text);
+    let mut reply = vec![
+        (header[0] & !0x07) | OP_WRITE_RSP,
+        header[1],
+        0,
+        0,
+        0,
+        0,
+        header[6],
+        0,
+    ];
+    reply[2..4].copy_from_slice(&(reply_payload.len() as u16).to_be_bytes());
+    reply.extend(reply_payload);
+    Some(frame(&reply))
+}
+
+/// `packet` framed as one console line
+pub fn frame(packet: &[u8]) -> String {
+    let mut body = ((packet.len() + 2) as u16).to_be_bytes().to_vec();
+    body.extend_from_slice(packet);
+    body.extend_from_slice(&crc16(packet).to_be_bytes());
+    format!("{}{}\n", FRAME_START, encode(&body))
+}
+
+/// Text of the `"d"` entry of an echo request map
+fn echo_text(payload: &[u8]) -> Option<&[u8]> {
+    // Definite map of one entry, or indefinite
+    let entries = match payload.first()? {
+        0xA1 | 0xBF => &payload[1..],
+        _ => return None,
+    };
+    let value = entries.strip_prefix(&[0x61, b'd'])?;
+    let (len, start) = match *value.first()? {
+        short @ 0x60..=0x77 => ((short - 0x60) as usize, 1),
+        0x78 => (*value.get(1)? as usize, 2),
+        0x79 => (
+            u16::from_be_bytes([*value.get(1)?, *value.get(2)?]) as usize,
+            3,
+        ),
+        _ => return None,
+    };
+    value.get(start..start + len)
+}
+
+/// CBOR text string header for `len` bytes
+fn text_header(len: usize) -> Vec<u8> {
+    match len {
+        0..=23 => vec![0x60 + len as u8],
+        24..=255 => vec![0x78, len as u8],
+        _ => {
+            let mut header = vec![0x79];
+            header.extend_from_slice(&(len as u16).to_be_bytes());
+            header
+        }
+    }
+}
+
+/// CRC-16/XMODEM: polynomial 0x1021, initial value 0
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn encode(data: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in data.chunks(3) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, &b)| bits | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(bits >> (18 - 6 * i) & 0x3F) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+fn decode(text: &str) -> Option<Vec<u8>> {
+    let digits = text
+        .trim_end_matches('=')
+        .bytes()
+        .map(|c| BASE64.iter().position(|&d| d == c).map(|d| d as u32))
+        .collect::<Option<Vec<u32>>>()?;
+    let mut out = Vec::new();
+    for chunk in digits.chunks(4) {
+        let bits = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |bits, (i, d)| bits | d << (18 - 6 * i));
+        for i in 0..chunk.len().saturating_sub(1) {
+            out.push((bits >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(out)
+}
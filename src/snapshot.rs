@@ -0,0 +1,183 @@
+/*
+ * E-ink Power CLI - Support Snapshot
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `snapshot`: everything a support ticket asks for in one document
+//!
+//! Each section is read with the same typed client method and parser as the
+//! command that shows it on its own, so a snapshot also exercises the whole
+//! parsing surface against a real controller. A section that fails records
+//! its error and the rest are still read.
+//!
+//! `--redact` drops the unit identity before the document is shared.
+
+use crate::error::{PowerCliError, Result};
+use crate::firmware::slots::FirmwareImage;
+use crate::firmware::FirmwareManager;
+use crate::json::{
+    BatteryJson, NfcJson, PowerStatsJson, RailDefaultsJson, ResponseParser, RtcStatusJson,
+    SystemInfoJson,
+};
+use crate::power::control::PowerController;
+use crate::power::identity::DeviceIdentity;
+use crate::power::reboot;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Error recorded for a section left out by `--redact`
+pub const REDACTED: &str = "redacted";
+
+/// One part of the snapshot: its data, or why there is none
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Section<T> {
+    pub data: Option<T>,
+    /// Why `data` is missing; `None` with no data means there was nothing
+    /// to report (e.g. no identity programmed)
+    pub error: Option<String>,
+}
+
+impl<T> Section<T> {
+    pub(crate) fn read(result: Result<T>) -> Self {
+        Self::read_optional(result.map(Some))
+    }
+
+    pub(crate) fn read_optional(result: Result<Option<T>>) -> Self {
+        match result {
+            Ok(data) => Self { data, error: None },
+            Err(e) => Self::failed(e.to_string()),
+        }
+    }
+
+    pub(crate) fn failed(error: String) -> Self {
+        Self {
+            data: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// How the CLI talked to the controller
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionSettings {
+    pub device: String,
+    pub baud: u32,
+    pub timeout_s: u64,
+    pub pacing_ms: u64,
+    /// Whether status replies were reused within the invocation
+    pub response_cache: bool,
+    pub auto_recover_shell: bool,
+}
+
+/// Result of `snapshot`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken_at: DateTime<Utc>,
+    /// Version of this CLI
+    pub cli_version: String,
+    /// Set when the identity was left out
+    pub redacted: bool,
+    pub connection: ConnectionSettings,
+    pub system: Section<SystemInfoJson>,
+    pub uptime_ms: Section<u64>,
+    pub identity: Section<DeviceIdentity>,
+    /// Image slots from mcumgr
+    pub firmware_images: Section<Vec<FirmwareImage>>,
+    pub battery: Section<BatteryJson>,
+    pub power_stats: Section<PowerStatsJson>,
+    pub rail_defaults: Section<RailDefaultsJson>,
+    pub rtc: Section<RtcStatusJson>,
+    pub nfc: Section<NfcJson>,
+}
+
+impl Snapshot {
+    /// Read every section, recording failures instead of stopping at them
+    pub async fn take(
+        controller: &mut PowerController,
+        firmware: &mut FirmwareManager,
+        connection: ConnectionSettings,
+    ) -> Self {
+        let system = controller
+            .get_system_info_detailed()
+            .await
+            .map(|response| ResponseParser::parse_system_info(&response));
+        let uptime_ms = controller.get_system_uptime().await.and_then(|response| {
+            reboot::parse_uptime_ms(&response).ok_or(PowerCliError::InvalidResponse { response })
+        });
+        let identity = controller.read_identity().await;
+        let firmware_images = match firmware.image_slots().await {
+            Err(PowerCliError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                Section::failed("mcumgr is not installed".to_string())
+            }
+            result => Section::read(result),
+        };
+        let battery = controller
+            .battery_read()
+            .await
+            .map(|response| ResponseParser::parse_battery_response(&response));
+        let power_stats = controller
+            .pm_stats()
+            .await
+            .map(|response| ResponseParser::parse_pm_stats(&response));
+        let rail_defaults = controller
+            .pm_command("defaults")
+            .await
+            .map(|response| ResponseParser::parse_rail_defaults(&response));
+        let rtc = controller
+            .rtc_status()
+            .await
+            .map(|response| ResponseParser::parse_rtc_status(&response));
+        let nfc = controller
+            .nfc_command("status")
+            .await
+            .map(|response| ResponseParser::parse_nfc_status(&response));
+
+        Self {
+            taken_at: Utc::now(),
+            cli_version: env!("CARGO_PKG_VERSION").to_string(),
+            redacted: false,
+            connection,
+            system: Section::read(system),
+            uptime_ms: Section::read(uptime_ms),
+            identity: Section::read_optional(identity),
+            firmware_images,
+            battery: Section::read(battery),
+            power_stats: Section::read(power_stats),
+            rail_defaults: Section::read(rail_defaults),
+            rtc: Section::read(rtc),
+            nfc: Section::read(nfc),
+        }
+    }
+
+    /// Drop the unit identity and serial number before sharing
+    pub fn redact(&mut self) {
+        self.redacted = true;
+        self.identity = Section::failed(REDACTED.to_string());
+        if let Some(system) = self.system.data.as_mut() {
+            system.serial = None;
+            system.hw_rev = None;
+            system.manufacture_date = None;
+        }
+    }
+
+    /// Names of the sections that failed, redaction aside
+    pub fn failed_sections(&self) -> Vec<&'static str> {
+        let errors = [
+            ("system", &self.system.error),
+            ("uptime", &self.uptime_ms.error),
+            ("identity", &self.identity.error),
+            ("firmware images", &self.firmware_images.error),
+            ("battery", &self.battery.error),
+            ("power stats", &self.power_stats.error),
+            ("rail defaults", &self.rail_defaults.error),
+            ("rtc", &self.rtc.error),
+            ("nfc", &self.nfc.error),
+        ];
+        errors
+            .into_iter()
+            .filter(|(_, error)| error.as_deref().is_some_and(|e| e != REDACTED))
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
@@ -0,0 +1,315 @@
+/*
+ * E-ink Power CLI - Persistent State
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Per-device state files shared between concurrent invocations
+//!
+//! Everything the CLI remembers between runs lives in one directory per
+//! device under [`state_dir`]. Writers take an advisory lock on a `.lock`
+//! sidecar for the duration of a read-modify-write, and JSON documents are
+//! replaced atomically (temp file + rename) so readers never see a partial
+//! write, even from a cron job racing an interactive session.
+
+use fs2::FileExt;
+use log::{debug, warn};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Environment variable overriding the state directory
+pub const STATE_DIR_ENV: &str = "EINK_POWER_CLI_STATE_DIR";
+
+/// Root directory holding per-device state
+///
+/// Uses `$EINK_POWER_CLI_STATE_DIR` when set, otherwise the XDG state
+/// directory (`~/.local/state/eink-power-cli`), falling back to the local
+/// data directory on platforms without one.
+pub fn state_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os(STATE_DIR_ENV) {
+        return Some(PathBuf::from(dir));
+    }
+    dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .map(|dir| dir.join("eink-power-cli"))
+}
+
+/// File-name-safe key for a serial device path (e.g. `dev_ttyLP2`)
+pub fn device_key(device: &str) -> String {
+    device
+        .trim_start_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Exclusive advisory lock on a state file, released on drop
+pub struct StateLock {
+    file: File,
+}
+
+impl StateLock {
+    /// Block until the lock guarding `path` is acquired
+    pub fn acquire(path: &Path) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(lock_path(path))?;
+        file.lock_exclusive()?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+/// Sidecar lock file for `path`
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+/// Replace `path` with `contents` so readers see either the old or the new file
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Unique per writer, so tasks of one process writing the same file
+    // never share a temporary file
+    static WRITERS: AtomicU64 = AtomicU64::new(0);
+    let mut tmp_name = path.as_os_str().to_owned();
+    tmp_name.push(format!(
+        ".tmp.{}.{}",
+        std::process::id(),
+        WRITERS.fetch_add(1, Ordering::Relaxed)
+    ));
+    let tmp_path = PathBuf::from(tmp_name);
+
+    let result = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+/// A JSON document kept in a [`StateFile`]
+///
+/// Bump [`Self::SCHEMA_VERSION`] when the layout changes and teach
+/// [`Self::migrate`] how to lift older payloads.
+pub trait StatePayload: Serialize + DeserializeOwned {
+    /// Current schema version written to disk
+    const SCHEMA_VERSION: u32;
+
+    /// Convert a payload written with an older (or newer) schema
+    ///
+    /// Returning `None` discards the stored state, which is then treated as
+    /// absent.
+    fn migrate(_from_version: u32, _data: serde_json::Value) -> Option<Self> {
+        None
+    }
+}
+
+/// On-disk envelope around a [`StatePayload`]
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    schema_version: u32,
+    data: T,
+}
+
+/// Schema-versioned JSON document under the state directory
+pub struct StateFile<T> {
+    path: PathBuf,
+    _payload: std::marker::PhantomData<T>,
+}
+
+impl<T: StatePayload> StateFile<T> {
+    /// State file at an explicit path
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            _payload: std::marker::PhantomData,
+        }
+    }
+
+    /// Path of the document
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Read the current value, migrating it if it has an older schema
+    ///
+    /// Missing, unreadable or unmigratable documents yield `None`.
+    pub fn load(&self) -> io::Result<Option<T>> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let envelope: Envelope<serde_json::Value> = match serde_json::from_slice(&bytes) {
+            Ok(envelope) => envelope,
+            Err(e) => {
+                warn!("Ignoring corrupt state file {}: {}", self.path.display(), e);
+                return Ok(None);
+            }
+        };
+
+        if envelope.schema_version == T::SCHEMA_VERSION {
+            return match serde_json::from_value(envelope.data) {
+                Ok(data) => Ok(Some(data)),
+                Err(e) => {
+                    warn!("Ignoring invalid state file {}: {}", self.path.display(), e);
+                    Ok(None)
+                }
+            };
+        }
+
+        debug!(
+            "Migrating {} from schema {} to {}",
+            self.path.display(),
+            envelope.schema_version,
+            T::SCHEMA_VERSION
+        );
+        let migrated = T::migrate(envelope.schema_version, envelope.data);
+        if migrated.is_none() {
+            warn!(
+                "Discarding state file {} with unsupported schema {}",
+                self.path.display(),
+                envelope.schema_version
+            );
+        }
+        Ok(migrated)
+    }
+
+    /// Atomically read, modify and write the document under its lock
+    ///
+    /// Returns the value that was stored.
+    pub fn update<F>(&self, f: F) -> io::Result<T>
+    where
+        F: FnOnce(Option<T>) -> T,
+    {
+        let _lock = StateLock::acquire(&self.path)?;
+        let value = f(self.load()?);
+        self.write_unlocked(&value)?;
+        Ok(value)
+    }
+
+    /// Replace the document under its lock
+    pub fn store(&self, value: &T) -> io::Result<()> {
+        let _lock = StateLock::acquire(&self.path)?;
+        self.write_unlocked(value)
+    }
+
+    /// Delete the document under its lock; `false` if there was none
+    pub fn remove(&self) -> io::Result<bool> {
+        let _lock = StateLock::acquire(&self.path)?;
+        match fs::remove_file(&self.path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write_unlocked(&self, value: &T) -> io::Result<()> {
+        let envelope = Envelope {
+            schema_version: T::SCHEMA_VERSION,
+            data: value,
+        };
+        let mut json = serde_json::to_vec_pretty(&envelope)?;
+        json.push(b'\n');
+        write_atomic(&self.path, &json)
+    }
+}
+
+/// State directory of one device
+pub struct DeviceState {
+    dir: PathBuf,
+}
+
+impl DeviceState {
+    /// State for a serial device under [`state_dir`]
+    pub fn for_device(device: &str) -> Option<Self> {
+        state_dir().map(|root| Self::in_dir(root.join(device_key(device))))
+    }
+
+    /// State rooted at an explicit directory
+    pub fn in_dir(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Directory holding this device's files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Path of a named file in this device's directory
+    pub fn file_path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Typed JSON document stored in this device's directory
+    pub fn file<T: StatePayload>(&self, name: &str) -> StateFile<T> {
+        StateFile::new(self.file_path(name))
+    }
+
+    /// Stored files (excluding lock and temporary files), sorted by name
+    pub fn files(&self) -> io::Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if path.is_file() && !name.ends_with(".lock") && !name.contains(".tmp.") {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Delete every stored file for the device, returning how many were removed
+    ///
+    /// Each file is removed while holding its lock so no writer is cut off
+    /// mid-update. Lock files are left in place so waiting writers keep
+    /// contending on the same lock.
+    pub fn clear(&self) -> io::Result<usize> {
+        let files = self.files()?;
+        for path in &files {
+            let _lock = StateLock::acquire(path)?;
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        Ok(files.len())
+    }
+}
@@ -0,0 +1,190 @@
+/*
+ * E-ink Power CLI - Status File
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `monitor --status-file`: the latest state as a JSON file for other services
+//!
+//! Services on the host that want the battery level or the rail states read
+//! the file instead of opening the serial port, which only one process can
+//! hold. Every write goes to a temporary file that is renamed over the old
+//! one, so a reader sees either the previous document or the new one, never
+//! half of each.
+//!
+//! The document carries [`OUTPUT_SCHEMA_VERSION`] like the `--format json`
+//! envelope and changes under the same rules. `stale_after` tells a reader
+//! when to stop trusting it if the monitor dies without cleaning up; on a
+//! clean shutdown the monitor sets `stale` itself.
+
+use crate::json::{MeasurementJson, ResponseParser, OUTPUT_SCHEMA_VERSION};
+use crate::power::battery::ChargingState;
+use crate::power::control::PowerController;
+use crate::power::rails::PowerRail;
+use crate::power::wake::WakeMask;
+use crate::state;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Contents of the status file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusDocument {
+    /// [`OUTPUT_SCHEMA_VERSION`] of the build that wrote the file
+    pub schema_version: u32,
+    /// Serial device of the controller
+    pub device: String,
+    /// When the file was last written
+    pub updated_at: DateTime<Utc>,
+    /// When the file counts as stale if it has not been written again
+    pub stale_after: DateTime<Utc>,
+    /// Set when the monitor has stopped
+    pub stale: bool,
+    /// When the battery was last sampled; `None` before the first sample
+    pub sampled_at: Option<DateTime<Utc>>,
+    pub battery: Option<MeasurementJson>,
+    pub charging_state: Option<ChargingState>,
+    /// Switched rails by name; a rail that could not be read is left out
+    pub rails: BTreeMap<PowerRail, bool>,
+    pub wake_sources: Option<WakeMask>,
+    pub firmware_version: Option<String>,
+    /// PMU resets seen since the monitor started
+    pub reboots: u32,
+}
+
+impl StatusDocument {
+    /// Document for `device` with nothing sampled yet
+    pub fn new(device: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            schema_version: OUTPUT_SCHEMA_VERSION,
+            device: device.to_string(),
+            updated_at: now,
+            stale_after: now,
+            stale: false,
+            sampled_at: None,
+            battery: None,
+            charging_state: None,
+            rails: BTreeMap::new(),
+            wake_sources: None,
+            firmware_version: None,
+            reboots: 0,
+        }
+    }
+
+    /// Read the firmware version; left unset if the PMU does not report it
+    pub async fn read_firmware(&mut self, controller: &mut PowerController) {
+        match controller.get_system_info().await {
+            Ok(response) => {
+                self.firmware_version = ResponseParser::parse_system_info(&response)
+                    .version_info
+                    .semver
+            }
+            Err(e) => log::warn!("Status file: firmware version not read: {}", e),
+        }
+    }
+
+    /// Read the rail states and wake sources, keeping the previous values
+    /// of any that fail
+    pub async fn read_power_state(&mut self, controller: &mut PowerController) {
+        match controller.rail_states().await {
+            Ok(rails) => self.rails = rails,
+            Err(e) => log::warn!("Status file: rail states not read: {}", e),
+        }
+        match controller.wake_sources().await {
+            Ok(mask) => self.wake_sources = mask,
+            Err(e) => log::warn!("Status file: wake sources not read: {}", e),
+        }
+        self.reboots = controller.reboots();
+    }
+
+    /// Whether a reader should stop trusting the document at `now`
+    #[allow(dead_code)] // Used by tests
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        self.stale || now > self.stale_after
+    }
+}
+
+/// Status file written by `monitor`
+pub struct StatusFile {
+    path: PathBuf,
+    /// Time between writes
+    period: Duration,
+    write_interval: Option<Duration>,
+    timer: Option<tokio::time::Interval>,
+}
+
+impl StatusFile {
+    /// Status file at `path`, written after every sample, or every
+    /// `write_interval` when one is given
+    pub fn new(path: PathBuf, sample_interval: Duration, write_interval: Option<Duration>) -> Self {
+        Self {
+            path,
+            period: write_interval.unwrap_or(sample_interval),
+            write_interval,
+            timer: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file is written on its own timer rather than per sample
+    pub fn is_timed(&self) -> bool {
+        self.write_interval.is_some()
+    }
+
+    /// Wait for the next timed write; never returns for a per-sample file
+    ///
+    /// The first call returns at once.
+    pub async fn tick(&mut self) {
+        let Some(period) = self.write_interval else {
+            return std::future::pending().await;
+        };
+        let timer = self.timer.get_or_insert_with(|| {
+            let mut timer = tokio::time::interval(period);
+            timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            timer
+        });
+        timer.tick().await;
+    }
+
+    /// Stamp `document` and write it atomically
+    ///
+    /// The document counts as stale after two missed writes.
+    pub fn write(&self, document: &mut StatusDocument) -> io::Result<()> {
+        let grace = chrono::Duration::from_std(self.period.saturating_mul(2))
+            .unwrap_or(chrono::Duration::MAX);
+        document.updated_at = Utc::now();
+        document.stale_after = document
+            .updated_at
+            .checked_add_signed(grace)
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        document.stale = false;
+        store(&self.path, document)
+    }
+
+    /// Mark the file stale on shutdown, keeping the last readings
+    pub fn mark_stale(&self, document: &mut StatusDocument) -> io::Result<()> {
+        document.updated_at = Utc::now();
+        document.stale = true;
+        store(&self.path, document)
+    }
+}
+
+fn store(path: &Path, document: &StatusDocument) -> io::Result<()> {
+    let mut json = serde_json::to_vec_pretty(document).map_err(io::Error::other)?;
+    json.push(b'\n');
+    state::write_atomic(path, &json)
+}
+
+/// Read a status file as another service would
+#[allow(dead_code)] // Used by tests
+pub fn read(path: &Path) -> io::Result<StatusDocument> {
+    let text = std::fs::read_to_string(path)?;
+    serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
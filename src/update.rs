@@ -0,0 +1,330 @@
+/*
+ * E-ink Power CLI - Self-Update
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `self-update`: replace this executable with the latest release
+//!
+//! Field units run pinned binaries, so the tool updates itself from the
+//! artifact server set as `url` in the `[update]` section of the
+//! configuration file. Releases are laid out by channel and target triple:
+//!
+//! ```text
+//! <url>/<channel>/<target>/latest.json         {"version": "2.6.0", "artifact": "eink-power-cli"}
+//! <url>/<channel>/<target>/<artifact>          the executable
+//! <url>/<channel>/<target>/<artifact>.sha256   its SHA-256, as written by sha256sum
+//! <url>/<channel>/<target>/<artifact>.minisig  minisign signature, checked with [update] public_key
+//! ```
+//!
+//! Nothing next to the executable is touched until the download has
+//! matched its checksum (and signature). The new executable is then written
+//! beside the running one and renamed over it, with the previous one kept
+//! as `<executable>.old` to roll back to, so a failure at any stage leaves
+//! the installed binary as it was.
+
+use crate::error::{PowerCliError, Result};
+use log::{debug, info};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Target triple this executable was built for, which releases are
+/// looked up by
+pub const TARGET: &str = env!("EINK_POWER_CLI_TARGET");
+
+/// Channel used when neither `--channel` nor `[update] channel` is given
+pub const DEFAULT_CHANNEL: &str = "stable";
+
+/// Release manifest read from the server
+pub const MANIFEST_FILE: &str = "latest.json";
+
+/// Suffix of the previous executable kept after an update
+pub const BACKUP_SUFFIX: &str = ".old";
+
+/// Largest executable downloaded
+const MAX_ARTIFACT_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Time allowed for each request to the artifact server
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Where releases for this executable are published
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateSource {
+    /// Artifact server root, e.g. `https://artifacts.example.com/eink-power-cli`
+    pub url: String,
+    pub channel: String,
+    pub target: String,
+}
+
+impl UpdateSource {
+    /// Releases on `channel` of the server at `url` for this build's target
+    pub fn new(url: &str, channel: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            channel: channel.to_string(),
+            target: TARGET.to_string(),
+        }
+    }
+
+    /// URL of `file` in this channel and target's directory
+    pub fn file_url(&self, file: &str) -> String {
+        format!("{}/{}/{}/{}", self.url, self.channel, self.target, file)
+    }
+}
+
+/// Contents of `latest.json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReleaseManifest {
+    /// Version of the release, e.g. `2.6.0`
+    pub version: String,
+    /// File name of the executable in the same directory
+    pub artifact: String,
+}
+
+/// Outcome of `self-update`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateStatus {
+    /// The running version is the latest (or newer)
+    UpToDate,
+    /// A newer release exists and `--check-only` left it alone
+    Available,
+    /// The newer release replaced the executable
+    Updated,
+}
+
+/// What `self-update` found and did
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateReport {
+    pub status: UpdateStatus,
+    pub current_version: String,
+    pub latest_version: String,
+    pub channel: String,
+    pub target: String,
+    /// Executable downloaded, when a newer release exists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_url: Option<String>,
+    /// Whether the minisign signature was checked as well as the checksum
+    #[serde(default)]
+    pub signature_verified: bool,
+    /// Executable that was replaced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub executable: Option<PathBuf>,
+    /// Previous executable, to move back for a rollback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backup: Option<PathBuf>,
+}
+
+/// Look up the latest release and, unless `check_only`, install it over
+/// `executable` when it is newer than `current`
+///
+/// With `public_key` (a minisign public key, as the second line of its
+/// `.pub` file or the whole file) the release must also carry a valid
+/// signature.
+pub fn self_update(
+    source: &UpdateSource,
+    current: &Version,
+    public_key: Option<&str>,
+    check_only: bool,
+    executable: &Path,
+) -> Result<UpdateReport> {
+    let manifest = latest_release(source)?;
+    let latest = Version::parse(manifest.version.trim()).map_err(|e| {
+        update_error(format!(
+            "{} has an invalid version '{}': {}",
+            source.file_url(MANIFEST_FILE),
+            manifest.version,
+            e
+        ))
+    })?;
+    let mut report = UpdateReport {
+        status: UpdateStatus::UpToDate,
+        current_version: current.to_string(),
+        latest_version: latest.to_string(),
+        channel: source.channel.clone(),
+        target: source.target.clone(),
+        artifact_url: None,
+        signature_verified: false,
+        executable: None,
+        backup: None,
+    };
+    if latest <= *current {
+        return Ok(report);
+    }
+
+    report.artifact_url = Some(source.file_url(&manifest.artifact));
+    if check_only {
+        report.status = UpdateStatus::Available;
+        return Ok(report);
+    }
+
+    let contents = download_verified(source, &manifest, public_key)?;
+    report.signature_verified = public_key.is_some();
+    let backup = install(executable, &contents)?;
+    info!(
+        "Updated {} from {} to {}",
+        executable.display(),
+        current,
+        latest
+    );
+    report.status = UpdateStatus::Updated;
+    report.executable = Some(executable.to_path_buf());
+    report.backup = Some(backup);
+    Ok(report)
+}
+
+/// Read `latest.json` for the source's channel and target
+pub fn latest_release(source: &UpdateSource) -> Result<ReleaseManifest> {
+    let url = source.file_url(MANIFEST_FILE);
+    let body = fetch(&url, MAX_ARTIFACT_BYTES)?;
+    serde_json::from_slice(&body)
+        .map_err(|e| update_error(format!("{} is not a release manifest: {}", url, e)))
+}
+
+/// Download the release's executable and check it against its detached
+/// checksum, and signature when `public_key` is given
+pub fn download_verified(
+    source: &UpdateSource,
+    manifest: &ReleaseManifest,
+    public_key: Option<&str>,
+) -> Result<Vec<u8>> {
+    let url = source.file_url(&manifest.artifact);
+    let contents = fetch(&url, MAX_ARTIFACT_BYTES)?;
+    debug!("Downloaded {} bytes from {}", contents.len(), url);
+
+    let checksum = fetch(&format!("{}.sha256", url), 4096)?;
+    verify_sha256(&contents, &String::from_utf8_lossy(&checksum))
+        .map_err(|message| update_error(format!("{}: {}", url, message)))?;
+
+    if let Some(key) = public_key {
+        let signature = fetch(&format!("{}.minisig", url), 4096)?;
+        verify_signature(&contents, &String::from_utf8_lossy(&signature), key)
+            .map_err(|message| update_error(format!("{}: {}", url, message)))?;
+    }
+    Ok(contents)
+}
+
+/// Check `contents` against a `sha256sum` line (`<hex>  <file>`) or a bare
+/// hex digest
+pub fn verify_sha256(contents: &[u8], checksum: &str) -> std::result::Result<(), String> {
+    let expected = checksum
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| "empty checksum file".to_string())?
+        .to_ascii_lowercase();
+    let actual: String = Sha256::digest(contents)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    if expected != actual {
+        return Err(format!(
+            "checksum mismatch: expected {}, downloaded {}",
+            expected, actual
+        ));
+    }
+    Ok(())
+}
+
+/// Check a minisign `signature` of `contents` with `public_key`
+pub fn verify_signature(
+    contents: &[u8],
+    signature: &str,
+    public_key: &str,
+) -> std::result::Result<(), String> {
+    let key = public_key.trim();
+    let key = match key.lines().count() {
+        1 => minisign_verify::PublicKey::from_base64(key),
+        _ => minisign_verify::PublicKey::decode(key),
+    }
+    .map_err(|e| format!("invalid [update] public_key: {}", e))?;
+    let signature = minisign_verify::Signature::decode(signature)
+        .map_err(|e| format!("invalid signature file: {}", e))?;
+    key.verify(contents, &signature, false)
+        .map_err(|e| format!("signature check failed: {}", e))
+}
+
+/// Put `contents` in place of `executable`, keeping the previous one as
+/// `<executable>.old`; returns the path of that backup
+///
+/// The new file is written and synced beside the executable, with its
+/// permissions, then renamed over it. If any step fails the new file is
+/// removed and the executable is left as it was.
+pub fn install(executable: &Path, contents: &[u8]) -> Result<PathBuf> {
+    let staged = sibling(executable, ".new");
+    let backup = sibling(executable, BACKUP_SUFFIX);
+
+    let result = (|| -> io::Result<()> {
+        let permissions = fs::metadata(executable)?.permissions();
+        let mut file = File::create(&staged)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::set_permissions(&staged, permissions)?;
+
+        match fs::remove_file(&backup) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        if fs::hard_link(executable, &backup).is_err() {
+            fs::copy(executable, &backup)?;
+        }
+        if let Err(e) = fs::rename(&staged, executable) {
+            let _ = fs::remove_file(&backup);
+            return Err(e);
+        }
+        Ok(())
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&staged);
+        return Err(update_error(format!(
+            "cannot replace {}: {}",
+            executable.display(),
+            e
+        )));
+    }
+    Ok(backup)
+}
+
+/// `path` with `suffix` appended to its file name
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Body of a successful GET of `url`, at most `limit` bytes
+fn fetch(url: &str, limit: u64) -> Result<Vec<u8>> {
+    debug!("GET {}", url);
+    let response = ureq::AgentBuilder::new()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .get(url)
+        .call()
+        .map_err(|e| match e {
+            ureq::Error::Status(code, _) => update_error(format!("{} returned HTTP {}", url, code)),
+            ureq::Error::Transport(transport) => {
+                update_error(format!("cannot reach {}: {}", url, transport))
+            }
+        })?;
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .take(limit + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| update_error(format!("cannot read {}: {}", url, e)))?;
+    if body.len() as u64 > limit {
+        return Err(update_error(format!(
+            "{} is larger than {} bytes",
+            url, limit
+        )));
+    }
+    Ok(body)
+}
+
+fn update_error(message: String) -> PowerCliError {
+    PowerCliError::UpdateError { message }
+}
@@ -0,0 +1,282 @@
+/*
+ * E-ink Power CLI - Countdowns
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Waits that show their progress and stop when cancelled
+//!
+//! [`countdown`] replaces a bare `sleep` wherever the CLI waits for the
+//! controller, e.g. for new firmware to boot. It shows the time left on
+//! stderr and returns early once its [`CancellationToken`] is cancelled,
+//! which [`cancel_on_ctrl_c`] does on Ctrl-C. Nothing more is printed after
+//! the cancellation is noticed.
+//!
+//! How the progress is shown is set once per invocation with
+//! [`set_progress`]: redrawn in place on a terminal, a line at the start and
+//! end otherwise, and not at all with `--quiet` or a machine format.
+
+use crate::cli::{Cli, OutputFormat};
+use crate::error::{PowerCliError, Result};
+use crate::render::OutputStyle;
+use std::io::{IsTerminal, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::{sleep_until, Instant};
+pub use tokio_util::sync::CancellationToken;
+
+/// Clear from the cursor to the end of the line
+const CLEAR_LINE: &str = "\x1b[K";
+
+/// Time between redraws of a countdown on a terminal
+pub const COUNTDOWN_TICK: Duration = Duration::from_secs(1);
+
+/// Frames of a [`Spinner`]
+const SPINNER_FRAMES: &[char] = &['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_FRAMES_ASCII: &[char] = &['|', '/', '-', '\\'];
+
+/// Exit code after a second Ctrl-C, as for a process killed by SIGINT
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Progress display for this invocation
+static PROGRESS: Mutex<Progress> = Mutex::new(Progress::DEFAULT);
+
+/// How countdowns show their progress
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub style: OutputStyle,
+    /// Show anything at all
+    pub visible: bool,
+    /// Redraw the time left in place; otherwise only the start and end
+    pub live: bool,
+}
+
+impl Progress {
+    const DEFAULT: Self = Self {
+        style: OutputStyle {
+            emoji: true,
+            color: false,
+            width: None,
+        },
+        visible: true,
+        live: false,
+    };
+
+    /// Progress for the global options: hidden with `--quiet` and for
+    /// machine formats, redrawn in place when stderr is a terminal
+    pub fn for_cli(cli: &Cli) -> Self {
+        Self {
+            style: cli.output_style(),
+            visible: !cli.quiet && matches!(cli.format, OutputFormat::Human | OutputFormat::Csv),
+            live: std::io::stderr().is_terminal(),
+        }
+    }
+
+    /// Nothing shown
+    pub const fn hidden() -> Self {
+        Self {
+            visible: false,
+            ..Self::DEFAULT
+        }
+    }
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Use `progress` for the countdowns of this invocation
+pub fn set_progress(progress: Progress) {
+    *PROGRESS.lock().unwrap_or_else(|e| e.into_inner()) = progress;
+}
+
+fn progress() -> Progress {
+    *PROGRESS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// How a countdown ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Completed,
+    Cancelled,
+}
+
+impl Outcome {
+    /// `Ok` if completed, otherwise a [`PowerCliError::Cancelled`] naming
+    /// what was being waited for
+    pub fn or_cancelled(self, during: &str) -> Result<()> {
+        match self {
+            Outcome::Completed => Ok(()),
+            Outcome::Cancelled => Err(PowerCliError::Cancelled {
+                during: during.to_string(),
+            }),
+        }
+    }
+}
+
+/// Wait `duration`, showing the time left as `label`, until `cancel` fires
+pub async fn countdown(duration: Duration, label: &str, cancel: &CancellationToken) -> Outcome {
+    countdown_to(&mut std::io::stderr(), progress(), duration, label, cancel).await
+}
+
+/// [`countdown`] writing to `out` with the given display
+pub async fn countdown_to<W: Write>(
+    out: &mut W,
+    progress: Progress,
+    duration: Duration,
+    label: &str,
+    cancel: &CancellationToken,
+) -> Outcome {
+    let deadline = Instant::now() + duration;
+    let style = progress.style;
+    let mut shown = |line: String, end: bool| {
+        if !progress.visible {
+            return;
+        }
+        let _ = match (progress.live, end) {
+            (true, false) => write!(out, "\r{}{}", line, CLEAR_LINE),
+            (true, true) => writeln!(out, "\r{}{}", line, CLEAR_LINE),
+            (false, _) => writeln!(out, "{}", line),
+        };
+        let _ = out.flush();
+    };
+
+    if !progress.live {
+        shown(
+            style.prefixed(
+                "⏱️",
+                &format!("{} ({} s)...", label, seconds_left(duration)),
+            ),
+            false,
+        );
+    }
+    let outcome = loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break Outcome::Completed;
+        }
+        if progress.live {
+            shown(
+                style.prefixed(
+                    "⏱️",
+                    &format!("{}... {} s remaining", label, seconds_left(left)),
+                ),
+                false,
+            );
+        }
+        // Redraw on whole seconds left, so the count never skips a number
+        let step = match left.as_nanos() % COUNTDOWN_TICK.as_nanos() {
+            0 => COUNTDOWN_TICK,
+            part => Duration::from_nanos(part as u64),
+        };
+        tokio::select! {
+            _ = sleep_until(Instant::now() + step) => {}
+            _ = cancel.cancelled() => break Outcome::Cancelled,
+        }
+    };
+
+    match outcome {
+        Outcome::Completed => shown(style.prefixed("✅", &format!("{} done", label)), true),
+        Outcome::Cancelled => shown(style.prefixed("⚠️", &format!("{} cancelled", label)), true),
+    }
+    outcome
+}
+
+/// Wait `duration` without showing anything, until `cancel` fires
+pub async fn wait(duration: Duration, cancel: &CancellationToken) -> Outcome {
+    countdown_to(
+        &mut std::io::sink(),
+        Progress::hidden(),
+        duration,
+        "",
+        cancel,
+    )
+    .await
+}
+
+/// Activity indicator for a wait of unknown length, e.g. an mcumgr upload
+///
+/// Shown like a countdown: animated in place on a terminal, the label once
+/// otherwise.
+pub struct Spinner {
+    label: String,
+    progress: Progress,
+    frame: usize,
+}
+
+impl Spinner {
+    /// Start showing `label` on stderr
+    pub fn new(label: &str) -> Self {
+        let spinner = Self {
+            label: label.to_string(),
+            progress: progress(),
+            frame: 0,
+        };
+        if spinner.progress.visible && !spinner.progress.live {
+            eprintln!("{}", spinner.progress.style.prefixed("⏳", label));
+        }
+        spinner
+    }
+
+    /// Advance the animation
+    pub fn tick(&mut self) {
+        if !(self.progress.visible && self.progress.live) {
+            return;
+        }
+        let frames = if self.progress.style.emoji {
+            SPINNER_FRAMES
+        } else {
+            SPINNER_FRAMES_ASCII
+        };
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r{} {}{}",
+            frames[self.frame % frames.len()],
+            self.label,
+            CLEAR_LINE
+        );
+        let _ = stderr.flush();
+        self.frame += 1;
+    }
+
+    /// Replace the animation with `message` after `icon`
+    pub fn finish(self, icon: &str, message: &str) {
+        if !self.progress.visible {
+            return;
+        }
+        let line = self.progress.style.prefixed(icon, message);
+        if self.progress.live {
+            eprintln!("\r{}{}", line, CLEAR_LINE);
+        } else {
+            eprintln!("{}", line);
+        }
+    }
+}
+
+/// Whole seconds in `left`, rounded up so the count ends at 1
+fn seconds_left(left: Duration) -> u64 {
+    left.as_millis().div_ceil(1000) as u64
+}
+
+/// A token cancelled by Ctrl-C; a second Ctrl-C exits at once
+///
+/// Must be called within a Tokio runtime. Once called, Ctrl-C no longer
+/// ends the process by itself, so only commands that check the token use it.
+pub fn cancel_on_ctrl_c() -> CancellationToken {
+    let token = CancellationToken::new();
+    let cancel = token.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        log::warn!("Interrupted; stopping (Ctrl-C again to quit at once)");
+        cancel.cancel();
+        if tokio::signal::ctrl_c().await.is_ok() {
+            std::process::exit(INTERRUPTED_EXIT_CODE);
+        }
+    });
+    token
+}
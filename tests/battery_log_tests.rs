@@ -0,0 +1,314 @@
+/*
+ * E-ink Power CLI - Battery Log Export Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Reading NDJSON, CSV and console battery logs and writing them back as
+//! tables
+
+use assert_cmd::Command;
+use chrono::{TimeZone, Utc};
+use eink_power_cli::battery_log::{
+    export_rows, parse_time_bound, read_log, write_csv, ConsoleLog, Export, ExportFormat,
+    EXPORT_COLUMNS,
+};
+
+/// `battery read --watch`, `battery read` (before schema_version),
+/// `battery read --samples` and `monitor` envelopes, with a summary, an
+/// error envelope and two lines that cannot be read
+const NDJSON_LOG: &str = r#"{"schema_version":1,"timestamp":"2025-10-09T14:00:00.500Z","command":"battery watch","status":"success","data":{"timestamp":"2025-10-09T14:00:00Z","voltage_mv":3850,"current_ma":-125,"charge_mah":1500,"power_mw":-481,"temperature_c":null,"soc_percent":null},"raw_response":null}
+{"timestamp":"2025-10-09T14:01:00Z","command":"battery read","status":"success","data":{"voltage_mv":3840,"current_ma":-120,"charge_mah":1498,"power_mw":null,"temperature_c":24.5},"raw_response":"..."}
+{"schema_version":1,"timestamp":"2025-10-09T14:02:00Z","command":"monitor","status":"success","data":{"voltage_mv":3830,"current_ma":-110,"adc_mode":null,"source":"pm measure","charging_state":"discharging","charging":false,"since":null},"raw_response":null}
+{"schema_version":1,"timestamp":"2025-10-09T14:03:30Z","command":"battery read","status":"success","data":{"median":{},"requested":2,"failed":0,"samples":[{"timestamp":"2025-10-09T14:03:00Z","voltage_mv":3820,"current_ma":null,"charge_mah":1494,"power_mw":null,"temperature_c":null,"soc_percent":null},{"timestamp":"2025-10-09T14:03:01Z","voltage_mv":3821,"current_ma":null,"charge_mah":1494,"power_mw":null,"temperature_c":null,"soc_percent":null}]},"raw_response":null}
+{"schema_version":1,"timestamp":"2025-10-09T14:04:00Z","command":"battery watch summary","status":"success","data":{"samples":4},"raw_response":null}
+{"schema_version":1,"timestamp":"2025-10-09T14:04:00Z","command":"battery read","status":"error","data":{"error":"timeout"},"raw_response":null}
+{"schema_version":1,"timestamp":"2025-10-09T14:05:00Z","command":"battery watch","status":"succ
+{"schema_version":99,"timestamp":"2025-10-09T14:06:00Z","command":"battery watch","status":"success","data":{"timestamp":"2025-10-09T14:06:00Z"},"raw_response":null}
+"#;
+
+/// `battery read --watch --format csv` output, logged twice with a torn line
+const CSV_LOG: &str = "timestamp,voltage_mv,current_ma,charge_mah,power_mw,soc_percent
+2025-10-09T14:00:00+00:00,3850,-125,1500,-481,
+2025-10-09T14:00:10+00:00,3849,-126,1499,,
+2025-10-09T14:00:20+00:00,38
+timestamp,voltage_mv,current_ma,charge_mah,power_mw,soc_percent
+2025-10-09T13:59:50+00:00,3851,-124,1500,,50.0
+";
+
+/// `monitor --follow --console-log` output: firmware 2.3 samples, a log
+/// line and a line without a time stamp
+const CONSOLE_LOG: &str =
+    "2025-10-09T14:00:00.000Z [00:01:07.427,000] <inf> power_mgmt: Monitor sample:
+2025-10-09T14:00:00.004Z   Voltage: 7412 mV
+2025-10-09T14:00:00.004Z   Current: -142 mA
+2025-10-09T14:00:02.100Z [00:01:09.527,000] <wrn> nfc: field lost
+2025-10-09T14:00:05.000Z [00:01:12.427,000] <inf> power_mgmt: Monitor sample:
+2025-10-09T14:00:05.003Z   Voltage: 7409 mV
+2025-10-09T14:00:05.003Z   Current: -139 mA
+  Power: -1030 mW
+";
+
+fn at(h: u32, m: u32, s: u32) -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 10, 9, h, m, s).unwrap()
+}
+
+#[test]
+fn ndjson_logs_are_normalized_across_commands_and_schema_versions() {
+    let (records, stats) = read_log(NDJSON_LOG);
+
+    assert_eq!(stats.lines, 8);
+    assert_eq!(stats.ignored, 2);
+    assert_eq!(stats.skipped, 2);
+    let times: Vec<_> = records.iter().map(|record| record.timestamp).collect();
+    assert_eq!(
+        times,
+        [
+            at(14, 0, 0),
+            at(14, 1, 0),
+            at(14, 2, 0),
+            at(14, 3, 0),
+            at(14, 3, 1)
+        ]
+    );
+    assert_eq!(records[1].battery.temperature_c, Some(24.5));
+    assert_eq!(records[2].battery.voltage_mv, Some(3830));
+    assert_eq!(records[2].battery.charge_mah, None);
+}
+
+#[test]
+fn csv_logs_are_read_by_header_and_sorted_on_export() {
+    let (records, stats) = read_log(CSV_LOG);
+    assert_eq!(stats.lines, 6);
+    assert_eq!(stats.skipped, 1);
+    assert_eq!(stats.ignored, 2);
+
+    let rows = export_rows(records, None, None, None);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].record.timestamp, at(13, 59, 50));
+    assert_eq!(rows[0].record.soc_percent, Some(50.0));
+    assert_eq!(rows[0].dt_s, None);
+
+    // Power from voltage and current when the log has none
+    assert_eq!(rows[2].record.battery.power_mw, Some(-485));
+    assert_eq!(rows[2].elapsed_s, 20.0);
+    assert_eq!(rows[2].dt_s, Some(10.0));
+    assert_eq!(rows[2].voltage_delta_mv, Some(-1));
+    assert_eq!(rows[2].charge_delta_mah, Some(-1));
+}
+
+#[test]
+fn time_range_keeps_from_up_to_but_not_including_to() {
+    let (records, _) = read_log(NDJSON_LOG);
+    let rows = export_rows(records, Some(at(14, 1, 0)), Some(at(14, 3, 0)), Some(3000));
+
+    let times: Vec<_> = rows.iter().map(|row| row.record.timestamp).collect();
+    assert_eq!(times, [at(14, 1, 0), at(14, 2, 0)]);
+    assert_eq!(rows[0].elapsed_s, 0.0);
+    let soc = rows[0].record.soc_percent.unwrap();
+    assert!((soc - 49.93).abs() < 0.01, "{}", soc);
+    // The monitor sample has no charge, so no state of charge either
+    assert_eq!(rows[1].record.soc_percent, None);
+}
+
+#[test]
+fn time_bounds_accept_dates_local_times_and_rfc3339() {
+    assert_eq!(
+        parse_time_bound("2025-10-09T14:00:00Z").unwrap(),
+        at(14, 0, 0)
+    );
+    let midnight = chrono::Local
+        .with_ymd_and_hms(2025, 10, 9, 0, 0, 0)
+        .unwrap()
+        .with_timezone(&Utc);
+    assert_eq!(parse_time_bound("2025-10-09").unwrap(), midnight);
+    assert_eq!(
+        parse_time_bound("2025-10-09 00:00").unwrap(),
+        parse_time_bound("2025-10-09").unwrap()
+    );
+    assert!(parse_time_bound("yesterday").is_err());
+}
+
+#[test]
+fn exported_csv_reads_back_as_the_same_records() {
+    let (records, _) = read_log(NDJSON_LOG);
+    let rows = export_rows(records, None, None, None);
+    let mut csv = Vec::new();
+    write_csv(&rows, &mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+
+    assert_eq!(csv.lines().next().unwrap(), EXPORT_COLUMNS.join(","));
+    assert_eq!(
+        csv.lines().nth(2).unwrap(),
+        "2025-10-09T14:01:00.000Z,3840,-120,1498,-461,24.5,,60.000,60.000,-10,-2"
+    );
+
+    let (again, stats) = read_log(&csv);
+    assert_eq!(stats.skipped, 0);
+    let again = export_rows(again, None, None, None);
+    assert_eq!(again, rows);
+}
+
+#[test]
+fn export_picks_the_format_from_the_output_file() {
+    assert_eq!(
+        ExportFormat::for_path("day.PARQUET".as_ref()),
+        ExportFormat::Parquet
+    );
+    assert_eq!(
+        ExportFormat::for_path("day.csv".as_ref()),
+        ExportFormat::Csv
+    );
+    assert_eq!(ExportFormat::for_path("day".as_ref()), ExportFormat::Csv);
+}
+
+#[cfg(not(feature = "parquet"))]
+#[test]
+fn parquet_needs_the_feature() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("battery.csv");
+    std::fs::write(&input, CSV_LOG).unwrap();
+    let export = Export {
+        input,
+        output: dir.path().join("battery.parquet"),
+        format: None,
+        from: None,
+        to: None,
+        capacity_mah: None,
+        show_unparsed: false,
+    };
+    let error = export.run().unwrap_err().to_string();
+    assert!(error.contains("'parquet' feature"), "{}", error);
+}
+
+#[cfg(feature = "parquet")]
+#[test]
+fn parquet_export_has_a_row_per_record() {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("battery.ndjson");
+    std::fs::write(&input, NDJSON_LOG).unwrap();
+    let output = dir.path().join("battery.parquet");
+    let report = Export {
+        input,
+        output: output.clone(),
+        format: None,
+        from: None,
+        to: None,
+        capacity_mah: None,
+        show_unparsed: false,
+    }
+    .run()
+    .unwrap();
+    assert_eq!(report.format, ExportFormat::Parquet);
+
+    let reader = SerializedFileReader::new(std::fs::File::open(output).unwrap()).unwrap();
+    let metadata = reader.metadata().file_metadata();
+    assert_eq!(metadata.num_rows(), 5);
+    let columns: Vec<&str> = metadata
+        .schema_descr()
+        .columns()
+        .iter()
+        .map(|column| column.name())
+        .collect();
+    assert_eq!(columns, EXPORT_COLUMNS);
+}
+
+#[test]
+fn log_export_command_reports_what_it_wrote() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("battery.ndjson");
+    std::fs::write(&input, NDJSON_LOG).unwrap();
+    let output = dir.path().join("battery.csv");
+
+    let result = Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .env("EINK_POWER_CLI_STATE_DIR", dir.path())
+        .args(["--format", "json", "log", "export"])
+        .arg(&input)
+        .arg("-o")
+        .arg(&output)
+        .args(["--from", "2025-10-09T14:01:00Z"])
+        .output()
+        .unwrap();
+    assert!(result.status.success(), "{:?}", result);
+
+    let json: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    assert_eq!(json["command"], "log export");
+    assert_eq!(json["data"]["format"], "csv");
+    assert_eq!(json["data"]["written"], 4);
+    assert_eq!(json["data"]["filtered"], 1);
+    assert_eq!(json["data"]["skipped"], 2);
+    let csv = std::fs::read_to_string(output).unwrap();
+    assert_eq!(csv.lines().count(), 5);
+}
+
+#[test]
+fn console_logs_are_read_with_the_monitor_sample_formats() {
+    let (records, stats) = read_log(CONSOLE_LOG);
+
+    assert_eq!(stats.lines, 8);
+    // The NFC warning and the line without a time stamp
+    assert_eq!(stats.skipped, 2);
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].timestamp, at(14, 0, 0));
+    assert_eq!(records[0].battery.voltage_mv, Some(7412));
+    assert_eq!(records[1].timestamp, at(14, 0, 5));
+    assert_eq!(records[1].battery.current_ma, Some(-139));
+}
+
+#[test]
+fn console_log_stamps_lines_as_they_complete() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("console.log");
+    let mut log = ConsoleLog::append(&path).unwrap();
+    log.record(at(14, 0, 0), "Monitor sample:\r\n  Volt")
+        .unwrap();
+    log.record(at(14, 0, 1), "age: 7412 mV\r\n\r\n  Current: -142 mA")
+        .unwrap();
+    log.finish().unwrap();
+    drop(log);
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(
+        text,
+        "2025-10-09T14:00:00.000Z Monitor sample:
+2025-10-09T14:00:00.000Z   Voltage: 7412 mV
+2025-10-09T14:00:01.000Z   Current: -142 mA
+"
+    );
+    let (records, _) = read_log(&text);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].battery.current_ma, Some(-142));
+}
+
+#[test]
+fn log_export_shows_unparsed_console_lines_when_asked() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("console.log");
+    std::fs::write(&input, CONSOLE_LOG).unwrap();
+
+    let result = Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .env("EINK_POWER_CLI_STATE_DIR", dir.path())
+        .args(["--format", "json", "log", "export"])
+        .arg(&input)
+        .arg("-o")
+        .arg(dir.path().join("battery.csv"))
+        .arg("--show-unparsed")
+        .output()
+        .unwrap();
+    assert!(result.status.success(), "{:?}", result);
+
+    let json: serde_json::Value = serde_json::from_slice(&result.stdout).unwrap();
+    let data = &json["data"];
+    assert_eq!(data["written"], 2);
+    assert_eq!(data["sample_format"], "labeled");
+    assert_eq!(data["unparsed"][0]["line"], 4);
+    assert_eq!(
+        data["unparsed"][0]["text"],
+        "[00:01:09.527,000] <wrn> nfc: field lost"
+    );
+    assert_eq!(data["unparsed"].as_array().unwrap().len(), 1);
+}
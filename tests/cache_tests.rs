@@ -0,0 +1,216 @@
+/*
+ * E-ink Power CLI - Response Cache Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Response cache tests; every scripted mock exchange is one serial round trip
+
+use eink_power_cli::power::control::{PowerController, PowerState};
+use eink_power_cli::power::Rail;
+use eink_power_cli::serial::cache::is_cacheable;
+use eink_power_cli::serial::{Connection, MockSerial};
+use std::time::Duration;
+
+const TTL: Duration = Duration::from_secs(2);
+
+fn reply(command: &str, body: &str) -> String {
+    format!("{}\r\n{}\r\nprod:~$ ", command, body)
+}
+
+#[tokio::test]
+async fn repeated_query_within_ttl_is_sent_once() {
+    let serial = MockSerial::builder()
+        .expect("version", &reply("version", "Version: 2.5.0"))
+        .build();
+    let mut connection = Connection::mock(serial);
+    connection.enable_response_cache(TTL);
+
+    for _ in 0..3 {
+        assert_eq!(
+            connection.send_command("version").await.unwrap(),
+            "Version: 2.5.0"
+        );
+    }
+    assert_eq!(connection.commands_sent(), ["version"]);
+    let stats = connection.cache_stats().unwrap();
+    assert_eq!((stats.hits, stats.misses), (2, 1));
+}
+
+#[tokio::test]
+async fn expired_entry_is_queried_again() {
+    let serial = MockSerial::builder()
+        .expect("ltc2959 read", &reply("ltc2959 read", "Voltage: 3850 mV"))
+        .expect("ltc2959 read", &reply("ltc2959 read", "Voltage: 3849 mV"))
+        .build();
+    let mut connection = Connection::mock(serial);
+    connection.enable_response_cache(Duration::from_millis(50));
+
+    connection.send_command("ltc2959 read").await.unwrap();
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    assert_eq!(
+        connection.send_command("ltc2959 read").await.unwrap(),
+        "Voltage: 3849 mV"
+    );
+}
+
+/// Controller over a scripted mock with the cache enabled
+fn cached_controller(serial: MockSerial) -> PowerController {
+    let mut connection = Connection::mock(serial);
+    connection.enable_response_cache(TTL);
+    PowerController::new(connection)
+}
+
+#[tokio::test]
+async fn rail_change_invalidates_pm_and_battery_but_not_version() {
+    let serial = MockSerial::builder()
+        .expect("version", &reply("version", "Version: 2.5.0"))
+        .expect("pm pmic status", &reply("pm pmic status", "PMIC: OFF"))
+        .expect("ltc2959 read", &reply("ltc2959 read", "Current: -5 mA"))
+        .expect("pm pmic on", &reply("pm pmic on", "PMIC power ON"))
+        .expect("pm pmic status", &reply("pm pmic status", "PMIC: ON"))
+        .expect("ltc2959 read", &reply("ltc2959 read", "Current: -120 mA"))
+        .build();
+    let mut controller = cached_controller(serial);
+
+    controller.get_system_info().await.unwrap();
+    assert_eq!(
+        controller.pm_command("pmic status").await.unwrap(),
+        "PMIC: OFF"
+    );
+    controller.battery_read().await.unwrap();
+
+    controller
+        .control_rail(Rail::Pmic, PowerState::On)
+        .await
+        .unwrap();
+
+    // Still cached: version is a different subsystem
+    controller.get_system_info().await.unwrap();
+    assert_eq!(
+        controller.pm_command("pmic status").await.unwrap(),
+        "PMIC: ON"
+    );
+    assert!(controller.battery_read().await.unwrap().contains("-120"));
+    assert_eq!(controller.connection().commands_sent().len(), 6);
+}
+
+#[tokio::test]
+async fn reset_invalidates_everything() {
+    let serial = MockSerial::builder()
+        .expect("version", &reply("version", "Version: 2.5.0"))
+        .expect("pm defaults", &reply("pm defaults", "PMIC: ON"))
+        .expect("pm system reset", &reply("pm system reset", "Resetting"))
+        .expect("version", &reply("version", "Version: 2.6.0"))
+        .expect("pm defaults", &reply("pm defaults", "PMIC: OFF"))
+        .build();
+    let mut controller = cached_controller(serial);
+
+    controller.get_system_info().await.unwrap();
+    controller.pm_command("defaults").await.unwrap();
+    controller.pm_command("system reset").await.unwrap();
+    assert_eq!(
+        controller.get_system_info().await.unwrap(),
+        "Version: 2.6.0"
+    );
+    assert_eq!(
+        controller.pm_command("defaults").await.unwrap(),
+        "PMIC: OFF"
+    );
+    assert_eq!(controller.connection().cache_stats().unwrap().hits, 0);
+}
+
+#[tokio::test]
+async fn coulomb_counter_command_invalidates_battery_readings() {
+    let serial = MockSerial::builder()
+        .expect("ltc2959 read", &reply("ltc2959 read", "Charge: 2450 mAh"))
+        .expect(
+            "power coulomb",
+            &reply("power coulomb", "Coulomb counter: 0"),
+        )
+        .expect("ltc2959 read", &reply("ltc2959 read", "Charge: 0 mAh"))
+        .build();
+    let mut connection = Connection::mock(serial);
+    connection.enable_response_cache(TTL);
+
+    connection.send_command("ltc2959 read").await.unwrap();
+    connection.send_command("power coulomb").await.unwrap();
+    assert_eq!(
+        connection.send_command("ltc2959 read").await.unwrap(),
+        "Charge: 0 mAh"
+    );
+    assert_eq!(connection.cache_stats().unwrap().hits, 0);
+}
+
+#[tokio::test]
+async fn gpio_write_invalidates_rail_status() {
+    let serial = MockSerial::builder()
+        .expect("pm wifi status", &reply("pm wifi status", "WiFi: OFF"))
+        .expect("gpio set A 5 1", &reply("gpio set A 5 1", "OK"))
+        .expect("pm wifi status", &reply("pm wifi status", "WiFi: ON"))
+        .build();
+    let mut connection = Connection::mock(serial);
+    connection.enable_response_cache(TTL);
+
+    connection.send_command("pm wifi status").await.unwrap();
+    connection.send_command("gpio set A 5 1").await.unwrap();
+    assert_eq!(
+        connection.send_command("pm wifi status").await.unwrap(),
+        "WiFi: ON"
+    );
+}
+
+#[tokio::test]
+async fn unlisted_command_invalidates_everything() {
+    let serial = MockSerial::builder()
+        .expect("version", &reply("version", "Version: 2.5.0"))
+        .expect("shell crlf off", &reply("shell crlf off", "OK"))
+        .expect("version", &reply("version", "Version: 2.5.0"))
+        .expect("ping", &reply("ping", "pong"))
+        .build();
+    let mut connection = Connection::mock(serial);
+    connection.enable_response_cache(TTL);
+
+    connection.send_command("version").await.unwrap();
+    connection.send_command("shell crlf off").await.unwrap();
+    connection.send_command("version").await.unwrap();
+    // A listed read-only command keeps the entries
+    connection.send_command("ping").await.unwrap();
+    connection.send_command("version").await.unwrap();
+    assert_eq!(connection.cache_stats().unwrap().hits, 1);
+}
+
+#[tokio::test]
+async fn cache_is_off_unless_enabled() {
+    let serial = MockSerial::builder()
+        .expect("version", &reply("version", "Version: 2.5.0"))
+        .expect("version", &reply("version", "Version: 2.5.0"))
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    connection.send_command("version").await.unwrap();
+    connection.send_command("version").await.unwrap();
+    assert_eq!(connection.cache_stats(), None);
+}
+
+#[test]
+fn only_read_only_queries_are_cacheable() {
+    for command in [
+        "version",
+        "pm defaults",
+        "pm wifi status",
+        "ltc2959 read",
+        "gpio get A 5",
+    ] {
+        assert!(is_cacheable(command), "{}", command);
+    }
+    for command in [
+        "ping",
+        "pm measure",
+        "pm pmic on",
+        "pm defaults save",
+        "gpio set A 5 1",
+    ] {
+        assert!(!is_cacheable(command), "{}", command);
+    }
+}
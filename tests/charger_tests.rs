@@ -0,0 +1,189 @@
+/*
+ * E-ink Power CLI - Charger State Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Charger state from firmware replies, the fallback to the current and
+//! conflicts between them
+//!
+//! The `ltc2959_status_charger_*.txt` fixtures are `ltc2959 status` replies
+//! of firmware printing a `Charger:` line, one per state; `cc_gpio_*.txt`
+//! are `ltc2959 cc_gpio status` replies.
+
+use chrono::{TimeZone, Utc};
+use eink_power_cli::json::ResponseParser;
+use eink_power_cli::power::battery::{ChargingState, ChargingTracker, DEFAULT_DEADBAND_MA};
+use eink_power_cli::power::charger::{
+    reported_state, ChargerReport, ChargerState, ChargerStatus, CURRENT_SOURCE,
+};
+
+mod common;
+use common::fixture;
+
+/// Charger status from an `ltc2959 status` reply and the current it shows
+fn status_of(reply: &str) -> ChargerStatus {
+    let current = ResponseParser::parse_ltc2959_status(reply)
+        .current_ma
+        .map(|ma| ChargingState::from_current(ma, DEFAULT_DEADBAND_MA));
+    let reports: Vec<ChargerReport> = reported_state(reply)
+        .map(|state| ChargerReport {
+            source: "ltc2959 status",
+            state,
+        })
+        .into_iter()
+        .collect();
+    ChargerStatus::resolve(&reports, current)
+}
+
+#[test]
+fn every_reported_state_is_read() {
+    for (name, state, brief) in [
+        ("charging", ChargerState::Charging, "charging"),
+        ("trickle", ChargerState::Trickle, "trickle"),
+        ("complete", ChargerState::Complete, "complete"),
+        ("discharging", ChargerState::Discharging, "discharging"),
+        ("fault", ChargerState::Fault, "fault"),
+        ("unknown", ChargerState::Unknown, "unknown"),
+    ] {
+        let status = status_of(&fixture(&format!("ltc2959_status_charger_{}.txt", name)));
+        assert_eq!(status.state, state, "{}", name);
+        assert_eq!(status.source, "ltc2959 status", "{}", name);
+        assert!(
+            status.conflicts.is_empty(),
+            "{}: {:?}",
+            name,
+            status.conflicts
+        );
+        assert_eq!(status.brief(), brief, "{}", name);
+    }
+}
+
+#[test]
+fn trickle_charge_is_charging_though_the_current_is_idle() {
+    let status = status_of(&fixture("ltc2959_status_charger_trickle.txt"));
+    // 3 mA is within the deadband
+    assert_eq!(status.current_state, Some(ChargingState::Idle));
+    assert!(status.state.is_charging());
+    assert!(!ChargerState::Complete.is_charging());
+    assert!(!ChargerState::Fault.is_charging());
+}
+
+#[test]
+fn without_a_reported_state_the_current_decides() {
+    let reply = fixture("ltc2959_status_fw23.txt");
+    // `Charge Complete: NO` does not say whether a charger is there
+    assert_eq!(reported_state(&reply), None);
+    assert_eq!(reported_state(&fixture("cc_gpio_low.txt")), None);
+
+    let status = status_of(&reply);
+    assert_eq!(status.state, ChargerState::Discharging);
+    assert_eq!(status.source, CURRENT_SOURCE);
+    assert!(!status.is_reported());
+    assert_eq!(status.brief(), "discharging");
+
+    // Within the deadband the state is unknown, but the brief form keeps
+    // the word the current gives
+    let idle = ChargerStatus::resolve(&[], Some(ChargingState::Idle));
+    assert_eq!(idle.state, ChargerState::Unknown);
+    assert_eq!(idle.brief(), "idle");
+    assert_eq!(
+        ChargerStatus::resolve(&[], None).state,
+        ChargerState::Unknown
+    );
+}
+
+#[test]
+fn flags_report_a_complete_charge() {
+    assert_eq!(
+        reported_state(&fixture("cc_gpio_high.txt")),
+        Some(ChargerState::Complete)
+    );
+    assert_eq!(
+        reported_state("📊 LTC2959 Status:\nCharge Complete: YES\n"),
+        Some(ChargerState::Complete)
+    );
+    // A `Charger:` line wins over the flag
+    assert_eq!(
+        reported_state("Charge Complete: YES\nCharger: Trickle\n"),
+        Some(ChargerState::Trickle)
+    );
+}
+
+#[test]
+fn current_against_the_charger_is_a_conflict() {
+    let status = status_of(&fixture("ltc2959_status_charger_conflict.txt"));
+    assert_eq!(status.state, ChargerState::Complete);
+    assert_eq!(status.current_state, Some(ChargingState::Discharging));
+    assert_eq!(
+        status.conflicts,
+        ["the battery current says discharging but `ltc2959 status` reports complete"]
+    );
+
+    let json = serde_json::to_value(&status).unwrap();
+    assert_eq!(json["state"], "complete");
+    assert_eq!(json["current_state"], "discharging");
+    assert_eq!(json["conflicts"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn sources_that_disagree_are_listed_and_the_first_wins() {
+    let reports = [
+        ChargerReport {
+            source: "ltc2959 status",
+            state: ChargerState::Trickle,
+        },
+        ChargerReport {
+            source: "ltc2959 cc_gpio status",
+            state: ChargerState::Complete,
+        },
+    ];
+    let status = ChargerStatus::resolve(&reports, Some(ChargingState::Idle));
+    assert_eq!(status.state, ChargerState::Trickle);
+    assert_eq!(
+        status.conflicts,
+        ["`ltc2959 cc_gpio status` reports complete but `ltc2959 status` reports trickle"]
+    );
+}
+
+#[test]
+fn firmware_words_for_each_state() {
+    for (raw, state) in [
+        ("CC", Some(ChargerState::Charging)),
+        ("Fast-charge", Some(ChargerState::Charging)),
+        ("PRECHARGE", Some(ChargerState::Trickle)),
+        ("Done", Some(ChargerState::Complete)),
+        ("No input", Some(ChargerState::Discharging)),
+        ("Thermal fault", Some(ChargerState::Fault)),
+        ("Safety timeout", Some(ChargerState::Fault)),
+        ("42", None),
+    ] {
+        assert_eq!(ChargerState::parse(raw), state, "{}", raw);
+    }
+}
+
+#[test]
+fn charger_state_decides_monitor_transitions() {
+    let at = |second| Utc.with_ymd_and_hms(2025, 10, 9, 14, 2, second).unwrap();
+    let mut tracker = ChargingTracker::new(DEFAULT_DEADBAND_MA, 1);
+    assert_eq!(tracker.update(-140, at(0)), None);
+
+    // Trickle charge at 3 mA would read as idle from the current
+    let transition = tracker
+        .update_with(3, Some(ChargerState::Trickle), at(1))
+        .unwrap();
+    assert_eq!(transition.to, ChargingState::Charging);
+    assert!(tracker.is_charging());
+
+    // Termination with a few mA still flowing is idle
+    let transition = tracker
+        .update_with(8, Some(ChargerState::Complete), at(2))
+        .unwrap();
+    assert_eq!(transition.to, ChargingState::Idle);
+
+    // A fault says nothing about the direction; the current decides
+    let transition = tracker
+        .update_with(-90, Some(ChargerState::Fault), at(3))
+        .unwrap();
+    assert_eq!(transition.to, ChargingState::Discharging);
+}
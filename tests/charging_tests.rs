@@ -0,0 +1,178 @@
+/*
+ * E-ink Power CLI - Charging State Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Deadband and debounce of the charging-state tracker, and the running
+//! statistics of `battery read --watch`
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use eink_power_cli::json::{BatteryJson, BatteryWatchSampleJson};
+use eink_power_cli::power::battery::{
+    BatteryWatchStats, ChargingState, ChargingTracker, VoltageHistory,
+};
+
+fn at(second: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 10, 9, 14, 2, 0).unwrap() + Duration::seconds(second)
+}
+
+/// Feed `currents` one second apart; returns `(sample index, new state)` per transition
+fn transitions(tracker: &mut ChargingTracker, currents: &[i16]) -> Vec<(usize, ChargingState)> {
+    currents
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &current)| {
+            tracker
+                .update(current, at(i as i64))
+                .map(|transition| (i, transition.to))
+        })
+        .collect()
+}
+
+#[test]
+fn deadband_classifies_small_currents_as_idle() {
+    assert_eq!(ChargingState::from_current(0, 5), ChargingState::Idle);
+    assert_eq!(ChargingState::from_current(5, 5), ChargingState::Idle);
+    assert_eq!(ChargingState::from_current(-5, 5), ChargingState::Idle);
+    assert_eq!(ChargingState::from_current(6, 5), ChargingState::Charging);
+    assert_eq!(
+        ChargingState::from_current(-6, 5),
+        ChargingState::Discharging
+    );
+    assert_eq!(ChargingState::from_current(1, 0), ChargingState::Charging);
+    assert_eq!(
+        ChargingState::from_current(i16::MIN, 5),
+        ChargingState::Discharging
+    );
+}
+
+#[test]
+fn noise_around_zero_stays_idle() {
+    let mut tracker = ChargingTracker::new(5, 1);
+    let noise = [0, 3, -2, 5, -5, 1, -4, 2, 0, -1];
+
+    assert!(transitions(&mut tracker, &noise).is_empty());
+    assert_eq!(tracker.state(), Some(ChargingState::Idle));
+    assert_eq!(tracker.since(), Some(at(0)));
+    assert_eq!(tracker.transitions(), 0);
+}
+
+#[test]
+fn single_sample_spikes_are_debounced() {
+    let mut tracker = ChargingTracker::new(5, 3);
+    // Discharging with two-sample spikes into charging and idle
+    let currents = [-120, -118, 40, 38, -121, 2, 0, -119, -122];
+
+    assert!(transitions(&mut tracker, &currents).is_empty());
+    assert_eq!(tracker.state(), Some(ChargingState::Discharging));
+    assert!(!tracker.is_charging());
+}
+
+#[test]
+fn sustained_change_switches_from_its_first_sample() {
+    let mut tracker = ChargingTracker::new(5, 3);
+    let currents = [-120, -119, 250, 12, 248, 251, 249, 0, 1, -2, 3];
+
+    // 12 mA is still charging, so the run starting at index 2 completes at 4
+    let seen = transitions(&mut tracker, &currents);
+    assert_eq!(
+        seen,
+        [(4, ChargingState::Charging), (9, ChargingState::Idle)]
+    );
+    assert_eq!(tracker.since(), Some(at(7)));
+    assert_eq!(tracker.transitions(), 2);
+}
+
+#[test]
+fn interrupted_candidate_restarts_debounce() {
+    let mut tracker = ChargingTracker::new(5, 2);
+    // Idle at the edge of the deadband, flickering in and out
+    let currents = [0, 6, 4, 7, 5, 6, 8, 9];
+
+    let seen = transitions(&mut tracker, &currents);
+    assert_eq!(seen, [(6, ChargingState::Charging)]);
+    assert_eq!(tracker.since(), Some(at(5)));
+    assert!(tracker.is_charging());
+}
+
+#[test]
+fn zero_debounce_switches_immediately() {
+    let mut tracker = ChargingTracker::new(5, 0);
+    let seen = transitions(&mut tracker, &[-50, 50, -50]);
+
+    assert_eq!(
+        seen,
+        [
+            (1, ChargingState::Charging),
+            (2, ChargingState::Discharging)
+        ]
+    );
+}
+
+#[test]
+fn tracker_has_no_state_before_first_sample() {
+    let tracker = ChargingTracker::new(5, 2);
+    assert_eq!(tracker.state(), None);
+    assert_eq!(tracker.since(), None);
+    assert!(!tracker.is_charging());
+}
+
+fn reading(
+    voltage_mv: Option<u16>,
+    current_ma: Option<i16>,
+    charge_mah: Option<u16>,
+) -> BatteryJson {
+    BatteryJson {
+        voltage_mv,
+        current_ma,
+        charge_mah,
+        power_mw: None,
+        temperature_c: None,
+    }
+}
+
+#[test]
+fn watch_stats_track_extremes_and_averages() {
+    let mut stats = BatteryWatchStats::new();
+    stats.record(&reading(Some(7410), Some(-142), Some(2450)));
+    stats.record(&reading(None, Some(-150), None));
+    stats.record(&reading(Some(7390), Some(-128), Some(2448)));
+
+    let summary = stats.summary();
+    assert_eq!(summary.samples, 3);
+    assert_eq!(summary.voltage_min_mv, Some(7390));
+    assert_eq!(summary.voltage_max_mv, Some(7410));
+    assert_eq!(summary.voltage_avg_mv, Some(7400.0));
+    assert_eq!(summary.current_min_ma, Some(-150));
+    assert_eq!(summary.current_max_ma, Some(-128));
+    assert_eq!(summary.current_avg_ma, Some(-140.0));
+    assert_eq!(summary.charge_delta_mah, Some(-2));
+}
+
+#[test]
+fn watch_stats_without_readings_are_empty() {
+    let summary = BatteryWatchStats::new().summary();
+    assert_eq!(summary.samples, 0);
+    assert_eq!(summary.voltage_avg_mv, None);
+    assert_eq!(summary.charge_delta_mah, None);
+}
+
+#[test]
+fn voltage_history_keeps_the_latest_readings() {
+    let mut history = VoltageHistory::new(3);
+    for mv in [7400, 7410, 7420, 7430] {
+        history.push(Some(mv));
+    }
+    history.push(None);
+    assert_eq!(history.samples(), [7410, 7420, 7430]);
+}
+
+#[test]
+fn state_of_charge_needs_a_capacity() {
+    let battery = reading(Some(7410), Some(-142), Some(2340));
+    let soc = |capacity| BatteryWatchSampleJson::new(battery.clone(), capacity).soc_percent;
+    assert_eq!(soc(Some(3000)), Some(78.0));
+    assert_eq!(soc(Some(0)), None);
+    assert_eq!(soc(None), None);
+}
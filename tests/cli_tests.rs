@@ -0,0 +1,489 @@
+/*
+ * E-ink Power CLI - Command Line Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Tests for global option validation and command aliases
+
+use clap::{CommandFactory, FromArgMatches, Parser};
+use eink_power_cli::cli::deprecations::{self, DEPRECATIONS};
+use eink_power_cli::cli::examples::EXAMPLES;
+use eink_power_cli::cli::{BatteryCommands, Cli, Commands, Ltc2959Commands, OutputFormat};
+use eink_power_cli::config::{BatteryConfig, Config, ConnectionConfig, OutputConfig};
+use eink_power_cli::json::{OutputKind, ParsedPayload};
+use std::time::Duration;
+
+fn parse(args: &[&str]) -> Cli {
+    Cli::try_parse_from([&["eink-power-cli"], args].concat()).unwrap()
+}
+
+#[test]
+fn quiet_and_verbose_are_rejected() {
+    let err = parse(&["-q", "-v", "version"]).validate().unwrap_err();
+    assert!(err.contains("--quiet and --verbose"), "{}", err);
+
+    assert!(parse(&["-v", "version"]).validate().is_ok());
+    assert!(parse(&["-q", "version"]).validate().is_ok());
+}
+
+#[test]
+fn questionable_combinations_only_warn() {
+    let mut cli = parse(&["-q", "--format", "human", "version"]);
+    cli.format_given = true;
+    assert!(cli.validate().is_ok());
+    assert!(parse(&["-q", "--format", "json", "version"])
+        .validate()
+        .is_ok());
+    assert!(parse(&["--format", "csv", "history"]).validate().is_ok());
+    assert!(parse(&["--allow-bootloader", "ping"]).validate().is_ok());
+}
+
+#[test]
+fn report_commands_have_no_csv_output() {
+    let has_csv = |args: &[&str]| parse(args).command.unwrap().has_csv_output();
+
+    assert!(has_csv(&["battery", "read"]));
+    assert!(has_csv(&["latency"]));
+    assert!(!has_csv(&["history"]));
+    assert!(!has_csv(&["system", "factory-reset"]));
+    assert!(!has_csv(&["system", "time-ref"]));
+    assert!(!has_csv(&["power", "sequence", "wifi"]));
+}
+
+#[test]
+fn prometheus_is_rejected_for_commands_without_metrics() {
+    let validate = |args: &[&str]| parse(&[&["--format", "prometheus"], args].concat()).validate();
+
+    for args in [&["battery", "read"][..], &["ping"], &["run", "--list"]] {
+        let err = validate(args).unwrap_err();
+        assert!(err.contains("has no Prometheus output"), "{}", err);
+    }
+    assert!(validate(&["latency"]).is_ok());
+    assert!(validate(&["stats"]).is_ok());
+    assert!(validate(&["monitor", "--continuous"]).is_ok());
+    assert!(validate(&["battery", "read", "--watch"]).is_ok());
+    assert!(validate(&["ltc2959", "read", "--continuous"]).is_ok());
+}
+
+#[test]
+fn commands_pick_their_reply_parser() {
+    let payload = |args: &[&str]| parse(args).command.unwrap().payload();
+
+    assert_eq!(payload(&["battery", "read"]), ParsedPayload::Battery);
+    // The battery monitor reads `ltc2959 status`
+    assert_eq!(payload(&["battery", "status"]), ParsedPayload::Ltc2959);
+    assert_eq!(payload(&["pm", "battery-check"]), ParsedPayload::Raw);
+    assert_eq!(payload(&["status"]), ParsedPayload::PowerStats);
+    assert_eq!(payload(&["ping"]), ParsedPayload::Raw);
+    let gpio_a5 = ParsedPayload::Gpio {
+        port: "A".to_string(),
+        pin: 5,
+    };
+    assert_eq!(payload(&["gpio", "get", "gpioa", "5"]), gpio_a5);
+    assert_eq!(payload(&["gpio", "get", "PTA5"]), gpio_a5);
+}
+
+/// The struct printed for each example is the one its envelope reads back as
+#[test]
+fn reply_parsers_match_the_output_read_back() {
+    for example in EXAMPLES {
+        let command = Cli::try_parse_from(example.args())
+            .unwrap()
+            .command
+            .unwrap();
+        // Aliases record the name of the command they stand for
+        if format!("{:?}", command) != format!("{:?}", command.clone().resolve_alias()) {
+            continue;
+        }
+        let kind = match command.payload() {
+            ParsedPayload::Battery => OutputKind::Battery,
+            ParsedPayload::System => OutputKind::SystemInfo,
+            ParsedPayload::Nfc => OutputKind::Nfc,
+            ParsedPayload::Ltc2959 => OutputKind::Ltc2959,
+            ParsedPayload::Gpio { .. } => OutputKind::Gpio,
+            ParsedPayload::Rtc => OutputKind::RtcStatus,
+            ParsedPayload::RtcCounter => OutputKind::RtcCounter,
+            ParsedPayload::PowerStats => OutputKind::PowerStats,
+            ParsedPayload::Measurement => OutputKind::Measurement,
+            ParsedPayload::RailDefaults => OutputKind::RailDefaults,
+            ParsedPayload::StateChange => OutputKind::StateChange,
+            // Built by the handler, or read back from its `sections` shape
+            ParsedPayload::Raw => continue,
+        };
+        assert_eq!(
+            OutputKind::for_command(example.command),
+            kind,
+            "{}",
+            example.invocation
+        );
+    }
+}
+
+#[test]
+fn invalid_arguments_fail_before_connecting() {
+    let state = tempfile::tempdir().unwrap();
+    let output = assert_cmd::Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .env("EINK_POWER_CLI_STATE_DIR", state.path())
+        .args(["--device", "/dev/nonexistent", "-q", "-v", "version"])
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid arguments"), "{}", stderr);
+    assert!(!stderr.contains("Device not found"), "{}", stderr);
+}
+
+#[test]
+fn quiet_with_an_explicit_human_format_warns() {
+    let state = tempfile::tempdir().unwrap();
+    let stderr = |args: &[&str]| {
+        let output = assert_cmd::Command::cargo_bin("eink-power-cli")
+            .unwrap()
+            .env("EINK_POWER_CLI_STATE_DIR", state.path())
+            .env("XDG_CONFIG_HOME", state.path())
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    let warned = stderr(&["-q", "--format", "human", "examples"]);
+    assert!(
+        warned.contains("--quiet suppresses all human-readable output"),
+        "{}",
+        warned
+    );
+    let silent = stderr(&["-q", "examples"]);
+    assert!(!silent.contains("--quiet"), "{}", silent);
+    assert!(!stderr(&["-q", "--format", "json", "examples"]).contains("--quiet"));
+}
+
+#[test]
+fn top_level_aliases_resolve_to_nested_commands() {
+    let resolved = |args: &[&str]| format!("{:?}", parse(args).command.unwrap().resolve_alias());
+
+    assert_eq!(resolved(&["info"]), resolved(&["system", "info"]));
+    assert_eq!(resolved(&["info", "--all"]), resolved(&["snapshot"]));
+    assert_eq!(resolved(&["status"]), resolved(&["pm", "stats"]));
+    assert_eq!(resolved(&["version"]), "Version");
+}
+
+#[test]
+fn deadline_defaults_from_timeout_and_exempts_long_running_commands() {
+    let deadline = |args: &[&str]| parse(args).deadline();
+
+    assert_eq!(deadline(&["version"]), Some(Duration::from_secs(22)));
+    assert_eq!(
+        deadline(&["--timeout", "5", "version"]),
+        Some(Duration::from_secs(30))
+    );
+    assert_eq!(deadline(&["monitor", "--continuous"]), None);
+    assert_eq!(deadline(&["batch", "--file", "cmds.txt"]), None);
+    assert_eq!(deadline(&["battery", "read", "--watch"]), None);
+    assert_eq!(deadline(&["system", "erase", "app"]), None);
+    assert_eq!(deadline(&["snapshot"]), None);
+    assert_eq!(deadline(&["info", "--all"]), None);
+    assert!(deadline(&["battery", "read"]).is_some());
+    assert_eq!(
+        deadline(&["--max-duration", "60", "monitor"]),
+        Some(Duration::from_secs(60))
+    );
+
+    let err = parse(&["--max-duration", "0", "version"])
+        .validate()
+        .unwrap_err();
+    assert!(err.contains("--max-duration"), "{}", err);
+}
+
+#[test]
+fn watch_options_need_watch() {
+    let parses = |args: &[&str]| Cli::try_parse_from([&["eink-power-cli"], args].concat()).is_ok();
+
+    assert!(parses(&["battery", "read", "--watch", "--interval", "5"]));
+    assert!(parses(&[
+        "battery",
+        "read",
+        "--watch",
+        "--capacity",
+        "3000"
+    ]));
+    assert!(!parses(&["battery", "read", "--interval", "5"]));
+    assert!(!parses(&["battery", "read", "--sparkline", "10"]));
+    assert!(!parses(&["battery", "read", "--watch", "--interval", "0"]));
+}
+
+#[test]
+fn gpio_config_takes_one_pin_or_a_pin_list() {
+    use eink_power_cli::cli::{Commands, GpioCommands};
+    use eink_power_cli::power::gpio::GpioPin;
+
+    let pins = |args: &[&str]| match parse(args).command.unwrap() {
+        Commands::Gpio(GpioCommands::Config {
+            pins, pins_mode, ..
+        }) => (pins, pins_mode),
+        other => panic!("unexpected command {:?}", other),
+    };
+
+    let (list, mode) = pins(&["gpio", "config", "--pins", "a0,a1,b3", "--mode", "input"]);
+    assert_eq!(
+        list,
+        [
+            GpioPin {
+                port: "A".into(),
+                pin: 0
+            },
+            GpioPin {
+                port: "A".into(),
+                pin: 1
+            },
+            GpioPin {
+                port: "B".into(),
+                pin: 3
+            },
+        ]
+    );
+    assert_eq!(mode.as_deref(), Some("input"));
+    assert!(pins(&["gpio", "config", "A", "5", "output"]).0.is_empty());
+
+    let parse_err =
+        |args: &[&str]| Cli::try_parse_from([&["eink-power-cli"], args].concat()).is_err();
+    assert!(parse_err(&["gpio", "config", "--pins", "a0"]));
+    assert!(parse_err(&[
+        "gpio", "config", "--pins", "0a", "--mode", "input"
+    ]));
+    assert!(parse_err(&[
+        "gpio", "config", "A", "5", "output", "--pins", "a0", "--mode", "input"
+    ]));
+}
+
+#[test]
+fn gpio_script_parses_its_step_list() {
+    let parses = |args: &[&str]| Cli::try_parse_from([&["eink-power-cli"], args].concat()).is_ok();
+
+    assert!(parses(&["gpio", "script", "a0=1,5ms,a1=1"]));
+    assert!(parses(&["gpio", "script", "--host", "a0=1"]));
+    assert!(!parses(&["gpio", "script", "a0=1,5"]));
+    assert!(!parses(&["gpio", "script", "5ms"]));
+    assert!(!parses(&["gpio", "script"]));
+}
+
+#[test]
+fn monitor_status_file_needs_a_continuous_session() {
+    let parses = |args: &[&str]| Cli::try_parse_from([&["eink-power-cli"], args].concat()).is_ok();
+
+    assert!(parses(&["monitor", "-c", "--status-file", "/run/pmu.json"]));
+    assert!(parses(&[
+        "monitor",
+        "-c",
+        "--status-file",
+        "/run/pmu.json",
+        "--status-file-interval",
+        "5"
+    ]));
+    assert!(!parses(&["monitor", "--status-file", "/run/pmu.json"]));
+    assert!(!parses(&["monitor", "-c", "--status-file-interval", "5"]));
+    assert!(!parses(&[
+        "monitor",
+        "-c",
+        "--status-file",
+        "/run/pmu.json",
+        "--status-file-interval",
+        "0"
+    ]));
+}
+
+#[test]
+fn config_fills_in_options_left_at_their_default() {
+    let config = Config {
+        connection: ConnectionConfig {
+            device: Some("/dev/ttyUSB0".into()),
+            timeout: Some(7),
+            pacing_ms: None,
+            resync: None,
+            verify_echo: None,
+            retry_timeout: None,
+            steal_unit: None,
+        },
+        output: OutputConfig {
+            format: Some(OutputFormat::Json),
+        },
+        battery: BatteryConfig {
+            capacity_mah: Some(3000),
+        },
+        ..Config::default()
+    };
+
+    let with_config = |args: &[&str]| {
+        let matches = Cli::command()
+            .try_get_matches_from([&["eink-power-cli"], args].concat())
+            .unwrap();
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        cli.apply_config(&config, &matches);
+        cli
+    };
+
+    let cli = with_config(&["battery", "read", "--watch"]);
+    assert_eq!(cli.device, "/dev/ttyUSB0");
+    assert_eq!(cli.timeout, 7);
+    assert!(
+        !cli.timeout_given,
+        "a config timeout leaves the per-command table in force"
+    );
+    assert_eq!(cli.format, OutputFormat::Json);
+    assert!(!cli.format_given);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Battery(BatteryCommands::Read {
+            capacity: Some(3000),
+            ..
+        }))
+    ));
+
+    // The command line wins, even when it repeats the built-in default
+    let cli = with_config(&[
+        "--device",
+        "/dev/ttyLP2",
+        "-t",
+        "3",
+        "--format",
+        "human",
+        "battery",
+        "read",
+        "--watch",
+        "--capacity",
+        "2000",
+    ]);
+    assert_eq!(cli.device, "/dev/ttyLP2");
+    assert_eq!(cli.timeout, 3);
+    assert!(cli.timeout_given);
+    assert_eq!(cli.format, OutputFormat::Human);
+    assert!(cli.format_given);
+    assert!(matches!(
+        cli.command,
+        Some(Commands::Battery(BatteryCommands::Read {
+            capacity: Some(2000),
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn setup_rejects_dry_run() {
+    let err = parse(&["--dry-run", "setup", "--yes"])
+        .validate()
+        .unwrap_err();
+    assert!(err.contains("--dry-run"), "{}", err);
+    assert!(!parse(&["setup"]).command.unwrap().has_csv_output());
+}
+
+/// Subcommand reached by `path`, following aliases as clap does
+fn resolve<'a>(mut command: &'a clap::Command, path: &str) -> &'a clap::Command {
+    for word in path.split_whitespace() {
+        command = command
+            .find_subcommand(word)
+            .unwrap_or_else(|| panic!("no subcommand '{}' in '{}'", word, path));
+    }
+    command
+}
+
+#[test]
+fn deprecated_names_parse_as_their_replacements() {
+    let root = Cli::command();
+    for deprecation in DEPRECATIONS {
+        let old = resolve(&root, deprecation.old);
+        let new = resolve(&root, deprecation.new);
+        assert_eq!(old.get_name(), new.get_name(), "{}", deprecation.old);
+
+        // Every renamed command so far takes a rail state
+        let parsed = |path: &str| {
+            let args: Vec<&str> = path.split_whitespace().chain(["on"]).collect();
+            let command = parse(&args).command.unwrap();
+            (command.name(), format!("{:?}", command))
+        };
+        let (name, old_command) = parsed(deprecation.old);
+        assert_eq!(name, deprecation.new);
+        assert_eq!(old_command, parsed(deprecation.new).1);
+    }
+}
+
+#[test]
+fn only_the_old_spelling_of_the_parsed_command_is_deprecated() {
+    let find = |args: &[&str]| {
+        let words: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        deprecations::find(&words, &parse(args).command.unwrap()).map(|d| d.new)
+    };
+
+    assert_eq!(
+        find(&["--device", "/dev/ttyLP2", "pm", "disp", "off"]),
+        Some("pm display")
+    );
+    assert_eq!(
+        find(&["pm", "defaults", "disp", "on"]),
+        Some("pm defaults display")
+    );
+    assert_eq!(find(&["pm", "display", "off"]), None);
+    assert_eq!(find(&["schedule", "at", "+1m", "pm", "disp", "off"]), None);
+}
+
+#[test]
+fn ltc2959_read_options_need_continuous() {
+    let parses = |args: &[&str]| Cli::try_parse_from([&["eink-power-cli"], args].concat()).is_ok();
+
+    assert!(parses(&["ltc2959", "read"]));
+    assert!(parses(&[
+        "ltc2959",
+        "read",
+        "--continuous",
+        "--interval-ms",
+        "200",
+        "--duration",
+        "30s"
+    ]));
+    assert!(!parses(&["ltc2959", "read", "--interval-ms", "200"]));
+    assert!(!parses(&["ltc2959", "read", "--duration", "30s"]));
+    assert!(!parses(&[
+        "ltc2959",
+        "read",
+        "--continuous",
+        "--duration",
+        "0s"
+    ]));
+    assert!(!parses(&[
+        "ltc2959",
+        "read",
+        "--continuous",
+        "--interval-ms",
+        "0"
+    ]));
+
+    match parse(&["ltc2959", "read", "--continuous", "--duration", "1m30s"]).command {
+        Some(Commands::Ltc2959(Ltc2959Commands::Read {
+            continuous: true,
+            interval_ms: 200,
+            duration,
+        })) => assert_eq!(duration, Some(Duration::from_secs(90))),
+        other => panic!("unexpected command {:?}", other),
+    }
+}
+
+#[test]
+fn expect_version_takes_a_semver_requirement() {
+    let parse = |requirement: &str| {
+        Cli::try_parse_from([
+            "eink-power-cli",
+            "system",
+            "info",
+            "--expect-version",
+            requirement,
+        ])
+    };
+
+    assert!(parse(">=2.2, <3").is_ok());
+    assert!(parse("~2.4.0-rc.1").is_ok());
+    let error = parse("at least 2.2").unwrap_err().to_string();
+    assert!(error.contains("invalid version requirement"), "{}", error);
+}
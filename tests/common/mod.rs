@@ -0,0 +1,25 @@
+/*
+ * E-ink Power CLI - Shared Test Helpers
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Helpers shared by the integration tests, included with `mod common;`
+
+// Each test crate uses only some of these
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+
+/// Contents of `tests/fixtures/<name>`
+pub fn fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e))
+}
+
+/// Lines of `tests/fixtures/<name>`
+pub fn fixture_lines(name: &str) -> Vec<String> {
+    fixture(name).lines().map(String::from).collect()
+}
@@ -0,0 +1,214 @@
+/*
+ * E-ink Power CLI - Console Quirk Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Consoles left with local echo or CRLF translation on
+//!
+//! The `*.duplicated_echo.txt` fixtures are transcripts from a shell with
+//! local echo on, where every line arrives twice; the `*.doubled_crlf.txt`
+//! ones are from a shell with CRLF translation on, where a blank line
+//! follows every line.
+
+use eink_power_cli::json::ResponseParser;
+use eink_power_cli::serial::console::{self, ConsoleQuirk};
+use eink_power_cli::serial::{Connection, MockSerial};
+
+mod common;
+use common::fixture;
+
+/// Reply to `command` through a connection normalizing for `quirks`
+async fn reply(command: &str, transcript: &str, quirks: Vec<ConsoleQuirk>) -> String {
+    let serial = MockSerial::builder().expect(command, transcript).build();
+    let mut connection = Connection::mock(serial);
+    connection.set_console_quirks(quirks);
+    connection.send_command(command).await.unwrap()
+}
+
+#[test]
+fn a_sane_console_has_no_quirks() {
+    assert!(console::detect("ping\r\npong\r\nprod:~$ ", "ping", "pong").is_empty());
+    // Without echo, as after `shell echo off`
+    assert!(console::detect("pong\r\nprod:~$ ", "ping", "pong").is_empty());
+}
+
+#[test]
+fn duplicated_lines_are_detected() {
+    assert_eq!(
+        console::detect("ping\r\nping\r\npong\r\npong\r\nprod:~$ ", "ping", "pong"),
+        [ConsoleQuirk::DuplicatedLines]
+    );
+}
+
+#[test]
+fn doubled_line_endings_are_detected() {
+    assert_eq!(
+        console::detect("ping\r\n\r\npong\r\n\r\nprod:~$ ", "ping", "pong"),
+        [ConsoleQuirk::DoubledLineEndings]
+    );
+}
+
+#[test]
+fn both_quirks_are_detected_together() {
+    assert_eq!(
+        console::detect(
+            "ping\r\n\r\nping\r\n\r\npong\r\n\r\npong\r\n\r\nprod:~$ ",
+            "ping",
+            "pong"
+        ),
+        [
+            ConsoleQuirk::DoubledLineEndings,
+            ConsoleQuirk::DuplicatedLines
+        ]
+    );
+}
+
+#[test]
+fn output_before_the_probe_reply_is_ignored() {
+    let raw = "[00:00:01.000,000] <inf> pmu: boot\r\n\r\nping\r\npong\r\nprod:~$ ";
+    assert!(console::detect(raw, "ping", "pong").is_empty());
+}
+
+#[test]
+fn a_reply_without_the_probe_shows_no_quirks() {
+    assert!(console::detect("uart:~$ uart:~$ ", "ping", "pong").is_empty());
+}
+
+#[test]
+fn normalizing_without_quirks_keeps_the_reply() {
+    let raw = "version\r\n\r\nPMU v2.1.0\r\nprod:~$ ";
+    assert_eq!(console::normalize(raw, &[]), raw);
+}
+
+#[test]
+fn normalizing_keeps_blank_lines_of_the_reply() {
+    // One blank line of the reply, doubled, among doubled line endings
+    let raw = "a\r\n\r\n\r\n\r\nb\r\n\r\nprod:~$ ";
+    assert_eq!(
+        console::normalize(raw, &[ConsoleQuirk::DoubledLineEndings]),
+        "a\r\n\r\nb\r\nprod:~$ "
+    );
+}
+
+#[test]
+fn normalizing_keeps_a_line_printed_once() {
+    // A log line printed between the twins of a duplicated line
+    let raw = "pong\r\n<inf> log\r\npong\r\npong\r\nprod:~$ ";
+    assert_eq!(
+        console::normalize(raw, &[ConsoleQuirk::DuplicatedLines]),
+        "pong\r\n<inf> log\r\npong\r\nprod:~$ "
+    );
+}
+
+#[test]
+fn duplicated_transcripts_normalize_to_the_sane_console() {
+    let raw = fixture("ltc2959_read.duplicated_echo.txt");
+    let sane = console::normalize(&raw, &[ConsoleQuirk::DuplicatedLines]);
+    assert!(sane.starts_with("ltc2959 read\r\n📊 LTC2959 Measurements:\r\n"));
+    assert_eq!(sane.matches("Voltage").count(), 1);
+    assert!(sane.ends_with("Power: -481 mW\r\nprod:~$ "));
+}
+
+#[test]
+fn doubled_transcripts_normalize_to_the_sane_console() {
+    let raw = fixture("pm_stats.doubled_crlf.txt");
+    let sane = console::normalize(&raw, &[ConsoleQuirk::DoubledLineEndings]);
+    assert!(!sane.contains("\r\n\r\n"), "{:?}", sane);
+    assert!(sane.starts_with("pm stats\r\n📊 Power Management Statistics:\r\n"));
+}
+
+#[tokio::test]
+async fn battery_values_parse_from_a_duplicated_echo_transcript() {
+    let response = reply(
+        "ltc2959 read",
+        &fixture("ltc2959_read.duplicated_echo.txt"),
+        vec![ConsoleQuirk::DuplicatedLines],
+    )
+    .await;
+    let battery = ResponseParser::parse_battery_response(&response);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.current_ma, Some(-125));
+    assert_eq!(battery.charge_mah, Some(2450));
+    assert_eq!(battery.power_mw, Some(-481));
+    assert!(!response.contains("ltc2959 read"), "{:?}", response);
+}
+
+#[tokio::test]
+async fn battery_values_parse_from_a_doubled_crlf_transcript() {
+    let response = reply(
+        "ltc2959 read",
+        &fixture("ltc2959_read.doubled_crlf.txt"),
+        vec![ConsoleQuirk::DoubledLineEndings],
+    )
+    .await;
+    let battery = ResponseParser::parse_battery_response(&response);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.current_ma, Some(-125));
+    assert_eq!(battery.charge_mah, Some(2450));
+    assert_eq!(battery.power_mw, Some(-481));
+}
+
+#[tokio::test]
+async fn stats_parse_from_a_duplicated_echo_transcript() {
+    let response = reply(
+        "pm stats",
+        &fixture("pm_stats.duplicated_echo.txt"),
+        vec![ConsoleQuirk::DuplicatedLines],
+    )
+    .await;
+    let stats = ResponseParser::parse_pm_stats(&response);
+    assert_eq!(stats.sleep_cycles, Some(4));
+    assert_eq!(stats.ltc2959_state.as_deref(), Some("Smart Sleep"));
+    assert_eq!(stats.nfc_state.as_deref(), Some("Sleep"));
+    assert_eq!(response.matches("PMIC").count(), 1, "{:?}", response);
+}
+
+#[tokio::test]
+async fn stats_parse_from_a_doubled_crlf_transcript() {
+    let response = reply(
+        "pm stats",
+        &fixture("pm_stats.doubled_crlf.txt"),
+        vec![ConsoleQuirk::DoubledLineEndings],
+    )
+    .await;
+    let stats = ResponseParser::parse_pm_stats(&response);
+    assert_eq!(stats.sleep_cycles, Some(4));
+    assert_eq!(stats.ltc2959_state.as_deref(), Some("Smart Sleep"));
+    assert_eq!(stats.nfc_state.as_deref(), Some("Sleep"));
+}
+
+#[test]
+fn quirks_name_the_shell_command_that_fixes_them() {
+    assert_eq!(ConsoleQuirk::DuplicatedLines.fix(), "shell local_echo off");
+    assert_eq!(ConsoleQuirk::DoubledLineEndings.fix(), "shell crlf off");
+    assert_eq!(
+        console::describe(&[
+            ConsoleQuirk::DuplicatedLines,
+            ConsoleQuirk::DoubledLineEndings
+        ]),
+        "every line duplicated (local echo), doubled line endings (CRLF translation)"
+    );
+}
+
+#[test]
+fn a_doubled_local_echo_normalizes_to_the_typed_line() {
+    let quirks = [ConsoleQuirk::DuplicatedLines];
+    assert_eq!(console::echo_len(2, &quirks), 4);
+    assert_eq!(console::echo_len(2, &[]), 2);
+    // Echoed in one piece, or byte by byte
+    assert_eq!(console::normalize_echo(b"pmpm", b"pm", &quirks), b"pm");
+    assert_eq!(console::normalize_echo(b"ppmm", b"pm", &quirks), b"pm");
+    assert_eq!(
+        console::normalize_echo(b"pm wpm wifi offifi off", b"pm wifi off", &quirks),
+        b"pm wifi off"
+    );
+    // A corrupted echo stays as received, to be reported
+    assert_eq!(console::normalize_echo(b"p~p~", b"pm", &quirks), b"p~p~");
+    // Without local echo the echo is compared as received
+    assert_eq!(console::normalize_echo(b"pmpm", b"pm", &[]), b"pmpm");
+    assert_eq!(
+        console::normalize_echo(b"pm", b"pm", &[ConsoleQuirk::DoubledLineEndings]),
+        b"pm"
+    );
+}
@@ -0,0 +1,133 @@
+/*
+ * E-ink Power CLI - Error Context Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Tests for error context chains
+
+use clap::Parser;
+use eink_power_cli::cli::Cli;
+use eink_power_cli::context;
+use eink_power_cli::error::{ContextualError, PowerCliError, PARTIAL_REPLY_LIMIT};
+use eink_power_cli::json::TimeoutJson;
+use eink_power_cli::serial::{Connection, MockSerial};
+use std::error::Error;
+use std::time::Duration;
+
+#[test]
+fn context_chain_prints_outermost_first_above_root() {
+    let err = PowerCliError::timeout(Duration::from_secs(3), b"")
+        .with_context("while sending 'version' to /dev/ttyLP2")
+        .context("while executing command version");
+
+    assert!(err.to_string().starts_with(
+        "while executing command version\nwhile sending 'version' to /dev/ttyLP2\nCommand timeout after 3s"
+    ));
+    assert!(matches!(
+        err.error,
+        PowerCliError::Timeout { timeout: 3, .. }
+    ));
+    assert!(err
+        .source()
+        .unwrap()
+        .to_string()
+        .starts_with("Command timeout after 3s"));
+    assert_eq!(err.exit_code(), 1);
+}
+
+#[test]
+fn context_macro_wraps_both_error_types() {
+    let root: Result<(), PowerCliError> = Err(PowerCliError::NotConnected);
+    let inner = context!(root, "while reading {}", "battery");
+    let outer: Result<(), ContextualError> = context!(inner, "in step {}", 2);
+
+    let err = outer.unwrap_err();
+    assert_eq!(err.context, ["while reading battery", "in step 2"]);
+
+    let ok: Result<u8, PowerCliError> = Ok(7);
+    assert_eq!(context!(ok, "unused").unwrap(), 7);
+}
+
+#[tokio::test]
+async fn failed_send_records_its_context() {
+    let serial = MockSerial::builder()
+        .expect("version", "version\r\n2.5.0\r\nprod:~$ ")
+        .expect_error("ltc2959 read", PowerCliError::NotConnected)
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    connection.send_command("version").await.unwrap();
+    assert_eq!(connection.error_context(), None);
+
+    // The variant is untouched; the context is kept beside it
+    let err = connection.send_command("ltc2959 read").await.unwrap_err();
+    assert!(matches!(err, PowerCliError::Io(_)));
+    assert_eq!(
+        connection.error_context(),
+        Some("while sending 'ltc2959 read' to mock")
+    );
+}
+
+#[test]
+fn command_names_follow_the_subcommand_path() {
+    let name = |args: &[&str]| {
+        let cli = Cli::try_parse_from([&["eink-power-cli"], args].concat()).unwrap();
+        cli.command.unwrap().name()
+    };
+
+    assert_eq!(name(&["version"]), "version");
+    assert_eq!(name(&["battery", "read"]), "battery read");
+    assert_eq!(
+        name(&["system", "factory-reset", "--yes"]),
+        "system factory-reset"
+    );
+    assert_eq!(name(&["system", "set-baud", "921600"]), "system set-baud");
+}
+
+#[test]
+fn timeout_says_whether_anything_arrived() {
+    let silent = PowerCliError::timeout(Duration::from_secs(3), b"");
+    assert_eq!(
+        silent.to_string(),
+        "Command timeout after 3s: no data received.\n\
+         Check the wiring, --device and --baud; the PMU may be asleep"
+    );
+    assert_eq!(
+        TimeoutJson::of(&silent),
+        Some(TimeoutJson {
+            timeout_s: 3,
+            bytes_received: 0,
+            partial: None,
+        })
+    );
+
+    let slow = PowerCliError::timeout(Duration::from_millis(2500), b"ltc2959 read\r\n");
+    assert_eq!(
+        slow.to_string(),
+        "Command timeout after 3s: partial response of 14 bytes.\n\
+         The command may be slow; consider increasing --timeout"
+    );
+    let json = serde_json::to_value(TimeoutJson::of(&slow).unwrap()).unwrap();
+    assert_eq!(json["bytes_received"], 14);
+    assert_eq!(json["partial"], "ltc2959 read\r\n");
+
+    assert_eq!(TimeoutJson::of(&PowerCliError::NotConnected), None);
+}
+
+#[test]
+fn partial_reply_in_a_timeout_is_truncated() {
+    let received = "📡 ".repeat(200);
+    let error = PowerCliError::timeout(Duration::from_secs(1), received.as_bytes());
+    let PowerCliError::Timeout {
+        bytes_received,
+        partial: Some(partial),
+        ..
+    } = error
+    else {
+        panic!("unexpected error {:?}", error);
+    };
+    assert_eq!(bytes_received, received.len());
+    assert!(partial.len() <= PARTIAL_REPLY_LIMIT);
+    assert!(received.starts_with(&partial));
+}
@@ -0,0 +1,86 @@
+/*
+ * E-ink Power CLI - Usage Example Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Every registered example must parse as the command it is filed under
+
+use clap::{CommandFactory, Parser};
+use eink_power_cli::cli::examples::{self, EXAMPLES};
+use eink_power_cli::cli::Cli;
+
+#[test]
+fn every_example_parses_as_its_command() {
+    for example in EXAMPLES {
+        let cli = Cli::try_parse_from(example.args())
+            .unwrap_or_else(|e| panic!("'{}' does not parse:\n{}", example.invocation, e));
+        let command = cli
+            .command
+            .as_ref()
+            .unwrap_or_else(|| panic!("'{}' has no command", example.invocation));
+        assert_eq!(command.name(), example.command, "{}", example.invocation);
+        cli.validate()
+            .unwrap_or_else(|e| panic!("'{}' is rejected: {}", example.invocation, e));
+    }
+}
+
+/// Paths of every subcommand without children, e.g. `pm defaults show`
+fn leaf_commands(command: &clap::Command, path: &str, leaves: &mut Vec<String>) {
+    for sub in command.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        let sub_path = format!("{} {}", path, sub.get_name()).trim().to_string();
+        if sub.has_subcommands() {
+            leaf_commands(sub, &sub_path, leaves);
+        } else {
+            leaves.push(sub_path);
+        }
+    }
+}
+
+#[test]
+fn every_command_has_an_example() {
+    let mut leaves = Vec::new();
+    leaf_commands(&Cli::command(), "", &mut leaves);
+    let missing: Vec<&String> = leaves
+        .iter()
+        .filter(|leaf| examples::for_command(leaf).next().is_none())
+        .collect();
+    assert!(missing.is_empty(), "no examples for {:?}", missing);
+}
+
+#[test]
+fn examples_appear_in_long_help_only() {
+    let mut command = examples::with_examples(Cli::command());
+    let sleep = command
+        .find_subcommand_mut("pm")
+        .and_then(|pm| pm.find_subcommand_mut("sleep"))
+        .unwrap();
+    let long = sleep.render_long_help().to_string();
+    assert!(long.contains("eink-power-cli pm sleep --time 1h --vlls1 --alloff"));
+    assert!(!long.contains("identity write"));
+    assert!(!sleep.render_help().to_string().contains("Examples:"));
+}
+
+#[test]
+fn keyword_filter_is_case_insensitive() {
+    let sleep = examples::matching(Some("SLEEP"));
+    assert!(sleep.iter().any(|e| e.command == "pm sleep"));
+    assert!(sleep.iter().all(|e| e.matches("sleep")));
+    assert_eq!(examples::matching(None).len(), EXAMPLES.len());
+    assert!(examples::matching(Some("no-such-keyword")).is_empty());
+}
+
+#[test]
+fn examples_command_needs_no_device() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = assert_cmd::Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .env("EINK_POWER_CLI_STATE_DIR", dir.path())
+        .args(["--device", "/dev/nonexistent", "--format", "json"])
+        .args(["examples", "vlls1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"][0]["command"], "pm sleep");
+}
@@ -0,0 +1,535 @@
+/*
+ * E-ink Power CLI - Firmware Upload Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Event-stream tests for `firmware upload` and slot reporting for
+//! `firmware info`, using a fake `mcumgr`
+#![cfg(unix)]
+
+use chrono::{TimeZone, Utc};
+use eink_power_cli::firmware::dfu::{DfuExit, DfuModeReport};
+use eink_power_cli::firmware::slots::{parse_image_list, FirmwareInfo};
+use eink_power_cli::firmware::{
+    bootloader_probe, FirmwareEvent, FirmwareManager, FirmwareStep, FirmwareSummary,
+    FirmwareTransports, McumgrTransport, StepStatus,
+};
+use eink_power_cli::serial::Connection;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Writer that keeps everything written to it for inspection
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Console on the nonexistent test device, mcumgr on `/dev/fake`
+fn mcumgr_on_fake_port() -> FirmwareTransports {
+    FirmwareTransports::resolve("/dev/nonexistent", 115200, Some("/dev/fake"), None)
+}
+
+/// Install a fake mcumgr whose `image upload` sleeps and exits with `upload_status`
+fn fake_mcumgr(dir: &Path, upload_status: i32) -> PathBuf {
+    let path = dir.join("mcumgr");
+    let script = format!(
+        "#!/bin/sh\ncase \"$*\" in\n  *\"image upload\"*) sleep 1.2; echo 'upload failed: NMP timeout' >&2; exit {} ;;\nesac\nexit 0\n",
+        upload_status
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+/// Run an upload against the fake mcumgr and return the parsed output lines
+async fn run_upload(upload_status: i32) -> (Result<String, String>, Vec<serde_json::Value>) {
+    let dir = tempfile::tempdir().unwrap();
+    let mcumgr = fake_mcumgr(dir.path(), upload_status);
+    let image = dir.path().join("app.signed.bin");
+    std::fs::write(&image, [0u8; 64]).unwrap();
+
+    let mut connection = Connection::new("/dev/nonexistent", 115200, true).unwrap();
+    connection.set_dry_run(true);
+    let mut manager = FirmwareManager::new(connection, mcumgr_on_fake_port());
+    let captured = Captured::default();
+    manager.set_json_events(Box::new(captured.clone()));
+    manager.set_mcumgr_program(mcumgr.to_str().unwrap());
+    manager.set_boot_wait(Duration::ZERO);
+
+    let result = manager
+        .upload_firmware(&image, true)
+        .await
+        .map_err(|e| e.to_string());
+
+    let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+    let lines = output
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every line is a JSON document"))
+        .collect();
+    (result, lines)
+}
+
+/// `(step, status)` of every event line, ignoring the final summary
+fn transitions(lines: &[serde_json::Value]) -> Vec<(FirmwareStep, StepStatus)> {
+    lines[..lines.len() - 1]
+        .iter()
+        .map(|line| {
+            let event: FirmwareEvent = serde_json::from_value(line.clone()).unwrap();
+            (event.step, event.status)
+        })
+        .filter(|(_, status)| *status != StepStatus::Progress)
+        .collect()
+}
+
+#[tokio::test]
+async fn test_upload_event_sequence_on_success() {
+    let (result, lines) = run_upload(0).await;
+    assert!(result.is_ok(), "{:?}", result);
+
+    assert_eq!(
+        transitions(&lines),
+        vec![
+            (FirmwareStep::Reset, StepStatus::Skipped),
+            (FirmwareStep::Upload, StepStatus::Started),
+            (FirmwareStep::Upload, StepStatus::Completed),
+            (FirmwareStep::FinalReset, StepStatus::Started),
+            (FirmwareStep::FinalReset, StepStatus::Completed),
+            (FirmwareStep::Verify, StepStatus::Started),
+            (FirmwareStep::Verify, StepStatus::Completed),
+        ]
+    );
+
+    // The 1.2 s upload produces progress ticks between start and completion
+    let ticks = lines
+        .iter()
+        .filter(|l| l["step"] == "upload" && l["status"] == "progress")
+        .count();
+    assert!(ticks >= 1);
+    let completed = lines
+        .iter()
+        .find(|l| l["step"] == "upload" && l["status"] == "completed")
+        .unwrap();
+    assert!(completed["duration_ms"].as_u64().unwrap() >= 1000);
+
+    let summary: FirmwareSummary = serde_json::from_value(lines.last().unwrap().clone()).unwrap();
+    assert!(summary.success);
+    assert!(summary.verified);
+    assert_eq!(summary.steps.len(), 4);
+    assert!(summary.error.is_none());
+}
+
+#[tokio::test]
+async fn test_upload_event_sequence_on_upload_failure() {
+    let (result, lines) = run_upload(1).await;
+    let error = result.unwrap_err();
+    assert!(error.contains("NMP timeout"), "{}", error);
+
+    assert_eq!(
+        transitions(&lines),
+        vec![
+            (FirmwareStep::Reset, StepStatus::Skipped),
+            (FirmwareStep::Upload, StepStatus::Started),
+            (FirmwareStep::Upload, StepStatus::Failed),
+        ]
+    );
+    let failed = &lines[lines.len() - 2];
+    assert!(failed["error"].as_str().unwrap().contains("NMP timeout"));
+
+    let summary: FirmwareSummary = serde_json::from_value(lines.last().unwrap().clone()).unwrap();
+    assert!(!summary.success);
+    assert!(!summary.verified);
+    assert_eq!(summary.steps.last().unwrap().status, StepStatus::Failed);
+    assert_eq!(summary.error.as_deref(), Some(error.as_str()));
+}
+
+#[test]
+fn test_step_names_are_stable() {
+    let names: Vec<String> = [
+        FirmwareStep::Reset,
+        FirmwareStep::Upload,
+        FirmwareStep::FinalReset,
+        FirmwareStep::Verify,
+    ]
+    .iter()
+    .map(|s| serde_json::to_string(s).unwrap())
+    .collect();
+    assert_eq!(
+        names,
+        vec!["\"reset\"", "\"upload\"", "\"final_reset\"", "\"verify\""]
+    );
+}
+
+#[test]
+fn bootloader_probe_reports_whether_smp_echo_answers() {
+    let dir = tempfile::tempdir().unwrap();
+    let transport = McumgrTransport::Serial {
+        port: "/dev/fake".to_string(),
+        baud: 115200,
+    };
+
+    let answering = fake_mcumgr(dir.path(), 0);
+    assert!(bootloader_probe(
+        answering.to_str().unwrap(),
+        transport.clone()
+    )());
+
+    let silent = dir.path().join("silent-mcumgr");
+    std::fs::write(&silent, "#!/bin/sh\necho 'NMP timeout' >&2\nexit 1\n").unwrap();
+    std::fs::set_permissions(&silent, std::fs::Permissions::from_mode(0o755)).unwrap();
+    assert!(!bootloader_probe(
+        silent.to_str().unwrap(),
+        transport.clone()
+    )());
+
+    let missing = dir.path().join("no-such-mcumgr");
+    assert!(!bootloader_probe(missing.to_str().unwrap(), transport)());
+}
+
+/// MCUboot image: header, `body_len` bytes of body, then a TLV area holding
+/// a SHA-256 hash and, if `signed`, an ECDSA-P256 signature
+fn mcuboot_image(flags: u32, body_len: u32, signed: bool) -> Vec<u8> {
+    use eink_power_cli::firmware::verify::{IMAGE_HEADER_SIZE, IMAGE_MAGIC};
+
+    let mut image = Vec::new();
+    image.extend_from_slice(&IMAGE_MAGIC.to_le_bytes());
+    image.extend_from_slice(&0x0000_8000u32.to_le_bytes()); // load address
+    image.extend_from_slice(&(IMAGE_HEADER_SIZE as u16).to_le_bytes());
+    image.extend_from_slice(&0u16.to_le_bytes()); // protected TLV size
+    image.extend_from_slice(&body_len.to_le_bytes());
+    image.extend_from_slice(&flags.to_le_bytes());
+    image.extend_from_slice(&[2, 5]); // major, minor
+    image.extend_from_slice(&1u16.to_le_bytes()); // revision
+    image.extend_from_slice(&42u32.to_le_bytes()); // build number
+    image.extend_from_slice(&0u32.to_le_bytes()); // padding
+    image.resize(image.len() + body_len as usize, 0xaa);
+
+    let mut entries = Vec::new();
+    entries.extend_from_slice(&0x10u16.to_le_bytes()); // SHA-256
+    entries.extend_from_slice(&32u16.to_le_bytes());
+    entries.extend_from_slice(&[0u8; 32]);
+    if signed {
+        entries.extend_from_slice(&0x22u16.to_le_bytes()); // ECDSA-P256
+        entries.extend_from_slice(&72u16.to_le_bytes());
+        entries.extend_from_slice(&[0u8; 72]);
+    }
+    image.extend_from_slice(&0x6907u16.to_le_bytes());
+    image.extend_from_slice(&(entries.len() as u16 + 4).to_le_bytes());
+    image.extend_from_slice(&entries);
+    image
+}
+
+#[test]
+fn analyze_reads_header_and_detects_signature() {
+    use eink_power_cli::firmware::verify::analyze_firmware_bytes;
+
+    let info = analyze_firmware_bytes(&mcuboot_image(0, 256, true)).unwrap();
+    assert!(info.magic_valid);
+    assert_eq!(info.version.to_string(), "2.5.1+42");
+    assert_eq!(info.image_size, 256);
+    assert_eq!(info.header_size, 32);
+    assert_eq!(info.load_addr, 0x8000);
+    assert!(!info.non_bootable);
+    assert!(info.has_signature);
+}
+
+#[test]
+fn analyze_unsigned_non_bootable_image() {
+    use eink_power_cli::firmware::verify::{analyze_firmware_bytes, IMAGE_F_NON_BOOTABLE};
+
+    let info = analyze_firmware_bytes(&mcuboot_image(IMAGE_F_NON_BOOTABLE, 64, false)).unwrap();
+    assert!(info.magic_valid);
+    assert!(info.non_bootable);
+    assert!(!info.has_signature);
+}
+
+#[test]
+fn analyze_rejects_non_mcuboot_files() {
+    use eink_power_cli::firmware::verify::analyze_firmware_bytes;
+
+    let info = analyze_firmware_bytes(&[0u8; 64]).unwrap();
+    assert!(!info.magic_valid);
+    assert!(!info.has_signature);
+    assert!(analyze_firmware_bytes(&[0u8; 16]).is_err());
+}
+
+#[test]
+fn analyze_command_needs_no_device() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("app.signed.bin");
+    std::fs::write(&file, mcuboot_image(0, 128, true)).unwrap();
+
+    let output = assert_cmd::Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .env("EINK_POWER_CLI_STATE_DIR", dir.path())
+        .args(["--device", "/dev/nonexistent", "--format", "json"])
+        .args(["firmware", "analyze"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["has_signature"], true);
+    assert_eq!(json["data"]["version"]["build_num"], 42);
+}
+
+/// Factory state: one confirmed image, secondary slot never written
+const SLOTS_EMPTY_SECONDARY: &str = "Images:
+ image=0 slot=0
+    version: 2.2.0.298
+    bootable: true
+    flags: active confirmed
+    hash: 3b4c5d6e7f
+Split status: N/A (0)
+";
+
+/// New image uploaded and marked for a test boot, not yet reset
+const SLOTS_TEST_PENDING: &str = "Images:
+ image=0 slot=0
+    version: 2.2.0.298
+    bootable: true
+    flags: active confirmed
+    hash: 3b4c5d6e7f
+ image=0 slot=1
+    version: 2.3.0.12
+    bootable: true
+    flags: pending
+    hash: 9a8b7c6d5e
+Split status: N/A (0)
+";
+
+/// After the test boot: new image running and confirmed, old one kept
+const SLOTS_CONFIRMED: &str = "Images:
+ image=0 slot=0
+    version: 2.3.0.12
+    bootable: true
+    flags: active confirmed
+    hash: 9a8b7c6d5e
+ image=0 slot=1
+    version: 2.2.0.298
+    bootable: true
+    flags:
+    hash: 3b4c5d6e7f
+Split status: N/A (0)
+";
+
+#[test]
+fn image_list_fills_in_the_empty_slot() {
+    let slots = parse_image_list(SLOTS_EMPTY_SECONDARY);
+    assert_eq!(slots.len(), 1);
+    assert_eq!(slots[0].version.as_deref(), Some("2.2.0.298"));
+    assert_eq!(slots[0].flags(), "active confirmed");
+
+    let info = FirmwareInfo::new(Some(Some("2.2.0".to_string())), Some(slots));
+    let slots = info.slots.as_ref().unwrap();
+    assert_eq!(slots.len(), 2);
+    assert!(slots[1].empty);
+    assert_eq!(slots[1].version, None);
+    assert_eq!(info.active_slot, Some(0));
+    assert_eq!(info.confirmed, Some(true));
+    assert_eq!(info.pending_slot, None);
+    assert!(info.format_human().contains("(empty)"));
+}
+
+#[test]
+fn image_list_reports_a_pending_test_image() {
+    let info = FirmwareInfo::new(None, Some(parse_image_list(SLOTS_TEST_PENDING)));
+    assert_eq!(info.active_slot, Some(0));
+    assert_eq!(info.pending_slot, Some(1));
+    assert_eq!(info.pending_test, Some(true));
+    let slots = info.slots.as_ref().unwrap();
+    assert_eq!(slots[1].hash.as_deref(), Some("9a8b7c6d5e"));
+    assert_eq!(slots[1].bootable, Some(true));
+    assert!(info.format_human().contains("Pending slot:    1 (test)"));
+}
+
+#[test]
+fn image_list_after_confirming_has_nothing_pending() {
+    let info = FirmwareInfo::new(
+        Some(Some("2.3.0".to_string())),
+        Some(parse_image_list(SLOTS_CONFIRMED)),
+    );
+    let slots = info.slots.as_ref().unwrap();
+    assert_eq!(slots[0].version.as_deref(), Some("2.3.0.12"));
+    assert_eq!(slots[1].flags(), "");
+    assert!(!slots[1].empty);
+    assert_eq!(info.confirmed, Some(true));
+    assert_eq!(info.pending_slot, None);
+    assert!(info.format_human().contains("Pending slot:    none"));
+}
+
+/// Fake mcumgr whose `image list` prints `listing`, or fails if `None`
+fn fake_mcumgr_listing(dir: &Path, listing: Option<&str>) -> PathBuf {
+    let path = dir.join("mcumgr");
+    let script = match listing {
+        Some(listing) => {
+            std::fs::write(dir.join("listing"), listing).unwrap();
+            format!("#!/bin/sh\ncat '{}'\n", dir.join("listing").display())
+        }
+        None => "#!/bin/sh\necho 'NMP timeout' >&2\nexit 1\n".to_string(),
+    };
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn info_manager(mcumgr: &Path) -> FirmwareManager {
+    // Not dry run, so the console connection really fails
+    let connection = Connection::new("/dev/nonexistent", 115200, true).unwrap();
+    let mut manager = FirmwareManager::new(connection, mcumgr_on_fake_port());
+    manager.set_mcumgr_program(mcumgr.to_str().unwrap());
+    manager
+}
+
+#[tokio::test]
+async fn info_without_console_reports_null_version() {
+    let dir = tempfile::tempdir().unwrap();
+    let mcumgr = fake_mcumgr_listing(dir.path(), Some(SLOTS_TEST_PENDING));
+    let info = info_manager(&mcumgr).get_info().await.unwrap();
+
+    assert!(!info.console_reachable);
+    assert!(info.bootloader_reachable);
+    assert_eq!(info.pending_slot, Some(1));
+    let json = serde_json::to_value(&info).unwrap();
+    assert_eq!(json["running_version"], serde_json::Value::Null);
+    assert_eq!(json["slots"][1]["version"], "2.3.0.12");
+}
+
+#[tokio::test]
+async fn info_fails_when_nothing_answers() {
+    let dir = tempfile::tempdir().unwrap();
+    let mcumgr = fake_mcumgr_listing(dir.path(), None);
+    assert!(info_manager(&mcumgr).get_info().await.is_err());
+}
+
+#[test]
+fn info_with_console_only_has_null_slots() {
+    let info = FirmwareInfo::new(Some(Some("2.2.0".to_string())), None);
+    let json = serde_json::to_value(&info).unwrap();
+    assert_eq!(json["running_version"], "2.2.0");
+    assert_eq!(json["bootloader_reachable"], false);
+    assert_eq!(json["active_slot"], serde_json::Value::Null);
+    assert_eq!(json["slots"], serde_json::Value::Null);
+    assert!(info.format_human().contains("Pending slot:    unknown"));
+}
+
+#[test]
+fn dfu_window_deadline_counts_from_the_request() {
+    let requested = Utc.with_ymd_and_hms(2025, 10, 9, 14, 0, 0).unwrap();
+    let checked = requested + chrono::TimeDelta::milliseconds(2400);
+
+    let report = DfuModeReport::new(20, requested, checked, true, String::new());
+    assert_eq!(
+        report.deadline,
+        Some(Utc.with_ymd_and_hms(2025, 10, 9, 14, 0, 20).unwrap())
+    );
+    assert_eq!(report.remaining_s, Some(17));
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["deadline"], "2025-10-09T14:00:20Z");
+
+    let closed = DfuModeReport::new(2, requested, checked, false, String::new());
+    assert_eq!(closed.remaining_s, Some(0));
+
+    let forever = DfuModeReport::new(0, requested, checked, true, String::new());
+    assert_eq!(forever.deadline, None);
+    assert_eq!(forever.remaining_s, None);
+}
+
+/// Fake mcumgr answering `reset` with `reset_status`
+fn fake_mcumgr_reset(dir: &Path, reset_status: i32) -> PathBuf {
+    let path = dir.join("mcumgr");
+    let script = format!(
+        "#!/bin/sh
+case \"$*\" in\n  *reset*) echo 'Done'; exit {} ;;\nesac\nexit 0\n",
+        reset_status
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn dfu_cancel_resets_the_bootloader_then_the_console() {
+    for (reset_status, method) in [(0, DfuExit::McumgrReset), (1, DfuExit::ConsoleReset)] {
+        let dir = tempfile::tempdir().unwrap();
+        let mcumgr = fake_mcumgr_reset(dir.path(), reset_status);
+        let mut connection = Connection::new("/dev/nonexistent", 115200, true).unwrap();
+        connection.set_dry_run(true);
+        let mut manager = FirmwareManager::new(connection, mcumgr_on_fake_port());
+        manager.set_mcumgr_program(mcumgr.to_str().unwrap());
+
+        let report = manager.exit_bootloader().await.unwrap();
+        assert_eq!(report.method, method);
+    }
+}
+
+#[tokio::test]
+async fn dfu_cancel_fails_when_nothing_takes_a_reset() {
+    let dir = tempfile::tempdir().unwrap();
+    let mcumgr = fake_mcumgr_reset(dir.path(), 1);
+    // Not dry run, so the console connection really fails
+    let connection = Connection::new("/dev/nonexistent", 115200, true).unwrap();
+    let mut manager = FirmwareManager::new(connection, mcumgr_on_fake_port());
+    manager.set_mcumgr_program(mcumgr.to_str().unwrap());
+
+    let error = manager.exit_bootloader().await.unwrap_err().to_string();
+    assert!(error.contains("power cycle"), "{}", error);
+}
+
+#[test]
+fn mcumgr_follows_the_console_unless_overridden() {
+    let inherited = FirmwareTransports::resolve("/dev/ttyUSB0", 57600, None, None);
+    assert_eq!(
+        inherited.mcumgr,
+        McumgrTransport::Serial {
+            port: "/dev/ttyUSB0".to_string(),
+            baud: 57600,
+        }
+    );
+    assert_eq!(inherited.mismatch_warning(), None);
+
+    let port_only = FirmwareTransports::resolve("/dev/ttyUSB0", 57600, Some("/dev/ttyLP2"), None);
+    assert_eq!(port_only.mcumgr.connstring(), "/dev/ttyLP2,baud=57600");
+    let baud_only = FirmwareTransports::resolve("/dev/ttyUSB0", 57600, None, Some(115200));
+    assert_eq!(baud_only.mcumgr.connstring(), "/dev/ttyUSB0,baud=115200");
+    assert_eq!(baud_only.console_baud, 57600);
+
+    let ble = inherited.with_mcumgr(McumgrTransport::Ble {
+        address: "C0:FF:EE:00:11:22".to_string(),
+    });
+    assert_eq!(ble.console_device, "/dev/ttyUSB0");
+    assert_eq!(ble.mcumgr.conntype(), "ble");
+}
+
+#[test]
+fn differing_serial_transports_are_warned_about() {
+    let warning = FirmwareTransports::resolve("/dev/ttyUSB0", 115200, Some("/dev/ttyLP2"), None)
+        .mismatch_warning()
+        .unwrap();
+    assert_eq!(
+        warning,
+        "mcumgr uses /dev/ttyLP2 at 115200 baud but the console is /dev/ttyUSB0 at 115200 baud"
+    );
+    assert!(
+        FirmwareTransports::resolve("/dev/ttyUSB0", 115200, None, Some(921600))
+            .mismatch_warning()
+            .is_some()
+    );
+
+    // A network transport is a separate link by choice
+    let udp = FirmwareTransports::resolve("/dev/ttyUSB0", 115200, None, None)
+        .with_mcumgr(McumgrTransport::udp_from_str("192.168.1.20:1337").unwrap());
+    assert_eq!(udp.mismatch_warning(), None);
+}
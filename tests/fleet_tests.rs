@@ -0,0 +1,204 @@
+/*
+ * E-ink Power CLI - Fleet Inventory Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Matching units across inventories and the changes reported between them
+
+use eink_power_cli::error::PowerCliError;
+use eink_power_cli::fleet::{self, FleetChangeKind, FleetEntry, FleetInventory, ProbeStatus};
+use eink_power_cli::json::{parse_output, CommandOutput, JsonResponse};
+use std::collections::BTreeMap;
+
+/// A PMU that answered on `port`
+fn unit(port: &str, usb_serial: Option<&str>, firmware: &str, serial: &str) -> FleetEntry {
+    FleetEntry {
+        port: port.to_string(),
+        usb_serial: usb_serial.map(String::from),
+        usb_id: usb_serial.map(|_| "0403:6001".to_string()),
+        status: ProbeStatus::Ok,
+        error: None,
+        firmware_version: Some(firmware.to_string()),
+        serial_number: Some(serial.to_string()),
+        battery_mv: Some(3850),
+        rails: BTreeMap::new(),
+        errors: Vec::new(),
+    }
+}
+
+/// Kinds of the changes, by unit
+fn kinds(before: &FleetInventory, after: &FleetInventory) -> Vec<(String, FleetChangeKind)> {
+    fleet::diff(before, after)
+        .changes
+        .into_iter()
+        .map(|change| (change.unit, change.kind))
+        .collect()
+}
+
+#[test]
+fn identical_inventories_have_no_changes() {
+    let ports = vec![
+        unit("/dev/ttyUSB0", Some("A10K3J2"), "2.3.0", "EPC-0041"),
+        unit("/dev/ttyLP2", None, "2.3.0", "EPC-0042"),
+    ];
+    let diff = fleet::diff(
+        &FleetInventory::new(ports.clone()),
+        &FleetInventory::new(ports),
+    );
+    assert!(diff.changes.is_empty(), "{:?}", diff.changes);
+    assert_eq!(diff.unchanged, 2);
+}
+
+#[test]
+fn units_are_matched_by_usb_serial_across_ports() {
+    let before = FleetInventory::new(vec![
+        unit("/dev/ttyUSB0", Some("A10K3J2"), "2.3.0", "EPC-0041"),
+        unit("/dev/ttyUSB1", Some("B20L4K3"), "2.3.0", "EPC-0042"),
+    ]);
+    // After a reboot the adapters came up the other way round
+    let after = FleetInventory::new(vec![
+        unit("/dev/ttyUSB0", Some("B20L4K3"), "2.3.0", "EPC-0042"),
+        unit("/dev/ttyUSB1", Some("A10K3J2"), "2.3.0", "EPC-0041"),
+    ]);
+    let diff = fleet::diff(&before, &after);
+    assert_eq!(diff.changes.len(), 2, "{:?}", diff.changes);
+    let moved = &diff.changes[0];
+    assert_eq!(moved.unit, "usb:A10K3J2");
+    assert_eq!(moved.kind, FleetChangeKind::Moved);
+    assert_eq!(moved.before.as_deref(), Some("/dev/ttyUSB0"));
+    assert_eq!(moved.after.as_deref(), Some("/dev/ttyUSB1"));
+    assert_eq!(diff.unchanged, 0);
+}
+
+#[test]
+fn firmware_and_identity_changes_are_reported() {
+    let before = FleetInventory::new(vec![
+        unit("/dev/ttyUSB0", Some("A10K3J2"), "2.2.0", "EPC-0041"),
+        unit("/dev/ttyUSB1", Some("B20L4K3"), "2.3.0", "EPC-0042"),
+    ]);
+    let after = FleetInventory::new(vec![
+        unit("/dev/ttyUSB0", Some("A10K3J2"), "2.3.0", "EPC-0041"),
+        unit("/dev/ttyUSB1", Some("B20L4K3"), "2.3.0", "EPC-0099"),
+    ]);
+    let diff = fleet::diff(&before, &after);
+    assert_eq!(
+        kinds(&before, &after),
+        [
+            ("usb:A10K3J2".to_string(), FleetChangeKind::Firmware),
+            ("usb:B20L4K3".to_string(), FleetChangeKind::Identity),
+        ]
+    );
+    assert_eq!(diff.changes[0].before.as_deref(), Some("2.2.0"));
+    assert_eq!(diff.changes[0].after.as_deref(), Some("2.3.0"));
+    assert_eq!(diff.changes[1].after.as_deref(), Some("EPC-0099"));
+}
+
+#[test]
+fn units_added_removed_or_gone_quiet() {
+    let mut quiet = unit("/dev/ttyUSB1", Some("B20L4K3"), "2.3.0", "EPC-0042");
+    quiet.status = ProbeStatus::Busy;
+    quiet.firmware_version = None;
+    quiet.serial_number = None;
+    quiet.error = Some("Serial port /dev/ttyUSB1 is in use by agetty".to_string());
+
+    let before = FleetInventory::new(vec![
+        unit("/dev/ttyUSB0", Some("A10K3J2"), "2.3.0", "EPC-0041"),
+        unit("/dev/ttyUSB1", Some("B20L4K3"), "2.3.0", "EPC-0042"),
+    ]);
+    let after = FleetInventory::new(vec![
+        quiet,
+        unit("/dev/ttyUSB2", Some("C30M5L4"), "2.3.0", "EPC-0043"),
+    ]);
+    let diff = fleet::diff(&before, &after);
+    assert_eq!(
+        kinds(&before, &after),
+        [
+            ("usb:B20L4K3".to_string(), FleetChangeKind::Status),
+            ("usb:C30M5L4".to_string(), FleetChangeKind::Added),
+            ("usb:A10K3J2".to_string(), FleetChangeKind::Removed),
+        ]
+    );
+    // A unit that stopped answering is not also a firmware change
+    assert_eq!(diff.changes[0].before.as_deref(), Some("ok"));
+    assert_eq!(diff.changes[0].after.as_deref(), Some("busy"));
+    assert_eq!(diff.changes[2].port, "/dev/ttyUSB0");
+}
+
+#[test]
+fn ports_without_a_usb_serial_are_matched_by_path() {
+    let before = FleetInventory::new(vec![unit("/dev/ttyLP2", None, "2.3.0", "EPC-0042")]);
+    let after = FleetInventory::new(vec![unit("/dev/ttyLP3", None, "2.3.0", "EPC-0042")]);
+    assert_eq!(
+        kinds(&before, &after),
+        [
+            ("port:/dev/ttyLP3".to_string(), FleetChangeKind::Added),
+            ("port:/dev/ttyLP2".to_string(), FleetChangeKind::Removed),
+        ]
+    );
+}
+
+#[test]
+fn saved_inventories_and_envelopes_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let inventory = FleetInventory::new(vec![unit(
+        "/dev/ttyUSB0",
+        Some("A10K3J2"),
+        "2.3.0",
+        "EPC-0041",
+    )]);
+
+    let saved = dir.path().join("fleet.json");
+    inventory.save(&saved).unwrap();
+    assert_eq!(FleetInventory::load(&saved).unwrap(), inventory);
+
+    let envelope = JsonResponse::success("fleet scan", serde_json::to_value(&inventory).unwrap());
+    let printed = serde_json::to_string(&envelope).unwrap();
+    let scanned = dir.path().join("scan.json");
+    std::fs::write(&scanned, &printed).unwrap();
+    assert_eq!(FleetInventory::load(&scanned).unwrap(), inventory);
+    match parse_output(&printed).unwrap() {
+        CommandOutput::FleetScan(read) => assert_eq!(read, inventory),
+        other => panic!("unexpected output {:?}", other),
+    }
+
+    let other = dir.path().join("other.json");
+    let envelope = JsonResponse::success("ping", serde_json::json!({}));
+    std::fs::write(&other, serde_json::to_string(&envelope).unwrap()).unwrap();
+    assert!(FleetInventory::load(&other).is_err());
+}
+
+#[test]
+fn probe_failures_map_to_a_status() {
+    for (error, status) in [
+        (
+            PowerCliError::PortBusy {
+                device: "/dev/ttyUSB1".to_string(),
+                holder: "agetty".to_string(),
+            },
+            ProbeStatus::Busy,
+        ),
+        (
+            PowerCliError::DeviceNotFound {
+                device: "/dev/ttyUSB7".to_string(),
+            },
+            ProbeStatus::Missing,
+        ),
+        (
+            PowerCliError::Timeout {
+                timeout: 1,
+                bytes_received: 0,
+                partial: None,
+            },
+            ProbeStatus::Unresponsive,
+        ),
+        (
+            PowerCliError::InvalidArguments {
+                message: "bad".to_string(),
+            },
+            ProbeStatus::Failed,
+        ),
+    ] {
+        assert_eq!(ProbeStatus::of_error(&error), status, "{}", error);
+    }
+}
@@ -0,0 +1,89 @@
+/*
+ * E-ink Power CLI - Binary Framing Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Tests for length-prefixed binary frames
+
+use eink_power_cli::serial::protocol::framing::{decode_frame, encode_frame, FRAME_HEADER_LEN};
+
+#[test]
+fn frames_round_trip_at_boundary_lengths() {
+    for len in [0usize, 1, 255, 256, 65535] {
+        let payload: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+        let frame = encode_frame(&payload);
+
+        assert_eq!(frame.len(), FRAME_HEADER_LEN + len);
+        assert_eq!(&frame[..2], &(len as u16).to_be_bytes());
+        let (decoded, rest) = decode_frame(&frame).expect("complete frame");
+        assert_eq!(decoded, payload.as_slice(), "length {}", len);
+        assert!(rest.is_empty());
+    }
+}
+
+#[test]
+fn decode_returns_remainder_and_waits_for_complete_frames() {
+    let mut buf = encode_frame(b"version");
+    buf.extend_from_slice(&encode_frame(b"ping"));
+
+    let (first, rest) = decode_frame(&buf).unwrap();
+    assert_eq!(first, b"version");
+    let (second, rest) = decode_frame(rest).unwrap();
+    assert_eq!(second, b"ping");
+    assert!(rest.is_empty());
+
+    let frame = encode_frame(b"version");
+    assert_eq!(decode_frame(&frame[..1]), None);
+    assert_eq!(decode_frame(&frame[..frame.len() - 1]), None);
+}
+
+#[test]
+#[should_panic(expected = "exceeds")]
+fn oversized_payload_panics() {
+    encode_frame(&vec![0u8; 65536]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn binary_mode_sends_and_reads_frames() {
+    use eink_power_cli::serial::{Connection, Protocol};
+    use serialport::{SerialPort, TTYPort};
+    use std::io::{Read, Write};
+    use std::time::Duration;
+
+    let (mut master, slave) = TTYPort::pair().unwrap();
+    master.set_timeout(Duration::from_secs(5)).unwrap();
+    let device = slave.name().unwrap();
+    drop(slave);
+
+    // Answer one frame with a frame, like binary-protocol firmware would
+    let controller = std::thread::spawn(move || {
+        let mut received = Vec::new();
+        let mut buf = [0u8; 256];
+        let request = loop {
+            match master.read(&mut buf) {
+                Ok(n) => received.extend_from_slice(&buf[..n]),
+                // No client has the slave open yet
+                Err(_) => std::thread::sleep(Duration::from_millis(10)),
+            }
+            if let Some((payload, _)) = decode_frame(&received) {
+                break payload.to_vec();
+            }
+        };
+        master.write_all(&encode_frame(b"Version: 3.0.0")).unwrap();
+        master.flush().unwrap();
+        // Closing the master would discard the unread reply
+        (request, master)
+    });
+
+    let mut connection = Connection::new(&device, 115200, true).unwrap();
+    connection.set_shell_check(false);
+    let mut protocol = Protocol::new(connection);
+    protocol.enable_binary_mode(true);
+
+    let reply = protocol.execute_system_command("version").await.unwrap();
+    assert!(reply.contains("3.0.0"), "{}", reply);
+    let (request, _master) = controller.join().unwrap();
+    assert_eq!(request, b"version");
+}
@@ -0,0 +1,118 @@
+/*
+ * E-ink Power CLI - GPIO Pin Name Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Port and pin spellings and the firmware form they are sent in
+
+use eink_power_cli::power::gpio::{comm_signal_title, GpioPin, GPIO_PORTS};
+
+fn from_parts(port: &str, pin: Option<u8>) -> Result<GpioPin, String> {
+    GpioPin::from_parts(port, pin)
+}
+
+#[test]
+fn every_spelling_of_every_port_maps_to_the_firmware_form() {
+    for upper in GPIO_PORTS.chars() {
+        let lower = upper.to_ascii_lowercase();
+        let expected = GpioPin {
+            port: upper.to_string(),
+            pin: 5,
+        };
+
+        for port in [
+            format!("gpio{}", lower),
+            format!("GPIO{}", upper),
+            format!("Gpio{}", lower),
+            lower.to_string(),
+            upper.to_string(),
+            format!("pt{}", lower),
+            format!("PT{}", upper),
+            format!("Pt{}", upper),
+        ] {
+            assert_eq!(from_parts(&port, Some(5)).unwrap(), expected, "{} 5", port);
+        }
+
+        for name in [
+            format!("{}5", upper),
+            format!("{}5", lower),
+            format!("pt{}5", lower),
+            format!("PT{}5", upper),
+            format!("gpio{}5", lower),
+            format!(" PT{}5 ", upper),
+        ] {
+            assert_eq!(from_parts(&name, None).unwrap(), expected, "{}", name);
+            assert_eq!(name.parse::<GpioPin>().unwrap(), expected, "{}", name);
+        }
+
+        assert_eq!(expected.firmware_port(), format!("gpio{}", lower));
+        assert_eq!(expected.schematic_name(), format!("PT{}5", upper));
+        assert_eq!(expected.to_string(), format!("{}5", upper));
+    }
+}
+
+#[test]
+fn pin_numbers_cover_zero_to_thirty_one() {
+    assert_eq!(from_parts("PTE0", None).unwrap().pin, 0);
+    assert_eq!(from_parts("gpioe", Some(31)).unwrap().pin, 31);
+    assert_eq!("e31".parse::<GpioPin>().unwrap().pin, 31);
+}
+
+#[test]
+fn out_of_range_ports_and_pins_are_rejected() {
+    for port in ["F5", "ptf5", "gpiof", "z"] {
+        let error = from_parts(port, Some(5)).unwrap_err();
+        assert!(error.contains("ports A to E"), "{}: {}", port, error);
+    }
+    for (port, pin) in [("A32", None), ("gpioa", Some(32)), ("pta300", None)] {
+        let error = from_parts(port, pin).unwrap_err();
+        assert!(error.contains("pins 0 to 31"), "{}: {}", port, error);
+    }
+    let error = "a300".parse::<GpioPin>().unwrap_err();
+    assert!(error.contains("not a pin like a0"), "{}", error);
+    assert!(error.contains("out of range"), "{}", error);
+}
+
+#[test]
+fn ambiguous_and_malformed_spellings_are_rejected() {
+    for port in ["ab5", "ptab5", "gpioab", "porta5"] {
+        let error = from_parts(port, None).unwrap_err();
+        assert!(error.contains("ambiguous"), "{}: {}", port, error);
+    }
+    // A whole pin followed by a pin number
+    let error = from_parts("PTA5", Some(3)).unwrap_err();
+    assert!(error.contains("ambiguous"), "{}", error);
+
+    for port in ["gpio5", "pt", "5", ""] {
+        let error = from_parts(port, Some(5)).unwrap_err();
+        assert!(error.contains("no port"), "{}: {}", port, error);
+    }
+    assert!(from_parts("a5x", None)
+        .unwrap_err()
+        .contains("after the pin number"));
+    assert!(from_parts("PTA", None)
+        .unwrap_err()
+        .contains("needs a pin number"));
+    assert!("gpioa"
+        .parse::<GpioPin>()
+        .unwrap_err()
+        .contains("no pin number"));
+}
+
+#[test]
+fn names_keep_the_typed_spelling_next_to_the_canonical_forms() {
+    let pin = from_parts("pta5", None).unwrap();
+    let names = pin.names("pta5");
+
+    assert_eq!(names.typed, "pta5");
+    assert_eq!(names.firmware, "gpioa 5");
+    assert_eq!(names.schematic, "PTA5");
+    assert_eq!(names.describe(), "pta5 → gpioa 5 (PTA5)");
+}
+
+#[test]
+fn comm_signals_show_their_pins() {
+    assert_eq!(comm_signal_title("bt_wake"), "BT_WAKE_HOST (PTC1, gpioc 1)");
+    assert_eq!(comm_signal_title("wl_wake"), "WL_WAKE_HOST (PTC3, gpioc 3)");
+}
@@ -0,0 +1,146 @@
+/*
+ * E-ink Power CLI - GPIO Script Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! The `gpio script` step list and the `gpio batch` reply
+
+use eink_power_cli::power::gpio::{
+    GpioOp, GpioOpStatus, GpioPin, GpioScript, GpioScriptMode, GpioScriptReport,
+};
+use std::time::Duration;
+
+fn pin(name: &str) -> GpioPin {
+    name.parse().unwrap()
+}
+
+fn parse_err(script: &str) -> String {
+    script.parse::<GpioScript>().unwrap_err()
+}
+
+#[test]
+fn script_parses_settings_and_delays() {
+    let script: GpioScript = "a0=1,5ms,a1=1, 2ms ,B3=0,500us,c10=1,1s".parse().unwrap();
+    assert_eq!(
+        script.ops,
+        [
+            GpioOp::Set {
+                pin: pin("A0"),
+                value: 1
+            },
+            GpioOp::Delay(Duration::from_millis(5)),
+            GpioOp::Set {
+                pin: pin("A1"),
+                value: 1
+            },
+            GpioOp::Delay(Duration::from_millis(2)),
+            GpioOp::Set {
+                pin: pin("B3"),
+                value: 0
+            },
+            GpioOp::Delay(Duration::from_micros(500)),
+            GpioOp::Set {
+                pin: pin("C10"),
+                value: 1
+            },
+            GpioOp::Delay(Duration::from_secs(1)),
+        ]
+    );
+    assert_eq!(
+        script.to_string(),
+        "A0=1,5ms,A1=1,2ms,B3=0,500us,C10=1,1000ms"
+    );
+    assert_eq!(
+        script.batch_args(),
+        "A0=1 5ms A1=1 2ms B3=0 500us C10=1 1000ms"
+    );
+}
+
+#[test]
+fn malformed_scripts_name_the_bad_step() {
+    assert_eq!(parse_err("a0=1,,a1=1"), "step 2 is empty", "doubled comma");
+    assert!(parse_err("a0=1,").starts_with("step 2"));
+    assert!(parse_err("a0=2").contains("not a pin value"));
+    assert!(parse_err("a0=").contains("not a pin value"));
+    assert!(parse_err("0a=1").contains("not a pin like a0"));
+    assert!(parse_err("a=1").contains("not a pin like a0"));
+    assert!(parse_err("a300=1").contains("not a pin like a0"));
+    assert!(parse_err("a0=1,5").contains("needs a unit"));
+    assert!(parse_err("a0=1,5min").contains("unknown unit"));
+    assert!(parse_err("a0=1,ms").contains("neither a pin setting"));
+    assert!(parse_err("a0=1,-5ms").contains("neither a pin setting"));
+    assert!(parse_err("a0=1,11s").contains("limit"));
+    assert!(parse_err("a0=1,abc,a1=0").starts_with("step 2:"));
+}
+
+#[test]
+fn script_must_set_a_pin() {
+    assert_eq!(parse_err("5ms,2ms"), "the script sets no pin");
+    assert_eq!(parse_err(""), "step 1 is empty");
+}
+
+#[test]
+fn steps_carry_the_delay_before_them() {
+    let script: GpioScript = "5ms,a0=1,2ms,3ms,a1=1,a2=0".parse().unwrap();
+    let delays: Vec<(String, u64)> = script
+        .steps()
+        .into_iter()
+        .map(|(pin, value, delay)| (format!("{}={}", pin, value), delay.as_millis() as u64))
+        .collect();
+    assert_eq!(
+        delays,
+        [
+            ("A0=1".to_string(), 5),
+            ("A1=1".to_string(), 5),
+            ("A2=0".to_string(), 0)
+        ]
+    );
+}
+
+#[test]
+fn batch_reply_gives_firmware_timing() {
+    let script: GpioScript = "a0=1,5ms,a1=1,2ms,b3=0".parse().unwrap();
+    let reply = "A0=1 OK +0us\nA1=1 OK +5012us\nB3=0 OK +7031us\nBatch complete";
+    let report = GpioScriptReport::from_batch(&script, reply);
+
+    assert_eq!(report.mode, GpioScriptMode::Firmware);
+    assert!(report.success);
+    let timing: Vec<(Option<u64>, Option<u64>, u64)> = report
+        .ops
+        .iter()
+        .map(|op| (op.at_us, op.gap_us, op.requested_gap_us))
+        .collect();
+    assert_eq!(
+        timing,
+        [
+            (Some(0), None, 0),
+            (Some(5012), Some(5012), 5000),
+            (Some(7031), Some(2019), 2000)
+        ]
+    );
+    assert!(report.failure_error().is_none());
+}
+
+#[test]
+fn batch_reply_failures_and_gaps_are_reported() {
+    let script: GpioScript = "a0=1,a1=1,b3=0".parse().unwrap();
+    let report = GpioScriptReport::from_batch(&script, "A0=1: OK +0us\nA1=1: FAILED (pin locked)");
+
+    let statuses: Vec<GpioOpStatus> = report.ops.iter().map(|op| op.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            GpioOpStatus::Ok,
+            GpioOpStatus::Failed,
+            GpioOpStatus::Unconfirmed
+        ]
+    );
+    assert_eq!(
+        report.ops[1].error.as_deref(),
+        Some("A1=1: FAILED (pin locked)")
+    );
+    assert!(!report.success);
+    let err = report.failure_error().unwrap().to_string();
+    assert!(err.contains("A1=1, B3=0"), "{}", err);
+}
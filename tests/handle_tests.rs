@@ -0,0 +1,99 @@
+/*
+ * E-ink Power CLI - Controller Handle Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Concurrent requests through cloned `PowerHandle`s against the simulator
+#![cfg(unix)]
+
+use eink_power_cli::power::control::PowerState;
+use eink_power_cli::power::handle::PowerHandle;
+use eink_power_cli::power::{PowerController, Rail};
+use eink_power_cli::serial::Connection;
+use eink_power_cli::simulator::{Faults, PmuSimulator, BATTERY_REPLY, PM_STATS_REPLY};
+use eink_power_cli::PowerCliError;
+use std::time::Duration;
+
+fn controller(sim: &PmuSimulator) -> PowerController {
+    PowerController::new(Connection::new(sim.device(), 115200, true).unwrap())
+}
+
+#[tokio::test]
+async fn concurrent_requests_get_their_own_replies() {
+    let sim = PmuSimulator::start();
+    let handle = controller(&sim).into_handle();
+
+    let tasks: Vec<_> = (0..8)
+        .map(|i| {
+            let handle = handle.clone();
+            tokio::spawn(async move {
+                match i % 3 {
+                    0 => (i, handle.battery_read().await.unwrap()),
+                    1 => (i, handle.pm_command("stats").await.unwrap()),
+                    _ => (
+                        i,
+                        handle
+                            .control_rail(Rail::Wifi, PowerState::Status)
+                            .await
+                            .unwrap(),
+                    ),
+                }
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        let (i, response) = task.await.unwrap();
+        let expected = match i % 3 {
+            0 => BATTERY_REPLY,
+            1 => PM_STATS_REPLY,
+            _ => "WIFI: OFF",
+        };
+        assert!(response.contains(expected), "request {}: {}", i, response);
+    }
+    // One command at a time on the wire, none lost or repeated
+    let received = sim.received();
+    assert_eq!(received.iter().filter(|c| *c == "ltc2959 read").count(), 3);
+    assert_eq!(received.iter().filter(|c| *c == "pm stats").count(), 3);
+    assert_eq!(
+        received.iter().filter(|c| *c == "pm wifi status").count(),
+        2
+    );
+}
+
+#[tokio::test]
+async fn slow_requests_time_out_without_blocking_the_handle() {
+    let sim = PmuSimulator::with_faults(Faults {
+        reply_delay: Duration::from_millis(600),
+        ..Faults::default()
+    });
+    let handle = controller(&sim)
+        .into_handle()
+        .with_timeout(Duration::from_millis(200));
+
+    let error = handle.battery_read().await.unwrap_err();
+    assert!(matches!(error, PowerCliError::Timeout { .. }), "{}", error);
+
+    // ping is answered without the delay once the slow read has finished
+    let patient = handle.with_timeout(Duration::from_secs(5));
+    assert_eq!(patient.ping().await.unwrap().trim(), "pong");
+}
+
+#[tokio::test]
+async fn dropping_the_last_handle_closes_the_connection() {
+    let sim = PmuSimulator::start();
+    let (handle, task) = PowerHandle::spawn(controller(&sim), 1);
+    let other = handle.clone();
+    handle.ping().await.unwrap();
+    drop(handle);
+    assert!(!task.is_finished());
+
+    // Still served while a clone is left
+    other.ping().await.unwrap();
+    drop(other);
+    tokio::time::timeout(Duration::from_secs(5), task)
+        .await
+        .expect("controller task still running")
+        .unwrap();
+}
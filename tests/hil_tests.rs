@@ -0,0 +1,573 @@
+/*
+ * E-ink Power CLI - Hardware-in-the-Loop Plan Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Reading `hil-test` plans, writing their JUnit reports and the checks
+//! `assert` steps make on JSON output
+
+use chrono::{TimeZone, Utc};
+use eink_power_cli::cli::{Commands, FirmwareCommands, FAST_UPLOAD_BAUD};
+use eink_power_cli::hil::{
+    junit_xml, xml_escape, HilAction, HilCheck, HilPlan, HilReport, HilStepResult, HilStepStatus,
+    DEFAULT_STEP_TIMEOUT_S, DEFAULT_UPLOAD_TIMEOUT_S, DEFAULT_WAKE_ALLOWANCE_S,
+};
+use eink_power_cli::json::assertion::{Assertion, Operator, PathSegment};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const PLAN: &str = r#"
+name = "pmu-candidate"
+timeout_s = 45
+retries = 1
+
+[[step]]
+type = "upload-firmware"
+file = "zephyr.signed.bin"
+fast = true
+critical = true
+
+[[step]]
+name = "Reports the candidate"
+type = "command"
+command = "version"
+expect = ["2.6.0"]
+
+[[step]]
+type = "assert"
+command = "battery read"
+checks = ["voltage_mv >= 3300", "voltage_mv <= 4300"]
+retries = 3
+timeout_s = 20
+
+[[step]]
+type = "sleep-test"
+duration = "10s"
+options = ["--pmic", "--vlls3"]
+
+[[step]]
+type = "raw"
+line = "pm stats"
+"#;
+
+#[test]
+fn plan_steps_are_read_in_order() {
+    let plan = HilPlan::parse(PLAN).unwrap();
+
+    assert_eq!(plan.name(), "pmu-candidate");
+    let kinds: Vec<&str> = plan.steps.iter().map(|step| step.action.kind()).collect();
+    assert_eq!(
+        kinds,
+        ["upload-firmware", "command", "assert", "sleep-test", "raw"]
+    );
+    let names: Vec<String> = plan.steps.iter().map(|step| step.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "firmware upload zephyr.signed.bin",
+            "Reports the candidate",
+            "battery read",
+            "sleep 10s",
+            "raw pm stats",
+        ]
+    );
+    assert!(plan.steps[0].critical);
+    assert!(!plan.steps[1].critical);
+    assert_eq!(plan.steps[1].expect, ["2.6.0"]);
+    assert_eq!(
+        plan.steps[4].action,
+        HilAction::Raw {
+            line: "pm stats".to_string()
+        }
+    );
+}
+
+#[test]
+fn steps_become_the_subcommands_they_run() {
+    let plan = HilPlan::parse(PLAN).unwrap();
+
+    match plan.steps[0].command().unwrap() {
+        Some(Commands::Firmware(FirmwareCommands::Upload {
+            file,
+            fast,
+            skip_reset,
+            ..
+        })) => {
+            assert_eq!(file, PathBuf::from("zephyr.signed.bin"));
+            assert_eq!(fast, Some(FAST_UPLOAD_BAUD));
+            assert!(!skip_reset);
+        }
+        other => panic!("expected firmware upload, got {:?}", other),
+    }
+    assert_eq!(plan.steps[1].command().unwrap().unwrap().name(), "version");
+    assert_eq!(
+        plan.steps[2].command().unwrap().unwrap().name(),
+        "battery read"
+    );
+    assert_eq!(plan.steps[3].command().unwrap().unwrap().name(), "pm sleep");
+    assert_eq!(
+        plan.steps[3].sleep_duration(),
+        Some(Duration::from_secs(10))
+    );
+    assert!(plan.steps[4].command().unwrap().is_none());
+}
+
+#[test]
+fn timeouts_and_retries_fall_back_to_the_plan_then_the_defaults() {
+    let plan = HilPlan::parse(PLAN).unwrap();
+    assert_eq!(plan.timeout(&plan.steps[0]), Duration::from_secs(45));
+    assert_eq!(plan.timeout(&plan.steps[2]), Duration::from_secs(20));
+    assert_eq!(plan.retries(&plan.steps[1]), 1);
+    assert_eq!(plan.retries(&plan.steps[2]), 3);
+
+    let plan = HilPlan::parse(
+        r#"
+[[step]]
+type = "command"
+command = "ping"
+
+[[step]]
+type = "upload-firmware"
+file = "a.bin"
+
+[[step]]
+type = "sleep-test"
+duration = "2m"
+"#,
+    )
+    .unwrap();
+    assert_eq!(plan.name(), "hil-test");
+    assert_eq!(
+        plan.timeout(&plan.steps[0]),
+        Duration::from_secs(DEFAULT_STEP_TIMEOUT_S)
+    );
+    assert_eq!(
+        plan.timeout(&plan.steps[1]),
+        Duration::from_secs(DEFAULT_UPLOAD_TIMEOUT_S)
+    );
+    // Long enough for the sleep itself and the wake-up
+    assert_eq!(
+        plan.timeout(&plan.steps[2]),
+        Duration::from_secs(120 + DEFAULT_WAKE_ALLOWANCE_S)
+    );
+    assert_eq!(plan.retries(&plan.steps[0]), 0);
+}
+
+#[test]
+fn plan_mistakes_are_refused_before_anything_runs() {
+    let refused = |plan: &str, expected: &str| {
+        let error = HilPlan::parse(plan).unwrap_err();
+        assert!(error.contains(expected), "{:?}: {}", plan, error);
+    };
+
+    refused("name = \"empty\"\n", "no [[step]]");
+    refused("[[step]]\ntype = \"reboot\"\n", "unknown variant `reboot`");
+    refused("[[step]]\ntype = \"command\"\n", "missing field `command`");
+    refused(
+        "[[step]]\ntype = \"command\"\ncommand = \"batery read\"\n",
+        "step 1 (batery read)",
+    );
+    refused(
+        "[[step]]\ntype = \"command\"\ncommand = \"ping\"\nretry = 2\n",
+        "unknown field `retry` for a command step",
+    );
+    refused(
+        "[[step]]\ntype = \"raw\"\nline = \"ping\"\ncommand = \"ping\"\n",
+        "unknown field `command` for a raw step",
+    );
+    refused(
+        "[[step]]\ntype = \"assert\"\ncommand = \"battery read\"\nchecks = []\n",
+        "at least one check",
+    );
+    refused(
+        "[[step]]\ntype = \"assert\"\ncommand = \"battery read\"\nchecks = [\"voltage_mv 3300\"]\n",
+        "has no operator",
+    );
+    refused(
+        "[[step]]\ntype = \"sleep-test\"\nduration = \"soon\"\n",
+        "invalid duration 'soon'",
+    );
+    refused(
+        "[[step]]\ntype = \"sleep-test\"\nduration = \"5s\"\noptions = [\"--nap\"]\n",
+        "step 1 (sleep 5s)",
+    );
+    refused("[[step]]\ntype = \"raw\"\nline = \"  \"\n", "needs a line");
+    refused(
+        "[[step]]\ntype = \"command\"\ncommand = \"ping\"\ntimeout_s = 0\n",
+        "timeout_s must be at least 1",
+    );
+    refused(
+        "timeout_s = 0\n[[step]]\ntype = \"command\"\ncommand = \"ping\"\n",
+        "timeout_s must be at least 1",
+    );
+    refused(
+        "retires = 1\n[[step]]\ntype = \"command\"\ncommand = \"ping\"\n",
+        "unknown field `retires`",
+    );
+}
+
+#[test]
+fn loaded_plan_finds_images_beside_it_and_is_named_after_its_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("nightly.toml");
+    std::fs::write(
+        &path,
+        "[[step]]\ntype = \"upload-firmware\"\nfile = \"build/zephyr.bin\"\n\n\
+         [[step]]\ntype = \"upload-firmware\"\nfile = \"/opt/images/golden.bin\"\n",
+    )
+    .unwrap();
+
+    let plan = HilPlan::load(&path).unwrap();
+    assert_eq!(plan.name(), "nightly");
+    let files: Vec<&Path> = plan
+        .steps
+        .iter()
+        .map(|step| match &step.action {
+            HilAction::UploadFirmware { file, .. } => file.as_path(),
+            other => panic!("unexpected {:?}", other),
+        })
+        .collect();
+    assert_eq!(
+        files,
+        [
+            dir.path().join("build/zephyr.bin").as_path(),
+            Path::new("/opt/images/golden.bin")
+        ]
+    );
+
+    let error = HilPlan::load(&dir.path().join("missing.toml")).unwrap_err();
+    assert!(error.to_string().contains("No such file"), "{}", error);
+}
+
+fn step(name: &str, kind: &str, status: HilStepStatus, duration_ms: u64) -> HilStepResult {
+    HilStepResult {
+        name: name.to_string(),
+        kind: kind.to_string(),
+        critical: false,
+        status,
+        attempts: u32::from(status != HilStepStatus::Skipped),
+        duration_ms,
+        error: None,
+        checks: Vec::new(),
+        output: None,
+    }
+}
+
+fn report(steps: Vec<HilStepResult>) -> HilReport {
+    HilReport::new(
+        "pmu-candidate",
+        Utc.with_ymd_and_hms(2025, 10, 9, 14, 30, 0).unwrap(),
+        steps,
+    )
+}
+
+#[test]
+fn report_counts_steps_by_status() {
+    let passing = report(vec![
+        step("version", "command", HilStepStatus::Passed, 120),
+        step("battery read", "assert", HilStepStatus::Passed, 380),
+    ]);
+    assert!(passing.success);
+    assert_eq!((passing.passed, passing.failed, passing.skipped), (2, 0, 0));
+    assert_eq!(passing.duration_ms, 500);
+
+    let failing = report(vec![
+        step("upload", "upload-firmware", HilStepStatus::Failed, 1000),
+        step("version", "command", HilStepStatus::Skipped, 0),
+    ]);
+    assert!(!failing.success);
+    assert_eq!((failing.passed, failing.failed, failing.skipped), (0, 1, 1));
+
+    // A run where every step was skipped did not pass either
+    assert!(!report(vec![step("version", "command", HilStepStatus::Skipped, 0)]).success);
+}
+
+#[test]
+fn step_failure_is_its_error_or_first_failed_check() {
+    let mut failed = step("battery read", "assert", HilStepStatus::Failed, 10);
+    failed.checks = vec![
+        HilCheck {
+            check: "voltage_mv >= 3300".to_string(),
+            passed: true,
+            reason: None,
+        },
+        HilCheck {
+            check: "voltage_mv <= 3500".to_string(),
+            passed: false,
+            reason: Some("voltage_mv is 3850".to_string()),
+        },
+    ];
+    assert_eq!(
+        failed.failure().as_deref(),
+        Some("voltage_mv <= 3500 failed: voltage_mv is 3850")
+    );
+
+    failed.error = Some("timed out after 20s".to_string());
+    assert_eq!(failed.failure().as_deref(), Some("timed out after 20s"));
+
+    let passed = step("version", "command", HilStepStatus::Passed, 10);
+    assert_eq!(passed.failure(), None);
+}
+
+#[test]
+fn expected_substrings_are_checked_against_the_output() {
+    let found = HilCheck::substring("2.6.0", "Firmware: 2.6.0+build.7\n");
+    assert!(found.passed);
+    assert_eq!(found.check, "expect \"2.6.0\"");
+    assert_eq!(found.reason, None);
+
+    let missing = HilCheck::substring("2.6.0", "Firmware: 2.5.0\n");
+    assert!(!missing.passed);
+    assert_eq!(missing.reason.as_deref(), Some("not in the output"));
+}
+
+#[test]
+fn junit_has_a_test_case_per_step() {
+    let xml = junit_xml(&report(vec![
+        step("version", "command", HilStepStatus::Passed, 120),
+        step("battery read", "assert", HilStepStatus::Passed, 1380),
+    ]));
+
+    assert_eq!(
+        xml,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="eink-power-cli" tests="2" failures="0" skipped="0" time="1.500">
+  <testsuite name="pmu-candidate" tests="2" failures="0" errors="0" skipped="0" time="1.500" timestamp="2025-10-09T14:30:00">
+    <testcase name="version" classname="pmu-candidate.command" time="0.120"/>
+    <testcase name="battery read" classname="pmu-candidate.assert" time="1.380"/>
+  </testsuite>
+</testsuites>
+"#
+    );
+}
+
+#[test]
+fn junit_records_failures_skips_and_output() {
+    let mut upload = step("upload", "upload-firmware", HilStepStatus::Passed, 42_000);
+    upload.output = Some("Upload complete\n".to_string());
+    let mut assert = step("battery read", "assert", HilStepStatus::Failed, 900);
+    assert.attempts = 3;
+    assert.checks = vec![
+        HilCheck {
+            check: "voltage_mv >= 3300".to_string(),
+            passed: true,
+            reason: None,
+        },
+        HilCheck {
+            check: "current_ma < 0".to_string(),
+            passed: false,
+            reason: Some("current_ma is 12".to_string()),
+        },
+    ];
+    let mut errored = step("sleep 10s", "sleep-test", HilStepStatus::Failed, 40_000);
+    errored.error = Some("timed out after 40s".to_string());
+    errored.critical = true;
+    let skipped = step("raw pm stats", "raw", HilStepStatus::Skipped, 0);
+
+    let xml = junit_xml(&report(vec![upload, assert, errored, skipped]));
+    assert!(xml.contains(
+        r#"<testsuites name="eink-power-cli" tests="4" failures="2" skipped="1" time="82.900">"#
+    ));
+    assert!(xml.contains(
+        r#"    <testcase name="upload" classname="pmu-candidate.upload-firmware" time="42.000">
+      <system-out>Upload complete
+</system-out>
+    </testcase>"#
+    ));
+    assert!(xml.contains(
+        r#"    <testcase name="battery read" classname="pmu-candidate.assert" time="0.900">
+      <failure message="current_ma &lt; 0 failed: current_ma is 12" type="check">attempts: 3
+pass: voltage_mv &gt;= 3300
+FAIL: current_ma &lt; 0 (current_ma is 12)</failure>
+    </testcase>"#
+    ));
+    assert!(xml
+        .contains(r#"<failure message="timed out after 40s" type="error">attempts: 1</failure>"#));
+    assert!(xml.contains(
+        r#"    <testcase name="raw pm stats" classname="pmu-candidate.raw" time="0.000">
+      <skipped message="a critical step failed"/>
+    </testcase>"#
+    ));
+}
+
+#[test]
+fn junit_escapes_names_and_output() {
+    let mut odd = step(
+        "check <\"fast\" & 'slow'>",
+        "command",
+        HilStepStatus::Passed,
+        1,
+    );
+    odd.output = Some("\u{1b}[32mOK\u{1b}[0m & done\n".to_string());
+    let xml = junit_xml(&HilReport::new(
+        "a&b",
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+        vec![odd],
+    ));
+
+    assert!(xml.contains(
+        r#"<testcase name="check &lt;&quot;fast&quot; &amp; &apos;slow&apos;&gt;" classname="a&amp;b.command""#
+    ));
+    // The color codes of terminal output cannot appear in XML 1.0
+    assert!(xml.contains("<system-out>[32mOK[0m &amp; done\n</system-out>"));
+    assert!(xml.contains(r#"<testsuite name="a&amp;b""#));
+}
+
+#[test]
+fn xml_escape_keeps_whitespace_and_drops_other_control_characters() {
+    assert_eq!(xml_escape("a\tb\r\nc"), "a\tb\r\nc");
+    assert_eq!(xml_escape("bell\u{7} nul\u{0} \u{fffe}"), "bell nul ");
+    assert_eq!(xml_escape("µA → ✅"), "µA → ✅");
+    assert_eq!(xml_escape("<&>\"'"), "&lt;&amp;&gt;&quot;&apos;");
+}
+
+#[test]
+fn report_round_trips_through_json() {
+    let mut failed = step("battery read", "assert", HilStepStatus::Failed, 900);
+    failed.error = Some("no reply".to_string());
+    let report = report(vec![
+        step("version", "command", HilStepStatus::Passed, 120),
+        failed,
+    ]);
+
+    let value = serde_json::to_value(&report).unwrap();
+    assert_eq!(value["steps"][0]["type"], "command");
+    assert_eq!(value["steps"][1]["status"], "failed");
+    // Empty fields are left out
+    assert!(value["steps"][0].get("error").is_none());
+    assert!(value["steps"][0].get("checks").is_none());
+    assert_eq!(serde_json::from_value::<HilReport>(value).unwrap(), report);
+}
+
+#[test]
+fn assertions_parse_path_operator_and_value() {
+    let assertion = Assertion::parse("  slots[1].version contains \"2.6\" ").unwrap();
+    assert_eq!(assertion.expression, "slots[1].version contains \"2.6\"");
+    assert_eq!(
+        assertion.path,
+        [
+            PathSegment::Key("slots".to_string()),
+            PathSegment::Index(1),
+            PathSegment::Key("version".to_string())
+        ]
+    );
+    assert_eq!(assertion.op, Operator::Contains);
+    assert_eq!(assertion.expected, json!("2.6"));
+    assert_eq!(assertion.path_text(), "slots[1].version");
+
+    let cases = [
+        ("voltage_mv>=3300", Operator::Ge, json!(3300)),
+        ("voltage_mv <= 4300.5", Operator::Le, json!(4300.5)),
+        ("rails.pmic == true", Operator::Eq, json!(true)),
+        ("state != charging", Operator::Ne, json!("charging")),
+        ("serial == null", Operator::Eq, json!(null)),
+        ("current_ma < -10", Operator::Lt, json!(-10)),
+        ("temperature_c > 0", Operator::Gt, json!(0)),
+        ("serial exists", Operator::Exists, json!(null)),
+    ];
+    for (expression, op, expected) in cases {
+        let assertion = Assertion::parse(expression).unwrap();
+        assert_eq!(
+            (assertion.op, &assertion.expected),
+            (op, &expected),
+            "{}",
+            expression
+        );
+    }
+    // A word operator must stand apart from the path
+    let assertion = Assertion::parse("existsflag == 1").unwrap();
+    assert_eq!(assertion.path, [PathSegment::Key("existsflag".to_string())]);
+}
+
+#[test]
+fn malformed_assertions_are_refused() {
+    for (expression, expected) in [
+        ("voltage_mv", "has no operator"),
+        ("voltage_mv >=", "has no value"),
+        (">= 3300", "no field to check"),
+        ("voltage_mv >= high", "needs a number"),
+        ("serial exists now", "nothing may follow exists"),
+        ("slots[x].version == 1", "invalid index"),
+        ("slots[0.version == 1", "unclosed ["),
+        ("a..b == 1", "empty field name"),
+        ("a b == 1", "invalid field name"),
+        ("name == \"open", "unterminated string"),
+    ] {
+        let error = Assertion::parse(expression).unwrap_err();
+        assert!(error.contains(expected), "{}: {}", expression, error);
+    }
+}
+
+#[test]
+fn assertions_evaluate_against_command_data() {
+    let data = json!({
+        "voltage_mv": 3850,
+        "current_ma": -125.0,
+        "state": "discharging",
+        "serial": null,
+        "rails": {"pmic": true},
+        "slots": [{"version": "2.5.0"}, {"version": "2.6.0-rc1"}],
+        "flags": ["low_power", "usb"]
+    });
+    let check = |expression: &str| Assertion::parse(expression).unwrap().evaluate(&data);
+
+    for passing in [
+        "voltage_mv >= 3300",
+        "voltage_mv == 3850.0",
+        "current_ma < 0",
+        "current_ma == -125",
+        "state == discharging",
+        "state != charging",
+        "rails.pmic == true",
+        "slots[1].version contains \"2.6\"",
+        "flags contains \"usb\"",
+        "voltage_mv exists",
+        "serial == null",
+    ] {
+        assert!(check(passing).passed, "{}", passing);
+    }
+
+    let outcome = check("voltage_mv <= 3500");
+    assert!(!outcome.passed);
+    assert_eq!(outcome.actual, Some(json!(3850)));
+    assert_eq!(outcome.reason.as_deref(), Some("voltage_mv is 3850"));
+
+    let outcome = check("slots[2].version exists");
+    assert!(!outcome.passed);
+    assert_eq!(outcome.actual, None);
+    assert_eq!(
+        outcome.reason.as_deref(),
+        Some("slots[2].version is missing")
+    );
+
+    let outcome = check("serial exists");
+    assert_eq!(outcome.reason.as_deref(), Some("serial is missing"));
+    assert_eq!(
+        check("serial != null").reason.as_deref(),
+        Some("serial is null")
+    );
+    assert_eq!(
+        check("state > 3").reason.as_deref(),
+        Some("state is not a number")
+    );
+    assert_eq!(
+        check("rails contains 1").reason.as_deref(),
+        Some("rails is not a string or array")
+    );
+}
+
+#[test]
+fn assertion_checks_keep_the_expression_and_reason() {
+    let assertion = Assertion::parse("voltage_mv >= 3300").unwrap();
+    let check = HilCheck::assertion(&assertion, assertion.evaluate(&json!({"voltage_mv": 3100})));
+    assert_eq!(
+        check,
+        HilCheck {
+            check: "voltage_mv >= 3300".to_string(),
+            passed: false,
+            reason: Some("voltage_mv is 3100".to_string()),
+        }
+    );
+}
@@ -0,0 +1,103 @@
+/*
+ * E-ink Power CLI - History Log Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+use eink_power_cli::history::{HistoryEntry, HistoryLog, RESPONSE_EXCERPT_LEN};
+use eink_power_cli::state::device_key;
+
+fn entry(command: &str, exit_status: i32) -> HistoryEntry {
+    HistoryEntry::new(
+        &[command.to_string()],
+        Some("OK"),
+        exit_status,
+        (exit_status != 0).then(|| "failed".to_string()),
+    )
+}
+
+#[test]
+fn test_device_key_is_file_name_safe() {
+    assert_eq!(device_key("/dev/ttyLP2"), "dev_ttyLP2");
+    assert_eq!(
+        device_key("/dev/serial/by-id/usb-1"),
+        "dev_serial_by-id_usb-1"
+    );
+}
+
+#[test]
+fn test_append_and_query() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = HistoryLog::new(dir.path().join("state").join("dev_ttyLP2.history.jsonl"));
+
+    log.append(&entry("version", 0)).unwrap();
+    log.append(&entry("pm pmic off", 1)).unwrap();
+    log.append(&entry("battery", 0)).unwrap();
+
+    let all = log.query(10, None).unwrap();
+    assert_eq!(all.len(), 3);
+    assert_eq!(all[0].commands_sent, vec!["version"]);
+    assert_eq!(all[1].exit_status, 1);
+    assert_eq!(all[1].error.as_deref(), Some("failed"));
+
+    let last = log.query(1, None).unwrap();
+    assert_eq!(last.len(), 1);
+    assert_eq!(last[0].commands_sent, vec!["battery"]);
+
+    let pmic = log.query(10, Some("PMIC")).unwrap();
+    assert_eq!(pmic.len(), 1);
+    assert_eq!(pmic[0].commands_sent, vec!["pm pmic off"]);
+}
+
+#[test]
+fn test_missing_log_reads_empty() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = HistoryLog::new(dir.path().join("none.history.jsonl"));
+    assert!(log.query(20, None).unwrap().is_empty());
+}
+
+#[test]
+fn test_rotation_keeps_backup() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dev.history.jsonl");
+    let log = HistoryLog::new(path.clone()).with_max_bytes(1);
+
+    log.append(&entry("first", 0)).unwrap();
+    log.append(&entry("second", 0)).unwrap();
+    log.append(&entry("third", 0)).unwrap();
+
+    assert!(path.with_extension("jsonl.1").exists());
+    // Only one backup is kept, so the oldest entry is gone
+    let entries = log.query(10, None).unwrap();
+    let commands: Vec<_> = entries
+        .iter()
+        .map(|e| e.commands_sent[0].as_str())
+        .collect();
+    assert_eq!(commands, vec!["second", "third"]);
+}
+
+#[test]
+fn test_corrupt_lines_are_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dev.history.jsonl");
+    let log = HistoryLog::new(path.clone());
+
+    log.append(&entry("version", 0)).unwrap();
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .and_then(|mut f| std::io::Write::write_all(&mut f, b"{\"torn\n"))
+        .unwrap();
+    log.append(&entry("battery", 0)).unwrap();
+
+    assert_eq!(log.query(10, None).unwrap().len(), 2);
+}
+
+#[test]
+fn test_response_excerpt_is_truncated() {
+    let long = "é".repeat(RESPONSE_EXCERPT_LEN);
+    let entry = HistoryEntry::new(&[], Some(&long), 0, None);
+    let response = entry.response.unwrap();
+    assert!(response.ends_with('…'));
+    assert!(response.len() <= RESPONSE_EXCERPT_LEN + '…'.len_utf8());
+}
@@ -0,0 +1,87 @@
+/*
+ * E-ink Power CLI - Port Holder Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Finding other processes with the serial port open in a `/proc` tree
+
+use eink_power_cli::serial::holders::{find_holders, unit_to_steal, PortHolder};
+use std::path::Path;
+
+fn fixture_holders(own_pid: u32) -> Vec<PortHolder> {
+    find_holders(
+        Path::new("tests/fixtures/proc"),
+        Path::new("/dev/ttyLP2"),
+        own_pid,
+    )
+}
+
+#[test]
+fn processes_with_the_port_open_are_found_in_pid_order() {
+    let holders = fixture_holders(1500);
+    let pids: Vec<u32> = holders.iter().map(|holder| holder.pid).collect();
+    assert_eq!(pids, [812, 2044]);
+
+    assert_eq!(holders[0].name, "agetty");
+    assert_eq!(
+        holders[0].cmdline,
+        "/sbin/agetty -o -p -- \\u --keep-baud 115200 ttyLP2 vt220"
+    );
+    assert_eq!(
+        holders[0].unit.as_deref(),
+        Some("serial-getty@ttyLP2.service")
+    );
+    assert_eq!(
+        holders[0].to_string(),
+        "agetty (pid 812, unit serial-getty@ttyLP2.service)"
+    );
+
+    assert_eq!(holders[1].name, "picocom");
+    assert_eq!(holders[1].unit, None);
+    assert_eq!(holders[1].to_string(), "picocom (pid 2044)");
+}
+
+#[test]
+fn this_process_is_not_a_holder() {
+    let pids: Vec<u32> = fixture_holders(812).iter().map(|h| h.pid).collect();
+    assert_eq!(pids, [1500, 2044]);
+}
+
+#[test]
+fn a_missing_proc_tree_has_no_holders() {
+    assert!(find_holders(
+        Path::new("tests/fixtures/nope"),
+        Path::new("/dev/ttyLP2"),
+        1
+    )
+    .is_empty());
+}
+
+#[test]
+fn the_configured_unit_is_stolen_before_the_holders_own() {
+    let holders = fixture_holders(1500);
+    assert_eq!(
+        unit_to_steal(None, &holders).as_deref(),
+        Some("serial-getty@ttyLP2.service")
+    );
+    assert_eq!(
+        unit_to_steal(Some("console.service"), &holders).as_deref(),
+        Some("console.service")
+    );
+    assert_eq!(unit_to_steal(None, &holders[1..]), None);
+}
+
+#[test]
+fn a_process_below_a_service_is_not_in_that_service() {
+    // minicom in an app scope of the user manager, user@1000.service
+    let holders = find_holders(
+        Path::new("tests/fixtures/proc"),
+        Path::new("/dev/ttyLP3"),
+        1500,
+    );
+    assert_eq!(holders.len(), 1);
+    assert_eq!(holders[0].name, "minicom");
+    assert_eq!(holders[0].unit, None);
+    assert_eq!(unit_to_steal(None, &holders), None);
+}
@@ -0,0 +1,85 @@
+/*
+ * E-ink Power CLI - Device Identity Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! EEPROM layout of the unit identity
+
+use chrono::NaiveDate;
+use eink_power_cli::error::PowerCliError;
+use eink_power_cli::power::identity::{
+    format_block, parse_eeprom_dump, touches_identity, DeviceIdentity, IDENTITY_LEN,
+};
+
+fn identity() -> DeviceIdentity {
+    DeviceIdentity::new(
+        "EPC-0001234",
+        3,
+        NaiveDate::from_ymd_opt(2025, 10, 9).unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn identity_round_trips_through_eeprom_layout() {
+    let identity = identity();
+    let bytes = identity.encode();
+    assert_eq!(&bytes[..4], &[b'I', b'D', 1, 3]);
+    assert_eq!(&bytes[4..15], b"EPC-0001234");
+    assert_eq!(bytes[15], 0);
+    assert_eq!(&bytes[16..19], &[25, 10, 9]);
+    assert_eq!(DeviceIdentity::decode(&bytes), Some(identity));
+}
+
+#[test]
+fn erased_or_short_block_is_unprogrammed() {
+    assert_eq!(DeviceIdentity::decode(&[0xFF; IDENTITY_LEN]), None);
+    assert_eq!(DeviceIdentity::decode(&[0x00; IDENTITY_LEN]), None);
+    assert_eq!(DeviceIdentity::decode(&identity().encode()[..12]), None);
+}
+
+#[test]
+fn corrupted_block_is_rejected() {
+    let mut bytes = identity().encode();
+    bytes[6] ^= 0x01;
+    assert_eq!(DeviceIdentity::decode(&bytes), None);
+
+    let mut bytes = identity().encode();
+    bytes[0] = b'X';
+    assert_eq!(DeviceIdentity::decode(&bytes), None);
+}
+
+#[test]
+fn invalid_fields_are_refused() {
+    let date = NaiveDate::from_ymd_opt(2025, 10, 9).unwrap();
+    for serial in ["", "EPC 1", "EPC-000123456"] {
+        assert!(matches!(
+            DeviceIdentity::new(serial, 1, date),
+            Err(PowerCliError::InvalidArguments { .. })
+        ));
+    }
+    let too_early = NaiveDate::from_ymd_opt(1999, 12, 31).unwrap();
+    assert!(DeviceIdentity::new("EPC-1", 1, too_early).is_err());
+}
+
+#[test]
+fn eeprom_dump_skips_headers() {
+    let dump = "📡 NFC EEPROM:\n0x1FB: 49 44 01 03\n0x1FC: 45 50 43 2D\nRead 2 blocks";
+    assert_eq!(
+        parse_eeprom_dump(dump),
+        [0x49, 0x44, 0x01, 0x03, 0x45, 0x50, 0x43, 0x2D]
+    );
+    assert_eq!(format_block(&[0x49, 0x44, 0x01, 0x0A]), "4944010A");
+}
+
+#[test]
+fn eeprom_commands_on_identity_blocks_are_recognised() {
+    assert!(touches_identity("nfc eeprom read 0x1FB 5"));
+    assert!(touches_identity("eeprom write 0x1FF 00112233"));
+    assert!(touches_identity("nfc eeprom read 0x1F0 12"));
+    assert!(touches_identity("nfc eeprom read last"));
+    assert!(!touches_identity("nfc eeprom read 0x1F0 11"));
+    assert!(!touches_identity("nfc eeprom write 0x200 00"));
+    assert!(!touches_identity("nfc status"));
+}
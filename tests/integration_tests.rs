@@ -27,6 +27,7 @@ async fn test_connection() {
     match result {
         Ok(()) => {
             println!("✅ Connection test passed");
+            #[allow(deprecated)]
             connection.disconnect().await;
         }
         Err(e) => {
@@ -0,0 +1,386 @@
+/*
+ * E-ink Power CLI - Mock Serial Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Connection and controller tests against the in-memory serial mock
+
+use eink_power_cli::error::PowerCliError;
+use eink_power_cli::power::control::{PowerController, PowerState};
+use eink_power_cli::power::gpio::{GpioConfigReport, GpioConfigStatus};
+use eink_power_cli::power::Rail;
+use eink_power_cli::serial::console::ConsoleQuirk;
+use eink_power_cli::serial::{Connection, MockSerial};
+use std::time::Duration;
+
+#[tokio::test]
+async fn send_command_strips_echo_and_prompt() {
+    let serial = MockSerial::builder()
+        .expect("version", "version\r\nPMU v2.1.0\r\nprod:~$ ")
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    let response = connection.send_command("version").await.unwrap();
+    assert_eq!(response, "PMU v2.1.0");
+    assert_eq!(connection.commands_sent(), ["version"]);
+}
+
+#[tokio::test]
+async fn controller_commands_follow_the_script() {
+    let serial = MockSerial::builder()
+        .expect("ping", "ping\r\npong\r\nprod:~$ ")
+        .expect("pm pmic on", "pm pmic on\r\nPMIC enabled\r\nprod:~$ ")
+        .build();
+    let mut controller = PowerController::new(Connection::mock(serial));
+
+    assert_eq!(controller.ping().await.unwrap(), "pong");
+    controller
+        .control_rail(Rail::Pmic, PowerState::On)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn scripted_error_surfaces_as_io_error() {
+    let serial = MockSerial::builder()
+        .expect_error("version", PowerCliError::NotConnected)
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    let err = connection.send_command("version").await.unwrap_err();
+    assert!(matches!(err, PowerCliError::Io(_)), "got {:?}", err);
+}
+
+#[tokio::test]
+async fn delayed_reply_past_timeout_times_out() {
+    let serial = MockSerial::builder()
+        .expect_delayed(
+            "version",
+            Duration::from_millis(50),
+            "PMU v2.1.0\r\nprod:~$ ",
+        )
+        .expect_delayed("ping", Duration::from_millis(1500), "pong\r\nprod:~$ ")
+        .build();
+    let mut connection = Connection::mock(serial);
+    connection.set_timeout(1);
+
+    assert_eq!(
+        connection.send_command("version").await.unwrap(),
+        "PMU v2.1.0"
+    );
+    let err = connection.send_command("ping").await.unwrap_err();
+    assert!(matches!(
+        err,
+        PowerCliError::Timeout {
+            timeout: 1,
+            bytes_received: 0,
+            ..
+        }
+    ));
+}
+
+#[tokio::test]
+async fn gpio_config_reads_back_the_mode() {
+    let serial = MockSerial::builder()
+        .expect("gpio config gpioa 5 input-pullup", "OK\r\nprod:~$ ")
+        .expect(
+            "gpio get gpioa 5",
+            "GPIO A5: 1 (INPUT, PULL-UP, HIGH)\r\nprod:~$ ",
+        )
+        .build();
+    let mut controller = PowerController::new(Connection::mock(serial));
+
+    let result = controller
+        .configure_gpio(&"PTA5".parse().unwrap(), "input-pullup")
+        .await
+        .unwrap();
+    assert_eq!(result.status, GpioConfigStatus::Verified);
+    assert_eq!(result.actual.pull.as_deref(), Some("UP"));
+}
+
+#[tokio::test]
+async fn gpio_config_ignored_by_firmware_is_a_mismatch() {
+    let serial = MockSerial::builder()
+        .expect("gpio config gpioa 5 output", "OK\r\nprod:~$ ")
+        .expect("gpio get gpioa 5", "GPIO A5: 0 (INPUT, LOW)\r\nprod:~$ ")
+        .expect("gpio config gpiob 3 output", "OK\r\nprod:~$ ")
+        .expect("gpio get gpiob 3", "GPIO B3: 0 (OUTPUT, LOW)\r\nprod:~$ ")
+        .build();
+    let mut controller = PowerController::new(Connection::mock(serial));
+
+    let mut results = Vec::new();
+    for pin in ["A5", "B3"] {
+        results.push(
+            controller
+                .configure_gpio(&pin.parse().unwrap(), "output")
+                .await
+                .unwrap(),
+        );
+    }
+    assert_eq!(results[0].status, GpioConfigStatus::Mismatch);
+    assert_eq!(results[0].actual.direction.as_deref(), Some("INPUT"));
+    assert_eq!(results[1].status, GpioConfigStatus::Verified);
+
+    let report = GpioConfigReport::new("output", results);
+    assert!(!report.success);
+    let err = report.mismatch_error().unwrap();
+    assert!(matches!(err, PowerCliError::GpioError { .. }));
+    assert!(err.to_string().contains("A5"), "{}", err);
+    assert!(!err.to_string().contains("B3"), "{}", err);
+}
+
+#[tokio::test]
+async fn gpio_config_without_direction_in_readback_is_unverified() {
+    let serial = MockSerial::builder()
+        .expect("gpio config gpioa 5 output", "OK\r\nprod:~$ ")
+        .expect("gpio get gpioa 5", "GPIO A5: 0\r\nprod:~$ ")
+        .build();
+    let mut controller = PowerController::new(Connection::mock(serial));
+
+    let result = controller
+        .configure_gpio(&"a5".parse().unwrap(), "output")
+        .await
+        .unwrap();
+    assert_eq!(result.status, GpioConfigStatus::Unverified);
+    assert!(GpioConfigReport::new("output", vec![result]).success);
+}
+
+#[tokio::test]
+async fn command_dropped_by_full_shell_buffer_is_sent_again() {
+    let serial = MockSerial::builder()
+        .expect(
+            "pm stats",
+            "pm stats\r\nshell: input buffer full\r\nprod:~$ ",
+        )
+        .expect("pm stats", "pm stats\r\nSleep cycles: 4\r\nprod:~$ ")
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    let response = connection.send_command("pm stats").await.unwrap();
+    assert_eq!(response, "Sleep cycles: 4");
+    assert_eq!(connection.commands_sent(), ["pm stats", "pm stats"]);
+}
+
+#[tokio::test]
+async fn missing_echo_after_echoed_command_is_sent_again() {
+    let serial = MockSerial::builder()
+        .expect("version", "version\r\nPMU v2.1.0\r\nprod:~$ ")
+        .expect("pm stats", "pm st\r\nError: unknown command\r\nprod:~$ ")
+        .expect("pm stats", "pm stats\r\nSleep cycles: 4\r\nprod:~$ ")
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    connection.send_command("version").await.unwrap();
+    let response = connection.send_command("pm stats").await.unwrap();
+    assert_eq!(response, "Sleep cycles: 4");
+}
+
+#[tokio::test]
+async fn stats_count_commands_bytes_and_latency() {
+    let serial = MockSerial::builder()
+        .expect("version", "version\r\nPMU v2.1.0\r\nprod:~$ ")
+        .expect("pm pmic on", "pm pmic on\r\nPMIC enabled\r\nprod:~$ ")
+        .build();
+    let mut connection = Connection::mock(serial);
+    connection.enable_response_cache(Duration::from_secs(2));
+
+    connection.send_command("version").await.unwrap();
+    connection.send_command("version").await.unwrap(); // from the cache
+    connection.send_command("pm pmic on").await.unwrap();
+
+    let stats = connection.stats();
+    assert_eq!(stats.commands, 2);
+    assert_eq!(
+        stats.bytes_sent,
+        ("version\n".len() + "pm pmic on\n".len()) as u64
+    );
+    assert_eq!(
+        stats.bytes_received,
+        ("version\r\nPMU v2.1.0\r\nprod:~$ ".len() + "pm pmic on\r\nPMIC enabled\r\nprod:~$ ".len())
+            as u64
+    );
+    assert_eq!(
+        stats.families.iter().collect::<Vec<_>>(),
+        [(&"pm".to_string(), &1), (&"version".to_string(), &1)]
+    );
+    assert_eq!(stats.timed_replies, 2);
+    assert!(stats.avg_latency_ms.is_some());
+    assert_eq!((stats.timeouts, stats.retries, stats.reconnects), (0, 0, 0));
+
+    connection.reset_stats();
+    assert!(connection.stats().is_empty());
+    assert_eq!(connection.stats().families.len(), 0);
+}
+
+#[tokio::test]
+async fn stats_count_timeouts() {
+    let serial = MockSerial::builder()
+        .expect_delayed("ping", Duration::from_millis(1500), "pong\r\nprod:~$ ")
+        .build();
+    let mut connection = Connection::mock(serial);
+    connection.set_timeout(1);
+    let mut controller = PowerController::new(connection);
+
+    assert!(controller.ping().await.is_err());
+    let stats = controller.connection_stats();
+    assert_eq!(stats.timeouts, 1);
+    assert_eq!(stats.commands, 1);
+    assert_eq!(stats.timed_replies, 0);
+    assert_eq!(stats.avg_latency_ms, None);
+}
+
+#[tokio::test]
+async fn stats_count_retries_of_dropped_commands() {
+    let serial = MockSerial::builder()
+        .expect(
+            "pm stats",
+            "pm stats\r\nshell: input buffer full\r\nprod:~$ ",
+        )
+        .expect("pm stats", "pm stats\r\nSleep cycles: 4\r\nprod:~$ ")
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    connection.send_command("pm stats").await.unwrap();
+    let stats = connection.stats();
+    assert_eq!(stats.retries, 1);
+    assert_eq!(stats.commands, 2);
+    assert_eq!(stats.families.get("pm"), Some(&2));
+    assert_eq!(stats.timeouts, 0);
+}
+
+/// `nfc debug` transcript whose dump contains `uart:` and prompt text
+fn nfc_debug_transcript() -> String {
+    let path =
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/nfc_debug.raw.txt");
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[tokio::test]
+async fn prompt_text_inside_a_reply_does_not_end_it() {
+    let transcript = nfc_debug_transcript();
+    // Deliver the reply in pieces that each end where a prompt search
+    // could stop: after `uart:` and after every `:~$ `
+    let mut cuts: Vec<usize> = ["uart:", ":~$ "]
+        .iter()
+        .flat_map(|marker| transcript.match_indices(marker).map(|(at, m)| at + m.len()))
+        .filter(|&cut| cut < transcript.len())
+        .collect();
+    cuts.sort();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    for (i, cut) in cuts.iter().chain([&transcript.len()]).enumerate() {
+        chunks.push((
+            Duration::from_millis(2 * i as u64),
+            &transcript[start..*cut],
+        ));
+        start = *cut;
+    }
+    assert!(chunks.len() >= 5, "{:?}", chunks);
+
+    let serial = MockSerial::builder()
+        .expect_chunks("nfc debug", &chunks)
+        .expect("ping", "ping\r\npong\r\nprod:~$ ")
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    let response = connection.send_command("nfc debug").await.unwrap();
+    let lines: Vec<&str> = transcript.lines().collect();
+    assert_eq!(response, lines[1..lines.len() - 1].join("\n").trim());
+    assert!(response.ends_with("Field: absent"));
+    assert!(response.contains("Last wake: uart:~$"));
+
+    // Nothing of the dump is left over for the next command
+    assert_eq!(connection.send_command("ping").await.unwrap(), "pong");
+    assert_eq!(
+        connection.stats().bytes_received,
+        (transcript.len() + "ping\r\npong\r\nprod:~$ ".len()) as u64
+    );
+}
+
+#[tokio::test]
+async fn reply_without_a_prompt_ends_when_the_line_goes_quiet() {
+    let serial = MockSerial::builder()
+        .expect_chunks(
+            "nfc debug",
+            &[
+                (Duration::ZERO, "NTAG5 debug dump\r\n"),
+                (Duration::from_millis(40), "  Field: absent\r\n"),
+            ],
+        )
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    let response = connection.send_command("nfc debug").await.unwrap();
+    assert_eq!(response, "NTAG5 debug dump\n  Field: absent");
+}
+
+#[test]
+#[should_panic(expected = "unconsumed expectations")]
+fn unconsumed_expectation_panics_on_drop() {
+    let serial = MockSerial::builder()
+        .expect("ping", "pong\r\nprod:~$ ")
+        .build();
+    assert_eq!(serial.remaining, ["ping"]);
+    drop(Connection::mock(serial));
+}
+
+#[tokio::test]
+async fn destructive_command_is_run_after_its_echo() {
+    let serial = MockSerial::builder()
+        .expect("ping", "ping\r\npong\r\nprod:~$ ")
+        .expect("pm wifi off", "pm wifi off\r\nWIFI power OFF\r\nprod:~$ ")
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    // The shell has shown it echoes
+    connection.send_command("ping").await.unwrap();
+    let response = connection.send_command("pm wifi off").await.unwrap();
+    assert_eq!(response, "WIFI power OFF");
+    // Typed, echoed, then Enter: the same bytes on the wire as one write
+    let stats = connection.stats();
+    assert_eq!(stats.bytes_sent, "ping\npm wifi off\n".len() as u64);
+    assert_eq!(
+        stats.bytes_received,
+        "ping\r\npong\r\nprod:~$ pm wifi off\r\nWIFI power OFF\r\nprod:~$ ".len() as u64
+    );
+}
+
+#[tokio::test]
+async fn destructive_command_on_a_shell_without_echo_is_sent_unchecked() {
+    let serial = MockSerial::builder()
+        .silent()
+        .expect("ping", "pong\r\nprod:~$ ")
+        .expect("pm wifi off", "WIFI power OFF\r\nprod:~$ ")
+        .build();
+    let mut connection = Connection::mock(serial);
+
+    connection.send_command("ping").await.unwrap();
+    let response = connection.send_command("pm wifi off").await.unwrap();
+    assert_eq!(response, "WIFI power OFF");
+    assert_eq!(connection.stats().retries, 0);
+}
+
+#[tokio::test]
+async fn destructive_command_runs_after_its_doubled_local_echo() {
+    let serial = MockSerial::builder()
+        .local_echo()
+        .expect("ping", "ping\r\nping\r\npong\r\npong\r\nprod:~$ ")
+        .expect(
+            "pm wifi off",
+            "pm wifi off\r\nWIFI power OFF\r\nWIFI power OFF\r\nprod:~$ ",
+        )
+        .build();
+    let mut connection = Connection::mock(serial);
+    connection.set_console_quirks(vec![ConsoleQuirk::DuplicatedLines]);
+
+    assert_eq!(connection.send_command("ping").await.unwrap(), "pong");
+    let response = connection.send_command("pm wifi off").await.unwrap();
+    assert_eq!(response, "WIFI power OFF");
+    // Typed once: the doubled echo was not taken for a corrupted one
+    assert_eq!(connection.stats().retries, 0);
+    assert_eq!(
+        connection.stats().bytes_sent,
+        "ping\npm wifi off\n".len() as u64
+    );
+}
@@ -0,0 +1,126 @@
+/*
+ * E-ink Power CLI - Output File Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `--output` writes the result only when the command succeeds
+
+use assert_cmd::Command;
+use std::path::Path;
+
+/// The CLI binary with isolated state and a device that does not exist
+fn cli(state_dir: &Path) -> Command {
+    let mut cmd = Command::cargo_bin("eink-power-cli").unwrap();
+    cmd.env("EINK_POWER_CLI_STATE_DIR", state_dir)
+        .args(["--device", "/dev/nonexistent"]);
+    cmd
+}
+
+#[test]
+fn failed_command_leaves_the_output_file_alone() {
+    let state = tempfile::tempdir().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("battery.json");
+    std::fs::write(&file, "previous result\n").unwrap();
+
+    let output = cli(state.path())
+        .args(["--format", "json", "--output"])
+        .arg(&file)
+        .args(["battery", "read"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Error:"));
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "previous result\n");
+    let names: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    // No temporary file left behind either
+    assert_eq!(names, ["battery.json"]);
+
+    let missing = dir.path().join("missing.json");
+    cli(state.path())
+        .arg("--output")
+        .arg(&missing)
+        .args(["battery", "read"])
+        .assert()
+        .failure();
+    assert!(!missing.exists());
+}
+
+#[test]
+fn output_on_error_writes_the_error_envelope() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("battery.json");
+
+    let output = cli(dir.path())
+        .args(["--format", "json", "--output-on-error", "--output"])
+        .arg(&file)
+        .args(["battery", "read"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+    assert_eq!(json["status"], "error");
+    assert_eq!(json["command"], "battery read");
+    assert!(json["data"]["error"]
+        .as_str()
+        .unwrap()
+        .contains("/dev/nonexistent"));
+}
+
+#[test]
+fn output_goes_to_the_file_instead_of_stdout() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("examples.json");
+
+    let output = cli(dir.path())
+        .args(["--format", "json", "--output"])
+        .arg(&file)
+        .args(["examples", "vlls1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(output.stdout.is_empty());
+    let json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&file).unwrap()).unwrap();
+    assert_eq!(json["data"][0]["command"], "pm sleep");
+}
+
+#[test]
+fn concurrent_invocations_write_their_own_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let filters = ["vlls1", "dfu", "gpio", "history"];
+
+    let runs: Vec<_> = filters
+        .iter()
+        .map(|filter| {
+            let dir = dir.path().to_path_buf();
+            let filter = filter.to_string();
+            std::thread::spawn(move || {
+                let file = dir.join(format!("{}.ndjson", filter));
+                for _ in 0..10 {
+                    cli(&dir)
+                        .args(["--format", "ndjson", "--output"])
+                        .arg(&file)
+                        .args(["examples", &filter])
+                        .assert()
+                        .success();
+                    let contents = std::fs::read_to_string(&file).unwrap();
+                    let json: serde_json::Value = serde_json::from_str(&contents).unwrap();
+                    assert_eq!(contents.lines().count(), 1, "{}", contents);
+                    for example in json["data"].as_array().unwrap() {
+                        let text = example.to_string().to_lowercase();
+                        assert!(text.contains(&filter), "{}: {}", filter, text);
+                    }
+                }
+            })
+        })
+        .collect();
+    for run in runs {
+        run.join().unwrap();
+    }
+}
@@ -0,0 +1,485 @@
+/*
+ * E-ink Power CLI - JSON Output Round-Trip Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Every `--format json` envelope must read back with `json::parse_output`
+
+use eink_power_cli::config::{Config, ConnectionConfig};
+use eink_power_cli::error::PowerCliError;
+use eink_power_cli::firmware::slots::{FirmwareImage, FirmwareInfo};
+use eink_power_cli::firmware::verify::SemVer;
+use eink_power_cli::firmware::FirmwareImageInfo;
+use eink_power_cli::history::HistoryEntry;
+use eink_power_cli::json::output::{SystemVerifyJson, OUTPUT_SCHEMA_VERSION};
+use eink_power_cli::json::schema::DEVICE_EXAMPLES;
+use eink_power_cli::json::{
+    parse_output, BatteryWatchSampleJson, BatteryWatchSummaryJson, CommandOutput, JsonResponse,
+    MonitorSampleJson, MonitorSummaryJson, ParsedPayload, ResponseParser,
+};
+use eink_power_cli::power::battery::ChargingState;
+use eink_power_cli::power::charger::{ChargerReport, ChargerState, ChargerStatus};
+use eink_power_cli::power::control::PowerStats;
+use eink_power_cli::power::factory_reset::{
+    FactoryResetReport, FactoryResetStatus, FactoryResetStep, FactoryResetStepResult,
+};
+use eink_power_cli::power::gpio::{
+    GpioConfigReport, GpioConfigResult, GpioMode, GpioScript, GpioScriptReport,
+};
+use eink_power_cli::power::identity::DeviceIdentity;
+use eink_power_cli::power::reboot::RebootDetector;
+use eink_power_cli::power::rtc::RtcCalibration;
+use eink_power_cli::power::sleep::VllsMode;
+use eink_power_cli::power::stats::{PowerCounters, PowerStatsDerived, PowerStatsReport};
+use eink_power_cli::power::timeref::{TimeRefReport, TimeReference};
+use eink_power_cli::power::wake::{SleepReport, WakeMask};
+use eink_power_cli::serial::connection::{BaudStage, BaudTransition, RoundTrip};
+use eink_power_cli::serial::{BaudChange, ConnectionStats, LatencyStats};
+use eink_power_cli::setup::{SetupCheck, SetupReport};
+use eink_power_cli::simulator::POWER_STATS_REPLY;
+use serde::Serialize;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Print `data` the way the CLI does and parse it back
+///
+/// Asserts that the typed output serializes to the original `data`.
+fn round_trip<T: Serialize>(command: &str, data: &T) -> CommandOutput {
+    let data = serde_json::to_value(data).unwrap();
+    let envelope = JsonResponse::success_with_raw(command, data.clone(), "raw");
+    let text = serde_json::to_string_pretty(&envelope).unwrap();
+
+    let output = parse_output(&text).unwrap_or_else(|e| panic!("{}: {}", command, e));
+    assert_eq!(serde_json::to_value(&output).unwrap(), data, "{}", command);
+    output
+}
+
+/// Round-trip the generic output path for a raw controller response
+fn round_trip_response(command: &str, payload: ParsedPayload, response: &str) -> CommandOutput {
+    round_trip(command, &CommandOutput::from_response(&payload, response))
+}
+
+#[test]
+fn parser_outputs_round_trip() {
+    let examples = DEVICE_EXAMPLES;
+    assert!(matches!(
+        round_trip_response(
+            "battery read",
+            ParsedPayload::Battery,
+            examples.battery_example
+        ),
+        CommandOutput::Battery(_)
+    ));
+    assert!(matches!(
+        round_trip_response(
+            "pm measure",
+            ParsedPayload::Measurement,
+            examples.battery_example
+        ),
+        CommandOutput::Measurement(_)
+    ));
+    assert!(matches!(
+        round_trip_response(
+            "system info",
+            ParsedPayload::System,
+            examples.system_info_example
+        ),
+        CommandOutput::SystemInfo(_)
+    ));
+    assert!(matches!(
+        round_trip_response("nfc status", ParsedPayload::Nfc, examples.nfc_example),
+        CommandOutput::Nfc(_)
+    ));
+    assert!(matches!(
+        round_trip_response(
+            "ltc2959 status",
+            ParsedPayload::Ltc2959,
+            examples.ltc2959_example
+        ),
+        CommandOutput::Ltc2959(_)
+    ));
+    let gpio_a5 = ParsedPayload::Gpio {
+        port: "A".to_string(),
+        pin: 5,
+    };
+    match round_trip_response("gpio get", gpio_a5, examples.gpio_example) {
+        CommandOutput::Gpio(gpio) => assert_eq!((gpio.port.as_str(), gpio.pin), ("A", 5)),
+        other => panic!("unexpected output {:?}", other),
+    }
+    // An `ltc2959 status` reply, not a battery measurement
+    assert!(matches!(
+        round_trip_response(
+            "battery status",
+            ParsedPayload::Ltc2959,
+            examples.ltc2959_example
+        ),
+        CommandOutput::Ltc2959(_)
+    ));
+    let stats =
+        "Sleep cycles: 42\nWake cycles: 41\nLTC2959: Smart Sleep\nUptime: 0:01:07 (67427 ms)";
+    match round_trip_response("pm stats", ParsedPayload::PowerStats, stats) {
+        CommandOutput::PowerStats(stats) => assert_eq!(stats.sleep_cycles, Some(42)),
+        other => panic!("unexpected output {:?}", other),
+    }
+    assert!(matches!(
+        round_trip_response("rtc status", ParsedPayload::Rtc, examples.rtc_example),
+        CommandOutput::RtcStatus(_)
+    ));
+    assert!(matches!(
+        round_trip_response(
+            "pm defaults",
+            ParsedPayload::RailDefaults,
+            "PMIC: ON\nWiFi: OFF\nDisplay: ON"
+        ),
+        CommandOutput::RailDefaults(_)
+    ));
+    match round_trip_response("rtc get", ParsedPayload::RtcCounter, "12345") {
+        CommandOutput::RtcCounter(counter) => assert_eq!(counter.counter, Some(12345)),
+        other => panic!("unexpected output {:?}", other),
+    }
+    match round_trip_response("ping", ParsedPayload::Raw, "pong") {
+        CommandOutput::Sectioned(sectioned) => {
+            assert_eq!(sectioned.sections.len(), 1);
+            assert_eq!(sectioned.sections[0].lines, ["pong"]);
+        }
+        other => panic!("unexpected output {:?}", other),
+    }
+}
+
+#[test]
+fn command_specific_outputs_round_trip() {
+    let health = ResponseParser::parse_battery_health(DEVICE_EXAMPLES.battery_health_example);
+    assert!(matches!(
+        round_trip("pm battery_check", &health),
+        CommandOutput::BatteryHealth(_)
+    ));
+
+    let sample = MonitorSampleJson {
+        measurement: ResponseParser::parse_measurement(DEVICE_EXAMPLES.battery_example),
+        charging_state: Some(ChargingState::Charging),
+        charging: true,
+        since: Some(chrono::Utc::now()),
+        charger: Some(ChargerStatus::resolve(
+            &[ChargerReport {
+                source: "ltc2959 status",
+                state: ChargerState::Trickle,
+            }],
+            Some(ChargingState::Idle),
+        )),
+    };
+    match round_trip("monitor", &sample) {
+        CommandOutput::MonitorSample(read) => assert_eq!(read, sample),
+        other => panic!("unexpected output {:?}", other),
+    }
+    let summary = MonitorSummaryJson {
+        samples: 12,
+        charging_transitions: 2,
+        final_state: Some(ChargingState::Idle),
+        reboots: 1,
+        link: ConnectionStats::starting_now(),
+        sample_format: Some("labeled".to_string()),
+        unparsed_lines: 3,
+        power: None,
+    };
+    assert!(matches!(
+        round_trip("monitor summary", &summary),
+        CommandOutput::MonitorSummary(_)
+    ));
+    let stats = PowerStats::parse(POWER_STATS_REPLY);
+    let summary = MonitorSummaryJson {
+        power: Some(PowerStatsDerived::since_boot(&PowerCounters::new(
+            &stats, 3_600_000,
+        ))),
+        ..summary
+    };
+    match round_trip("monitor summary", &summary) {
+        CommandOutput::MonitorSummary(read) => assert_eq!(read, summary),
+        other => panic!("unexpected output {:?}", other),
+    }
+    assert!(matches!(
+        round_trip(
+            "power stats",
+            &PowerStatsReport::new(stats, Some(3_600_000))
+        ),
+        CommandOutput::PowerStatsReport(_)
+    ));
+    let watch = BatteryWatchSampleJson::new(
+        ResponseParser::parse_battery_response(DEVICE_EXAMPLES.battery_example),
+        Some(4900),
+    );
+    match round_trip("battery watch", &watch) {
+        CommandOutput::BatteryWatch(read) => assert_eq!(read, watch),
+        other => panic!("unexpected output {:?}", other),
+    }
+    let watch_summary = BatteryWatchSummaryJson {
+        samples: 3,
+        voltage_min_mv: Some(3850),
+        ..BatteryWatchSummaryJson::default()
+    };
+    match round_trip("battery watch summary", &watch_summary) {
+        CommandOutput::BatteryWatchSummary(read) => assert_eq!(read, watch_summary),
+        other => panic!("unexpected output {:?}", other),
+    }
+    let time_ref = TimeRefReport::new(
+        None,
+        TimeReference::from_exchange(
+            3_600_000,
+            1,
+            RoundTrip {
+                sent_at: chrono::Utc::now(),
+                first_byte: Duration::from_millis(12),
+            },
+        ),
+        Some(67_427),
+    );
+    match round_trip("system time-ref", &time_ref) {
+        CommandOutput::TimeRef(read) => assert_eq!(read, time_ref),
+        other => panic!("unexpected output {:?}", other),
+    }
+    let config = Config {
+        profile: Some("bench".into()),
+        connection: ConnectionConfig {
+            device: Some("/dev/ttyUSB0".into()),
+            ..ConnectionConfig::default()
+        },
+        ..Config::default()
+    };
+    let setup = SetupReport {
+        config_path: "/home/user/.config/eink-power-cli/config.toml".into(),
+        config,
+        checks: vec![SetupCheck {
+            name: "Serial link".into(),
+            passed: true,
+            detail: "PMU answered in 12 ms".into(),
+        }],
+    };
+    match round_trip("setup", &setup) {
+        CommandOutput::Setup(read) => assert_eq!(read, setup),
+        other => panic!("unexpected output {:?}", other),
+    }
+    let script: GpioScript = "a0=1,5ms,a1=0".parse().unwrap();
+    let batch = GpioScriptReport::from_batch(&script, "A0=1 OK +0us\nA1=0 OK +5040us");
+    match round_trip("gpio script", &batch) {
+        CommandOutput::GpioScript(read) => assert_eq!(read, batch),
+        other => panic!("unexpected output {:?}", other),
+    }
+    let mut link = ConnectionStats::starting_now();
+    link.record_command("pm stats");
+    link.record_latency(std::time::Duration::from_micros(2500));
+    match round_trip("stats", &link) {
+        CommandOutput::LinkStats(read) => assert_eq!(read, link),
+        other => panic!("unexpected output {:?}", other),
+    }
+    let mut detector = RebootDetector::new();
+    detector.observe(Some(90_000), false);
+    let reboot = detector.observe(Some(1_000), false).unwrap();
+    match round_trip("monitor reboot", &reboot) {
+        CommandOutput::MonitorReboot(read) => assert_eq!(read, reboot),
+        other => panic!("unexpected output {:?}", other),
+    }
+
+    let verify = SystemVerifyJson {
+        passed: false,
+        violations: vec!["Build type is Debug, expected Release".to_string()],
+        system: ResponseParser::parse_system_info(DEVICE_EXAMPLES.system_info_example),
+    };
+    assert!(matches!(
+        round_trip("system verify", &verify),
+        CommandOutput::SystemVerify(_)
+    ));
+
+    let tag = ResponseParser::parse_nfc_tag_info(
+        "Tag Type: ISO15693\nUID: E0 04 01 50 8A 3B 2C 11\nNDEF: No",
+    )
+    .unwrap();
+    assert!(matches!(
+        round_trip("nfc tag", &tag),
+        CommandOutput::NfcTag(_)
+    ));
+
+    let gpio = GpioConfigResult::check(
+        GpioMode::parse("output"),
+        ResponseParser::parse_gpio_response("GPIO A5: 0 (INPUT, LOW)", "A", 5),
+        "OK".to_string(),
+    );
+    assert!(matches!(
+        round_trip("gpio config", &GpioConfigReport::new("output", vec![gpio])),
+        CommandOutput::GpioConfig(report) if !report.success
+    ));
+
+    for command in ["rtc calibrate", "rtc calibration"] {
+        let calibration = RtcCalibration::for_ppm(-12);
+        assert!(matches!(
+            round_trip(command, &calibration),
+            CommandOutput::RtcCalibration(_)
+        ));
+    }
+
+    let latency = LatencyStats::from_samples(&[
+        Duration::from_millis(12),
+        Duration::from_micros(15_250),
+        Duration::from_millis(9),
+    ])
+    .unwrap();
+    assert!(matches!(
+        round_trip("latency", &latency),
+        CommandOutput::Latency(_)
+    ));
+}
+
+#[test]
+fn report_outputs_round_trip() {
+    let change = BaudChange {
+        success: false,
+        original_rate: 115200,
+        requested_rate: 921600,
+        persist: true,
+        link_rate: None,
+        transitions: vec![BaudTransition {
+            stage: BaudStage::Switched,
+            from: 115200,
+            to: 921600,
+            ok: false,
+            error: Some("Command timeout after 1s".to_string()),
+        }],
+    };
+    assert!(matches!(
+        round_trip("system set-baud", &change),
+        CommandOutput::BaudChange(_)
+    ));
+
+    let report = FactoryResetReport {
+        success: true,
+        steps: vec![
+            FactoryResetStepResult {
+                step: FactoryResetStep::EraseDefaults,
+                status: FactoryResetStatus::Ok,
+                detail: Some("Defaults erased".to_string()),
+            },
+            FactoryResetStepResult {
+                step: FactoryResetStep::RtcConfig,
+                status: FactoryResetStatus::Skipped,
+                detail: None,
+            },
+        ],
+    };
+    assert!(matches!(
+        round_trip("system factory-reset", &report),
+        CommandOutput::FactoryReset(_)
+    ));
+
+    let image = FirmwareImageInfo {
+        magic_valid: true,
+        version: SemVer {
+            major: 2,
+            minor: 2,
+            revision: 0,
+            build_num: 298,
+        },
+        image_size: 41_000,
+        header_size: 32,
+        load_addr: 0,
+        flags: 0,
+        non_bootable: false,
+        has_signature: true,
+    };
+    assert!(matches!(
+        round_trip("firmware analyze", &image),
+        CommandOutput::FirmwareImage(_)
+    ));
+
+    let history = vec![HistoryEntry::new(
+        &["version".to_string()],
+        Some("2.2.0"),
+        0,
+        None,
+    )];
+    assert!(matches!(
+        round_trip("history", &history),
+        CommandOutput::History(entries) if entries.len() == 1
+    ));
+
+    let configured = WakeMask::from_bits(0x1F);
+    let sleep = SleepReport {
+        command: "sleep --vlls0".to_string(),
+        vlls_mode: Some(VllsMode::Vlls0),
+        duration_s: None,
+        effective_wake: Some(configured.effective_in(Some(VllsMode::Vlls0))),
+        configured_wake: Some(configured.clone()),
+        response: "Entering low power mode".to_string(),
+    };
+    assert!(matches!(
+        round_trip("pm sleep", &sleep),
+        CommandOutput::Sleep(_)
+    ));
+    assert!(matches!(
+        round_trip("pm wake-sources", &Some(configured)),
+        CommandOutput::WakeSources(Some(_))
+    ));
+
+    let identity = DeviceIdentity::new(
+        "EPC-0042",
+        3,
+        chrono::NaiveDate::from_ymd_opt(2025, 10, 9).unwrap(),
+    )
+    .unwrap();
+    assert!(matches!(
+        round_trip("identity write", &Some(identity)),
+        CommandOutput::Identity(Some(_))
+    ));
+    assert!(matches!(
+        round_trip("identity show", &None::<DeviceIdentity>),
+        CommandOutput::Identity(None)
+    ));
+
+    let slot = FirmwareImage {
+        version: Some("2.2.0.298".to_string()),
+        bootable: Some(true),
+        active: true,
+        confirmed: true,
+        hash: Some("3b4c5d6e7f".to_string()),
+        empty: false,
+        ..FirmwareImage::empty(0, 0)
+    };
+    assert!(matches!(
+        round_trip("firmware info", &FirmwareInfo::new(None, Some(vec![slot]))),
+        CommandOutput::FirmwareInfo(_)
+    ));
+
+    let state = serde_json::json!({"directory": "/var/lib/eink-power-cli", "files": []});
+    assert!(matches!(
+        round_trip("state show", &state),
+        CommandOutput::Untyped(_)
+    ));
+}
+
+#[test]
+fn error_envelope_reads_back() {
+    let text = serde_json::to_string(&JsonResponse::error("version", "timeout")).unwrap();
+    match parse_output(&text).unwrap() {
+        CommandOutput::Error(error) => assert_eq!(error.error, "timeout"),
+        other => panic!("unexpected output {:?}", other),
+    }
+}
+
+#[test]
+fn schema_version_mismatch_is_rejected() {
+    let envelope = JsonResponse::success("version", serde_json::json!({}));
+    let mut value = serde_json::to_value(&envelope).unwrap();
+    assert_eq!(value["schema_version"], OUTPUT_SCHEMA_VERSION);
+
+    value["schema_version"] = Value::from(OUTPUT_SCHEMA_VERSION + 1);
+    let err = parse_output(&value.to_string()).unwrap_err();
+    assert!(matches!(err, PowerCliError::SchemaVersion { .. }));
+    assert!(
+        err.to_string()
+            .contains(&format!("schema version {}", OUTPUT_SCHEMA_VERSION + 1)),
+        "{}",
+        err
+    );
+
+    value.as_object_mut().unwrap().remove("schema_version");
+    let err = parse_output(&value.to_string()).unwrap_err();
+    assert!(err.to_string().contains("schema version none"), "{}", err);
+}
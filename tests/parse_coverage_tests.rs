@@ -0,0 +1,149 @@
+/*
+ * E-ink Power CLI - Parse Coverage Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! The transcript corpus in `tests/fixtures/transcripts` parsed at least as
+//! well as its baseline records, and regressions reported when it is not
+//!
+//! After adding transcripts, record the new baseline with
+//! `eink-power-cli parse-check tests/fixtures/transcripts --update-baseline`.
+
+use eink_power_cli::json::coverage::{
+    self, CoverageBaseline, CoverageRegression, CoverageReport, BASELINE_FILE,
+};
+use eink_power_cli::json::{parse_output, CommandOutput};
+use std::path::{Path, PathBuf};
+
+fn corpus() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/transcripts")
+}
+
+/// A copy of the corpus and its baseline to change
+fn corpus_copy() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for entry in std::fs::read_dir(corpus()).unwrap() {
+        let path = entry.unwrap().path();
+        std::fs::copy(&path, dir.path().join(path.file_name().unwrap())).unwrap();
+    }
+    dir
+}
+
+fn checked(dir: &Path) -> CoverageReport {
+    let path = dir.join(BASELINE_FILE);
+    let baseline = CoverageBaseline::load(&path).unwrap().expect("baseline");
+    let mut report = CoverageReport::scan(dir).unwrap();
+    report.check(&baseline, &path);
+    report
+}
+
+#[test]
+fn corpus_meets_its_baseline() {
+    let report = checked(&corpus());
+    assert!(report.passed, "{:?}", report.failures());
+    assert!(report.unmatched.is_empty(), "{:?}", report.unmatched);
+    // Every parser has a transcript
+    assert_eq!(report.commands.len(), coverage::PARSERS.len());
+}
+
+#[test]
+fn file_names_pick_the_longest_command() {
+    let command = |name: &str| coverage::parser_for(name).map(|(command, _)| command);
+    assert_eq!(command("ltc2959_status.fw23"), Some("ltc2959 status"));
+    assert_eq!(command("pm_battery_check_low"), Some("pm battery_check"));
+    assert_eq!(command("pm_stats"), Some("pm stats"));
+    assert_eq!(command("pm_statsx"), None);
+    assert_eq!(command("ltc2959"), None);
+}
+
+#[test]
+fn reworded_reply_is_a_regression() {
+    let dir = corpus_copy();
+    // A release that renamed the wake source line
+    let transcript = dir.path().join("rtc_status.fw22.txt");
+    let text = std::fs::read_to_string(&transcript).unwrap();
+    std::fs::write(&transcript, text.replace("Last Wake Source", "Woken by")).unwrap();
+
+    let report = checked(dir.path());
+    assert!(!report.passed);
+    assert!(report.regressions.contains(&CoverageRegression {
+        command: "rtc status".to_string(),
+        field: Some("last_wake_source".to_string()),
+        baseline: 1.0,
+        current: Some(0.0),
+    }));
+    assert!(report
+        .failures()
+        .contains(&"rtc status last_wake_source at 0% (baseline 100%)".to_string()));
+}
+
+#[test]
+fn command_missing_from_the_corpus_is_a_regression() {
+    let dir = corpus_copy();
+    std::fs::remove_file(dir.path().join("gpio_get.fw22.txt")).unwrap();
+
+    let report = checked(dir.path());
+    assert_eq!(
+        report.regressions,
+        [CoverageRegression {
+            command: "gpio get".to_string(),
+            field: None,
+            baseline: 1.0,
+            current: None,
+        }]
+    );
+}
+
+#[test]
+fn parser_panics_are_caught() {
+    let fields = coverage::populated_fields(|_| panic!("unexpected line"), "Voltage: ?");
+    assert_eq!(fields, Err("unexpected line".to_string()));
+
+    let fields = coverage::populated_fields(
+        |_| serde_json::json!({"a": 1, "b": {"c": null}}),
+        "Voltage: 1 mV",
+    )
+    .unwrap();
+    assert_eq!(fields.get("a"), Some(&true));
+    assert_eq!(fields.get("b.c"), Some(&false));
+}
+
+#[test]
+fn binary_parse_check_records_and_checks_a_baseline() {
+    let corpus = corpus_copy();
+    std::fs::remove_file(corpus.path().join(BASELINE_FILE)).unwrap();
+    let state = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| {
+        assert_cmd::Command::cargo_bin("eink-power-cli")
+            .unwrap()
+            .env("EINK_POWER_CLI_STATE_DIR", state.path())
+            .args(["--device", "/dev/nonexistent", "--format", "json"])
+            .arg("parse-check")
+            .arg(corpus.path())
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--update-baseline"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert!(corpus.path().join(BASELINE_FILE).exists());
+
+    let output = run(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    match parse_output(&String::from_utf8_lossy(&output.stdout)).unwrap() {
+        CommandOutput::ParseCheck(report) => {
+            assert!(report.passed);
+            assert_eq!(report.transcripts(), 13);
+        }
+        other => panic!("unexpected output {:?}", other),
+    }
+
+    let transcript = corpus.path().join("pm_stats.fw22.txt");
+    std::fs::write(&transcript, "Power statistics unavailable\n").unwrap();
+    let output = run(&[]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Parse coverage check failed"), "{}", stderr);
+}
@@ -0,0 +1,787 @@
+/*
+ * E-ink Power CLI - Response Parser Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Fixture-based tests for the PMU response parsers
+//!
+//! These tests run without hardware and exercise the parsers against
+//! response text captured from (or modelled on) real firmware builds.
+
+use eink_power_cli::json::{
+    parse_decimal, parse_integer, write_ndjson, BuildType, MeasurementJson, ResponseParser,
+};
+
+/// LTC2959 readout from a firmware build running under a C locale
+const BATTERY_C_LOCALE: &str = "📊 LTC2959 Measurements:
+   🔋 Voltage: 6088 mV
+   ⚡ Current: -170 mA
+   🔋 Charge: 1250 mAh
+   ⚡ Power: -1040 mW
+   🌡️  Temperature: 23.5°C";
+
+/// The same readout from a build whose libc locale groups thousands with `,`
+const BATTERY_GROUPED: &str = "📊 LTC2959 Measurements:
+   🔋 Voltage: 6,088 mV
+   ⚡ Current: -1,170 mA
+   🔋 Charge: 1 250 mAh
+   ⚡ Power: -7,123 mW
+   🌡️  Temperature: 23,5 °C";
+
+/// A build with a decimal comma mixed with grouping dots
+const BATTERY_DECIMAL_COMMA: &str = "Voltage: 3.850,4 mV
+Current: -170,6 mA
+Charge: 2.450 mAh
+Power: -1.040 mW";
+
+#[test]
+fn test_parse_integer_locales() {
+    assert_eq!(parse_integer("6088"), Some(6088));
+    assert_eq!(parse_integer("6,088"), Some(6088));
+    assert_eq!(parse_integer("6 088"), Some(6088));
+    assert_eq!(parse_integer("6\u{00A0}088"), Some(6088));
+    assert_eq!(parse_integer("1.234.567"), Some(1_234_567));
+    assert_eq!(parse_integer("1.234,5"), Some(1235));
+    assert_eq!(parse_integer("1,234.5"), Some(1235));
+    assert_eq!(parse_integer("-170,6"), Some(-171));
+    assert_eq!(parse_integer("+42"), Some(42));
+    assert_eq!(parse_integer(""), None);
+    assert_eq!(parse_integer("abc"), None);
+}
+
+#[test]
+fn test_parse_decimal_locales() {
+    assert_eq!(parse_decimal("3.85"), Some(3.85));
+    assert_eq!(parse_decimal("3,85"), Some(3.85));
+    assert_eq!(parse_decimal("-0,142"), Some(-0.142));
+    assert_eq!(parse_decimal("1.234,5"), Some(1234.5));
+    assert_eq!(parse_decimal("1,234.5"), Some(1234.5));
+    assert_eq!(parse_decimal("7"), Some(7.0));
+    assert_eq!(parse_decimal("1,2,3"), Some(123.0));
+    assert_eq!(parse_decimal("-"), None);
+}
+
+#[test]
+fn test_battery_response_c_locale() {
+    let battery = ResponseParser::parse_battery_response(BATTERY_C_LOCALE);
+    assert_eq!(battery.voltage_mv, Some(6088));
+    assert_eq!(battery.current_ma, Some(-170));
+    assert_eq!(battery.charge_mah, Some(1250));
+    assert_eq!(battery.power_mw, Some(-1040));
+    assert_eq!(battery.temperature_c, Some(23.5));
+}
+
+#[test]
+fn test_battery_response_grouped_thousands() {
+    let battery = ResponseParser::parse_battery_response(BATTERY_GROUPED);
+    assert_eq!(battery.voltage_mv, Some(6088));
+    assert_eq!(battery.current_ma, Some(-1170));
+    assert_eq!(battery.charge_mah, Some(1250));
+    assert_eq!(battery.power_mw, Some(-7123));
+    assert_eq!(battery.temperature_c, Some(23.5));
+}
+
+#[test]
+fn test_space_grouping_needs_three_digit_groups() {
+    let battery = ResponseParser::parse_battery_response("Voltage: 12 3456 mV\nCharge: 1 250 mAh");
+    assert_eq!(battery.voltage_mv, None);
+    assert_eq!(battery.charge_mah, Some(1250));
+
+    let rtc = ResponseParser::parse_rtc_status("Internal RTC: running, Wake events: 12 3456");
+    assert_eq!(rtc.internal_rtc.wake_events, Some(12));
+}
+
+#[test]
+fn test_battery_response_decimal_comma() {
+    let battery = ResponseParser::parse_battery_response(BATTERY_DECIMAL_COMMA);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.current_ma, Some(-171));
+    assert_eq!(battery.charge_mah, Some(2450));
+    assert_eq!(battery.power_mw, Some(-1040));
+}
+
+#[test]
+fn test_ltc2959_status_mixed_formats() {
+    let response = "LTC2959 Status Register: 0x01
+ADC Mode: Smart Sleep
+Coulomb Counter: Enabled
+Voltage: 7,412 mV
+Current: -142 mA";
+    let ltc = ResponseParser::parse_ltc2959_status(response);
+    assert_eq!(ltc.status_register.as_deref(), Some("0x01"));
+    assert_eq!(ltc.adc_mode.as_deref(), Some("Smart Sleep"));
+    assert_eq!(ltc.voltage_mv, Some(7412));
+    assert_eq!(ltc.current_ma, Some(-142));
+}
+
+/// Firmware 2.2.x and earlier print milli-units
+const UNITS_MILLI: &str = "Voltage: 3850 mV
+Current: -170 mA
+Charge: 2450 mAh
+Power: -654 mW";
+
+/// Firmware 2.5.x prints base units with decimals
+const UNITS_BASE: &str = "Voltage: 3.85 V
+Current: -0.17 A
+Charge: 2.45 Ah
+Power: -0.654 W";
+
+/// Deep-sleep diagnostics print sub-milliamp currents in micro-units
+const UNITS_MICRO: &str = "Voltage: 3850000 µV
+Current: -850 µA
+Charge: 2450000 uAh
+Power: -3270 μW";
+
+#[test]
+fn test_battery_units_milli() {
+    let battery = ResponseParser::parse_battery_response(UNITS_MILLI);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.current_ma, Some(-170));
+    assert_eq!(battery.charge_mah, Some(2450));
+    assert_eq!(battery.power_mw, Some(-654));
+}
+
+#[test]
+fn test_battery_units_base() {
+    let battery = ResponseParser::parse_battery_response(UNITS_BASE);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.current_ma, Some(-170));
+    assert_eq!(battery.charge_mah, Some(2450));
+    assert_eq!(battery.power_mw, Some(-654));
+}
+
+#[test]
+fn test_battery_units_micro() {
+    let battery = ResponseParser::parse_battery_response(UNITS_MICRO);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.current_ma, Some(-1));
+    assert_eq!(battery.charge_mah, Some(2450));
+    assert_eq!(battery.power_mw, Some(-3));
+}
+
+#[test]
+fn test_battery_units_localized_base() {
+    let battery = ResponseParser::parse_battery_response("Voltage: 3,85 V\nCurrent: -0,142 A");
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.current_ma, Some(-142));
+}
+
+#[test]
+fn test_battery_units_rounding() {
+    let battery = ResponseParser::parse_battery_response("Voltage: 3.8505 V\nCurrent: -0.0005 A");
+    assert_eq!(battery.voltage_mv, Some(3851));
+    assert_eq!(battery.current_ma, Some(-1));
+}
+
+#[test]
+fn test_battery_units_duplicate_prefers_precise() {
+    // Summary line in volts followed by the detailed millivolt reading
+    let battery = ResponseParser::parse_battery_response("Voltage: 3.9 V\nVoltage: 3872 mV");
+    assert_eq!(battery.voltage_mv, Some(3872));
+
+    // A high-resolution volt reading beats a whole-millivolt one
+    let battery = ResponseParser::parse_battery_response("Voltage: 3872 mV\nVoltage: 3.8724 V");
+    assert_eq!(battery.voltage_mv, Some(3872));
+
+    // Conflicting readings still resolve to the finest unit
+    let battery = ResponseParser::parse_battery_response("Current: -1 A\nCurrent: -142 mA");
+    assert_eq!(battery.current_ma, Some(-142));
+}
+
+#[test]
+fn test_charge_unit_not_confused_with_current() {
+    let battery = ResponseParser::parse_battery_response("Current: 12 mA\nCharge: 1.2 Ah");
+    assert_eq!(battery.current_ma, Some(12));
+    assert_eq!(battery.charge_mah, Some(1200));
+}
+
+#[test]
+fn test_ltc2959_status_base_units() {
+    let ltc = ResponseParser::parse_ltc2959_status(
+        "ADC Mode: Continuous\nVoltage: 7.412 V\nPower: -1.05 W",
+    );
+    assert_eq!(ltc.voltage_mv, Some(7412));
+    assert_eq!(ltc.power_mw, Some(-1050));
+}
+
+#[test]
+fn test_rtc_response_parsing() {
+    use eink_power_cli::serial::protocol::RtcResponse;
+
+    let rtc = RtcResponse::parse("Internal RTC counter: 67427");
+    assert_eq!(rtc.counter, Some(67427));
+    assert_eq!(rtc.alarm_set, None);
+
+    let rtc = RtcResponse::parse("67427");
+    assert_eq!(rtc.counter, Some(67427));
+
+    let rtc = RtcResponse::parse("Alarm: Not set\nInterrupt Action: WAKE");
+    assert_eq!(rtc.counter, None);
+    assert_eq!(rtc.alarm_set, Some(false));
+    assert_eq!(rtc.config_action.as_deref(), Some("WAKE"));
+
+    let rtc = RtcResponse::parse("⏰ Alarm: Enabled");
+    assert_eq!(rtc.alarm_set, Some(true));
+}
+
+#[test]
+fn test_rail_defaults_saved() {
+    let response = "⚙️ Power Rail Defaults (saved in flash):
+   PMIC_EN: ON
+   WIFI_EN: OFF
+   DISP_EN: ON";
+    let defaults = ResponseParser::parse_rail_defaults(response);
+    assert_eq!(defaults.pmic, Some(true));
+    assert_eq!(defaults.wifi, Some(false));
+    assert_eq!(defaults.disp, Some(true));
+    assert!(defaults.saved_in_flash);
+}
+
+#[test]
+fn test_rail_defaults_unsaved_and_factory() {
+    let unsaved = "Power rail defaults (not saved):\n  PMIC: on\n  WiFi: on\n  Display: off";
+    let defaults = ResponseParser::parse_rail_defaults(unsaved);
+    assert_eq!(defaults.pmic, Some(true));
+    assert_eq!(defaults.wifi, Some(true));
+    assert_eq!(defaults.disp, Some(false));
+    assert!(!defaults.saved_in_flash);
+
+    let factory = "Power rail defaults: factory\nPMIC: ON\nWiFi: ON\nDISP: ON";
+    assert!(!ResponseParser::parse_rail_defaults(factory).saved_in_flash);
+}
+
+#[test]
+fn test_rail_defaults_reordered_listing() {
+    // Firmware 2.4 lists the display rail first and uses enabled/disabled
+    let response = "Defaults stored in flash\nDISP default: disabled\nPMIC default: enabled\nWIFI default: enabled";
+    let defaults = ResponseParser::parse_rail_defaults(response);
+    assert_eq!(defaults.pmic, Some(true));
+    assert_eq!(defaults.wifi, Some(true));
+    assert_eq!(defaults.disp, Some(false));
+    assert!(defaults.saved_in_flash);
+
+    let expected = ResponseParser::parse_rail_defaults("PMIC: ON\nWiFi: OFF\nDISP: OFF");
+    assert_eq!(
+        defaults.mismatches(&expected),
+        vec!["WiFi default is ON, expected OFF".to_string()]
+    );
+}
+
+#[test]
+fn test_rail_state() {
+    assert_eq!(ResponseParser::parse_rail_state("PMIC: ON"), Some(true));
+    assert_eq!(
+        ResponseParser::parse_rail_state("WiFi: off\r\n"),
+        Some(false)
+    );
+    assert_eq!(
+        ResponseParser::parse_rail_state("Display power status: enabled"),
+        Some(true)
+    );
+    assert_eq!(ResponseParser::parse_rail_state("DISP power ON"), None);
+    assert_eq!(ResponseParser::parse_rail_state("Error: busy"), None);
+}
+
+#[test]
+fn test_nfc_tag_info_iso14443a() {
+    let response = "🏷️ NFC Tag Detected:
+   Tag Type: ISO14443A
+   UID: 04:AB:CD:EF:12:34:56
+   NDEF: Yes
+   Memory: 888 bytes";
+    let tag = ResponseParser::parse_nfc_tag_info(response).expect("tag should parse");
+    assert_eq!(tag.tag_type, "ISO14443A");
+    assert_eq!(tag.uid, "04:AB:CD:EF:12:34:56");
+    assert_eq!(
+        tag.uid_bytes,
+        vec![0x04, 0xAB, 0xCD, 0xEF, 0x12, 0x34, 0x56]
+    );
+    assert!(tag.ndef_capable);
+    assert_eq!(tag.memory_size, Some(888));
+    assert_eq!(tag.uid_raw(), "04ABCDEF123456");
+    assert_eq!(tag.uid_decimal(), "1314800874435670");
+}
+
+#[test]
+fn test_nfc_tag_info_iso15693() {
+    let response = "Tag Type: ISO15693
+UID: E0 04 01 50 8A 3B 2C 11
+NDEF: No";
+    let tag = ResponseParser::parse_nfc_tag_info(response).expect("tag should parse");
+    assert_eq!(tag.tag_type, "ISO15693");
+    assert_eq!(tag.uid_bytes.len(), 8);
+    assert_eq!(tag.uid, "E0:04:01:50:8A:3B:2C:11");
+    assert!(!tag.ndef_capable);
+    assert_eq!(tag.memory_size, None);
+}
+
+#[test]
+fn test_nfc_tag_info_no_tag() {
+    assert!(ResponseParser::parse_nfc_tag_info("No tag in field").is_none());
+}
+
+/// `pm measure` while the LTC2959 ADC is in smart-sleep
+const MEASURE_SMART_SLEEP: &str = "🔋 Battery Measurement:
+  Voltage: 3851 mV
+  Current: -170 mA
+  ADC Mode: Smart Sleep (forced conversion)
+prod:~$ ";
+
+/// `pm measure` with the ADC running continuously, reported in base units
+const MEASURE_CONTINUOUS: &str = "🔋 Battery Measurement:
+  Voltage: 7.412 V
+  Current: 0.085 A
+  ADC Mode: Continuous V/I
+prod:~$ ";
+
+#[test]
+fn test_measurement_smart_sleep() {
+    let measurement = ResponseParser::parse_measurement(MEASURE_SMART_SLEEP);
+    assert_eq!(measurement.voltage_mv, Some(3851));
+    assert_eq!(measurement.current_ma, Some(-170));
+    assert_eq!(
+        measurement.adc_mode.as_deref(),
+        Some("Smart Sleep (forced conversion)")
+    );
+    assert_eq!(measurement.source, "pm measure");
+}
+
+#[test]
+fn test_measurement_continuous() {
+    let measurement = ResponseParser::parse_measurement(MEASURE_CONTINUOUS);
+    assert_eq!(measurement.voltage_mv, Some(7412));
+    assert_eq!(measurement.current_ma, Some(85));
+    assert_eq!(measurement.adc_mode.as_deref(), Some("Continuous V/I"));
+}
+
+#[test]
+fn test_measurement_from_ltc2959_read() {
+    let measurement: MeasurementJson = ResponseParser::parse_ltc2959_status(
+        "ADC Mode: Smart Sleep\nVoltage: 3851 mV\nCurrent: -170 mA",
+    )
+    .into();
+    assert_eq!(measurement.voltage_mv, Some(3851));
+    assert_eq!(measurement.adc_mode.as_deref(), Some("Smart Sleep"));
+    assert_eq!(measurement.source, "ltc2959 read");
+}
+
+#[test]
+fn test_ndjson_records_end_with_newline() {
+    let records = [
+        ResponseParser::parse_measurement(MEASURE_SMART_SLEEP),
+        ResponseParser::parse_measurement(MEASURE_CONTINUOUS),
+        // Embedded newlines must not split a record across lines
+        MeasurementJson {
+            voltage_mv: None,
+            current_ma: None,
+            adc_mode: Some("line one\nline two".to_string()),
+            source: "pm measure".to_string(),
+        },
+    ];
+
+    let mut out = Vec::new();
+    for record in &records {
+        write_ndjson(&mut out, record).unwrap();
+        assert_eq!(out.last(), Some(&b'\n'));
+    }
+
+    let text = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), records.len());
+    for (line, record) in lines.iter().zip(&records) {
+        let parsed: MeasurementJson = serde_json::from_str(line).unwrap();
+        assert_eq!(&parsed, record);
+    }
+}
+
+#[test]
+fn rtc_calibration_rounds_to_nearest_register_step() {
+    use eink_power_cli::power::rtc::{ppm_to_pcf2131_offset, RtcCalibration};
+
+    assert_eq!(ppm_to_pcf2131_offset(0), 0);
+    assert_eq!(ppm_to_pcf2131_offset(2), 0);
+    assert_eq!(ppm_to_pcf2131_offset(3), 1);
+    assert_eq!(ppm_to_pcf2131_offset(-22), -5);
+    assert_eq!(ppm_to_pcf2131_offset(100), 23);
+    assert_eq!(ppm_to_pcf2131_offset(-100), -23);
+
+    let calibration = RtcCalibration::for_ppm(-22);
+    assert_eq!(calibration.register_value, -5);
+    assert_eq!(calibration.register_bits(), 0x7B);
+    assert!((calibration.ppm_actual + 21.7).abs() < 1e-9);
+    assert!((calibration.residual_ppm.unwrap() + 0.3).abs() < 1e-9);
+}
+
+#[test]
+fn parse_rtc_calibration_register() {
+    use eink_power_cli::power::rtc::RtcCalibration;
+
+    let decimal = RtcCalibration::parse("PCF2131 offset register: -5\r\n").unwrap();
+    assert_eq!(decimal.register_value, -5);
+    assert_eq!(decimal.ppm_requested, None);
+
+    let hex = RtcCalibration::parse("OFFSET: 0x7B").unwrap();
+    assert_eq!(hex.register_value, -5);
+
+    assert!(RtcCalibration::parse("offset: 99").is_none());
+    assert!(RtcCalibration::parse("rtc: unknown command").is_none());
+}
+
+#[test]
+fn test_firmware_version_components() {
+    use eink_power_cli::json::FirmwareVersion;
+
+    let dirty = FirmwareVersion::parse("2.2.0-+0fa46fb-dirty.298");
+    assert_eq!(dirty.semver.as_deref(), Some("2.2.0"));
+    assert_eq!(dirty.git_hash.as_deref(), Some("0fa46fb"));
+    assert_eq!(dirty.dirty, Some(true));
+    assert_eq!(dirty.build_number, Some(298));
+
+    let clean = FirmwareVersion::parse("2.3.1-+a1b2c3d4.17");
+    assert_eq!(clean.git_hash.as_deref(), Some("a1b2c3d4"));
+    assert_eq!(clean.dirty, Some(false));
+    assert_eq!(clean.build_number, Some(17));
+
+    // Older firmware only reported the semver
+    let old = FirmwareVersion::parse("v2.0");
+    assert_eq!(old.semver.as_deref(), Some("2.0"));
+    assert_eq!(old.git_hash, None);
+    assert_eq!(old.dirty, None);
+    assert_eq!(old.build_number, None);
+
+    assert_eq!(
+        FirmwareVersion::parse("unknown"),
+        FirmwareVersion::default()
+    );
+}
+
+#[test]
+fn test_firmware_semver_drops_build_suffixes() {
+    use eink_power_cli::json::firmware_semver;
+
+    let version = |raw: &str| firmware_semver(raw).map(|v| v.to_string());
+    assert_eq!(
+        version("2.2.0-+0fa46fb-dirty.298").as_deref(),
+        Some("2.2.0")
+    );
+    assert_eq!(version("2.3.1-+a1b2c3d4.17").as_deref(), Some("2.3.1"));
+    assert_eq!(version("2.3.1-0fa46fb-dirty").as_deref(), Some("2.3.1"));
+    assert_eq!(version("2.3.1-g0fa46fb").as_deref(), Some("2.3.1"));
+    assert_eq!(version("2.3.1-dirty").as_deref(), Some("2.3.1"));
+    assert_eq!(version("v2.0").as_deref(), Some("2.0.0"));
+    assert_eq!(version("2.4.0-rc.1+0fa46fb").as_deref(), Some("2.4.0-rc.1"));
+    assert_eq!(
+        version("2.4.0-beta.2-0fa46fb-dirty.12").as_deref(),
+        Some("2.4.0-beta.2")
+    );
+    assert_eq!(version("unknown"), None);
+}
+
+#[test]
+fn test_system_info_expected_version() {
+    use semver::VersionReq;
+
+    let meets = |raw: &str, requirement: &str| {
+        let info = ResponseParser::parse_system_info(&format!("Version: {}", raw));
+        info.meets_version(&VersionReq::parse(requirement).unwrap())
+    };
+    let fleet = ">=2.2, <3";
+    assert!(meets("2.2.0-+0fa46fb-dirty.298", fleet));
+    assert!(meets("2.9.14-+a1b2c3d4.17", fleet));
+    assert!(!meets("2.1.9-+a1b2c3d4.17", fleet));
+    assert!(!meets("3.0.0-+a1b2c3d4.1", fleet));
+    assert!(meets("v2.2", fleet));
+
+    // Pre-releases only meet requirements naming a pre-release of the same
+    // version
+    assert!(!meets("2.4.0-rc.1+0fa46fb", fleet));
+    assert!(meets("2.4.0-rc.1+0fa46fb", ">=2.4.0-rc.1"));
+    assert!(!meets("2.4.0-rc.1-dirty", ">=2.4.0-rc.2"));
+    assert!(meets("2.4.0-rc.2", "=2.4.0-rc.2"));
+    assert!(meets("2.4.0", ">=2.4.0-rc.1"));
+
+    assert!(meets("2.2.0-+0fa46fb-dirty.298", "~2.2"));
+    assert!(!meets("2.2.0-+0fa46fb-dirty.298", "^2.3"));
+    assert!(!meets("unknown", "*"));
+    assert!(!ResponseParser::parse_system_info("Board: X").meets_version(&VersionReq::STAR));
+}
+
+#[test]
+fn test_system_info_release_policy() {
+    let response = "Board: MCXC143VFM E-Ink Power Controller
+Version: 2.2.0-+0fa46fb-dirty.298
+Build: 2025-10-09 11:13:59 UTC
+Build Type: Debug";
+    let info = ResponseParser::parse_system_info(response);
+    assert_eq!(info.build_type, Some(BuildType::Debug));
+
+    assert!(info.policy_violations(false, None).unwrap().is_empty());
+    let violations = info.policy_violations(true, Some("2.3")).unwrap();
+    assert_eq!(violations.len(), 3, "{:?}", violations);
+    assert!(violations[0].contains("production build required"));
+    assert!(violations[1].contains("dirty"));
+    assert!(violations[2].contains("older than required 2.3"));
+    assert!(info
+        .policy_violations(false, Some("not-a-version"))
+        .is_err());
+
+    let release =
+        ResponseParser::parse_system_info("Version: 2.4.0-+1234abc.12\nBuild Type: Production");
+    assert!(release
+        .policy_violations(true, Some("2.4.0"))
+        .unwrap()
+        .is_empty());
+
+    let json = serde_json::to_value(&release).unwrap();
+    assert_eq!(json["build_type"], "production");
+    assert_eq!(json["semver"], "2.4.0");
+    assert_eq!(json["dirty"], false);
+    assert_eq!(json["build_number"], 12);
+}
+
+#[test]
+fn test_parsers_populate_every_field_of_reference_responses() {
+    use eink_power_cli::json::schema::{ParserSchema, DEVICE_EXAMPLES};
+
+    ParserSchema::assert_all_fields_populated(&DEVICE_EXAMPLES);
+}
+
+#[test]
+#[should_panic(expected = "nfc.sram_status")]
+fn test_parser_schema_reports_missing_fields() {
+    use eink_power_cli::json::schema::{ParserSchema, DEVICE_EXAMPLES};
+
+    let schema = ParserSchema {
+        nfc_example:
+            "NTA5332 Status: 0x02\nRF Field: Absent\nNFC Active: NO\nI2C Ready: YES\nEEPROM: Ready",
+        ..DEVICE_EXAMPLES
+    };
+    ParserSchema::assert_all_fields_populated(&schema);
+}
+
+/// Machine keys next to the firmware's wording, so scripts never match
+/// display text such as `RF Field: Absent`
+#[test]
+fn test_typed_companions_of_reference_responses() {
+    use eink_power_cli::json::schema::DEVICE_EXAMPLES;
+
+    let nfc = serde_json::to_value(ResponseParser::parse_nfc_status(
+        DEVICE_EXAMPLES.nfc_example,
+    ))
+    .unwrap();
+    assert_eq!(nfc["rf_field"], "Absent");
+    assert_eq!(nfc["rf_field_present"], false);
+    assert_eq!(nfc["status_register_value"], 2);
+    assert_eq!(nfc["eeprom_ready"], true);
+    assert_eq!(nfc["sram_idle"], true);
+    assert_eq!(nfc["nfc_active"], false);
+    assert_eq!(nfc["i2c_ready"], true);
+
+    let system = serde_json::to_value(ResponseParser::parse_system_info(
+        DEVICE_EXAMPLES.system_info_example,
+    ))
+    .unwrap();
+    assert_eq!(system["uptime"], "0:01:07 (67427 ms)");
+    assert_eq!(system["uptime_ms"], 67427);
+    assert_eq!(system["build_time"], "2025-10-09T11:13:59Z");
+    assert_eq!(system["build_type"], "debug");
+    assert_eq!(system["semver"], "2.2.0");
+
+    let ltc = serde_json::to_value(ResponseParser::parse_ltc2959_status(
+        DEVICE_EXAMPLES.ltc2959_example,
+    ))
+    .unwrap();
+    assert_eq!(ltc["adc_mode"], "Smart Sleep");
+    assert_eq!(ltc["adc_mode_id"], "smart_sleep");
+    assert_eq!(ltc["coulomb_counter_enabled"], true);
+    assert_eq!(ltc["status_register_value"], 1);
+    assert_eq!(ltc["charge_complete"], false);
+}
+
+#[test]
+fn test_typed_companions_of_other_wordings() {
+    use eink_power_cli::power::ltc2959::AdcMode;
+
+    let nfc = ResponseParser::parse_nfc_status(
+        "RF Field: Present
+EEPROM: Busy
+SRAM: Busy",
+    );
+    assert_eq!(nfc.rf_field_present, Some(true));
+    assert_eq!(nfc.eeprom_ready, Some(false));
+    assert_eq!(nfc.sram_idle, Some(false));
+
+    // Wording the companion does not know is kept for display only
+    let nfc = ResponseParser::parse_nfc_status(
+        "RF Field: Weak
+SRAM: Pass-through",
+    );
+    assert_eq!(nfc.rf_field.as_deref(), Some("Weak"));
+    assert_eq!(nfc.rf_field_present, None);
+    assert_eq!(nfc.sram_idle, None);
+
+    let ltc = ResponseParser::parse_ltc2959_status(
+        "ADC Mode: Continuous V/I (forced conversion)\nCoulomb Counter: Disabled",
+    );
+    assert_eq!(ltc.adc_mode_id, Some(AdcMode::ContinuousVI));
+    assert_eq!(ltc.coulomb_counter_enabled, Some(false));
+
+    let system =
+        ResponseParser::parse_system_info("Build: Oct  9 2025 11:13:59\nSystem Uptime: 0:01:07");
+    assert!(system.build_date.is_some());
+    assert_eq!(system.build_time, None);
+    assert_eq!(system.uptime_ms, None);
+}
+
+#[test]
+fn test_power_defaults_round_trip() {
+    use eink_power_cli::json::PowerDefaults;
+
+    let listing = "Defaults stored in flash\nPMIC: ON\nWiFi: OFF\nDISP: ON";
+    let defaults = PowerDefaults::try_from(ResponseParser::parse_rail_defaults(listing)).unwrap();
+    assert_eq!(
+        defaults,
+        PowerDefaults {
+            pmic: true,
+            wifi: false,
+            disp: true
+        }
+    );
+
+    let json = serde_json::to_string(&defaults).unwrap();
+    assert_eq!(json, r#"{"pmic":true,"wifi":false,"disp":true}"#);
+    assert_eq!(
+        serde_json::from_str::<PowerDefaults>(&json).unwrap(),
+        defaults
+    );
+
+    assert!(serde_json::from_str::<PowerDefaults>(r#"{"pmic":true,"wifi":false}"#).is_err());
+    assert!(PowerDefaults::try_from(ResponseParser::parse_rail_defaults("PMIC: ON")).is_err());
+}
+
+#[test]
+fn test_parse_battery_health_full_report() {
+    use eink_power_cli::json::BatteryVerdict;
+
+    let health = ResponseParser::parse_battery_health(
+        "🔋 Battery Health Check:\nUnloaded Voltage: 3.85 V\nLoaded Voltage: 3712 mV\nInternal Resistance: 0.138 Ω\nLoad Test: FAIL (sag too large)\nVerdict: Degraded",
+    );
+    assert_eq!(health.unloaded_voltage_mv, Some(3850));
+    assert_eq!(health.loaded_voltage_mv, Some(3712));
+    assert_eq!(health.internal_resistance_mohm, Some(138));
+    assert_eq!(health.result.as_deref(), Some("FAIL (sag too large)"));
+    assert_eq!(health.verdict, Some(BatteryVerdict::Degraded));
+}
+
+#[test]
+fn test_parse_battery_health_verdict_only() {
+    use eink_power_cli::json::BatteryVerdict;
+
+    let health = ResponseParser::parse_battery_health("Battery: HEALTHY");
+    assert_eq!(health.verdict, Some(BatteryVerdict::Healthy));
+    assert_eq!(health.result, None);
+    assert_eq!(health.internal_resistance_mohm, None);
+    assert_eq!(health.loaded_voltage_mv, None);
+
+    let health = ResponseParser::parse_battery_health("Battery check complete\nFAILED");
+    assert_eq!(health.verdict, Some(BatteryVerdict::Failed));
+    assert_eq!(ResponseParser::parse_battery_health("busy").verdict, None);
+}
+
+#[test]
+fn test_battery_verdict_exit_codes() {
+    use eink_power_cli::json::BatteryVerdict;
+
+    assert_eq!(BatteryVerdict::Healthy.exit_code(), 0);
+    use eink_power_cli::error::PowerCliError;
+
+    let degraded = PowerCliError::BatteryUnhealthy {
+        verdict: BatteryVerdict::Degraded,
+    };
+    assert_eq!(degraded.exit_code(), 2);
+    let failed = PowerCliError::BatteryUnhealthy {
+        verdict: BatteryVerdict::Failed,
+    };
+    assert_eq!(failed.exit_code(), 3);
+    assert_eq!(PowerCliError::NotConnected.exit_code(), 1);
+}
+
+#[test]
+fn test_parse_diagnostics_explain_null_fields() {
+    use eink_power_cli::json::diagnostics::{collect, ParseOutcome};
+
+    let response = "Voltage: 3.85 kV\nCurrent: -170 mA\nPower: n/a";
+    let (battery, diagnostics) = collect(|| ResponseParser::parse_battery_response(response));
+    assert_eq!(battery.voltage_mv, None);
+    assert_eq!(battery.current_ma, Some(-170));
+
+    let outcome = |field: &str| {
+        let diagnostic = diagnostics
+            .iter()
+            .find(|d| d.field == field)
+            .unwrap_or_else(|| panic!("no diagnostic for {}", field));
+        (diagnostic.outcome, diagnostic.line.as_deref())
+    };
+    assert_eq!(
+        outcome("voltage_mv"),
+        (ParseOutcome::Unparsed, Some("Voltage: 3.85 kV"))
+    );
+    assert_eq!(
+        outcome("current_ma"),
+        (ParseOutcome::Matched, Some("Current: -170 mA"))
+    );
+    assert_eq!(
+        outcome("power_mw"),
+        (ParseOutcome::Unparsed, Some("Power: n/a"))
+    );
+    assert_eq!(outcome("charge_mah"), (ParseOutcome::Absent, None));
+    assert_eq!(outcome("temperature_c"), (ParseOutcome::Absent, None));
+}
+
+#[test]
+fn test_parse_gpio_pull() {
+    let pull = |response: &str| ResponseParser::parse_gpio_response(response, "A", 0).pull;
+
+    assert_eq!(
+        pull("GPIO A0: 1 (INPUT, PULL-UP, HIGH)").as_deref(),
+        Some("UP")
+    );
+    assert_eq!(
+        pull("GPIO A0: 0 (INPUT, PULLDOWN, LOW)").as_deref(),
+        Some("DOWN")
+    );
+    assert_eq!(
+        pull("GPIO A0: 0 (INPUT, NOPULL, LOW)").as_deref(),
+        Some("NONE")
+    );
+    assert_eq!(pull("GPIO A0: 1 (OUTPUT, HIGH)"), None);
+}
+
+#[test]
+fn test_parse_diagnostics_only_collected_inside_collect() {
+    use eink_power_cli::json::diagnostics::collect;
+
+    // Outside `collect` the parsers only log
+    ResponseParser::parse_nfc_status("RF Field: Absent");
+
+    let ((_, inner), outer) = collect(|| {
+        ResponseParser::parse_gpio_response("GPIO A5: 1", "A", 5);
+        collect(|| ResponseParser::parse_nfc_status("RF Field: Absent"))
+    });
+    assert_eq!(inner.len(), 6);
+    // GPIO records `value` and `pull`
+    assert_eq!(outer.len(), 2 + inner.len());
+    assert_eq!(outer[0].field, "value");
+}
+
+#[test]
+fn test_every_response_pattern_compiles() {
+    use eink_power_cli::json::patterns;
+    use std::sync::LazyLock;
+
+    for (name, pattern) in patterns::ALL {
+        let compiled = std::panic::catch_unwind(|| LazyLock::force(pattern).as_str().len());
+        assert!(compiled.is_ok(), "pattern {} does not compile", name);
+    }
+}
@@ -0,0 +1,97 @@
+/*
+ * E-ink Power CLI - Pass-Through Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Mailbox framing and reassembly of NFC pass-through transfers
+
+use eink_power_cli::power::passthrough::{
+    crc32, mailboxes, parse_hex, Mailbox, Reassembly, Received, MAILBOX_PAYLOAD, MAILBOX_SIZE,
+    MAX_TRANSFER_LEN,
+};
+
+#[test]
+fn crc32_matches_the_ieee_check_value() {
+    assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    assert_eq!(crc32(&[]), 0);
+}
+
+#[test]
+fn data_is_split_into_numbered_mailboxes_and_an_end_mailbox() {
+    let data: Vec<u8> = (0..130u8).collect();
+    let sent = mailboxes(&data).unwrap();
+
+    assert_eq!(sent.len(), 4);
+    assert_eq!(
+        sent[2],
+        Mailbox::Data {
+            seq: 2,
+            payload: data[2 * MAILBOX_PAYLOAD..].to_vec()
+        }
+    );
+    assert_eq!(
+        sent[3],
+        Mailbox::End {
+            seq: 3,
+            len: 130,
+            crc: crc32(&data)
+        }
+    );
+
+    let encoded = sent[2].encode();
+    assert_eq!(encoded.len(), MAILBOX_SIZE);
+    assert_eq!(&encoded[..3], &[2, 6, 124]);
+    assert!(encoded[8..].iter().all(|&b| b == 0));
+    for mailbox in &sent {
+        assert_eq!(&Mailbox::decode(&mailbox.encode()).unwrap(), mailbox);
+    }
+
+    assert!(mailboxes(&vec![0; MAX_TRANSFER_LEN + 1]).is_err());
+}
+
+#[test]
+fn reassembly_skips_a_repeated_mailbox_and_checks_the_end() {
+    let data: Vec<u8> = (0..100u8).collect();
+    let sent = mailboxes(&data).unwrap();
+    let mut reassembly = Reassembly::default();
+
+    assert_eq!(reassembly.push(sent[0].clone()).unwrap(), Received::Data);
+    assert_eq!(
+        reassembly.push(sent[0].clone()).unwrap(),
+        Received::Repeated
+    );
+    assert_eq!(reassembly.push(sent[1].clone()).unwrap(), Received::Data);
+    assert_eq!(
+        reassembly.push(sent[2].clone()).unwrap(),
+        Received::Complete(data)
+    );
+}
+
+#[test]
+fn reassembly_rejects_gaps_and_checksum_mismatches() {
+    let sent = mailboxes(&[7; 100]).unwrap();
+
+    let mut skipped = Reassembly::default();
+    assert!(skipped.push(sent[1].clone()).is_err());
+
+    let mut corrupted = Reassembly::default();
+    corrupted
+        .push(Mailbox::Data {
+            seq: 0,
+            payload: vec![8; MAILBOX_PAYLOAD],
+        })
+        .unwrap();
+    corrupted.push(sent[1].clone()).unwrap();
+    let error = corrupted.push(sent[2].clone()).unwrap_err();
+    assert!(error.to_string().contains("checksum mismatch"), "{}", error);
+}
+
+#[test]
+fn hex_data_is_parsed_in_byte_pairs() {
+    assert_eq!(parse_hex("0102a0FF").unwrap(), [0x01, 0x02, 0xA0, 0xFF]);
+    assert_eq!(parse_hex("0x0A").unwrap(), [0x0A]);
+    assert!(parse_hex("").is_err());
+    assert!(parse_hex("123").is_err());
+    assert!(parse_hex("zz").is_err());
+}
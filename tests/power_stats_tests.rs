@@ -0,0 +1,206 @@
+/*
+ * E-ink Power CLI - Derived Power Statistics Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Rates and shares derived from the power counters
+
+use eink_power_cli::json::PowerStatsJson;
+use eink_power_cli::power::control::PowerStats;
+use eink_power_cli::power::stats::{PowerCounters, PowerStatsDerived, PowerStatsReport};
+use eink_power_cli::power::wake::WakeSource;
+use eink_power_cli::simulator::POWER_STATS_REPLY;
+use std::collections::BTreeMap;
+
+const HOUR_MS: u64 = 3_600_000;
+
+fn counters(
+    uptime_ms: u64,
+    active_ms: u32,
+    sleeps: u32,
+    wakes: &[(WakeSource, u32)],
+) -> PowerCounters {
+    PowerCounters {
+        uptime_ms,
+        active_time_ms: Some(active_ms),
+        sleep_count: Some(sleeps),
+        wake_count: Some(wakes.iter().map(|(_, n)| n).sum()),
+        wakes_by_source: wakes.iter().copied().collect(),
+    }
+}
+
+fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.unwrap_or_else(|| panic!("expected {}, got None", expected));
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "{} != {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn power_stats_reply_parses_every_counter() {
+    let stats = PowerStats::parse(POWER_STATS_REPLY);
+    assert_eq!(stats.active_time_ms, Some(900_000));
+    assert_eq!(stats.sleep_count, Some(4));
+    assert_eq!(stats.wake_count, Some(4));
+    assert_eq!(stats.rtc_wake_count, Some(2));
+    assert_eq!(stats.nfc_wake_count, Some(1));
+    assert_eq!(stats.uart_wake_count, Some(1));
+}
+
+#[test]
+fn counters_the_firmware_does_not_print_are_missing() {
+    let stats = PowerStats::parse("Active time: 5000 ms\nSleep cycles: 2\nWake events: 3");
+    assert_eq!(stats.wake_count, Some(3));
+    assert_eq!(stats.sleep_count, Some(2));
+    assert_eq!(stats.rtc_wake_count, None);
+    assert!(!stats.is_empty());
+    assert!(PowerStats::parse("Error: unknown command 'power stats'").is_empty());
+}
+
+#[test]
+fn metrics_since_boot() {
+    let derived = PowerStatsDerived::since_boot(&counters(
+        2 * HOUR_MS,
+        1_800_000,
+        6,
+        &[(WakeSource::Rtc, 4), (WakeSource::Nfc, 2)],
+    ));
+    assert!(derived.since_boot);
+    assert_eq!(derived.window_ms, 2 * HOUR_MS);
+    assert_close(derived.wakes_per_hour, 3.0);
+    assert_close(
+        derived
+            .wakes_per_hour_by_source
+            .get(&WakeSource::Rtc)
+            .copied(),
+        2.0,
+    );
+    assert_close(
+        derived.wake_share_percent.get(&WakeSource::Nfc).copied(),
+        100.0 / 3.0,
+    );
+    assert_close(derived.active_percent, 25.0);
+    assert_close(derived.asleep_percent, 75.0);
+    // 1.5 h asleep over 6 sleeps
+    assert_close(derived.average_sleep_ms, 900_000.0);
+    assert!(!derived.counter_wrapped);
+}
+
+#[test]
+fn zero_uptime_gives_no_rates() {
+    let derived = PowerStatsDerived::since_boot(&counters(0, 0, 0, &[(WakeSource::Uart, 0)]));
+    assert_eq!(derived.window_ms, 0);
+    assert_eq!(derived.wakes_per_hour, None);
+    assert!(derived.wakes_per_hour_by_source.is_empty());
+    assert_eq!(derived.active_percent, None);
+    assert_eq!(derived.asleep_percent, None);
+    assert_eq!(derived.average_sleep_ms, None);
+    assert!(derived.is_empty());
+}
+
+#[test]
+fn zero_wakes_give_zero_rates_and_no_shares() {
+    let derived = PowerStatsDerived::since_boot(&counters(
+        HOUR_MS,
+        HOUR_MS as u32,
+        0,
+        &[(WakeSource::Rtc, 0), (WakeSource::Nfc, 0)],
+    ));
+    assert_close(derived.wakes_per_hour, 0.0);
+    assert_close(
+        derived
+            .wakes_per_hour_by_source
+            .get(&WakeSource::Rtc)
+            .copied(),
+        0.0,
+    );
+    assert!(derived.wake_share_percent.is_empty());
+    assert_close(derived.active_percent, 100.0);
+    // Never slept, so there is no average sleep
+    assert_eq!(derived.average_sleep_ms, None);
+}
+
+#[test]
+fn wrapped_counters_are_differenced_modulo_their_width() {
+    let start = counters(
+        10 * HOUR_MS,
+        u32::MAX - 999,
+        u32::MAX - 1,
+        &[(WakeSource::Rtc, u32::MAX)],
+    );
+    let end = counters(11 * HOUR_MS, 899_000, 2, &[(WakeSource::Rtc, 3)]);
+    let derived = PowerStatsDerived::between(&start, &end);
+
+    assert!(derived.counter_wrapped);
+    assert!(!derived.since_boot);
+    assert_eq!(derived.window_ms, HOUR_MS);
+    assert_close(derived.wakes_per_hour, 4.0);
+    assert_close(
+        derived.wake_share_percent.get(&WakeSource::Rtc).copied(),
+        100.0,
+    );
+    // 900 s awake across the wrap of the active-time counter
+    assert_close(derived.active_percent, 25.0);
+    assert_close(derived.average_sleep_ms, 2_700_000.0 / 4.0);
+}
+
+#[test]
+fn active_time_over_a_window_longer_than_its_counter_is_left_out() {
+    let uptime_ms = u64::from(u32::MAX) + HOUR_MS;
+    let derived = PowerStatsDerived::since_boot(&counters(uptime_ms, 1_000, 10, &[]));
+    assert_eq!(derived.active_percent, None);
+    assert_eq!(derived.average_sleep_ms, None);
+    assert!(derived.wakes_per_hour.is_some());
+}
+
+#[test]
+fn a_reset_between_readings_counts_from_the_new_boot() {
+    let start = counters(5 * HOUR_MS, 100_000, 40, &[(WakeSource::Uart, 40)]);
+    let end = counters(HOUR_MS, 360_000, 2, &[(WakeSource::Uart, 2)]);
+    let derived = PowerStatsDerived::between(&start, &end);
+    assert!(derived.since_boot);
+    assert!(!derived.counter_wrapped);
+    assert_eq!(derived.window_ms, HOUR_MS);
+    assert_close(derived.wakes_per_hour, 2.0);
+    assert_close(derived.active_percent, 10.0);
+}
+
+#[test]
+fn pm_stats_give_rates_without_a_time_split() {
+    let stats = PowerStatsJson {
+        sleep_cycles: Some(8),
+        wake_cycles: Some(8),
+        ltc2959_state: None,
+        nfc_state: None,
+        uart_state: None,
+        uptime_ms: Some(4 * HOUR_MS),
+    };
+    let derived = PowerStatsDerived::since_boot(&PowerCounters::from_pm_stats(&stats).unwrap());
+    assert_close(derived.wakes_per_hour, 2.0);
+    assert_eq!(derived.active_percent, None);
+    assert_eq!(derived.wake_share_percent, BTreeMap::new());
+
+    let without_uptime = PowerStatsJson {
+        uptime_ms: None,
+        ..stats
+    };
+    assert_eq!(PowerCounters::from_pm_stats(&without_uptime), None);
+}
+
+#[test]
+fn report_nests_the_metrics_under_derived() {
+    let report = PowerStatsReport::new(PowerStats::parse(POWER_STATS_REPLY), Some(HOUR_MS));
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["wake_count"], 4);
+    assert_eq!(json["uptime_ms"], HOUR_MS);
+    assert_eq!(json["derived"]["wakes_per_hour"], 4.0);
+    assert_eq!(json["derived"]["wake_share_percent"]["rtc"], 50.0);
+    assert_eq!(json["derived"]["asleep_percent"], 75.0);
+
+    let report = PowerStatsReport::new(PowerStats::parse(POWER_STATS_REPLY), None);
+    assert!(serde_json::to_value(&report).unwrap()["derived"].is_null());
+}
@@ -0,0 +1,86 @@
+/*
+ * E-ink Power CLI - Progress Rewrite Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Replies with `\r` progress updates, collapsed before parsing and display
+//!
+//! `tests/fixtures/erase_app.raw.txt` is a `system erase app` transcript as
+//! it came off the wire; `erase_app.collapsed.txt` is what a terminal shows
+//! for it.
+
+use eink_power_cli::json::output::{CommandOutput, ParsedPayload};
+use eink_power_cli::json::progress::collapse;
+use eink_power_cli::json::{JsonResponse, ResponseParser};
+use eink_power_cli::render::{self, OutputStyle};
+use std::borrow::Cow;
+
+mod common;
+use common::fixture;
+
+/// Battery readout that shows a running value while the gauge averages
+const BATTERY_AVERAGING: &str = "Averaging...\rVoltage: 3712 mV\rVoltage: 3850 mV
+Current: 0 mA\rCurrent: -125 mA
+Charge: 2450 mAh
+Power: -481 mW";
+
+#[test]
+fn erase_transcript_collapses_to_its_final_rendering() {
+    assert_eq!(
+        collapse(&fixture("erase_app.raw.txt")),
+        fixture("erase_app.collapsed.txt")
+    );
+}
+
+#[test]
+fn replies_without_carriage_returns_are_borrowed() {
+    let reply = "Voltage: 3850 mV\nCurrent: -125 mA";
+    assert!(matches!(collapse(reply), Cow::Borrowed(text) if text == reply));
+}
+
+#[test]
+fn shorter_rewrites_leave_the_tail_of_longer_text() {
+    // As on a terminal: nothing clears the end of the line
+    assert_eq!(collapse("Erasing 100%\rDone"), "Doneing 100%");
+    assert_eq!(collapse("Erasing 100%\r\x1b[KDone"), "Done");
+    assert_eq!(collapse("Erasing 100%\r\x1b[2KDone"), "Done");
+}
+
+#[test]
+fn trailing_carriage_returns_keep_the_line() {
+    assert_eq!(collapse("50%\r100%\r"), "100%");
+    assert_eq!(collapse("Done\r\r\nNext"), "Done\nNext");
+}
+
+#[test]
+fn parser_reads_the_final_value_of_a_rewritten_field() {
+    let battery = ResponseParser::parse_battery_response(BATTERY_AVERAGING);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.current_ma, Some(-125));
+    assert_eq!(battery.charge_mah, Some(2450));
+    assert_eq!(battery.power_mw, Some(-481));
+}
+
+#[test]
+fn sectioned_output_reads_the_final_state_and_the_envelope_keeps_the_raw_reply() {
+    let raw = "Progress: 50%\rProgress: 100%";
+    let data =
+        serde_json::to_value(CommandOutput::from_response(&ParsedPayload::Raw, raw)).unwrap();
+    assert_eq!(data["sections"][0]["fields"]["Progress"], "100%");
+
+    let envelope = JsonResponse::success_with_raw("flash erase", data, raw);
+    assert_eq!(envelope.raw_response.as_deref(), Some(raw));
+}
+
+#[test]
+fn human_output_shows_the_collapsed_reply() {
+    let text = render::titled(
+        &OutputStyle::ascii(),
+        "🗑️",
+        "Erase Application",
+        &fixture("erase_app.raw.txt"),
+    );
+    assert!(!text.contains('\r'));
+    assert!(text.ends_with(&fixture("erase_app.collapsed.txt")));
+}
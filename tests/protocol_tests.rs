@@ -0,0 +1,796 @@
+/*
+ * E-ink Power CLI - Protocol Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Tests for the command strings sent to the controller shell
+
+use eink_power_cli::cli::DeviceAction;
+use eink_power_cli::config::Config;
+use eink_power_cli::firmware::{FirmwareManager, FirmwareTransports, McumgrTransport};
+use eink_power_cli::power::Rail;
+use eink_power_cli::serial::connection::{ends_with_prompt, is_destructive, ShellState};
+use eink_power_cli::serial::protocol::classify::{classify, ResponseClass};
+use eink_power_cli::serial::protocol::device_action_command;
+use eink_power_cli::serial::timeouts::TimeoutSource;
+use eink_power_cli::serial::{
+    CommandFamily, CommandMap, Connection, EchoCheck, LatencyStats, MockSerial, Protocol,
+    ResyncMode, TimeoutPolicy,
+};
+use std::time::Duration;
+
+mod common;
+use common::fixture_lines;
+
+#[test]
+fn test_device_action_command_has_no_duplicate_tokens() {
+    for device in ["ltc2959", "nfc"] {
+        for action in [DeviceAction::Wake, DeviceAction::Sleep] {
+            let command = device_action_command(device, action.as_str());
+            let tokens: Vec<&str> = command.split_whitespace().collect();
+
+            assert_eq!(tokens, vec![device, action.as_str()], "{}", command);
+            assert!(!command.contains("pm "), "unexpected prefix: {}", command);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_device_actions_reach_the_wire_without_a_prefix() {
+    let mut builder = MockSerial::builder();
+    for device in ["ltc2959", "nfc"] {
+        for action in ["wake", "sleep"] {
+            let command = format!("{} {}", device, action);
+            builder = builder.expect(&command, &format!("{}\r\nOK\r\nprod:~$ ", command));
+        }
+    }
+    let serial = builder.build();
+    let wire = serial.wire();
+    let mut protocol = Protocol::new(Connection::mock(serial));
+
+    for device in ["ltc2959", "nfc"] {
+        for action in [DeviceAction::Wake, DeviceAction::Sleep] {
+            protocol
+                .execute_device_action_command(device, action.as_str())
+                .await
+                .unwrap();
+        }
+    }
+    assert_eq!(
+        String::from_utf8(wire.lock().unwrap().clone()).unwrap(),
+        "ltc2959 wake\nltc2959 sleep\nnfc wake\nnfc sleep\n"
+    );
+}
+
+#[test]
+fn test_device_action_keywords() {
+    assert_eq!(DeviceAction::Wake.as_str(), "wake");
+    assert_eq!(DeviceAction::Sleep.as_str(), "sleep");
+}
+
+#[test]
+fn test_latency_stats() {
+    let samples: Vec<Duration> = [10, 20, 30, 40]
+        .iter()
+        .map(|ms| Duration::from_millis(*ms))
+        .collect();
+    let stats = LatencyStats::from_samples(&samples).unwrap();
+
+    assert_eq!(stats.min_ms, 10);
+    assert_eq!(stats.max_ms, 40);
+    assert!((stats.avg_ms - 25.0).abs() < 1e-9);
+    assert!((stats.std_dev_ms - 125f64.sqrt()).abs() < 1e-9);
+    assert_eq!(stats.quantile_ms(0.5), 20.0);
+    assert_eq!(stats.quantile_ms(0.99), 40.0);
+}
+
+#[test]
+fn test_latency_stats_empty() {
+    assert!(LatencyStats::from_samples(&[]).is_none());
+}
+
+/// Console output captured while the PMU shell was disabled: log lines only,
+/// no echo of `ping`, no prompt
+const LOG_STORM: &str = "[00:12:41.118,000] <inf> ltc2959: V=3851mV I=-170mA
+[00:12:41.218,000] <inf> ltc2959: V=3851mV I=-171mA
+[00:12:41.318,000] <wrn> pm: PMIC rail sag detected (3.29V)
+[00:12:41.418,000] <inf> ltc2959: V=3850mV I=-170mA
+[00:12:41.518,000] <inf> nfc: field lost
+";
+
+#[test]
+fn test_log_storm_is_shell_unavailable() {
+    match ShellState::classify(LOG_STORM) {
+        ShellState::LogOnly { snippet } => {
+            assert!(snippet.contains("nfc: field lost"));
+            assert!(snippet.len() <= 200);
+        }
+        other => panic!("expected LogOnly, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_healthy_ping_is_ready() {
+    assert_eq!(
+        ShellState::classify("ping\r\npong\r\nprod:~$ "),
+        ShellState::Ready
+    );
+    // Logs interleaved with a working shell still count as ready
+    let mixed = format!("{}ping\r\nprod:~$ ", LOG_STORM);
+    assert_eq!(ShellState::classify(&mixed), ShellState::Ready);
+}
+
+#[test]
+fn test_no_output_is_silent() {
+    assert_eq!(ShellState::classify(""), ShellState::Silent);
+    assert_eq!(ShellState::classify("\r\n"), ShellState::Silent);
+}
+
+#[test]
+fn test_mcumgr_transport_args() {
+    let connection = Connection::new("/dev/null", 115200, true).unwrap();
+    let transports = FirmwareTransports::resolve("/dev/ttyLP2", 115200, None, None);
+    let mut manager = FirmwareManager::new(connection, transports);
+    assert_eq!(
+        manager.build_mcumgr_args(&["image", "list"]),
+        vec![
+            "--conntype",
+            "serial",
+            "--connstring",
+            "/dev/ttyLP2,baud=115200",
+            "image",
+            "list"
+        ]
+    );
+
+    manager.set_transport(McumgrTransport::Ble {
+        address: "C0:FF:EE:00:11:22".to_string(),
+    });
+    assert_eq!(
+        manager.build_mcumgr_args(&["reset"]),
+        vec![
+            "--conntype",
+            "ble",
+            "--connstring",
+            "peer_id=C0:FF:EE:00:11:22",
+            "reset"
+        ]
+    );
+
+    manager.set_transport(McumgrTransport::udp_from_str("192.168.1.20:1337").unwrap());
+    assert_eq!(
+        manager.build_mcumgr_args(&["echo", "hi"]),
+        vec![
+            "--conntype",
+            "udp",
+            "--connstring",
+            "192.168.1.20:1337",
+            "echo",
+            "hi"
+        ]
+    );
+}
+
+#[test]
+fn test_mcumgr_udp_endpoint_parsing() {
+    assert_eq!(
+        McumgrTransport::udp_from_str("[fe80::1]:1337")
+            .unwrap()
+            .connstring(),
+        "[fe80::1]:1337"
+    );
+    assert!(McumgrTransport::udp_from_str("192.168.1.20").is_err());
+    assert!(McumgrTransport::udp_from_str(":1337").is_err());
+    assert!(McumgrTransport::udp_from_str("host:notaport").is_err());
+}
+
+/// Config file of a partner firmware fork with every root renamed
+const FORKED_FIRMWARE_CONFIG: &str = r#"
+[connection]
+device = "/dev/ttyUSB0"
+
+[commands]
+power = "pwr"
+pm = "powermgr"
+ltc2959 = "gauge"
+nfc = "tag"
+gpio = "io"
+rtc = "clock"
+system = "sys"
+board = "brd"
+comm = "link"
+"#;
+
+#[tokio::test]
+async fn test_remapped_profile_covers_every_family() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, FORKED_FIRMWARE_CONFIG).unwrap();
+    let config = Config::load(Some(&path)).unwrap();
+    assert_eq!(config.commands.remapped().len(), CommandFamily::ALL.len());
+
+    let mut connection = Connection::new("/dev/nonexistent", 115200, true).unwrap();
+    connection.set_dry_run(true);
+    let mut protocol = Protocol::new(connection);
+    protocol.set_command_map(config.commands);
+
+    protocol
+        .execute_system_command("power stats")
+        .await
+        .unwrap();
+    protocol
+        .execute_power_command(Rail::Pmic, "on")
+        .await
+        .unwrap();
+    protocol.execute_pm_command("stats").await.unwrap();
+    protocol.execute_ltc2959_command("read").await.unwrap();
+    protocol.execute_nfc_command("status").await.unwrap();
+    protocol
+        .execute_gpio_command("get", "gpioA", 1, None)
+        .await
+        .unwrap();
+    protocol.execute_rtc_command("status").await.unwrap();
+    protocol
+        .execute_system_command("system info")
+        .await
+        .unwrap();
+    protocol.execute_board_command("reset").await.unwrap();
+    protocol
+        .execute_comm_command("bt-wake", "on")
+        .await
+        .unwrap();
+    protocol
+        .execute_device_action_command("ltc2959", "wake")
+        .await
+        .unwrap();
+    protocol.execute_system_command("version").await.unwrap();
+
+    assert_eq!(
+        protocol.connection().commands_sent(),
+        [
+            "pwr stats",
+            "powermgr pmic on",
+            "powermgr stats",
+            "gauge read",
+            "tag status",
+            "io get gpioA 1",
+            "clock status",
+            "sys info",
+            "brd reset",
+            "link bt-wake on",
+            "gauge wake",
+            "version",
+        ]
+    );
+}
+
+#[test]
+fn test_default_command_map_matches_stock_firmware() {
+    let map = CommandMap::default();
+    assert!(!map.is_remapped());
+    for family in CommandFamily::ALL {
+        assert_eq!(map.root(family), family.default_root());
+    }
+    assert_eq!(map.apply("power stats"), "power stats");
+
+    let partial = map.with_root(CommandFamily::Ltc2959, "gauge");
+    assert_eq!(partial.describe_remaps(), "ltc2959→gauge");
+    assert_eq!(partial.command(CommandFamily::Pm, "stats"), "pm stats");
+}
+
+#[test]
+fn test_unknown_command_family_is_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "[commands]\nbogus = \"x\"\n").unwrap();
+    assert!(Config::load(Some(&path)).is_err());
+}
+
+#[test]
+fn test_connection_pacing_is_read_from_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(
+        &path,
+        "[connection]\ndevice = \"/dev/ttyUSB0\"\npacing_ms = 20\nresync = \"always\"\nverify_echo = \"never\"\n",
+    )
+    .unwrap();
+    let config = Config::load(Some(&path)).unwrap();
+    assert_eq!(config.connection.pacing_ms, Some(20));
+    assert_eq!(config.connection.resync, Some(ResyncMode::Always));
+    assert_eq!(config.connection.verify_echo, Some(EchoCheck::Never));
+
+    std::fs::write(&path, FORKED_FIRMWARE_CONFIG).unwrap();
+    assert_eq!(
+        Config::load(Some(&path)).unwrap().connection.pacing_ms,
+        None
+    );
+}
+
+#[test]
+fn sleep_builder_orders_flags_like_the_shell() {
+    use eink_power_cli::power::sleep::{SleepCommandBuilder, VllsMode};
+
+    assert_eq!(SleepCommandBuilder::new().build(), "sleep");
+
+    let builder = SleepCommandBuilder::new()
+        .vlls_mode(VllsMode::Vlls1)
+        .wifi_off()
+        .duration("1d12h30m")
+        .pmic_off();
+    builder.validate().unwrap();
+    assert_eq!(builder.build(), "sleep 1d12h30m --pmic --wifi --vlls1");
+
+    let all = SleepCommandBuilder::new()
+        .display_off()
+        .all_off()
+        .vlls_mode(VllsMode::Vlls3);
+    assert_eq!(all.build(), "sleep --disp --alloff --vlls3");
+}
+
+#[test]
+fn sleep_builder_rejects_conflicting_modes_and_bad_durations() {
+    use eink_power_cli::power::sleep::{parse_sleep_duration, SleepCommandBuilder, VllsMode};
+    use std::time::Duration;
+
+    assert_eq!(parse_sleep_duration("30s"), Some(Duration::from_secs(30)));
+    assert_eq!(parse_sleep_duration("90"), Some(Duration::from_secs(90)));
+    assert_eq!(
+        parse_sleep_duration("1d12h30m"),
+        Some(Duration::from_secs(86_400 + 12 * 3_600 + 30 * 60))
+    );
+    assert_eq!(parse_sleep_duration("5x"), None);
+    assert_eq!(parse_sleep_duration("m"), None);
+    assert_eq!(parse_sleep_duration("12h30"), None);
+
+    let modes = SleepCommandBuilder::new()
+        .vlls_mode(VllsMode::Vlls0)
+        .vlls_mode(VllsMode::Vlls2);
+    assert!(modes.validate().is_err());
+
+    let repeated = SleepCommandBuilder::new()
+        .vlls_mode(VllsMode::Vlls2)
+        .vlls_mode(VllsMode::Vlls2);
+    assert!(repeated.validate().is_ok());
+
+    assert!(SleepCommandBuilder::new()
+        .duration("soon")
+        .validate()
+        .is_err());
+}
+
+#[tokio::test]
+async fn factory_reset_runs_steps_in_order_and_honours_skip() {
+    use eink_power_cli::power::factory_reset::{FactoryResetStatus, FactoryResetStep};
+    use eink_power_cli::power::PowerController;
+
+    let mut connection = Connection::new("/dev/nonexistent", 115200, true).unwrap();
+    connection.set_dry_run(true);
+    let mut controller = PowerController::new(connection);
+
+    let report = controller
+        .factory_reset(&[FactoryResetStep::RtcConfig])
+        .await;
+    assert!(report.success);
+    assert_eq!(
+        controller.connection().commands_sent(),
+        [
+            "pm system erase defaults",
+            "ltc2959 production_reset",
+            "pm system reset",
+            "pm defaults",
+            "ltc2959 read",
+        ]
+    );
+
+    let statuses: Vec<_> = report.steps.iter().map(|s| (s.step, s.status)).collect();
+    assert_eq!(
+        statuses,
+        [
+            (FactoryResetStep::EraseDefaults, FactoryResetStatus::Ok),
+            (FactoryResetStep::ChargeReset, FactoryResetStatus::Ok),
+            (FactoryResetStep::RtcConfig, FactoryResetStatus::Skipped),
+            (FactoryResetStep::Reboot, FactoryResetStatus::Ok),
+            (FactoryResetStep::Verify, FactoryResetStatus::Ok),
+        ]
+    );
+
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["steps"][2]["step"], "rtc-config");
+    assert_eq!(json["steps"][2]["status"], "skipped");
+}
+
+#[test]
+fn rails_map_to_the_firmware_shell_names() {
+    let shell: Vec<&str> = Rail::ALL.iter().map(|rail| rail.as_shell_str()).collect();
+    assert_eq!(shell, ["pmic", "wifi", "disp", "imx93", "all"]);
+
+    assert_eq!(Rail::Display.to_string(), "display");
+    assert_eq!("disp".parse::<Rail>(), Ok(Rail::Display));
+    assert_eq!("WiFi".parse::<Rail>(), Ok(Rail::Wifi));
+    assert!("nfc".parse::<Rail>().is_err());
+
+    assert_eq!(serde_json::to_value(Rail::Imx93).unwrap(), "imx93");
+    assert_eq!(
+        serde_json::from_str::<Rail>("\"disp\"").unwrap(),
+        Rail::Display
+    );
+}
+
+#[test]
+fn rail_graph_sorts_dependencies_first() {
+    use eink_power_cli::power::rails::{PowerRail, PowerRailGraph};
+
+    let graph = PowerRailGraph::default();
+    assert!(graph.depends_on(PowerRail::Wifi, PowerRail::Pmic));
+    assert!(graph.dependencies(PowerRail::Nfc).is_empty());
+
+    assert!(graph
+        .validate_sequence(&[PowerRail::Pmic, PowerRail::Wifi, PowerRail::Display])
+        .is_ok());
+    assert!(graph
+        .validate_sequence(&[PowerRail::Wifi, PowerRail::Pmic])
+        .is_err());
+    // Dependencies outside the sequence are assumed to be on already
+    assert!(graph.validate_sequence(&[PowerRail::Display]).is_ok());
+
+    assert_eq!(
+        graph.sort_power_on(&[PowerRail::Nfc, PowerRail::Wifi, PowerRail::Pmic]),
+        [PowerRail::Nfc, PowerRail::Pmic, PowerRail::Wifi]
+    );
+}
+
+#[test]
+fn rail_graph_rejects_cycles() {
+    use eink_power_cli::power::rails::{PowerRail, PowerRailGraph};
+
+    let mut graph = PowerRailGraph::default();
+    assert!(graph
+        .add_dependency(PowerRail::Pmic, PowerRail::Display)
+        .is_err());
+    assert!(graph
+        .add_dependency(PowerRail::Nfc, PowerRail::Nfc)
+        .is_err());
+    graph
+        .add_dependency(PowerRail::Display, PowerRail::Ltc2959)
+        .unwrap();
+    assert_eq!(
+        graph.sort_power_on(&[PowerRail::Display]),
+        [PowerRail::Pmic, PowerRail::Ltc2959, PowerRail::Display]
+    );
+}
+
+#[test]
+fn rail_rules_replace_the_board_topology() {
+    use eink_power_cli::power::rails::{PowerRail, PowerRailGraph};
+
+    let graph = PowerRailGraph::from_rules(&["disp requires wifi", "wifi requires pmic"]).unwrap();
+    assert!(graph.depends_on(PowerRail::Display, PowerRail::Pmic));
+    assert!(!graph.depends_on(PowerRail::Imx93, PowerRail::Pmic));
+    assert_eq!(
+        graph.prerequisites(PowerRail::Display),
+        [PowerRail::Pmic, PowerRail::Wifi]
+    );
+    assert_eq!(graph.dependents(PowerRail::Wifi), [PowerRail::Display]);
+    assert_eq!(
+        PowerRailGraph::from_rules::<&str>(&[]).unwrap(),
+        PowerRailGraph::empty()
+    );
+
+    for (rule, reason) in [
+        ("disp requires modem", "unknown rail 'modem'"),
+        ("disp needs pmic", "expected '<rail> requires <rail>'"),
+        ("pmic requires pmic", "cycle"),
+    ] {
+        let error = PowerRailGraph::from_rules(&[rule]).unwrap_err().to_string();
+        assert!(error.contains(reason), "{}: {}", rule, error);
+    }
+}
+
+#[test]
+fn rail_states_give_missing_prerequisites_and_live_dependents() {
+    use eink_power_cli::power::rails::{PowerRail, PowerRailGraph};
+    use std::collections::BTreeMap;
+
+    let graph = PowerRailGraph::from_rules(&["disp requires wifi", "wifi requires pmic"]).unwrap();
+    let states = BTreeMap::from([
+        (PowerRail::Pmic, false),
+        (PowerRail::Wifi, false),
+        (PowerRail::Display, true),
+    ]);
+    assert_eq!(
+        graph.missing_prerequisites(PowerRail::Display, &states),
+        [PowerRail::Pmic, PowerRail::Wifi]
+    );
+    assert!(graph
+        .missing_prerequisites(PowerRail::Pmic, &states)
+        .is_empty());
+    // A rail whose state is unknown is not reported
+    assert!(graph
+        .missing_prerequisites(PowerRail::Display, &BTreeMap::new())
+        .is_empty());
+
+    assert_eq!(
+        graph.dependents_on(PowerRail::Pmic, &states),
+        [PowerRail::Display]
+    );
+    assert!(graph.dependents_on(PowerRail::Display, &states).is_empty());
+}
+
+#[tokio::test]
+async fn sequence_power_on_adds_missing_dependencies() {
+    use eink_power_cli::power::rails::PowerRail;
+    use eink_power_cli::power::PowerController;
+
+    let mut connection = Connection::new("/dev/nonexistent", 115200, true).unwrap();
+    connection.set_dry_run(true);
+    let mut controller = PowerController::new(connection);
+
+    let responses = controller
+        .sequence_power_on(&[PowerRail::Wifi, PowerRail::Imx93])
+        .await
+        .unwrap();
+    let rails: Vec<_> = responses.iter().map(|(rail, _)| *rail).collect();
+    assert_eq!(rails, [PowerRail::Pmic, PowerRail::Wifi, PowerRail::Imx93]);
+    assert_eq!(
+        controller.connection().commands_sent(),
+        ["pm pmic on", "pm wifi on"]
+    );
+}
+
+#[tokio::test]
+async fn import_rail_defaults_sets_each_rail_then_saves() {
+    use eink_power_cli::json::PowerDefaults;
+    use eink_power_cli::power::PowerController;
+
+    let mut connection = Connection::new("/dev/nonexistent", 115200, true).unwrap();
+    connection.set_dry_run(true);
+    let mut controller = PowerController::new(connection);
+
+    let defaults = PowerDefaults {
+        pmic: true,
+        wifi: false,
+        disp: true,
+    };
+    // Dry-run responses are empty, so the save verification fails
+    assert!(controller.import_rail_defaults(&defaults).await.is_err());
+    assert_eq!(
+        controller.connection().commands_sent(),
+        [
+            "pm defaults pmic on",
+            "pm defaults wifi off",
+            "pm defaults disp on",
+            "pm defaults",
+            "pm defaults save",
+            "pm defaults",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn set_baud_rate_sends_the_change_and_follows_locally() {
+    use eink_power_cli::power::PowerController;
+    use eink_power_cli::serial::connection::BaudStage;
+
+    let mut connection = Connection::new("/dev/nonexistent", 115200, true).unwrap();
+    connection.set_dry_run(true);
+    let mut controller = PowerController::new(connection);
+
+    let change = controller.set_baud_rate(921600, true).await;
+    assert!(change.success);
+    assert!(change.persist);
+    assert_eq!(change.failure_message(), None);
+    assert_eq!(controller.connection().baud_rate(), 921600);
+    assert_eq!(
+        controller.connection().commands_sent(),
+        ["system baud 921600 --persist"]
+    );
+    let stages: Vec<_> = change.transitions.iter().map(|t| t.stage).collect();
+    assert_eq!(stages, [BaudStage::Requested, BaudStage::Switched]);
+}
+
+#[test]
+fn wake_mask_parses_source_lines_and_raw_mask() {
+    use eink_power_cli::power::wake::{WakeMask, WakeSource};
+
+    let lines = WakeMask::parse(
+        "⏰ Wake Sources:\nWake mask: 0xFF\nUART: disabled\nLPTMR: enabled\nRTC: on\nNFC: off\nBUTTON: enabled",
+    )
+    .unwrap();
+    assert_eq!(
+        lines.sources,
+        vec![WakeSource::Lptmr, WakeSource::Rtc, WakeSource::Button]
+    );
+    assert_eq!(lines.mask, 0x16);
+
+    let raw = WakeMask::parse("Wake mask: 0x0B").unwrap();
+    assert!(raw.contains(WakeSource::Uart));
+    assert!(!raw.contains(WakeSource::Rtc));
+    assert_eq!(WakeMask::parse("Error: unknown command"), None);
+    assert_eq!(WakeMask::parse(""), None);
+}
+
+#[test]
+fn vlls0_without_uart_wake_locks_the_console_out() {
+    use eink_power_cli::power::sleep::VllsMode;
+    use eink_power_cli::power::wake::{console_lockout, WakeMask, WakeSource};
+
+    let all = WakeMask::from_bits(0x1F);
+    let vlls0 = all.effective_in(Some(VllsMode::Vlls0));
+    assert!(!vlls0.contains(WakeSource::Lptmr));
+    assert_eq!(all.effective_in(Some(VllsMode::Vlls1)), all);
+
+    let no_uart = WakeMask::from_bits(0x1F & !WakeSource::Uart.bit());
+    assert!(!console_lockout(Some(VllsMode::Vlls0), false, &all));
+    assert!(console_lockout(Some(VllsMode::Vlls0), false, &no_uart));
+    assert!(console_lockout(Some(VllsMode::Vlls1), false, &no_uart));
+    assert!(!console_lockout(Some(VllsMode::Vlls1), true, &no_uart));
+    assert!(!console_lockout(Some(VllsMode::Vlls3), false, &no_uart));
+    assert!(!console_lockout(None, false, &no_uart));
+
+    // A duration only helps while a timer can still wake the controller
+    let buttons_only = WakeMask::from_bits(WakeSource::Button.bit());
+    assert!(console_lockout(Some(VllsMode::Vlls0), true, &buttons_only));
+}
+
+#[test]
+fn test_prompt_must_end_the_output_on_its_own_line() {
+    assert!(ends_with_prompt("pong\r\nprod:~$ "));
+    assert!(ends_with_prompt("pong\r\ndebug:~$ \r\n"));
+    assert!(ends_with_prompt("pong\r\nuart:~$ "));
+    assert!(ends_with_prompt("pong\r\n\x1b[1;32muart:~$ \x1b[m"));
+
+    // Prompt text inside a reply
+    assert!(!ends_with_prompt("Last wake: uart:~$ "));
+    assert!(!ends_with_prompt("Wake sources: rtc, nfc, uart:"));
+    assert!(!ends_with_prompt("debug:~$ \r\n  nfc status"));
+    assert!(!ends_with_prompt("prod:~$ nfc status"));
+    assert!(!ends_with_prompt(""));
+}
+
+#[test]
+fn test_command_timeouts_follow_precedence() {
+    let default = Duration::from_secs(3);
+    let secs = |policy: &TimeoutPolicy, command: &str| policy.timeout_for(command, default);
+
+    let table = TimeoutPolicy::default();
+    assert_eq!(
+        secs(&table, "pm system erase app"),
+        (
+            Duration::from_secs(30),
+            TimeoutSource::Table("system erase")
+        )
+    );
+    assert_eq!(
+        secs(&table, "nfc init"),
+        (Duration::from_secs(8), TimeoutSource::Table("nfc init"))
+    );
+    assert_eq!(secs(&table, "ping"), (default, TimeoutSource::Default));
+    // Whole words only
+    assert_eq!(secs(&table, "pm system eraser").1, TimeoutSource::Default);
+
+    let config = table.with_config(
+        &[
+            ("system  erase".to_string(), 45),
+            ("pm".to_string(), 5),
+            ("pm system".to_string(), 12),
+        ]
+        .into_iter()
+        .collect(),
+    );
+    assert_eq!(
+        secs(&config, "pm system erase app"),
+        (
+            Duration::from_secs(45),
+            TimeoutSource::Config("system erase".to_string())
+        )
+    );
+    // The longest matching entry wins
+    assert_eq!(secs(&config, "pm system info").0, Duration::from_secs(12));
+    assert_eq!(secs(&config, "pm stats").0, Duration::from_secs(5));
+    assert_eq!(secs(&config, "nfc init").0, Duration::from_secs(8));
+
+    let forced = config.with_forced(Some(Duration::from_secs(2)));
+    assert_eq!(
+        secs(&forced, "pm system erase app"),
+        (Duration::from_secs(2), TimeoutSource::CommandLine)
+    );
+}
+
+#[test]
+fn test_guard_times_follow_the_timeout_table() {
+    let ms = Duration::from_millis;
+    let table = TimeoutPolicy::default();
+    assert_eq!(table.guard_for("pm sleep 1s --vlls1"), Some(ms(500)));
+    assert_eq!(table.guard_for("pm system reset"), Some(ms(1500)));
+    assert_eq!(table.guard_for("ping"), None);
+    // Device sleep leaves the PMU UART alone
+    assert_eq!(table.guard_for("nfc sleep"), None);
+
+    let config = table.with_guards(
+        &[
+            ("pm sleep".to_string(), 800),
+            ("system reset".to_string(), 0),
+        ]
+        .into_iter()
+        .collect(),
+    );
+    assert_eq!(config.guard_for("pm sleep 1s"), Some(ms(800)));
+    assert_eq!(config.guard_for("pm system reset"), None);
+
+    let disabled = config.without_guards(true);
+    assert_eq!(disabled.guard_for("pm sleep 1s"), None);
+}
+
+#[test]
+fn test_command_timeouts_are_read_from_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(
+        &path,
+        "[timeouts]\n\"system erase\" = 40\n\"battery\" = 6\n\n[guards]\n\"pm sleep\" = 750\n",
+    )
+    .unwrap();
+    let config = Config::load(Some(&path)).unwrap();
+    assert_eq!(config.timeouts.get("system erase"), Some(&40));
+    assert_eq!(config.timeouts.get("battery"), Some(&6));
+    assert_eq!(config.guards.get("pm sleep"), Some(&750));
+    assert!(config.to_toml().unwrap().contains("[timeouts]"));
+    assert!(config.to_toml().unwrap().contains("[guards]"));
+
+    std::fs::write(&path, FORKED_FIRMWARE_CONFIG).unwrap();
+    let config = Config::load(Some(&path)).unwrap();
+    assert!(config.timeouts.is_empty());
+    assert!(config.guards.is_empty());
+    assert!(!config.to_toml().unwrap().contains("[timeouts]"));
+}
+
+#[test]
+fn test_destructive_commands_are_recognized() {
+    for command in [
+        "pm pmic off",
+        "powermgr wifi off",
+        "pm system erase app",
+        "pm system erase defaults",
+        "ltc2959 reg_write 0x01 0xA8",
+        "nfc eeprom write 0x010 DEADBEEF",
+        "pm production_reset",
+    ] {
+        assert!(is_destructive(command), "{}", command);
+    }
+    for command in [
+        "pm pmic on",
+        "pm pmic status",
+        "ltc2959 reg_read 0x01",
+        "nfc eeprom read 0x010 4",
+        "pm stats",
+        "version",
+    ] {
+        assert!(!is_destructive(command), "{}", command);
+    }
+}
+
+#[test]
+fn test_already_in_state_replies_are_unchanged_not_failures() {
+    for name in ["unchanged.old.txt", "unchanged.new.txt"] {
+        for reply in fixture_lines(name) {
+            assert_eq!(classify(&reply), ResponseClass::Unchanged, "{}", reply);
+        }
+    }
+}
+
+#[test]
+fn test_genuine_failures_keep_failing() {
+    for reply in fixture_lines("failures.txt") {
+        assert_eq!(classify(&reply), ResponseClass::Failure, "{}", reply);
+    }
+    for reply in [
+        "WIFI power ON",
+        "GPIO A5 set to 1",
+        "NFC RF enabled",
+        "PMIC: ON",
+    ] {
+        assert_eq!(classify(reply), ResponseClass::Success, "{}", reply);
+    }
+}
@@ -0,0 +1,64 @@
+/*
+ * E-ink Power CLI - Provisioning Manifest Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Reading `provision` manifests
+
+use eink_power_cli::cli::ExternalRtcAction;
+use eink_power_cli::provision::{ProvisionManifest, ProvisionStep};
+
+#[test]
+fn manifest_sections_become_steps_in_run_order() {
+    let manifest = ProvisionManifest::parse(
+        r#"
+[health]
+max_voltage_mv = 4200
+
+[rtc]
+action = "auto"
+
+[identity]
+serial = "EINK-1"
+hw_rev = 2
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        manifest.steps(),
+        [
+            ProvisionStep::Identity,
+            ProvisionStep::Rtc,
+            ProvisionStep::Health
+        ]
+    );
+    assert!(!manifest.auto_confirm);
+    assert_eq!(manifest.rtc.unwrap().action, ExternalRtcAction::Auto);
+    let health = manifest.health.unwrap();
+    // The battery check is on unless turned off
+    assert!(health.battery_check);
+    assert_eq!(health.max_voltage_mv, Some(4200));
+    assert_eq!(health.min_voltage_mv, None);
+    assert!(ProvisionManifest::parse("").unwrap().steps().is_empty());
+}
+
+#[test]
+fn manifest_mistakes_are_refused_before_anything_runs() {
+    let error = ProvisionManifest::parse("[battery]\ncapacity = 3000\n").unwrap_err();
+    assert!(error.contains("unknown field `capacity`"), "{}", error);
+
+    let error = ProvisionManifest::parse("[firmware]\nversion = \"2.5\"\n").unwrap_err();
+    assert!(
+        error.starts_with("invalid firmware version '2.5'"),
+        "{}",
+        error
+    );
+
+    let error = ProvisionManifest::parse("[identity]\nserial = \"\"\nhw_rev = 1\n").unwrap_err();
+    assert!(error.contains("serial"), "{}", error);
+
+    let error = ProvisionManifest::parse("[rtc]\naction = \"sleep\"\n").unwrap_err();
+    assert!(error.contains("unknown variant `sleep`"), "{}", error);
+}
@@ -0,0 +1,161 @@
+/*
+ * E-ink Power CLI - Raw Response Storage Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `raw_response` kept in full, truncated, compressed or left out, and read
+//! back from the printed envelope
+
+use eink_power_cli::json::output::parse_envelope;
+use eink_power_cli::json::raw::{
+    self, RawEncoding, RawMode, RawOptions, RawResponseInfo, DEFAULT_RAW_LIMIT,
+};
+use eink_power_cli::json::{parse_output, CommandOutput, JsonResponse};
+use serde_json::json;
+
+/// A reply well over the limit, with multi-byte characters throughout
+fn long_reply() -> String {
+    (0..200)
+        .map(|i| format!("📡 Register 0x{:04X}: 0x{:02X} µs\n", 0x1000 + i, i % 256))
+        .collect()
+}
+
+/// `response` printed as JSON and read back
+fn round_trip(response: &JsonResponse) -> JsonResponse {
+    parse_envelope(&serde_json::to_string(response).unwrap()).unwrap()
+}
+
+fn options(mode: RawMode, compress: bool) -> RawOptions {
+    RawOptions {
+        mode,
+        compress,
+        ..RawOptions::default()
+    }
+}
+
+#[test]
+fn short_replies_are_unchanged_by_default() {
+    let response = JsonResponse::success_with_raw("ping", json!({}), "pong");
+    let printed = serde_json::to_value(&response).unwrap();
+    assert_eq!(printed["raw_response"], "pong");
+    assert!(printed.get("raw_response_info").is_none());
+
+    let read = round_trip(&response);
+    assert_eq!(read.raw_text().unwrap().as_deref(), Some("pong"));
+    assert!(!read.raw_truncated());
+}
+
+#[test]
+fn long_replies_are_truncated_by_default() {
+    let reply = long_reply();
+    assert!(reply.len() > DEFAULT_RAW_LIMIT);
+    let response = JsonResponse::success_with_raw("nfc debug", json!({}), &reply);
+
+    let printed = serde_json::to_value(&response).unwrap();
+    assert_eq!(printed["raw_response_info"]["truncated"], true);
+    assert_eq!(printed["raw_response_info"]["original_length"], reply.len());
+    assert!(printed["raw_response_info"].get("encoding").is_none());
+
+    let read = round_trip(&response);
+    assert!(read.raw_truncated());
+    let text = read.raw_text().unwrap().unwrap();
+    assert!(text.len() <= DEFAULT_RAW_LIMIT);
+    // A character the cut would split is left out whole
+    assert!(text.len() > DEFAULT_RAW_LIMIT - 4);
+    assert!(reply.starts_with(&text));
+}
+
+#[test]
+fn truncation_never_splits_a_character() {
+    let text = "aé📡";
+    assert_eq!(raw::truncate(text, 0), "");
+    assert_eq!(raw::truncate(text, 2), "a");
+    assert_eq!(raw::truncate(text, 3), "aé");
+    assert_eq!(raw::truncate(text, 6), "aé");
+    assert_eq!(raw::truncate(text, 7), "aé📡");
+    assert_eq!(raw::truncate(text, 100), "aé📡");
+}
+
+#[test]
+fn full_keeps_the_reply_as_received() {
+    let reply = long_reply();
+    let response = JsonResponse::success("nfc debug", json!({}))
+        .with_raw(&reply, &options(RawMode::Full, false));
+    assert_eq!(response.raw_response.as_deref(), Some(reply.as_str()));
+    assert_eq!(response.raw_response_info, None);
+    assert_eq!(round_trip(&response).raw_text().unwrap(), Some(reply));
+}
+
+#[test]
+fn omit_leaves_the_reply_out() {
+    let response = JsonResponse::success("nfc debug", json!({"ok": true}))
+        .with_raw(&long_reply(), &options(RawMode::Omit, false));
+    let printed = serde_json::to_value(&response).unwrap();
+    assert_eq!(printed["raw_response"], serde_json::Value::Null);
+    assert!(printed.get("raw_response_info").is_none());
+
+    let read = round_trip(&response);
+    assert_eq!(read.raw_text().unwrap(), None);
+    assert!(!read.raw_truncated());
+}
+
+#[test]
+fn compressed_replies_decode_to_the_original() {
+    let reply = long_reply();
+    let response = JsonResponse::success("nfc debug", json!({}))
+        .with_raw(&reply, &options(RawMode::Full, true));
+    let stored = response.raw_response.as_deref().unwrap();
+    assert!(stored.len() < reply.len() / 4, "{} bytes", stored.len());
+    assert_eq!(
+        response.raw_response_info,
+        Some(RawResponseInfo {
+            truncated: false,
+            original_length: reply.len(),
+            encoding: Some(RawEncoding::ZlibBase64),
+        })
+    );
+
+    let printed = serde_json::to_value(&response).unwrap();
+    assert_eq!(printed["raw_response_info"]["encoding"], "zlib+base64");
+    assert_eq!(round_trip(&response).raw_text().unwrap(), Some(reply));
+}
+
+#[test]
+fn truncated_and_compressed_keeps_the_start() {
+    let reply = long_reply();
+    let response = JsonResponse::success("nfc debug", json!({}))
+        .with_raw(&reply, &options(RawMode::Truncated, true));
+    let read = round_trip(&response);
+    assert!(read.raw_truncated());
+    let text = read.raw_text().unwrap().unwrap();
+    assert_eq!(text, raw::truncate(&reply, DEFAULT_RAW_LIMIT));
+}
+
+#[test]
+fn a_damaged_compressed_reply_is_an_error() {
+    let mut response = JsonResponse::success("nfc debug", json!({}))
+        .with_raw("pong", &options(RawMode::Full, true));
+    response.raw_response = Some("not base64!".to_string());
+    assert!(response.raw_text().is_err());
+    response.raw_response = Some("cG9uZw==".to_string());
+    assert!(response.raw_text().is_err());
+}
+
+#[test]
+fn typed_data_reads_the_same_in_every_form() {
+    let reply = long_reply();
+    for (mode, compress) in [
+        (RawMode::Full, false),
+        (RawMode::Truncated, false),
+        (RawMode::Omit, false),
+        (RawMode::Full, true),
+    ] {
+        let response = JsonResponse::success("examples", json!({"ok": true}))
+            .with_raw(&reply, &options(mode, compress));
+        match parse_output(&serde_json::to_string(&response).unwrap()).unwrap() {
+            CommandOutput::Untyped(data) => assert_eq!(data["ok"], true),
+            other => panic!("{:?}/{}: unexpected output {:?}", mode, compress, other),
+        }
+    }
+}
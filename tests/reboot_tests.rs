@@ -0,0 +1,79 @@
+/*
+ * E-ink Power CLI - Reboot Detection Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Uptime parsing and reset detection across probes
+
+use eink_power_cli::power::reboot::{
+    format_uptime, parse_uptime_ms, RebootDetector, RebootEvidence, SessionState,
+};
+
+#[test]
+fn uptime_parses_milliseconds_or_clock() {
+    assert_eq!(
+        parse_uptime_ms("System Uptime: 0:01:07 (67427 ms)"),
+        Some(67_427)
+    );
+    assert_eq!(parse_uptime_ms("Uptime: 2:00:05"), Some(7_205_000));
+    assert_eq!(
+        parse_uptime_ms("System Uptime: 1d 0:00:01"),
+        Some(86_401_000)
+    );
+    assert_eq!(parse_uptime_ms("Error: unknown command"), None);
+    assert_eq!(format_uptime(3_605_999), "1:00:05");
+}
+
+#[test]
+fn uptime_going_backwards_is_one_reboot() {
+    let mut detector = RebootDetector::new();
+    assert_eq!(detector.observe(Some(60_000), false), None);
+    assert_eq!(detector.observe(Some(90_000), false), None);
+
+    // The banner of the same reset is not counted again
+    let event = detector.observe(Some(3_000), true).unwrap();
+    assert_eq!(event.evidence, RebootEvidence::Uptime);
+    assert_eq!(event.previous_uptime_ms, Some(90_000));
+    assert_eq!(event.reboots, 1);
+    assert_eq!(
+        event.message(),
+        "PMU rebooted (uptime went from 0:01:30 to 0:00:03)"
+    );
+
+    assert_eq!(detector.observe(Some(8_000), false), None);
+    assert_eq!(detector.reboots(), 1);
+}
+
+#[test]
+fn banner_alone_is_a_reboot_after_the_first_probe() {
+    let mut detector = RebootDetector::new();
+    // Printed before the session started
+    assert_eq!(detector.observe(None, true), None);
+
+    let event = detector.observe(None, true).unwrap();
+    assert_eq!(event.evidence, RebootEvidence::BootBanner);
+    assert_eq!(event.message(), "PMU rebooted (boot banner on the console)");
+    assert_eq!(detector.reboots(), 1);
+}
+
+#[test]
+fn banner_without_uptime_resets_the_baseline() {
+    let mut detector = RebootDetector::new();
+    detector.observe(Some(500_000), false);
+    assert!(detector.observe(None, true).is_some());
+    // First readable uptime after the reset is a new baseline, not a second reset
+    assert_eq!(detector.observe(Some(2_000), false), None);
+    assert_eq!(detector.reboots(), 1);
+}
+
+#[test]
+fn session_remembers_monitor_start_until_stopped() {
+    let mut session = SessionState::default();
+    session.record_pm_command("stats");
+    assert!(session.pm_commands().is_empty());
+    session.record_pm_command("monitor start 30");
+    assert_eq!(session.pm_commands(), ["monitor start 30"]);
+    session.record_pm_command("monitor stop");
+    assert!(session.pm_commands().is_empty());
+}
@@ -0,0 +1,217 @@
+/*
+ * E-ink Power CLI - Human Output Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Human output compared against the snapshots in `tests/golden`
+//!
+//! After an intended change to the output, regenerate the snapshots with
+//! `UPDATE_GOLDEN=1 cargo test --test render_tests` and review the diff.
+
+use eink_power_cli::firmware::slots::parse_image_list;
+use eink_power_cli::json::schema::DEVICE_EXAMPLES;
+use eink_power_cli::json::{
+    BatteryJson, BatteryWatchSampleJson, BatteryWatchSummaryJson, ResponseParser,
+};
+use eink_power_cli::power::battery::VoltageHistory;
+use eink_power_cli::power::control::PowerStats;
+use eink_power_cli::power::stats::PowerStatsReport;
+use eink_power_cli::render::{self, OutputStyle};
+use eink_power_cli::simulator::POWER_STATS_REPLY;
+use std::path::PathBuf;
+
+const IMAGE_LIST: &str = "Images:
+ image=0 slot=0
+    version: 2.2.0.298
+    bootable: true
+    flags: active confirmed
+    hash: 3b4c5d6e7f
+ image=0 slot=1
+    version: 2.3.0.12
+    bootable: true
+    flags: pending
+    hash: 9a8b7c6d5e
+Split status: N/A (0)
+";
+
+/// Render with emoji and in plain ASCII and compare each to its snapshot
+fn assert_golden(name: &str, render: impl Fn(&OutputStyle) -> String) {
+    for (variant, style) in [
+        ("emoji", OutputStyle::default()),
+        ("ascii", OutputStyle::ascii()),
+    ] {
+        let rendered = render(&style) + "\n";
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.{}.txt", name, variant));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &rendered).unwrap();
+            continue;
+        }
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1)", path.display(), e));
+        assert_eq!(
+            rendered,
+            expected,
+            "{} differs from its snapshot",
+            path.display()
+        );
+        if variant == "ascii" {
+            assert!(rendered.is_ascii(), "{} is not plain ASCII", path.display());
+        }
+    }
+}
+
+#[test]
+fn battery_matches_snapshot() {
+    let battery = ResponseParser::parse_battery_response(DEVICE_EXAMPLES.battery_example);
+    assert_golden("battery", |style| render::battery(style, &battery).unwrap());
+}
+
+#[test]
+fn power_stats_match_snapshot() {
+    let stats = PowerStats {
+        active_time_ms: Some(123456),
+        sleep_count: Some(42),
+        wake_count: Some(38),
+        rtc_wake_count: Some(15),
+        nfc_wake_count: Some(12),
+        uart_wake_count: Some(11),
+        timestamp: chrono::Utc::now(),
+    };
+    assert_golden("power_stats", |style| render::power_stats(style, &stats));
+}
+
+#[test]
+fn power_stats_report_matches_snapshot() {
+    let stats = PowerStats::parse(POWER_STATS_REPLY);
+    let report = PowerStatsReport::new(stats, Some(3_600_000));
+    assert_golden("power_stats_report", |style| {
+        render::power_stats_report(style, &report)
+    });
+}
+
+#[test]
+fn gpio_matches_snapshot() {
+    let gpio = ResponseParser::parse_gpio_response(DEVICE_EXAMPLES.gpio_example, "A", 0);
+    assert_golden("gpio", |style| render::gpio(style, &gpio).unwrap());
+}
+
+#[test]
+fn firmware_list_matches_snapshot() {
+    let images = parse_image_list(IMAGE_LIST);
+    assert_golden("firmware_list", |style| {
+        render::firmware_images(style, &images).unwrap()
+    });
+}
+
+#[test]
+fn nfc_status_matches_snapshot() {
+    let nfc = ResponseParser::parse_nfc_status(DEVICE_EXAMPLES.nfc_example);
+    assert_golden("nfc_status", |style| {
+        render::nfc_status(style, &nfc).unwrap()
+    });
+}
+
+#[test]
+fn rtc_status_matches_snapshot() {
+    let rtc = ResponseParser::parse_rtc_status(DEVICE_EXAMPLES.rtc_example);
+    assert_golden("rtc_status", |style| {
+        render::rtc_status(style, &rtc).unwrap()
+    });
+}
+
+#[test]
+fn battery_watch_summary_matches_snapshot() {
+    let summary = BatteryWatchSummaryJson {
+        samples: 42,
+        duration_s: 84,
+        voltage_min_mv: Some(7390),
+        voltage_max_mv: Some(7415),
+        voltage_avg_mv: Some(7402.5),
+        current_min_ma: Some(-150),
+        current_max_ma: Some(-131),
+        current_avg_ma: Some(-142.25),
+        charge_delta_mah: Some(-3),
+    };
+    assert_golden("battery_watch_summary", |style| {
+        render::battery_watch_summary(style, &summary)
+    });
+}
+
+#[test]
+fn battery_live_line_is_compact() {
+    let battery = BatteryJson {
+        voltage_mv: Some(7410),
+        current_ma: Some(-142),
+        charge_mah: Some(2340),
+        power_mw: Some(-1052),
+        temperature_c: None,
+    };
+    let sample = BatteryWatchSampleJson::new(battery, Some(3000));
+    let mut voltages = VoltageHistory::new(4);
+    for mv in [7380, 7390, 7400, 7410] {
+        voltages.push(Some(mv));
+    }
+
+    assert_eq!(
+        render::battery_live(&OutputStyle::default(), &sample, &voltages),
+        "V=7.41V I=-142mA P=-1.05W SoC=78% ▁▃▅█"
+    );
+    assert_eq!(
+        render::battery_live(&OutputStyle::ascii(), &sample, &voltages),
+        "V=7.41V I=-142mA P=-1.05W SoC=78% _-=#"
+    );
+}
+
+#[test]
+fn sparkline_of_a_flat_series_sits_mid_range() {
+    let style = OutputStyle::default();
+    assert_eq!(render::sparkline(&style, &[7400, 7400, 7400]), "▄▄▄");
+    assert_eq!(render::sparkline(&style, &[]), "");
+}
+
+#[test]
+fn battery_compact_marks_missing_values() {
+    let battery = ResponseParser::parse_battery_response("Voltage: 3850 mV");
+    let sample = BatteryWatchSampleJson::new(battery, None);
+    assert_eq!(render::battery_compact(&sample), "V=3.85V I=? P=?");
+}
+
+#[test]
+fn unrecognized_replies_render_nothing() {
+    let style = OutputStyle::default();
+    let battery = ResponseParser::parse_battery_response("Battery monitoring enabled");
+    assert_eq!(render::battery(&style, &battery), None);
+    let nfc = ResponseParser::parse_nfc_status("nfc: unknown command");
+    assert_eq!(render::nfc_status(&style, &nfc), None);
+    assert_eq!(render::firmware_images(&style, &parse_image_list("")), None);
+}
+
+#[test]
+fn width_cuts_long_lines() {
+    let style = OutputStyle {
+        width: Some(40),
+        ..OutputStyle::default()
+    };
+    let text = render::firmware_images(&style, &parse_image_list(IMAGE_LIST)).unwrap();
+    for line in text.lines() {
+        assert!(line.chars().count() <= 40, "'{}' is too long", line);
+    }
+    assert!(text.lines().nth(1).unwrap().ends_with("..."));
+}
+
+#[test]
+fn color_marks_headings_and_statuses() {
+    let style = OutputStyle {
+        color: true,
+        ..OutputStyle::ascii()
+    };
+    assert_eq!(style.heading("🔋", "Battery"), "\x1b[1mBattery:\x1b[0m");
+    assert_eq!(
+        render::verify(&style, Some("2.2.0"), &[]),
+        "\x1b[1mFirmware Verification:\x1b[0m\nVersion: 2.2.0\n\x1b[32m[OK]\x1b[0m Firmware meets release policy"
+    );
+}
@@ -0,0 +1,201 @@
+/*
+ * E-ink Power CLI - Monitor Sample Format Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `pm monitor` samples as firmware 2.2 and 2.3 print them
+//!
+//! `tests/fixtures/monitor_fw22.txt` and `monitor_fw23.txt` are console
+//! captures of the two layouts; `monitor_unknown.txt` is a layout no format
+//! knows.
+
+use eink_power_cli::json::samples::{
+    Attempt, CompactFormat, LabeledFormat, SampleFormat, SampleParser, UnparsedLine,
+};
+
+mod common;
+use common::fixture;
+
+fn readings(parser: &mut SampleParser, text: &str) -> Vec<(Option<u16>, Option<i16>)> {
+    parser
+        .parse_all(text)
+        .into_iter()
+        .map(|sample| (sample.battery.voltage_mv, sample.battery.current_ma))
+        .collect()
+}
+
+#[test]
+fn firmware_2_2_compact_samples() {
+    let mut parser = SampleParser::new();
+    let samples = parser.parse_all(&fixture("monitor_fw22.txt"));
+
+    assert_eq!(parser.format(), Some("compact"));
+    let values: Vec<_> = samples
+        .iter()
+        .map(|sample| (sample.battery.voltage_mv, sample.battery.current_ma))
+        .collect();
+    assert_eq!(
+        values,
+        [
+            (Some(7412), Some(-142)),
+            (Some(7409), Some(-139)),
+            (Some(7405), Some(-137))
+        ]
+    );
+    assert_eq!(samples[1].battery.power_mw, Some(-1030));
+    assert_eq!(samples[0].battery.power_mw, None);
+    assert_eq!(samples[2].line, 5);
+    // The start notice and the NFC warning
+    assert_eq!(parser.stats().unparsed, 2);
+}
+
+#[test]
+fn firmware_2_3_labeled_samples() {
+    let mut parser = SampleParser::new().keep_unparsed(true);
+    let samples = parser.parse_all(&fixture("monitor_fw23.txt"));
+
+    assert_eq!(parser.format(), Some("labeled"));
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[0].battery.voltage_mv, Some(7412));
+    assert_eq!(samples[0].battery.current_ma, Some(-142));
+    assert_eq!(samples[0].battery.power_mw, Some(-1052));
+    assert_eq!(samples[0].line, 2);
+    assert_eq!(samples[1].battery.charge_mah, Some(2310));
+    assert_eq!(samples[1].line, 7);
+    assert_eq!(
+        parser.take_unparsed(),
+        [
+            UnparsedLine {
+                line: 1,
+                text:
+                    "[00:01:05.102,000] <inf> power_mgmt: Power monitoring started (interval 5 s)"
+                        .to_string()
+            },
+            UnparsedLine {
+                line: 11,
+                text: "[00:01:14.880,000] <wrn> nfc: field lost".to_string()
+            }
+        ]
+    );
+    assert!(parser.take_unparsed().is_empty());
+}
+
+#[test]
+fn unknown_layout_parses_nothing_and_counts_every_line() {
+    let mut parser = SampleParser::new();
+    assert!(parser.parse_all(&fixture("monitor_unknown.txt")).is_empty());
+
+    assert_eq!(parser.format(), None);
+    assert_eq!(parser.stats().samples, 0);
+    assert_eq!(parser.stats().unparsed, 3);
+    // Not kept unless asked for
+    assert!(parser.take_unparsed().is_empty());
+}
+
+#[test]
+fn samples_split_across_reads_are_held_until_they_end() {
+    let text = fixture("monitor_fw23.txt");
+    let whole = readings(&mut SampleParser::new(), &text);
+
+    // Split the capture at every byte boundary a read could end on
+    for split in (1..text.len()).filter(|at| text.is_char_boundary(*at)) {
+        let mut parser = SampleParser::new();
+        let mut samples = parser.push(&text[..split]);
+        samples.extend(parser.push(&text[split..]));
+        samples.extend(parser.flush());
+        let values: Vec<_> = samples
+            .iter()
+            .map(|sample| (sample.battery.voltage_mv, sample.battery.current_ma))
+            .collect();
+        assert_eq!(values, whole, "split at {}", split);
+    }
+}
+
+#[test]
+fn labeled_sample_at_the_end_of_a_read_waits_for_more() {
+    let mut parser = SampleParser::new();
+    assert!(parser
+        .push("Monitor sample:\r\n  Voltage: 7412 mV\r\n")
+        .is_empty());
+    // The current arrives in the next read
+    assert!(parser.push("  Current: -142 mA\r\n").is_empty());
+    let samples = parser.push("[00:01:08.000,000] <inf> main: tick\r\n");
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0].battery.current_ma, Some(-142));
+    assert_eq!(parser.stats().unparsed, 1);
+}
+
+#[test]
+fn quiet_console_flushes_a_held_sample() {
+    let mut parser = SampleParser::new();
+    assert!(parser
+        .push("Monitor sample:\n  Voltage: 7.412 V\n  Current: -0.142 A\n")
+        .is_empty());
+    let samples = parser.flush();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0].battery.voltage_mv, Some(7412));
+    assert_eq!(samples[0].battery.current_ma, Some(-142));
+}
+
+#[test]
+fn formats_decline_each_others_samples() {
+    assert_eq!(
+        CompactFormat.attempt(&["Monitor sample:", "  Voltage: 7412 mV"], true),
+        Attempt::NoMatch
+    );
+    assert_eq!(
+        LabeledFormat.attempt(&["V=7412mV I=-142mA"], true),
+        Attempt::NoMatch
+    );
+    // A heading alone is not a sample once nothing more can follow
+    assert_eq!(
+        LabeledFormat.attempt(&["Monitor sample:"], true),
+        Attempt::NoMatch
+    );
+    assert_eq!(
+        LabeledFormat.attempt(&["Monitor sample:"], false),
+        Attempt::Incomplete
+    );
+}
+
+/// A layout a later firmware might print, added without touching the parser
+struct JsonFormat;
+
+impl SampleFormat for JsonFormat {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn attempt(&self, lines: &[&str], _complete: bool) -> Attempt {
+        let Some(json) = lines[0].split_once("sample ").map(|(_, json)| json) else {
+            return Attempt::NoMatch;
+        };
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(json) else {
+            return Attempt::NoMatch;
+        };
+        Attempt::Parsed {
+            battery: eink_power_cli::json::BatteryJson {
+                voltage_mv: value["v"].as_u64().map(|v| v as u16),
+                current_ma: value["i"].as_i64().map(|i| i as i16),
+                charge_mah: None,
+                power_mw: None,
+                temperature_c: None,
+            },
+            lines: 1,
+        }
+    }
+}
+
+#[test]
+fn new_formats_plug_into_the_chain() {
+    let mut formats = eink_power_cli::json::samples::default_formats();
+    formats.push(Box::new(JsonFormat));
+    let mut parser = SampleParser::with_formats(formats);
+
+    let values = readings(&mut parser, &fixture("monitor_unknown.txt"));
+    assert_eq!(values[0], (Some(7412), Some(-142)));
+    assert_eq!(values.len(), 3);
+    assert_eq!(parser.format(), Some("json"));
+    assert_eq!(parser.stats().unparsed, 0);
+}
@@ -0,0 +1,86 @@
+/*
+ * E-ink Power CLI - Sampling Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Aggregates of `--samples` runs and their tolerance for failed readings
+
+use eink_power_cli::error::PowerCliError;
+use eink_power_cli::json::{
+    BatteryHealthJson, BatteryHealthSamplesJson, BatteryVerdict, SampleStatsJson,
+};
+use eink_power_cli::power::battery::SampleRun;
+
+fn timeout() -> PowerCliError {
+    PowerCliError::Timeout {
+        timeout: 3,
+        bytes_received: 0,
+        partial: None,
+    }
+}
+
+#[test]
+fn median_is_the_middle_value_or_the_mean_of_the_middle_two() {
+    let odd = SampleStatsJson::of([3850.0, 3600.0, 3852.0]).unwrap();
+    assert_eq!(odd.median, 3850.0);
+    assert_eq!(odd.min, 3600.0);
+    assert_eq!(odd.max, 3852.0);
+    assert!((odd.mean - 3767.333).abs() < 0.001);
+
+    let even = SampleStatsJson::of([3852.0, 3600.0, 3848.0, 3850.0]).unwrap();
+    assert_eq!(even.median, 3849.0);
+    assert!(SampleStatsJson::of([]).is_none());
+}
+
+#[test]
+fn a_run_survives_failures_while_half_the_readings_succeed() {
+    let mut run = SampleRun::new(4);
+    for reading in [Ok(1), Err(timeout()), Ok(2), Err(timeout())] {
+        run.record(reading);
+    }
+    assert_eq!(run.finish().unwrap(), (vec![1, 2], 2));
+
+    let mut run = SampleRun::new(3);
+    for reading in [Err(timeout()), Ok(1), Err(timeout())] {
+        run.record(reading);
+    }
+    let error = run.finish().unwrap_err().to_string();
+    assert!(error.contains("only 1 of 3 samples succeeded"), "{}", error);
+    assert!(error.contains("timeout"), "{}", error);
+}
+
+#[test]
+fn a_tied_verdict_takes_the_worse_one() {
+    let check = |verdict, loaded_mv| BatteryHealthJson {
+        result: Some(format!("{:?}", verdict).to_uppercase()),
+        internal_resistance_mohm: None,
+        loaded_voltage_mv: Some(loaded_mv),
+        unloaded_voltage_mv: None,
+        verdict: Some(verdict),
+    };
+
+    let tied = BatteryHealthSamplesJson::new(
+        2,
+        0,
+        vec![
+            check(BatteryVerdict::Healthy, 3700),
+            check(BatteryVerdict::Degraded, 3500),
+        ],
+    );
+    assert_eq!(tied.median.verdict, Some(BatteryVerdict::Degraded));
+    assert_eq!(tied.median.result.as_deref(), Some("DEGRADED"));
+    assert_eq!(tied.median.loaded_voltage_mv, Some(3600));
+
+    let outvoted = BatteryHealthSamplesJson::new(
+        3,
+        0,
+        vec![
+            check(BatteryVerdict::Healthy, 3700),
+            check(BatteryVerdict::Failed, 3100),
+            check(BatteryVerdict::Healthy, 3710),
+        ],
+    );
+    assert_eq!(outvoted.median.verdict, Some(BatteryVerdict::Healthy));
+    assert_eq!(outvoted.median.loaded_voltage_mv, Some(3700));
+}
@@ -0,0 +1,153 @@
+/*
+ * E-ink Power CLI - Schedule Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Due times, command validation and pruning of deferred commands
+
+use chrono::{DateTime, Local, TimeDelta, TimeZone, Utc};
+use eink_power_cli::schedule::{
+    parse_due, validate_command, ScheduleBackend, ScheduleStatus, ScheduledCommand, Schedules,
+};
+use std::path::Path;
+
+fn now() -> DateTime<Local> {
+    Local.with_ymd_and_hms(2025, 10, 9, 14, 0, 0).unwrap()
+}
+
+fn words(command: &str) -> Vec<String> {
+    command.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn relative_due_times_count_from_now() {
+    assert_eq!(
+        parse_due("+90s", now()).unwrap(),
+        now() + TimeDelta::seconds(90)
+    );
+    assert_eq!(
+        parse_due("+1h30m", now()).unwrap(),
+        now() + TimeDelta::minutes(90)
+    );
+}
+
+#[test]
+fn time_of_day_rolls_over_to_tomorrow_once_past() {
+    let later = parse_due("22:00", now()).unwrap();
+    assert_eq!(
+        later,
+        Local.with_ymd_and_hms(2025, 10, 9, 22, 0, 0).unwrap()
+    );
+
+    let earlier = parse_due("09:30:15", now()).unwrap();
+    assert_eq!(
+        earlier,
+        Local.with_ymd_and_hms(2025, 10, 10, 9, 30, 15).unwrap()
+    );
+}
+
+#[test]
+fn absolute_due_times_are_local_or_rfc3339() {
+    assert_eq!(
+        parse_due("2025-12-24 22:00", now()).unwrap(),
+        Local.with_ymd_and_hms(2025, 12, 24, 22, 0, 0).unwrap()
+    );
+    assert_eq!(
+        parse_due("2025-12-24T22:00:30", now()).unwrap(),
+        Local.with_ymd_and_hms(2025, 12, 24, 22, 0, 30).unwrap()
+    );
+    assert_eq!(
+        parse_due("2030-01-01T00:00:00Z", now()).unwrap(),
+        Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap()
+    );
+}
+
+#[test]
+fn past_and_malformed_due_times_are_rejected() {
+    assert!(parse_due("2020-01-01 00:00", now())
+        .unwrap_err()
+        .contains("in the past"));
+    assert!(parse_due("+0s", now()).is_err());
+    assert!(parse_due("+soon", now()).is_err());
+    assert!(parse_due("tomorrow", now()).is_err());
+    assert!(parse_due("25:00", now()).is_err());
+}
+
+#[test]
+fn scheduled_commands_are_parsed_like_the_command_line() {
+    assert_eq!(
+        validate_command(&words("power display off"))
+            .unwrap()
+            .name(),
+        "power display"
+    );
+    assert!(validate_command(&words("pm sleep --time 8h")).is_ok());
+    assert!(validate_command(&words("power display sideways")).is_err());
+    assert!(validate_command(&words("bogus")).is_err());
+}
+
+#[test]
+fn commands_that_never_finish_cannot_be_scheduled() {
+    for command in [
+        "monitor --continuous",
+        "battery read --watch",
+        "schedule list",
+        "setup --yes",
+    ] {
+        let error = validate_command(&words(command)).unwrap_err();
+        assert!(
+            error.contains("cannot be scheduled"),
+            "{}: {}",
+            command,
+            error
+        );
+    }
+    assert!(validate_command(&words("monitor")).is_ok());
+}
+
+#[test]
+fn systemd_timer_runs_the_hidden_runner_at_the_due_time() {
+    let due = Utc.with_ymd_and_hms(2030, 1, 1, 22, 0, 0).unwrap();
+    let entry = ScheduledCommand::new(
+        due,
+        words("power display off"),
+        words("--device /dev/ttyLP2 --baud 115200"),
+        ScheduleBackend::Systemd,
+    );
+    let args = entry.systemd_run_args(Path::new("/usr/bin/eink-power-cli"));
+
+    assert!(args.contains(&format!("--unit=eink-power-cli-schedule-{}", entry.id)));
+    assert!(args.contains(&"--on-calendar=2030-01-01 22:00:00 UTC".to_string()));
+    let runner = args.iter().position(|arg| arg == "--").unwrap();
+    assert_eq!(
+        args[runner + 1..].join(" "),
+        format!(
+            "/usr/bin/eink-power-cli --device /dev/ttyLP2 --baud 115200 schedule run {}",
+            entry.id
+        )
+    );
+}
+
+#[test]
+fn finished_schedules_are_pruned_after_a_week() {
+    let due = Utc.with_ymd_and_hms(2025, 10, 1, 12, 0, 0).unwrap();
+    let entry = |status| ScheduledCommand {
+        status,
+        finished_at: Some(due),
+        ..ScheduledCommand::new(due, words("ping"), Vec::new(), ScheduleBackend::Detach)
+    };
+    let mut schedules = Schedules {
+        entries: vec![
+            entry(ScheduleStatus::Pending),
+            entry(ScheduleStatus::Done),
+            entry(ScheduleStatus::Cancelled),
+        ],
+    };
+
+    schedules.prune(due + TimeDelta::days(6));
+    assert_eq!(schedules.entries.len(), 3);
+    schedules.prune(due + TimeDelta::days(8));
+    assert_eq!(schedules.entries.len(), 1);
+    assert_eq!(schedules.entries[0].status, ScheduleStatus::Pending);
+}
@@ -0,0 +1,302 @@
+/*
+ * E-ink Power CLI - Response Section Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Compound responses split into sections, and the typed parsers on top
+//!
+//! The `*_fw23.txt` fixtures are firmware 2.3 console captures of
+//! `system info`, `nfc status`, `nfc debug` and `ltc2959 status`;
+//! `image_list.txt` is `mcumgr image list` output.
+
+use eink_power_cli::firmware::slots::parse_image_list;
+use eink_power_cli::json::output::{parse_output, CommandOutput, ParsedPayload};
+use eink_power_cli::json::schema::DEVICE_EXAMPLES;
+use eink_power_cli::json::sections::{split_pair, SectionedJson};
+use eink_power_cli::json::{BuildType, JsonResponse, ResponseParser};
+
+mod common;
+use common::fixture;
+
+/// Section names, and each section's keys in order
+fn outline(sectioned: &SectionedJson) -> Vec<(Option<&str>, Vec<&str>)> {
+    sectioned
+        .sections
+        .iter()
+        .map(|section| {
+            let keys = section.fields.iter().map(|f| f.key.as_str()).collect();
+            (section.name.as_deref(), keys)
+        })
+        .collect()
+}
+
+#[test]
+fn system_info_splits_at_the_marker() {
+    let sectioned = SectionedJson::parse(&fixture("system_info_fw23.txt"));
+    assert_eq!(
+        outline(&sectioned),
+        [
+            (
+                Some("System Information"),
+                vec![
+                    "Board",
+                    "SoC",
+                    "HW Version",
+                    "Version",
+                    "Build",
+                    "Build Type"
+                ]
+            ),
+            (Some("Runtime"), vec!["System Uptime", "Reset Cause"]),
+        ]
+    );
+    let runtime = sectioned.section("runtime").unwrap();
+    assert_eq!(runtime.get("reset cause"), Some("Power-on"));
+    // A log line is not a pair
+    assert_eq!(runtime.lines, ["[00:01:05.102,000] <inf> main: heartbeat"]);
+}
+
+#[test]
+fn system_info_reads_every_field_by_label() {
+    let info = ResponseParser::parse_system_info(&fixture("system_info_fw23.txt"));
+    assert_eq!(
+        info.board.as_deref(),
+        Some("MCXC143VFM E-Ink Power Controller")
+    );
+    assert_eq!(info.soc.as_deref(), Some("NXP MCXC143VFM (ARM Cortex-M0+)"));
+    // `HW Version:` comes first but is not the `Version:` line
+    assert_eq!(info.version.as_deref(), Some("2.3.0-+4d1e2a9.12"));
+    assert_eq!(info.version_info.semver.as_deref(), Some("2.3.0"));
+    assert_eq!(info.build_date.as_deref(), Some("2025-11-02 09:41:07 UTC"));
+    assert_eq!(info.build_type, Some(BuildType::Production));
+    assert_eq!(info.uptime.as_deref(), Some("1d 2:03:04 (93784000 ms)"));
+}
+
+#[test]
+fn nfc_status_with_emoji_and_indentation() {
+    let text = fixture("nfc_status_fw23.txt");
+    let sectioned = SectionedJson::parse(&text);
+    assert_eq!(
+        outline(&sectioned),
+        [(
+            Some("NFC Status"),
+            vec![
+                "NTA5332 Status",
+                "RF Field",
+                "NFC Active",
+                "I2C Ready",
+                "EEPROM",
+                "SRAM"
+            ]
+        )]
+    );
+
+    let nfc = ResponseParser::parse_nfc_status(&text);
+    assert_eq!(nfc.status_register.as_deref(), Some("0x06"));
+    assert_eq!(nfc.rf_field.as_deref(), Some("Present"));
+    assert_eq!(nfc.nfc_active, Some(true));
+    assert_eq!(nfc.i2c_ready, Some(true));
+    assert_eq!(nfc.eeprom_status.as_deref(), Some("Ready"));
+    assert_eq!(
+        nfc.sram_status.as_deref(),
+        Some("Mailbox (64 bytes pending)")
+    );
+}
+
+#[test]
+fn nfc_debug_sections_tables_and_repeated_keys() {
+    let text = fixture("nfc_debug_fw23.txt");
+    let sectioned = SectionedJson::parse(&text);
+    assert_eq!(
+        outline(&sectioned),
+        [
+            (
+                Some("NTA5332 Debug"),
+                vec!["Session registers", "Config registers", "ED pin", "Field"]
+            ),
+            (
+                Some("Status"),
+                vec![
+                    "NTA5332 Status",
+                    "RF Field",
+                    "NFC Active",
+                    "I2C Ready",
+                    "EEPROM",
+                    "SRAM"
+                ]
+            ),
+            (Some("I2C"), vec!["Errors"]),
+        ]
+    );
+    let i2c = sectioned.section("I2C").unwrap();
+    assert_eq!(i2c.get("Errors"), Some("0"));
+    assert_eq!(
+        i2c.lines,
+        [
+            "Address | Register | Value",
+            "0x54    | 0x10A0   | 0x06",
+            "0x54    | 0x10A1   | 0x48",
+            "Errors: 2"
+        ]
+    );
+
+    // The status block of the dump reads like `nfc status`
+    let nfc = ResponseParser::parse_nfc_status(&text);
+    assert_eq!(nfc.status_register.as_deref(), Some("0x06"));
+    assert_eq!(nfc.rf_field.as_deref(), Some("Present"));
+    assert_eq!(nfc.sram_status.as_deref(), Some("Idle"));
+}
+
+#[test]
+fn ltc2959_status_and_measurements() {
+    let text = fixture("ltc2959_status_fw23.txt");
+    let sectioned = SectionedJson::parse(&text);
+    assert_eq!(
+        outline(&sectioned),
+        [
+            (
+                Some("LTC2959 Status"),
+                vec![
+                    "LTC2959 Status Register",
+                    "ADC Mode",
+                    "Coulomb Counter",
+                    "Charge Complete"
+                ]
+            ),
+            (
+                Some("LTC2959 Measurements"),
+                vec!["Voltage", "Current", "Charge", "Power"]
+            ),
+        ]
+    );
+
+    let status = ResponseParser::parse_ltc2959_status(&text);
+    assert_eq!(status.status_register.as_deref(), Some("0x01"));
+    assert_eq!(status.adc_mode.as_deref(), Some("Continuous V/I"));
+    assert_eq!(status.coulomb_counter.as_deref(), Some("Enabled"));
+    assert_eq!(status.charge_complete, Some(false));
+    assert_eq!(status.voltage_mv, Some(3850));
+    assert_eq!(status.current_ma, Some(-142));
+    assert_eq!(status.charge_mah, Some(2310));
+    assert_eq!(status.power_mw, Some(-547));
+}
+
+#[test]
+fn image_list_slots_are_sections() {
+    let text = fixture("image_list.txt");
+    let names: Vec<_> = SectionedJson::parse(&text)
+        .sections
+        .into_iter()
+        .map(|section| section.name)
+        .collect();
+    assert_eq!(
+        names,
+        [
+            Some("image=0 slot=0".to_string()),
+            Some("image=0 slot=1".to_string()),
+            None
+        ]
+    );
+
+    let slots = parse_image_list(&text);
+    assert_eq!(slots.len(), 2);
+    assert_eq!(slots[0].version.as_deref(), Some("2.3.0.12"));
+    assert!(slots[0].active && slots[0].confirmed);
+    assert_eq!(slots[1].hash.as_deref(), Some("3b4c5d6e7f"));
+    assert_eq!(slots[1].flags(), "");
+}
+
+#[test]
+fn reference_examples_parse_the_same_through_sections() {
+    let info = ResponseParser::parse_system_info(DEVICE_EXAMPLES.system_info_example);
+    assert_eq!(info.version.as_deref(), Some("2.2.0-+0fa46fb-dirty.298"));
+    assert_eq!(info.build_date.as_deref(), Some("2025-10-09 11:13:59 UTC"));
+    assert_eq!(info.build_type, Some(BuildType::Debug));
+    assert_eq!(info.uptime.as_deref(), Some("0:01:07 (67427 ms)"));
+
+    let nfc = ResponseParser::parse_nfc_status(DEVICE_EXAMPLES.nfc_example);
+    assert_eq!(nfc.status_register.as_deref(), Some("0x02"));
+    assert_eq!(nfc.i2c_ready, Some(true));
+
+    let ltc = ResponseParser::parse_ltc2959_status(DEVICE_EXAMPLES.ltc2959_example);
+    assert_eq!(ltc.adc_mode.as_deref(), Some("Smart Sleep"));
+    assert_eq!(ltc.coulomb_counter.as_deref(), Some("Enabled"));
+}
+
+#[test]
+fn labels_inside_other_lines_are_still_found() {
+    // Older firmware prints several fields on one line
+    let info = ResponseParser::parse_system_info("Board: EPC | Version: 2.1.0 | Build Type: Debug");
+    assert_eq!(info.version.as_deref(), Some("2.1.0 | Build Type: Debug"));
+    assert_eq!(
+        info.board.as_deref(),
+        Some("EPC | Version: 2.1.0 | Build Type: Debug")
+    );
+}
+
+#[test]
+fn pairs_need_a_label_before_the_colon() {
+    assert_eq!(
+        split_pair("  ⚡ Current: -142 mA"),
+        Some(("Current", "-142 mA"))
+    );
+    assert_eq!(
+        split_pair("Build: 11:13:59 UTC"),
+        Some(("Build", "11:13:59 UTC"))
+    );
+    assert_eq!(
+        split_pair("Last wake: uart:~$"),
+        Some(("Last wake", "uart:~$"))
+    );
+    assert_eq!(
+        split_pair("Console scrollback:"),
+        Some(("Console scrollback", ""))
+    );
+    assert_eq!(split_pair("uart:~$ nfc status"), None);
+    assert_eq!(split_pair("[00:01:14.880,000] <wrn> nfc: field lost"), None);
+    assert_eq!(split_pair("0x54: 06 48"), None);
+    assert_eq!(split_pair("V=7412mV I=-142mA"), None);
+}
+
+#[test]
+fn blank_lines_and_rules_split_unnamed_sections() {
+    let sectioned = SectionedJson::parse("a: 1\n\nb: 2\n----------\nc: 3\n");
+    assert_eq!(
+        outline(&sectioned),
+        [(None, vec!["a"]), (None, vec!["b"]), (None, vec!["c"])]
+    );
+    assert_eq!(sectioned.get("C"), Some("3"));
+    assert!(SectionedJson::parse("\n\n").sections.is_empty());
+}
+
+#[test]
+fn commands_without_a_struct_get_sectioned_json() {
+    let text = fixture("nfc_debug_fw23.txt");
+    let output = CommandOutput::from_response(&ParsedPayload::Raw, &text);
+    let data = serde_json::to_value(&output).unwrap();
+    assert_eq!(data["sections"][0]["name"], "NTA5332 Debug");
+    assert_eq!(data["sections"][0]["fields"]["ED pin"], "low");
+    assert_eq!(
+        data["sections"][2]["lines"][0],
+        "Address | Register | Value"
+    );
+    // Pairs are written in the order printed
+    let printed = serde_json::to_string(&output).unwrap();
+    assert!(
+        printed.find("Session registers") < printed.find("Config registers"),
+        "{}",
+        printed
+    );
+    assert!(data.get("parsed").is_none());
+
+    let envelope = JsonResponse::success_with_raw("nfc debug", data, &text);
+    let json = serde_json::to_string(&envelope).unwrap();
+    match parse_output(&json).unwrap() {
+        CommandOutput::Sectioned(read) => {
+            assert_eq!(read.sections.len(), 3);
+            assert_eq!(read.section("status").unwrap().get("SRAM"), Some("Idle"));
+        }
+        other => panic!("unexpected output {:?}", other),
+    }
+}
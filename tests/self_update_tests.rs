@@ -0,0 +1,292 @@
+/*
+ * E-ink Power CLI - Self-Update Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! `self-update` against a local artifact server: releases found or not,
+//! downloads that fail verification and installs that cannot replace the
+//! executable, none of which may touch the installed file
+
+use base64::Engine;
+use eink_power_cli::error::PowerCliError;
+use eink_power_cli::update::{self, UpdateSource, UpdateStatus, MANIFEST_FILE};
+use semver::Version;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const INSTALLED: &[u8] = b"#!/bin/sh\necho 2.5.0\n";
+const RELEASE: &[u8] = b"#!/bin/sh\necho 2.6.0\n";
+
+/// Serve `files` by path on a local port until the test process exits;
+/// returns the server root
+fn serve(files: HashMap<String, Vec<u8>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let root = format!("http://{}", listener.local_addr().unwrap());
+    let files = Arc::new(files);
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request = String::new();
+            reader.read_line(&mut request).unwrap();
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap() > 2 {
+                header.clear();
+            }
+            let path = request.split_whitespace().nth(1).unwrap_or("/");
+            let (status, body) = match files.get(path) {
+                Some(body) => ("200 OK", body.as_slice()),
+                None => ("404 Not Found", &b""[..]),
+            };
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            let _ = stream.write_all(body);
+        }
+    });
+    root
+}
+
+/// Server publishing `version` on the stable channel, with `checksum` as
+/// the detached SHA-256 of [`RELEASE`] unless it is `None`
+fn artifact_server(version: &str, checksum: Option<&str>) -> UpdateSource {
+    let source = UpdateSource::new("http://unused", "stable");
+    let path = |file: &str| {
+        source
+            .file_url(file)
+            .trim_start_matches("http://unused")
+            .to_string()
+    };
+    let mut files = HashMap::from([
+        (
+            path(MANIFEST_FILE),
+            format!(
+                r#"{{"version": "{}", "artifact": "eink-power-cli"}}"#,
+                version
+            )
+            .into_bytes(),
+        ),
+        (path("eink-power-cli"), RELEASE.to_vec()),
+    ]);
+    if let Some(checksum) = checksum {
+        files.insert(
+            path("eink-power-cli.sha256"),
+            format!("{}  eink-power-cli\n", checksum).into_bytes(),
+        );
+    }
+    UpdateSource::new(&serve(files), "stable")
+}
+
+fn sha256(contents: &[u8]) -> String {
+    Sha256::digest(contents)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// An installed executable in a directory of its own
+fn installed() -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let executable = dir.path().join("eink-power-cli");
+    std::fs::write(&executable, INSTALLED).unwrap();
+    (dir, executable)
+}
+
+fn current() -> Version {
+    Version::new(2, 5, 0)
+}
+
+fn files_in(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn same_version_is_up_to_date_and_leaves_the_executable() {
+    let source = artifact_server("2.5.0", Some(&sha256(RELEASE)));
+    let (dir, executable) = installed();
+
+    let report = update::self_update(&source, &current(), None, false, &executable).unwrap();
+    assert_eq!(report.status, UpdateStatus::UpToDate);
+    assert_eq!(report.latest_version, "2.5.0");
+    assert_eq!(std::fs::read(&executable).unwrap(), INSTALLED);
+    assert_eq!(files_in(dir.path()), vec!["eink-power-cli"]);
+}
+
+#[test]
+fn older_release_is_not_installed() {
+    let source = artifact_server("2.4.1", Some(&sha256(RELEASE)));
+    let (_dir, executable) = installed();
+
+    let report = update::self_update(&source, &current(), None, false, &executable).unwrap();
+    assert_eq!(report.status, UpdateStatus::UpToDate);
+    assert_eq!(std::fs::read(&executable).unwrap(), INSTALLED);
+}
+
+#[test]
+fn check_only_reports_the_newer_release_without_installing() {
+    let source = artifact_server("2.6.0", Some(&sha256(RELEASE)));
+    let (dir, executable) = installed();
+
+    let report = update::self_update(&source, &current(), None, true, &executable).unwrap();
+    assert_eq!(report.status, UpdateStatus::Available);
+    assert_eq!(report.latest_version, "2.6.0");
+    assert_eq!(
+        report.artifact_url.as_deref(),
+        Some(source.file_url("eink-power-cli").as_str())
+    );
+    assert_eq!(std::fs::read(&executable).unwrap(), INSTALLED);
+    assert_eq!(files_in(dir.path()), vec!["eink-power-cli"]);
+}
+
+#[test]
+fn newer_release_replaces_the_executable_and_keeps_a_rollback() {
+    let source = artifact_server("2.6.0", Some(&sha256(RELEASE)));
+    let (dir, executable) = installed();
+
+    let report = update::self_update(&source, &current(), None, false, &executable).unwrap();
+    assert_eq!(report.status, UpdateStatus::Updated);
+    assert!(!report.signature_verified);
+    assert_eq!(std::fs::read(&executable).unwrap(), RELEASE);
+    let backup = report.backup.expect("backup");
+    assert_eq!(backup, dir.path().join("eink-power-cli.old"));
+    assert_eq!(std::fs::read(&backup).unwrap(), INSTALLED);
+    assert_eq!(
+        files_in(dir.path()),
+        vec!["eink-power-cli", "eink-power-cli.old"]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn replacement_keeps_the_executable_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let source = artifact_server("2.6.0", Some(&sha256(RELEASE)));
+    let (_dir, executable) = installed();
+    std::fs::set_permissions(&executable, std::fs::Permissions::from_mode(0o750)).unwrap();
+
+    update::self_update(&source, &current(), None, false, &executable).unwrap();
+    let mode = std::fs::metadata(&executable).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o750);
+}
+
+#[test]
+fn bad_checksum_is_rejected_before_anything_is_written() {
+    let source = artifact_server("2.6.0", Some(&sha256(b"some other build")));
+    let (dir, executable) = installed();
+
+    let err = update::self_update(&source, &current(), None, false, &executable).unwrap_err();
+    match &err {
+        PowerCliError::UpdateError { message } => {
+            assert!(message.contains("checksum mismatch"), "{}", message)
+        }
+        other => panic!("expected UpdateError, got {:?}", other),
+    }
+    assert_eq!(std::fs::read(&executable).unwrap(), INSTALLED);
+    assert_eq!(files_in(dir.path()), vec!["eink-power-cli"]);
+}
+
+#[test]
+fn missing_checksum_is_rejected() {
+    let source = artifact_server("2.6.0", None);
+    let (dir, executable) = installed();
+
+    let err = update::self_update(&source, &current(), None, false, &executable).unwrap_err();
+    assert!(err.to_string().contains("HTTP 404"), "{}", err);
+    assert_eq!(std::fs::read(&executable).unwrap(), INSTALLED);
+    assert_eq!(files_in(dir.path()), vec!["eink-power-cli"]);
+}
+
+#[test]
+fn signature_from_another_key_is_rejected() {
+    let source = artifact_server("2.6.0", Some(&sha256(RELEASE)));
+    let (dir, executable) = installed();
+    // Release signing key of the minisign documentation, which the fixture
+    // server has no signature from
+    let key = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+
+    let err = update::self_update(&source, &current(), Some(key), false, &executable).unwrap_err();
+    assert!(
+        matches!(err, PowerCliError::UpdateError { .. }),
+        "{:?}",
+        err
+    );
+    assert_eq!(std::fs::read(&executable).unwrap(), INSTALLED);
+    assert_eq!(files_in(dir.path()), vec!["eink-power-cli"]);
+}
+
+#[test]
+fn forged_signature_is_rejected() {
+    let key = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+    let key_id = &base64::engine::general_purpose::STANDARD
+        .decode(key)
+        .unwrap()[2..10];
+    let encode = |bytes: &[u8]| base64::engine::general_purpose::STANDARD.encode(bytes);
+    let signature_line = [b"ED".as_slice(), key_id, &[0u8; 64]].concat();
+    let signature = format!(
+        "untrusted comment: forged\n{}\ntrusted comment: forged\n{}\n",
+        encode(&signature_line),
+        encode(&[0u8; 64])
+    );
+
+    let err = update::verify_signature(RELEASE, &signature, key).unwrap_err();
+    assert!(err.contains("signature check failed"), "{}", err);
+}
+
+#[cfg(unix)]
+#[test]
+fn unwritable_directory_leaves_the_executable_in_place() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let source = artifact_server("2.6.0", Some(&sha256(RELEASE)));
+    let (dir, executable) = installed();
+    std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o555)).unwrap();
+    if std::fs::write(dir.path().join("probe"), b"").is_ok() {
+        // Running as root: directory permissions are not enforced
+        std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+        eprintln!("skipped: directory permissions not enforced for this user");
+        return;
+    }
+
+    let result = update::self_update(&source, &current(), None, false, &executable);
+    std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755)).unwrap();
+    match result {
+        Err(PowerCliError::UpdateError { message }) => {
+            assert!(message.contains("Permission denied"), "{}", message)
+        }
+        other => panic!("expected UpdateError, got {:?}", other),
+    }
+    assert_eq!(std::fs::read(&executable).unwrap(), INSTALLED);
+    assert_eq!(files_in(dir.path()), vec!["eink-power-cli"]);
+}
+
+#[test]
+fn unreachable_server_is_an_update_error() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let root = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    let (_dir, executable) = installed();
+
+    let err = update::self_update(
+        &UpdateSource::new(&root, "stable"),
+        &current(),
+        None,
+        true,
+        &executable,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("cannot reach"), "{}", err);
+}
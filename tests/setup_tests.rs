@@ -0,0 +1,127 @@
+/*
+ * E-ink Power CLI - Setup Wizard Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! The configuration file written by `setup` and the wizard's questions
+
+use eink_power_cli::cli::OutputFormat;
+use eink_power_cli::config::Config;
+use eink_power_cli::error::PowerCliError;
+use eink_power_cli::serial::{CommandFamily, CommandMap};
+use eink_power_cli::setup::{Prompter, Setup, SetupAnswers};
+use std::io::Cursor;
+
+fn answers() -> SetupAnswers {
+    SetupAnswers {
+        device: "/dev/ttyLP2".into(),
+        format: OutputFormat::Human,
+        timeout: 3,
+        capacity_mah: None,
+        profile: None,
+    }
+}
+
+/// Prompter answering from `script`, one line per question
+fn scripted(script: &str) -> Prompter<Cursor<Vec<u8>>, Vec<u8>> {
+    Prompter::new(Cursor::new(script.as_bytes().to_vec()), Vec::new())
+}
+
+#[test]
+fn saved_config_loads_back_unchanged() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("eink-power-cli/config.toml");
+    let mut config = answers().apply(Config::default());
+    config.profile = Some("bench".into());
+    config.output.format = Some(OutputFormat::Ndjson);
+    config.battery.capacity_mah = Some(3000);
+    config.connection.pacing_ms = Some(20);
+    config.commands = CommandMap::default().with_root(CommandFamily::Ltc2959, "gauge");
+
+    config.save(&path).unwrap();
+    assert_eq!(Config::load(Some(&path)).unwrap(), config);
+}
+
+#[test]
+fn unset_options_are_left_out_of_the_file() {
+    let toml = answers().apply(Config::default()).to_toml().unwrap();
+    assert_eq!(
+        toml,
+        "[connection]\ndevice = \"/dev/ttyLP2\"\ntimeout = 3\n\n[output]\nformat = \"human\"\n\n[battery]\n"
+    );
+}
+
+#[test]
+fn answers_override_the_offered_defaults() {
+    let mut prompter = scripted("json\n10\n3000\nfleet\n");
+    let asked = answers().ask(&mut prompter).unwrap();
+    assert_eq!(asked.format, OutputFormat::Json);
+    assert_eq!(asked.timeout, 10);
+    assert_eq!(asked.capacity_mah, Some(3000));
+    assert_eq!(asked.profile.as_deref(), Some("fleet"));
+}
+
+#[test]
+fn empty_answers_keep_the_defaults() {
+    let mut prompter = scripted("\n\n\n\n");
+    assert_eq!(answers().ask(&mut prompter).unwrap(), answers());
+}
+
+#[test]
+fn invalid_answers_are_asked_again() {
+    let mut prompter = scripted("xml\nJSON\nsoon\n5\n\n\n");
+    let asked = answers().ask(&mut prompter).unwrap();
+    assert_eq!(asked.format, OutputFormat::Json);
+    assert_eq!(asked.timeout, 5);
+}
+
+#[test]
+fn running_out_of_answers_fails() {
+    let mut prompter = scripted("json\n");
+    let err = answers().ask(&mut prompter).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn port_is_chosen_by_number_or_path() {
+    let ports = ["/dev/ttyLP2".to_string(), "/dev/ttyUSB0".to_string()];
+    assert_eq!(
+        scripted("2\n").choose_port(&ports, "/dev/ttyLP2").unwrap(),
+        "/dev/ttyUSB0"
+    );
+    assert_eq!(
+        scripted("/dev/ttyACM0\n")
+            .choose_port(&ports, "/dev/ttyLP2")
+            .unwrap(),
+        "/dev/ttyACM0"
+    );
+    assert_eq!(
+        scripted("\n").choose_port(&ports, "/dev/ttyLP2").unwrap(),
+        "/dev/ttyLP2"
+    );
+}
+
+#[tokio::test]
+async fn existing_config_is_kept_without_force() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "[connection]\npacing_ms = 20\n").unwrap();
+
+    let setup = Setup {
+        path: path.clone(),
+        force: false,
+        baud: 115200,
+        answers: answers(),
+    };
+    let err = setup
+        .run(None::<&mut Prompter<Cursor<Vec<u8>>, Vec<u8>>>)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, PowerCliError::InvalidArguments { .. }));
+    assert!(err.to_string().contains("--force"), "{}", err);
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "[connection]\npacing_ms = 20\n"
+    );
+}
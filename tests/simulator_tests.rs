@@ -0,0 +1,4116 @@
+/*
+ * E-ink Power CLI - Simulator Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! End-to-end tests against the scripted PMU simulator
+//!
+//! Each test drives either the library client or the real binary over a
+//! pseudo-terminal, so they run under plain `cargo test` without hardware.
+#![cfg(unix)]
+
+use assert_cmd::Command;
+use eink_power_cli::audit::{AuditCheck, PowerAudit};
+use eink_power_cli::cli::{OutputFormat, SimScenario};
+use eink_power_cli::config::Config;
+use eink_power_cli::error::PowerCliError;
+use eink_power_cli::firmware::{FirmwareManager, FirmwareTransports};
+use eink_power_cli::fleet::{FleetChangeKind, FleetInventory, ProbeStatus};
+use eink_power_cli::hil::HilStepStatus;
+use eink_power_cli::json::{
+    parse_envelope, parse_output, BatteryVerdict, CommandOutput, ResponseParser,
+};
+use eink_power_cli::power::battery::ChargingState;
+use eink_power_cli::power::charger::ChargerState;
+use eink_power_cli::power::control::PowerState;
+use eink_power_cli::power::coulomb::{CoulombDelta, COULOMB_DELTA_FILE};
+use eink_power_cli::power::gpio::{GpioScript, GpioScriptMode};
+use eink_power_cli::power::identity::DeviceIdentity;
+use eink_power_cli::power::ltc2959::{self, AdcMode, ContinuousRead};
+use eink_power_cli::power::rails::{PowerRail, Rail};
+use eink_power_cli::power::reboot::RebootEvidence;
+use eink_power_cli::power::wake::WakeSource;
+use eink_power_cli::power::PowerController;
+use eink_power_cli::provision::{self, ProvisionManifest, ProvisionStatus, ProvisionStep};
+use eink_power_cli::serial::cache::DEFAULT_CACHE_TTL;
+use eink_power_cli::serial::connection::BaudStage;
+use eink_power_cli::serial::console::ConsoleQuirk;
+use eink_power_cli::serial::{
+    CommandFamily, Connection, EchoCheck, ResyncMode, TimeoutPolicy, TimeoutRetry,
+};
+use eink_power_cli::setup::{Prompter, Setup, SetupAnswers};
+use eink_power_cli::simulator::{
+    smp, Faults, PmuSimulator, BOOT_BANNER, DEBUG_PROMPT, INITIAL_CHARGE_MAH, INITIAL_UPTIME,
+    LOG_LINE, LOW_BATTERY_CHARGE_MAH, LOW_BATTERY_MV, ROOT_COMMANDS,
+};
+use eink_power_cli::state::{device_key, DeviceState};
+use eink_power_cli::status;
+use eink_power_cli::util::CancellationToken;
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn controller(sim: &PmuSimulator) -> PowerController {
+    PowerController::new(Connection::new(sim.device(), 115200, true).unwrap())
+}
+
+/// The CLI binary pointed at the simulator, isolated from user state and
+/// failing on a connection left open
+fn cli(sim: &PmuSimulator, state_dir: &std::path::Path) -> Command {
+    let mut cmd = Command::cargo_bin("eink-power-cli").unwrap();
+    cmd.env("EINK_POWER_CLI_STATE_DIR", state_dir)
+        .env("EINK_POWER_CLI_MCUMGR", "false")
+        .env("EINK_POWER_CLI_ASSERT_CLOSED", "1")
+        .args(["--device", sim.device()]);
+    cmd
+}
+
+#[tokio::test]
+async fn ping_after_handshake() {
+    let sim = PmuSimulator::start();
+    let mut controller = controller(&sim);
+
+    assert_eq!(controller.ping().await.unwrap(), "pong");
+    // The connect handshake pings before the command itself
+    assert_eq!(sim.received(), ["ping", "ping"]);
+}
+
+#[tokio::test]
+async fn version_reply_parses_build_metadata() {
+    let sim = PmuSimulator::start();
+    let response = controller(&sim).get_system_info().await.unwrap();
+
+    let info = ResponseParser::parse_system_info(&response);
+    assert_eq!(info.version_info.semver.as_deref(), Some("2.5.0"));
+    assert_eq!(info.version_info.dirty, Some(false));
+    assert!(info
+        .policy_violations(true, Some("2.5.0"))
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn battery_read_parses_measurements() {
+    let sim = PmuSimulator::start();
+    let response = controller(&sim).battery_read().await.unwrap();
+
+    let battery = ResponseParser::parse_battery_response(&response);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.current_ma, Some(-125));
+    assert_eq!(battery.charge_mah, Some(2450));
+    assert!(
+        !response.contains("prod:~$"),
+        "prompt stripped: {:?}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn battery_watch_reads_on_every_tick() {
+    let sim = PmuSimulator::start();
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.enable_response_cache(DEFAULT_CACHE_TTL);
+    let mut controller = PowerController::new(connection);
+    let mut monitor = controller.battery_monitor();
+    let mut readings = monitor.watch(Duration::from_millis(20));
+
+    for _ in 0..3 {
+        assert_eq!(readings.next().await.unwrap().voltage_mv, Some(3850));
+    }
+    let reads = sim
+        .received()
+        .iter()
+        .filter(|c| *c == "ltc2959 read")
+        .count();
+    assert_eq!(reads, 3);
+}
+
+#[tokio::test]
+async fn time_reference_matches_the_simulated_boot() {
+    let started = chrono::Utc::now();
+    let sim = PmuSimulator::start();
+    let reference = controller(&sim).time_reference().await.unwrap();
+
+    let boot = started - chrono::TimeDelta::from_std(INITIAL_UPTIME).unwrap();
+    let error = (reference.pmu_boot_wallclock - boot)
+        .num_milliseconds()
+        .abs();
+    assert!(error < 100, "boot estimate off by {} ms", error);
+    assert!(reference.uncertainty_ms < 100.0, "{:?}", reference);
+}
+
+#[test]
+fn binary_time_ref_is_saved_and_reused() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let time_ref = |args: &[&str]| {
+        let output = cli(&sim, state.path())
+            .args(["--format", "json", "system", "time-ref"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()["data"].clone()
+    };
+
+    let saved = time_ref(&[]);
+    assert_eq!(saved["reused"], false);
+    let translated = time_ref(&["--at", "0"]);
+    assert_eq!(translated["reused"], true);
+    assert_eq!(
+        translated["pmu_boot_wallclock"],
+        saved["pmu_boot_wallclock"]
+    );
+    assert_eq!(
+        translated["translation"]["wallclock"],
+        saved["pmu_boot_wallclock"]
+    );
+}
+
+#[tokio::test]
+async fn gpio_get_reads_pin() {
+    let sim = PmuSimulator::start();
+    let response = controller(&sim)
+        .control_gpio("A", 5, eink_power_cli::power::control::GpioAction::Get)
+        .await
+        .unwrap();
+
+    assert_eq!(response, "GPIO A5: 1");
+    assert_eq!(sim.received().last().unwrap(), "gpio get A 5");
+}
+
+#[tokio::test]
+async fn interleaved_log_lines_do_not_break_parsing() {
+    let sim = PmuSimulator::with_faults(Faults {
+        log_lines: vec![LOG_LINE.to_string(), LOG_LINE.to_string()],
+        ..Faults::default()
+    });
+    let response = controller(&sim).battery_read().await.unwrap();
+
+    assert!(response.contains("<inf>"));
+    let battery = ResponseParser::parse_battery_response(&response);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.power_mw, Some(-481));
+}
+
+#[tokio::test]
+async fn debug_prompt_is_stripped() {
+    let sim = PmuSimulator::with_faults(Faults {
+        prompt: DEBUG_PROMPT.to_string(),
+        ..Faults::default()
+    });
+    let response = controller(&sim).get_system_info().await.unwrap();
+
+    assert!(response.starts_with("Board:"), "{:?}", response);
+    assert!(!response.contains("debug:~$"));
+}
+
+#[tokio::test]
+async fn slow_reply_within_timeout_succeeds() {
+    let sim = PmuSimulator::with_faults(Faults {
+        reply_delay: Duration::from_millis(500),
+        ..Faults::default()
+    });
+    let response = controller(&sim).battery_read().await.unwrap();
+
+    assert_eq!(
+        ResponseParser::parse_battery_response(&response).voltage_mv,
+        Some(3850)
+    );
+}
+
+#[tokio::test]
+async fn per_command_timeout_outlasts_the_connection_timeout() {
+    let sim = PmuSimulator::with_faults(Faults {
+        reply_delay: Duration::from_millis(1500),
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_timeout(1);
+    let mut controller = PowerController::new(connection);
+    controller.set_timeout_policy(
+        TimeoutPolicy::default().with_config(&[("ltc2959 read".to_string(), 3)].into()),
+    );
+
+    controller.battery_read().await.unwrap();
+    // Other commands keep the connection timeout
+    assert!(matches!(
+        controller.pm_stats().await,
+        Err(PowerCliError::Timeout { timeout: 1, .. })
+    ));
+}
+
+#[tokio::test]
+async fn reply_after_timeout_is_reported() {
+    let sim = PmuSimulator::with_faults(Faults {
+        reply_delay: Duration::from_millis(1500),
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_timeout(1);
+    let mut controller = PowerController::new(connection);
+
+    match controller.battery_read().await {
+        Err(PowerCliError::Timeout {
+            timeout,
+            bytes_received,
+            ..
+        }) => {
+            assert_eq!(timeout, 1);
+            // Not even the echo came back in time
+            assert_eq!(bytes_received, 0);
+        }
+        other => panic!("expected a timeout, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn truncated_reply_leaves_missing_fields_empty() {
+    let sim = PmuSimulator::with_faults(Faults {
+        truncate_at: Some(60),
+        ..Faults::default()
+    });
+    let response = controller(&sim).battery_read().await.unwrap();
+
+    let battery = ResponseParser::parse_battery_response(&response);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.charge_mah, None);
+    assert_eq!(battery.power_mw, None);
+}
+
+#[tokio::test]
+async fn disabled_shell_is_detected_on_connect() {
+    let sim = PmuSimulator::with_faults(Faults {
+        shell_disabled: true,
+        ..Faults::default()
+    });
+
+    match controller(&sim).ping().await {
+        Err(PowerCliError::ShellUnavailable { snippet }) => {
+            assert!(snippet.contains("power_mgmt"), "{:?}", snippet)
+        }
+        other => panic!("expected ShellUnavailable, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn controller_error_reply_is_an_error() {
+    let sim = PmuSimulator::start();
+
+    match controller(&sim).pm_command("bogus").await {
+        Err(PowerCliError::ControllerError { message }) => {
+            assert!(message.contains("unknown command"), "{:?}", message)
+        }
+        other => panic!("expected ControllerError, got {:?}", other),
+    }
+}
+
+/// Times `command` was received by `sim`
+fn times_received(sim: &PmuSimulator, command: &str) -> usize {
+    sim.received().iter().filter(|c| *c == command).count()
+}
+
+#[tokio::test]
+async fn silent_timeout_is_retried_once() {
+    let sim = PmuSimulator::with_faults(Faults {
+        reply_delay: Duration::from_millis(1500),
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_timeout(1);
+    let mut controller = PowerController::new(connection);
+
+    match controller.battery_read().await {
+        Err(PowerCliError::Timeout {
+            bytes_received: 0,
+            partial: None,
+            ..
+        }) => {}
+        other => panic!("expected a silent timeout, got {:?}", other),
+    }
+    assert_eq!(times_received(&sim, "ltc2959 read"), 2);
+    assert_eq!(controller.connection().stats().retries, 1);
+
+    let sim = PmuSimulator::with_faults(Faults {
+        reply_delay: Duration::from_millis(1500),
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_timeout(1);
+    connection.set_timeout_retry(TimeoutRetry::Never);
+    let mut controller = PowerController::new(connection);
+    assert!(controller.battery_read().await.is_err());
+    assert_eq!(times_received(&sim, "ltc2959 read"), 1);
+}
+
+#[tokio::test]
+async fn echo_without_a_reply_is_a_partial_timeout() {
+    let sim = PmuSimulator::with_faults(Faults {
+        echo_stall: Duration::from_millis(1500),
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_timeout(1);
+    let mut controller = PowerController::new(connection);
+
+    // The echo alone is not taken for the reply
+    match controller.battery_read().await {
+        Err(PowerCliError::Timeout {
+            timeout: 1,
+            bytes_received,
+            partial: Some(partial),
+        }) => {
+            assert_eq!(bytes_received, partial.len());
+            assert_eq!(partial.trim(), "ltc2959 read");
+        }
+        other => panic!("expected a partial timeout, got {:?}", other),
+    }
+    // The PMU is running the command, so it is not sent again
+    assert_eq!(times_received(&sim, "ltc2959 read"), 1);
+
+    // A timeout long enough for the command gets the whole reply
+    controller.set_timeout_policy(
+        TimeoutPolicy::default().with_config(&[("ltc2959 read".to_string(), 3)].into()),
+    );
+    let response = controller.battery_read().await.unwrap();
+    assert_eq!(
+        ResponseParser::parse_battery_response(&response).voltage_mv,
+        Some(3850)
+    );
+
+    let sim = PmuSimulator::with_faults(Faults {
+        echo_stall: Duration::from_millis(1500),
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_timeout(1);
+    connection.set_timeout_retry(TimeoutRetry::Always);
+    let mut controller = PowerController::new(connection);
+    assert!(controller.battery_read().await.is_err());
+    assert_eq!(times_received(&sim, "ltc2959 read"), 2);
+}
+
+#[test]
+fn binary_timeout_hint_and_json_error_carry_the_partial_reply() {
+    let sim = PmuSimulator::with_faults(Faults {
+        echo_stall: Duration::from_millis(1500),
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["--bug-report", "--timeout", "1", "--format", "json"])
+        .args(["battery", "read"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("consider increasing --timeout"),
+        "{}",
+        stderr
+    );
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let timeout = &json["data"]["timeout"];
+    assert_eq!(timeout["timeout_s"], 1);
+    assert!(timeout["bytes_received"].as_u64().unwrap() > 0);
+    assert!(
+        timeout["partial"]
+            .as_str()
+            .unwrap()
+            .contains("ltc2959 read"),
+        "{}",
+        timeout
+    );
+
+    let sim = PmuSimulator::with_faults(Faults {
+        reply_delay: Duration::from_millis(1500),
+        ..Faults::default()
+    });
+    let output = cli(&sim, state.path())
+        .args(["--timeout", "1", "--retry-timeout", "never"])
+        .args(["battery", "read"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no data received"), "{}", stderr);
+    assert!(stderr.contains("--baud"), "{}", stderr);
+    assert_eq!(times_received(&sim, "ltc2959 read"), 1);
+}
+
+#[tokio::test]
+async fn baud_change_switches_the_local_port() {
+    let sim = PmuSimulator::start();
+    let mut controller = controller(&sim);
+
+    let change = controller.set_baud_rate(921600, false).await;
+    assert!(change.success, "{:?}", change);
+    assert_eq!(change.link_rate, Some(921600));
+    assert_eq!(controller.connection().baud_rate(), 921600);
+    assert!(sim.received().contains(&"system baud 921600".to_string()));
+}
+
+#[tokio::test]
+async fn baud_change_rolls_back_when_the_new_rate_is_silent() {
+    let sim = PmuSimulator::with_faults(Faults {
+        ignored_after_baud: 1,
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_timeout(1);
+    let mut controller = PowerController::new(connection);
+
+    let change = controller.set_baud_rate(921600, false).await;
+    assert!(!change.success);
+    assert_eq!(change.link_rate, Some(115200));
+    assert_eq!(controller.connection().baud_rate(), 115200);
+    let stages: Vec<_> = change.transitions.iter().map(|t| (t.stage, t.ok)).collect();
+    assert_eq!(
+        stages,
+        [
+            (BaudStage::Requested, true),
+            (BaudStage::Switched, false),
+            (BaudStage::RolledBack, true),
+        ]
+    );
+    assert!(change.failure_message().unwrap().contains("link restored"));
+}
+
+#[tokio::test]
+async fn lost_link_during_baud_change_gives_recovery_steps() {
+    let sim = PmuSimulator::with_faults(Faults {
+        ignored_after_baud: usize::MAX,
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_timeout(1);
+    let mut controller = PowerController::new(connection);
+
+    let change = controller.set_baud_rate(921600, true).await;
+    assert!(!change.success);
+    assert_eq!(change.link_rate, None);
+    let message = change.failure_message().unwrap();
+    assert!(message.contains("--baud 921600"), "{}", message);
+    assert!(message.contains("system set-baud 115200"), "{}", message);
+    assert!(message.contains("persisted"), "{}", message);
+}
+
+#[test]
+fn binary_json_battery_read() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "battery", "read"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["command"], "battery read");
+    assert_eq!(json["status"], "success");
+    assert_eq!(json["data"]["voltage_mv"], 3850);
+    assert_eq!(json["data"]["current_ma"], -125);
+}
+
+#[test]
+fn binary_battery_watch_prints_lines_when_piped() {
+    use assert_cmd::cargo::CommandCargoExt;
+
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let child = std::process::Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .env("EINK_POWER_CLI_STATE_DIR", state.path())
+        .env("EINK_POWER_CLI_ASSERT_CLOSED", "1")
+        .args(["--device", sim.device(), "battery", "read", "--watch"])
+        .args(["--interval", "1", "--capacity", "4900"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(2500));
+    let status = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains('\r'), "{:?}", stdout);
+    let samples = stdout
+        .lines()
+        .filter(|line| line.ends_with("V=3.85V I=-125mA P=-0.48W SoC=50%"))
+        .count();
+    assert!(samples >= 2, "{}", stdout);
+    assert!(stdout.contains("Watch Summary"), "{}", stdout);
+}
+
+#[test]
+fn binary_json_lines_records_arrive_while_the_command_runs() {
+    use assert_cmd::cargo::CommandCargoExt;
+    use std::io::BufRead;
+
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let mut child = std::process::Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .env("EINK_POWER_CLI_STATE_DIR", state.path())
+        .env("EINK_POWER_CLI_ASSERT_CLOSED", "1")
+        .args(["--device", sim.device(), "--format", "json-lines"])
+        .args(["monitor", "--continuous", "--interval", "1"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Read on a thread so a missing line fails the test instead of hanging it
+    let stdout = std::io::BufReader::new(child.stdout.take().unwrap());
+    let (lines, received) = std::sync::mpsc::channel();
+    let reader = std::thread::spawn(move || {
+        for line in stdout.lines() {
+            lines.send(line.unwrap()).unwrap();
+        }
+    });
+    let first = received.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert!(
+        child.try_wait().unwrap().is_none(),
+        "exited before {}",
+        first
+    );
+    let record: serde_json::Value = serde_json::from_str(&first).unwrap();
+    assert_eq!(record["type"], "sample");
+    assert_eq!(record["command"], "monitor");
+    assert_eq!(record["data"]["voltage_mv"], 3850);
+
+    let status = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(child.wait().unwrap().success());
+    reader.join().unwrap();
+    let rest: Vec<serde_json::Value> = received
+        .try_iter()
+        .map(|line| serde_json::from_str(&line).unwrap())
+        .collect();
+    let last = rest.last().unwrap();
+    assert_eq!(last["type"], "summary");
+    assert_eq!(last["command"], "monitor summary");
+}
+
+#[test]
+fn binary_json_lines_one_shot_commands_print_one_record() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["--format", "json-lines", "battery", "read"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+    let record: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(record["type"], "summary");
+    assert_eq!(record["data"]["voltage_mv"], 3850);
+
+    let output = Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .env("EINK_POWER_CLI_STATE_DIR", state.path())
+        .args([
+            "--device",
+            "/dev/nonexistent",
+            "--format",
+            "json-lines",
+            "ping",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+    let record: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(record["type"], "error");
+    assert_eq!(record["status"], "error");
+}
+
+#[test]
+fn binary_monitor_keeps_a_status_file_and_marks_it_stale_on_exit() {
+    use assert_cmd::cargo::CommandCargoExt;
+    use eink_power_cli::power::rails::PowerRail;
+
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let path = state.path().join("run/pmu-status.json");
+    let child = std::process::Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .env("EINK_POWER_CLI_STATE_DIR", state.path())
+        .env("EINK_POWER_CLI_ASSERT_CLOSED", "1")
+        .args([
+            "--device",
+            sim.device(),
+            "--quiet",
+            "monitor",
+            "--continuous",
+        ])
+        .args(["--interval", "1", "--status-file"])
+        .arg(&path)
+        .spawn()
+        .unwrap();
+
+    let deadline = std::time::Instant::now() + Duration::from_secs(10);
+    let running = loop {
+        if let Ok(document) = status::read(&path) {
+            break document;
+        }
+        assert!(std::time::Instant::now() < deadline, "no status file");
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    assert!(!running.is_stale(chrono::Utc::now()));
+    assert_eq!(running.device, sim.device());
+    assert_eq!(running.battery.as_ref().unwrap().voltage_mv, Some(3850));
+    assert_eq!(running.charging_state, Some(ChargingState::Discharging));
+    assert!(running.rails[&PowerRail::Pmic]);
+    assert!(!running.rails[&PowerRail::Wifi]);
+    assert!(running.wake_sources.is_some());
+    assert!(running.firmware_version.is_some());
+    // Two sample intervals
+    assert_eq!((running.stale_after - running.updated_at).num_seconds(), 2);
+
+    let status = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let exit = child.wait_with_output().unwrap();
+    assert!(exit.status.success(), "{:?}", exit);
+
+    let stopped = status::read(&path).unwrap();
+    assert!(stopped.stale);
+    assert_eq!(stopped.battery, running.battery);
+}
+
+#[test]
+fn binary_snapshot_reads_every_section() {
+    let sim = PmuSimulator::with_faults(Faults {
+        unsupported: vec!["nfc status".to_string()],
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let file = state.path().join("snapshot.json");
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "snapshot", "--redact", "--output"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let saved = std::fs::read_to_string(&file).unwrap();
+    let snapshot = match parse_output(&saved).unwrap() {
+        CommandOutput::Snapshot(snapshot) => snapshot,
+        other => panic!("unexpected output {:?}", other),
+    };
+    assert_eq!(
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()["data"],
+        serde_json::to_value(&snapshot).unwrap()
+    );
+
+    assert_eq!(snapshot.connection.device, sim.device());
+    let system = snapshot.system.data.as_ref().unwrap();
+    assert_eq!(system.version_info.semver.as_deref(), Some("2.5.0"));
+    assert!(snapshot.uptime_ms.data.is_some());
+    assert_eq!(
+        snapshot.battery.data.as_ref().unwrap().voltage_mv,
+        Some(3850)
+    );
+    assert_eq!(
+        snapshot.power_stats.data.as_ref().unwrap().sleep_cycles,
+        Some(4)
+    );
+    let defaults = snapshot.rail_defaults.data.as_ref().unwrap();
+    assert_eq!((defaults.pmic, defaults.wifi), (Some(true), Some(false)));
+    let rtc = snapshot.rtc.data.as_ref().unwrap();
+    assert_eq!(rtc.internal_rtc.wake_events, Some(12));
+
+    // A failing subsystem is recorded and the rest are still read
+    let nfc_error = snapshot.nfc.error.as_deref().unwrap();
+    assert!(nfc_error.contains("unknown command"), "{}", nfc_error);
+    assert!(snapshot.firmware_images.error.is_some());
+    assert_eq!(snapshot.failed_sections(), ["firmware images", "nfc"]);
+
+    assert!(snapshot.redacted);
+    assert_eq!(snapshot.identity.error.as_deref(), Some("redacted"));
+    assert!(snapshot.identity.data.is_none());
+}
+
+#[test]
+fn binary_snapshot_outlives_the_default_deadline_when_sections_time_out() {
+    let sim = PmuSimulator::with_faults(Faults {
+        reply_delay: Duration::from_millis(1500),
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+
+    // Every section times out, together taking longer than --timeout x 4 + 10
+    let output = cli(&sim, state.path())
+        .args(["--timeout", "1", "--format", "json", "snapshot"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let snapshot = match parse_output(&String::from_utf8_lossy(&output.stdout)).unwrap() {
+        CommandOutput::Snapshot(snapshot) => snapshot,
+        other => panic!("unexpected output {:?}", other),
+    };
+    assert!(snapshot.system.data.is_none());
+    assert!(snapshot.battery.data.is_none());
+    assert_eq!(
+        snapshot.failed_sections(),
+        [
+            "system",
+            "uptime",
+            "identity",
+            "firmware images",
+            "battery",
+            "power stats",
+            "rail defaults",
+            "rtc",
+            "nfc"
+        ]
+    );
+}
+
+/// `power-audit` run through the binary, read back from its JSON output
+fn power_audit(sim: &PmuSimulator, args: &[&str]) -> Box<PowerAudit> {
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(sim, state.path())
+        .args(["--format", "json", "power-audit"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    match parse_output(&String::from_utf8_lossy(&output.stdout)).unwrap() {
+        CommandOutput::PowerAudit(audit) => audit,
+        other => panic!("unexpected output {:?}", other),
+    }
+}
+
+#[test]
+fn binary_power_audit_flags_the_checklist() {
+    let sim = PmuSimulator::with_faults(Faults {
+        unsupported: vec!["nfc status".to_string()],
+        ..Faults::default()
+    });
+    let audit = power_audit(&sim, &[]);
+
+    assert_eq!(
+        audit.firmware_version.data.as_deref(),
+        Some("2.5.0-+1234abc.42")
+    );
+    let rails = audit.rails.data.as_ref().unwrap();
+    assert!(rails[&PowerRail::Display]);
+    assert!(!rails[&PowerRail::Wifi]);
+    assert_eq!(audit.adc_mode(), Some("Smart Sleep"));
+    assert_eq!(audit.wake_sources.data.as_ref().unwrap().mask, 0x1F);
+    assert!(audit.sleep_sample.is_none());
+    assert_eq!(audit.estimated_sleep_current_ma, None);
+    // Without `nfc status` in the firmware, `pm stats` still reports NFC
+    assert_eq!(audit.failed_sections(), ["nfc"]);
+
+    // The simulator's display rail defaults to and is ON
+    let checks: Vec<AuditCheck> = audit.findings.iter().map(|f| f.check).collect();
+    assert_eq!(
+        checks,
+        [AuditCheck::DisplayDefaultOn, AuditCheck::DisplayOn]
+    );
+
+    let sim = PmuSimulator::with_faults(Faults {
+        adc_mode: "Continuous V/I".to_string(),
+        nfc_state: "Active".to_string(),
+        wake_mask: 0,
+        ..Faults::default()
+    });
+    let audit = power_audit(&sim, &[]);
+    let checks: Vec<AuditCheck> = audit.findings.iter().map(|f| f.check).collect();
+    assert_eq!(
+        checks,
+        [
+            AuditCheck::DisplayDefaultOn,
+            AuditCheck::DisplayOn,
+            AuditCheck::NfcAwake,
+            AuditCheck::ContinuousAdc,
+            AuditCheck::NoWakeSource,
+        ]
+    );
+    assert_eq!(audit.findings[2].detail, "NFC is Active");
+}
+
+#[test]
+fn binary_power_audit_estimates_sleep_current_from_a_sample() {
+    let sim = PmuSimulator::start();
+    let audit = power_audit(&sim, &["--sleep-sample-s", "2"]);
+
+    let sample = audit.sleep_sample.as_ref().unwrap().data.as_ref().unwrap();
+    assert_eq!(
+        (sample.duration_s, sample.readings, sample.failed),
+        (2, 2, 0)
+    );
+    assert_eq!(sample.current_ma.unwrap().median, -125.0);
+    assert_eq!(audit.estimated_sleep_current_ma, Some(125.0));
+
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["power-audit", "--sleep-sample-s", "1"])
+        .output()
+        .unwrap();
+    let text = String::from_utf8_lossy(&output.stdout);
+    assert!(text.contains("Estimated sleep current: 125 mA"), "{}", text);
+    assert!(text.contains("display rail defaults to ON"), "{}", text);
+}
+
+#[test]
+fn binary_info_all_shows_the_snapshot_by_section() {
+    let sim = PmuSimulator::with_faults(Faults {
+        unsupported: vec!["nfc status".to_string()],
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["info", "--all"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for heading in [
+        "Connection:",
+        "System Information:",
+        "Device Identity:",
+        "Firmware Images:",
+        "Battery Measurements:",
+        "Power Management Statistics:",
+        "Power Rail Defaults:",
+        "RTC Status:",
+        "NFC Status:",
+    ] {
+        assert!(stdout.contains(heading), "{} missing:\n{}", heading, stdout);
+    }
+    assert!(stdout.contains("Not programmed"), "{}", stdout);
+    assert!(
+        stdout.contains("unknown command 'nfc status'"),
+        "{}",
+        stdout
+    );
+}
+
+#[test]
+fn binary_battery_check_exit_code_follows_verdict() {
+    let state = tempfile::tempdir().unwrap();
+
+    let sim = PmuSimulator::start();
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "pm", "battery-check"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["verdict"], "healthy");
+    assert_eq!(json["data"]["internal_resistance_mohm"], 138);
+
+    for (verdict, code) in [("DEGRADED", 2), ("FAILED", 3)] {
+        let sim = PmuSimulator::with_faults(Faults {
+            battery_verdict: verdict.to_string(),
+            ..Faults::default()
+        });
+        cli(&sim, state.path())
+            .args(["pm", "battery-check"])
+            .assert()
+            .code(code);
+    }
+}
+
+#[test]
+fn binary_error_shows_context_chain() {
+    let sim = PmuSimulator::with_faults(Faults {
+        shell_disabled: true,
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["battery", "read"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let expected = format!(
+        "Error: while executing command battery read\nwhile sending 'ltc2959 read' to {}\nPMU shell unavailable",
+        sim.device()
+    );
+    assert!(stderr.contains(&expected), "{}", stderr);
+}
+
+#[test]
+fn binary_bug_report_adds_the_session_to_errors() {
+    let sim = PmuSimulator::with_faults(Faults {
+        shell_disabled: true,
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["--bug-report", "--timeout", "1", "--format", "json"])
+        .args(["battery", "read"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Session context:"), "{}", stderr);
+    assert!(
+        stderr.contains("Last command:   ltc2959 read"),
+        "{}",
+        stderr
+    );
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["status"], "error");
+    assert_eq!(json["command"], "battery read");
+    let session = &json["data"]["session"];
+    assert_eq!(session["connection"]["device"], sim.device());
+    assert_eq!(session["connection"]["timeout_s"], 1);
+    assert_eq!(session["last_command"], "ltc2959 read");
+    assert_eq!(session["redacted"], false);
+    let tail = session["received_tail"].as_str().unwrap();
+    assert!(tail.contains(LOG_LINE), "{:?}", tail);
+
+    // Without the flag the error stays a single message
+    let output = cli(&sim, state.path())
+        .args(["--timeout", "1", "--format", "json", "battery", "read"])
+        .output()
+        .unwrap();
+    assert!(output.stdout.is_empty());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("Session context:"));
+}
+
+#[test]
+fn binary_bug_report_leaves_out_identity_data() {
+    let sim = PmuSimulator::with_faults(Faults {
+        eeprom_read_only: true,
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["--bug-report", "--format", "json", "identity", "write"])
+        .args(["--serial", "EPC-0042", "--hw-rev", "3", "--yes"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let session = &json["data"]["session"];
+    assert_eq!(session["redacted"], true);
+    assert_eq!(session["received_tail"], "[redacted]");
+    assert_eq!(session["last_command"], "nfc eeprom read 0x1FB 5");
+    assert!(!session.to_string().contains("4550432D"), "{}", session);
+}
+
+/// Charge resets recorded in the simulator's device state
+fn coulomb_delta(sim: &PmuSimulator, state_dir: &std::path::Path) -> Option<CoulombDelta> {
+    DeviceState::in_dir(state_dir.join(device_key(sim.device())))
+        .file::<CoulombDelta>(COULOMB_DELTA_FILE)
+        .load()
+        .unwrap()
+}
+
+#[test]
+fn binary_coulomb_reset_zeroes_the_charge_and_keeps_the_old_value() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "power", "coulomb", "--reset"])
+        .write_stdin("yes\n")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert_eq!(coulomb_delta(&sim, state.path()), None);
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "power", "coulomb", "--reset", "--yes"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["command"], "power coulomb reset");
+    assert_eq!(json["data"]["previous_charge_mah"], INITIAL_CHARGE_MAH);
+    assert_eq!(json["data"]["charge_mah"], 0);
+    assert_eq!(json["data"]["verified"], true);
+    assert!(parse_output(&json.to_string()).is_ok());
+    assert!(sim.received().contains(&"ltc2959 set_charge 0".to_string()));
+
+    // The alias reads the zeroed counter and adds nothing to the total
+    cli(&sim, state.path())
+        .args(["ltc2959", "zero-charge", "--yes"])
+        .assert()
+        .success();
+    let delta = coulomb_delta(&sim, state.path()).unwrap();
+    assert_eq!(delta.discarded_mah, u64::from(INITIAL_CHARGE_MAH));
+    let previous: Vec<_> = delta
+        .resets
+        .iter()
+        .map(|reset| reset.previous_charge_mah)
+        .collect();
+    assert_eq!(previous, [Some(INITIAL_CHARGE_MAH), Some(0)]);
+}
+
+#[test]
+fn binary_coulomb_reset_fails_when_the_readback_is_not_zero() {
+    let sim = PmuSimulator::with_faults(Faults {
+        charge_read_only: true,
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "power", "coulomb", "--reset", "--yes"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["verified"], false);
+    assert_eq!(json["data"]["charge_mah"], INITIAL_CHARGE_MAH);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("the counter reads 2450 mAh after writing 0"),
+        "{}",
+        stderr
+    );
+    // The write was sent, so the old value is kept all the same
+    assert_eq!(coulomb_delta(&sim, state.path()).unwrap().resets.len(), 1);
+}
+
+#[test]
+fn binary_appended_csv_output_keeps_one_header() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let file = state.path().join("latency.csv");
+
+    for _ in 0..3 {
+        let output = cli(&sim, state.path())
+            .args(["--format", "csv", "--append", "--output"])
+            .arg(&file)
+            .args(["latency", "--samples", "2"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        assert!(!String::from_utf8_lossy(&output.stdout).contains("min_ms"));
+    }
+    let csv = std::fs::read_to_string(&file).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", csv);
+    assert_eq!(lines[0], "min_ms,max_ms,avg_ms,std_dev_ms");
+    assert!(
+        lines[1..].iter().all(|line| line.split(',').count() == 4),
+        "{}",
+        csv
+    );
+}
+
+#[test]
+fn binary_aliases_match_nested_commands() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| {
+        let output = cli(&sim, state.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        output.stdout
+    };
+
+    let json = |args: &[&str]| {
+        let mut value: serde_json::Value = serde_json::from_slice(&run(args)).unwrap();
+        value.as_object_mut().unwrap().remove("timestamp");
+        value
+    };
+    assert_eq!(
+        json(&["--format", "json", "info"]),
+        json(&["--format", "json", "system", "info"])
+    );
+
+    assert_eq!(run(&["info"]), run(&["system", "info"]));
+    assert_eq!(run(&["status"]), run(&["pm", "stats"]));
+    assert!(String::from_utf8_lossy(&run(&["status"])).contains("Sleep cycles: 4"));
+}
+
+#[test]
+fn binary_reports_charging_state() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| {
+        let output = cli(&sim, state.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // The simulator reports -125 mA
+    assert_eq!(run(&["battery", "status", "--brief"]), "discharging\n");
+    assert_eq!(
+        run(&["battery", "status", "--brief", "--deadband", "200"]),
+        "idle\n"
+    );
+
+    let output = run(&["--format", "json", "monitor", "--source", "ltc2959"]);
+    match parse_output(&output).unwrap() {
+        CommandOutput::MonitorSample(sample) => {
+            assert_eq!(sample.measurement.current_ma, Some(-125));
+            assert_eq!(sample.charging_state, Some(ChargingState::Discharging));
+            assert!(!sample.charging);
+            assert!(sample.since.is_some());
+        }
+        other => panic!("unexpected output {:?}", other),
+    }
+}
+
+#[test]
+fn binary_reports_the_charger_state_the_firmware_gives() {
+    let sim = PmuSimulator::with_faults(Faults {
+        charger: Some("Trickle".to_string()),
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| {
+        let output = cli(&sim, state.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert_eq!(run(&["battery", "status", "--brief"]), "trickle\n");
+    // Firmware without `cc_gpio status` is not an error
+    assert!(sim
+        .received()
+        .contains(&"ltc2959 cc_gpio status".to_string()));
+
+    // Trickle charge while the simulator reports -125 mA
+    let output = run(&["--format", "json", "monitor", "--charger"]);
+    match parse_output(&output).unwrap() {
+        CommandOutput::MonitorSample(sample) => {
+            assert!(sample.charging);
+            assert_eq!(sample.charging_state, Some(ChargingState::Charging));
+            let charger = sample.charger.unwrap();
+            assert_eq!(charger.state, ChargerState::Trickle);
+            assert_eq!(charger.current_state, Some(ChargingState::Discharging));
+            assert_eq!(charger.conflicts.len(), 1, "{:?}", charger.conflicts);
+        }
+        other => panic!("unexpected output {:?}", other),
+    }
+}
+
+#[test]
+fn binary_charge_complete_input_is_a_charger_state() {
+    let sim = PmuSimulator::with_faults(Faults {
+        cc_gpio: Some(true),
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["battery", "status", "--brief"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "complete\n");
+}
+
+#[test]
+fn binary_explain_parse_adds_diagnostics() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let json = |args: &[&str]| {
+        let output = cli(&sim, state.path()).args(args).output().unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let plain = json(&["--format", "json", "system", "info"]);
+    assert!(plain.get("parse_diagnostics").is_none());
+
+    let explained = json(&["--format", "json", "--explain-parse", "system", "info"]);
+    let diagnostics = explained["parse_diagnostics"].as_array().unwrap();
+    let outcome = |field: &str| {
+        diagnostics
+            .iter()
+            .find(|d| d["field"] == field)
+            .map(|d| d["outcome"].clone())
+    };
+    assert_eq!(outcome("board"), Some("matched".into()));
+    assert_eq!(outcome("uptime"), Some("absent".into()));
+    assert!(parse_output(&explained.to_string()).is_ok());
+}
+
+#[test]
+fn binary_unprogrammed_identity_is_null() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "system", "info", "--identity"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["board"], "MCXC143VFM E-Ink Power Controller");
+    assert!(json["data"]["serial"].is_null());
+    assert!(json["data"]["manufacture_date"].is_null());
+    assert!(sim
+        .received()
+        .contains(&"nfc eeprom read 0x1FB 5".to_string()));
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "identity", "show"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["data"].is_null());
+}
+
+#[test]
+fn binary_identity_write_reads_back() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let write = [
+        "identity",
+        "write",
+        "--serial",
+        "EPC-0042",
+        "--hw-rev",
+        "3",
+        "--date",
+        "2025-10-09",
+    ];
+
+    let output = cli(&sim, state.path())
+        .args(write)
+        .write_stdin("n\n")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(!sim
+        .received()
+        .iter()
+        .any(|c| c.starts_with("nfc eeprom write")));
+
+    cli(&sim, state.path())
+        .args(write)
+        .arg("--yes")
+        .assert()
+        .success();
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "system", "info", "--identity"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["serial"], "EPC-0042");
+    assert_eq!(json["data"]["hw_rev"], 3);
+    assert_eq!(json["data"]["manufacture_date"], "2025-10-09");
+    assert!(parse_output(&json.to_string()).is_ok());
+}
+
+#[tokio::test]
+async fn identity_readback_mismatch_is_an_error() {
+    let sim = PmuSimulator::with_faults(Faults {
+        eeprom_read_only: true,
+        ..Faults::default()
+    });
+    let identity = DeviceIdentity::new(
+        "EPC-0042",
+        3,
+        chrono::NaiveDate::from_ymd_opt(2025, 10, 9).unwrap(),
+    )
+    .unwrap();
+
+    match controller(&sim).write_identity(&identity).await {
+        Err(PowerCliError::NfcError { message }) => {
+            assert!(message.contains("readback mismatch"), "{:?}", message)
+        }
+        other => panic!("expected NfcError, got {:?}", other),
+    }
+}
+
+/// Simulator whose shell drops commands sent within 100 ms of a reply
+fn overrunning_simulator() -> PmuSimulator {
+    PmuSimulator::with_faults(Faults {
+        min_command_gap: Duration::from_millis(100),
+        ..Faults::default()
+    })
+}
+
+/// Send `gpio get A 5` ten times back to back
+async fn gpio_burst(connection: &mut Connection) -> Result<(), PowerCliError> {
+    for _ in 0..10 {
+        assert_eq!(connection.send_command("gpio get A 5").await?, "GPIO A5: 1");
+    }
+    Ok(())
+}
+
+/// Simulator that leaves garbage in the shell input buffer after `replies`
+fn noisy_simulator(replies: usize) -> PmuSimulator {
+    PmuSimulator::with_faults(Faults {
+        line_noise: Some((replies, "\x7f#q".to_string())),
+        ..Faults::default()
+    })
+}
+
+fn garbled(sim: &PmuSimulator) -> Vec<String> {
+    sim.received()
+        .into_iter()
+        .filter(|command| command.contains("#q"))
+        .collect()
+}
+
+#[tokio::test]
+async fn noise_before_a_command_is_recovered_by_default() {
+    // Noise after the handshake ping and the first read
+    let sim = noisy_simulator(2);
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+
+    connection.send_command("ltc2959 read").await.unwrap();
+    // The garbled line is rejected; the line is cleared and the command sent again
+    let stats = connection.send_command("pm stats").await.unwrap();
+    assert!(stats.contains("Sleep cycles: 4"), "{}", stats);
+    assert_eq!(connection.stats().retries, 1);
+    assert_eq!(garbled(&sim), ["\x7f#qpm stats"]);
+    connection.send_command("pm stats").await.unwrap();
+    assert_eq!(connection.stats().retries, 1);
+}
+
+#[tokio::test]
+async fn resync_always_clears_noise_before_it_reaches_a_command() {
+    let sim = noisy_simulator(2);
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_resync(ResyncMode::Always);
+
+    connection.send_command("ltc2959 read").await.unwrap();
+    connection.send_command("pm stats").await.unwrap();
+    assert_eq!(connection.stats().retries, 0);
+    assert!(garbled(&sim).is_empty(), "{:?}", sim.received());
+}
+
+#[tokio::test]
+async fn controller_error_clears_the_line_before_the_next_command() {
+    // Noise arrives with the error reply to `pm bogus`
+    let sim = noisy_simulator(2);
+    let mut controller = controller(&sim);
+
+    assert!(matches!(
+        controller.pm_command("bogus").await,
+        Err(PowerCliError::ControllerError { .. })
+    ));
+    controller.pm_stats().await.unwrap();
+    assert_eq!(controller.connection_stats().retries, 0);
+    assert!(garbled(&sim).is_empty(), "{:?}", sim.received());
+}
+
+/// Simulator corrupting `lines` command lines after the handshake
+fn corrupting_simulator(lines: usize) -> PmuSimulator {
+    PmuSimulator::with_faults(Faults {
+        corrupted_lines: lines,
+        ..Faults::default()
+    })
+}
+
+#[tokio::test]
+async fn corrupted_echo_of_a_rail_off_is_typed_again_before_it_runs() {
+    let sim = corrupting_simulator(1);
+    let mut controller = controller(&sim);
+
+    let response = controller
+        .control_rail(Rail::Pmic, PowerState::Off)
+        .await
+        .unwrap();
+    assert_eq!(response, "PMIC power OFF");
+    assert_eq!(controller.connection_stats().retries, 1);
+    // The corrupted line was discarded before Enter
+    assert_eq!(sim.received(), ["ping", "pm pmic off"]);
+}
+
+#[tokio::test]
+async fn persistently_corrupted_echo_never_runs_the_command() {
+    let sim = corrupting_simulator(5);
+    let mut controller = controller(&sim);
+
+    match controller.control_rail(Rail::Pmic, PowerState::Off).await {
+        Err(PowerCliError::InvalidResponse { response }) => {
+            assert!(response.contains("not run"), "{}", response)
+        }
+        other => panic!("expected InvalidResponse, got {:?}", other),
+    }
+    assert_eq!(sim.received(), ["ping"]);
+}
+
+#[tokio::test]
+async fn without_echo_check_a_corrupted_command_reaches_the_firmware() {
+    let sim = corrupting_simulator(1);
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_echo_check(EchoCheck::Never);
+    let mut controller = PowerController::new(connection);
+
+    controller
+        .control_rail(Rail::Pmic, PowerState::Off)
+        .await
+        .unwrap();
+    assert_eq!(sim.received(), ["ping", "p~ pmic off", "pm pmic off"]);
+}
+
+#[tokio::test]
+async fn echo_check_always_covers_status_queries() {
+    let sim = corrupting_simulator(1);
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_echo_check(EchoCheck::Always);
+
+    connection.send_command("pm stats").await.unwrap();
+    assert_eq!(sim.received(), ["ping", "pm stats"]);
+}
+
+#[tokio::test]
+async fn unpaced_burst_overruns_the_shell() {
+    let sim = overrunning_simulator();
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_pacing(Duration::ZERO);
+
+    let err = gpio_burst(&mut connection).await.unwrap_err();
+    assert!(
+        matches!(&err, PowerCliError::InvalidResponse { response } if response.contains("dropped")),
+        "{:?}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn paced_burst_survives_an_overrunning_shell() {
+    let sim = overrunning_simulator();
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_pacing(Duration::from_millis(150));
+
+    gpio_burst(&mut connection).await.unwrap();
+    // The first command follows the handshake unpaced and may be re-sent
+    let sent = sim
+        .received()
+        .iter()
+        .filter(|c| *c == "gpio get A 5")
+        .count();
+    assert!((10..=11).contains(&sent), "{:?}", sim.received());
+}
+
+#[test]
+fn binary_slow_drip_exceeds_overall_deadline() {
+    let sim = PmuSimulator::with_faults(Faults {
+        slow_drip: Duration::from_millis(700),
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+
+    // Every ping answers well within --timeout, but ten of them do not
+    // fit in --max-duration
+    let started = std::time::Instant::now();
+    let output = cli(&sim, state.path())
+        .args(["--timeout", "2", "--max-duration", "3"])
+        .args(["latency", "--samples", "10"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(started.elapsed() < Duration::from_secs(6));
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("while executing command latency\nDeadline of 3s exceeded during command"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn binary_vlls_sleep_requires_force_without_console_wake() {
+    let state = tempfile::tempdir().unwrap();
+    let no_uart = Faults {
+        wake_mask: 0x1E,
+        ..Faults::default()
+    };
+
+    let sim = PmuSimulator::with_faults(no_uart.clone());
+    let output = cli(&sim, state.path())
+        .args(["pm", "sleep", "--vlls0"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--force"), "{}", stderr);
+    assert!(!sim.received().iter().any(|c| c.starts_with("pm sleep")));
+
+    let sim = PmuSimulator::with_faults(no_uart.clone());
+    cli(&sim, state.path())
+        .args(["pm", "sleep", "--vlls0", "--force"])
+        .assert()
+        .success();
+    assert!(sim.received().contains(&"pm sleep --vlls0".to_string()));
+
+    // The external RTC still wakes a timed VLLS0 sleep
+    let sim = PmuSimulator::with_faults(no_uart);
+    cli(&sim, state.path())
+        .args(["pm", "sleep", "--vlls0", "--time", "30s"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn binary_sleep_json_shows_effective_wake_mask() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "pm", "sleep", "--vlls0"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["command"], "pm sleep");
+    assert_eq!(json["data"]["vlls_mode"], "vlls0");
+    assert_eq!(json["data"]["configured_wake"]["mask"], 0x1F);
+    assert_eq!(json["data"]["effective_wake"]["mask"], 0x1D);
+    assert_eq!(
+        json["data"]["effective_wake"]["sources"],
+        serde_json::json!(["uart", "rtc", "nfc", "button"])
+    );
+}
+
+#[test]
+fn binary_batch_runs_each_line() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let batch = state.path().join("commands.txt");
+    std::fs::write(&batch, "# bring-up\nping\npower pmic on\n\ngpio get A 5\n").unwrap();
+
+    cli(&sim, state.path())
+        .args(["batch", "--file", batch.to_str().unwrap()])
+        .assert()
+        .success();
+
+    let commands: Vec<String> = sim
+        .received()
+        .into_iter()
+        .filter(|command| command != "ping")
+        .collect();
+    assert_eq!(commands, ["pm pmic on", "gpio get gpioa 5"]);
+}
+
+#[test]
+fn binary_back_to_back_commands_find_the_port_free() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    for round in 0..100 {
+        for command in ["ping", "version"] {
+            let output = cli(&sim, state.path())
+                .args(["--quiet", "--no-history", command])
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "round {}: {:?}", round, output);
+        }
+    }
+}
+
+#[test]
+fn binary_batch_stops_at_invalid_line() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let batch = state.path().join("commands.txt");
+    std::fs::write(&batch, "power pmic on\npower teleport on\ngpio get A 5\n").unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["batch", "--file", batch.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("commands.txt:2"), "{}", stderr);
+    assert!(!sim.received().iter().any(|c| c.starts_with("gpio")));
+}
+
+#[test]
+fn binary_batch_waits_for_the_uart_after_sleep() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let batch = state.path().join("commands.txt");
+    std::fs::write(&batch, "pm sleep --time 1s\nping\n").unwrap();
+    let file = batch.to_str().unwrap();
+
+    cli(&sim, state.path())
+        .args(["--timeout", "1", "batch", "--file", file])
+        .assert()
+        .success();
+    assert_eq!(times_received(&sim, "ping"), 2);
+
+    // Without the guard the ping arrives while the UART is reinitialized
+    let output = cli(&sim, state.path())
+        .args(["--timeout", "1", "--no-guard", "--retry-timeout", "never"])
+        .args(["batch", "--file", file])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no data received"), "{}", stderr);
+}
+
+/// Configuration file with the macros used by the `run` tests
+fn macros_config(dir: &std::path::Path) -> std::path::PathBuf {
+    let config = dir.join("config.toml");
+    std::fs::write(
+        &config,
+        r#"
+[macros]
+bring-up = ["power pmic on", "gpio get A 5"]
+nfc-check = ["power pmic on", "nfc enable", "gpio get A 5"]
+typo = ["power pmic on", "power wifi on", "power teleport on"]
+"#,
+    )
+    .unwrap();
+    config
+}
+
+#[test]
+fn binary_run_macro_runs_each_step() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let config = macros_config(state.path());
+
+    let output = cli(&sim, state.path())
+        .args(["--config", config.to_str().unwrap(), "--format", "csv"])
+        .args(["run", "bring-up"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let commands: Vec<String> = sim
+        .received()
+        .into_iter()
+        .filter(|command| command != "ping")
+        .collect();
+    assert_eq!(commands, ["pm pmic on", "gpio get gpioa 5"]);
+    // Only the report, one row per step
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let rows: Vec<&str> = stdout
+        .lines()
+        .skip_while(|line| !line.starts_with("macro,"))
+        .collect();
+    assert_eq!(rows.len(), 3, "{}", stdout);
+    assert!(rows[1].starts_with("\"bring-up\",1,\"power pmic on\",ok,"));
+    assert!(rows[2].starts_with("\"bring-up\",2,\"gpio get A 5\",ok,"));
+}
+
+#[test]
+fn binary_run_macro_checks_every_step_before_sending() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let config = macros_config(state.path());
+
+    let output = cli(&sim, state.path())
+        .args(["--config", config.to_str().unwrap(), "run", "typo"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("macro 'typo' step 3"), "{}", stderr);
+    assert!(sim.received().is_empty(), "{:?}", sim.received());
+
+    let output = cli(&sim, state.path())
+        .args(["--config", config.to_str().unwrap(), "run", "bring-upp"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("run --list"), "{}", stderr);
+}
+
+#[test]
+fn binary_run_macro_reports_the_failed_step() {
+    let sim = PmuSimulator::with_faults(Faults {
+        unsupported: vec!["nfc enable".into()],
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let config = macros_config(state.path());
+
+    let output = cli(&sim, state.path())
+        .args([
+            "--config",
+            config.to_str().unwrap(),
+            "--format",
+            "prometheus",
+        ])
+        .args(["run", "nfc-check"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(!sim.received().iter().any(|c| c.starts_with("gpio")));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for metric in [
+        "eink_macro_success{macro=\"nfc-check\"} 0",
+        "eink_macro_step_success{macro=\"nfc-check\",step=\"1\",status=\"ok\"} 1",
+        "eink_macro_step_success{macro=\"nfc-check\",step=\"2\",status=\"failed\"} 0",
+        "eink_macro_step_success{macro=\"nfc-check\",step=\"3\",status=\"not_run\"} 0",
+    ] {
+        assert!(stdout.contains(metric), "{}", stdout);
+    }
+}
+
+#[test]
+fn binary_run_macro_dry_run_and_list() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let config = macros_config(state.path());
+
+    let output = cli(&sim, state.path())
+        .args(["--config", config.to_str().unwrap(), "--format", "ndjson"])
+        .args(["run", "bring-up", "--dry-run"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(sim.received().is_empty(), "{:?}", sim.received());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let report = match parse_output(stdout.lines().last().unwrap()).unwrap() {
+        CommandOutput::Macro(report) => report,
+        other => panic!("unexpected output {:?}", other),
+    };
+    assert_eq!(report.name, "bring-up");
+    assert!(report.dry_run);
+    assert!(report.success);
+    assert_eq!(report.steps[1].command, "gpio get A 5");
+
+    let output = cli(&sim, state.path())
+        .args(["--config", config.to_str().unwrap(), "--format", "json"])
+        .args(["run", "--list"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let listing = match parse_output(&String::from_utf8(output.stdout).unwrap()).unwrap() {
+        CommandOutput::MacroList(listing) => listing,
+        other => panic!("unexpected output {:?}", other),
+    };
+    assert_eq!(listing[0].name, "bring-up");
+    assert_eq!(listing.len(), 3);
+    assert!(sim.received().is_empty());
+}
+
+#[test]
+fn binary_hil_test_runs_the_plan_and_writes_junit() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let plan = state.path().join("candidate.toml");
+    std::fs::write(
+        &plan,
+        r#"
+[[step]]
+type = "command"
+command = "version"
+expect = ["2.5.0"]
+
+[[step]]
+type = "assert"
+command = "battery read"
+checks = ["voltage_mv >= 3300", "current_ma < 0"]
+
+[[step]]
+name = "charging"
+type = "assert"
+command = "battery read"
+checks = ["current_ma > 0"]
+retries = 1
+
+[[step]]
+type = "raw"
+line = "system uptime"
+"#,
+    )
+    .unwrap();
+    let junit = state.path().join("junit.xml");
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "hil-test"])
+        .arg(&plan)
+        .arg("--junit")
+        .arg(&junit)
+        .output()
+        .unwrap();
+    // One step failed, so the run did
+    assert!(!output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 of 4 steps failed"), "{}", stderr);
+
+    let report = match parse_output(&String::from_utf8(output.stdout).unwrap()).unwrap() {
+        CommandOutput::HilTest(report) => report,
+        other => panic!("unexpected output {:?}", other),
+    };
+    assert_eq!(report.plan, "candidate");
+    assert_eq!((report.passed, report.failed, report.skipped), (3, 1, 0));
+    let statuses: Vec<HilStepStatus> = report.steps.iter().map(|step| step.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            HilStepStatus::Passed,
+            HilStepStatus::Passed,
+            HilStepStatus::Failed,
+            HilStepStatus::Passed
+        ]
+    );
+    assert!(report.steps[0].output.as_deref().unwrap().contains("2.5.0"));
+    // The failed check was tried again, and the run went on after it
+    let failed = &report.steps[2];
+    assert_eq!(failed.attempts, 2);
+    assert_eq!(
+        failed.failure().as_deref(),
+        Some("current_ma > 0 failed: current_ma is -125")
+    );
+    assert!(sim.received().contains(&"system uptime".to_string()));
+
+    let xml = std::fs::read_to_string(&junit).unwrap();
+    assert!(
+        xml.contains(r#"tests="4" failures="1" skipped="0""#),
+        "{}",
+        xml
+    );
+    assert!(xml.contains(r#"<testcase name="charging" classname="candidate.assert""#));
+}
+
+#[test]
+fn binary_hil_test_skips_the_rest_after_a_critical_failure() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let plan = state.path().join("critical.toml");
+    std::fs::write(
+        &plan,
+        r#"
+name = "critical"
+
+[[step]]
+type = "command"
+command = "version"
+expect = ["9.9.9"]
+critical = true
+
+[[step]]
+type = "command"
+command = "battery read"
+"#,
+    )
+    .unwrap();
+
+    let output = cli(&sim, state.path())
+        .arg("hil-test")
+        .arg(&plan)
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("expect \"9.9.9\" failed: not in the output"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("0 passed, 1 failed, 1 skipped"),
+        "{}",
+        stdout
+    );
+    assert!(!sim.received().contains(&"ltc2959 read".to_string()));
+}
+
+#[tokio::test]
+async fn reboot_mid_session_is_reported_and_monitoring_restarted() {
+    let sim = PmuSimulator::with_faults(Faults {
+        // Handshake ping, monitor start, two uptime probes
+        reboot_after: Some(4),
+        ..Faults::default()
+    });
+    let mut controller = controller(&sim);
+    controller.pm_command("monitor start 30").await.unwrap();
+    assert_eq!(controller.check_for_reboot().await.unwrap(), None);
+    assert_eq!(controller.check_for_reboot().await.unwrap(), None);
+    // Let the boot banner arrive before the next probe
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let event = controller.check_for_reboot().await.unwrap().unwrap();
+    assert_eq!(event.evidence, RebootEvidence::Uptime);
+    assert!(event.previous_uptime_ms.unwrap() >= 3_600_000);
+    assert!(event.uptime_ms.unwrap() < 5_000);
+    assert!(
+        event
+            .message()
+            .starts_with("PMU rebooted (uptime went from 1:00:0"),
+        "{}",
+        event.message()
+    );
+    assert_eq!(controller.reboots(), 1);
+    assert_eq!(
+        sim.received().last().map(String::as_str),
+        Some("pm monitor start 30")
+    );
+
+    assert_eq!(event.reset_cause.as_deref(), Some("watchdog"));
+    // The banner and the uptime drop are one reset
+    assert_eq!(controller.check_for_reboot().await.unwrap(), None);
+    assert_eq!(controller.reboots(), 1);
+}
+
+#[tokio::test]
+async fn boot_banner_on_connect_is_kept_and_parsed() {
+    let sim = PmuSimulator::with_faults(Faults {
+        booting: true,
+        ..Faults::default()
+    });
+    let mut controller = controller(&sim);
+    let info = controller.get_system_info().await.unwrap();
+    assert!(!info.contains("Booting"), "{}", info);
+
+    let banner = controller.connection().boot_banner().unwrap().clone();
+    assert!(banner.text.starts_with(BOOT_BANNER), "{}", banner.text);
+    assert!(
+        banner.text.ends_with("Reset cause: watchdog"),
+        "{}",
+        banner.text
+    );
+    assert!(!banner.text.contains("pong"), "{}", banner.text);
+    assert_eq!(banner.zephyr_version.as_deref(), Some("v3.7.0"));
+    assert_eq!(
+        banner.firmware_version.as_deref(),
+        Some("2.5.0-+1234abc.42")
+    );
+    assert_eq!(banner.reset_cause.as_deref(), Some("watchdog"));
+
+    // The reset happened before this session, so it is not one of its reboots
+    assert_eq!(controller.check_for_reboot().await.unwrap(), None);
+    controller.close().await;
+}
+
+#[tokio::test]
+async fn long_running_pmu_has_no_boot_banner() {
+    let sim = PmuSimulator::start();
+    let mut controller = controller(&sim);
+    controller.get_system_info().await.unwrap();
+    assert_eq!(controller.connection().boot_banner(), None);
+    controller.close().await;
+}
+
+#[test]
+fn binary_json_carries_the_boot_banner_on_the_first_envelope() {
+    let state = tempfile::tempdir().unwrap();
+    let fresh = PmuSimulator::with_faults(Faults {
+        booting: true,
+        ..Faults::default()
+    });
+    let output = cli(&fresh, state.path())
+        .args(["--format", "json", "battery", "read"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["boot_banner"]["reset_cause"], "watchdog");
+    assert_eq!(json["boot_banner"]["firmware_version"], "2.5.0-+1234abc.42");
+
+    let running = PmuSimulator::start();
+    let output = cli(&running, state.path())
+        .args(["--format", "json", "battery", "read"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json.get("boot_banner").is_none(), "{}", json);
+}
+
+#[test]
+fn binary_show_banner_prints_it_to_stderr() {
+    let sim = PmuSimulator::with_faults(Faults {
+        booting: true,
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["--show-banner", "battery", "read"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Boot banner:"), "{}", stderr);
+    assert!(stderr.contains(BOOT_BANNER), "{}", stderr);
+    assert!(!String::from_utf8_lossy(&output.stdout).contains(BOOT_BANNER));
+}
+
+#[test]
+fn binary_raw_options_store_the_reply() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| {
+        let output = cli(&sim, state.path())
+            .args(["--format", "json"])
+            .args(args)
+            .args(["system", "info"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        parse_envelope(&String::from_utf8(output.stdout).unwrap()).unwrap()
+    };
+
+    let full = run(&[]);
+    assert_eq!(full.raw_response_info, None);
+    let reply = full.raw_text().unwrap().unwrap();
+    assert!(reply.contains("Board:"), "{}", reply);
+
+    let compressed = run(&["--raw-compress"]);
+    assert_ne!(compressed.raw_response.as_deref(), Some(reply.as_str()));
+    assert_eq!(compressed.raw_text().unwrap(), Some(reply));
+    assert!(!compressed.raw_truncated());
+
+    let omitted = run(&["--raw", "omit"]);
+    assert_eq!(omitted.raw_response, None);
+    assert_eq!(omitted.data, full.data);
+
+    let output = cli(&sim, state.path())
+        .args(["--raw", "omit", "--raw-compress", "ping"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--raw-compress"));
+}
+
+#[test]
+fn binary_progress_rewrites_collapse_in_human_output_only() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    let human = cli(&sim, state.path())
+        .args(["system", "erase", "app"])
+        .output()
+        .unwrap();
+    assert!(human.status.success(), "{:?}", human);
+    let stdout = String::from_utf8_lossy(&human.stdout);
+    assert!(!stdout.contains('\r'), "{:?}", stdout);
+    assert!(
+        stdout.contains("slot0: [##########] 100%\nslot1: erased\n"),
+        "{}",
+        stdout
+    );
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "system", "erase", "app"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let raw = json["raw_response"].as_str().unwrap();
+    assert!(
+        raw.contains("slot0: [          ]   0%\rslot0: [#####     ]  50%\r"),
+        "{:?}",
+        raw
+    );
+}
+
+#[tokio::test]
+async fn setup_saves_the_answers_and_checks_the_new_setup() {
+    let sim = PmuSimulator::start();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    std::fs::write(&path, "[commands]\nnfc = \"nfc\"\n").unwrap();
+
+    let setup = Setup {
+        path: path.clone(),
+        force: true,
+        baud: 115200,
+        answers: SetupAnswers {
+            device: "/dev/ttyLP2".into(),
+            format: OutputFormat::Human,
+            timeout: 3,
+            capacity_mah: None,
+            profile: None,
+        },
+    };
+    // Port by path, then format, timeout, capacity and profile
+    let script = format!("{}\njson\n5\n3000\nbench\n", sim.device());
+    let mut prompter = Prompter::new(std::io::Cursor::new(script.into_bytes()), Vec::new());
+    let report = setup.run(Some(&mut prompter)).await.unwrap();
+
+    let config = Config::load(Some(&path)).unwrap();
+    assert_eq!(config, report.config);
+    assert_eq!(config.connection.device.as_deref(), Some(sim.device()));
+    assert_eq!(config.output.format, Some(OutputFormat::Json));
+    assert_eq!(config.battery.capacity_mah, Some(3000));
+    assert_eq!(config.profile.as_deref(), Some("bench"));
+    // Sections the wizard does not ask about survive
+    assert!(!config.commands.is_empty());
+
+    let checks: Vec<(&str, bool)> = report
+        .checks
+        .iter()
+        .map(|check| (check.name.as_str(), check.passed))
+        .collect();
+    assert_eq!(
+        checks,
+        [
+            ("Serial link", true),
+            ("Firmware", true),
+            ("Battery gauge", true)
+        ]
+    );
+    assert_eq!(report.checks[2].detail, "3850 mV");
+}
+
+#[test]
+fn binary_setup_yes_writes_config_once() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let config = state.path().join("config.toml");
+    let setup = |extra: &[&str]| {
+        cli(&sim, state.path())
+            .args(["--config", config.to_str().unwrap(), "--format", "json"])
+            .args(["setup", "--yes", "--profile", "fleet", "--capacity", "3000"])
+            .args(extra)
+            .output()
+            .unwrap()
+    };
+
+    let output = setup(&[]);
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["command"], "setup");
+    assert_eq!(json["data"]["config"]["profile"], "fleet");
+    let written = std::fs::read_to_string(&config).unwrap();
+    assert!(
+        written.contains(&format!("device = \"{}\"", sim.device())),
+        "{}",
+        written
+    );
+    assert!(written.contains("format = \"json\""), "{}", written);
+
+    let output = setup(&[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--force"));
+
+    assert!(setup(&["--force"]).status.success());
+}
+
+#[tokio::test]
+async fn gpio_script_runs_as_one_batch_when_supported() {
+    let sim = PmuSimulator::with_faults(Faults {
+        gpio_batch: true,
+        ..Faults::default()
+    });
+    let mut controller = controller(&sim);
+    let script: GpioScript = "a0=1,5ms,a1=1,2ms,b3=0".parse().unwrap();
+
+    assert!(controller.supports_gpio_batch().await);
+    let report = controller
+        .run_gpio_script(&script, GpioScriptMode::Firmware)
+        .await
+        .unwrap();
+    assert!(report.success);
+    assert_eq!(report.ops[2].at_us, Some(7080));
+    assert_eq!(
+        sim.received().last().map(String::as_str),
+        Some("gpio batch A0=1 5ms A1=1 2ms B3=0")
+    );
+}
+
+#[tokio::test]
+async fn gpio_script_falls_back_to_host_timing() {
+    let sim = PmuSimulator::start();
+    let mut controller = controller(&sim);
+    let script: GpioScript = "a0=1,20ms,a1=0".parse().unwrap();
+
+    assert!(!controller.supports_gpio_batch().await);
+    let report = controller
+        .run_gpio_script(&script, GpioScriptMode::Host)
+        .await
+        .unwrap();
+    assert!(report.success, "{:?}", report);
+    assert_eq!(report.mode, GpioScriptMode::Host);
+    assert!(report.ops[1].gap_us.unwrap() >= 20_000);
+    let sets: Vec<String> = sim
+        .received()
+        .into_iter()
+        .filter(|c| c.starts_with("gpio set"))
+        .collect();
+    assert_eq!(sets, ["gpio set gpioa 0 1", "gpio set gpioa 1 0"]);
+}
+
+#[test]
+fn binary_gpio_pins_are_sent_in_firmware_form() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "gpio", "get", "pta5"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["command"], "gpio get");
+    assert_eq!(json["data"]["value"], 1);
+    assert_eq!(json["data"]["names"]["typed"], "pta5");
+    assert_eq!(json["data"]["names"]["firmware"], "gpioa 5");
+    assert_eq!(json["data"]["names"]["schematic"], "PTA5");
+
+    let output = cli(&sim, state.path())
+        .args(["gpio", "set", "B3", "1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("B3 → gpiob 3 (PTB3)"), "{}", stdout);
+
+    cli(&sim, state.path())
+        .args(["gpio", "get", "gpiof", "1"])
+        .assert()
+        .failure();
+
+    let commands: Vec<String> = sim
+        .received()
+        .into_iter()
+        .filter(|command| command.starts_with("gpio"))
+        .collect();
+    assert_eq!(commands, ["gpio get gpioa 5", "gpio set gpiob 3 1"]);
+}
+
+#[test]
+fn binary_gpio_script_host_flag_skips_the_batch() {
+    let sim = PmuSimulator::with_faults(Faults {
+        gpio_batch: true,
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "gpio", "script", "--host", "a0=1,a1=1"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["mode"], "host");
+    assert_eq!(json["data"]["ops"][1]["status"], "ok");
+    assert!(!sim.received().iter().any(|c| c.starts_with("gpio batch")));
+}
+
+#[test]
+fn binary_stats_add_up_across_invocations_until_reset() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let stats = || {
+        let output = cli(&sim, state.path())
+            .args(["--format", "json", "stats"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["data"].clone()
+    };
+
+    for _ in 0..2 {
+        cli(&sim, state.path()).arg("ping").assert().success();
+    }
+    let total = stats();
+    // The connect handshake pings before each command
+    assert_eq!(total["commands"], 4);
+    assert_eq!(total["families"]["ping"], 4);
+    assert_eq!(total["timeouts"], 0);
+    assert_eq!(total["bytes_sent"], 4 * "ping\n".len());
+    assert!(total["avg_latency_ms"].as_f64().is_some());
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "stats", "--reset"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["command"], "stats reset");
+    assert_eq!(json["data"]["commands"], 4);
+    assert_eq!(stats()["commands"], 0);
+}
+
+#[test]
+fn binary_schedule_runs_the_command_when_due() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let schedules = || {
+        let output = cli(&sim, state.path())
+            .args(["--format", "json", "schedule", "list"])
+            .output()
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["data"].clone()
+    };
+
+    let output = cli(&sim, state.path())
+        .args([
+            "--format", "json", "schedule", "at", "+2s", "power", "display", "off",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["command"], "schedule at");
+    assert_eq!(json["data"]["status"], "pending");
+    assert!(json["data"]["pid"].as_u64().is_some());
+    assert!(!sim.received().contains(&"pm disp off".to_string()));
+
+    let deadline = std::time::Instant::now() + Duration::from_secs(20);
+    while schedules()[0]["status"] != "done" {
+        assert!(std::time::Instant::now() < deadline, "{}", schedules());
+        std::thread::sleep(Duration::from_millis(200));
+    }
+    assert!(sim.received().contains(&"pm disp off".to_string()));
+    assert_eq!(schedules()[0]["exit_status"], 0);
+
+    // The deferred command took the normal path and was recorded
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "history"])
+        .output()
+        .unwrap();
+    let history = String::from_utf8_lossy(&output.stdout);
+    assert!(history.contains("power display off"), "{}", history);
+}
+
+#[test]
+fn binary_cancelled_schedule_is_not_run() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args([
+            "--format", "json", "schedule", "at", "+3s", "power", "display", "off",
+        ])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let id = json["data"]["id"].as_str().unwrap().to_string();
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "schedule", "cancel", &id])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["status"], "cancelled");
+
+    std::thread::sleep(Duration::from_secs(5));
+    assert!(!sim.received().contains(&"pm disp off".to_string()));
+    let output = cli(&sim, state.path())
+        .args(["schedule", "list"])
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[cancelled] power display off"));
+    cli(&sim, state.path())
+        .args(["schedule", "cancel", &id])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn binary_schedule_rejects_commands_that_would_not_parse() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    for command in [
+        &["+1m", "power", "display", "sideways"][..],
+        &["+1m", "monitor", "--continuous"],
+        &["yesterday", "ping"],
+    ] {
+        cli(&sim, state.path())
+            .args(["schedule", "at"])
+            .args(command)
+            .assert()
+            .failure();
+    }
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "schedule", "list"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"], serde_json::json!([]));
+}
+
+#[test]
+fn binary_systemd_schedule_records_its_timer_unit() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["--dry-run", "schedule", "at", "+1h", "--backend", "systemd"])
+        .args(["power", "display", "off"])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(
+            "[dry-run] would schedule: systemd-run --user --unit=eink-power-cli-schedule-"
+        ),
+        "{}",
+        stdout
+    );
+
+    let output = cli(&sim, state.path())
+        .env("EINK_POWER_CLI_SYSTEMD_RUN", "true")
+        .args([
+            "--format",
+            "json",
+            "schedule",
+            "at",
+            "+1h",
+            "--backend",
+            "systemd",
+        ])
+        .args(["power", "display", "off"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["backend"], "systemd");
+    assert_eq!(
+        json["data"]["unit"],
+        format!(
+            "eink-power-cli-schedule-{}",
+            json["data"]["id"].as_str().unwrap()
+        )
+    );
+}
+
+#[test]
+fn binary_rail_already_in_state_succeeds_unchanged() {
+    for reply in [
+        "Error: WiFi already disabled",
+        "WiFi: no change (already off)",
+    ] {
+        let sim = PmuSimulator::with_faults(Faults {
+            unchanged_reply: Some(reply.to_string()),
+            ..Faults::default()
+        });
+        let state = tempfile::tempdir().unwrap();
+        let set = |state_word: &str| {
+            let output = cli(&sim, state.path())
+                .args(["--format", "json", "power", "wifi", state_word])
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{}: {:?}", reply, output);
+            serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+        };
+
+        let unchanged = set("off");
+        assert_eq!(unchanged["command"], "power wifi");
+        assert_eq!(unchanged["data"]["changed"], false, "{}", reply);
+        assert_eq!(unchanged["raw_response"], reply);
+        assert_eq!(set("on")["data"]["changed"], true);
+    }
+}
+
+#[test]
+fn binary_genuine_controller_errors_still_fail() {
+    let sim = PmuSimulator::with_faults(Faults {
+        unchanged_reply: Some("Error: WiFi already disabled".to_string()),
+        unsupported: vec!["nfc enable".to_string()],
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+
+    // Firmware without a command answers it with an error
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "nfc", "enable"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unknown command"));
+}
+
+#[test]
+fn binary_unchanged_rail_is_noted_in_human_output() {
+    let sim = PmuSimulator::with_faults(Faults {
+        unchanged_reply: Some("Error: PMIC already on".to_string()),
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["pm", "pmic", "on"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("already in requested state"));
+}
+
+/// A process other than the CLI holding the simulator port, as a getty would
+fn port_holder(sim: &PmuSimulator) -> std::process::Child {
+    std::process::Command::new("sleep")
+        .arg("30")
+        .stdin(std::fs::File::open(sim.device()).unwrap())
+        .spawn()
+        .unwrap()
+}
+
+/// Fake systemctl logging its arguments and killing `HOLDER_PID` on stop
+fn fake_systemctl(dir: &std::path::Path) -> std::path::PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+    let path = dir.join("systemctl");
+    let script = format!(
+        "#!/bin/sh\necho \"$*\" >> {}\n[ \"$1\" = stop ] && kill \"$HOLDER_PID\"\nexit 0\n",
+        dir.join("systemctl.log").display()
+    );
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[test]
+fn binary_refuses_a_port_held_by_another_process() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let mut holder = port_holder(&sim);
+
+    let output = cli(&sim, state.path()).arg("ping").output().unwrap();
+    holder.kill().unwrap();
+    holder.wait().unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!("in use by sleep (pid {})", holder.id())),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("--steal"), "{}", stderr);
+    assert!(sim.received().is_empty());
+}
+
+#[test]
+fn binary_steal_stops_the_holder_unit_and_restarts_it() {
+    let sim = PmuSimulator::with_faults(Faults {
+        unsupported: vec!["nfc enable".to_string()],
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let config = state.path().join("config.toml");
+    std::fs::write(
+        &config,
+        "[connection]\nsteal_unit = \"getty-test.service\"\n",
+    )
+    .unwrap();
+    let systemctl = fake_systemctl(state.path());
+    let log = state.path().join("systemctl.log");
+
+    for (command, succeeds) in [(["ping"].as_slice(), true), (&["nfc", "enable"], false)] {
+        let mut holder = port_holder(&sim);
+        let output = cli(&sim, state.path())
+            .env("EINK_POWER_CLI_SYSTEMCTL", &systemctl)
+            .env("HOLDER_PID", holder.id().to_string())
+            .args(["--config", config.to_str().unwrap(), "--steal"])
+            .args(command)
+            .output()
+            .unwrap();
+        holder.wait().unwrap();
+
+        assert_eq!(output.status.success(), succeeds, "{:?}", output);
+        // Started again on the error path too
+        assert_eq!(
+            std::fs::read_to_string(&log).unwrap(),
+            "stop getty-test.service\nstart getty-test.service\n"
+        );
+        std::fs::remove_file(&log).unwrap();
+    }
+}
+
+#[test]
+fn binary_deprecated_name_warns_and_marks_json() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| {
+        let output = cli(&sim, state.path())
+            .args(["--format", "json"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        (json, String::from_utf8_lossy(&output.stderr).into_owned())
+    };
+
+    let (json, stderr) = run(&["pm", "disp", "off"]);
+    assert_eq!(json["command"], "pm display");
+    assert_eq!(json["deprecated"], true);
+    assert!(
+        stderr.contains("warning: deprecated command 'pm disp', use 'pm display'"),
+        "{}",
+        stderr
+    );
+
+    let (json, stderr) = run(&["pm", "display", "off"]);
+    assert!(json.get("deprecated").is_none());
+    assert_eq!(json["data"]["rail"], "display");
+    assert!(!stderr.contains("deprecated"), "{}", stderr);
+
+    let output = cli(&sim, state.path())
+        .args(["--quiet", "pm", "disp", "off"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("deprecated"));
+    assert_eq!(
+        sim.received()
+            .iter()
+            .filter(|command| *command == "pm disp off")
+            .count(),
+        3
+    );
+}
+
+#[test]
+fn binary_battery_samples_report_the_median_past_a_failed_reading() {
+    let sim = PmuSimulator::with_faults(Faults {
+        battery_voltages: vec![Some(3850), Some(3600), None, Some(3852), Some(3848)],
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "battery", "read"])
+        .args(["--samples", "5", "--sample-interval-ms", "10"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let data = &json["data"];
+    assert_eq!(json["command"], "battery read");
+    // The dip after a refresh does not drag the median down
+    assert_eq!(data["median"]["voltage_mv"], 3849);
+    assert_eq!(data["voltage_mv"]["min"], 3600.0);
+    assert_eq!(data["voltage_mv"]["max"], 3852.0);
+    assert_eq!(data["requested"], 5);
+    assert_eq!(data["failed"], 1);
+    assert_eq!(data["samples"].as_array().unwrap().len(), 4);
+    assert_eq!(data["samples"][1]["voltage_mv"], 3600);
+}
+
+#[test]
+fn binary_battery_samples_fail_when_most_readings_fail() {
+    let sim = PmuSimulator::with_faults(Faults {
+        battery_voltages: vec![None, Some(3850), None, None],
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args([
+            "battery",
+            "read",
+            "--samples",
+            "4",
+            "--sample-interval-ms",
+            "10",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("only 1 of 4 samples succeeded"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn binary_battery_check_samples_decide_on_the_median_verdict() {
+    let sim = PmuSimulator::with_faults(Faults {
+        battery_verdict: "DEGRADED".to_string(),
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "pm", "battery-check"])
+        .args(["--samples", "3", "--sample-interval-ms", "10"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["median"]["verdict"], "degraded");
+    assert_eq!(json["data"]["samples"].as_array().unwrap().len(), 3);
+    let checks = sim
+        .received()
+        .iter()
+        .filter(|command| *command == "pm battery_check")
+        .count();
+    assert_eq!(checks, 3);
+}
+
+/// `--format json` document of a successful run of `args`
+fn json_output(
+    sim: &PmuSimulator,
+    state_dir: &std::path::Path,
+    args: &[&str],
+) -> serde_json::Value {
+    let output = cli(sim, state_dir)
+        .args(["--format", "json"])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn binary_sram_write_is_read_back() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    let written = json_output(&sim, state.path(), &["nfc", "sram-write", "0102A0FF"]);
+    assert_eq!(written["data"]["bytes"], 4);
+    assert!(sim
+        .received()
+        .contains(&"nfc sram write 0102A0FF".to_string()));
+
+    let read = json_output(&sim, state.path(), &["nfc", "sram-read"]);
+    assert_eq!(read["command"], "nfc sram-read");
+    assert_eq!(read["data"]["bytes"], 64);
+    let data = read["data"]["data"].as_str().unwrap();
+    assert_eq!(&data[..8], "0102A0FF");
+    assert_eq!(&data[8..], "0".repeat(120));
+}
+
+#[test]
+fn binary_transfer_receives_a_blob_from_the_phone() {
+    let blob: Vec<u8> = (0..150u8).collect();
+    let sim = PmuSimulator::with_faults(Faults {
+        phone: Some(blob.clone()),
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let file = state.path().join("provisioning.bin");
+
+    let json = json_output(
+        &sim,
+        state.path(),
+        &["nfc", "transfer", "from-phone", file.to_str().unwrap()],
+    );
+
+    assert_eq!(std::fs::read(&file).unwrap(), blob);
+    assert_eq!(json["command"], "nfc transfer");
+    assert_eq!(json["data"]["direction"], "from-phone");
+    assert_eq!(json["data"]["bytes"], 150);
+    // Three data mailboxes and the end mailbox
+    assert_eq!(json["data"]["mailboxes"], 4);
+    assert_eq!(json["data"]["retries"], 0);
+    assert_eq!(
+        sim.received().last().map(String::as_str),
+        Some("nfc session write 0xA1 1 0x0F 0x00")
+    );
+}
+
+#[test]
+fn binary_transfer_to_the_phone_repeats_mailboxes_after_field_loss() {
+    let sim = PmuSimulator::with_faults(Faults {
+        phone: Some(Vec::new()),
+        field_drops: vec![1, 5],
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let file = state.path().join("provisioning.bin");
+    let blob: Vec<u8> = (0..200u8).rev().collect();
+    std::fs::write(&file, &blob).unwrap();
+
+    let json = json_output(
+        &sim,
+        state.path(),
+        &["nfc", "transfer", "to-phone", file.to_str().unwrap()],
+    );
+
+    // Four data mailboxes, the end mailbox and the phone's acknowledgement,
+    // which is what the second drop hits
+    assert_eq!(json["data"]["mailboxes"], 6);
+    assert_eq!(json["data"]["retries"], 2);
+    assert_eq!(
+        json["data"]["crc32"],
+        format!("{:08X}", eink_power_cli::power::passthrough::crc32(&blob))
+    );
+    let second_mailbox = sim
+        .received()
+        .iter()
+        .filter(|command| command.starts_with("nfc sram write 013E"))
+        .count();
+    assert_eq!(second_mailbox, 2);
+    let handshakes = sim
+        .received()
+        .iter()
+        .filter(|command| *command == "nfc session write 0xA1 1 0x0F 0x0E")
+        .count();
+    assert_eq!(handshakes, 2);
+}
+
+#[test]
+fn binary_transfer_reports_progress_on_stderr() {
+    let sim = PmuSimulator::with_faults(Faults {
+        phone: Some(vec![0x5A; 70]),
+        field_drops: vec![0],
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let file = state.path().join("provisioning.bin");
+
+    let output = cli(&sim, state.path())
+        .args(["nfc", "transfer", "from-phone", file.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Mailbox 1, 62 bytes, 1 retried after field loss"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("Mailbox 3, 70 bytes"), "{}", stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Received 70 bytes into"), "{}", stdout);
+    assert!(stdout.contains("(verified)"), "{}", stdout);
+    assert_eq!(std::fs::read(&file).unwrap(), vec![0x5A; 70]);
+}
+
+#[test]
+fn binary_transfer_without_a_phone_times_out() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let file = state.path().join("provisioning.bin");
+    std::fs::write(&file, b"config").unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["nfc", "transfer", "to-phone", file.to_str().unwrap()])
+        .args(["--timeout", "1"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no RF field within 1 s"), "{}", stderr);
+}
+
+#[test]
+fn binary_rail_off_warns_about_dependents_still_on() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    // The simulated display is on and, on this board, supplied by the PMIC
+    let output = cli(&sim, state.path())
+        .args(["power", "pmic", "off"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("warning: Display still on and depending on PMIC"),
+        "{}",
+        stderr
+    );
+    assert_eq!(sim.received().last().unwrap(), "pm pmic off");
+}
+
+#[test]
+fn binary_rail_on_refuses_a_missing_prerequisite_without_auto_deps() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let config = state.path().join("config.toml");
+    std::fs::write(
+        &config,
+        "[rails]\ndependencies = [\"disp requires wifi\"]\n",
+    )
+    .unwrap();
+
+    let output = cli(&sim, state.path())
+        .args([
+            "--config",
+            config.to_str().unwrap(),
+            "power",
+            "display",
+            "on",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Display needs WiFi on first; run 'power wifi on' or pass --auto-deps"),
+        "{}",
+        stderr
+    );
+    assert!(!sim.received().contains(&"pm disp on".to_string()));
+
+    let json = json_output(
+        &sim,
+        state.path(),
+        &[
+            "--config",
+            config.to_str().unwrap(),
+            "--auto-deps",
+            "power",
+            "display",
+            "on",
+        ],
+    );
+    assert_eq!(json["data"]["enabled_first"], serde_json::json!(["wifi"]));
+    let switched: Vec<String> = sim
+        .received()
+        .into_iter()
+        .filter(|command| command.ends_with(" on"))
+        .collect();
+    assert_eq!(switched, ["pm wifi on", "pm disp on"]);
+}
+
+#[test]
+fn binary_dfu_mode_reports_the_window_deadline() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    let json = json_output(&sim, state.path(), &["system", "dfu-mode", "30"]);
+    assert_eq!(json["command"], "system dfu-mode");
+    assert_eq!(json["data"]["timeout_s"], 30);
+    // The fake mcumgr in these tests never answers
+    assert_eq!(json["data"]["enumerated"], false);
+    let remaining = json["data"]["remaining_s"].as_u64().unwrap();
+    assert!((25..=28).contains(&remaining), "{}", remaining);
+    let deadline = json["data"]["deadline"].as_str().unwrap();
+    assert!(
+        chrono::DateTime::parse_from_rfc3339(deadline).is_ok(),
+        "{}",
+        deadline
+    );
+    assert_eq!(sim.received().last().unwrap(), "system dfu-mode 30");
+}
+
+#[test]
+fn binary_infinite_dfu_mode_needs_confirmation() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    let output = cli(&sim, state.path())
+        .args(["system", "dfu-mode", "0"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--yes"));
+    assert!(!sim
+        .received()
+        .iter()
+        .any(|c| c.starts_with("system dfu-mode")));
+
+    let json = json_output(&sim, state.path(), &["system", "dfu-mode", "0", "--yes"]);
+    assert_eq!(json["data"]["deadline"], serde_json::Value::Null);
+    assert_eq!(sim.received().last().unwrap(), "system dfu-mode 0");
+}
+
+/// `ltc2959 adc_mode` commands the simulator received, in order
+fn adc_mode_commands(sim: &PmuSimulator) -> Vec<String> {
+    sim.received()
+        .into_iter()
+        .filter(|command| command.starts_with("ltc2959 adc_mode"))
+        .collect()
+}
+
+fn continuous_read(duration: Option<Duration>) -> ContinuousRead {
+    ContinuousRead {
+        interval: Duration::from_millis(50),
+        duration,
+    }
+}
+
+#[tokio::test]
+async fn continuous_read_restores_the_adc_mode_when_done() {
+    let sim = PmuSimulator::start();
+    let mut controller = controller(&sim);
+    let mut samples = Vec::new();
+
+    let result = continuous_read(Some(Duration::from_millis(500)))
+        .run(&mut controller, &CancellationToken::new(), |sample| {
+            samples.push(sample);
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    assert!(samples.len() >= 3, "{} samples", samples.len());
+    assert_eq!(result.summary.samples, samples.len() as u64);
+    assert_eq!(result.summary.current_min_ma, Some(-125));
+    assert_eq!(result.summary.current_avg_ma, Some(-125.0));
+    assert_eq!(result.interval_ms, 50);
+    let intervals = result.intervals_ms.unwrap();
+    assert!(intervals.min >= 40.0, "{:?}", intervals);
+    assert!(result.jitter_ms.unwrap() >= 0.0);
+    assert!(!result.cancelled);
+    assert_eq!(result.restored_adc_mode, AdcMode::SmartSleep);
+
+    assert_eq!(
+        adc_mode_commands(&sim),
+        ["ltc2959 adc_mode 4", "ltc2959 adc_mode 1"]
+    );
+    assert_eq!(
+        ltc2959::adc_mode(&mut controller).await.unwrap(),
+        AdcMode::SmartSleep
+    );
+}
+
+#[tokio::test]
+async fn continuous_read_restores_the_adc_mode_after_a_failed_reading() {
+    let sim = PmuSimulator::with_faults(Faults {
+        battery_voltages: vec![Some(3850), Some(3849), None],
+        ..Faults::default()
+    });
+    let mut controller = controller(&sim);
+    let mut samples = 0;
+
+    let error = continuous_read(None)
+        .run(&mut controller, &CancellationToken::new(), |_| {
+            samples += 1;
+            Ok(())
+        })
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(error, PowerCliError::ControllerError { .. }),
+        "{:?}",
+        error
+    );
+    assert_eq!(samples, 2);
+    assert_eq!(
+        adc_mode_commands(&sim),
+        ["ltc2959 adc_mode 4", "ltc2959 adc_mode 1"]
+    );
+    assert_eq!(
+        ltc2959::adc_mode(&mut controller).await.unwrap(),
+        AdcMode::SmartSleep
+    );
+}
+
+#[tokio::test]
+async fn continuous_read_restores_the_adc_mode_when_output_fails() {
+    let sim = PmuSimulator::start();
+    let mut controller = controller(&sim);
+
+    let error = continuous_read(None)
+        .run(&mut controller, &CancellationToken::new(), |_| {
+            Err(PowerCliError::Io(std::io::ErrorKind::BrokenPipe.into()))
+        })
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, PowerCliError::Io(_)), "{:?}", error);
+    assert_eq!(
+        adc_mode_commands(&sim),
+        ["ltc2959 adc_mode 4", "ltc2959 adc_mode 1"]
+    );
+}
+
+#[tokio::test]
+async fn continuous_read_restores_the_previous_mode_on_cancel() {
+    let sim = PmuSimulator::with_faults(Faults {
+        adc_mode: "Sleep".to_string(),
+        ..Faults::default()
+    });
+    let mut controller = controller(&sim);
+    let cancel = CancellationToken::new();
+    let interrupt = {
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            cancel.cancel();
+        })
+    };
+
+    let result = continuous_read(None)
+        .run(&mut controller, &cancel, |_| Ok(()))
+        .await
+        .unwrap();
+    interrupt.await.unwrap();
+
+    assert!(result.cancelled);
+    assert!(result.summary.samples >= 1);
+    assert_eq!(result.restored_adc_mode, AdcMode::Sleep);
+    assert_eq!(
+        adc_mode_commands(&sim),
+        ["ltc2959 adc_mode 4", "ltc2959 adc_mode 0"]
+    );
+    assert_eq!(
+        ltc2959::adc_mode(&mut controller).await.unwrap(),
+        AdcMode::Sleep
+    );
+}
+
+#[tokio::test]
+async fn continuous_read_refuses_an_adc_mode_it_could_not_restore() {
+    let sim = PmuSimulator::with_faults(Faults {
+        adc_mode: "Turbo".to_string(),
+        ..Faults::default()
+    });
+    let mut controller = controller(&sim);
+
+    let error = continuous_read(Some(Duration::from_millis(200)))
+        .run(&mut controller, &CancellationToken::new(), |_| Ok(()))
+        .await
+        .unwrap_err();
+
+    assert!(error.to_string().contains("'Turbo'"), "{}", error);
+    assert!(adc_mode_commands(&sim).is_empty());
+}
+
+#[test]
+fn binary_ltc2959_continuous_read_streams_and_summarizes() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["--format", "ndjson", "ltc2959", "read", "--continuous"])
+        .args(["--interval-ms", "100", "--duration", "1s"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let documents: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let (summary, samples) = documents.split_last().unwrap();
+    assert!(samples.len() >= 5, "{:?}", documents);
+    assert!(samples
+        .iter()
+        .all(|sample| sample["command"] == "ltc2959 read" && sample["data"]["current_ma"] == -125));
+    assert_eq!(summary["command"], "ltc2959 read summary");
+    assert_eq!(summary["data"]["restored_adc_mode"], "smart_sleep");
+    assert_eq!(summary["data"]["current_max_ma"], -125);
+    assert!(summary["data"]["jitter_ms"].is_number());
+    assert_eq!(
+        adc_mode_commands(&sim),
+        ["ltc2959 adc_mode 4", "ltc2959 adc_mode 1"]
+    );
+}
+
+/// `pm monitor` output of firmware 2.3: two labeled samples and a log line
+fn labeled_monitor_output() -> Vec<String> {
+    [
+        "[00:01:07.427,000] <inf> power_mgmt: Monitor sample:",
+        "  Voltage: 7412 mV",
+        "  Current: -142 mA",
+        "[00:01:08.100,000] <wrn> nfc: field lost",
+        "[00:01:12.427,000] <inf> power_mgmt: Monitor sample:",
+        "  Voltage: 7409 mV",
+        "  Current: -139 mA",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+#[tokio::test]
+async fn monitor_output_is_read_between_commands() {
+    use eink_power_cli::json::samples::SampleParser;
+
+    let sim = PmuSimulator::with_faults(Faults {
+        monitor_output: labeled_monitor_output(),
+        ..Faults::default()
+    });
+    let mut controller = controller(&sim);
+    controller.pm_command("monitor start 1").await.unwrap();
+
+    let mut parser = SampleParser::new();
+    let mut samples = Vec::new();
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while samples.len() < 2 && tokio::time::Instant::now() < deadline {
+        let output = controller
+            .read_console(Duration::from_millis(200))
+            .await
+            .unwrap();
+        samples.extend(if output.is_empty() {
+            parser.flush()
+        } else {
+            parser.push(&output)
+        });
+    }
+    controller.pm_command("monitor stop").await.unwrap();
+
+    assert_eq!(parser.format(), Some("labeled"));
+    let voltages: Vec<_> = samples.iter().map(|s| s.battery.voltage_mv).collect();
+    assert_eq!(voltages, [Some(7412), Some(7409)]);
+    assert_eq!(parser.stats().unparsed, 1);
+    assert_eq!(
+        sim.received().last().map(String::as_str),
+        Some("pm monitor stop")
+    );
+}
+
+#[test]
+fn binary_monitor_follow_reads_samples_and_stops_the_monitor() {
+    use assert_cmd::cargo::CommandCargoExt;
+
+    let sim = PmuSimulator::with_faults(Faults {
+        monitor_output: labeled_monitor_output(),
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let console = state.path().join("console.log");
+    let child = std::process::Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .env("EINK_POWER_CLI_STATE_DIR", state.path())
+        .env("EINK_POWER_CLI_ASSERT_CLOSED", "1")
+        .args(["--device", sim.device(), "--format", "ndjson", "monitor"])
+        .args(["--follow", "--interval", "1", "--console-log"])
+        .arg(&console)
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(3000));
+    let status = std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(status.success());
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let samples: Vec<_> = lines
+        .iter()
+        .filter(|line| line["command"] == "monitor")
+        .collect();
+    assert_eq!(samples.len(), 2, "{}", stdout);
+    assert_eq!(samples[0]["data"]["voltage_mv"], 7412);
+    assert_eq!(samples[1]["data"]["current_ma"], -139);
+    assert_eq!(samples[0]["data"]["source"], "pm monitor");
+    let summary = lines.last().unwrap();
+    assert_eq!(summary["data"]["samples"], 2);
+    assert_eq!(summary["data"]["sample_format"], "labeled");
+    assert_eq!(summary["data"]["unparsed_lines"], 1);
+
+    let received = sim.received();
+    assert!(received.contains(&"pm monitor start 1".to_string()));
+    assert_eq!(received.last().map(String::as_str), Some("pm monitor stop"));
+
+    // The console log reads back as the same samples
+    let (records, _) =
+        eink_power_cli::battery_log::read_log(&std::fs::read_to_string(&console).unwrap());
+    let voltages: Vec<_> = records.iter().map(|r| r.battery.voltage_mv).collect();
+    assert_eq!(voltages, [Some(7412), Some(7409)]);
+}
+
+#[test]
+fn binary_system_info_checks_the_expected_version() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+
+    // The simulator runs 2.5.0-+1234abc.42
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "system", "info"])
+        .args(["--expect-version", ">=2.2, <3"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["version_ok"], true);
+    assert_eq!(json["data"]["semver"], "2.5.0");
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "system", "info"])
+        .args(["--expect-version", ">=3"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["version_ok"], false);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Firmware version 2.5.0-+1234abc.42 does not meet the requirement >=3"),
+        "{}",
+        stderr
+    );
+
+    let output = cli(&sim, state.path())
+        .args(["system", "info", "--expect-version", "<2.5"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("❌ Firmware 2.5.0-+1234abc.42 does not meet <2.5"),
+        "{}",
+        stdout
+    );
+
+    // Without a requirement the field is left out
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "system", "info"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(json["data"].get("version_ok").is_none(), "{}", json);
+}
+
+#[test]
+fn binary_history_records_the_exit_code_of_the_error() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["system", "info", "--expect-version", ">=3"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "history"])
+        .output()
+        .unwrap();
+    let entries = match parse_output(&String::from_utf8_lossy(&output.stdout)).unwrap() {
+        CommandOutput::History(entries) => entries,
+        other => panic!("unexpected output {:?}", other),
+    };
+    let entry = entries.last().unwrap();
+    assert!(entry.invocation.contains("--expect-version"), "{:?}", entry);
+    assert_eq!(entry.exit_status, 4);
+}
+
+/// Every step of provisioning, with a firmware requirement the simulator
+/// already meets
+const PROVISION_MANIFEST: &str = r#"
+auto_confirm = true
+
+[firmware]
+version = "2.4.0"
+
+[identity]
+serial = "EINK-000123"
+hw_rev = 3
+date = "2025-10-09"
+
+[defaults]
+pmic = true
+wifi = false
+disp = true
+
+[battery]
+capacity_mah = 3000
+
+[rtc]
+action = "wake"
+
+[health]
+min_voltage_mv = 3600
+max_discharge_ma = 200
+"#;
+
+fn firmware_manager(sim: &PmuSimulator) -> FirmwareManager {
+    let connection = Connection::new(sim.device(), 115200, true).unwrap();
+    let transports = FirmwareTransports::resolve(sim.device(), 115200, None, None);
+    FirmwareManager::new(connection, transports)
+}
+
+#[tokio::test]
+async fn provision_runs_every_step_in_order() {
+    let sim = PmuSimulator::start();
+    let mut controller = controller(&sim);
+    let mut firmware = firmware_manager(&sim);
+    let manifest = ProvisionManifest::parse(PROVISION_MANIFEST).unwrap();
+
+    let mut saved = Vec::new();
+    let record = provision::run(&mut controller, &mut firmware, &manifest, None, |record| {
+        saved.push(record.steps.len())
+    })
+    .await;
+    controller.close().await;
+
+    assert!(record.success, "{}", record.format_human());
+    let statuses: Vec<_> = record.steps.iter().map(|s| (s.step, s.status)).collect();
+    assert_eq!(
+        statuses,
+        ProvisionStep::ALL.map(|step| (step, ProvisionStatus::Ok))
+    );
+    // Saved after each step
+    assert_eq!(saved, [1, 2, 3, 4, 5, 6]);
+    assert_eq!(record.serial.as_deref(), Some("EINK-000123"));
+    assert_eq!(
+        record.firmware_version.as_deref(),
+        Some("2.5.0-+1234abc.42")
+    );
+    assert_eq!(
+        record.steps[3].detail.as_deref(),
+        Some("charge set to 3000 mAh (verified)")
+    );
+
+    let received = sim.received();
+    let sent = |command: &str| received.iter().filter(|c| *c == command).count();
+    assert_eq!(sent("pm defaults disp on"), 1);
+    assert_eq!(sent("pm defaults save"), 1);
+    assert_eq!(sent("ltc2959 production_reset"), 1);
+    assert_eq!(sent("ltc2959 set_charge 3000"), 1);
+    assert_eq!(sent("rtc config wake"), 1);
+    assert_eq!(sent("pm battery_check"), 1);
+
+    let mut controller = self::controller(&sim);
+    let identity = controller.read_identity().await.unwrap().unwrap();
+    controller.close().await;
+    assert_eq!(identity.serial, "EINK-000123");
+    assert_eq!(identity.hw_rev, 3);
+}
+
+#[tokio::test]
+async fn provision_resumes_after_the_failed_step() {
+    // The gauge ignores the charge written to it
+    let sim = PmuSimulator::with_faults(Faults {
+        charge_read_only: true,
+        ..Faults::default()
+    });
+    let manifest = ProvisionManifest::parse(PROVISION_MANIFEST).unwrap();
+    let mut controller = controller(&sim);
+    let record = provision::run(
+        &mut controller,
+        &mut firmware_manager(&sim),
+        &manifest,
+        None,
+        |_| {},
+    )
+    .await;
+    controller.close().await;
+
+    assert!(!record.success);
+    let failed = record.failed_step().unwrap();
+    assert_eq!(failed.step, ProvisionStep::Gauge);
+    assert_eq!(
+        failed.detail.as_deref(),
+        Some("Battery monitoring error: charge register reads 2450 mAh after setting 3000 mAh")
+    );
+    assert_eq!(record.steps[4].status, ProvisionStatus::NotRun);
+    assert_eq!(record.steps[5].status, ProvisionStatus::NotRun);
+
+    // Only the gauge and the steps after it run again
+    let sim = PmuSimulator::start();
+    let mut controller = self::controller(&sim);
+    let resumed = provision::run(
+        &mut controller,
+        &mut firmware_manager(&sim),
+        &manifest,
+        Some(&record),
+        |_| {},
+    )
+    .await;
+    controller.close().await;
+
+    assert!(resumed.success, "{}", resumed.format_human());
+    let statuses: Vec<_> = resumed.steps.iter().map(|s| s.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            ProvisionStatus::Resumed,
+            ProvisionStatus::Resumed,
+            ProvisionStatus::Resumed,
+            ProvisionStatus::Ok,
+            ProvisionStatus::Ok,
+            ProvisionStatus::Ok,
+        ]
+    );
+    assert_eq!(resumed.steps[1].detail, record.steps[1].detail);
+    assert_eq!(resumed.firmware_version, record.firmware_version);
+    let received = sim.received();
+    assert!(!received.iter().any(|c| c.starts_with("nfc eeprom write")));
+    assert!(!received.iter().any(|c| c == "pm defaults save"));
+    assert!(received.iter().any(|c| c == "ltc2959 set_charge 3000"));
+}
+
+#[tokio::test]
+async fn provision_stops_when_the_firmware_is_old_and_there_is_no_image() {
+    let sim = PmuSimulator::start();
+    let manifest = ProvisionManifest::parse(
+        "[firmware]\nversion = \"3.0.0\"\n\n[identity]\nserial = \"EINK-1\"\nhw_rev = 1\n",
+    )
+    .unwrap();
+    let mut controller = controller(&sim);
+    let record = provision::run(
+        &mut controller,
+        &mut firmware_manager(&sim),
+        &manifest,
+        None,
+        |_| {},
+    )
+    .await;
+    controller.close().await;
+
+    assert!(!record.success);
+    assert_eq!(record.steps[0].status, ProvisionStatus::Failed);
+    assert!(
+        record.steps[0].detail.as_deref().unwrap().contains(
+            "unit runs 2.5.0-+1234abc.42, older than 3.0.0, and the manifest names no image"
+        ),
+        "{:?}",
+        record.steps[0]
+    );
+    assert_eq!(record.steps[1].status, ProvisionStatus::NotRun);
+    assert_eq!(record.steps[2].status, ProvisionStatus::NotRun);
+    assert!(!sim.received().iter().any(|c| c.starts_with("nfc eeprom")));
+}
+
+#[test]
+fn binary_provision_keeps_a_failed_run_to_resume() {
+    let sim = PmuSimulator::with_faults(Faults {
+        battery_verdict: "DEGRADED".to_string(),
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let manifest = state.path().join("unit.toml");
+    std::fs::write(&manifest, PROVISION_MANIFEST).unwrap();
+    let record_file = state.path().join("record.json");
+    let run = |args: &[&str]| {
+        cli(&sim, state.path())
+            .args(["--format", "json", "provision"])
+            .arg(&manifest)
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&["--output", record_file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["command"], "provision");
+    assert_eq!(json["data"]["success"], false);
+    assert_eq!(json["data"]["serial"], "EINK-000123");
+    assert_eq!(json["data"]["steps"][4]["step"], "rtc");
+    assert_eq!(json["data"]["steps"][4]["status"], "ok");
+    assert_eq!(json["data"]["steps"][5]["status"], "failed");
+    assert_eq!(
+        json["data"]["steps"][5]["detail"],
+        "Battery monitoring error: health check failed: battery check verdict is degraded"
+    );
+    let archived: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&record_file).unwrap()).unwrap();
+    assert_eq!(archived["data"], json["data"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Provisioning failed at step 'Health checks'"),
+        "{}",
+        stderr
+    );
+    let progress = state
+        .path()
+        .join(device_key(sim.device()))
+        .join("provision.json");
+    assert!(progress.exists());
+
+    // Only the health checks run again
+    let output = run(&["--resume"]);
+    assert_eq!(output.status.code(), Some(1));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["steps"][0]["status"], "resumed");
+    assert_eq!(json["data"]["steps"][4]["status"], "resumed");
+    assert_eq!(json["data"]["steps"][5]["status"], "failed");
+    let received = sim.received();
+    let sent = |command: &str| received.iter().filter(|c| *c == command).count();
+    assert_eq!(sent("pm defaults save"), 1);
+    assert_eq!(sent("rtc config wake"), 1);
+    assert_eq!(sent("pm battery_check"), 2);
+
+    // A different manifest is not resumed
+    std::fs::write(&manifest, PROVISION_MANIFEST.replace("3000", "2800")).unwrap();
+    let output = run(&["--resume"]);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("the manifest changed"));
+}
+
+#[test]
+fn binary_provision_forgets_a_run_once_it_passes() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let manifest = state.path().join("unit.toml");
+    std::fs::write(&manifest, PROVISION_MANIFEST).unwrap();
+
+    let output = cli(&sim, state.path())
+        .arg("provision")
+        .arg(&manifest)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("✅ Battery gauge"), "{}", stdout);
+    assert!(stdout.contains("PASS: EINK-000123"), "{}", stdout);
+    assert!(!state
+        .path()
+        .join(device_key(sim.device()))
+        .join("provision.json")
+        .exists());
+}
+
+/// Commands the CLI sends, at least one per firmware root command
+const CLI_COMMANDS: &[&str] = &[
+    "board shutdown",
+    "comm bt_wake on",
+    "gpio get gpioa 5",
+    "gpio set gpioa 5 1",
+    "help",
+    "ltc2959 read",
+    "ltc2959 status",
+    "ltc2959 enable",
+    "ltc2959 sleep",
+    "nfc status",
+    "nfc info",
+    "nfc field_detect",
+    "nfc tag_info",
+    "ping",
+    "pm stats",
+    "pm measure",
+    "pm battery_check",
+    "pm wake config",
+    "pm pmic status",
+    "pm defaults",
+    "power stats",
+    "power coulomb",
+    "rtc status",
+    "rtc get",
+    "rtc show",
+    "rtc calibration",
+    "rtc config auto",
+    "system info",
+    "system uptime",
+    "version",
+];
+
+#[tokio::test]
+async fn simulator_covers_every_root_command_of_the_firmware() {
+    let roots: Vec<&str> = ROOT_COMMANDS.iter().map(|(root, _)| *root).collect();
+    for family in CommandFamily::ALL {
+        assert!(roots.contains(&family.default_root()), "{:?}", family);
+        assert!(
+            CLI_COMMANDS
+                .iter()
+                .any(|command| command.split(' ').next() == Some(family.default_root())),
+            "{:?}",
+            family
+        );
+    }
+
+    let sim = PmuSimulator::start();
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    for command in CLI_COMMANDS {
+        assert!(
+            roots.contains(&command.split(' ').next().unwrap()),
+            "{}",
+            command
+        );
+        let reply = connection.send_command(command).await.unwrap();
+        assert!(!reply.contains("unknown command"), "{}: {}", command, reply);
+    }
+    let help = connection.send_command("help").await.unwrap();
+    for root in roots {
+        assert!(help.contains(&format!("\n  {} ", root)), "{}", help);
+    }
+    connection.close().await;
+}
+
+#[tokio::test]
+async fn low_battery_scenario_reads_low_and_fails_the_health_check() {
+    let sim = PmuSimulator::with_faults(Faults::scenario(SimScenario::LowBattery));
+    let mut controller = controller(&sim);
+    let reading = controller.measure().await.unwrap();
+    let health = controller.battery_health_check().await.unwrap();
+    controller.close().await;
+
+    assert_eq!(reading.voltage_mv, Some(LOW_BATTERY_MV));
+    assert_eq!(health.verdict, Some(BatteryVerdict::Degraded));
+}
+
+#[tokio::test]
+async fn flaky_serial_scenario_still_gets_through() {
+    let sim = PmuSimulator::with_faults(Faults::scenario(SimScenario::FlakySerial));
+    let mut controller = controller(&sim);
+    for _ in 0..3 {
+        let reading = controller.ltc2959_measurement().await.unwrap();
+        assert_eq!(reading.voltage_mv, Some(3850));
+    }
+    controller.close().await;
+
+    // The first command after the handshake arrived garbled and was sent again
+    let received = sim.received();
+    assert!(
+        received.iter().any(|c| c.starts_with("l~")),
+        "{:?}",
+        received
+    );
+}
+
+#[tokio::test]
+async fn bootloader_scenario_answers_only_mcumgr_echo() {
+    let sim = PmuSimulator::with_faults(Faults::scenario(SimScenario::BootloaderMode));
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_timeout(1);
+    assert!(matches!(
+        connection.send_command("version").await,
+        Err(PowerCliError::Timeout { .. })
+    ));
+    connection.close().await;
+
+    // `mcumgr echo bootloader_test`, sequence number 42
+    let request = "\x06\tAB0CAAATAAAqAKFhZG9ib290bG9hZGVyX3Rlc3S6xw==\n";
+    let mut port = serialport::new(sim.device(), 115200)
+        .timeout(Duration::from_millis(500))
+        .open()
+        .unwrap();
+    port.write_all(request.as_bytes()).unwrap();
+    let mut reply = Vec::new();
+    let mut buf = [0u8; 128];
+    while !reply.ends_with(b"\n") {
+        let n = port.read(&mut buf).unwrap();
+        reply.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(
+        String::from_utf8(reply).unwrap(),
+        "\x06\tAB0DAAATAAAqAKFhcm9ib290bG9hZGVyX3Rlc3TT7A==\n"
+    );
+    // A corrupted CRC gets no answer
+    assert_eq!(
+        smp::echo_reply("\x06\tAB0CAAATAAAqAKFhZG9ib290bG9hZGVyX3Rlc3S7xw==\n"),
+        None
+    );
+    assert_eq!(smp::crc16(b"123456789"), 0x31C3);
+}
+
+#[tokio::test]
+async fn bootloader_probe_leaves_the_runtime_free() {
+    let sim = PmuSimulator::with_faults(Faults::scenario(SimScenario::BootloaderMode));
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_timeout(1);
+    let probing = Arc::new(AtomicBool::new(false));
+    let in_probe = Arc::clone(&probing);
+    connection.set_bootloader_probe(Box::new(move || {
+        in_probe.store(true, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(300));
+        in_probe.store(false, Ordering::SeqCst);
+        true
+    }));
+
+    // On this single-threaded runtime, the ticker only runs during the probe
+    // if the probe is off the runtime's thread
+    let ticks = Arc::new(AtomicUsize::new(0));
+    let ticker = tokio::spawn({
+        let ticks = Arc::clone(&ticks);
+        async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                if probing.load(Ordering::SeqCst) {
+                    ticks.fetch_add(1, Ordering::SeqCst);
+                }
+            }
+        }
+    });
+    assert!(matches!(
+        connection.connect().await,
+        Err(PowerCliError::InBootloader { .. })
+    ));
+    ticker.abort();
+    assert!(ticks.load(Ordering::SeqCst) > 0);
+}
+
+#[test]
+fn binary_simulate_runs_an_invocation_against_the_simulator() {
+    let state = tempfile::tempdir().unwrap();
+    let run = |args: &[&str]| {
+        Command::cargo_bin("eink-power-cli")
+            .unwrap()
+            .env("EINK_POWER_CLI_STATE_DIR", state.path())
+            .env("EINK_POWER_CLI_MCUMGR", "false")
+            .arg("simulate")
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = run(&[
+        "--sim-scenario",
+        "low-battery",
+        "--format",
+        "json",
+        "battery",
+        "read",
+    ]);
+    assert!(output.status.success(), "{:?}", output);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["command"], "battery read");
+    assert_eq!(json["data"]["voltage_mv"], LOW_BATTERY_MV);
+    assert_eq!(json["data"]["charge_mah"], LOW_BATTERY_CHARGE_MAH);
+
+    let output = run(&["--sim-nfc-field", "nfc", "status"]);
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("RF field: Present"), "{}", stdout);
+    let output = run(&["--sim-firmware", "2.6.1", "version"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Version: 2.6.1"));
+
+    // Errors come from the invocation, with its exit code
+    let output = run(&[
+        "--format",
+        "json",
+        "system",
+        "info",
+        "--expect-version",
+        ">=3",
+    ]);
+    assert_eq!(output.status.code(), Some(4), "{:?}", output);
+}
+
+#[test]
+fn binary_simulate_pty_keeps_serving_in_the_background() {
+    let state = tempfile::tempdir().unwrap();
+    let output = Command::cargo_bin("eink-power-cli")
+        .unwrap()
+        .args(["simulate", "--pty", "--sim-scenario", "low-battery"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    // Only the path, for `--device $(eink-power-cli simulate --pty)`
+    let device = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        device.starts_with("/dev/") && device.ends_with('\n'),
+        "{:?}",
+        device
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let pid = stderr
+        .split("as process ")
+        .nth(1)
+        .and_then(|rest| rest.split(';').next())
+        .unwrap_or_else(|| panic!("{}", stderr))
+        .to_string();
+
+    for _ in 0..2 {
+        let output = Command::cargo_bin("eink-power-cli")
+            .unwrap()
+            .env("EINK_POWER_CLI_STATE_DIR", state.path())
+            .env("EINK_POWER_CLI_MCUMGR", "false")
+            .args([
+                "--device",
+                device.trim(),
+                "--format",
+                "json",
+                "battery",
+                "read",
+            ])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(json["data"]["voltage_mv"], LOW_BATTERY_MV);
+    }
+
+    assert!(std::process::Command::new("kill")
+        .arg(&pid)
+        .status()
+        .unwrap()
+        .success());
+}
+
+#[test]
+fn binary_fleet_scan_reports_every_port_and_diffs_a_saved_scan() {
+    let first = PmuSimulator::start();
+    let second = PmuSimulator::start();
+    let quiet = PmuSimulator::with_faults(Faults {
+        shell_disabled: true,
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let saved = state.path().join("fleet.json");
+    let missing = state.path().join("ttyUSB9").display().to_string();
+
+    let output = cli(&first, state.path())
+        .args(["--timeout", "1", "--format", "json", "fleet", "scan"])
+        .args(["--port", first.device(), "--port", second.device()])
+        .args(["--port", quiet.device(), "--port", &missing])
+        .arg("--save")
+        .arg(&saved)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let inventory = match parse_output(&String::from_utf8(output.stdout).unwrap()).unwrap() {
+        CommandOutput::FleetScan(inventory) => inventory,
+        other => panic!("unexpected output {:?}", other),
+    };
+    let statuses: Vec<ProbeStatus> = inventory.ports.iter().map(|entry| entry.status).collect();
+    assert_eq!(
+        statuses,
+        [
+            ProbeStatus::Ok,
+            ProbeStatus::Ok,
+            ProbeStatus::Unresponsive,
+            ProbeStatus::Missing
+        ]
+    );
+    assert_eq!(inventory.responsive(), 2);
+    let answered = &inventory.ports[0];
+    assert!(answered.firmware_version.is_some(), "{:?}", answered);
+    assert_eq!(answered.battery_mv, Some(3850));
+    assert_eq!(answered.rails.len(), 3, "{:?}", answered.rails);
+    assert!(inventory.ports[3].error.is_some());
+    assert_eq!(FleetInventory::load(&saved).unwrap(), inventory);
+
+    // The first unit was on older firmware when the file was saved
+    let mut earlier = inventory.clone();
+    earlier.ports[0].firmware_version = Some("2.2.0".to_string());
+    earlier.save(&saved).unwrap();
+    let output = cli(&first, state.path())
+        .args(["--timeout", "1", "--format", "json", "fleet", "diff"])
+        .arg(&saved)
+        .args(["--port", first.device(), "--port", second.device()])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    let diff = match parse_output(&String::from_utf8(output.stdout).unwrap()).unwrap() {
+        CommandOutput::FleetDiff(diff) => diff,
+        other => panic!("unexpected output {:?}", other),
+    };
+    // Pseudo-terminals have no USB serial, so units are matched by path
+    let changes: Vec<(FleetChangeKind, &str)> = diff
+        .changes
+        .iter()
+        .map(|change| (change.kind, change.port.as_str()))
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            (FleetChangeKind::Firmware, first.device()),
+            (FleetChangeKind::Removed, quiet.device()),
+            (FleetChangeKind::Removed, missing.as_str()),
+        ]
+    );
+    assert_eq!(diff.changes[0].before.as_deref(), Some("2.2.0"));
+    assert_eq!(diff.unchanged, 1);
+}
+
+#[tokio::test]
+async fn console_quirks_are_detected_on_connect_and_normalized() {
+    let sim = PmuSimulator::with_faults(Faults {
+        duplicated_lines: true,
+        doubled_line_endings: true,
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.connect().await.unwrap();
+    assert_eq!(
+        connection.console_quirks(),
+        [
+            ConsoleQuirk::DoubledLineEndings,
+            ConsoleQuirk::DuplicatedLines
+        ]
+    );
+
+    let response = connection.send_command("ltc2959 read").await.unwrap();
+    let battery = ResponseParser::parse_battery_response(&response);
+    assert_eq!(battery.voltage_mv, Some(3850));
+    assert_eq!(battery.current_ma, Some(-125));
+    assert_eq!(response.matches("Voltage").count(), 1, "{:?}", response);
+    // Detection needs no commands beyond the handshake
+    assert_eq!(sim.received(), ["ping", "ltc2959 read"]);
+}
+
+#[tokio::test]
+async fn rail_off_runs_after_its_echo_with_local_echo_on() {
+    let sim = PmuSimulator::with_faults(Faults {
+        duplicated_lines: true,
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.connect().await.unwrap();
+
+    let response = connection.send_command("pm wifi off").await.unwrap();
+    assert_eq!(response, "WIFI power OFF");
+    assert_eq!(connection.stats().retries, 0);
+    assert_eq!(sim.received(), ["ping", "pm wifi off"]);
+}
+
+#[tokio::test]
+async fn fix_console_turns_the_quirks_off() {
+    let sim = PmuSimulator::with_faults(Faults {
+        duplicated_lines: true,
+        doubled_line_endings: true,
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_fix_console(true);
+    connection.connect().await.unwrap();
+
+    assert!(connection.console_quirks().is_empty());
+    assert_eq!(
+        sim.received(),
+        ["ping", "shell crlf off", "shell local_echo off", "ping"]
+    );
+    let response = connection.send_command("pm stats").await.unwrap();
+    assert_eq!(
+        ResponseParser::parse_pm_stats(&response).sleep_cycles,
+        Some(4)
+    );
+}
+
+#[tokio::test]
+async fn fix_console_leaves_quirks_the_firmware_cannot_fix() {
+    let sim = PmuSimulator::with_faults(Faults {
+        duplicated_lines: true,
+        unsupported: vec!["shell local_echo off".to_string()],
+        ..Faults::default()
+    });
+    let mut connection = Connection::new(sim.device(), 115200, true).unwrap();
+    connection.set_fix_console(true);
+    connection.connect().await.unwrap();
+
+    assert_eq!(connection.console_quirks(), [ConsoleQuirk::DuplicatedLines]);
+    let response = connection.send_command("ltc2959 read").await.unwrap();
+    assert_eq!(
+        ResponseParser::parse_battery_response(&response).charge_mah,
+        Some(INITIAL_CHARGE_MAH)
+    );
+}
+
+#[test]
+fn binary_reports_console_quirks_in_verbose_output() {
+    let sim = PmuSimulator::with_faults(Faults {
+        doubled_line_endings: true,
+        ..Faults::default()
+    });
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["--verbose", "--format", "json", "battery", "read"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("doubled line endings (CRLF translation)"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("--fix-console"), "{}", stderr);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["data"]["voltage_mv"], 3850);
+}
+
+#[test]
+fn binary_power_stats_derives_rates_from_the_uptime() {
+    let sim = PmuSimulator::start();
+    let state = tempfile::tempdir().unwrap();
+    let output = cli(&sim, state.path())
+        .args(["--format", "json", "power", "stats"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+
+    let report = match parse_output(&String::from_utf8_lossy(&output.stdout)).unwrap() {
+        CommandOutput::PowerStatsReport(report) => report,
+        other => panic!("unexpected output {:?}", other),
+    };
+    assert_eq!(report.stats.wake_count, Some(4));
+    assert!(report.uptime_ms.unwrap() >= INITIAL_UPTIME.as_millis() as u64);
+    let derived = report.derived.unwrap();
+    assert!(derived.since_boot);
+    // Four wakes in just over an hour, two of them by the RTC
+    assert!((3.9..=4.0).contains(&derived.wakes_per_hour.unwrap()));
+    assert_eq!(derived.wake_share_percent[&WakeSource::Rtc], 50.0);
+    assert!((74.9..=75.1).contains(&derived.asleep_percent.unwrap()));
+    assert_eq!(sim.received(), ["ping", "power stats", "system uptime"]);
+}
@@ -0,0 +1,209 @@
+/*
+ * E-ink Power CLI - State Directory Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Tests for the per-device state files, including concurrent writers
+
+use eink_power_cli::history::{HistoryEntry, HistoryLog};
+use eink_power_cli::state::{write_atomic, DeviceState, StateFile, StatePayload};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct Counter {
+    count: u64,
+    /// Padding so a torn write would be detectable as invalid JSON
+    history: Vec<u64>,
+}
+
+impl StatePayload for Counter {
+    const SCHEMA_VERSION: u32 = 2;
+
+    fn migrate(from_version: u32, data: serde_json::Value) -> Option<Self> {
+        // Version 1 stored the bare count
+        match from_version {
+            1 => data.as_u64().map(|count| Counter {
+                count,
+                history: Vec::new(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn test_store_and_load_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let file: StateFile<Counter> = StateFile::new(dir.path().join("counter.json"));
+
+    assert_eq!(file.load().unwrap(), None);
+    file.store(&Counter {
+        count: 7,
+        history: vec![7],
+    })
+    .unwrap();
+    assert_eq!(file.load().unwrap().unwrap().count, 7);
+
+    assert!(file.remove().unwrap());
+    assert_eq!(file.load().unwrap(), None);
+    assert!(!file.remove().unwrap());
+}
+
+#[test]
+fn test_schema_migration() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("counter.json");
+    let file: StateFile<Counter> = StateFile::new(path.clone());
+
+    std::fs::write(&path, r#"{"schema_version": 1, "data": 41}"#).unwrap();
+    let migrated = file.update(|c| {
+        let mut c = c.unwrap();
+        c.count += 1;
+        c
+    });
+    assert_eq!(migrated.unwrap().count, 42);
+
+    let on_disk: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(on_disk["schema_version"], 2);
+
+    // Unknown future schemas are discarded rather than misread
+    std::fs::write(&path, r#"{"schema_version": 99, "data": {}}"#).unwrap();
+    assert_eq!(file.load().unwrap(), None);
+
+    std::fs::write(&path, "not json").unwrap();
+    assert_eq!(file.load().unwrap(), None);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_updates_are_not_lost_or_torn() {
+    const TASKS: u64 = 8;
+    const UPDATES: u64 = 25;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = Arc::new(dir.path().join("counter.json"));
+
+    let mut handles = Vec::new();
+    for _ in 0..TASKS {
+        let path = Arc::clone(&path);
+        handles.push(tokio::task::spawn_blocking(move || {
+            let file: StateFile<Counter> = StateFile::new((*path).clone());
+            for _ in 0..UPDATES {
+                file.update(|c| {
+                    let mut c = c.unwrap_or_default();
+                    c.count += 1;
+                    c.history.push(c.count);
+                    c
+                })
+                .unwrap();
+
+                // Readers outside the lock must always see a complete document
+                let raw = std::fs::read_to_string(&*path).unwrap();
+                serde_json::from_str::<serde_json::Value>(&raw).expect("partial write observed");
+            }
+        }));
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    let file: StateFile<Counter> = StateFile::new((*path).clone());
+    let counter = file.load().unwrap().unwrap();
+    assert_eq!(counter.count, TASKS * UPDATES);
+    assert_eq!(counter.history, (1..=TASKS * UPDATES).collect::<Vec<_>>());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_unlocked_writers_in_one_process_do_not_share_a_temp_file() {
+    const TASKS: usize = 8;
+    const WRITES: usize = 50;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = Arc::new(dir.path().join("status.json"));
+
+    let mut handles = Vec::new();
+    for task in 0..TASKS {
+        let path = Arc::clone(&path);
+        handles.push(tokio::task::spawn_blocking(move || {
+            for write in 0..WRITES {
+                let contents = format!("{{\"task\":{},\"write\":{}}}", task, write);
+                write_atomic(&path, contents.as_bytes()).unwrap();
+            }
+        }));
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    let raw = std::fs::read_to_string(&*path).unwrap();
+    serde_json::from_str::<serde_json::Value>(&raw).expect("torn write observed");
+    let leftovers: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .flatten()
+        .map(|entry| entry.file_name())
+        .filter(|name| name != "status.json")
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_history_appends() {
+    const TASKS: usize = 6;
+    const APPENDS: usize = 20;
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = Arc::new(dir.path().join("history.jsonl"));
+
+    let mut handles = Vec::new();
+    for task in 0..TASKS {
+        let path = Arc::clone(&path);
+        handles.push(tokio::task::spawn_blocking(move || {
+            let log = HistoryLog::new((*path).clone()).with_max_bytes(4096);
+            for i in 0..APPENDS {
+                let command = format!("task {} append {}", task, i);
+                log.append(&HistoryEntry::new(&[command], Some("OK"), 0, None))
+                    .unwrap();
+            }
+        }));
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+
+    // Every line in both the active and rotated log must be a whole entry
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    for file in [(*path).clone(), rotated.into()] {
+        for line in std::fs::read_to_string(file).unwrap().lines() {
+            serde_json::from_str::<HistoryEntry>(line).expect("torn history line");
+        }
+    }
+}
+
+#[test]
+fn test_device_state_show_and_clear() {
+    let dir = tempfile::tempdir().unwrap();
+    let state = DeviceState::in_dir(dir.path().join("dev_ttyLP2"));
+    assert!(state.files().unwrap().is_empty());
+
+    state
+        .file::<Counter>("counter.json")
+        .store(&Counter::default())
+        .unwrap();
+    HistoryLog::new(state.file_path("history.jsonl"))
+        .append(&HistoryEntry::new(&[], None, 0, None))
+        .unwrap();
+
+    let names: Vec<String> = state
+        .files()
+        .unwrap()
+        .iter()
+        .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, vec!["counter.json", "history.jsonl"]);
+
+    assert_eq!(state.clear().unwrap(), 2);
+    assert!(state.files().unwrap().is_empty());
+}
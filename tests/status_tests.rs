@@ -0,0 +1,120 @@
+/*
+ * E-ink Power CLI - Status File Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! The JSON status file `monitor --status-file` keeps for other services
+
+use eink_power_cli::json::{MeasurementJson, OUTPUT_SCHEMA_VERSION};
+use eink_power_cli::power::rails::PowerRail;
+use eink_power_cli::status::{self, StatusDocument, StatusFile};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn document(voltage_mv: u16) -> StatusDocument {
+    let mut document = StatusDocument::new("/dev/ttyLP2");
+    document.battery = Some(MeasurementJson {
+        voltage_mv: Some(voltage_mv),
+        current_ma: Some(-125),
+        adc_mode: None,
+        source: "pm measure".into(),
+    });
+    document.rails.insert(PowerRail::Pmic, true);
+    document.rails.insert(PowerRail::Wifi, false);
+    document
+}
+
+#[test]
+fn readers_never_see_a_partial_write() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("pmu-status.json");
+    let file = StatusFile::new(path.clone(), Duration::from_secs(30), None);
+    file.write(&mut document(3000)).unwrap();
+
+    let done = Arc::new(AtomicBool::new(false));
+    let writer = {
+        let done = done.clone();
+        std::thread::spawn(move || {
+            for voltage_mv in 3000..3400 {
+                file.write(&mut document(voltage_mv)).unwrap();
+            }
+            done.store(true, Ordering::SeqCst);
+        })
+    };
+
+    let mut reads = 0;
+    while !done.load(Ordering::SeqCst) {
+        // A torn or truncated file would fail to parse
+        let read = status::read(&path).unwrap();
+        assert_eq!(read.rails.len(), 2);
+        reads += 1;
+    }
+    writer.join().unwrap();
+    assert!(reads > 0);
+
+    let last = status::read(&path).unwrap();
+    assert_eq!(last.battery.unwrap().voltage_mv, Some(3399));
+    let names: Vec<_> = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(names, ["pmu-status.json"], "temporary files left behind");
+}
+
+#[test]
+fn document_goes_stale_after_two_missed_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("status.json");
+    let mut document = document(3850);
+
+    StatusFile::new(path.clone(), Duration::from_secs(30), None)
+        .write(&mut document)
+        .unwrap();
+    assert_eq!(
+        (document.stale_after - document.updated_at).num_seconds(),
+        60
+    );
+
+    // Timed writes set the window, not the sample interval
+    let timed = StatusFile::new(
+        path.clone(),
+        Duration::from_secs(30),
+        Some(Duration::from_secs(5)),
+    );
+    timed.write(&mut document).unwrap();
+    assert_eq!(
+        (document.stale_after - document.updated_at).num_seconds(),
+        10
+    );
+
+    let read = status::read(&path).unwrap();
+    assert!(!read.is_stale(read.updated_at));
+    assert!(read.is_stale(read.stale_after + chrono::Duration::seconds(1)));
+}
+
+#[test]
+fn clean_shutdown_marks_the_file_stale_and_keeps_the_readings() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("status.json");
+    let file = StatusFile::new(path.clone(), Duration::from_secs(30), None);
+    let mut document = document(3850);
+    file.write(&mut document).unwrap();
+    file.mark_stale(&mut document).unwrap();
+
+    let read = status::read(&path).unwrap();
+    assert!(read.stale);
+    assert!(read.is_stale(read.updated_at));
+    assert_eq!(read.battery.unwrap().voltage_mv, Some(3850));
+}
+
+#[test]
+fn document_layout_is_versioned() {
+    let json = serde_json::to_value(document(3850)).unwrap();
+    assert_eq!(json["schema_version"], OUTPUT_SCHEMA_VERSION);
+    assert_eq!(json["rails"]["pmic"], true);
+    assert_eq!(json["rails"]["wifi"], false);
+    assert_eq!(json["battery"]["voltage_mv"], 3850);
+    assert!(json["sampled_at"].is_null());
+}
@@ -0,0 +1,111 @@
+/*
+ * E-ink Power CLI - Time Reference Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Latency compensation and reboot invalidation of the PMU time reference
+
+use chrono::{DateTime, TimeDelta, TimeZone, Utc};
+use eink_power_cli::power::timeref::{uptime_resolution_ms, TimeRefReport, TimeReference};
+use eink_power_cli::serial::connection::RoundTrip;
+use std::time::Duration;
+
+fn at(ms: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 10, 9, 14, 0, 0).unwrap() + TimeDelta::milliseconds(ms)
+}
+
+/// Uptime `uptime_ms` read by a command sent at host time `sent_ms` whose
+/// reply started `round_trip_ms` later
+fn reference(uptime_ms: u64, sent_ms: i64, round_trip_ms: u64) -> TimeReference {
+    TimeReference::from_exchange(
+        uptime_ms,
+        1,
+        RoundTrip {
+            sent_at: at(sent_ms),
+            first_byte: Duration::from_millis(round_trip_ms),
+        },
+    )
+}
+
+#[test]
+fn uptime_is_placed_halfway_through_the_round_trip() {
+    let reference = reference(10_000, 0, 80);
+
+    assert_eq!(reference.host_time, at(40));
+    assert_eq!(reference.pmu_boot_wallclock, at(40 - 10_000));
+    assert_eq!(reference.round_trip_ms, 80.0);
+    assert_eq!(reference.uncertainty_ms, 41.0);
+    assert_eq!(reference.wallclock_at(67_427), at(40 - 10_000 + 67_427));
+}
+
+#[test]
+fn slow_links_give_the_same_boot_time() {
+    // The same PMU read over a fast and a slow link
+    let fast = reference(10_000, 0, 4);
+    let slow = reference(10_000, -98, 200);
+
+    assert_eq!(fast.pmu_boot_wallclock, slow.pmu_boot_wallclock);
+    assert!(slow.uncertainty_ms > fast.uncertainty_ms);
+}
+
+#[test]
+fn seconds_only_uptime_widens_the_uncertainty() {
+    assert_eq!(uptime_resolution_ms("System Uptime: 0:01:07 (67427 ms)"), 1);
+    assert_eq!(uptime_resolution_ms("Uptime: 0:01:07"), 1000);
+
+    let coarse = TimeReference::from_exchange(
+        67_000,
+        uptime_resolution_ms("Uptime: 0:01:07"),
+        RoundTrip {
+            sent_at: at(0),
+            first_byte: Duration::from_millis(10),
+        },
+    );
+    assert_eq!(coarse.uncertainty_ms, 1005.0);
+}
+
+#[test]
+fn reboot_is_seen_from_a_later_reference() {
+    let first = reference(3_600_000, 0, 10);
+
+    // A minute later, within measurement jitter
+    assert!(!first.rebooted_before(&reference(3_660_000, 60_000, 10)));
+    assert!(!first.rebooted_before(&reference(3_659_950, 60_000, 10)));
+    // Uptime restarted from zero
+    assert!(first.rebooted_before(&reference(5_000, 60_000, 10)));
+    // Rebooted long enough ago that uptime passed the old value again
+    assert!(first.rebooted_before(&reference(3_650_000, 3_600_000, 10)));
+}
+
+#[test]
+fn translation_reuses_a_valid_stored_reference() {
+    let stored = reference(3_600_000, 0, 10);
+    let fresh = reference(3_660_000, 60_000, 30);
+
+    let report = TimeRefReport::new(Some(stored.clone()), fresh.clone(), Some(1_000));
+    assert!(report.reused);
+    assert!(!report.replaced_stale);
+    assert_eq!(report.reference, stored);
+    assert_eq!(
+        report.translation.unwrap().wallclock,
+        stored.pmu_boot_wallclock + TimeDelta::seconds(1)
+    );
+
+    // Without --at the new reading is recorded
+    let report = TimeRefReport::new(Some(stored), fresh.clone(), None);
+    assert!(!report.reused);
+    assert_eq!(report.reference, fresh);
+    assert_eq!(report.translation, None);
+}
+
+#[test]
+fn stale_reference_is_replaced() {
+    let stored = reference(3_600_000, 0, 10);
+    let after_reboot = reference(2_000, 60_000, 10);
+
+    let report = TimeRefReport::new(Some(stored), after_reboot.clone(), Some(1_000));
+    assert!(report.replaced_stale);
+    assert!(!report.reused);
+    assert_eq!(report.reference, after_reboot);
+}
@@ -0,0 +1,159 @@
+/*
+ * E-ink Power CLI - Countdown Tests
+ * Copyright (c) 2025 Dynamic Devices Ltd
+ * All rights reserved.
+ */
+
+//! Countdowns on a paused clock: what they print and when they stop
+
+use eink_power_cli::error::PowerCliError;
+use eink_power_cli::render::OutputStyle;
+use eink_power_cli::util::{countdown_to, CancellationToken, Outcome, Progress};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Writer whose contents the test can read while a countdown runs
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Shared {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn terminal() -> Progress {
+    Progress {
+        live: true,
+        ..Progress::default()
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn countdown_redraws_every_second_until_done() {
+    let mut out = Shared::default();
+    let started = Instant::now();
+    let outcome = countdown_to(
+        &mut out,
+        terminal(),
+        Duration::from_secs(3),
+        "Waiting for boot",
+        &CancellationToken::new(),
+    )
+    .await;
+
+    assert_eq!(outcome, Outcome::Completed);
+    assert_eq!(started.elapsed(), Duration::from_secs(3));
+    let text = out.text();
+    let counts: Vec<&str> = text
+        .split('\r')
+        .filter(|line| line.contains("remaining"))
+        .collect();
+    assert_eq!(counts.len(), 3, "{:?}", text);
+    assert!(counts[0].contains("Waiting for boot... 3 s remaining"));
+    assert!(counts[2].contains("1 s remaining"));
+    assert!(
+        text.ends_with("✅ Waiting for boot done\x1b[K\n"),
+        "{:?}",
+        text
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn cancellation_stops_the_countdown_and_its_output() {
+    let out = Shared::default();
+    let cancel = CancellationToken::new();
+    let started = Instant::now();
+    let task = {
+        let (mut out, cancel) = (out.clone(), cancel.clone());
+        tokio::spawn(async move {
+            countdown_to(
+                &mut out,
+                terminal(),
+                Duration::from_secs(60),
+                "Waiting for boot",
+                &cancel,
+            )
+            .await
+        })
+    };
+
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    cancel.cancel();
+    assert_eq!(task.await.unwrap(), Outcome::Cancelled);
+    assert_eq!(started.elapsed(), Duration::from_millis(2500));
+
+    let text = out.text();
+    assert!(text.contains("58 s remaining"), "{:?}", text);
+    assert!(!text.contains("57 s remaining"), "{:?}", text);
+    assert!(
+        text.ends_with("⚠️ Waiting for boot cancelled\x1b[K\n"),
+        "{:?}",
+        text
+    );
+
+    // Nothing more once cancelled
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    assert_eq!(out.text(), text);
+}
+
+#[tokio::test(start_paused = true)]
+async fn countdown_already_cancelled_returns_at_once() {
+    let mut out = Shared::default();
+    let cancel = CancellationToken::new();
+    cancel.cancel();
+    let started = Instant::now();
+    let outcome = countdown_to(
+        &mut out,
+        Progress::hidden(),
+        Duration::from_secs(5),
+        "Waiting",
+        &cancel,
+    )
+    .await;
+
+    assert_eq!(outcome, Outcome::Cancelled);
+    assert_eq!(started.elapsed(), Duration::ZERO);
+    assert!(out.text().is_empty());
+    let error = outcome.or_cancelled("waiting").unwrap_err();
+    assert!(matches!(error, PowerCliError::Cancelled { .. }));
+    assert_eq!(error.to_string(), "Cancelled while waiting");
+    assert_eq!(error.exit_code(), 130);
+}
+
+#[tokio::test(start_paused = true)]
+async fn countdown_off_a_terminal_prints_only_start_and_end() {
+    let mut out = Shared::default();
+    let progress = Progress {
+        style: OutputStyle::ascii(),
+        visible: true,
+        live: false,
+    };
+    let outcome = countdown_to(
+        &mut out,
+        progress,
+        Duration::from_millis(2500),
+        "Waiting for boot",
+        &CancellationToken::new(),
+    )
+    .await;
+
+    assert_eq!(outcome, Outcome::Completed);
+    assert_eq!(
+        out.text(),
+        "Waiting for boot (3 s)...\n[OK] Waiting for boot done\n"
+    );
+}